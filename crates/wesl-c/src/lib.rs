@@ -430,9 +430,14 @@ pub unsafe extern "C" fn wesl_compile(
             generics: opts.generics,
             strip: opts.strip,
             lower: opts.lower,
-            validate: opts.validate,
+            validation_level: if opts.validate {
+                wesl::ValidationLevel::Standard
+            } else {
+                wesl::ValidationLevel::None
+            },
             lazy: opts.lazy,
             mangle_root: opts.mangle_root,
+            mangler: opts.mangler.into(),
             keep: keep_vec,
             features: wesl::Features {
                 default: wesl::Feature::Disable,
@@ -442,9 +447,32 @@ pub unsafe extern "C" fn wesl_compile(
                     .collect(),
             },
             keep_root: opts.keep_root,
+            entry_points: None,
+            prelude: None,
+            group_by_module: false,
+            extensions: Default::default(),
+            assign_override_ids: false,
+            strip_members: false,
+            package_features: Vec::new(),
+            limits: Default::default(),
+            debug_printf: None,
+            binding_policy: None,
+            target: wesl::Target::Core,
+            header: None,
+            max_workgroup_storage_size: None,
+            cost_budget: None,
+            eliminate_common_subexpressions: false,
+            normalize_attributes: false,
+            binding_arrays: false,
+            plain_wgsl: false,
+            naming: Default::default(),
+            emit: None,
+            slots: Default::default(),
+            define_consts: Default::default(),
+            lower_switches: false,
+            ..Default::default()
         })
-        .use_sourcemap(opts.sourcemap)
-        .set_mangler(opts.mangler.into());
+        .use_sourcemap(opts.sourcemap);
 
     match compiler.compile(&root_path) {
         Ok(result) => {
@@ -527,9 +555,14 @@ pub unsafe extern "C" fn wesl_eval(
             generics: opts.generics,
             strip: opts.strip,
             lower: opts.lower,
-            validate: opts.validate,
+            validation_level: if opts.validate {
+                wesl::ValidationLevel::Standard
+            } else {
+                wesl::ValidationLevel::None
+            },
             lazy: opts.lazy,
             mangle_root: opts.mangle_root,
+            mangler: opts.mangler.into(),
             keep: None,
             features: wesl::Features {
                 default: wesl::Feature::Disable,
@@ -539,11 +572,34 @@ pub unsafe extern "C" fn wesl_eval(
                     .collect(),
             },
             keep_root: opts.keep_root,
+            entry_points: None,
+            prelude: None,
+            group_by_module: false,
+            extensions: Default::default(),
+            assign_override_ids: false,
+            strip_members: false,
+            package_features: Vec::new(),
+            limits: Default::default(),
+            debug_printf: None,
+            binding_policy: None,
+            target: wesl::Target::Core,
+            header: None,
+            max_workgroup_storage_size: None,
+            cost_budget: None,
+            eliminate_common_subexpressions: false,
+            normalize_attributes: false,
+            binding_arrays: false,
+            plain_wgsl: false,
+            naming: Default::default(),
+            emit: None,
+            slots: Default::default(),
+            define_consts: Default::default(),
+            lower_switches: false,
+            ..Default::default()
         })
-        .use_sourcemap(opts.sourcemap)
-        .set_mangler(opts.mangler.into());
+        .use_sourcemap(opts.sourcemap);
 
-    match compiler.compile(&root_path) {
+    match compiler.compile_result(&root_path) {
         Ok(result) => match result.eval(&expr_str) {
             Ok(eval_result) => WeslResult {
                 success: true,
@@ -653,9 +709,14 @@ pub unsafe extern "C" fn wesl_exec(
             generics: opts.generics,
             strip: opts.strip,
             lower: opts.lower,
-            validate: opts.validate,
+            validation_level: if opts.validate {
+                wesl::ValidationLevel::Standard
+            } else {
+                wesl::ValidationLevel::None
+            },
             lazy: opts.lazy,
             mangle_root: opts.mangle_root,
+            mangler: opts.mangler.into(),
             keep: None,
             features: wesl::Features {
                 default: wesl::Feature::Disable,
@@ -665,11 +726,34 @@ pub unsafe extern "C" fn wesl_exec(
                     .collect(),
             },
             keep_root: opts.keep_root,
+            entry_points: None,
+            prelude: None,
+            group_by_module: false,
+            extensions: Default::default(),
+            assign_override_ids: false,
+            strip_members: false,
+            package_features: Vec::new(),
+            limits: Default::default(),
+            debug_printf: None,
+            binding_policy: None,
+            target: wesl::Target::Core,
+            header: None,
+            max_workgroup_storage_size: None,
+            cost_budget: None,
+            eliminate_common_subexpressions: false,
+            normalize_attributes: false,
+            binding_arrays: false,
+            plain_wgsl: false,
+            naming: Default::default(),
+            emit: None,
+            slots: Default::default(),
+            define_consts: Default::default(),
+            lower_switches: false,
+            ..Default::default()
         })
-        .use_sourcemap(opts.sourcemap)
-        .set_mangler(opts.mangler.into());
+        .use_sourcemap(opts.sourcemap);
 
-    match compiler.compile(&root_path) {
+    match compiler.compile_result(&root_path) {
         Ok(result) => {
             // parse resources
             let parsed_resources: Result<HashMap<(u32, u32), RefInstance>, wesl::Error> =
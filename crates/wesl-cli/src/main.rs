@@ -1,17 +1,23 @@
 //! The Command-line interface for `wesl-rs`.
 
+mod repl;
+
 use clap::{Args, Parser, Subcommand, ValueEnum, command};
 use std::{
+    collections::HashSet,
     convert::Infallible,
     error::Error,
     fs::{self, File},
     io::{Read, Write},
-    path::PathBuf,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 use wesl::{
-    CompileOptions, CompileResult, Diagnostic, Feature, Features, Inputs, ManglerKind, ModulePath,
-    PkgBuilder, Router, StandardResolver, SyntaxUtil, VirtualResolver, Wesl,
+    CompileOptions, CompileResult, ConstValue, Diagnostic, Emit, EmitStage, Feature, Features,
+    ImportMap, ImportMapResolver, ImportTarget, Inputs, ManglerKind, Manifest, ModulePath,
+    NamingLints, PkgBuilder, RecordingResolver, ReplayResolver, Resolver, Router, StandardResolver,
+    SyntaxUtil, ValidationLevel, VirtualResolver, Wesl,
     eval::{Eval, EvalAttrs, Instance, RefInstance, Ty, ty_eval_ty},
     syntax::{self, AccessMode, AddressSpace, PathOrigin, TranslationUnit},
 };
@@ -32,6 +38,14 @@ where
     }
 }
 
+/// Parse a `NAME=VALUE` constant definition.
+fn parse_define(s: &str) -> Result<(String, ConstValue), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `NAME=VALUE`, found `{s}`"))?;
+    Ok((name.to_string(), value.parse()?))
+}
+
 #[derive(Parser)]
 #[command(version, author, about)]
 #[command(propagate_version = true)]
@@ -57,17 +71,33 @@ enum Command {
     Exec(ExecArgs),
     /// Generate a publishable Cargo package from WESL source code
     Package(PkgArgs),
+    /// Interactively declare items and evaluate const-expressions
+    Repl,
+    /// Rewrite import paths and inline qualified paths in WESL files
+    FixImports(FixImportsArgs),
+    /// Convert a directory of plain WGSL files into a WESL package with explicit imports
+    ImportWgsl(ImportWgslArgs),
+    /// Generate interface modules (.wesli), without function bodies, of a WESL package
+    Interface(InterfaceArgs),
+    /// Print the memory layout of the host-shareable structs
+    Layout(LayoutArgs),
+    /// Print the estimated cost of the entry points
+    Cost(CostArgs),
+    /// Print the import graph with the module sizes, import cycles and heaviest path
+    Graph(GraphArgs),
+    /// Print the size of the output by originating package, module and declaration
+    Size(SizeArgs),
 }
 
 #[derive(Default, Clone, Copy, Debug, ValueEnum)]
 pub enum ClapManglerKind {
-    /// Escaped path mangler. `foo/bar/{item} -> foo_bar_item`
+    /// Escaped path mangler. `foo_bar::item -> _1foo_bar_item`
     #[default]
     Escape,
-    /// Hash mangler. `foo/bar/{item} -> item_1985638328947`
+    /// Hash mangler. `foo::bar::item -> item_1985638328947`
     Hash,
     /// Make valid identifiers with unicode "confusables" characters.
-    /// `foo/{bar<baz, moo>} -> foo::barᐸbazˏmooᐳ`
+    /// `foo::bar<baz, moo> -> foo::barᐸbazˏmooᐳ`
     Unicode,
     /// Disable mangling (warning: will break if case of name conflicts!)
     None,
@@ -122,11 +152,53 @@ impl From<ClapFeature> for Feature {
     }
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ClapValidationLevel {
+    None,
+    Syntax,
+    Standard,
+    Pedantic,
+}
+
+impl From<ClapValidationLevel> for ValidationLevel {
+    fn from(value: ClapValidationLevel) -> Self {
+        match value {
+            ClapValidationLevel::None => Self::None,
+            ClapValidationLevel::Syntax => Self::Syntax,
+            ClapValidationLevel::Standard => Self::Standard,
+            ClapValidationLevel::Pedantic => Self::Pedantic,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ClapEmitStage {
+    AfterCondcomp,
+    AfterImports,
+    AfterMangle,
+    AfterLower,
+}
+
+impl From<ClapEmitStage> for EmitStage {
+    fn from(value: ClapEmitStage) -> Self {
+        match value {
+            ClapEmitStage::AfterCondcomp => Self::AfterCondcomp,
+            ClapEmitStage::AfterImports => Self::AfterImports,
+            ClapEmitStage::AfterMangle => Self::AfterMangle,
+            ClapEmitStage::AfterLower => Self::AfterLower,
+        }
+    }
+}
+
 #[derive(Args, Clone, Debug)]
 struct CompOptsArgs {
-    /// Name mangling strategy
-    #[arg(long, default_value = "escape")]
-    mangler: ClapManglerKind,
+    /// JSON file containing the compilation options. Other command-line flags take
+    /// precedence over the options file
+    #[arg(long, value_name = "FILE")]
+    options: Option<PathBuf>,
+    /// Name mangling strategy [default: escape]
+    #[arg(long)]
+    mangler: Option<ClapManglerKind>,
     /// Show nicer error messages by computing a sourcemap
     #[arg(long)]
     no_sourcemap: bool,
@@ -145,9 +217,41 @@ struct CompOptsArgs {
     /// Enable lowering output to compatibility-mode WGSL
     #[arg(long)]
     lower: bool,
-    /// Disable performing validation checks
+    /// Convert `switch` statements to `if` chains
+    #[arg(long)]
+    lower_switches: bool,
+    /// Compute repeated pure expressions once, in `let` declarations
+    #[arg(long)]
+    eliminate_common_subexpressions: bool,
+    /// Sort attributes in a canonical order, e.g. `@group` before `@binding`
+    #[arg(long)]
+    normalize_attributes: bool,
+    /// Turn `@binding_range` variables into `binding_array`s instead of expanding them
+    #[arg(long)]
+    binding_arrays: bool,
+    /// Reject WESL-only syntax in modules loaded from `.wgsl` files
+    #[arg(long)]
+    plain_wgsl: bool,
+    /// Warn about local declarations whose names do not follow the recommended naming
+    /// conventions, unless the options file configures the naming lints
+    #[arg(long)]
+    naming_lints: bool,
+    /// Write the syntax tree after these compilation stages to `--emit-dir`, to find
+    /// which stage introduced an issue in the output
+    #[arg(long, value_name = "STAGE", value_delimiter = ',')]
+    emit: Vec<ClapEmitStage>,
+    /// Directory of the trees written with `--emit`
+    #[arg(long, value_name = "DIR", default_value = "wesl-emit")]
+    emit_dir: PathBuf,
+    /// Fill the `@slot("NAME")` blocks with the statements of a file. Can be repeated
+    #[arg(long, value_name = "NAME=FILE", value_parser = parse_key_val::<String, PathBuf>)]
+    slot: Vec<(String, PathBuf)>,
+    /// Disable performing validation checks, same as `--validation-level none`
     #[arg(long)]
     no_validate: bool,
+    /// Amount of validation checks. `pedantic` adds portability checks [default: standard]
+    #[arg(long, value_name = "LEVEL")]
+    validation_level: Option<ClapValidationLevel>,
     /// Eager imports: load all modules referenced by an identifier, regardless of if it is
     /// used.
     #[arg(long)]
@@ -166,45 +270,148 @@ struct CompOptsArgs {
     /// Keep all root module declarations instead of just the entrypoints
     #[arg(long)]
     keep_root: bool,
+    /// Entry point that must be present in the output. Can be repeated
+    #[arg(long = "entry-point", value_name = "NAME")]
+    entry_points: Option<Vec<String>>,
+    /// Module whose declarations are in scope in all modules without imports.
+    /// Example: `--prelude package::prelude` or `--prelude package::"my-prelude"`
+    #[arg(long, value_name = "MODULE")]
+    prelude: Option<ModulePath>,
+    /// Group the output declarations by module, with banner comments
+    #[arg(long)]
+    group_by_module: bool,
+    /// Give an `@id` to pipeline-overridable constants that have none
+    #[arg(long)]
+    assign_override_ids: bool,
+    /// Remove unused members of structs that are not visible to the host
+    #[arg(long)]
+    strip_members: bool,
+    /// Check the output against the restrictions of WebGPU compatibility mode
+    #[arg(long)]
+    compat: bool,
+    /// Prepend a comment header with the compiler version, root module, feature flags and
+    /// options hash
+    #[arg(long)]
+    header: bool,
+    /// Add the compilation time to the header. Implies `--header`
+    #[arg(long)]
+    header_timestamp: bool,
+    /// Maximum workgroup memory of compute entry points, in bytes (e.g. 16384 in
+    /// WebGPU). Warns when more than half is used
+    #[arg(long, value_name = "BYTES")]
+    max_workgroup_storage_size: Option<u32>,
     /// Set a conditional compilation feature flag. Can be repeated
     #[arg(short='D', long, value_name="NAME | NAME=[enable, disable, keep, error]", value_parser = parse_key_val::<String, ClapFeature>)]
     feature: Vec<(String, ClapFeature)>,
-    /// Default behavior for unspecified conditional compilation features
-    #[arg(long, default_value = "disable")]
-    feature_default: ClapFeature,
+    /// Declare a module-scope constant in the root module, e.g. `MAX_LIGHTS=16u` or
+    /// `TINT=vec3f(1.0, 0.5, 0.0)`. Can be repeated
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_define)]
+    define_const: Vec<(String, ConstValue)>,
+    /// Default behavior for unspecified conditional compilation features [default: disable]
+    #[arg(long)]
+    feature_default: Option<ClapFeature>,
     /// Root folder for `package::` imports. Defaults to the parent directory of the root module
     #[arg(long)]
     base: Option<PathBuf>,
+    /// Record the module sources read by the compilation in this JSON manifest file
+    #[arg(long, value_name = "FILE", conflicts_with = "replay_manifest")]
+    record_manifest: Option<PathBuf>,
+    /// Read the module sources from a manifest file recorded with `--record-manifest`
+    /// instead of the filesystem
+    #[arg(long, value_name = "FILE")]
+    replay_manifest: Option<PathBuf>,
+    /// Rewrite module path prefixes with this import map file, e.g. to use a vendored
+    /// fork of a package. Only JSON files are accepted. Directories are relative to the
+    /// file
+    #[arg(long, value_name = "FILE")]
+    import_map: Option<PathBuf>,
+    /// Print the import map mappings applied during compilation
+    #[arg(long, short)]
+    verbose: bool,
 }
 
-impl From<&CompOptsArgs> for CompileOptions {
-    fn from(opts: &CompOptsArgs) -> Self {
-        let flags = opts
-            .feature
-            .iter()
-            .map(|(k, v)| (k.clone(), (*v).into()))
-            .collect();
-
-        Self {
-            imports: !opts.no_imports,
-            condcomp: !opts.no_cond_comp,
-            generics: opts.generics,
-            strip: !opts.no_strip,
-            lower: opts.lower,
-            validate: !opts.no_validate,
-            lazy: !opts.eager,
-            mangle_root: opts.mangle_root,
-            keep: if opts.no_strip {
-                None
-            } else {
-                opts.keep.clone()
-            },
-            keep_root: opts.keep_root,
-            features: Features {
-                default: opts.feature_default.into(),
-                flags,
-            },
+impl CompOptsArgs {
+    /// Read the options file (if any) and override it with command-line flags.
+    fn compile_options(&self) -> Result<CompileOptions, CliError> {
+        let mut options = if let Some(path) = &self.options {
+            let file =
+                fs::read_to_string(path).map_err(|e| CliError::Io(path.clone(), e.to_string()))?;
+            serde_json::from_str(&file).map_err(|e| CliError::OptionsFile(e.to_string()))?
+        } else {
+            CompileOptions::default()
+        };
+
+        if let Some(mangler) = self.mangler {
+            options.mangler = mangler.into();
+        }
+        options.imports &= !self.no_imports;
+        options.condcomp &= !self.no_cond_comp;
+        options.generics |= self.generics;
+        options.strip &= !self.no_strip;
+        options.lower |= self.lower;
+        options.lower_switches |= self.lower_switches;
+        options.eliminate_common_subexpressions |= self.eliminate_common_subexpressions;
+        options.normalize_attributes |= self.normalize_attributes;
+        options.binding_arrays |= self.binding_arrays;
+        options.plain_wgsl |= self.plain_wgsl;
+        if self.naming_lints && !options.naming.is_enabled() {
+            options.naming = NamingLints::recommended();
+        }
+        if !self.emit.is_empty() {
+            let stages = self.emit.iter().map(|stage| (*stage).into());
+            options.emit = Some(Emit::new(&self.emit_dir, stages));
+        }
+        for (name, file) in &self.slot {
+            let snippet =
+                fs::read_to_string(file).map_err(|e| CliError::Io(file.clone(), e.to_string()))?;
+            options.slots.insert(name.clone(), snippet);
+        }
+        if let Some(level) = self.validation_level {
+            options.validation_level = level.into();
         }
+        if self.no_validate {
+            options.validation_level = ValidationLevel::None;
+        }
+        options.lazy &= !self.eager;
+        options.mangle_root |= self.mangle_root;
+        if self.keep.is_some() {
+            options.keep = self.keep.clone();
+        }
+        if !options.strip {
+            options.keep = None;
+        }
+        options.keep_root |= self.keep_root;
+        if self.entry_points.is_some() {
+            options.entry_points = self.entry_points.clone();
+        }
+        if self.prelude.is_some() {
+            options.prelude = self.prelude.clone();
+        }
+        options.group_by_module |= self.group_by_module;
+        options.assign_override_ids |= self.assign_override_ids;
+        options.strip_members |= self.strip_members;
+        if self.compat {
+            options.target = wesl::Target::Compat;
+        }
+        if self.header || self.header_timestamp {
+            let header = options.header.get_or_insert_default();
+            header.timestamp |= self.header_timestamp;
+        }
+        if self.max_workgroup_storage_size.is_some() {
+            options.max_workgroup_storage_size = self.max_workgroup_storage_size;
+        }
+        if let Some(default) = self.feature_default {
+            options.features.default = default.into();
+        }
+        options
+            .features
+            .flags
+            .extend(self.feature.iter().map(|(k, v)| (k.clone(), (*v).into())));
+        options
+            .define_consts
+            .extend(self.define_const.iter().cloned());
+
+        Ok(options)
     }
 }
 
@@ -212,10 +419,35 @@ impl From<&CompOptsArgs> for CompileOptions {
 struct CompileArgs {
     #[command(flatten)]
     options: CompOptsArgs,
+    /// Only resolve imports and print the list of files the root module depends on
+    #[arg(long, value_name = "FORMAT")]
+    emit_deps: Option<DepsFormat>,
+    /// Name of the target in the Makefile depfile. Defaults to the root file with a
+    /// `.wgsl` extension
+    #[arg(long, value_name = "NAME")]
+    deps_target: Option<String>,
+    /// Write one WGSL file per module in this directory instead of printing the output.
+    /// The files can be concatenated in alphabetical order. Implies `--group-by-module`
+    #[arg(long, value_name = "DIR")]
+    split_modules: Option<PathBuf>,
+    /// Write one WGSL file per entry point in this directory, each keeping only the
+    /// declarations its entry point uses, and print a report of the duplicated code
+    #[arg(long, value_name = "DIR", conflicts_with = "split_modules")]
+    split_entry_points: Option<PathBuf>,
     /// WESL file entry point
     file: Option<PathBuf>,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum DepsFormat {
+    /// Makefile/ninja depfile
+    Make,
+    /// JSON list of modules and files
+    Json,
+    /// Hash of the module sources and compilation options, e.g. a remote cache key
+    CacheKey,
+}
+
 #[derive(Args, Clone, Debug)]
 struct CheckArgs {
     /// Input file type (wgsl or wesl)
@@ -225,6 +457,17 @@ struct CheckArgs {
     #[cfg(feature = "naga")]
     #[arg(long)]
     naga: bool,
+    /// Compile the root module with every combination of the feature flags declared with
+    /// `@feature`, in parallel, and report the combinations triggering each error. Flags
+    /// set with `--feature` are not varied
+    #[arg(long)]
+    all_variants: bool,
+    /// Check at most this many combinations with `--all-variants`, evenly spread over all
+    /// the combinations. Required when there are more than 1024 combinations
+    #[arg(long, value_name = "N", requires = "all_variants")]
+    max_variants: Option<NonZeroUsize>,
+    #[command(flatten)]
+    options: CompOptsArgs,
     /// WGSL file entry point
     file: Option<PathBuf>,
 }
@@ -340,6 +583,66 @@ struct EvalArgs {
     expr: String,
 }
 
+#[derive(Args, Clone, Debug)]
+struct LayoutArgs {
+    #[command(flatten)]
+    options: CompOptsArgs,
+    /// Output Markdown tables
+    #[arg(long)]
+    markdown: bool,
+    /// WESL entrypoint module declaring the structs
+    #[arg(long)]
+    file: Option<PathBuf>,
+    /// Name of the struct. Prints all structs if unset
+    name: Option<String>,
+}
+
+#[derive(Args, Clone, Debug)]
+struct CostArgs {
+    #[command(flatten)]
+    options: CompOptsArgs,
+    /// WESL file entry point
+    file: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug)]
+struct GraphArgs {
+    #[command(flatten)]
+    options: CompOptsArgs,
+    /// Output format. Import cycles are highlighted in red and the heaviest path in
+    /// bold
+    #[arg(long, default_value = "dot")]
+    format: GraphFormat,
+    /// WESL file entry point
+    file: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug)]
+struct SizeArgs {
+    #[command(flatten)]
+    options: CompOptsArgs,
+    /// Print one report per entry point, each keeping only the declarations its entry
+    /// point uses
+    #[arg(long)]
+    per_entry_point: bool,
+    /// Number of declarations to list, the largest first. Lists all declarations by
+    /// default
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+    /// WESL file entry point
+    file: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GraphFormat {
+    /// Graphviz DOT
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+    /// JSON object with the modules, imports, cycles and heaviest path
+    Json,
+}
+
 #[derive(Args, Clone, Debug)]
 struct ExecArgs {
     /// Context to evaluate the expression into
@@ -374,10 +677,80 @@ struct PkgArgs {
     dir: PathBuf,
 }
 
+#[derive(Args, Clone, Debug)]
+struct FixImportsArgs {
+    /// Replace a module path prefix. Can be repeated.
+    /// Example: `--map package::utils=package::common::utils` or `--map old_pkg=new_pkg`.
+    /// Components that are not module names are string literals, e.g. `package::"a=b"`
+    #[arg(long = "map", value_name = "OLD=NEW", value_parser = parse_path_map, required = true)]
+    maps: Vec<(ModulePath, ModulePath)>,
+    /// Print the files that would be modified without writing them
+    #[arg(long)]
+    dry_run: bool,
+    /// Files or directories to rewrite. Directories are searched recursively for .wesl
+    /// and .wgsl files.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug)]
+struct ImportWgslArgs {
+    /// Directory containing the WGSL files. It is searched recursively, and each file
+    /// becomes a module of the package.
+    input: PathBuf,
+    /// Output directory of the WESL package
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Print the files that would be written without writing them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args, Clone, Debug)]
+struct InterfaceArgs {
+    /// Directory containing the WESL package. It is searched recursively for .wesl and
+    /// .wgsl files.
+    input: PathBuf,
+    /// Output directory of the interface modules
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Print the files that would be written without writing them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+fn parse_path_map(s: &str) -> Result<(ModulePath, ModulePath), Box<dyn Error + Send + Sync>> {
+    // the separator is the first `=` outside of the string literal components.
+    let (mut in_str, mut escaped) = (false, false);
+    let pos = s
+        .find(|c| {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_str => escaped = true,
+                '"' => in_str = !in_str,
+                '=' if !in_str => return true,
+                _ => {}
+            }
+            false
+        })
+        .ok_or("expected OLD=NEW")?;
+    Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
+}
+
 #[derive(Clone, Debug, thiserror::Error)]
 enum CliError {
     #[error("input file not found")]
     FileNotFound,
+    #[error("invalid options file: {0}")]
+    OptionsFile(String),
+    #[error("invalid manifest file `{0}`: {1}")]
+    Manifest(PathBuf, String),
+    #[error("invalid import map file `{0}`: {1}")]
+    ImportMap(PathBuf, String),
+    #[error("failed to access `{0}`: {1}")]
+    Io(PathBuf, String),
+    #[error("struct `{0}` not found")]
+    StructNotFound(String),
     #[error("resource `@group({0}) @binding({1})` not found")]
     ResourceNotFound(u32, u32),
     #[error(
@@ -386,6 +759,12 @@ enum CliError {
     ResourceIncompatible(u32, u32, u32, wesl::eval::Type, u32),
     #[error("Could not convert instance to buffer (type `{0}` is not storable)")]
     NotStorable(wesl::eval::Type),
+    #[error("{0} of {1} feature variants failed")]
+    VariantsFailed(usize, usize),
+    #[error(
+        "{0} feature flags give {1} variants, more than {DEFAULT_MAX_VARIANTS}: pass `--max-variants` to check a subset"
+    )]
+    TooManyVariants(usize, u128),
     #[error("{0}")]
     WeslError(#[from] wesl::Error),
     #[error("{0}")]
@@ -398,22 +777,134 @@ enum CliError {
     NagaValid(Box<naga::WithSpan<naga::valid::ValidationError>>, String),
 }
 
+#[derive(Clone)]
 enum FileOrSource {
     File(PathBuf),
     Source(String),
 }
 
-fn run_compile(
+/// A resolver that writes the modules it resolves to a manifest file when dropped.
+struct ManifestRecorder {
+    resolver: RecordingResolver<Box<dyn Resolver>>,
+    file: PathBuf,
+}
+
+impl Resolver for ManifestRecorder {
+    fn resolve_source<'a>(
+        &'a self,
+        path: &ModulePath,
+    ) -> Result<std::borrow::Cow<'a, str>, wesl::ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, wesl::ResolveError> {
+        self.resolver.resolve_module(path)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+impl Drop for ManifestRecorder {
+    fn drop(&mut self) {
+        let json = serde_json::to_string_pretty(&self.resolver.manifest()).unwrap();
+        if let Err(e) = fs::write(&self.file, json) {
+            eprintln!("{}", CliError::Io(self.file.clone(), e.to_string()));
+        }
+    }
+}
+
+/// A resolver that prints the import map mappings it applied when dropped.
+struct ImportMapNotes {
+    resolver: ImportMapResolver<Box<dyn Resolver>>,
+}
+
+impl Resolver for ImportMapNotes {
+    fn resolve_source<'a>(
+        &'a self,
+        path: &ModulePath,
+    ) -> Result<std::borrow::Cow<'a, str>, wesl::ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, wesl::ResolveError> {
+        self.resolver.resolve_module(path)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+impl Drop for ImportMapNotes {
+    fn drop(&mut self) {
+        for mapping in self.resolver.applied() {
+            eprintln!("note: import map applied: {mapping}");
+        }
+    }
+}
+
+fn read_manifest(file: &PathBuf) -> Result<Manifest, CliError> {
+    let json = fs::read_to_string(file).map_err(|e| CliError::Io(file.clone(), e.to_string()))?;
+    serde_json::from_str(&json).map_err(|e| CliError::Manifest(file.clone(), e.to_string()))
+}
+
+/// Wrap the resolver to apply the `--import-map` (and print the mappings applied with
+/// `--verbose`), then to record or replay a manifest, with `--record-manifest` and
+/// `--replay-manifest`.
+fn hermetic_resolver(
+    options: &CompOptsArgs,
+    resolver: impl Resolver + 'static,
+) -> Result<Box<dyn Resolver>, CliError> {
+    let resolver: Box<dyn Resolver> = match &options.import_map {
+        Some(file) => {
+            let json =
+                fs::read_to_string(file).map_err(|e| CliError::Io(file.clone(), e.to_string()))?;
+            let mut map: ImportMap = serde_json::from_str(&json)
+                .map_err(|e| CliError::ImportMap(file.clone(), e.to_string()))?;
+            // directories are relative to the import map file.
+            let dir = file.parent().unwrap_or(Path::new(""));
+            for mapping in &mut map.mappings {
+                if let ImportTarget::Path(path) = &mut mapping.target {
+                    *path = dir.join(&*path);
+                }
+            }
+            let resolver: Box<dyn Resolver> = Box::new(resolver);
+            let resolver = ImportMapResolver::new(resolver, &map)
+                .map_err(|e| CliError::ImportMap(file.clone(), e.to_string()))?;
+            if options.verbose {
+                Box::new(ImportMapNotes { resolver })
+            } else {
+                Box::new(resolver)
+            }
+        }
+        None => Box::new(resolver),
+    };
+    if let Some(file) = &options.replay_manifest {
+        Ok(Box::new(ReplayResolver::new(read_manifest(file)?)))
+    } else if let Some(file) = &options.record_manifest {
+        Ok(Box::new(ManifestRecorder {
+            resolver: RecordingResolver::new(resolver),
+            file: file.clone(),
+        }))
+    } else {
+        Ok(resolver)
+    }
+}
+
+fn make_compiler(
     options: &CompOptsArgs,
     file_or_source: FileOrSource,
-) -> Result<CompileResult, CliError> {
-    let compile_options = CompileOptions::from(options);
+) -> Result<(Wesl<Box<dyn Resolver>>, ModulePath), CliError> {
+    let compile_options = options.compile_options()?;
 
     let mut compiler = Wesl::new_barebones();
     compiler
         .set_options(compile_options)
-        .use_sourcemap(!options.no_sourcemap)
-        .set_mangler(options.mangler.into());
+        .use_sourcemap(!options.no_sourcemap);
 
     match file_or_source {
         FileOrSource::File(path) => {
@@ -422,16 +913,15 @@ fn run_compile(
                 .as_deref()
                 .or(path.parent())
                 .ok_or(CliError::FileNotFound)?;
+            // the module name is the file name without its `.wesl` or `.wgsl` extension.
             let name = path
-                .file_name()
+                .file_stem()
                 .ok_or(CliError::FileNotFound)?
                 .to_string_lossy()
                 .to_string();
             let path = ModulePath::new(PathOrigin::Absolute, vec![name]);
-            let resolver = StandardResolver::new(base);
-
-            let res = compiler.set_custom_resolver(resolver).compile(&path)?;
-            Ok(res)
+            let resolver = hermetic_resolver(options, StandardResolver::new(base))?;
+            Ok((compiler.set_custom_resolver(resolver), path))
         }
         FileOrSource::Source(source) => {
             let base = std::env::current_dir().unwrap();
@@ -442,11 +932,223 @@ fn run_compile(
             resolver.add_module(ModulePath::new_root(), source.into());
             router.mount_resolver(path.clone(), resolver);
             router.mount_fallback_resolver(StandardResolver::new(base));
+            let resolver = hermetic_resolver(options, router)?;
+            Ok((compiler.set_custom_resolver(resolver), path))
+        }
+    }
+}
+
+fn run_compile(
+    options: &CompOptsArgs,
+    file_or_source: FileOrSource,
+) -> Result<CompileResult, CliError> {
+    let (compiler, path) = make_compiler(options, file_or_source)?;
+    let res = compiler.compile_result(&path)?;
+    for warning in &res.warnings {
+        eprintln!("{warning}");
+    }
+    Ok(res)
+}
+
+/// Display a combination of feature flags as a condition, e.g. `shadows && !fog`.
+fn variant_name(flags: &[String], variant: &Features) -> String {
+    if flags.is_empty() {
+        return "no feature flags".to_string();
+    }
+    flags
+        .iter()
+        .map(|flag| match variant.flags.get(flag) {
+            Some(Feature::Enable) => flag.clone(),
+            _ => format!("!{flag}"),
+        })
+        .collect::<Vec<_>>()
+        .join(" && ")
+}
+
+fn check_variant(
+    options: &CompOptsArgs,
+    file_or_source: FileOrSource,
+    variant: &Features,
+) -> Result<u64, CliError> {
+    let (mut compiler, path) = make_compiler(options, file_or_source)?;
+    compiler.set_features(variant.flags.clone());
+    let comp = compiler.compile_result(&path)?;
+    #[cfg(feature = "naga")]
+    if !options.no_naga {
+        naga_validate(&comp.to_string())?;
+    }
+    Ok(comp.output_hash())
+}
+
+/// The maximum number of combinations checked by `--all-variants` without
+/// `--max-variants`.
+const DEFAULT_MAX_VARIANTS: usize = 1024;
+
+/// Compile the root module with each combination of the declared feature flags, in
+/// parallel. Identical errors are reported once, with the combinations triggering them.
+/// Fails if there are more than [`DEFAULT_MAX_VARIANTS`] combinations and no `limit`.
+///
+/// Returns the number of variants and the number of distinct outputs.
+fn check_variants(
+    options: &CompOptsArgs,
+    file_or_source: FileOrSource,
+    limit: Option<NonZeroUsize>,
+) -> Result<(usize, usize), CliError> {
+    let (compiler, path) = make_compiler(options, file_or_source.clone())?;
+    let explicit = options.compile_options()?.features.flags;
+    let flags = compiler
+        .declared_features(&path)?
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| !explicit.contains_key(name))
+        .collect::<Vec<_>>();
+    let count = Features::variant_count(flags.len());
+    if limit.is_none() && count > DEFAULT_MAX_VARIANTS as u128 {
+        return Err(CliError::TooManyVariants(flags.len(), count));
+    }
+    let variants = Features::variants(&flags, limit);
+    // the variants would overwrite each other's manifest and repeat the notes.
+    let options = &CompOptsArgs {
+        record_manifest: None,
+        verbose: false,
+        ..options.clone()
+    };
 
-            let res = compiler.set_custom_resolver(router).compile(&path)?;
-            Ok(res)
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = variants.len().div_ceil(threads).max(1);
+    let results = std::thread::scope(|s| {
+        let handles = variants
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let file_or_source = &file_or_source;
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|variant| {
+                            check_variant(options, file_or_source.clone(), variant)
+                                .map_err(|e| e.to_string())
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    // errors and the variants triggering them, in order of first appearance.
+    let mut errors: Vec<(String, Vec<String>)> = Vec::new();
+    let mut outputs = HashSet::new();
+    for (variant, res) in variants.iter().zip(results) {
+        match res {
+            Ok(hash) => {
+                outputs.insert(hash);
+            }
+            Err(err) => {
+                let name = variant_name(&flags, variant);
+                match errors.iter_mut().find(|(e, _)| *e == err) {
+                    Some((_, names)) => names.push(name),
+                    None => errors.push((err, vec![name])),
+                }
+            }
+        }
+    }
+    for (err, names) in &errors {
+        eprintln!("{err}");
+        eprintln!("triggered by {} variant(s):", names.len());
+        for name in names {
+            eprintln!("  {name}");
         }
+        eprintln!();
+    }
+    let failed = errors.iter().map(|(_, names)| names.len()).sum::<usize>();
+    if failed > 0 {
+        return Err(CliError::VariantsFailed(failed, variants.len()));
     }
+    Ok((variants.len(), outputs.len()))
+}
+
+/// Escape a path for a Makefile depfile, following the rules GCC uses for `-MD`:
+/// spaces, `#` and `:` are backslash-escaped, `$` becomes `$$`, and backslashes
+/// are doubled only when they precede an escaped character. A Windows drive
+/// letter colon is left as is.
+fn make_escape(path: &Path) -> String {
+    let path = path.display().to_string();
+    let mut res = String::with_capacity(path.len());
+    let mut backslashes = 0;
+    for (i, c) in path.chars().enumerate() {
+        match c {
+            '\\' => {
+                backslashes += 1;
+                res.push(c);
+                continue;
+            }
+            '$' => res.push_str("$$"),
+            ':' if i == 1 && path.as_bytes()[0].is_ascii_alphabetic() => res.push(c),
+            ' ' | '#' | ':' => {
+                res.extend(std::iter::repeat_n('\\', backslashes + 1));
+                res.push(c);
+            }
+            _ => res.push(c),
+        }
+        backslashes = 0;
+    }
+    res
+}
+
+fn emit_deps(
+    options: &CompOptsArgs,
+    file_or_source: FileOrSource,
+    format: DepsFormat,
+    target: &str,
+) -> Result<String, CliError> {
+    let (compiler, path) = make_compiler(options, file_or_source)?;
+    let modules = compiler.resolve_dependencies(&path)?;
+    let deps = modules
+        .into_iter()
+        .map(|module| {
+            let file = compiler.resolver().fs_path(&module);
+            (module, file)
+        })
+        .collect::<Vec<_>>();
+
+    let res = match format {
+        // a replayed compilation only reads the manifest.
+        DepsFormat::Make if options.replay_manifest.is_some() => {
+            let file = options.replay_manifest.as_ref().unwrap();
+            format!("{}: {}", make_escape(Path::new(target)), make_escape(file))
+        }
+        DepsFormat::Make => {
+            // modules without a file (e.g. packages) are not tracked by make.
+            let files = deps
+                .iter()
+                .filter_map(|(_, file)| file.as_ref())
+                .map(|file| make_escape(file))
+                .collect::<Vec<_>>();
+            format!("{}: {}", make_escape(Path::new(target)), files.join(" "))
+        }
+        DepsFormat::CacheKey => {
+            let modules = deps.iter().map(|(module, _)| module);
+            let manifest =
+                Manifest::new(modules, compiler.resolver()).map_err(wesl::Error::from)?;
+            format!("{:016x}", manifest.cache_key(&options.compile_options()?))
+        }
+        DepsFormat::Json => {
+            let deps = deps
+                .iter()
+                .map(|(module, file)| {
+                    serde_json::json!({
+                        "module": module.to_string(),
+                        "file": file.as_ref().map(|file| file.display().to_string()),
+                    })
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_string_pretty(&deps).unwrap()
+        }
+    };
+    Ok(res)
 }
 
 fn parse_binding(
@@ -520,6 +1222,150 @@ fn parse_override(src: &str, wgsl: &TranslationUnit) -> Result<Instance, CliErro
     Ok(inst)
 }
 
+/// Collect the WESL and WGSL files in a list of files and directories.
+fn collect_shader_files(paths: &[PathBuf], files: &mut Vec<PathBuf>) -> Result<(), CliError> {
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)
+                .and_then(|dir| {
+                    dir.map(|entry| entry.map(|entry| entry.path()))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .map_err(|e| CliError::Io(path.clone(), e.to_string()))?;
+            entries.sort();
+            let entries = entries
+                .into_iter()
+                .filter(|path| {
+                    path.is_dir()
+                        || path
+                            .extension()
+                            .is_some_and(|ext| ext == "wesl" || ext == "wgsl")
+                })
+                .collect::<Vec<_>>();
+            collect_shader_files(&entries, files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(())
+}
+
+fn fix_imports(args: &FixImportsArgs) -> Result<(), CliError> {
+    let map = |path: &ModulePath| {
+        args.maps.iter().find_map(|(old, new)| {
+            (path.origin == old.origin && path.components.starts_with(&old.components)).then(|| {
+                new.clone()
+                    .join(path.components[old.components.len()..].iter().cloned())
+            })
+        })
+    };
+
+    let mut files = Vec::new();
+    collect_shader_files(&args.paths, &mut files)?;
+
+    for file in files {
+        let source =
+            fs::read_to_string(&file).map_err(|e| CliError::Io(file.clone(), e.to_string()))?;
+        let fixed = wesl::rewrite_import_paths_in_source(&source, &map).map_err(|e| {
+            Diagnostic::from(e)
+                .with_module_path(ModulePath::new_root(), Some(file.display().to_string()))
+                .with_source(source.clone())
+        })?;
+        if fixed != source {
+            println!("{}", file.display());
+            if !args.dry_run {
+                fs::write(&file, fixed).map_err(|e| CliError::Io(file.clone(), e.to_string()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn import_wgsl(args: &ImportWgslArgs) -> Result<(), CliError> {
+    let mut files = Vec::new();
+    collect_shader_files(std::slice::from_ref(&args.input), &mut files)?;
+
+    let mut sources = Vec::new();
+    for file in files
+        .iter()
+        .filter(|file| file.extension().is_some_and(|ext| ext == "wgsl"))
+    {
+        let source =
+            fs::read_to_string(file).map_err(|e| CliError::Io(file.clone(), e.to_string()))?;
+        let rel = file
+            .strip_prefix(&args.input)
+            .unwrap_or(file)
+            .with_extension("");
+        let path = ModulePath::new_root().join(
+            rel.components()
+                .map(|comp| comp.as_os_str().to_string_lossy().to_string()),
+        );
+        sources.push((path, source));
+    }
+
+    let res = wesl::import_wgsl(sources)?;
+
+    for (module, name, candidates) in &res.ambiguous {
+        let candidates = candidates
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        eprintln!("warning: `{name}` in `{module}` is ambiguous, candidates: {candidates}");
+    }
+    for (module, name) in &res.unresolved {
+        eprintln!("warning: `{name}` in `{module}` is not declared in any module");
+    }
+    for (module, source) in &res.modules {
+        // module names can contain dots: append the extension instead of replacing it.
+        let mut file = args
+            .output
+            .join(module.components.iter().collect::<PathBuf>())
+            .into_os_string();
+        file.push(".wesl");
+        let file = PathBuf::from(file);
+        println!("{}", file.display());
+        if !args.dry_run {
+            if let Some(dir) = file.parent() {
+                fs::create_dir_all(dir)
+                    .map_err(|e| CliError::Io(dir.to_path_buf(), e.to_string()))?;
+            }
+            fs::write(&file, source).map_err(|e| CliError::Io(file.clone(), e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+fn interface(args: &InterfaceArgs) -> Result<(), CliError> {
+    let mut files = Vec::new();
+    collect_shader_files(std::slice::from_ref(&args.input), &mut files)?;
+
+    for file in files {
+        let source =
+            fs::read_to_string(&file).map_err(|e| CliError::Io(file.clone(), e.to_string()))?;
+        let wesl = source.parse::<TranslationUnit>().map_err(|e| {
+            Diagnostic::from(wesl::Error::from(e))
+                .with_module_path(ModulePath::new_root(), Some(file.display().to_string()))
+                .with_source(source.clone())
+        })?;
+        let output = args
+            .output
+            .join(file.strip_prefix(&args.input).unwrap_or(&file))
+            .with_extension("wesli");
+        println!("{}", output.display());
+        if !args.dry_run {
+            if let Some(dir) = output.parent() {
+                fs::create_dir_all(dir)
+                    .map_err(|e| CliError::Io(dir.to_path_buf(), e.to_string()))?;
+            }
+            let interface = wesl::module_interface(&wesl).to_string();
+            fs::write(&output, interface)
+                .map_err(|e| CliError::Io(output.clone(), e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
 fn main() {
     let cli = Cli::try_parse()
         .inspect_err(|e| {
@@ -556,6 +1402,11 @@ fn naga_validate(source: &str) -> Result<(), CliError> {
 
 fn run(cli: Cli) -> Result<(), CliError> {
     match cli.command {
+        Command::Check(args) if args.all_variants => {
+            let input = file_or_source(args.file).ok_or(CliError::FileNotFound)?;
+            let (count, distinct) = check_variants(&args.options, input, args.max_variants)?;
+            println!("OK ({count} variants, {distinct} distinct outputs)");
+        }
         Command::Check(args) => {
             let source = if let Some(file) = &args.file {
                 fs::read_to_string(file).map_err(|_| CliError::FileNotFound)?
@@ -592,7 +1443,38 @@ fn run(cli: Cli) -> Result<(), CliError> {
             }
             println!("OK");
         }
-        Command::Compile(args) => {
+        Command::Compile(args) if args.emit_deps.is_some() => {
+            let format = args.emit_deps.unwrap();
+            let target = args.deps_target.clone().unwrap_or_else(|| {
+                args.file
+                    .as_ref()
+                    .map(|file| file.with_extension("wgsl").display().to_string())
+                    .unwrap_or_else(|| "command-line.wgsl".to_string())
+            });
+            let input = file_or_source(args.file).ok_or(CliError::FileNotFound)?;
+            let deps = emit_deps(&args.options, input, format, &target)?;
+            println!("{deps}");
+        }
+        Command::Compile(args) if args.split_entry_points.is_some() => {
+            let dir = args.split_entry_points.unwrap();
+            let input = file_or_source(args.file).ok_or(CliError::FileNotFound)?;
+            let (compiler, path) = make_compiler(&args.options, input)?;
+            let res = compiler.compile_entry_points(&path)?;
+            for warning in &res.warnings {
+                eprintln!("{warning}");
+            }
+            #[cfg(feature = "naga")]
+            if !args.options.no_naga {
+                for (_, comp) in res.iter() {
+                    naga_validate(&comp.to_string())?;
+                }
+            }
+            res.write_files(&dir)
+                .map_err(|e| CliError::Io(dir.clone(), e.to_string()))?;
+            print!("{}", res.report());
+        }
+        Command::Compile(mut args) => {
+            args.options.group_by_module |= args.split_modules.is_some();
             let comp = file_or_source(args.file)
                 .map(|input| run_compile(&args.options, input))
                 .unwrap_or_else(|| Ok(CompileResult::default()))?;
@@ -600,7 +1482,12 @@ fn run(cli: Cli) -> Result<(), CliError> {
             if !args.options.no_naga {
                 naga_validate(&comp.to_string())?;
             }
-            println!("{comp}");
+            if let Some(dir) = &args.split_modules {
+                comp.write_module_files(dir)
+                    .map_err(|e| CliError::Io(dir.clone(), e.to_string()))?;
+            } else {
+                println!("{comp}");
+            }
         }
         Command::Eval(args) => {
             let comp = file_or_source(args.file)
@@ -617,6 +1504,96 @@ fn run(cli: Cli) -> Result<(), CliError> {
                 println!("{}", eval.inst)
             }
         }
+        Command::Layout(args) => {
+            // unused structs are kept.
+            let options = CompOptsArgs {
+                no_strip: true,
+                ..args.options
+            };
+            let comp = file_or_source(args.file)
+                .map(|input| run_compile(&options, input))
+                .unwrap_or_else(|| Ok(CompileResult::default()))?;
+            let mut layouts = comp.struct_layouts()?;
+            if let Some(name) = &args.name {
+                layouts.retain(|layout| {
+                    layout.name == *name
+                        || comp
+                            .provenance_by_name(&layout.name)
+                            .is_some_and(|prov| prov.name == *name)
+                });
+                if layouts.is_empty() {
+                    return Err(CliError::StructNotFound(name.clone()));
+                }
+            }
+            for layout in layouts {
+                if args.markdown {
+                    println!("{}", layout.to_markdown());
+                } else {
+                    println!("{layout}");
+                }
+            }
+        }
+        Command::Cost(args) => {
+            let comp = file_or_source(args.file)
+                .map(|input| run_compile(&args.options, input))
+                .unwrap_or_else(|| Ok(CompileResult::default()))?;
+            for cost in comp.costs() {
+                println!("{cost}");
+            }
+        }
+        Command::Graph(args) => {
+            let input = file_or_source(args.file).ok_or(CliError::FileNotFound)?;
+            let (compiler, path) = make_compiler(&args.options, input)?;
+            let graph = compiler.module_graph(&path)?;
+            match args.format {
+                GraphFormat::Dot => print!("{}", graph.to_dot()),
+                GraphFormat::Mermaid => print!("{}", graph.to_mermaid()),
+                GraphFormat::Json => {
+                    let modules = graph
+                        .modules
+                        .iter()
+                        .map(|module| {
+                            serde_json::json!({
+                                "module": module.path.to_string(),
+                                "declarations": module.declarations,
+                                "bytes": module.bytes,
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    let json = serde_json::json!({
+                        "modules": modules,
+                        "imports": graph.imports,
+                        "cycles": graph.cycles(),
+                        "heaviest_path": graph.heaviest_path(),
+                    });
+                    println!("{}", serde_json::to_string_pretty(&json).unwrap());
+                }
+            }
+        }
+        Command::Size(args) => {
+            let input = file_or_source(args.file).ok_or(CliError::FileNotFound)?;
+            let (compiler, path) = make_compiler(&args.options, input)?;
+            let reports = if args.per_entry_point {
+                let res = compiler.compile_entry_points(&path)?;
+                res.iter()
+                    .map(|(name, comp)| (Some(name.to_string()), comp.size_report()))
+                    .collect()
+            } else {
+                vec![(None, compiler.compile(&path)?.report)]
+            };
+            for (i, (name, mut report)) in reports.into_iter().enumerate() {
+                if let Some(limit) = args.limit {
+                    report.declarations.truncate(limit);
+                }
+                if i > 0 {
+                    println!();
+                }
+                if let Some(name) = name {
+                    println!("entry point `{name}`");
+                }
+                print!("{report}");
+            }
+        }
         Command::Exec(args) => {
             let comp = file_or_source(args.file)
                 .map(|input| run_compile(&args.options, input))
@@ -687,6 +1664,162 @@ fn run(cli: Cli) -> Result<(), CliError> {
                 .codegen();
             println!("{code}");
         }
+        Command::Repl => repl::run(),
+        Command::FixImports(args) => fix_imports(&args)?,
+        Command::ImportWgsl(args) => import_wgsl(&args)?,
+        Command::Interface(args) => interface(&args)?,
     };
     Ok(())
 }
+
+#[test]
+fn test_options_file() {
+    #[derive(Parser)]
+    struct TestArgs {
+        #[command(flatten)]
+        options: CompOptsArgs,
+    }
+    let dir = std::env::temp_dir().join(format!("wesl-cli-options-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("options.json");
+    fs::write(&file, r#"{ "strip": false, "mangler": "hash" }"#).unwrap();
+    let options = |file: &Path, flags: &[&str]| {
+        let args = ["wesl", "--options", file.to_str().unwrap()];
+        TestArgs::parse_from(args.iter().chain(flags))
+            .options
+            .compile_options()
+    };
+
+    // missing fields take their default value
+    let opts = options(&file, &[]).unwrap();
+    assert!(!opts.strip);
+    assert!(opts.imports);
+    assert_eq!(opts.mangler, ManglerKind::Hash);
+
+    // command-line flags take precedence
+    let opts = options(&file, &["--mangler", "none", "--no-imports"]).unwrap();
+    assert_eq!(opts.mangler, ManglerKind::None);
+    assert!(!opts.imports);
+
+    let missing = dir.join("missing.json");
+    let err = options(&missing, &[]).unwrap_err();
+    assert!(
+        matches!(&err, CliError::Io(path, _) if *path == missing),
+        "{err}"
+    );
+
+    fs::write(&file, r#"{ "mangler": "unknown" }"#).unwrap();
+    let err = options(&file, &[]).unwrap_err();
+    assert!(matches!(err, CliError::OptionsFile(_)), "{err}");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_parse_path_map() {
+    let map = |s: &str| {
+        let (old, new) = parse_path_map(s).unwrap();
+        (old.to_string(), new.to_string())
+    };
+    assert_eq!(
+        map("package::a=package::b::c"),
+        ("package::a".to_string(), "package::b::c".to_string())
+    );
+    assert_eq!(
+        map(r#"package::"a=\"b"=old_pkg"#),
+        (r#"package::"a=\"b""#.to_string(), "old_pkg".to_string())
+    );
+    assert!(parse_path_map("package::a").is_err());
+    assert!(parse_path_map(r#"package::"a=b""#).is_err());
+}
+
+#[test]
+fn test_emit_deps() {
+    #[derive(Parser)]
+    struct TestArgs {
+        #[command(flatten)]
+        options: CompOptsArgs,
+    }
+    let dir = std::env::temp_dir().join(format!("wesl deps {}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.wesl");
+    let util = dir.join("util.wesl");
+    fs::write(
+        &main,
+        "import package::util::f;
+        @fragment fn main() { f(); }",
+    )
+    .unwrap();
+    fs::write(&util, "fn f() {}").unwrap();
+    let options = TestArgs::parse_from(["wesl"]).options;
+    let deps = |format| {
+        emit_deps(
+            &options,
+            FileOrSource::File(main.clone()),
+            format,
+            "out.wgsl",
+        )
+    };
+
+    // spaces in paths are escaped in depfiles.
+    let make = deps(DepsFormat::Make).unwrap();
+    assert_eq!(
+        make,
+        format!("out.wgsl: {} {}", make_escape(&main), make_escape(&util))
+    );
+    assert!(make.contains("wesl\\ deps"));
+
+    let json = deps(DepsFormat::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(json[0]["module"], "package::main");
+    assert_eq!(json[1]["module"], "package::util");
+    assert_eq!(json[1]["file"], util.display().to_string());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_max_variants() {
+    let args = [
+        "wesl",
+        "check",
+        "--all-variants",
+        "--max-variants",
+        "0",
+        "main.wesl",
+    ];
+    assert!(Cli::try_parse_from(args).is_err());
+
+    let dir = std::env::temp_dir().join(format!("wesl-variants-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.wesl");
+    let features = (0..11)
+        .map(|i| format!("@feature(f{i}) "))
+        .collect::<String>();
+    fs::write(&main, format!("{features} const c = 1;")).unwrap();
+    #[derive(Parser)]
+    struct TestArgs {
+        #[command(flatten)]
+        options: CompOptsArgs,
+    }
+    let options = TestArgs::parse_from(["wesl"]).options;
+    let check = |limit| check_variants(&options, FileOrSource::File(main.clone()), limit);
+
+    // 2^11 variants are more than the default maximum.
+    let err = check(None).unwrap_err();
+    assert!(matches!(err, CliError::TooManyVariants(11, 2048)), "{err}");
+    let (count, _) = check(NonZeroUsize::new(4)).unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(count, 4);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_make_escape() {
+    let escape = |path: &str| make_escape(Path::new(path));
+    assert_eq!(escape("dir/my file.wesl"), "dir/my\\ file.wesl");
+    assert_eq!(escape("a#b$c:d.wesl"), "a\\#b$$c\\:d.wesl");
+    // backslashes are doubled only before an escaped character.
+    assert_eq!(escape("a\\b\\ c"), "a\\b\\\\\\ c");
+    assert_eq!(escape("C:\\shaders\\main.wesl"), "C:\\shaders\\main.wesl");
+}
@@ -0,0 +1,247 @@
+//! The interactive evaluator (`wesl repl`).
+
+use std::io::{BufRead, Write};
+
+use wesl::{
+    Diagnostic, SyntaxUtil,
+    eval::{Context, EvalTy, Exec, Ty, Type, ty_eval_ty},
+    syntax::{Attribute, GlobalDeclaration, TranslationUnit},
+};
+
+const HELP: &str = "\
+Enter a declaration (const, override, fn, struct, alias) to add it to the module, or
+an expression to evaluate it. Redeclaring a name replaces the previous declaration.
+Functions are implicitly `@const`.
+
+Commands:
+  :type <expr>    show the type of an expression
+  :layout <type>  show the size and alignment of a type
+  :module         print the current module
+  :clear          remove all declarations
+  :help           show this message
+  :quit           exit the REPL";
+
+/// The module built interactively. Declarations are stored as source code and
+/// re-parsed on each change, so that identifiers stay consistent.
+#[derive(Default)]
+struct Repl {
+    decls: Vec<(Option<String>, String)>,
+}
+
+impl Repl {
+    fn module(&self) -> Result<TranslationUnit, String> {
+        let source = self
+            .decls
+            .iter()
+            .map(|(_, src)| src.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut wgsl = wgsl_parse::parse_str(&source)
+            .map_err(|e| Diagnostic::from(e).with_source(source.clone()).to_string())?;
+        wgsl.retarget_idents();
+        for decl in &mut wgsl.global_declarations {
+            if let GlobalDeclaration::Function(f) = decl.node_mut() {
+                if !f
+                    .attributes
+                    .iter()
+                    .any(|attr| *attr.node() == Attribute::Const)
+                {
+                    f.attributes.push(Attribute::Const.into());
+                }
+            }
+        }
+        Ok(wgsl)
+    }
+
+    fn declare(&mut self, decl: GlobalDeclaration, source: &str) -> Result<(), String> {
+        let name = decl.ident().map(|id| id.to_string());
+        let prev = self.decls.clone();
+        if let Some(name) = &name {
+            self.decls.retain(|(n, _)| n.as_ref() != Some(name));
+        }
+        self.decls.push((name, source.to_string()));
+
+        // reject the declaration if the module does not evaluate anymore
+        let res = self.module().and_then(|wgsl| {
+            let mut ctx = Context::new(&wgsl);
+            wgsl.exec(&mut ctx)
+                .map(|_| ())
+                .map_err(|e| Diagnostic::from(e).with_ctx(&ctx).to_string())
+        });
+        if res.is_err() {
+            self.decls = prev;
+        }
+        res
+    }
+
+    fn eval(&self, source: &str) -> Result<String, String> {
+        let wgsl = self.module()?;
+        let expr = wgsl_parse::parse_expression(source).map_err(|e| {
+            Diagnostic::from(e)
+                .with_source(source.to_string())
+                .to_string()
+        })?;
+        let (inst, ctx) = wesl::eval(&expr, &wgsl);
+        let inst = inst.map_err(|e| {
+            Diagnostic::from(e)
+                .with_source(source.to_string())
+                .with_ctx(&ctx)
+                .to_string()
+        })?;
+        Ok(format!("{inst}: {}", inst.ty()))
+    }
+
+    fn type_of(&self, source: &str) -> Result<String, String> {
+        let wgsl = self.module()?;
+        let expr = wgsl_parse::parse_expression(source).map_err(|e| {
+            Diagnostic::from(e)
+                .with_source(source.to_string())
+                .to_string()
+        })?;
+        let mut ctx = Context::new(&wgsl);
+        let ty = wgsl
+            .exec(&mut ctx)
+            .and_then(|_| expr.eval_ty(&mut ctx))
+            .map_err(|e| {
+                Diagnostic::from(e)
+                    .with_source(source.to_string())
+                    .with_ctx(&ctx)
+                    .to_string()
+            })?;
+        Ok(ty.to_string())
+    }
+
+    fn layout(&self, source: &str) -> Result<String, String> {
+        let wgsl = self.module()?;
+        let ty = wgsl_parse::parse_type(source).map_err(|e| {
+            Diagnostic::from(e)
+                .with_source(source.to_string())
+                .to_string()
+        })?;
+        let mut ctx = Context::new(&wgsl);
+        let ty = wgsl
+            .exec(&mut ctx)
+            .and_then(|_| ty_eval_ty(&ty, &mut ctx))
+            .map_err(|e| {
+                Diagnostic::from(e)
+                    .with_source(source.to_string())
+                    .with_ctx(&ctx)
+                    .to_string()
+            })?;
+        Ok(layout(&ty))
+    }
+
+    fn run_line(&mut self, line: &str) -> Result<Option<String>, String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        if let Some(cmd) = line.strip_prefix(':') {
+            let (cmd, arg) = cmd.split_once(char::is_whitespace).unwrap_or((cmd, ""));
+            return match cmd {
+                "type" | "t" => self.type_of(arg).map(Some),
+                "layout" | "l" => self.layout(arg).map(Some),
+                "module" | "m" => self.module().map(|wgsl| Some(wgsl.to_string())),
+                "clear" => {
+                    self.decls.clear();
+                    Ok(None)
+                }
+                "help" | "h" => Ok(Some(HELP.to_string())),
+                _ => Err(format!("unknown command `:{cmd}`, type `:help` for help")),
+            };
+        }
+        // the trailing semicolon of declarations is optional
+        let source = if line.ends_with(';') || line.ends_with('}') {
+            line.to_string()
+        } else {
+            format!("{line};")
+        };
+        match source.parse::<GlobalDeclaration>() {
+            Ok(GlobalDeclaration::Void) => Ok(None),
+            Ok(decl) => self.declare(decl, &source).map(|()| None),
+            Err(_) => self.eval(line).map(Some),
+        }
+    }
+}
+
+fn layout(ty: &Type) -> String {
+    let size = ty
+        .size_of()
+        .map(|size| size.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    let align = ty
+        .align_of()
+        .map(|align| align.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    format!("{ty}: size {size}, align {align}")
+}
+
+/// Run the REPL until the end of the input or `:quit`.
+pub(crate) fn run() {
+    let mut repl = Repl::default();
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    println!("WESL REPL. Type `:help` for help.");
+
+    loop {
+        print!("> ");
+        std::io::stdout().flush().unwrap();
+        let mut line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        // multi-line input: continue reading while braces are unbalanced
+        while line.matches('{').count() > line.matches('}').count() {
+            print!(". ");
+            std::io::stdout().flush().unwrap();
+            match lines.next() {
+                Some(Ok(next)) => {
+                    line.push('\n');
+                    line.push_str(&next);
+                }
+                _ => break,
+            }
+        }
+        if matches!(line.trim(), ":quit" | ":q") {
+            break;
+        }
+        match repl.run_line(&line) {
+            Ok(Some(out)) => println!("{out}"),
+            Ok(None) => {}
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
+
+#[test]
+fn test_run_line() {
+    let mut repl = Repl::default();
+    let run = |repl: &mut Repl, line: &str| repl.run_line(line).unwrap_or_else(|e| panic!("{e}"));
+
+    // declarations have no output, the trailing semicolon is optional
+    assert_eq!(run(&mut repl, "const a = 2"), None);
+    assert_eq!(
+        run(&mut repl, "fn f(x: u32) -> u32 { return x * 2u; }"),
+        None
+    );
+    assert_eq!(run(&mut repl, "struct S { a: vec3f, b: f32 }"), None);
+
+    assert_eq!(run(&mut repl, "f(3u) + 1u").as_deref(), Some("7u: u32"));
+    assert_eq!(
+        run(&mut repl, ":type a * 2.0").as_deref(),
+        Some("AbstractFloat")
+    );
+    assert_eq!(
+        run(&mut repl, ":layout S").as_deref(),
+        Some("S: size 16, align 16")
+    );
+
+    // a failed redeclaration keeps the previous declaration
+    assert!(repl.run_line("const a: u32 = -1;").is_err());
+    assert_eq!(run(&mut repl, "a").as_deref(), Some("2: AbstractInt"));
+    assert!(repl.run_line("const a = b;").is_err());
+    let module = run(&mut repl, ":module").unwrap();
+    assert!(module.contains("const a = 2;") && !module.contains("const a = b;"));
+
+    assert!(repl.run_line(":unknown").is_err());
+}
@@ -414,8 +414,9 @@ fn quote_impl_inline(kind: QuoteNodeKind, input: TokenStream) -> TokenStream {
     macro_rules! parser_impl {
         ($parser:ident) => {{
             let parser = $parser::new();
+            let options = ParseOptions::default();
 
-            let syntax = parser.parse(lexer).unwrap_or_else(|e| {
+            let syntax = parser.parse(&options, lexer).unwrap_or_else(|e| {
                 let err = wgsl_parse::Error::from(e);
                 let span = err.span;
                 let mut token_stream = FlattenRec::from(input.into_iter());
@@ -452,8 +453,9 @@ fn quote_impl_str(kind: QuoteNodeKind, str: &str) -> TokenStream {
     macro_rules! parser_impl {
         ($parser:ident) => {{
             let parser = $parser::new();
+            let options = ParseOptions::default();
 
-            let syntax = parser.parse(lexer).unwrap_or_else(|e| {
+            let syntax = parser.parse(&options, lexer).unwrap_or_else(|e| {
                 let err = wgsl_parse::Error::from(e);
                 abort_call_site!("{}", err)
             });
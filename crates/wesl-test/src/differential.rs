@@ -0,0 +1,128 @@
+//! Differential testing of the WGSL validator: compare the verdict of
+//! [`wesl::validate_wgsl`] with the verdicts of naga and tint on the same WGSL source.
+//!
+//! Tint is used when its command-line executable is available: set the `WESL_TINT`
+//! environment variable to its path, or put `tint` in the `PATH`.
+
+use std::{
+    fmt::{self, Display},
+    path::PathBuf,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use wesl::{SyntaxUtil, syntax::TranslationUnit};
+
+/// An external WGSL implementation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Validator {
+    Naga,
+    Tint,
+}
+
+impl Display for Validator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Validator::Naga => write!(f, "naga"),
+            Validator::Tint => write!(f, "tint"),
+        }
+    }
+}
+
+/// Whether a validator accepts a source (`Ok`) or rejects it, with the error message.
+pub type Verdict = Result<(), String>;
+
+/// A source accepted by wesl and rejected by another validator, or the reverse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disagreement {
+    pub validator: Validator,
+    pub wesl: Verdict,
+    pub other: Verdict,
+}
+
+impl Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let validator = self.validator;
+        match (&self.wesl, &self.other) {
+            (Ok(()), Err(e)) => write!(f, "accepted by wesl, rejected by {validator}:\n{e}"),
+            (Err(e), Ok(())) => write!(f, "rejected by wesl, accepted by {validator}:\n{e}"),
+            _ => write!(f, "wesl and {validator} agree"),
+        }
+    }
+}
+
+/// The verdict of wesl's validator. Parse errors are rejections.
+pub fn wesl_verdict(source: &str) -> Verdict {
+    let mut wgsl = source
+        .parse::<TranslationUnit>()
+        .map_err(|e| e.to_string())?;
+    wgsl.retarget_idents();
+    wesl::validate_wgsl(&wgsl).map_err(|e| e.to_string())
+}
+
+/// The verdict of naga, with all capabilities enabled.
+pub fn naga_verdict(source: &str) -> Verdict {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| e.emit_to_string(source))?;
+    let mut validator = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    );
+    validator
+        .validate(&module)
+        .map_err(|e| e.emit_to_string(source))?;
+    Ok(())
+}
+
+/// The tint executable, if available.
+fn tint() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("WESL_TINT") {
+        return Some(PathBuf::from(path));
+    }
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join("tint"))
+        .find(|path| path.is_file())
+}
+
+/// The verdict of tint, or `None` if tint is not available.
+pub fn tint_verdict(source: &str) -> Option<Verdict> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let tint = tint()?;
+    // tint reads the shader from a file.
+    let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let file = std::env::temp_dir().join(format!("wesl-diff-{}-{id}.wgsl", std::process::id()));
+    std::fs::write(&file, source).ok()?;
+    let output = Command::new(tint)
+        .args(["--format", "wgsl"])
+        .arg(&file)
+        .output();
+    let _ = std::fs::remove_file(&file);
+    let output = output.ok()?;
+    if output.status.success() {
+        Some(Ok(()))
+    } else {
+        Some(Err(String::from_utf8_lossy(&output.stderr).into_owned()))
+    }
+}
+
+/// Validate a WGSL source with wesl, naga and tint (if available), and return the
+/// validators that disagree with wesl.
+///
+/// Only acceptance is compared, not the error messages.
+pub fn compare(source: &str) -> Vec<Disagreement> {
+    let wesl = wesl_verdict(source);
+    let others = [
+        Some((Validator::Naga, naga_verdict(source))),
+        tint_verdict(source).map(|verdict| (Validator::Tint, verdict)),
+    ];
+    others
+        .into_iter()
+        .flatten()
+        .filter(|(_, other)| wesl.is_ok() != other.is_ok())
+        .map(|(validator, other)| Disagreement {
+            validator,
+            wesl: wesl.clone(),
+            other,
+        })
+        .collect()
+}
@@ -1 +1,3 @@
+#[cfg(feature = "differential")]
+pub mod differential;
 pub mod schemas;
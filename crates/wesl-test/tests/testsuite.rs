@@ -6,7 +6,10 @@
 
 use std::{ffi::OsStr, path::PathBuf, process::Command, str::FromStr};
 
-use wesl::{CompileOptions, EscapeMangler, NoMangler, VirtualResolver, syntax::*, validate_wesl};
+use wesl::{
+    CompileOptions, EscapeMangler, NoMangler, ValidationLevel, VirtualResolver, syntax::*,
+    validate_wesl,
+};
 use wesl_test::schemas::*;
 
 fn eprint_test(case: &Test) {
@@ -187,6 +190,26 @@ fn main() {
             })
     });
 
+    #[cfg(feature = "differential")]
+    tests.extend({
+        let entries = std::fs::read_dir("wgpu/in").expect("missing dir `wgpu/in`");
+        entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension() == Some(OsStr::new("wgsl")))
+            .map(|e| {
+                let filename = e.file_name();
+                let name = format!("differential::wgpu::{filename:?}");
+                libtest_mimic::Trial::test(name, move || differential_case(e.path()))
+                    .with_ignored_flag(
+                        [
+                            "subgroup-operations.wgsl", // naga does not require using `subgroupBallot`
+                        ]
+                        .iter()
+                        .any(|f| filename.to_str() == Some(f)),
+                    )
+            })
+    });
+
     let args = libtest_mimic::Arguments::from_args();
     libtest_mimic::run(&args, tests).exit();
 }
@@ -377,13 +400,40 @@ pub fn validation_case(path: PathBuf) -> Result<(), libtest_mimic::Failed> {
     let options = CompileOptions {
         strip: false,
         lower: true,
-        validate: true,
+        validation_level: ValidationLevel::Standard,
         ..Default::default()
     };
     wesl::compile_sourcemap(&root, &resolver, &NoMangler, &options)?;
     Ok(())
 }
 
+/// Compare the validation of the assembled output with naga and tint.
+#[cfg(feature = "differential")]
+pub fn differential_case(path: PathBuf) -> Result<(), libtest_mimic::Failed> {
+    let input = std::fs::read_to_string(path).expect("failed to read test file");
+    let mut resolver = VirtualResolver::new();
+    let root = ModulePath::from_str("package::main")?;
+    resolver.add_module(root.clone(), input.into());
+    let options = CompileOptions {
+        strip: false,
+        lower: true,
+        validation_level: ValidationLevel::None,
+        ..Default::default()
+    };
+    let output = wesl::compile(&root, &resolver, &NoMangler, &options)?.to_string();
+    let disagreements = wesl_test::differential::compare(&output);
+    if disagreements.is_empty() {
+        Ok(())
+    } else {
+        let report = disagreements
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Err(format!("{report}\noutput:\n{output}").into())
+    }
+}
+
 pub fn bevy_case(path: PathBuf) -> Result<(), libtest_mimic::Failed> {
     let base = path.parent().ok_or("file not found")?;
     let name = path
@@ -404,7 +454,7 @@ pub fn bevy_case(path: PathBuf) -> Result<(), libtest_mimic::Failed> {
         .set_options(CompileOptions {
             strip: false,
             lower: true,
-            validate: true,
+            validation_level: ValidationLevel::Standard,
             lazy: false,
             ..Default::default()
         })
@@ -188,9 +188,14 @@ fn run_compile(args: CompileOptions) -> Result<CompileResult, wesl::Error> {
             generics: args.generics,
             strip: args.strip,
             lower: args.lower,
-            validate: args.validate,
+            validation_level: if args.validate {
+                wesl::ValidationLevel::Standard
+            } else {
+                wesl::ValidationLevel::None
+            },
             lazy: args.lazy,
             mangle_root: args.mangle_root,
+            mangler: args.mangler.into(),
             keep: args.keep,
             features: wesl::Features {
                 default: wesl::Feature::Disable,
@@ -201,10 +206,33 @@ fn run_compile(args: CompileOptions) -> Result<CompileResult, wesl::Error> {
                     .collect(),
             },
             keep_root: args.keep_root,
+            entry_points: None,
+            prelude: None,
+            group_by_module: false,
+            extensions: Default::default(),
+            assign_override_ids: false,
+            strip_members: false,
+            package_features: Vec::new(),
+            limits: Default::default(),
+            debug_printf: None,
+            binding_policy: None,
+            target: wesl::Target::Core,
+            header: None,
+            max_workgroup_storage_size: None,
+            cost_budget: None,
+            eliminate_common_subexpressions: false,
+            normalize_attributes: false,
+            binding_arrays: false,
+            plain_wgsl: false,
+            naming: Default::default(),
+            emit: None,
+            slots: Default::default(),
+            define_consts: Default::default(),
+            lower_switches: false,
+            ..Default::default()
         })
         .use_sourcemap(args.sourcemap)
-        .set_mangler(args.mangler.into())
-        .compile(&root)?;
+        .compile_result(&root)?;
     Ok(comp)
 }
 
@@ -0,0 +1,444 @@
+//! Semantic annotations: custom attributes like `@units(meters)` or
+//! `@color_space(linear)` that are checked where values flow between declarations.
+//!
+//! See [`AnnotationLint`] and [`crate::Wesl::add_annotation_lint`].
+
+use std::collections::HashMap;
+
+use wgsl_parse::{
+    span::Span,
+    syntax::{
+        AssignmentOperator, Attribute, Attributes, BinaryOperator, Expression, ExpressionNode,
+        Function, FunctionCall, GlobalDeclaration, Ident, Statement, StatementNode, Struct,
+        TranslationUnit, UnaryOperator,
+    },
+};
+
+use crate::{Diagnostic, Error, validate::ValidateError, visit::Visit};
+
+/// A semantic annotation, checked across assignments and function calls.
+///
+/// Annotations are custom attributes with one argument, e.g. `@units(meters)`, placed on
+/// declarations, function parameters, function return types and struct members. The
+/// annotation of an expression is inferred from the declarations it reads, and is checked
+/// wherever it flows into another annotated declaration:
+/// * initializers of annotated declarations,
+/// * assignments (`=`, `+=` and `-=`),
+/// * arguments of function calls and struct constructors,
+/// * returned values,
+/// * operands of additions, subtractions and comparisons.
+///
+/// Unannotated values are compatible with every annotation. Products and quotients of
+/// two annotated values are unannotated, scaling an annotated value by an unannotated one
+/// preserves its annotation.
+///
+/// The annotation attributes are removed from the output.
+pub trait AnnotationLint: Send + Sync {
+    /// The name of the custom attribute, e.g. `units` for `@units(meters)`.
+    fn attribute(&self) -> &str;
+
+    /// Check that a value annotated `from` can be used where `to` is expected.
+    ///
+    /// The annotations are the attribute arguments, e.g. `meters`. Return an error
+    /// message if the flow is not allowed. By default, annotations must be identical.
+    fn check_flow(&self, from: &str, to: &str) -> Result<(), String> {
+        if from == to {
+            Ok(())
+        } else {
+            Err(format!("expected `{to}`, found `{from}`"))
+        }
+    }
+}
+
+/// An [`AnnotationLint`] that requires annotations to be identical.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExactAnnotation(pub String);
+
+impl AnnotationLint for ExactAnnotation {
+    fn attribute(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A value in scope: its annotation and the identifier of its type, if known.
+type Value = (Option<String>, Option<Ident>);
+
+struct Checker<'a> {
+    lint: &'a dyn AnnotationLint,
+    decls: &'a HashMap<Ident, &'a GlobalDeclaration>,
+    locals: HashMap<Ident, Value>,
+    function: Option<&'a Function>,
+}
+
+impl<'a> Checker<'a> {
+    fn annotation(&self, attrs: &Attributes) -> Option<String> {
+        attrs.iter().find_map(|attr| match attr.node() {
+            Attribute::Custom(attr) if attr.name == self.lint.attribute() => Some(
+                attr.arguments
+                    .iter()
+                    .flatten()
+                    .next()
+                    .map(|arg| arg.to_string())
+                    .unwrap_or_default(),
+            ),
+            _ => None,
+        })
+    }
+
+    fn flow(&self, from: &Option<String>, to: &Option<String>, span: Span) -> Result<(), Error> {
+        let (Some(from), Some(to)) = (from, to) else {
+            return Ok(());
+        };
+        self.lint.check_flow(from, to).map_err(|msg| {
+            let err = ValidateError::Annotation(self.lint.attribute().to_string(), msg);
+            let mut diagnostic = Diagnostic::from(err).with_span(span);
+            if let Some(f) = self.function {
+                diagnostic = diagnostic.with_declaration(f.ident.to_string());
+            }
+            diagnostic.into()
+        })
+    }
+
+    fn value(&self, ident: &Ident) -> Value {
+        if let Some(value) = self.locals.get(ident) {
+            return value.clone();
+        }
+        match self.decls.get(ident).copied() {
+            Some(GlobalDeclaration::Declaration(decl)) => (
+                self.annotation(&decl.attributes),
+                decl.ty.as_ref().map(|ty| ty.ident.clone()),
+            ),
+            _ => (None, None),
+        }
+    }
+
+    fn struct_decl(&self, ty: &Ident) -> Option<&'a Struct> {
+        match self.decls.get(ty)? {
+            GlobalDeclaration::Struct(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The identifier of the type of an expression, if it is a known struct type.
+    fn struct_ty(&self, expr: &Expression) -> Option<Ident> {
+        let ty = match expr {
+            Expression::Parenthesized(paren) => return self.struct_ty(&paren.expression),
+            Expression::TypeOrIdentifier(ty) => self.value(&ty.ident).1?,
+            Expression::NamedComponent(comp) => {
+                let s = self.struct_decl(&self.struct_ty(&comp.base)?)?;
+                let m = s
+                    .members
+                    .iter()
+                    .find(|m| *m.ident.name() == *comp.component.name())?;
+                m.ty.ident.clone()
+            }
+            Expression::FunctionCall(call) => match self.decls.get(&call.ty.ident)? {
+                GlobalDeclaration::Function(f) => f.return_type.as_ref()?.ident.clone(),
+                GlobalDeclaration::Struct(s) => s.ident.clone(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        self.struct_decl(&ty).map(|_| ty)
+    }
+
+    /// The annotation of an expression. Checks the flows in the expression.
+    fn expr(&self, expr: &ExpressionNode) -> Result<Option<String>, Error> {
+        let annotation = match expr.node() {
+            Expression::Literal(_) => None,
+            Expression::Parenthesized(paren) => self.expr(&paren.expression)?,
+            Expression::NamedComponent(comp) => {
+                let base = self.expr(&comp.base)?;
+                match self.struct_ty(&comp.base) {
+                    Some(ty) => self.struct_decl(&ty).and_then(|s| {
+                        let m = s
+                            .members
+                            .iter()
+                            .find(|m| *m.ident.name() == *comp.component.name())?;
+                        self.annotation(&m.attributes)
+                    }),
+                    // vector swizzle
+                    None => base,
+                }
+            }
+            Expression::Indexing(idx) => {
+                self.expr(&idx.index)?;
+                self.expr(&idx.base)?
+            }
+            Expression::Unary(unary) => {
+                let operand = self.expr(&unary.operand)?;
+                match unary.operator {
+                    UnaryOperator::Negation
+                    | UnaryOperator::AddressOf
+                    | UnaryOperator::Indirection => operand,
+                    _ => None,
+                }
+            }
+            Expression::Binary(binary) => {
+                let left = self.expr(&binary.left)?;
+                let right = self.expr(&binary.right)?;
+                match binary.operator {
+                    BinaryOperator::Addition
+                    | BinaryOperator::Subtraction
+                    | BinaryOperator::Remainder => {
+                        self.flow(&right, &left, expr.span())?;
+                        left.or(right)
+                    }
+                    BinaryOperator::Equality
+                    | BinaryOperator::Inequality
+                    | BinaryOperator::LessThan
+                    | BinaryOperator::LessThanEqual
+                    | BinaryOperator::GreaterThan
+                    | BinaryOperator::GreaterThanEqual => {
+                        self.flow(&right, &left, expr.span())?;
+                        None
+                    }
+                    BinaryOperator::Multiplication => match (left, right) {
+                        (Some(a), None) | (None, Some(a)) => Some(a),
+                        _ => None,
+                    },
+                    BinaryOperator::Division => right.is_none().then_some(left).flatten(),
+                    _ => None,
+                }
+            }
+            Expression::FunctionCall(call) => self.call(call)?,
+            Expression::TypeOrIdentifier(ty) => self.value(&ty.ident).0,
+        };
+        Ok(annotation)
+    }
+
+    /// The annotation of the return value of a call. Checks the arguments.
+    fn call(&self, call: &FunctionCall) -> Result<Option<String>, Error> {
+        let args = call
+            .arguments
+            .iter()
+            .map(|arg| Ok((self.expr(arg)?, arg.span())))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let (expected, ret) = match self.decls.get(&call.ty.ident).copied() {
+            Some(GlobalDeclaration::Function(f)) => (
+                f.parameters
+                    .iter()
+                    .map(|p| self.annotation(&p.attributes))
+                    .collect(),
+                self.annotation(&f.return_attributes),
+            ),
+            Some(GlobalDeclaration::Struct(s)) => (
+                s.members
+                    .iter()
+                    .map(|m| self.annotation(&m.attributes))
+                    .collect(),
+                None,
+            ),
+            _ => (Vec::new(), None),
+        };
+        for ((arg, span), expected) in args.iter().zip(&expected) {
+            self.flow(arg, expected, *span)?;
+        }
+        Ok(ret)
+    }
+
+    fn stat(&mut self, stat: &StatementNode) -> Result<(), Error> {
+        match stat.node() {
+            Statement::Declaration(decl) => {
+                let annotation = self.annotation(&decl.attributes);
+                let value = decl
+                    .initializer
+                    .as_ref()
+                    .map(|init| self.expr(init))
+                    .transpose()?
+                    .flatten();
+                self.flow(&value, &annotation, stat.span())?;
+                let ty = match &decl.ty {
+                    Some(ty) => Some(ty.ident.clone()),
+                    None => decl
+                        .initializer
+                        .as_ref()
+                        .and_then(|init| self.struct_ty(init)),
+                };
+                self.locals
+                    .insert(decl.ident.clone(), (annotation.or(value), ty));
+            }
+            Statement::Assignment(assign) => {
+                let lhs = self.expr(&assign.lhs)?;
+                let rhs = self.expr(&assign.rhs)?;
+                if matches!(
+                    assign.operator,
+                    AssignmentOperator::Equal
+                        | AssignmentOperator::PlusEqual
+                        | AssignmentOperator::MinusEqual
+                ) {
+                    self.flow(&rhs, &lhs, stat.span())?;
+                }
+            }
+            Statement::Increment(stat) => {
+                self.expr(&stat.expression)?;
+            }
+            Statement::Decrement(stat) => {
+                self.expr(&stat.expression)?;
+            }
+            Statement::If(stat) => {
+                self.expr(&stat.if_clause.expression)?;
+                for clause in &stat.else_if_clauses {
+                    self.expr(&clause.expression)?;
+                }
+            }
+            Statement::Switch(stat) => {
+                self.expr(&stat.expression)?;
+            }
+            Statement::Loop(stat) => {
+                if let Some(break_if) = stat.continuing.as_ref().and_then(|c| c.break_if.as_ref()) {
+                    self.expr(&break_if.expression)?;
+                }
+            }
+            Statement::For(stat) => {
+                if let Some(cond) = &stat.condition {
+                    self.expr(cond)?;
+                }
+            }
+            Statement::While(stat) => {
+                self.expr(&stat.condition)?;
+            }
+            Statement::Return(ret) => {
+                if let (Some(expr), Some(f)) = (&ret.expression, self.function) {
+                    let value = self.expr(expr)?;
+                    self.flow(&value, &self.annotation(&f.return_attributes), stat.span())?;
+                }
+            }
+            Statement::FunctionCall(stat) => {
+                self.call(&stat.call)?;
+            }
+            Statement::ConstAssert(stat) => {
+                self.expr(&stat.expression)?;
+            }
+            Statement::Void
+            | Statement::Compound(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Discard(_) => {}
+        }
+        for stat in Visit::<StatementNode>::visit(stat.node()) {
+            self.stat(stat)?;
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, f: &'a Function) -> Result<(), Error> {
+        self.function = Some(f);
+        self.locals = f
+            .parameters
+            .iter()
+            .map(|p| {
+                let value = (self.annotation(&p.attributes), Some(p.ty.ident.clone()));
+                (p.ident.clone(), value)
+            })
+            .collect();
+        for stat in &f.body.statements {
+            self.stat(stat)?;
+        }
+        self.function = None;
+        Ok(())
+    }
+}
+
+/// The module-scope declarations of resolved modules, by identifier.
+pub(crate) fn declarations<'a>(
+    modules: impl IntoIterator<Item = &'a TranslationUnit>,
+) -> HashMap<Ident, &'a GlobalDeclaration> {
+    modules
+        .into_iter()
+        .flat_map(|wesl| &wesl.global_declarations)
+        .filter_map(|decl| Some((decl.ident()?.clone(), decl.node())))
+        .collect()
+}
+
+/// Check the annotation flows in a module.
+///
+/// `decls` are the declarations of all modules, see [`declarations`]. Since identifiers
+/// are shared between modules after import resolution, this checks calls to imported
+/// functions.
+pub(crate) fn check_annotations(
+    wesl: &TranslationUnit,
+    decls: &HashMap<Ident, &GlobalDeclaration>,
+    lints: &[Box<dyn AnnotationLint>],
+) -> Result<(), Error> {
+    for lint in lints {
+        let mut checker = Checker {
+            lint: lint.as_ref(),
+            decls,
+            locals: HashMap::new(),
+            function: None,
+        };
+        for decl in &wesl.global_declarations {
+            match decl.node() {
+                GlobalDeclaration::Declaration(d) => {
+                    if let Some(init) = &d.initializer {
+                        let value = checker.expr(init)?;
+                        checker.flow(&value, &checker.annotation(&d.attributes), decl.span())?;
+                    }
+                }
+                GlobalDeclaration::Function(f) => checker.function(f)?,
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Remove the annotation attributes from a module.
+pub(crate) fn strip_annotations(wesl: &mut TranslationUnit, lints: &[Box<dyn AnnotationLint>]) {
+    for attrs in Visit::<Attributes>::visit_mut(wesl) {
+        attrs.retain(|attr| match attr.node() {
+            Attribute::Custom(attr) => !lints.iter().any(|lint| lint.attribute() == attr.name),
+            _ => true,
+        });
+    }
+}
+
+#[test]
+fn test_annotations() {
+    use crate::{VirtualResolver, Wesl};
+
+    let compile = |source: &'static str| {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module("package::main".parse().unwrap(), source.into());
+        resolver.add_module(
+            "package::light".parse().unwrap(),
+            "struct Light { @units(watts) power: f32, @units(meters) radius: f32 }
+            fn falloff(@units(meters) d: f32, @units(meters) r: f32) -> f32 { return d / r; }"
+                .into(),
+        );
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        compiler
+            .use_imports(true)
+            .add_annotation_lint(ExactAnnotation("units".to_string()));
+        compiler.compile(&"package::main".parse().unwrap())
+    };
+
+    let ok = compile(
+        "import package::light::{Light, falloff};
+        @units(meters) const range = 10.0;
+        fn power(l: Light, @units(meters) d: f32) -> @units(watts) f32 {
+            let r = l.radius;
+            return l.power * falloff(d, r + range);
+        }",
+    )
+    .unwrap();
+    assert!(!ok.to_string().contains("units"), "{ok}");
+
+    let err = |source| match compile(source) {
+        Err(e) => e.to_string(),
+        Ok(res) => panic!("expected an annotation error, got {res}"),
+    };
+    let msg = err("import package::light::falloff;
+        fn f(@units(watts) p: f32) -> f32 { return falloff(p, 1.0); }");
+    assert!(msg.contains("expected `meters`, found `watts`"), "{msg}");
+    let msg = err("import package::light::Light;
+        fn f(l: Light) { @units(meters) var d = 1.0; d = l.power * 2.0; }");
+    assert!(msg.contains("expected `meters`, found `watts`"), "{msg}");
+    let msg = err("import package::light::Light;
+        fn f(l: Light) -> @units(meters) f32 { let d = Light(l.radius, 1.0); return l.radius; }");
+    assert!(msg.contains("expected `watts`, found `meters`"), "{msg}");
+    let msg = err("import package::light::Light;
+        fn f(l: Light) -> bool { return l.power < l.radius; }");
+    assert!(msg.contains("mismatched `@units` annotation"), "{msg}");
+}
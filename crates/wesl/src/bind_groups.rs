@@ -0,0 +1,319 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{AccessMode, AddressSpace, Expression};
+use wgsl_types::{
+    syntax::{SampledType, TexelFormat},
+    ty::TextureType,
+};
+
+use crate::{Binding, EntryPointStage, Reflection};
+
+/// The kind of resource bound to a binding.
+enum Resource {
+    Buffer,
+    Texture,
+    Sampler,
+}
+
+impl Resource {
+    fn of(binding: &Binding) -> Option<Self> {
+        match binding.address_space {
+            AddressSpace::Uniform | AddressSpace::Storage => Some(Self::Buffer),
+            AddressSpace::Handle if binding.texture.is_some() => Some(Self::Texture),
+            AddressSpace::Handle => {
+                // the element type of a `binding_array`.
+                let ty = match binding.ty.template_args.as_deref() {
+                    Some([arg, ..]) if binding.binding_array => match arg.expression.node() {
+                        Expression::TypeOrIdentifier(ty) => ty,
+                        _ => return None,
+                    },
+                    _ => &binding.ty,
+                };
+                let name = ty.ident.name();
+                matches!(name.as_str(), "sampler" | "sampler_comparison").then_some(Self::Sampler)
+            }
+            _ => None,
+        }
+    }
+
+    /// The Rust type of the field and the `wgpu::BindingResource` variant.
+    fn rust(&self, array: bool) -> (&'static str, &'static str) {
+        match (self, array) {
+            (Self::Buffer, false) => ("wgpu::BufferBinding<'a>", "Buffer"),
+            (Self::Buffer, true) => ("&'a [wgpu::BufferBinding<'a>]", "BufferArray"),
+            (Self::Texture, false) => ("&'a wgpu::TextureView", "TextureView"),
+            (Self::Texture, true) => ("&'a [&'a wgpu::TextureView]", "TextureViewArray"),
+            (Self::Sampler, false) => ("&'a wgpu::Sampler", "Sampler"),
+            (Self::Sampler, true) => ("&'a [&'a wgpu::Sampler]", "SamplerArray"),
+        }
+    }
+}
+
+fn sample_type(ty: &SampledType) -> &'static str {
+    match ty {
+        SampledType::I32 => "wgpu::TextureSampleType::Sint",
+        SampledType::U32 => "wgpu::TextureSampleType::Uint",
+        SampledType::F32 => "wgpu::TextureSampleType::Float { filterable: true }",
+    }
+}
+
+fn texel_format(format: &TexelFormat) -> String {
+    match format {
+        #[cfg(feature = "naga-ext")]
+        TexelFormat::Rg11b10Float => "Rg11b10Ufloat".to_string(),
+        _ => format!("{format:?}"),
+    }
+}
+
+/// The `wgpu::BindingType` of a texture, `None` for external textures.
+fn texture_binding_type(texture: &TextureType) -> Option<String> {
+    use TextureType::*;
+    let dim = match texture {
+        Sampled1D(_) | Storage1D(..) => "D1",
+        Sampled2D(_) | Multisampled2D(_) | DepthMultisampled2D | Storage2D(..) | Depth2D => "D2",
+        Sampled2DArray(_) | Storage2DArray(..) | Depth2DArray => "D2Array",
+        Sampled3D(_) | Storage3D(..) => "D3",
+        SampledCube(_) | DepthCube => "Cube",
+        SampledCubeArray(_) | DepthCubeArray => "CubeArray",
+        External => return None,
+        #[cfg(feature = "naga-ext")]
+        Sampled1DArray(_) | Storage1DArray(..) => return None,
+        #[cfg(feature = "naga-ext")]
+        Multisampled2DArray(_) => "D2Array",
+    };
+    let view_dimension = format!("wgpu::TextureViewDimension::{dim}");
+    let ty = match texture {
+        Storage1D(format, access)
+        | Storage2D(format, access)
+        | Storage2DArray(format, access)
+        | Storage3D(format, access) => {
+            let access = match access {
+                AccessMode::Read => "ReadOnly",
+                AccessMode::Write => "WriteOnly",
+                _ => "ReadWrite",
+            };
+            format!(
+                "wgpu::BindingType::StorageTexture {{ access: wgpu::StorageTextureAccess::{access}, format: wgpu::TextureFormat::{}, view_dimension: {view_dimension} }}",
+                texel_format(format)
+            )
+        }
+        _ => {
+            let (sample_type, multisampled) = match texture {
+                Multisampled2D(ty) => (sample_type(ty), true),
+                #[cfg(feature = "naga-ext")]
+                Multisampled2DArray(ty) => (sample_type(ty), true),
+                DepthMultisampled2D => ("wgpu::TextureSampleType::Depth", true),
+                Depth2D | Depth2DArray | DepthCube | DepthCubeArray => {
+                    ("wgpu::TextureSampleType::Depth", false)
+                }
+                Sampled1D(ty) | Sampled2D(ty) | Sampled2DArray(ty) | Sampled3D(ty)
+                | SampledCube(ty) | SampledCubeArray(ty) => (sample_type(ty), false),
+                _ => return None,
+            };
+            format!(
+                "wgpu::BindingType::Texture {{ sample_type: {sample_type}, view_dimension: {view_dimension}, multisampled: {multisampled} }}"
+            )
+        }
+    };
+    Some(ty)
+}
+
+/// The `wgpu::BindingType` of a binding, `None` if it is not supported.
+fn binding_type(binding: &Binding, resource: &Resource, reflection: &Reflection) -> Option<String> {
+    match resource {
+        Resource::Buffer => {
+            let ty = match (binding.address_space, binding.access_mode) {
+                (AddressSpace::Uniform, _) => "wgpu::BufferBindingType::Uniform".to_string(),
+                (_, mode) => format!(
+                    "wgpu::BufferBindingType::Storage {{ read_only: {} }}",
+                    mode == AccessMode::Read
+                ),
+            };
+            // the size of a struct with a runtime-sized array counts one element.
+            let size = match reflection.struct_layouts.iter().find(|layout| {
+                binding.ty.template_args.is_none() && *binding.ty.ident.name() == layout.name
+            }) {
+                Some(layout) => format!("wgpu::BufferSize::new({})", layout.size),
+                None => "None".to_string(),
+            };
+            Some(format!(
+                "wgpu::BindingType::Buffer {{ ty: {ty}, has_dynamic_offset: false, min_binding_size: {size} }}"
+            ))
+        }
+        Resource::Texture => texture_binding_type(binding.texture.as_ref()?),
+        Resource::Sampler => {
+            let ty = if binding.ty.to_string().contains("sampler_comparison") {
+                "Comparison"
+            } else {
+                "Filtering"
+            };
+            Some(format!(
+                "wgpu::BindingType::Sampler(wgpu::SamplerBindingType::{ty})"
+            ))
+        }
+    }
+}
+
+/// Generate typed Rust wrappers of the bind groups of a shader, for the `wgpu` crate.
+///
+/// For each `@group(N)`, a `{Name}BindGroupResources<'a>` struct is generated, with one
+/// field per binding named after the variable, and a `{Name}BindGroup` newtype of
+/// `wgpu::BindGroup` with:
+/// * `GROUP`: the group index,
+/// * `LAYOUT`: the `wgpu::BindGroupLayoutDescriptor` and `create_layout(device)`,
+/// * `create(device, layout, resources)`: create the bind group.
+///
+/// Forgetting a resource or passing a texture for a buffer is then a compile-time error
+/// on the host. `Name` is given by `names`, e.g. `&[(0, "View")]`, and defaults to
+/// `Group{N}`. The layout entries are visible to the stages of the entry points using
+/// them, see [`crate::EntryPoints::stages_using`]. The minimum size of struct buffers
+/// is the size of the struct, see [`Reflection::struct_layouts`].
+///
+/// Push constants, external textures and other unsupported bindings are skipped.
+pub fn rust_bind_groups(reflection: &Reflection, names: &[(u32, &str)]) -> String {
+    let groups = reflection
+        .bindings
+        .iter()
+        .filter_map(|b| Some((b.group?, b.binding?, b, Resource::of(b)?)))
+        .sorted_by_key(|(group, binding, _, _)| (*group, *binding))
+        .chunk_by(|(group, _, _, _)| *group);
+
+    let mut res = String::new();
+    for (group, bindings) in &groups {
+        let name = names
+            .iter()
+            .find(|(n, _)| *n == group)
+            .map_or_else(|| format!("Group{group}"), |(_, name)| name.to_string());
+        let mut fields = String::new();
+        let mut layout = String::new();
+        let mut entries = String::new();
+        for (_, binding, b, resource) in bindings {
+            let Some(ty) = binding_type(b, &resource, reflection) else {
+                continue;
+            };
+            let (field_ty, variant) = resource.rust(b.binding_array);
+            let stages = reflection.entry_points.stages_using(&b.name);
+            let mut stages = stages.iter().map(|stage| match stage {
+                EntryPointStage::Vertex => "wgpu::ShaderStages::VERTEX",
+                EntryPointStage::Fragment => "wgpu::ShaderStages::FRAGMENT",
+                EntryPointStage::Compute => "wgpu::ShaderStages::COMPUTE",
+            });
+            let visibility = match stages.next() {
+                Some(first) => stages.fold(first.to_string(), |acc, s| format!("{acc}.union({s})")),
+                None => "wgpu::ShaderStages::NONE".to_string(),
+            };
+            let count = match b.count {
+                Some(n) => format!("std::num::NonZeroU32::new({n})"),
+                None => "None".to_string(),
+            };
+            writeln!(fields, "    /// `@binding({binding})`, `{}`.", b.ty).unwrap();
+            writeln!(fields, "    pub {}: {field_ty},", b.name).unwrap();
+            writeln!(
+                layout,
+                "            wgpu::BindGroupLayoutEntry {{ binding: {binding}, visibility: {visibility}, ty: {ty}, count: {count} }},"
+            )
+            .unwrap();
+            writeln!(
+                entries,
+                "                wgpu::BindGroupEntry {{ binding: {binding}, resource: wgpu::BindingResource::{variant}(resources.{}) }},",
+                b.name
+            )
+            .unwrap();
+        }
+        if !res.is_empty() {
+            res.push('\n');
+        }
+        write!(
+            res,
+            "/// The resources of `@group({group})`.
+pub struct {name}BindGroupResources<'a> {{
+{fields}}}
+
+/// A bind group with the layout of `@group({group})`.
+pub struct {name}BindGroup(pub wgpu::BindGroup);
+
+impl {name}BindGroup {{
+    pub const GROUP: u32 = {group};
+    pub const LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {{
+        label: Some(\"{name}\"),
+        entries: &[
+{layout}        ],
+    }};
+
+    pub fn create_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {{
+        device.create_bind_group_layout(&Self::LAYOUT)
+    }}
+
+    pub fn create(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        resources: {name}BindGroupResources<'_>,
+    ) -> Self {{
+        Self(device.create_bind_group(&wgpu::BindGroupDescriptor {{
+            label: Some(\"{name}\"),
+            layout,
+            entries: &[
+{entries}            ],
+        }}))
+    }}
+}}
+"
+        )
+        .unwrap();
+    }
+    res
+}
+
+#[test]
+fn test_bind_groups() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "struct Camera { view: mat4x4f, pos: vec3f }
+        @group(0) @binding(0) var<uniform> camera: Camera;
+        @group(1) @binding(1) var albedo_sampler: sampler;
+        @group(1) @binding(0) var albedo: texture_2d<f32>;
+        @group(1) @binding(2) var<storage> weights: array<f32>;
+        @vertex fn vs() -> @builtin(position) vec4f { return camera.view * vec4f(camera.pos, 1.0); }
+        @fragment fn fs() -> @location(0) vec4f {
+            return textureSample(albedo, albedo_sampler, vec2f(weights[0])) * camera.view[0];
+        }"
+        .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let res = compiler
+        .compile_result(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let code = res
+        .rust_bind_groups(&[(0, "View")])
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    assert!(
+        code.contains("pub struct ViewBindGroup(pub wgpu::BindGroup);"),
+        "{code}"
+    );
+    // the struct layouts require the `eval` crate feature.
+    #[cfg(feature = "eval")]
+    assert!(code.contains(
+        "wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::VERTEX.union(wgpu::ShaderStages::FRAGMENT), ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: wgpu::BufferSize::new(80) }, count: None },"
+    ));
+    // the fields of group 1 are sorted by binding.
+    assert!(code.contains(
+        "pub struct Group1BindGroupResources<'a> {
+    /// `@binding(0)`, `texture_2d<f32>`.
+    pub albedo: &'a wgpu::TextureView,
+    /// `@binding(1)`, `sampler`.
+    pub albedo_sampler: &'a wgpu::Sampler,
+    /// `@binding(2)`, `array<f32>`.
+    pub weights: wgpu::BufferBinding<'a>,
+}"
+    ));
+    assert!(code.contains("wgpu::BufferBindingType::Storage { read_only: true }"));
+    assert!(code.contains(
+        "wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(resources.albedo_sampler) },"
+    ));
+}
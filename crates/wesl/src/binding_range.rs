@@ -0,0 +1,490 @@
+//! Expansion of `@binding_range` variables into individual bindings.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{
+    Attribute, Declaration, Expression, ExpressionNode, FunctionCall, GlobalDeclaration, Ident,
+    LiteralExpression, Statement, StatementNode, TemplateArg, TranslationUnit, TypeExpression,
+};
+
+use crate::{
+    BindingError, FreshNames, SyntaxUtil, idents::builtin_ident, reflect::Folder, visit::Visit,
+};
+
+const BINDING_RANGE: &str = "binding_range";
+
+/// A `@binding_range` variable expanded into individual bindings.
+/// See [`crate::CompileResult::binding_ranges`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BindingRange {
+    /// The name of the `@binding_range` variable.
+    pub name: String,
+    pub group: u32,
+    /// The binding of the first element.
+    pub binding: u32,
+    /// The variables of the elements, in binding order.
+    pub elements: Vec<String>,
+    /// The generated functions that select an element by a non-constant index.
+    pub accessors: Vec<String>,
+}
+
+struct Range {
+    index: usize,
+    ident: Ident,
+    group: u32,
+    binding: u32,
+    elements: Vec<Ident>,
+    accessors: Vec<String>,
+}
+
+struct Expander<'a> {
+    source: &'a TranslationUnit,
+    folder: Folder<'a>,
+    names: FreshNames,
+    ranges: Vec<Range>,
+    /// The accessor of a range, callee and argument position.
+    accessors: HashMap<(usize, String, usize), Ident>,
+    generated: Vec<String>,
+}
+
+/// The `@binding_range` arguments and the element type of a variable, if it has a
+/// `@binding_range` attribute.
+fn parse_range(
+    decl: &Declaration,
+    wesl: &TranslationUnit,
+    folder: &mut Folder,
+) -> Result<Option<(u32, u32, u32, TypeExpression)>, BindingError> {
+    let Some(args) = decl.attributes.iter().find_map(|attr| match attr.node() {
+        Attribute::Custom(attr) if attr.name == BINDING_RANGE => Some(&attr.arguments),
+        _ => None,
+    }) else {
+        return Ok(None);
+    };
+    let name = decl.ident.to_string();
+    let err = |msg| BindingError::InvalidRange(name.clone(), msg);
+    let (binding, count) = match args.as_deref() {
+        Some([binding, count]) => (folder.fold(binding.node()), folder.fold(count.node())),
+        _ => (None, None),
+    };
+    let (Some(binding), Some(count @ 1..)) = (binding, count) else {
+        return Err(err(
+            "expected `@binding_range(binding, count)` with constant arguments",
+        ));
+    };
+    if decl
+        .attributes
+        .iter()
+        .any(|attr| matches!(attr.node(), Attribute::Binding(_)))
+    {
+        return Err(err("`@binding` and `@binding_range` are exclusive"));
+    }
+    let group = decl
+        .attributes
+        .iter()
+        .find_map(|attr| match attr.node() {
+            Attribute::Group(expr) => folder.fold(expr.node()),
+            _ => None,
+        })
+        .ok_or_else(|| err("missing or non-constant `@group`"))?;
+    let ty = decl
+        .ty
+        .as_ref()
+        .map(|ty| wesl.canonical_type(ty).unwrap_or_else(|| ty.clone()));
+    let elem = match ty.as_ref().and_then(|ty| {
+        (*ty.ident.name() == "array")
+            .then_some(ty.template_args.as_deref())
+            .flatten()
+    }) {
+        Some([elem, size]) => match elem.expression.node() {
+            Expression::TypeOrIdentifier(elem)
+                if folder.fold(size.expression.node()) == Some(count) =>
+            {
+                Some(elem.clone())
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    let elem = elem.ok_or_else(|| err("the type must be an `array` of `count` elements"))?;
+    Ok(Some((group, binding, count, elem)))
+}
+
+fn is_range(attr: &Attribute) -> bool {
+    matches!(attr, Attribute::Custom(attr) if attr.name == BINDING_RANGE)
+}
+
+fn builtin(name: &str) -> Ident {
+    builtin_ident(name)
+        .cloned()
+        .unwrap_or_else(|| Ident::new(name.to_string()))
+}
+
+impl Expander<'_> {
+    fn range_of(&self, expr: &Expression) -> Option<usize> {
+        match expr {
+            Expression::TypeOrIdentifier(ty) if ty.template_args.is_none() => {
+                self.ranges.iter().position(|r| r.ident == ty.ident)
+            }
+            _ => None,
+        }
+    }
+
+    /// The range and element index of a dynamic indexing expression of a range.
+    fn dynamic_index(&mut self, expr: &Expression) -> Option<usize> {
+        match expr {
+            Expression::Indexing(idx) => {
+                let r = self.range_of(&idx.base)?;
+                self.folder.fold(idx.index.node()).is_none().then_some(r)
+            }
+            _ => None,
+        }
+    }
+
+    /// Replace a call with a range element selected by a non-constant index as argument
+    /// by a call to an accessor, which calls the function with the selected element.
+    fn rewrite_call(&mut self, call: &mut FunctionCall) -> Result<(), BindingError> {
+        let dynamic = call
+            .arguments
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, arg)| Some((pos, self.dynamic_index(arg)?)))
+            .collect_vec();
+        let (pos, r) = match dynamic.as_slice() {
+            [] => return Ok(()),
+            [arg] => *arg,
+            [_, (_, r), ..] => return Err(self.dynamic_error(*r)),
+        };
+        let source = self.source;
+        let callee = match source.find_decl_by_ident(&call.ty.ident) {
+            Some(decl) => match decl.node() {
+                GlobalDeclaration::Function(f) if call.ty.template_args.is_none() => f,
+                _ => return Err(self.dynamic_error(r)),
+            },
+            None => return Err(self.dynamic_error(r)),
+        };
+
+        let key = (r, callee.ident.to_string(), pos);
+        let accessor = match self.accessors.get(&key) {
+            Some(ident) => ident.clone(),
+            None => {
+                let elements = self.ranges[r].elements.clone();
+                let name = format!("{}_{}", callee.ident, self.ranges[r].ident);
+                let name = self.names.fresh_name(&name);
+                let index = self.names.fresh_name("index");
+                let params = callee
+                    .parameters
+                    .iter()
+                    .map(|p| (self.names.fresh_name(&p.ident.name()), p.ty.to_string()))
+                    .collect_vec();
+                let signature = std::iter::once(format!("{index}: u32"))
+                    .chain(
+                        params
+                            .iter()
+                            .enumerate()
+                            .filter(|(i, _)| *i != pos)
+                            .map(|(_, (name, ty))| format!("{name}: {ty}")),
+                    )
+                    .format(", ");
+                let ret = match &callee.return_type {
+                    Some(ty) => format!(" -> {ty}"),
+                    None => String::new(),
+                };
+                let last = elements.len() - 1;
+                let cases = elements.iter().enumerate().map(|(i, elem)| {
+                    let args = params
+                        .iter()
+                        .enumerate()
+                        .map(|(j, (name, _))| {
+                            if j == pos {
+                                elem.to_string()
+                            } else {
+                                name.clone()
+                            }
+                        })
+                        .format(", ");
+                    let selector = if i == last {
+                        format!("{i}u, default")
+                    } else {
+                        format!("{i}u")
+                    };
+                    let ret = if callee.return_type.is_some() {
+                        "return "
+                    } else {
+                        ""
+                    };
+                    format!("case {selector}: {{ {ret}{}({args}); }}", callee.ident)
+                });
+                self.generated.push(format!(
+                    "fn {name}({signature}){ret} {{ switch {index} {{ {} }} }}",
+                    cases.format(" ")
+                ));
+                self.ranges[r].accessors.push(name.clone());
+                let ident = Ident::new(name);
+                self.accessors.insert(key, ident.clone());
+                ident
+            }
+        };
+
+        let Expression::Indexing(idx) = call.arguments.remove(pos).into_inner() else {
+            unreachable!("expected an indexing expression")
+        };
+        let index = Expression::FunctionCall(FunctionCall {
+            ty: TypeExpression::new(builtin("u32")),
+            arguments: vec![idx.index],
+        });
+        call.arguments.insert(0, index.into());
+        call.ty = TypeExpression::new(accessor);
+        Ok(())
+    }
+
+    fn dynamic_error(&self, r: usize) -> BindingError {
+        BindingError::DynamicRangeIndex(self.ranges[r].ident.to_string())
+    }
+
+    fn rewrite_expr(&mut self, expr: &mut ExpressionNode) -> Result<(), BindingError> {
+        if let Some(r) = self.range_of(expr.node()) {
+            return Err(BindingError::RangeUsage(self.ranges[r].ident.to_string()));
+        }
+        let indexed = match expr.node() {
+            Expression::Indexing(idx) => self.range_of(&idx.base).map(|r| (r, idx)),
+            _ => None,
+        };
+        if let Some((r, idx)) = indexed {
+            let range = &self.ranges[r];
+            let Some(i) = self.folder.fold(idx.index.node()) else {
+                return Err(self.dynamic_error(r));
+            };
+            let elem = range.elements.get(i as usize).ok_or_else(|| {
+                BindingError::RangeIndex(range.ident.to_string(), i, range.elements.len() as u32)
+            })?;
+            *expr.node_mut() = Expression::TypeOrIdentifier(TypeExpression::new(elem.clone()));
+            return Ok(());
+        }
+        if let Expression::FunctionCall(call) = expr.node_mut() {
+            self.rewrite_call(call)?;
+        }
+        for expr in Visit::<ExpressionNode>::visit_mut(expr.node_mut()) {
+            self.rewrite_expr(expr)?;
+        }
+        Ok(())
+    }
+
+    fn rewrite_calls(&mut self, stat: &mut StatementNode) -> Result<(), BindingError> {
+        if let Statement::FunctionCall(stat) = stat.node_mut() {
+            self.rewrite_call(&mut stat.call)?;
+        }
+        for stat in Visit::<StatementNode>::visit_mut(stat.node_mut()) {
+            self.rewrite_calls(stat)?;
+        }
+        Ok(())
+    }
+}
+
+/// Expand the variables with a `@binding_range(binding, count)` attribute and a type
+/// `array<T, count>` into `count` variables of type `T` with consecutive bindings.
+///
+/// Elements selected by a constant index are replaced by the element variable. A
+/// non-constant index is only allowed in an argument of a call to a user-declared
+/// function: the call is replaced by a call to a generated accessor, which selects the
+/// element with a `switch` and calls the function with it.
+///
+/// The arguments and the array size must be literals without the `eval` crate feature.
+/// If `binding_arrays` is enabled, the variables become `binding_array<T, count>` with
+/// a `@binding` instead, and nothing is expanded.
+pub(crate) fn expand_binding_ranges(
+    wesl: &mut TranslationUnit,
+    binding_arrays: bool,
+) -> Result<Vec<BindingRange>, BindingError> {
+    if !wesl
+        .declarations()
+        .any(|decl| decl.attributes.iter().any(|attr| is_range(attr.node())))
+    {
+        return Ok(Vec::new());
+    }
+    let source = wesl.clone();
+    let mut folder = Folder::new(&source);
+    let mut names = FreshNames::new(wesl);
+    let mut ranges = Vec::new();
+
+    for (index, decl) in wesl.global_declarations.iter_mut().enumerate() {
+        let GlobalDeclaration::Declaration(decl) = decl.node_mut() else {
+            continue;
+        };
+        let Some((group, binding, count, elem)) = parse_range(decl, &source, &mut folder)? else {
+            continue;
+        };
+        if binding_arrays {
+            for attr in &mut decl.attributes {
+                if is_range(attr.node()) {
+                    let binding =
+                        Expression::Literal(LiteralExpression::AbstractInt(binding.into()));
+                    *attr.node_mut() = Attribute::Binding(binding.into());
+                }
+            }
+            let count = Expression::Literal(LiteralExpression::AbstractInt(count.into()));
+            let mut ty = TypeExpression::new(builtin("binding_array"));
+            ty.template_args = Some(vec![
+                TemplateArg {
+                    expression: Expression::TypeOrIdentifier(elem).into(),
+                },
+                TemplateArg {
+                    expression: count.into(),
+                },
+            ]);
+            decl.ty = Some(ty);
+            continue;
+        }
+        let elements = (0..count)
+            .map(|i| Ident::new(names.fresh_name(&format!("{}_{i}", decl.ident))))
+            .collect();
+        decl.ty = Some(elem);
+        ranges.push(Range {
+            index,
+            ident: decl.ident.clone(),
+            group,
+            binding,
+            elements,
+            accessors: Vec::new(),
+        });
+    }
+    if ranges.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut expander = Expander {
+        source: &source,
+        folder,
+        names,
+        ranges,
+        accessors: HashMap::new(),
+        generated: Vec::new(),
+    };
+    for decl in &mut wesl.global_declarations {
+        if let GlobalDeclaration::Function(f) = decl.node_mut() {
+            for stat in &mut f.body.statements {
+                expander.rewrite_calls(stat)?;
+            }
+        }
+    }
+    for expr in Visit::<ExpressionNode>::visit_mut(wesl) {
+        expander.rewrite_expr(expr)?;
+    }
+
+    // replace each range variable by its elements.
+    for range in expander.ranges.iter().rev() {
+        let decl = wesl.global_declarations.remove(range.index);
+        let elements = range.elements.iter().enumerate().map(|(i, elem)| {
+            let mut decl = decl.clone();
+            if let GlobalDeclaration::Declaration(decl) = decl.node_mut() {
+                decl.ident = elem.clone();
+                for attr in &mut decl.attributes {
+                    if is_range(attr.node()) {
+                        let binding = (range.binding + i as u32).into();
+                        let binding = Expression::Literal(LiteralExpression::AbstractInt(binding));
+                        *attr.node_mut() = Attribute::Binding(binding.into());
+                    }
+                }
+            }
+            decl
+        });
+        wesl.global_declarations
+            .splice(range.index..range.index, elements);
+    }
+
+    if !expander.generated.is_empty() {
+        let mut generated = wgsl_parse::parse_str(&expander.generated.join("\n"))
+            .expect("generated accessors should be valid");
+        generated.retarget_idents();
+        // link the references to the declarations of the translation unit.
+        let decls = wesl
+            .global_declarations
+            .iter()
+            .filter_map(|decl| decl.ident())
+            .chain(expander.accessors.values())
+            .map(|id| (id.to_string(), id.clone()))
+            .collect::<HashMap<_, _>>();
+        Visit::<TypeExpression>::visit_rec_mut(&mut generated, &mut |ty| {
+            let id = decls.get(ty.ident.name().as_str()).cloned();
+            if let Some(id) = id {
+                ty.ident = id;
+            }
+        });
+        for decl in &mut generated.global_declarations {
+            if let GlobalDeclaration::Function(f) = decl.node_mut() {
+                let id = decls[f.ident.name().as_str()].clone();
+                f.ident = id;
+            }
+        }
+        wesl.global_declarations
+            .extend(generated.global_declarations);
+    }
+
+    let ranges = expander
+        .ranges
+        .into_iter()
+        .map(|range| BindingRange {
+            name: range.ident.to_string(),
+            group: range.group,
+            binding: range.binding,
+            elements: range.elements.iter().map(|id| id.to_string()).collect(),
+            accessors: range.accessors,
+        })
+        .collect();
+    Ok(ranges)
+}
+
+#[test]
+fn test_binding_range() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@group(1) @binding_range(2, 3) var textures: array<texture_2d<f32>, 3>;
+        @group(0) @binding(0) var smp: sampler;
+        fn sample(t: texture_2d<f32>, uv: vec2f) -> vec4f {
+            return textureSample(t, smp, uv);
+        }
+        @fragment fn main(@location(0) uv: vec2f, @location(1) @interpolate(flat) i: u32)
+            -> @location(0) vec4f {
+            return textureSample(textures[1], smp, uv) + sample(textures[i], uv);
+        }"
+        .into(),
+    );
+    let root = "package::main".parse().unwrap();
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let res = compiler
+        .compile_result(&root)
+        .unwrap_or_else(|e| panic!("{e}"));
+    let output = res.to_string();
+
+    assert_eq!(res.binding_ranges.len(), 1);
+    let range = &res.binding_ranges[0];
+    assert_eq!(range.elements, ["textures_0", "textures_1", "textures_2"]);
+    assert_eq!(range.accessors, ["sample_textures"]);
+    assert!(
+        output.contains("textureSample(textures_1, smp, uv)"),
+        "{output}"
+    );
+    assert!(output.contains("sample_textures(u32(i), uv)"), "{output}");
+    assert!(!output.contains("binding_range"), "{output}");
+    let bindings = res.bindings();
+    let elem = bindings.get_binding(1, 4).unwrap();
+    assert_eq!(elem.name, "textures_2");
+    assert_eq!(elem.range, Some(("textures".to_string(), 2)));
+    assert!(wgsl_parse::parse_str(&output).is_ok());
+
+    #[cfg(feature = "naga-ext")]
+    {
+        compiler.set_binding_arrays(true);
+        let res = compiler
+            .compile_result(&root)
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(res.binding_ranges.is_empty());
+        let binding = res.bindings().get("textures").cloned().unwrap();
+        assert_eq!((binding.binding, binding.count), (Some(2), Some(3)));
+        assert!(binding.binding_array);
+    }
+}
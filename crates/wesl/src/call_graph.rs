@@ -0,0 +1,171 @@
+use wgsl_parse::syntax::{TranslationUnit, TypeExpression};
+
+use crate::{
+    Bindings, EntryPointStage, ResourceUsage, SyntaxUtil, reflect::entry_point_stage,
+    usage::resource_usage, visit::Visit,
+};
+
+/// A function of a [`CallGraph`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionNode {
+    pub name: String,
+    /// The pipeline stage, if the function is an entry point.
+    pub stage: Option<EntryPointStage>,
+    /// The resource bindings used by the function, directly or through the functions it
+    /// calls, in declaration order. Resources passed by pointer or by handle to the
+    /// function are used by its callers, not by the function.
+    pub resources: Vec<ResourceUsage>,
+    /// The `@group`s of the resources, sorted.
+    pub groups: Vec<u32>,
+}
+
+/// The call graph of a shader, with the resource bindings each function uses. See
+/// [`crate::CompileResult::call_graph`].
+///
+/// Functions are indexed in declaration order. Use it to enforce layering rules, e.g.
+/// that material functions do not use the view bind group, and [`Self::call_path`] to
+/// report the calls that break them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CallGraph {
+    pub functions: Vec<FunctionNode>,
+    /// `(caller, callee)` pairs of function indices, sorted.
+    pub calls: Vec<(usize, usize)>,
+}
+
+impl CallGraph {
+    /// Build the call graph of a translation unit, e.g. of a compiled shader.
+    ///
+    /// Only user-declared functions are part of the graph, calls to built-in functions
+    /// and type constructors are ignored.
+    pub fn from_syntax(wesl: &TranslationUnit) -> Self {
+        let bindings = Bindings::from_syntax(wesl);
+        let functions = wesl.functions().collect::<Vec<_>>();
+        let mut calls = Vec::new();
+        for (caller, f) in functions.iter().enumerate() {
+            Visit::<TypeExpression>::visit_rec(*f, &mut |ty| {
+                if ty.path.is_none() {
+                    let callee = functions.iter().position(|f| f.ident == ty.ident);
+                    calls.extend(callee.map(|callee| (caller, callee)));
+                }
+            });
+        }
+        calls.sort();
+        calls.dedup();
+        let functions = functions
+            .iter()
+            .map(|f| {
+                let (resources, _) = resource_usage(f, wesl, &bindings);
+                let mut groups = resources
+                    .iter()
+                    .filter_map(|res| bindings.get(&res.name)?.group)
+                    .collect::<Vec<_>>();
+                groups.sort();
+                groups.dedup();
+                FunctionNode {
+                    name: f.ident.to_string(),
+                    stage: entry_point_stage(f),
+                    resources,
+                    groups,
+                }
+            })
+            .collect();
+        Self { functions, calls }
+    }
+
+    /// Find a function by name.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.functions.iter().position(|f| f.name == name)
+    }
+
+    /// The functions called by a function, in increasing order.
+    pub fn callees(&self, function: usize) -> impl Iterator<Item = usize> + '_ {
+        self.calls
+            .iter()
+            .filter(move |(from, _)| *from == function)
+            .map(|(_, to)| *to)
+    }
+
+    /// The functions calling a function, in increasing order.
+    pub fn callers(&self, function: usize) -> impl Iterator<Item = usize> + '_ {
+        self.calls
+            .iter()
+            .filter(move |(_, to)| *to == function)
+            .map(|(from, _)| *from)
+    }
+
+    /// A chain of calls from a function to a function that uses a resource binding
+    /// itself rather than through its callees, e.g. to explain a layering violation.
+    ///
+    /// Returns `None` if the function does not use the resource.
+    pub fn call_path(&self, function: usize, resource: &str) -> Option<Vec<usize>> {
+        let uses = |f: usize| {
+            self.functions[f]
+                .resources
+                .iter()
+                .any(|r| r.name == resource)
+        };
+        if !uses(function) {
+            return None;
+        }
+        let mut path = vec![function];
+        while let Some(next) = self
+            .callees(path[path.len() - 1])
+            .find(|f| uses(*f) && !path.contains(f))
+        {
+            path.push(next);
+        }
+        Some(path)
+    }
+}
+
+#[test]
+fn test_call_graph() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@group(0) @binding(0) var<uniform> view: mat4x4f;
+        @group(1) @binding(0) var<uniform> color: vec4f;
+        @group(1) @binding(1) var<storage, read_write> out: array<f32>;
+        fn project(p: vec4f) -> vec4f { return view * p; }
+        fn shade(p: vec4f) -> vec4f { return color * project(p).z; }
+        fn store(buf: ptr<storage, array<f32>, read_write>) { (*buf)[0] = 1.0; }
+        @fragment fn main(@builtin(position) p: vec4f) -> @location(0) vec4f {
+            store(&out);
+            return shade(p) + vec4f(abs(1.0));
+        }"
+        .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let res = compiler
+        .compile_result(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let graph = res.call_graph();
+
+    let names = graph.functions.iter().map(|f| f.name.as_str());
+    assert_eq!(
+        names.collect::<Vec<_>>(),
+        ["project", "shade", "store", "main"]
+    );
+    assert_eq!(graph.calls, [(1, 0), (3, 1), (3, 2)]);
+    assert_eq!(graph.callers(1).collect::<Vec<_>>(), [3]);
+
+    let main = graph.index("main").unwrap();
+    assert_eq!(graph.functions[main].stage, Some(EntryPointStage::Fragment));
+    let resources = graph.functions[main]
+        .resources
+        .iter()
+        .map(|r| r.name.as_str());
+    assert_eq!(resources.collect::<Vec<_>>(), ["view", "color", "out"]);
+    assert_eq!(graph.functions[main].groups, [0, 1]);
+    // resources passed by pointer are used by the caller.
+    assert!(graph.functions[2].resources.is_empty());
+
+    // `shade` breaks the layering rule "material functions must not use group 0".
+    assert_eq!(graph.functions[1].groups, [0, 1]);
+    assert_eq!(graph.call_path(main, "view"), Some(vec![3, 1, 0]));
+    assert_eq!(graph.call_path(main, "out"), Some(vec![3]));
+    assert_eq!(graph.call_path(2, "out"), None);
+}
@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+
+use wgsl_parse::syntax::{
+    AddressSpace, Attribute, Attributes, BuiltinValue, Expression, ExpressionNode,
+    GlobalDeclaration, Ident, InterpolateAttribute, InterpolationSampling, InterpolationType,
+    TranslationUnit,
+};
+use wgsl_types::ty::TextureType;
+
+use crate::{
+    Bindings, SyntaxUtil,
+    reflect::{EntryPointStage, EntryPoints},
+    visit::Visit,
+};
+
+/// The WebGPU feature level targeted by the output. See [`crate::CompileOptions::target`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Target {
+    /// Core WebGPU. No restrictions.
+    #[default]
+    Core,
+    /// WebGPU [compatibility mode](https://github.com/gpuweb/gpuweb/blob/main/proposals/compatibility-mode.md),
+    /// for devices supporting OpenGL ES 3.1 or Direct3D 11.
+    Compat,
+}
+
+/// Error produced when the output does not respect the restrictions of WebGPU
+/// compatibility mode.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum CompatError {
+    #[error(
+        "vertex entry point `{0}` uses the storage {1} `{2}`, which is not supported in compatibility mode"
+    )]
+    StorageInVertex(String, &'static str, String),
+    #[error("`{0}` is a cube array texture, which is not supported in compatibility mode")]
+    CubeArray(String),
+    #[error("`@interpolate({0})` is not supported in compatibility mode")]
+    Interpolate(String),
+    #[error("built-in value `{0}` is not supported in compatibility mode")]
+    Builtin(BuiltinValue),
+    #[error("`textureLoad` of the depth texture `{0}` is not supported in compatibility mode")]
+    DepthTextureLoad(String),
+}
+
+fn fmt_interpolate(attr: &InterpolateAttribute) -> String {
+    match attr.sampling {
+        Some(sampling) => format!("{}, {sampling}", attr.ty),
+        None => attr.ty.to_string(),
+    }
+}
+
+/// Lower or reject the interpolation attributes and built-in values.
+///
+/// `@interpolate(flat)` samples the first vertex by default, compatibility mode only
+/// supports `@interpolate(flat, either)`: it is lowered to it.
+fn check_io(attrs: &mut Attributes) -> Result<(), CompatError> {
+    for attr in attrs {
+        match attr.node_mut() {
+            Attribute::Interpolate(interp) => match (interp.ty, interp.sampling) {
+                (InterpolationType::Flat, None) => {
+                    interp.sampling = Some(InterpolationSampling::Either)
+                }
+                (InterpolationType::Linear, _)
+                | (_, Some(InterpolationSampling::Sample | InterpolationSampling::First)) => {
+                    return Err(CompatError::Interpolate(fmt_interpolate(interp)));
+                }
+                _ => {}
+            },
+            Attribute::Builtin(
+                builtin @ (BuiltinValue::SampleIndex | BuiltinValue::SampleMask),
+            ) => return Err(CompatError::Builtin(*builtin)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The global declarations used by an entry point, transitively.
+pub(crate) fn used_decls(entry_point: &Ident, wesl: &TranslationUnit) -> HashSet<Ident> {
+    let mut used = HashSet::new();
+    let mut stack = vec![entry_point.clone()];
+    while let Some(id) = stack.pop() {
+        if let Some(decl) = wesl.find_decl_by_ident(&id) {
+            for id in wesl.referenced_idents(decl) {
+                if used.insert(id.clone()) {
+                    stack.push(id);
+                }
+            }
+        }
+    }
+    used
+}
+
+/// Check that the output respects the restrictions of WebGPU compatibility mode, and
+/// lower what can be lowered.
+///
+/// The checked restrictions are:
+/// * no storage buffers and storage textures in vertex shaders,
+/// * no cube array textures,
+/// * no `textureLoad` of depth textures,
+/// * no `linear` interpolation and no `sample` or `first` interpolation sampling,
+/// * no `sample_index` and `sample_mask` built-in values.
+///
+/// Resources are only checked if they are used, so stripping should be performed first.
+pub(crate) fn check_compat(wesl: &mut TranslationUnit) -> Result<(), CompatError> {
+    for attrs in Visit::<Attributes>::visit_mut(wesl) {
+        check_io(attrs)?;
+    }
+
+    let bindings = Bindings::from_syntax(wesl);
+    if let Some(b) = bindings.iter().find(|b| {
+        matches!(
+            b.texture,
+            Some(TextureType::SampledCubeArray(_) | TextureType::DepthCubeArray)
+        )
+    }) {
+        return Err(CompatError::CubeArray(b.name.clone()));
+    }
+    let is_depth = |name: &str| {
+        bindings
+            .get(name)
+            .and_then(|b| b.texture.as_ref())
+            .is_some_and(|tex| tex.is_depth())
+    };
+    fn check_loads(
+        expr: &ExpressionNode,
+        is_depth: &impl Fn(&str) -> bool,
+    ) -> Result<(), CompatError> {
+        if let Expression::FunctionCall(call) = expr.node() {
+            if call.ty.path.is_none() && *call.ty.ident.name() == "textureLoad" {
+                if let Some(Expression::TypeOrIdentifier(ty)) =
+                    call.arguments.first().map(|arg| arg.node())
+                {
+                    if is_depth(&ty.ident.name()) {
+                        return Err(CompatError::DepthTextureLoad(ty.ident.to_string()));
+                    }
+                }
+            }
+            for expr in &call.arguments {
+                check_loads(expr, is_depth)?;
+            }
+        } else {
+            for expr in Visit::<ExpressionNode>::visit(expr.node()) {
+                check_loads(expr, is_depth)?;
+            }
+        }
+        Ok(())
+    }
+    for expr in Visit::<ExpressionNode>::visit(wesl) {
+        check_loads(expr, &is_depth)?;
+    }
+
+    let entry_points = EntryPoints::from_syntax(wesl);
+    for ep in entry_points
+        .iter()
+        .filter(|ep| ep.stage == EntryPointStage::Vertex)
+    {
+        let Some(func) = wesl.functions().find(|f| *f.ident.name() == ep.name) else {
+            continue;
+        };
+        for id in used_decls(&func.ident, wesl) {
+            let Some(GlobalDeclaration::Declaration(_)) =
+                wesl.find_decl_by_ident(&id).map(|decl| decl.node())
+            else {
+                continue;
+            };
+            let Some(b) = bindings.get(&id.name()) else {
+                continue;
+            };
+            let kind = if b.address_space == AddressSpace::Storage {
+                "buffer"
+            } else if b.texture.as_ref().is_some_and(|tex| tex.is_storage()) {
+                "texture"
+            } else {
+                continue;
+            };
+            return Err(CompatError::StorageInVertex(
+                ep.name.clone(),
+                kind,
+                b.name.clone(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_compat() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let compile = |source: &str| {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module("package::main".parse().unwrap(), source.into());
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        compiler
+            .set_options(CompileOptions::default())
+            .set_target(Target::Compat);
+        compiler
+            .compile(&"package::main".parse().unwrap())
+            .map(|res| res.to_string())
+            .map_err(|e| crate::Diagnostic::from(e).error.to_string())
+    };
+
+    let wgsl = compile(
+        "struct V { @builtin(position) pos: vec4f, @location(0) @interpolate(flat) id: u32 }
+        @group(0) @binding(0) var<storage> data: array<vec4f>;
+        @group(0) @binding(1) var<uniform> offset: vec4f;
+        @vertex fn vs() -> V { return V(offset, 0u); }
+        @fragment fn fs(v: V) -> @location(0) vec4f { return data[v.id]; }",
+    )
+    .unwrap();
+    assert!(wgsl.contains("@interpolate(flat, either)"));
+
+    let err = |source: &str| compile(source).unwrap_err();
+    assert!(
+        err("@group(0) @binding(0) var<storage> data: array<vec4f>;
+        fn f() -> vec4f { return data[0]; }
+        @vertex fn vs() -> @builtin(position) vec4f { return f(); }")
+        .contains("uses the storage buffer `data`")
+    );
+    assert!(
+        err("@group(0) @binding(0) var t: texture_cube_array<f32>;
+        @fragment fn fs() -> @location(0) vec4f { _ = t; return vec4f(); }")
+        .contains("cube array")
+    );
+    assert!(
+        err("@group(0) @binding(0) var t: texture_depth_2d;
+        @fragment fn fs() -> @location(0) vec4f { return vec4f(textureLoad(t, vec2u(), 0)); }")
+        .contains("`textureLoad` of the depth texture `t`")
+    );
+    assert!(err("@fragment fn fs(@builtin(sample_index) i: u32) -> @location(0) vec4f { return vec4f(); }")
+        .contains("`sample_index`"));
+    assert!(err("@fragment fn fs(@location(0) @interpolate(linear) x: f32) -> @location(0) vec4f { return vec4f(x); }")
+        .contains("`@interpolate(linear)`"));
+}
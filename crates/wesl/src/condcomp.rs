@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, num::NonZeroUsize, path::PathBuf};
 
-use crate::Diagnostic;
+use crate::{CompileOptions, Diagnostic, ResolveError, Resolver, visit::Visit};
 use thiserror::Error;
 use wgsl_parse::{Decorated, span::Spanned, syntax::*};
 
@@ -17,6 +17,14 @@ pub enum CondCompError {
     NoPrecedingIf,
     #[error("cannot have multiple @if/@elif/@else attributes on the same node")]
     DuplicateIf,
+    #[error("invalid feature declaration: expected `@feature(name)` or `@feature(name, default)`")]
+    InvalidFeatureDeclaration,
+    #[error("feature flag `{0}` is not declared by `{1}`")]
+    UndeclaredFeatureFlag(String, ModulePath),
+    #[error("{0}")]
+    CompileError(String),
+    #[error("{0}")]
+    CompileWarn(String),
 }
 
 type E = crate::Error;
@@ -29,7 +37,9 @@ type E = crate::Error;
 ///   [`CondCompError::UnexpectedFeatureFlag`].
 ///
 /// Default is `Disable`.
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Feature {
     Enable,
     #[default]
@@ -42,12 +52,69 @@ pub enum Feature {
 ///
 /// Feature flags set to `true` are enabled, and `false` are disabled. Feature flags not
 /// present in `flags` are treated according to `default`.
+///
+/// Modules can declare their feature flags and defaults with `@feature(name)` or
+/// `@feature(name, default)` attributes on module-scope items, e.g.
+/// `@feature(shadows, true)`. Declared flags apply to the module and its submodules, and
+/// take their declared default instead of `default` when not present in `flags`, unless
+/// `default` is [`Feature::Keep`]. Flags set for a package in
+/// [`crate::CompileOptions::package_features`] must be declared by the package, if it
+/// declares any.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Features {
     pub default: Feature,
     pub flags: HashMap<String, Feature>,
 }
 
+impl Features {
+    /// The combinations of enabling and disabling the feature flags, e.g. to check all the
+    /// variants of a shader. See [`crate::Wesl::declared_features`].
+    ///
+    /// With a `limit`, at most `limit` combinations are returned, evenly spread over all
+    /// the combinations. The first and last combinations, with all flags disabled and all
+    /// flags enabled, are always included. Without a limit, all the `2^n` combinations
+    /// are returned: check [`Self::variant_count`] first when the flags are not known in
+    /// advance.
+    pub fn variants(flags: &[String], limit: Option<NonZeroUsize>) -> Vec<Self> {
+        // combinations are numbered with one bit per flag.
+        let total = Self::variant_count(flags.len());
+        let count = limit.map_or(total, |limit| total.min(limit.get() as u128));
+        let step = (total - 1) / (count.max(2) - 1);
+        (0..count)
+            .map(|i| {
+                let n = if i + 1 == count && count > 1 {
+                    total - 1
+                } else {
+                    i * step
+                };
+                let flags = flags
+                    .iter()
+                    .enumerate()
+                    .map(|(bit, flag)| {
+                        let enabled = bit < 128 && (n >> bit) & 1 == 1;
+                        (flag.clone(), enabled.into())
+                    })
+                    .collect();
+                Self {
+                    default: Feature::default(),
+                    flags,
+                }
+            })
+            .collect()
+    }
+
+    /// The number of combinations of enabling and disabling `count` feature flags, i.e.
+    /// `2^count`, saturating at `u128::MAX`. See [`Self::variants`].
+    pub fn variant_count(count: usize) -> u128 {
+        u32::try_from(count)
+            .ok()
+            .and_then(|count| 1u128.checked_shl(count))
+            .unwrap_or(u128::MAX)
+    }
+}
+
 impl From<bool> for Feature {
     fn from(value: bool) -> Self {
         if value {
@@ -357,7 +424,156 @@ fn stmt_eval_if_attrs(statements: &mut Vec<StatementNode>, features: &Features)
     rec(statements, features).map(|_| ())
 }
 
+/// Emit the user diagnostics of the `@compile_error` and `@compile_warn` attributes,
+/// and remove these attributes. Run it after [`run`], so that only the nodes that
+/// survive conditional translation emit a diagnostic.
+///
+/// Returns the warnings, or the first error.
+pub(crate) fn user_diagnostics(
+    wesl: &mut TranslationUnit,
+) -> Result<Vec<Diagnostic<E>>, Diagnostic<E>> {
+    fn emit(
+        attrs: &[AttributeNode],
+        declaration: Option<&String>,
+        warnings: &mut Vec<Diagnostic<E>>,
+    ) -> Result<(), Diagnostic<E>> {
+        for attr in attrs {
+            let err = match attr.node() {
+                Attribute::CompileError(msg) => CondCompError::CompileError(msg.clone()),
+                Attribute::CompileWarn(msg) => CondCompError::CompileWarn(msg.clone()),
+                _ => continue,
+            };
+            let is_warning = matches!(err, CondCompError::CompileWarn(_));
+            let mut d = Diagnostic::from(E::from(err)).with_span(attr.span());
+            d.detail.declaration = declaration.cloned();
+            if is_warning {
+                d.detail.warning = true;
+                warnings.push(d);
+            } else {
+                return Err(d);
+            }
+        }
+        Ok(())
+    }
+
+    let mut warnings = Vec::new();
+    for import in &wesl.imports {
+        emit(import.attributes(), None, &mut warnings)?;
+    }
+    for directive in &wesl.global_directives {
+        emit(directive.attributes(), None, &mut warnings)?;
+    }
+    for decl in &mut wesl.global_declarations {
+        let name = decl.ident().map(|id| id.to_string());
+        for attrs in Visit::<Attributes>::visit_mut(decl.node_mut()) {
+            emit(attrs, name.as_ref(), &mut warnings)?;
+        }
+    }
+    strip_user_diagnostics(wesl);
+    Ok(warnings)
+}
+
+/// Remove the `@compile_error` and `@compile_warn` attributes without emitting their
+/// diagnostics, e.g. when conditional translation is disabled. They are not valid WGSL.
+pub(crate) fn strip_user_diagnostics(wesl: &mut TranslationUnit) {
+    for attrs in Visit::<Attributes>::visit_mut(wesl) {
+        attrs.retain(|attr| {
+            !matches!(
+                attr.node(),
+                Attribute::CompileError(_) | Attribute::CompileWarn(_)
+            )
+        });
+    }
+}
+
+/// A feature flag declared in a module: `@feature(name)` or `@feature(name, default)`.
+type FeatureDecl = (String, bool);
+
+fn feature_declaration(attr: &Attribute) -> Option<Result<FeatureDecl, E>> {
+    let Attribute::Custom(attr) = attr else {
+        return None;
+    };
+    if attr.name != "feature" {
+        return None;
+    }
+    let name = |expr: &ExpressionNode| match expr.node() {
+        Expression::TypeOrIdentifier(ty) if ty.template_args.is_none() => {
+            Some(ty.ident.to_string())
+        }
+        _ => None,
+    };
+    let decl = match attr.arguments.as_deref() {
+        Some([flag]) => name(flag).map(|name| (name, false)),
+        Some([flag, default]) => match (name(flag), default.node()) {
+            (Some(name), Expression::Literal(LiteralExpression::Bool(b))) => Some((name, *b)),
+            _ => None,
+        },
+        _ => None,
+    };
+    Some(decl.ok_or_else(|| CondCompError::InvalidFeatureDeclaration.into()))
+}
+
+fn extract_declarations(node: &mut impl Decorated, decls: &mut Vec<FeatureDecl>) -> Result<(), E> {
+    let mut err = None;
+    node.retain_attributes_mut(|attr| match feature_declaration(attr) {
+        Some(Ok(decl)) => {
+            decls.push(decl);
+            false
+        }
+        Some(Err(e)) => {
+            err.get_or_insert(e);
+            true
+        }
+        None => true,
+    });
+    err.map_or(Ok(()), Err)
+}
+
+/// Extract the feature flags declared in a module with `@feature` attributes on
+/// module-scope items, and remove the attributes.
+fn feature_declarations(wesl: &mut TranslationUnit) -> Result<Vec<FeatureDecl>, E> {
+    let mut decls = Vec::new();
+    for import in &mut wesl.imports {
+        extract_declarations(import, &mut decls)?;
+    }
+    for directive in &mut wesl.global_directives {
+        extract_declarations(directive, &mut decls)?;
+    }
+    for decl in &mut wesl.global_declarations {
+        extract_declarations(decl, &mut decls)?;
+    }
+    Ok(decls)
+}
+
+/// The feature flags with the declared defaults for the flags not set explicitly. With
+/// [`Feature::Keep`] as default, unset flags are kept as-is.
+fn with_declared_defaults<'a>(features: &'a Features, decls: &[FeatureDecl]) -> Cow<'a, Features> {
+    let unset = decls
+        .iter()
+        .filter(|(name, _)| !features.flags.contains_key(name))
+        .collect::<Vec<_>>();
+    if unset.is_empty() || features.default == Feature::Keep {
+        return Cow::Borrowed(features);
+    }
+    let mut features = features.clone();
+    for (name, default) in unset {
+        features.flags.insert(name.clone(), (*default).into());
+    }
+    Cow::Owned(features)
+}
+
+/// Run conditional translation on a module.
+///
+/// Modules can declare their feature flags with `@feature(name)` or
+/// `@feature(name, default)` attributes on module-scope items, e.g.
+/// `@feature(shadows, true)`. Declared flags not set in `features` take the declared
+/// default, `false` if unspecified.
 pub fn run(wesl: &mut TranslationUnit, features: &Features) -> Result<(), E> {
+    let decls = feature_declarations(wesl)?;
+    eval(wesl, &with_declared_defaults(features, &decls))
+}
+
+fn eval(wesl: &mut TranslationUnit, features: &Features) -> Result<(), E> {
     wesl.remove_voids();
     eval_if_attrs(&mut wesl.imports, features)?;
     eval_if_attrs(&mut wesl.global_directives, features)?;
@@ -377,3 +593,302 @@ pub fn run(wesl: &mut TranslationUnit, features: &Features) -> Result<(), E> {
 
     Ok(())
 }
+
+/// A resolver that runs conditional translation on each module, with the feature
+/// flags of the module. See [`CompileOptions::features_of`].
+///
+/// Feature flags declared in a module apply to the module and its submodules. Flags
+/// set with [`CompileOptions::package_features`] are checked against the flags
+/// declared at the prefix, if any.
+pub(crate) struct CondCompResolver<'a, R: Resolver> {
+    resolver: R,
+    options: &'a CompileOptions,
+    /// The feature flags declared in each module.
+    declared: RefCell<HashMap<ModulePath, Vec<FeatureDecl>>>,
+}
+
+impl<'a, R: Resolver> CondCompResolver<'a, R> {
+    pub(crate) fn new(resolver: R, options: &'a CompileOptions) -> Self {
+        Self {
+            resolver,
+            options,
+            declared: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The feature flags declared in a module. Modules that fail to resolve declare
+    /// no flags.
+    fn module_declarations(&self, path: &ModulePath) -> Vec<FeatureDecl> {
+        if let Some(decls) = self.declared.borrow().get(path) {
+            return decls.clone();
+        }
+        let decls = self
+            .resolver
+            .resolve_source(path)
+            .ok()
+            .and_then(|source| wgsl_parse::parse_str(&source).ok())
+            .and_then(|mut wesl| feature_declarations(&mut wesl).ok())
+            .unwrap_or_default();
+        self.declared
+            .borrow_mut()
+            .insert(path.clone(), decls.clone());
+        decls
+    }
+
+    /// The feature flags declared in a module and its parent modules, up to the package
+    /// root.
+    pub(crate) fn declarations(&self, path: &ModulePath) -> Vec<FeatureDecl> {
+        // the root of the local package is not a module.
+        let start = if path.origin.is_package() {
+            0
+        } else {
+            1
+        };
+        (start..=path.components.len())
+            .flat_map(|i| {
+                let parent = ModulePath::new(path.origin.clone(), path.components[..i].to_vec());
+                self.module_declarations(&parent)
+            })
+            .collect()
+    }
+
+    fn check_package_features(&self, path: &ModulePath) -> Result<(), E> {
+        let Some((prefix, features)) = self
+            .options
+            .package_features
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.components.len())
+        else {
+            return Ok(());
+        };
+        let decls = self.declarations(prefix);
+        if decls.is_empty() {
+            return Ok(());
+        }
+        match features
+            .flags
+            .keys()
+            .find(|flag| !decls.iter().any(|(name, _)| name == *flag))
+        {
+            Some(flag) => {
+                Err(CondCompError::UndeclaredFeatureFlag(flag.clone(), prefix.clone()).into())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn run(&self, path: &ModulePath, wesl: &mut TranslationUnit) -> Result<(), E> {
+        let own = feature_declarations(wesl)?;
+        self.declared.borrow_mut().insert(path.clone(), own);
+        self.check_package_features(path)?;
+        let decls = self.declarations(path);
+        let features = self.options.features_of(path);
+        eval(wesl, &with_declared_defaults(features, &decls))
+    }
+}
+
+impl<R: Resolver> Resolver for CondCompResolver<'_, R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let mut wesl = self.resolver.resolve_module(path)?;
+        self.run(path, &mut wesl).map_err(|e| {
+            Diagnostic::from(e)
+                .with_module_path(path.clone(), self.display_name(path))
+                .with_source(self.resolve_source(path).unwrap().to_string())
+        })?;
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+#[test]
+fn test_feature_variants() {
+    use itertools::Itertools;
+    let flags = ["a", "b", "c"].map(String::from);
+    let variants = Features::variants(&flags, None);
+    assert_eq!(variants.len(), 8);
+    assert!(
+        variants
+            .iter()
+            .map(|v| flags
+                .iter()
+                .map(|f| v.flags[f] == Feature::Enable)
+                .collect_vec())
+            .all_unique()
+    );
+
+    let limit = NonZeroUsize::new;
+    let variants = Features::variants(&flags, limit(3));
+    assert_eq!(variants.len(), 3);
+    assert!(variants[0].flags.values().all(|f| *f == Feature::Disable));
+    assert!(variants[2].flags.values().all(|f| *f == Feature::Enable));
+    assert_eq!(Features::variants(&flags, limit(1)).len(), 1);
+    assert_eq!(Features::variant_count(3), 8);
+    assert_eq!(Features::variant_count(200), u128::MAX);
+    assert_eq!(Features::variants(&[], None).len(), 1);
+}
+
+#[test]
+fn test_user_diagnostics() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@if(!fast) @compile_warn(\"`fast` is disabled\") const SLOW = true;
+        @if(legacy) @compile_error(\"`legacy` is not supported\") const LEGACY = true;
+        @fragment fn main() {}"
+            .into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        keep_root: true,
+        ..Default::default()
+    });
+    let res = compiler
+        .compile(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(res.warnings.len(), 1);
+    assert!(res.warnings[0].detail.warning);
+    assert_eq!(res.warnings[0].error.to_string(), "`fast` is disabled");
+    assert!(!res.to_string().contains("@compile_warn"));
+
+    compiler
+        .set_feature("fast", true)
+        .set_feature("legacy", true);
+    let Err(err) = compiler.compile(&"package::main".parse().unwrap()) else {
+        panic!("expected a compile error");
+    };
+    assert!(matches!(
+        *Diagnostic::from(err).error,
+        E::CondCompError(CondCompError::CompileError(_))
+    ));
+
+    // statements and struct members
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "struct S { @if(!fast) @compile_warn(\"slow member\") a: f32 }
+        @fragment fn main() {
+            @if(!fast) @compile_warn(\"slow statement\") let s = S(1.0);
+            @if(legacy) { @compile_error(\"legacy statement\") let x = 1; }
+        }"
+        .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        keep_root: true,
+        ..Default::default()
+    });
+    let res = compiler
+        .compile(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let warnings = res
+        .warnings
+        .iter()
+        .map(|w| w.error.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(warnings, ["slow member", "slow statement"]);
+    assert_eq!(res.warnings[1].detail.declaration.as_deref(), Some("main"));
+    assert!(!res.to_string().contains("@compile_warn"));
+
+    compiler.set_feature("legacy", true);
+    let Err(err) = compiler.compile(&"package::main".parse().unwrap()) else {
+        panic!("expected a compile error");
+    };
+    assert!(err.to_string().contains("legacy statement"));
+
+    // the attributes are stripped when conditional translation is disabled
+    compiler.set_options(CompileOptions {
+        keep_root: true,
+        condcomp: false,
+        ..Default::default()
+    });
+    let res = compiler
+        .compile(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert!(res.warnings.is_empty());
+    let res = res.to_string();
+    assert!(!res.contains("@compile_warn") && !res.contains("@compile_error"));
+}
+
+#[test]
+fn test_package_features() {
+    use crate::{VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::lib::f;
+        @if(shadows) const main_shadows = 1;
+        @compute @workgroup_size(1) fn main() { _ = f(); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::lib".parse().unwrap(),
+        "@if(shadows) fn f() -> u32 { return 1u; }
+        @else fn f() -> u32 { return 0u; }"
+            .into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        keep_root: true,
+        ..Default::default()
+    });
+    compiler.set_package_feature("package::lib".parse().unwrap(), "shadows", true);
+    let res = compiler
+        .compile(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"))
+        .to_string();
+    assert!(res.contains("return 1u;"));
+    assert!(!res.contains("main_shadows"));
+}
+
+#[test]
+fn test_feature_declarations() {
+    use crate::{VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import pkg_light::f;
+        @compute @workgroup_size(1) fn main() { _ = f(); }"
+            .into(),
+    );
+    resolver.add_module(
+        "pkg_light".parse().unwrap(),
+        "@feature(shadows, true) @feature(pcf)
+        @if(shadows && !pcf) fn f() -> u32 { return 1u; }
+        @else fn f() -> u32 { return 0u; }"
+            .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.use_imports(true).use_condcomp(true);
+    let root = "package::main".parse().unwrap();
+    let res = compiler
+        .compile(&root)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .to_string();
+    assert!(res.contains("return 1u;"), "{res}");
+    assert!(!res.contains("@feature"), "{res}");
+
+    compiler.set_feature("shadows", false);
+    let res = compiler.compile(&root).unwrap().to_string();
+    assert!(res.contains("return 0u;"), "{res}");
+
+    compiler.set_package_feature("pkg_light".parse().unwrap(), "shadow", true);
+    let Err(err) = compiler.compile(&root) else {
+        panic!("expected an undeclared feature flag error");
+    };
+    assert_eq!(
+        Diagnostic::from(err).error.to_string(),
+        "feature flag `shadow` is not declared by `pkg_light`"
+    );
+}
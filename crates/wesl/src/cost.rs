@@ -0,0 +1,637 @@
+use std::{collections::HashMap, fmt::Display};
+
+use wgsl_parse::syntax::{
+    AccessMode, AddressSpace, AssignmentOperator, Attribute, BinaryOperator, BuiltinValue,
+    CompoundStatement, Declaration, DeclarationKind, Expression, ForStatement, Function,
+    FunctionCall, GlobalDeclaration, Ident, Statement, TranslationUnit,
+};
+
+use crate::{SyntaxUtil, ValidateError};
+
+/// Static estimate of the complexity of an entry point. See
+/// [`crate::CompileResult::costs`].
+///
+/// The metrics are computed on the syntax tree, with the called functions inlined. They
+/// are only meant to be compared between versions of a shader, e.g. to catch
+/// regressions with a [`CostBudget`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cost {
+    /// The number of operators, function calls, component accesses and statements.
+    /// Loop bodies are counted once.
+    pub instructions: u32,
+    /// The number of texture sampling, gathering and loading calls.
+    pub texture_samples: u32,
+    /// The number of loops whose trip count is not known when the pipeline is created:
+    /// all `loop` and `while` loops, and the `for` loops that are not a counter
+    /// incremented from a constant to a constant.
+    pub dynamic_loops: u32,
+    /// The number of branches whose condition may differ between invocations, i.e. it
+    /// depends on per-invocation inputs or on mutable `storage` and `workgroup` memory.
+    pub divergent_branches: u32,
+    /// A proxy for register pressure: the maximum number of function parameters and
+    /// local declarations in scope at once, including those of the callers.
+    pub register_pressure: u32,
+}
+
+/// The [`Cost`] of an entry point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryPointCost {
+    pub name: String,
+    pub cost: Cost,
+}
+
+impl Display for EntryPointCost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cost = &self.cost;
+        write!(
+            f,
+            "{}: {} instructions, {} texture samples, {} dynamic loops, {} divergent branches, register pressure {}",
+            self.name,
+            cost.instructions,
+            cost.texture_samples,
+            cost.dynamic_loops,
+            cost.divergent_branches,
+            cost.register_pressure
+        )
+    }
+}
+
+/// Upper bounds of the [`Cost`] of entry points. See [`crate::CompileOptions::cost_budget`].
+///
+/// Exceeding a bound emits a [`ValidateError::CostBudget`] warning.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CostBudget {
+    pub instructions: Option<u32>,
+    pub texture_samples: Option<u32>,
+    pub dynamic_loops: Option<u32>,
+    pub divergent_branches: Option<u32>,
+    pub register_pressure: Option<u32>,
+}
+
+impl CostBudget {
+    /// The bounds exceeded by an entry point.
+    pub fn check(&self, ep: &EntryPointCost) -> Vec<ValidateError> {
+        let cost = &ep.cost;
+        [
+            ("instructions", cost.instructions, self.instructions),
+            (
+                "texture samples",
+                cost.texture_samples,
+                self.texture_samples,
+            ),
+            ("dynamic loops", cost.dynamic_loops, self.dynamic_loops),
+            (
+                "divergent branches",
+                cost.divergent_branches,
+                self.divergent_branches,
+            ),
+            (
+                "register pressure",
+                cost.register_pressure,
+                self.register_pressure,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(metric, value, budget)| {
+            let budget = budget.filter(|budget| value > *budget)?;
+            Some(ValidateError::CostBudget(
+                ep.name.clone(),
+                metric,
+                value,
+                budget,
+            ))
+        })
+        .collect()
+    }
+}
+
+/// The state of the function being estimated.
+#[derive(Default)]
+struct Frame {
+    cost: Cost,
+    /// The number of parameters and local declarations in scope.
+    locals: u32,
+    /// The local `const` declarations.
+    consts: Vec<Ident>,
+    /// The parameters and local declarations whose value may differ between invocations.
+    varying: Vec<Ident>,
+    /// The depth of control flow that may differ between invocations.
+    divergent: u32,
+    /// The name of the function.
+    function: String,
+    /// A built-in function called by the function or its callees that must be called in
+    /// uniform control flow.
+    barrier: Option<&'static str>,
+}
+
+impl Frame {
+    fn declare(&mut self) {
+        self.locals += 1;
+        self.cost.register_pressure = self.cost.register_pressure.max(self.locals);
+    }
+}
+
+/// Built-in functions that must be called in uniform control flow.
+///
+/// Reference: <https://www.w3.org/TR/WGSL/#uniformity-function-calls>
+pub(crate) const UNIFORM_BUILTINS: &[&str] = &[
+    "workgroupBarrier",
+    "storageBarrier",
+    "textureBarrier",
+    "workgroupUniformLoad",
+];
+
+/// The cost of a function, and the uniform built-in function it calls, if any.
+type FunctionCost = (Cost, Option<&'static str>);
+
+struct Estimator<'a> {
+    wesl: &'a TranslationUnit,
+    /// The cost of functions, and the uniform built-in they call, given which parameters
+    /// are uniform.
+    memo: HashMap<(Ident, Vec<bool>), FunctionCost>,
+    /// The uniform built-in functions called in non-uniform control flow, with the
+    /// function containing the call.
+    divergent_barriers: Vec<(&'static str, String)>,
+}
+
+impl<'a> Estimator<'a> {
+    fn global(&self, ident: &Ident) -> Option<&'a GlobalDeclaration> {
+        self.wesl.find_decl_by_ident(ident).map(|decl| decl.node())
+    }
+
+    fn new(wesl: &'a TranslationUnit) -> Self {
+        Self {
+            wesl,
+            memo: HashMap::new(),
+            divergent_barriers: Vec::new(),
+        }
+    }
+
+    fn entry_point(&mut self, func: &Function) -> Cost {
+        // only the workgroup id and count are the same for all invocations.
+        let uniform = func
+            .parameters
+            .iter()
+            .map(|param| {
+                param.attributes.iter().any(|attr| {
+                    matches!(
+                        attr.node(),
+                        Attribute::Builtin(BuiltinValue::WorkgroupId | BuiltinValue::NumWorkgroups)
+                    )
+                })
+            })
+            .collect();
+        self.function(func, uniform).0
+    }
+
+    fn function(&mut self, func: &Function, uniform: Vec<bool>) -> FunctionCost {
+        let key = (func.ident.clone(), uniform);
+        if let Some(res) = self.memo.get(&key) {
+            return *res;
+        }
+        // recursion is forbidden, this guards against infinite loops.
+        self.memo.insert(key.clone(), Default::default());
+        let mut frame = Frame {
+            function: func.ident.to_string(),
+            ..Default::default()
+        };
+        for (param, uniform) in func.parameters.iter().zip(&key.1) {
+            frame.declare();
+            if !uniform {
+                frame.varying.push(param.ident.clone());
+            }
+        }
+        self.block(&mut frame, &func.body);
+        let res = (frame.cost, frame.barrier);
+        self.memo.insert(key, res);
+        res
+    }
+
+    /// Record a call to a uniform built-in function, or to a function calling one.
+    fn barrier(&mut self, frame: &mut Frame, barrier: &'static str) {
+        frame.barrier.get_or_insert(barrier);
+        if frame.divergent > 0 {
+            let call = (barrier, frame.function.clone());
+            if !self.divergent_barriers.contains(&call) {
+                self.divergent_barriers.push(call);
+            }
+        }
+    }
+
+    fn is_uniform_ident(&self, frame: &Frame, ident: &Ident) -> bool {
+        if frame.varying.contains(ident) {
+            return false;
+        }
+        match self.global(ident) {
+            Some(GlobalDeclaration::Declaration(decl)) => !matches!(
+                decl.kind,
+                DeclarationKind::Var(Some(
+                    (AddressSpace::Storage, Some(AccessMode::ReadWrite))
+                        | (AddressSpace::Workgroup | AddressSpace::Private, _)
+                ))
+            ),
+            _ => true,
+        }
+    }
+
+    /// Whether the expression is known when the pipeline is created.
+    fn is_const(&self, frame: &Frame, expr: &Expression) -> bool {
+        match expr {
+            Expression::Literal(_) => true,
+            Expression::Parenthesized(e) => self.is_const(frame, &e.expression),
+            Expression::NamedComponent(e) => self.is_const(frame, &e.base),
+            Expression::Indexing(e) => {
+                self.is_const(frame, &e.base) && self.is_const(frame, &e.index)
+            }
+            Expression::Unary(e) => self.is_const(frame, &e.operand),
+            Expression::Binary(e) => {
+                self.is_const(frame, &e.left) && self.is_const(frame, &e.right)
+            }
+            Expression::FunctionCall(call) => {
+                self.global(&call.ty.ident).is_none()
+                    && call.arguments.iter().all(|arg| self.is_const(frame, arg))
+            }
+            Expression::TypeOrIdentifier(ty) => {
+                frame.consts.contains(&ty.ident)
+                    || matches!(
+                        self.global(&ty.ident),
+                        Some(GlobalDeclaration::Declaration(Declaration {
+                            kind: DeclarationKind::Const | DeclarationKind::Override,
+                            ..
+                        }))
+                    )
+            }
+        }
+    }
+
+    /// Estimate the cost of an expression. Returns whether its value is uniform.
+    fn expr(&mut self, frame: &mut Frame, expr: &Expression) -> bool {
+        match expr {
+            Expression::Literal(_) => true,
+            Expression::Parenthesized(e) => self.expr(frame, &e.expression),
+            Expression::NamedComponent(e) => {
+                frame.cost.instructions += 1;
+                self.expr(frame, &e.base)
+            }
+            Expression::Indexing(e) => {
+                frame.cost.instructions += 1;
+                self.expr(frame, &e.base) & self.expr(frame, &e.index)
+            }
+            Expression::Unary(e) => {
+                frame.cost.instructions += 1;
+                self.expr(frame, &e.operand)
+            }
+            Expression::Binary(e) => {
+                frame.cost.instructions += 1;
+                self.expr(frame, &e.left) & self.expr(frame, &e.right)
+            }
+            Expression::FunctionCall(call) => self.call(frame, call),
+            Expression::TypeOrIdentifier(ty) => self.is_uniform_ident(frame, &ty.ident),
+        }
+    }
+
+    fn call(&mut self, frame: &mut Frame, call: &FunctionCall) -> bool {
+        frame.cost.instructions += 1;
+        let uniform = call
+            .arguments
+            .iter()
+            .map(|arg| self.expr(frame, arg))
+            .collect::<Vec<_>>();
+        let all_uniform = uniform.iter().all(|u| *u);
+        if let Some(GlobalDeclaration::Function(func)) = self.global(&call.ty.ident) {
+            let (callee, barrier) = self.function(func, uniform);
+            if let Some(barrier) = barrier {
+                self.barrier(frame, barrier);
+            }
+            let cost = &mut frame.cost;
+            cost.instructions += callee.instructions;
+            cost.texture_samples += callee.texture_samples;
+            cost.dynamic_loops += callee.dynamic_loops;
+            cost.divergent_branches += callee.divergent_branches;
+            cost.register_pressure = cost
+                .register_pressure
+                .max(frame.locals + callee.register_pressure);
+            all_uniform
+        } else {
+            let name = call.ty.ident.name();
+            if let Some(barrier) = UNIFORM_BUILTINS.iter().find(|b| **b == *name) {
+                self.barrier(frame, barrier);
+                // the value loaded by `workgroupUniformLoad` is uniform.
+                return true;
+            }
+            let is_texture_read = name.starts_with("textureSample")
+                || name.starts_with("textureGather")
+                || *name == "textureLoad";
+            if is_texture_read {
+                frame.cost.texture_samples += 1;
+            }
+            // like in the WGSL uniformity analysis, the result of a built-in function
+            // is uniform when its arguments are.
+            all_uniform
+        }
+    }
+
+    /// Estimate the cost of a branch body. `uniform` is the uniformity of the condition.
+    fn branch(&mut self, frame: &mut Frame, uniform: bool, body: &CompoundStatement) {
+        if uniform {
+            self.block(frame, body);
+        } else {
+            frame.cost.divergent_branches += 1;
+            frame.divergent += 1;
+            self.block(frame, body);
+            frame.divergent -= 1;
+        }
+    }
+
+    fn block(&mut self, frame: &mut Frame, block: &CompoundStatement) {
+        let locals = frame.locals;
+        for stmt in &block.statements {
+            self.stmt(frame, stmt);
+        }
+        frame.locals = locals;
+    }
+
+    /// Mark the variable written by an assignment as varying, or as uniform again when
+    /// the whole variable is assigned a uniform value in uniform control flow.
+    fn assign(&mut self, frame: &mut Frame, lhs: &Expression, uniform: bool) {
+        if uniform && frame.divergent == 0 {
+            if let Expression::TypeOrIdentifier(ty) = lhs {
+                frame.varying.retain(|ident| *ident != ty.ident);
+            }
+            return;
+        }
+        let mut lhs = lhs;
+        loop {
+            lhs = match lhs {
+                Expression::Parenthesized(e) => &e.expression,
+                Expression::NamedComponent(e) => &e.base,
+                Expression::Indexing(e) => &e.base,
+                Expression::Unary(e) => &e.operand,
+                Expression::TypeOrIdentifier(ty) => {
+                    frame.varying.push(ty.ident.clone());
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Whether a `for` loop has a counter incremented from a constant to a constant.
+    fn is_static_for(&self, frame: &Frame, stmt: &ForStatement) -> bool {
+        let (Some(init), Some(cond), Some(update)) =
+            (&stmt.initializer, &stmt.condition, &stmt.update)
+        else {
+            return false;
+        };
+        let Statement::Declaration(init) = init.node() else {
+            return false;
+        };
+        let is_counter = |expr: &Expression| matches!(expr, Expression::TypeOrIdentifier(ty) if ty.ident == init.ident);
+        let init = init
+            .initializer
+            .as_ref()
+            .is_some_and(|expr| self.is_const(frame, expr));
+        let cond = match cond.node() {
+            Expression::Binary(e)
+                if matches!(
+                    e.operator,
+                    BinaryOperator::LessThan
+                        | BinaryOperator::LessThanEqual
+                        | BinaryOperator::GreaterThan
+                        | BinaryOperator::GreaterThanEqual
+                        | BinaryOperator::Inequality
+                ) =>
+            {
+                (is_counter(&e.left) && self.is_const(frame, &e.right))
+                    || (is_counter(&e.right) && self.is_const(frame, &e.left))
+            }
+            _ => false,
+        };
+        let update = match update.node() {
+            Statement::Increment(stmt) => is_counter(&stmt.expression),
+            Statement::Decrement(stmt) => is_counter(&stmt.expression),
+            Statement::Assignment(stmt) => {
+                stmt.operator != AssignmentOperator::Equal
+                    && is_counter(&stmt.lhs)
+                    && self.is_const(frame, &stmt.rhs)
+            }
+            _ => false,
+        };
+        init && cond && update
+    }
+
+    fn stmt(&mut self, frame: &mut Frame, stmt: &Statement) {
+        match stmt {
+            Statement::Void | Statement::ConstAssert(_) => {}
+            Statement::Compound(block) => self.block(frame, block),
+            Statement::Assignment(stmt) => {
+                frame.cost.instructions += 1;
+                let rhs = self.expr(frame, &stmt.rhs);
+                let lhs = self.expr(frame, &stmt.lhs);
+                // assigning a whole variable does not depend on its previous value.
+                let overwrite = stmt.operator == AssignmentOperator::Equal
+                    && matches!(stmt.lhs.node(), Expression::TypeOrIdentifier(_));
+                self.assign(frame, &stmt.lhs, rhs & (lhs | overwrite));
+            }
+            Statement::Increment(stmt) => {
+                frame.cost.instructions += 1;
+                let uniform = self.expr(frame, &stmt.expression);
+                self.assign(frame, &stmt.expression, uniform);
+            }
+            Statement::Decrement(stmt) => {
+                frame.cost.instructions += 1;
+                let uniform = self.expr(frame, &stmt.expression);
+                self.assign(frame, &stmt.expression, uniform);
+            }
+            Statement::If(stmt) => {
+                // the clauses following a divergent condition are divergent too.
+                let divergent = frame.divergent;
+                frame.cost.instructions += 1;
+                let uniform = self.expr(frame, &stmt.if_clause.expression);
+                self.branch(frame, uniform, &stmt.if_clause.body);
+                frame.divergent += u32::from(!uniform);
+                for clause in &stmt.else_if_clauses {
+                    frame.cost.instructions += 1;
+                    let uniform = self.expr(frame, &clause.expression);
+                    self.branch(frame, uniform, &clause.body);
+                    frame.divergent += u32::from(!uniform);
+                }
+                if let Some(clause) = &stmt.else_clause {
+                    self.block(frame, &clause.body);
+                }
+                frame.divergent = divergent;
+            }
+            Statement::Switch(stmt) => {
+                frame.cost.instructions += 1;
+                let uniform = self.expr(frame, &stmt.expression);
+                if !uniform {
+                    frame.cost.divergent_branches += 1;
+                }
+                frame.divergent += u32::from(!uniform);
+                for clause in &stmt.clauses {
+                    self.block(frame, &clause.body);
+                }
+                frame.divergent -= u32::from(!uniform);
+            }
+            Statement::Loop(stmt) => {
+                frame.cost.dynamic_loops += 1;
+                let locals = frame.locals;
+                for stmt in &stmt.body.statements {
+                    self.stmt(frame, stmt);
+                }
+                if let Some(continuing) = &stmt.continuing {
+                    self.block(frame, &continuing.body);
+                    if let Some(break_if) = &continuing.break_if {
+                        frame.cost.instructions += 1;
+                        if !self.expr(frame, &break_if.expression) {
+                            frame.cost.divergent_branches += 1;
+                        }
+                    }
+                }
+                frame.locals = locals;
+            }
+            Statement::For(stmt) => {
+                let locals = frame.locals;
+                if let Some(init) = &stmt.initializer {
+                    self.stmt(frame, init);
+                }
+                if !self.is_static_for(frame, stmt) {
+                    frame.cost.dynamic_loops += 1;
+                }
+                let uniform = match &stmt.condition {
+                    Some(cond) => {
+                        frame.cost.instructions += 1;
+                        self.expr(frame, cond)
+                    }
+                    None => true,
+                };
+                frame.divergent += u32::from(!uniform);
+                if let Some(update) = &stmt.update {
+                    self.stmt(frame, update);
+                }
+                frame.divergent -= u32::from(!uniform);
+                self.branch(frame, uniform, &stmt.body);
+                frame.locals = locals;
+            }
+            Statement::While(stmt) => {
+                frame.cost.dynamic_loops += 1;
+                frame.cost.instructions += 1;
+                let uniform = self.expr(frame, &stmt.condition);
+                self.branch(frame, uniform, &stmt.body);
+            }
+            Statement::Break(_) | Statement::Continue(_) | Statement::Discard(_) => {
+                frame.cost.instructions += 1;
+            }
+            Statement::Return(stmt) => {
+                frame.cost.instructions += 1;
+                if let Some(expr) = &stmt.expression {
+                    self.expr(frame, expr);
+                }
+            }
+            Statement::FunctionCall(stmt) => {
+                self.call(frame, &stmt.call);
+            }
+            Statement::Declaration(decl) => {
+                if decl.kind == DeclarationKind::Const {
+                    frame.consts.push(decl.ident.clone());
+                } else if decl
+                    .initializer
+                    .as_ref()
+                    .is_some_and(|init| !self.expr(frame, init))
+                {
+                    frame.varying.push(decl.ident.clone());
+                }
+                frame.declare();
+            }
+        }
+    }
+}
+
+/// Estimate the [`Cost`] of the entry points of a translation unit.
+///
+/// The idents must be linked to their declarations, see [`SyntaxUtil::retarget_idents`].
+pub fn estimate_costs(wesl: &TranslationUnit) -> Vec<EntryPointCost> {
+    let mut estimator = Estimator::new(wesl);
+    wesl.entry_point_functions()
+        .map(|func| EntryPointCost {
+            name: func.ident.to_string(),
+            cost: estimator.entry_point(func),
+        })
+        .collect()
+}
+
+/// The calls to built-in functions that must be called in uniform control flow (e.g.
+/// `workgroupBarrier`), but may be called in non-uniform control flow. Returns the
+/// built-in function and the function containing the call.
+///
+/// The uniformity analysis is the one of [`Cost::divergent_branches`], which is less
+/// precise than the WGSL uniformity analysis: it is only used by
+/// [`crate::ValidationLevel::Pedantic`].
+pub(crate) fn divergent_barriers(wesl: &TranslationUnit) -> Vec<(&'static str, String)> {
+    let mut estimator = Estimator::new(wesl);
+    for func in wesl.entry_point_functions() {
+        estimator.entry_point(func);
+    }
+    estimator.divergent_barriers
+}
+
+/// Check the cost of the entry points against a [`CostBudget`].
+pub(crate) fn check_costs(wesl: &TranslationUnit, budget: &CostBudget) -> Vec<ValidateError> {
+    estimate_costs(wesl)
+        .iter()
+        .flat_map(|ep| budget.check(ep))
+        .collect()
+}
+
+#[test]
+fn test_estimate_costs() {
+    let source = "@group(0) @binding(0) var tex: texture_2d<f32>;
+        @group(0) @binding(1) var samp: sampler;
+        @group(0) @binding(2) var<uniform> count: u32;
+        const N = 4;
+        fn blur(uv: vec2f) -> vec4f {
+            var sum = vec4f();
+            for (var i = 0; i < N; i++) {
+                sum += textureSample(tex, samp, uv + f32(i));
+            }
+            return sum;
+        }
+        @fragment fn main(@location(0) uv: vec2f) -> @location(0) vec4f {
+            var color = blur(uv);
+            for (var i = 0u; i < count; i++) {
+                color *= 0.5;
+            }
+            if color.a < 0.5 {
+                discard;
+            }
+            if count > 2u {
+                color = blur(uv * 2.0);
+            }
+            return color;
+        }";
+    let mut wesl = wgsl_parse::parse_str(source).unwrap();
+    wesl.retarget_idents();
+    let costs = estimate_costs(&wesl);
+    assert_eq!(costs.len(), 1);
+    let cost = costs[0].cost;
+    assert_eq!(cost.texture_samples, 2);
+    // the loop bounded by a uniform variable.
+    assert_eq!(cost.dynamic_loops, 1);
+    // the condition depending on the texture.
+    assert_eq!(cost.divergent_branches, 1);
+    // `uv` and `color`, plus the parameter, `sum` and loop counter of `blur`.
+    assert_eq!(cost.register_pressure, 5);
+    assert!(cost.instructions > 20);
+
+    let budget = CostBudget {
+        texture_samples: Some(1),
+        dynamic_loops: Some(1),
+        ..Default::default()
+    };
+    let errors = check_costs(&wesl, &budget);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].to_string().contains("texture samples"));
+}
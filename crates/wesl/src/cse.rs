@@ -0,0 +1,350 @@
+use std::collections::{HashMap, HashSet};
+
+use wgsl_parse::syntax::*;
+use wgsl_types::idents::{BUILTIN_CONSTRUCTOR_NAMES, BUILTIN_FUNCTION_NAMES};
+
+use crate::{FreshNames, hygiene::for_each_local, visit::Visit};
+
+/// Prefixes of the built-in functions that are not pure: they access memory, have side
+/// effects or must be called in uniform control flow.
+const IMPURE_BUILTIN_PREFIXES: &[&str] = &[
+    "texture",
+    "atomic",
+    "subgroup",
+    "quad",
+    "rayQuery",
+    "dpdx",
+    "dpdy",
+    "fwidth",
+    "workgroup",
+    "storageBarrier",
+    "arrayLength",
+];
+
+/// The identity of a pure expression: its text and the declarations it refers to, so
+/// that expressions referring to shadowed declarations are distinct. Built-in functions
+/// and types are identified by their name.
+type Key = (String, Vec<Ident>);
+
+/// The first statement containing an expression, its number of occurrences, and the
+/// expression.
+type Occurrences = (usize, usize, ExpressionNode);
+
+struct Cse<'a> {
+    /// The global declarations, and whether referring to them is pure.
+    globals: &'a HashMap<Ident, bool>,
+    /// The local declarations of the function.
+    locals: HashSet<Ident>,
+    names: &'a mut FreshNames,
+}
+
+/// Add the `let` declared by a statement to the values in scope.
+fn declare(stat: &StatementNode, values: &mut HashSet<Ident>) {
+    if let Statement::Declaration(decl) = stat.node() {
+        if decl.kind == DeclarationKind::Let {
+            values.insert(decl.ident.clone());
+        }
+    }
+}
+
+impl Cse<'_> {
+    /// Whether an expression is pure, i.e. it can be evaluated anywhere its declarations
+    /// are in scope with the same result. Collects the declarations it refers to, and
+    /// whether it refers to a runtime value (a parameter or a `let`).
+    fn is_pure(
+        &self,
+        expr: &Expression,
+        values: &HashSet<Ident>,
+        idents: &mut Vec<Ident>,
+        runtime: &mut bool,
+    ) -> bool {
+        match expr {
+            Expression::Literal(_) => true,
+            Expression::Parenthesized(e) => self.is_pure(&e.expression, values, idents, runtime),
+            Expression::NamedComponent(e) => self.is_pure(&e.base, values, idents, runtime),
+            Expression::Indexing(e) => {
+                self.is_pure(&e.base, values, idents, runtime)
+                    && self.is_pure(&e.index, values, idents, runtime)
+            }
+            Expression::Unary(e) => {
+                !matches!(
+                    e.operator,
+                    UnaryOperator::AddressOf | UnaryOperator::Indirection
+                ) && self.is_pure(&e.operand, values, idents, runtime)
+            }
+            Expression::Binary(e) => {
+                self.is_pure(&e.left, values, idents, runtime)
+                    && self.is_pure(&e.right, values, idents, runtime)
+            }
+            Expression::FunctionCall(call) => {
+                let id = &call.ty.ident;
+                let callee = match self.globals.get(id) {
+                    Some(true) => {
+                        idents.push(id.clone());
+                        true
+                    }
+                    Some(false) => false,
+                    None => {
+                        let name = id.name();
+                        !self.locals.contains(id)
+                            && (BUILTIN_CONSTRUCTOR_NAMES.contains(&name.as_str())
+                                || BUILTIN_FUNCTION_NAMES.contains(&name.as_str())
+                                    && !IMPURE_BUILTIN_PREFIXES
+                                        .iter()
+                                        .any(|prefix| name.starts_with(prefix)))
+                    }
+                };
+                callee
+                    && call.ty.path.is_none()
+                    && call
+                        .arguments
+                        .iter()
+                        .all(|arg| self.is_pure(arg, values, idents, runtime))
+            }
+            Expression::TypeOrIdentifier(ty) => {
+                let id = &ty.ident;
+                if ty.template_args.is_some() || ty.path.is_some() {
+                    false
+                } else if values.contains(id) {
+                    idents.push(id.clone());
+                    *runtime = true;
+                    true
+                } else if self.globals.get(id) == Some(&true) {
+                    idents.push(id.clone());
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// The key of an expression, if it is worth eliminating: a pure operation or function
+    /// call on runtime values. Constant expressions are left alone, since they may be
+    /// abstract.
+    fn key(&self, expr: &Expression, values: &HashSet<Ident>) -> Option<Key> {
+        let is_operation = match expr {
+            Expression::Unary(_) | Expression::Binary(_) => true,
+            Expression::FunctionCall(call) => !call.arguments.is_empty(),
+            _ => false,
+        };
+        let mut idents = Vec::new();
+        let mut runtime = false;
+        (is_operation && self.is_pure(expr, values, &mut idents, &mut runtime) && runtime)
+            .then(|| (expr.to_string(), idents))
+    }
+
+    fn count(
+        &self,
+        expr: &ExpressionNode,
+        values: &HashSet<Ident>,
+        index: usize,
+        counts: &mut HashMap<Key, Occurrences>,
+    ) {
+        if let Some(key) = self.key(expr, values) {
+            counts.entry(key).or_insert((index, 0, expr.clone())).1 += 1;
+        }
+        for expr in Visit::<ExpressionNode>::visit(expr.node()) {
+            self.count(expr, values, index, counts);
+        }
+    }
+
+    /// The longest expression occurring at least twice in the statements, with the index
+    /// of the first statement containing it.
+    fn most_common(
+        &self,
+        stats: &[StatementNode],
+        mut values: HashSet<Ident>,
+    ) -> Option<(Key, Occurrences)> {
+        let mut counts = HashMap::new();
+        for (index, stat) in stats.iter().enumerate() {
+            for expr in Visit::<ExpressionNode>::visit(stat.node()) {
+                self.count(expr, &values, index, &mut counts);
+            }
+            declare(stat, &mut values);
+        }
+        counts
+            .into_iter()
+            .filter(|(_, (_, count, _))| *count >= 2)
+            .max_by(|(k1, (i1, _, _)), (k2, (i2, _, _))| {
+                (k1.0.len(), i2, &k2.0).cmp(&(k2.0.len(), i1, &k1.0))
+            })
+    }
+
+    fn replace(
+        &self,
+        expr: &mut ExpressionNode,
+        key: &Key,
+        values: &HashSet<Ident>,
+        ident: &Ident,
+    ) {
+        // parentheses around the expression are removed too.
+        let inner = match expr.node() {
+            Expression::Parenthesized(paren) => &paren.expression,
+            _ => &*expr,
+        };
+        if self.key(inner, values).as_ref() == Some(key) {
+            *expr.node_mut() = Expression::TypeOrIdentifier(TypeExpression::new(ident.clone()));
+        } else {
+            for expr in Visit::<ExpressionNode>::visit_mut(expr.node_mut()) {
+                self.replace(expr, key, values, ident);
+            }
+        }
+    }
+
+    /// Eliminate the common subexpressions of a sequence of statements, then of the
+    /// nested blocks. `values` are the parameters and `let` declarations in scope.
+    fn block(&mut self, stats: &mut Vec<StatementNode>, values: HashSet<Ident>) {
+        while let Some((key, (index, _, expr))) = self.most_common(stats, values.clone()) {
+            let ident = self.names.fresh_ident("cse");
+            let mut scope = values.clone();
+            for stat in &stats[..index] {
+                declare(stat, &mut scope);
+            }
+            for stat in &mut stats[index..] {
+                for expr in Visit::<ExpressionNode>::visit_mut(stat.node_mut()) {
+                    self.replace(expr, &key, &scope, &ident);
+                }
+                declare(stat, &mut scope);
+            }
+            let decl = Declaration {
+                attributes: Vec::new(),
+                kind: DeclarationKind::Let,
+                ident: ident.clone(),
+                ty: None,
+                initializer: Some(expr),
+            };
+            stats.insert(index, Statement::Declaration(decl).into());
+            self.locals.insert(ident);
+        }
+
+        let mut values = values;
+        for stat in stats {
+            self.nested(stat.node_mut(), &values);
+            declare(stat, &mut values);
+        }
+    }
+
+    fn nested(&mut self, stat: &mut Statement, values: &HashSet<Ident>) {
+        match stat {
+            Statement::Compound(stat) => self.block(&mut stat.statements, values.clone()),
+            Statement::If(stat) => {
+                self.block(&mut stat.if_clause.body.statements, values.clone());
+                for clause in &mut stat.else_if_clauses {
+                    self.block(&mut clause.body.statements, values.clone());
+                }
+                if let Some(clause) = &mut stat.else_clause {
+                    self.block(&mut clause.body.statements, values.clone());
+                }
+            }
+            Statement::Switch(stat) => {
+                for clause in &mut stat.clauses {
+                    self.block(&mut clause.body.statements, values.clone());
+                }
+            }
+            Statement::Loop(stat) => {
+                self.block(&mut stat.body.statements, values.clone());
+                if let Some(continuing) = &mut stat.continuing {
+                    self.block(&mut continuing.body.statements, values.clone());
+                }
+            }
+            Statement::For(stat) => {
+                let mut values = values.clone();
+                if let Some(init) = &stat.initializer {
+                    declare(init, &mut values);
+                }
+                self.block(&mut stat.body.statements, values);
+            }
+            Statement::While(stat) => self.block(&mut stat.body.statements, values.clone()),
+            _ => {}
+        }
+    }
+}
+
+/// Eliminate the common subexpressions of function bodies: pure expressions computed
+/// more than once in a block are computed once in a `let` declaration. See
+/// [`crate::CompileOptions::eliminate_common_subexpressions`].
+///
+/// Pure expressions are operations and calls to type constructors and built-in
+/// functions without side effects, on literals, constants, parameters and `let`
+/// declarations. Built-in functions accessing memory or requiring uniform control flow
+/// (e.g. texture sampling and derivatives) are not pure. The longest expressions are
+/// eliminated first, and the `let` declaration is inserted before the first statement
+/// of the block containing the expression.
+///
+/// Idents must point at their declaration, see [`crate::SyntaxUtil::retarget_idents`].
+pub fn eliminate_common_subexpressions(wesl: &mut TranslationUnit) {
+    let globals = wesl
+        .global_declarations
+        .iter()
+        .filter_map(|decl| {
+            let pure = match decl.node() {
+                GlobalDeclaration::Declaration(decl) => {
+                    matches!(
+                        decl.kind,
+                        DeclarationKind::Const | DeclarationKind::Override
+                    )
+                }
+                GlobalDeclaration::TypeAlias(_) | GlobalDeclaration::Struct(_) => true,
+                _ => false,
+            };
+            Some((decl.ident()?.clone(), pure))
+        })
+        .collect::<HashMap<_, _>>();
+    let mut names = FreshNames::new(wesl);
+
+    for decl in &mut wesl.global_declarations {
+        let GlobalDeclaration::Function(func) = decl.node_mut() else {
+            continue;
+        };
+        let mut locals = HashSet::new();
+        for_each_local(func.body.statements.iter(), &mut |id| {
+            locals.insert(id.clone());
+        });
+        let values = func.parameters.iter().map(|p| p.ident.clone()).collect();
+        let mut cse = Cse {
+            globals: &globals,
+            locals,
+            names: &mut names,
+        };
+        cse.block(&mut func.body.statements, values);
+    }
+}
+
+#[test]
+fn test_eliminate_common_subexpressions() {
+    let mut wesl = wgsl_parse::parse_str(
+        "const K = 2.0;
+        var<private> g: f32;
+        fn f(a: vec3f, b: vec3f, t: f32) -> f32 {
+            let d = normalize(a - b) * K;
+            var x = dot(normalize(a - b), d) + (t * K);
+            if t > 0.0 {
+                let t = 1.0;
+                x += (t * K) + (t * K);
+            }
+            x += g * 2.0 + g * 2.0;
+            x += textureSample(t_tex, s, a.xy).x + textureSample(t_tex, s, a.xy).x;
+            return x + (t * K);
+        }",
+    )
+    .unwrap();
+    crate::SyntaxUtil::retarget_idents(&mut wesl);
+    eliminate_common_subexpressions(&mut wesl);
+    let out = wesl.to_string();
+    assert!(out.contains("let cse = normalize(a - b);"), "{out}");
+    assert!(out.contains("let d = cse * K;"), "{out}");
+    // the `let` is inserted before the first statement using the expression.
+    assert!(out.contains("let cse_1 = t * K;\n    var x"), "{out}");
+    assert!(out.contains("var x = dot(cse, d) + cse_1;"), "{out}");
+    assert!(out.contains("return x + cse_1;"), "{out}");
+    // the shadowing `t` is a different declaration.
+    assert!(out.contains("let cse_2 = t * K;\n"), "{out}");
+    assert!(out.contains("x += cse_2 + cse_2;"), "{out}");
+    // `var`s and texture sampling are not pure.
+    assert!(out.contains("g * 2.0 + g * 2.0"), "{out}");
+    assert!(
+        out.contains("textureSample(t_tex, s, a.xy).x + textureSample"),
+        "{out}"
+    );
+}
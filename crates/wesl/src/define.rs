@@ -0,0 +1,314 @@
+//! Module-scope constants defined by the host. See [`crate::CompileOptions::define_consts`].
+
+use std::{collections::HashMap, fmt::Display, hash::Hash, str::FromStr};
+
+use itertools::Itertools;
+use wgsl_parse::{
+    lexer::is_valid_ident,
+    syntax::{Expression, GlobalDeclaration, LiteralExpression, TranslationUnit, UnaryOperator},
+};
+
+use crate::{
+    Diagnostic, Error, SyntaxUtil, ValidateError, idents::builtin_ident, prelude::imported_names,
+};
+
+/// The value of a constant defined by the host, see [`crate::CompileOptions::define_consts`].
+///
+/// Scalars convert from the Rust types `bool`, `i32`, `u32` and `f32`, and vectors from
+/// arrays of 2 to 4 scalars, e.g. `ConstValue::from([1.0, 0.5, 0.0])` is a `vec3<f32>`.
+/// It is parsed from WGSL literals and vector constructors, e.g. `16u` or
+/// `vec2f(1.0, 0.5)`. Integer literals without suffix are `i32`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ConstValue {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    F32(f32),
+    /// A vector of 2 to 4 scalars of the same type.
+    Vec(Vec<ConstValue>),
+}
+
+// floats are compared and hashed by their bits, so that options can be compared.
+impl PartialEq for ConstValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::I32(a), Self::I32(b)) => a == b,
+            (Self::U32(a), Self::U32(b)) => a == b,
+            (Self::F32(a), Self::F32(b)) => a.to_bits() == b.to_bits(),
+            (Self::Vec(a), Self::Vec(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ConstValue {}
+
+impl Hash for ConstValue {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Bool(b) => b.hash(state),
+            Self::I32(i) => i.hash(state),
+            Self::U32(u) => u.hash(state),
+            Self::F32(f) => f.to_bits().hash(state),
+            Self::Vec(v) => v.hash(state),
+        }
+    }
+}
+
+impl ConstValue {
+    /// The WGSL type of the value, e.g. `vec3<f32>`.
+    ///
+    /// Returns an error if the value is an invalid vector or a non-finite float.
+    pub fn ty(&self) -> Result<String, &'static str> {
+        match self {
+            Self::Bool(_) => Ok("bool".to_string()),
+            Self::I32(_) => Ok("i32".to_string()),
+            Self::U32(_) => Ok("u32".to_string()),
+            Self::F32(f) if f.is_finite() => Ok("f32".to_string()),
+            Self::F32(_) => Err("WGSL floats must be finite"),
+            Self::Vec(comps) => {
+                if !(2..=4).contains(&comps.len()) {
+                    return Err("vectors must have 2 to 4 components");
+                }
+                let tys = comps
+                    .iter()
+                    .map(|comp| match comp {
+                        Self::Vec(_) => Err("vector components must be scalars"),
+                        comp => comp.ty(),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !tys.iter().all_equal() {
+                    return Err("vector components must have the same type");
+                }
+                Ok(format!("vec{}<{}>", comps.len(), tys[0]))
+            }
+        }
+    }
+}
+
+/// Format the value as a WGSL expression.
+impl Display for ConstValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{b}"),
+            // the negation of `2147483648i` would overflow.
+            Self::I32(i32::MIN) => write!(f, "(-2147483647i - 1i)"),
+            Self::I32(i) => write!(f, "{i}i"),
+            Self::U32(u) => write!(f, "{u}u"),
+            Self::F32(x) => write!(f, "{x:?}f"),
+            Self::Vec(comps) => {
+                let ty = self.ty().map_err(|_| std::fmt::Error)?;
+                write!(f, "{ty}({})", comps.iter().format(", "))
+            }
+        }
+    }
+}
+
+impl FromStr for ConstValue {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn convert(expr: &Expression) -> Option<ConstValue> {
+            match expr {
+                Expression::Literal(lit) => match lit {
+                    LiteralExpression::Bool(b) => Some(ConstValue::Bool(*b)),
+                    LiteralExpression::AbstractInt(i) => {
+                        i32::try_from(*i).ok().map(ConstValue::I32)
+                    }
+                    LiteralExpression::AbstractFloat(f) => Some(ConstValue::F32(*f as f32)),
+                    LiteralExpression::I32(i) => Some(ConstValue::I32(*i)),
+                    LiteralExpression::U32(u) => Some(ConstValue::U32(*u)),
+                    LiteralExpression::F32(f) => Some(ConstValue::F32(*f)),
+                    _ => None,
+                },
+                Expression::Unary(unary) if unary.operator == UnaryOperator::Negation => {
+                    match convert(unary.operand.node())? {
+                        ConstValue::I32(i) => Some(ConstValue::I32(-i)),
+                        ConstValue::F32(f) => Some(ConstValue::F32(-f)),
+                        _ => None,
+                    }
+                }
+                Expression::Parenthesized(expr) => convert(expr.expression.node()),
+                Expression::FunctionCall(call) => {
+                    let name = call.ty.ident.name();
+                    let (n, suffix) = name.strip_prefix("vec")?.split_at_checked(1)?;
+                    let n = n.parse::<usize>().ok()?;
+                    let ty = match (suffix, call.ty.template_args.as_deref()) {
+                        ("", Some([arg])) => Some(arg.expression.to_string()),
+                        ("i", None) => Some("i32".to_string()),
+                        ("u", None) => Some("u32".to_string()),
+                        ("f", None) => Some("f32".to_string()),
+                        _ => None,
+                    }?;
+                    let comps = call
+                        .arguments
+                        .iter()
+                        .map(|arg| {
+                            let comp = convert(arg.node())?;
+                            // abstract literals take the type of the vector.
+                            match (comp, ty.as_str()) {
+                                (ConstValue::I32(i), "u32") => {
+                                    u32::try_from(i).ok().map(ConstValue::U32)
+                                }
+                                (ConstValue::I32(i), "f32") => Some(ConstValue::F32(i as f32)),
+                                (comp, _) => Some(comp),
+                            }
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    let value = match comps.as_slice() {
+                        [comp] if n > 1 => ConstValue::Vec(vec![comp.clone(); n]),
+                        _ => ConstValue::Vec(comps),
+                    };
+                    (value.ty().ok()? == format!("vec{n}<{ty}>")).then_some(value)
+                }
+                _ => None,
+            }
+        }
+        let expr = wgsl_parse::parse_expression(s).map_err(|e| e.to_string())?;
+        convert(expr.node()).ok_or_else(|| format!("`{s}` is not a scalar or vector literal"))
+    }
+}
+
+impl From<bool> for ConstValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i32> for ConstValue {
+    fn from(value: i32) -> Self {
+        Self::I32(value)
+    }
+}
+
+impl From<u32> for ConstValue {
+    fn from(value: u32) -> Self {
+        Self::U32(value)
+    }
+}
+
+impl From<f32> for ConstValue {
+    fn from(value: f32) -> Self {
+        Self::F32(value)
+    }
+}
+
+impl<T: Into<ConstValue>, const N: usize> From<[T; N]> for ConstValue {
+    fn from(value: [T; N]) -> Self {
+        Self::Vec(value.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Convert the value to an instance of the evaluator, e.g. to pass arguments to
+/// [`crate::Wesl::eval_function`].
+///
+/// # Panics
+/// Panics if the value is an invalid vector, see [`ConstValue::ty`].
+#[cfg(feature = "eval")]
+impl From<ConstValue> for crate::eval::Instance {
+    fn from(value: ConstValue) -> Self {
+        use crate::eval::VecInstance;
+        match value {
+            ConstValue::Bool(b) => b.into(),
+            ConstValue::I32(i) => i.into(),
+            ConstValue::U32(u) => u.into(),
+            ConstValue::F32(f) => f.into(),
+            ConstValue::Vec(comps) => {
+                VecInstance::new(comps.into_iter().map(Into::into).collect()).into()
+            }
+        }
+    }
+}
+
+/// Add the constants defined by the host at the start of the root module, sorted by
+/// name.
+///
+/// Returns an error if a name is not a valid identifier, if it is declared or imported
+/// by the module, or if a value is invalid.
+pub(crate) fn define_consts(
+    wesl: &mut TranslationUnit,
+    consts: &HashMap<String, ConstValue>,
+) -> Result<(), Diagnostic<Error>> {
+    let imports = imported_names(&wesl.imports, false);
+    for (i, (name, value)) in consts.iter().sorted_by_key(|(name, _)| *name).enumerate() {
+        let err = |e: ValidateError| Diagnostic::from(Error::from(e));
+        if !is_valid_ident(name) || builtin_ident(name).is_some() {
+            return Err(err(ValidateError::InvalidDefine(
+                name.clone(),
+                "not a valid identifier",
+            )));
+        }
+        if let Some(decl) = wesl.find_decl(name) {
+            let e = ValidateError::DefineCollision(name.clone(), "a declaration");
+            return Err(err(e).with_span(decl.span()));
+        }
+        if imports.contains(name) {
+            let e = ValidateError::DefineCollision(name.clone(), "an import");
+            return Err(err(e));
+        }
+        let ty = value
+            .ty()
+            .map_err(|msg| err(ValidateError::InvalidDefine(name.clone(), msg)))?;
+        let decl = format!("const {name}: {ty} = {value};")
+            .parse::<GlobalDeclaration>()
+            .map_err(|e| Diagnostic::from(Error::from(e)))?;
+        wesl.global_declarations.insert(i, decl.into());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_define_consts() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    assert_eq!("16u".parse(), Ok(ConstValue::U32(16)));
+    assert_eq!("-2".parse(), Ok(ConstValue::I32(-2)));
+    assert_eq!(
+        "vec3f(1, 0.5, 0)".parse(),
+        Ok(ConstValue::from([1.0, 0.5, 0.0]))
+    );
+    assert_eq!("vec2<u32>(3)".parse(), Ok(ConstValue::from([3u32, 3])));
+    assert!("vec2f(1.0, true)".parse::<ConstValue>().is_err());
+    assert_eq!(
+        ConstValue::from([1.0, 2.0]).to_string(),
+        "vec2<f32>(1.0f, 2.0f)"
+    );
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@compute @workgroup_size(1) fn main() {
+            let n = MAX_LIGHTS * 2u;
+            let c = TINT * f32(n);
+        }"
+        .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    let mut options = CompileOptions::default();
+    options
+        .define_consts
+        .insert("MAX_LIGHTS".to_string(), 16u32.into());
+    options
+        .define_consts
+        .insert("TINT".to_string(), [1.0, 0.5, 0.0].into());
+    compiler.set_options(options);
+    let root = "package::main".parse().unwrap();
+    let res = compiler.compile(&root).unwrap_or_else(|e| panic!("{e}"));
+    let output = res.to_string();
+    assert!(output.contains("const MAX_LIGHTS: u32 = 16u;"), "{output}");
+    assert!(
+        output.contains("const TINT: vec3<f32> = vec3<f32>(1f, 0.5f, 0f);"),
+        "{output}"
+    );
+
+    compiler.set_define_const("main", 1i32);
+    let Err(err) = compiler.compile(&root) else {
+        panic!("defined constants must not collide with declarations");
+    };
+    let err = err.to_string();
+    assert!(err.contains("collides with a declaration"), "{err}");
+}
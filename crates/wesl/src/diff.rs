@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use wgsl_parse::syntax::{GlobalDeclaration, TranslationUnit};
+
+/// A difference between two versions of a module, reported by [`diff`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AstChange {
+    /// A declaration was added.
+    Added(String),
+    /// A declaration was removed.
+    Removed(String),
+    /// The body of a function or the initializer of a declaration changed.
+    Modified(String),
+    /// The signature of a declaration changed: function attributes, parameters or return
+    /// type, struct members, declaration kind or type, or alias type.
+    SignatureChanged(String),
+    /// The global directives (`enable`, `requires`, `diagnostic`) changed.
+    DirectivesChanged,
+    /// The import statements changed.
+    ImportsChanged,
+    /// The `const_assert` statements changed.
+    ConstAssertsChanged,
+}
+
+/// Compare two versions of a module.
+///
+/// Declarations are matched by name. Formatting, comments and declaration order are
+/// ignored. A declaration whose signature changed is only reported as
+/// [`AstChange::SignatureChanged`], even if its body changed as well.
+///
+/// A hot-reloading system can use it to decide whether a pipeline must be rebuilt,
+/// e.g. when an entry point signature or a binding changed, or whether the module can
+/// be swapped in place.
+pub fn diff(old: &TranslationUnit, new: &TranslationUnit) -> Vec<AstChange> {
+    let mut changes = Vec::new();
+
+    if strings(&old.imports) != strings(&new.imports) {
+        changes.push(AstChange::ImportsChanged);
+    }
+
+    if strings(&old.global_directives) != strings(&new.global_directives) {
+        changes.push(AstChange::DirectivesChanged);
+    }
+
+    let const_asserts = |wesl: &TranslationUnit| {
+        let mut asserts = wesl
+            .global_declarations
+            .iter()
+            .filter(|decl| decl.is_const_assert())
+            .map(|decl| decl.to_string())
+            .collect::<Vec<_>>();
+        asserts.sort();
+        asserts
+    };
+    if const_asserts(old) != const_asserts(new) {
+        changes.push(AstChange::ConstAssertsChanged);
+    }
+
+    let new_decls = named_decls(new);
+    let old_decls = named_decls(old);
+
+    for (name, old_decl) in &old_decls {
+        match new_decls.get(name) {
+            Some(new_decl) => {
+                if signature(old_decl) != signature(new_decl) {
+                    changes.push(AstChange::SignatureChanged(name.clone()));
+                } else if old_decl.to_string() != new_decl.to_string() {
+                    changes.push(AstChange::Modified(name.clone()));
+                }
+            }
+            None => changes.push(AstChange::Removed(name.clone())),
+        }
+    }
+
+    for name in new_decls.keys() {
+        if !old_decls.contains_key(name) {
+            changes.push(AstChange::Added(name.clone()));
+        }
+    }
+
+    changes.sort_by(|a, b| order(a).cmp(&order(b)));
+    changes
+}
+
+fn strings<T: ToString>(items: &[T]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
+
+fn named_decls(wesl: &TranslationUnit) -> HashMap<String, &GlobalDeclaration> {
+    wesl.global_declarations
+        .iter()
+        .filter_map(|decl| Some((decl.ident()?.to_string(), decl.node())))
+        .collect()
+}
+
+/// The part of a declaration that is visible to other declarations and to the host.
+fn signature(decl: &GlobalDeclaration) -> String {
+    match decl {
+        GlobalDeclaration::Declaration(d) => {
+            let mut d = d.clone();
+            d.initializer = None;
+            d.to_string()
+        }
+        GlobalDeclaration::Function(f) => {
+            let mut f = f.clone();
+            f.body = Default::default();
+            f.to_string()
+        }
+        _ => decl.to_string(),
+    }
+}
+
+/// Sort changes in a deterministic order: by kind, then by name.
+fn order(change: &AstChange) -> (u8, &str) {
+    match change {
+        AstChange::ImportsChanged => (0, ""),
+        AstChange::DirectivesChanged => (1, ""),
+        AstChange::ConstAssertsChanged => (2, ""),
+        AstChange::Removed(name) => (3, name),
+        AstChange::Added(name) => (4, name),
+        AstChange::SignatureChanged(name) => (5, name),
+        AstChange::Modified(name) => (6, name),
+    }
+}
+
+#[test]
+fn test_diff() {
+    let old = wgsl_parse::parse_str(
+        "const N = 4;
+        struct S { a: f32 }
+        fn f(x: f32) -> f32 { return x; }
+        @fragment fn main() {}
+        fn removed() {}",
+    )
+    .unwrap();
+    let new = wgsl_parse::parse_str(
+        "// formatting and order are ignored
+        @fragment fn main(  ) {  }
+        const N = 8;
+        struct S { a: f32, b: u32 }
+        fn f(x: f32) -> f32 { return 2.0 * x; }
+        fn added() {}",
+    )
+    .unwrap();
+    assert_eq!(
+        diff(&old, &new),
+        vec![
+            AstChange::Removed("removed".to_string()),
+            AstChange::Added("added".to_string()),
+            AstChange::SignatureChanged("S".to_string()),
+            AstChange::Modified("N".to_string()),
+            AstChange::Modified("f".to_string()),
+        ]
+    );
+    assert!(diff(&old, &old).is_empty());
+}
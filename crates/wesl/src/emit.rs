@@ -0,0 +1,230 @@
+//! Dump the syntax tree after compilation stages, to find which stage introduced an
+//! issue in the output. See [`Emit`].
+
+use std::{borrow::Cow, fmt::Display, path::PathBuf, str::FromStr};
+
+use wgsl_parse::syntax::{ModulePath, PathOrigin, TranslationUnit};
+
+use crate::{Error, ResolveError, Resolver};
+
+/// A compilation stage after which the syntax tree can be dumped. See [`Emit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum EmitStage {
+    /// Each module, after conditional translation.
+    AfterCondcomp,
+    /// Each module, after all imports are resolved. Declarations are not mangled yet.
+    AfterImports,
+    /// The program, after mangling and assembling the modules into one tree.
+    AfterMangle,
+    /// The program, after lowering, see [`crate::CompileOptions::lower`]. If lowering
+    /// is disabled, the tree after the validation of the assembled program.
+    AfterLower,
+}
+
+impl Display for EmitStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AfterCondcomp => f.write_str("after-condcomp"),
+            Self::AfterImports => f.write_str("after-imports"),
+            Self::AfterMangle => f.write_str("after-mangle"),
+            Self::AfterLower => f.write_str("after-lower"),
+        }
+    }
+}
+
+impl FromStr for EmitStage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "after-condcomp" => Ok(Self::AfterCondcomp),
+            "after-imports" => Ok(Self::AfterImports),
+            "after-mangle" => Ok(Self::AfterMangle),
+            "after-lower" => Ok(Self::AfterLower),
+            _ => Err(format!("unknown compilation stage `{s}`")),
+        }
+    }
+}
+
+/// Write the printed syntax tree after some compilation stages to a directory. See
+/// [`crate::CompileOptions::emit`].
+///
+/// The stages of each module are written to `<dir>/<stage>/<module path>.wesl`, e.g.
+/// `after-condcomp/package/util.wesl`. The stages of the assembled program are written
+/// to `<dir>/<stage>.wgsl`, e.g. `after-mangle.wgsl`. Existing files are overwritten.
+///
+/// Module path components which are not plain file names, e.g. `".."` or `"a/b"`, are
+/// percent-escaped so that files are never written outside of the directory.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Emit {
+    pub dir: PathBuf,
+    pub stages: Vec<EmitStage>,
+}
+
+impl Emit {
+    pub fn new(dir: impl Into<PathBuf>, stages: impl IntoIterator<Item = EmitStage>) -> Self {
+        Self {
+            dir: dir.into(),
+            stages: stages.into_iter().collect(),
+        }
+    }
+
+    /// The file of a stage, for a module or for the assembled program.
+    pub fn file(&self, stage: EmitStage, module: Option<&ModulePath>) -> PathBuf {
+        match module {
+            Some(path) => {
+                let origin = match &path.origin {
+                    PathOrigin::Absolute => vec!["package"],
+                    PathOrigin::Relative(n) => vec!["super"; *n],
+                    PathOrigin::Package(name) => vec![name.as_str()],
+                };
+                let mut names = origin
+                    .into_iter()
+                    .chain(path.components.iter().map(String::as_str))
+                    .map(file_name)
+                    .collect::<Vec<_>>();
+                if let Some(last) = names.last_mut() {
+                    last.push_str(".wesl");
+                }
+                let mut file = self.dir.join(stage.to_string());
+                file.extend(names);
+                file
+            }
+            None => self.dir.join(format!("{stage}.wgsl")),
+        }
+    }
+
+    /// Write the tree of a stage, if the stage is enabled.
+    pub(crate) fn write(
+        emit: Option<&Self>,
+        stage: EmitStage,
+        module: Option<&ModulePath>,
+        wesl: &TranslationUnit,
+    ) -> Result<(), Error> {
+        let Some(emit) = emit.filter(|emit| emit.stages.contains(&stage)) else {
+            return Ok(());
+        };
+        let file = emit.file(stage, module);
+        let res = match file.parent() {
+            Some(dir) => std::fs::create_dir_all(dir),
+            None => Ok(()),
+        };
+        res.and_then(|()| std::fs::write(&file, wesl.to_string()))
+            .map_err(|e| Error::EmitError(file, e.to_string()))
+    }
+}
+
+/// Escape a module path component into a single file name.
+fn file_name(name: &str) -> String {
+    let name = name
+        .replace('%', "%25")
+        .replace('/', "%2F")
+        .replace('\\', "%5C");
+    match name.as_str() {
+        "" => "%".to_string(),
+        "." | ".." => name.replace('.', "%2E"),
+        _ => name,
+    }
+}
+
+/// A resolver that writes each module it resolves for [`EmitStage::AfterCondcomp`].
+pub(crate) struct EmitResolver<'a, R: Resolver> {
+    resolver: R,
+    emit: &'a Emit,
+}
+
+impl<'a, R: Resolver> EmitResolver<'a, R> {
+    pub(crate) fn new(resolver: R, emit: &'a Emit) -> Self {
+        Self { resolver, emit }
+    }
+}
+
+impl<R: Resolver> Resolver for EmitResolver<'_, R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let wesl = self.resolver.resolve_module(path)?;
+        Emit::write(Some(self.emit), EmitStage::AfterCondcomp, Some(path), &wesl)
+            .map_err(crate::Diagnostic::from)?;
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+#[test]
+fn test_emit() {
+    use crate::{CompileOptions, ManglerKind, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f;
+        @compute @workgroup_size(1) fn main() { @if(debug) { let x = f(); } }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn f() -> u32 { @if(!debug) { return 0; } return 1; }".into(),
+    );
+    let dir = std::env::temp_dir().join(format!("wesl-emit-{}", std::process::id()));
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .set_mangler(ManglerKind::None)
+        .set_feature("debug", true)
+        .set_emit(Some(Emit::new(
+            &dir,
+            [
+                EmitStage::AfterCondcomp,
+                EmitStage::AfterImports,
+                EmitStage::AfterMangle,
+            ],
+        )));
+    compiler
+        .compile(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    let read = |file: &str| std::fs::read_to_string(dir.join(file)).unwrap();
+    let util = read("after-condcomp/package/util.wesl");
+    assert!(
+        util.contains("return 1;") && !util.contains("return 0;"),
+        "{util}"
+    );
+    assert!(read("after-imports/package/main.wesl").contains("let x = f();"));
+    // the modules are assembled into one tree.
+    let assembled = read("after-mangle.wgsl");
+    assert!(assembled.contains("fn main()") && assembled.contains("fn f()"));
+    assert!(!dir.join("after-lower.wgsl").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_emit_file() {
+    let emit = Emit::new("out", []);
+    let file = |path: &str| {
+        let path = path.parse().unwrap();
+        let file = emit.file(EmitStage::AfterImports, Some(&path));
+        file.strip_prefix("out/after-imports").unwrap().to_owned()
+    };
+    let path = |components: &[&str]| components.iter().collect::<PathBuf>();
+    assert_eq!(file("package::util"), path(&["package", "util.wesl"]));
+    assert_eq!(
+        file(r#"package::"my-shader.v2""#),
+        path(&["package", "my-shader.v2.wesl"])
+    );
+    assert_eq!(file(r#"package::"..""#), path(&["package", "%2E%2E.wesl"]));
+    assert_eq!(
+        file(r#"package::"../a/b"::c"#),
+        path(&["package", "..%2Fa%2Fb", "c.wesl"])
+    );
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeSet;
+
+use wgsl_parse::syntax::{GlobalDeclaration, Statement, StatementNode, TranslationUnit};
+
+use crate::{Error, SyntaxUtil, lower, normalize_attributes, visit::Visit};
+
+/// Normalize a translation unit for semantic comparison.
+///
+/// The returned translation unit is [lowered][lower] (aliases and constants are
+/// inlined, and with the `eval` feature, const-expressions are evaluated), its
+/// attributes are [sorted][normalize_attributes] and its function parameters and local
+/// declarations are renamed in order of appearance.
+///
+/// The input is not modified: identifiers are shared pointers, so it is re-parsed first.
+pub fn normalize(wesl: &TranslationUnit) -> Result<TranslationUnit, Error> {
+    let mut wesl = wgsl_parse::parse_str(&wesl.to_string())?;
+    wesl.retarget_idents();
+    lower(&mut wesl)?;
+    normalize_attributes(&mut wesl);
+
+    for decl in &mut wesl.global_declarations {
+        let mut n = 0;
+        let mut next = || {
+            // identifiers starting with `__` are reserved in WGSL, so they cannot clash
+            // with user declarations.
+            n += 1;
+            format!("__{n}")
+        };
+        if let GlobalDeclaration::Function(f) = decl.node_mut() {
+            for p in &mut f.parameters {
+                p.ident.rename(next());
+            }
+        }
+        for stat in Visit::<StatementNode>::visit_mut(decl.node_mut()) {
+            rename_locals(stat, &mut next);
+        }
+    }
+    Ok(wesl)
+}
+
+fn rename_locals(stat: &mut StatementNode, next: &mut impl FnMut() -> String) {
+    if let Statement::Declaration(d) = stat.node_mut() {
+        d.ident.rename(next());
+    }
+    for stat in Visit::<StatementNode>::visit_mut(stat.node_mut()) {
+        rename_locals(stat, next);
+    }
+}
+
+/// Check whether two translation units have the same semantics.
+///
+/// Both are [normalized][normalize], then compared declaration by declaration,
+/// ignoring declaration order. This is a conservative check: some equivalent programs
+/// are reported as different, e.g. if statements were reordered.
+pub fn equivalent(a: &TranslationUnit, b: &TranslationUnit) -> Result<bool, Error> {
+    Ok(normalized_decls(a)? == normalized_decls(b)?)
+}
+
+/// Assert that two translation units have the same semantics. See [`equivalent`].
+///
+/// Meant to be used in tests, to verify that a refactoring of a shader library or an
+/// upgrade of wesl did not change the output semantics, even when the text did.
+///
+/// # Panics
+/// Panics if the translation units are not equivalent or if they fail to lower. The
+/// panic message lists the declarations that differ.
+pub fn assert_equivalent(a: &TranslationUnit, b: &TranslationUnit) {
+    let left = normalized_decls(a).unwrap_or_else(|e| panic!("failed to normalize left: {e}"));
+    let right = normalized_decls(b).unwrap_or_else(|e| panic!("failed to normalize right: {e}"));
+    if left != right {
+        let only_left = left.difference(&right).cloned().collect::<Vec<_>>();
+        let only_right = right.difference(&left).cloned().collect::<Vec<_>>();
+        panic!(
+            "translation units are not equivalent\n  left: {}\n right: {}",
+            only_left.join("\n        "),
+            only_right.join("\n        ")
+        );
+    }
+}
+
+fn normalized_decls(wesl: &TranslationUnit) -> Result<BTreeSet<String>, Error> {
+    let wesl = normalize(wesl)?;
+    let directives = wesl.global_directives.iter().map(|d| d.to_string());
+    let decls = wesl
+        .global_declarations
+        .iter()
+        .filter(|decl| !decl.is_void())
+        .map(|decl| decl.to_string());
+    Ok(directives.chain(decls).collect())
+}
+
+#[test]
+fn test_equivalent() {
+    let a = wgsl_parse::parse_str(
+        "alias T = f32;
+        fn scale(x: T) -> T { let y = x * 2.0; return y; }
+        @fragment fn main() -> @location(0) vec4f { return vec4f(scale(1.0)); }",
+    )
+    .unwrap();
+    let b = wgsl_parse::parse_str(
+        "@fragment fn main() -> @location(0) vec4f { return vec4f(scale(1.0)); }
+        fn scale(value: f32) -> f32 { let scaled = value * 2.0; return scaled; }",
+    )
+    .unwrap();
+    assert_equivalent(&a, &b);
+
+    let c = wgsl_parse::parse_str(
+        "@fragment fn main() -> @location(0) vec4f { return vec4f(scale(1.0)); }
+        fn scale(value: f32) -> f32 { let scaled = value * 3.0; return scaled; }",
+    )
+    .unwrap();
+    assert!(!equivalent(&a, &c).unwrap());
+}
@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, path::PathBuf};
 
 use wgsl_parse::{
     span::Span,
@@ -12,6 +12,9 @@ use crate::GenericsError;
 
 use crate::CondCompError;
 use crate::ImportError;
+use crate::{
+    BindingError, CompatError, DebugPrintfError, InterfaceError, LimitError, SlotError, SynthError,
+};
 
 #[cfg(feature = "eval")]
 use crate::eval::{Context, EvalError};
@@ -29,6 +32,18 @@ pub enum Error {
     ImportError(#[from] ImportError),
     #[error("{0}")]
     CondCompError(#[from] CondCompError),
+    #[error("{0}")]
+    LimitError(#[from] LimitError),
+    #[error("{0}")]
+    DebugPrintfError(#[from] DebugPrintfError),
+    #[error("{0}")]
+    BindingError(#[from] BindingError),
+    #[error("{0}")]
+    CompatError(#[from] CompatError),
+    #[error("{0}")]
+    SynthError(#[from] SynthError),
+    #[error("{0}")]
+    InterfaceError(#[from] InterfaceError),
     #[cfg(feature = "generics")]
     #[error("{0}")]
     GenericsError(#[from] GenericsError),
@@ -36,6 +51,10 @@ pub enum Error {
     #[error("{0}")]
     EvalError(#[from] EvalError),
     #[error("{0}")]
+    SlotError(#[from] SlotError),
+    #[error("cannot write the syntax tree to `{0}`: {1}")]
+    EmitError(PathBuf, String),
+    #[error("{0}")]
     Error(#[from] Diagnostic<Error>),
     #[error("{0}")]
     Custom(String),
@@ -59,6 +78,55 @@ pub struct Detail {
     pub display_name: Option<String>,
     pub declaration: Option<String>,
     pub span: Option<Span>,
+    /// Secondary labels, possibly in other modules. See [`Diagnostic::with_label`].
+    pub labels: Vec<Label>,
+    /// Whether the diagnostic is a warning. Warnings do not stop the compilation, see
+    /// [`crate::CompileResult::warnings`].
+    pub warning: bool,
+}
+
+/// A secondary label of a [`Diagnostic`], e.g. "item imported here". Unlike the primary
+/// span, it can point at another module than the module of the diagnostic.
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub message: String,
+    pub span: Span,
+    /// The module of the span. `None` is the module of the diagnostic.
+    pub module_path: Option<ModulePath>,
+    pub display_name: Option<String>,
+    /// The source of the module. If `None`, it is provided by
+    /// [`Diagnostic::with_sourcemap`] or by the source of the diagnostic.
+    pub source: Option<String>,
+}
+
+impl Label {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            module_path: None,
+            display_name: None,
+            source: None,
+        }
+    }
+    /// Provide the module of the span. The `disp_name` is usually the file name of the
+    /// module.
+    pub fn with_module_path(mut self, path: ModulePath, disp_name: Option<String>) -> Self {
+        self.module_path = Some(path);
+        self.display_name = disp_name;
+        self
+    }
+    /// Provide the source code of the module of the span.
+    pub fn with_source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    fn display_origin(&self) -> Option<String> {
+        self.display_name
+            .clone()
+            .or_else(|| self.module_path.as_ref().map(|path| path.to_string()))
+    }
 }
 
 impl From<wgsl_parse::Error> for Diagnostic<Error> {
@@ -79,9 +147,11 @@ impl From<ValidateError> for Diagnostic<Error> {
 impl From<ResolveError> for Diagnostic<Error> {
     fn from(error: ResolveError) -> Self {
         match error {
-            ResolveError::FileNotFound(_, _) | ResolveError::ModuleNotFound(_, _) => {
-                Self::new(error.into())
-            }
+            ResolveError::FileNotFound(_, _)
+            | ResolveError::ModuleNotFound(_, _)
+            | ResolveError::AccessDenied(_, _)
+            | ResolveError::VersionMismatch(_, _, _, _)
+            | ResolveError::InvalidImportMap(_, _) => Self::new(error.into()),
             ResolveError::Error(e) => e,
         }
     }
@@ -91,6 +161,25 @@ impl From<ImportError> for Diagnostic<Error> {
     fn from(error: ImportError) -> Self {
         match error {
             ImportError::ResolveError(e) => Self::from(e),
+            ImportError::MissingDecl(ref path, _, ref similar) => {
+                let labels = similar
+                    .iter()
+                    .map(|decl| {
+                        let msg = format!("{} `{}` declared here", decl.kind, decl.name);
+                        Label::new(msg, decl.span).with_module_path(path.clone(), None)
+                    })
+                    .collect::<Vec<_>>();
+                labels
+                    .into_iter()
+                    .fold(Self::new(error.into()), |diag, label| {
+                        diag.with_label(label)
+                    })
+            }
+            ImportError::WrongKind(ref path, ref decl, _) => {
+                let msg = format!("{} `{}` declared here", decl.kind, decl.name);
+                let label = Label::new(msg, decl.span).with_module_path(path.clone(), None);
+                Self::new(error.into()).with_label(label)
+            }
             _ => Self::new(error.into()),
         }
     }
@@ -102,6 +191,18 @@ impl From<CondCompError> for Diagnostic<Error> {
     }
 }
 
+impl From<LimitError> for Diagnostic<Error> {
+    fn from(error: LimitError) -> Self {
+        Self::new(error.into())
+    }
+}
+
+impl From<DebugPrintfError> for Diagnostic<Error> {
+    fn from(error: DebugPrintfError) -> Self {
+        Self::new(error.into())
+    }
+}
+
 #[cfg(feature = "generics")]
 impl From<GenericsError> for Diagnostic<Error> {
     fn from(error: GenericsError) -> Self {
@@ -140,6 +241,8 @@ impl<E: std::error::Error> Diagnostic<E> {
                 display_name: None,
                 declaration: None,
                 span: None,
+                labels: Vec::new(),
+                warning: false,
             }),
         }
     }
@@ -158,6 +261,11 @@ impl<E: std::error::Error> Diagnostic<E> {
         }
         self
     }
+    /// Add a secondary label, e.g. pointing at a related declaration in another module.
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.detail.labels.push(label);
+        self
+    }
     /// Provide the declaration in which the error originated.
     pub fn with_declaration(mut self, decl: String) -> Self {
         if self.detail.declaration.is_none() {
@@ -215,6 +323,52 @@ impl<E: std::error::Error> Diagnostic<E> {
             }
         }
 
+        for label in &mut self.detail.labels {
+            if let Some(path) = &label.module_path {
+                if label.source.is_none() {
+                    label.source = sourcemap.get_source(path).map(|s| s.to_string());
+                }
+                if label.display_name.is_none() {
+                    label.display_name = sourcemap
+                        .get_display_name(path)
+                        .map(|name| name.to_string());
+                }
+            }
+        }
+
+        self
+    }
+
+    /// Use the module that each span was parsed from, see [`Span::source`]. `modules` is
+    /// the module order of the compilation, see [`crate::CompileResult::modules`].
+    ///
+    /// The span source takes precedence over the module of the declaration: after the
+    /// assembly, a diagnostic can point at syntax nodes spliced from another module.
+    pub(crate) fn with_span_modules(
+        mut self,
+        modules: &[ModulePath],
+        sourcemap: &impl SourceMap,
+    ) -> Self {
+        let module = |span: &Span| {
+            span.source()
+                .and_then(|source| modules.get(source as usize))
+        };
+
+        let path = self.detail.span.as_ref().and_then(module);
+        if let Some(path) = path.filter(|path| self.detail.module_path.as_ref() != Some(*path)) {
+            self.detail.module_path = Some(path.clone());
+            self.detail.display_name = sourcemap.get_display_name(path).map(|n| n.to_string());
+            self.detail.source = sourcemap.get_source(path).map(|s| s.to_string());
+        }
+
+        for label in &mut self.detail.labels {
+            if let Some(path) = module(&label.span).filter(|_| label.module_path.is_none()) {
+                label.module_path = Some(path.clone());
+                label.display_name = sourcemap.get_display_name(path).map(|n| n.to_string());
+                label.source = sourcemap.get_source(path).map(|s| s.to_string());
+            }
+        }
+
         self
     }
 
@@ -375,20 +529,73 @@ impl Diagnostic<Error> {
                 ValidateError::UndefinedSymbol(name)
                 | ValidateError::ParamCount(name, _, _)
                 | ValidateError::NotCallable(name)
-                | ValidateError::Duplicate(name) => unmangle_name(name, sourcemap, mangler),
+                | ValidateError::Duplicate(name)
+                | ValidateError::MissingEntryPoint(name)
+                | ValidateError::NotExportable(name)
+                | ValidateError::MustUse(name)
+                | ValidateError::PushConstantBinding(name)
+                | ValidateError::DisabledExtension(_, name)
+                | ValidateError::DuplicateOverrideId(_, name)
+                | ValidateError::OverrideId(name)
+                | ValidateError::MissingEnable(_, name)
+                | ValidateError::InvalidBlendSrc(name, _)
+                | ValidateError::WorkgroupMemory(name, _, _)
+                | ValidateError::WorkgroupPressure(name, _, _)
+                | ValidateError::CostBudget(name, _, _, _)
+                | ValidateError::StageBuiltin(_, _, name)
+                | ValidateError::NonUniformBarrier(_, name)
+                | ValidateError::VoidCall(name)
+                | ValidateError::FloatEquality(_, name)
+                | ValidateError::AbstractNarrowing(_, name, _) => {
+                    unmangle_name(name, sourcemap, mangler)
+                }
                 ValidateError::Cycle(name1, name2) => {
                     unmangle_name(name1, sourcemap, mangler);
                     unmangle_name(name2, sourcemap, mangler);
                 }
+                ValidateError::InvalidBindingArray
+                | ValidateError::InvalidTexture(_, _)
+                | ValidateError::SwitchDefault(_)
+                | ValidateError::DuplicateCase(_)
+                | ValidateError::CaseType(_, _, _)
+                | ValidateError::AttributeTarget(_, _)
+                | ValidateError::DuplicateAttribute(_)
+                | ValidateError::ConflictingAttributes(_, _)
+                | ValidateError::AttributeArgument(_, _)
+                | ValidateError::AtomicType(_, _)
+                | ValidateError::AtomicResult(_)
+                | ValidateError::Annotation(_, _)
+                | ValidateError::WeslSyntax(_)
+                | ValidateError::Naming(_, _, _, _)
+                | ValidateError::DefineCollision(_, _)
+                | ValidateError::InvalidDefine(_, _) => {}
             },
             Error::ResolveError(_) => {}
             Error::ImportError(_) => {}
+            Error::LimitError(_) => {}
+            Error::DebugPrintfError(_) => {}
+            Error::BindingError(_) | Error::SynthError(_) | Error::InterfaceError(_) => {}
+            Error::SlotError(_) => {}
+            Error::CompatError(e) => match e {
+                CompatError::StorageInVertex(name, _, res) => {
+                    unmangle_name(name, sourcemap, mangler);
+                    unmangle_name(res, sourcemap, mangler);
+                }
+                CompatError::CubeArray(name) | CompatError::DepthTextureLoad(name) => {
+                    unmangle_name(name, sourcemap, mangler)
+                }
+                CompatError::Interpolate(_) | CompatError::Builtin(_) => {}
+            },
             Error::CondCompError(e) => match e {
                 CondCompError::InvalidExpression(expr) => unmangle_expr(expr, sourcemap, mangler),
                 CondCompError::InvalidFeatureFlag(_)
                 | CondCompError::UnexpectedFeatureFlag(_)
                 | CondCompError::NoPrecedingIf
-                | CondCompError::DuplicateIf => {}
+                | CondCompError::DuplicateIf
+                | CondCompError::InvalidFeatureDeclaration
+                | CondCompError::UndeclaredFeatureFlag(_, _)
+                | CondCompError::CompileError(_)
+                | CondCompError::CompileWarn(_) => {}
             },
             #[cfg(feature = "generics")]
             Error::GenericsError(_) => {}
@@ -533,7 +740,7 @@ impl Diagnostic<Error> {
                 | EvalError::FlowInModule(_) => {}
             },
             Error::Error(_) => {}
-            Error::Custom(_) => {}
+            Error::EmitError(_, _) | Error::Custom(_) => {}
         };
 
         self
@@ -546,12 +753,25 @@ impl<E: std::error::Error> Display for Diagnostic<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use annotate_snippets::*;
         let msg = format!("{}", self.error);
-        let title = Level::ERROR.primary_title(&msg);
+        let level = if self.detail.warning {
+            Level::WARNING
+        } else {
+            Level::ERROR
+        };
+        let title = level.primary_title(&msg);
         let mut group = Group::with_title(title);
 
         let orig = self.display_origin();
         let short_orig = self.display_short_origin();
 
+        // labels in the module of the diagnostic are rendered in the primary snippet.
+        let (local_labels, other_labels): (Vec<_>, Vec<_>) =
+            self.detail.labels.iter().partition(|label| {
+                label.source.is_none()
+                    && (label.module_path.is_none() || label.module_path == self.detail.module_path)
+            });
+        let valid_label = |label: &Label, source: &str| label.span.range().end <= source.len();
+
         if let Some(span) = &self.detail.span {
             let source = self.detail.source.as_deref();
 
@@ -559,6 +779,14 @@ impl<E: std::error::Error> Display for Diagnostic<E> {
                 if span.range().end <= source.len() {
                     let annot = AnnotationKind::Primary.span(span.range()).label(&msg);
                     let mut snip = Snippet::source(source).fold(true).annotation(annot);
+                    for label in &local_labels {
+                        if valid_label(label, source) {
+                            let annot = AnnotationKind::Context
+                                .span(label.span.range())
+                                .label(&label.message);
+                            snip = snip.annotation(annot);
+                        }
+                    }
 
                     if let Some(orig) = &short_orig {
                         snip = snip.path(orig);
@@ -576,6 +804,35 @@ impl<E: std::error::Error> Display for Diagnostic<E> {
             }
         }
 
+        // labels without a primary snippet to be rendered in are rendered in their own.
+        let standalone_labels = local_labels.iter().filter(|_| self.detail.span.is_none());
+        for label in other_labels.iter().chain(standalone_labels) {
+            let source = label.source.as_deref().or(self
+                .detail
+                .source
+                .as_deref()
+                .filter(|_| label.module_path.is_none()));
+            match source {
+                Some(source) if valid_label(label, source) => {
+                    let annot = AnnotationKind::Context
+                        .span(label.span.range())
+                        .label(&label.message);
+                    let mut snip = Snippet::source(source).fold(true).annotation(annot);
+                    if let Some(orig) = label.display_origin().or(short_orig.clone()) {
+                        snip = snip.path(orig);
+                    }
+                    group = group.element(snip);
+                }
+                _ => {
+                    let note = match label.display_origin() {
+                        Some(orig) => format!("{} in {orig}", label.message),
+                        None => label.message.clone(),
+                    };
+                    group = group.element(Level::NOTE.message(note));
+                }
+            }
+        }
+
         let note;
         if let Some(decl) = &self.detail.declaration {
             note = format!("in declaration of `{decl}` in {orig}");
@@ -1,6 +1,8 @@
+use std::fmt::Display;
+
 use wgsl_parse::{
     Decorated,
-    syntax::{Attribute, AttributeNode, BuiltinValue, Expression},
+    syntax::{Attribute, AttributeNode, BuiltinValue, Expression, ExpressionNode},
 };
 use wgsl_types::{
     ShaderStage,
@@ -8,10 +10,63 @@ use wgsl_types::{
     ty::{Ty, Type},
 };
 
-use super::{Context, Eval, EvalError, with_stage};
+use super::{Context, Eval, EvalError, SyntaxUtil, with_stage};
 
 type E = EvalError;
 
+/// The value of an argument of a custom attribute, see [`EvalAttrs::attr_custom`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttrValue {
+    Bool(bool),
+    /// An integer of any integer type.
+    Int(i64),
+    /// A float of any floating-point type.
+    Float(f64),
+    /// An identifier that does not refer to a declaration, e.g. `linear` in
+    /// `@filter(linear)`. WGSL has no strings, identifiers are used instead.
+    Ident(String),
+}
+
+impl AttrValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+    /// Get the value as a float. Integers are converted.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Int(i) => Some(*i as f64),
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+    pub fn as_ident(&self) -> Option<&str> {
+        match self {
+            Self::Ident(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl Display for AttrValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(i) => write!(f, "{i}"),
+            Self::Float(x) => write!(f, "{x:?}"),
+            Self::Ident(name) => write!(f, "{name}"),
+        }
+    }
+}
+
 pub trait EvalAttrs: Decorated {
     fn attr_align(&self, ctx: &mut Context) -> Result<Option<u32>, E> {
         attr_align(self.attributes(), ctx).transpose()
@@ -34,6 +89,15 @@ pub trait EvalAttrs: Decorated {
     fn attr_blend_src(&self, ctx: &mut Context) -> Result<Option<bool>, E> {
         attr_blend_src(self.attributes(), ctx).transpose()
     }
+    /// Evaluate the arguments of the custom attribute `@name(...)`, if present.
+    ///
+    /// Arguments are const-evaluated: the context must have executed the module
+    /// declarations (see [`crate::eval()`]) for arguments that refer to them.
+    /// Identifiers that do not refer to a declaration evaluate to [`AttrValue::Ident`].
+    /// On error, [`Context::err_ctx`] points at the argument.
+    fn attr_custom(&self, name: &str, ctx: &mut Context) -> Result<Option<Vec<AttrValue>>, E> {
+        attr_custom(self.attributes(), name, ctx).transpose()
+    }
     fn attr_builtin(&self) -> Option<BuiltinValue> {
         self.attributes().iter().find_map(|attr| match attr.node() {
             Attribute::Builtin(attr) => Some(*attr),
@@ -154,3 +218,86 @@ fn attr_blend_src(attrs: &[AttributeNode], ctx: &mut Context) -> Option<Result<b
         _ => Err(E::InvalidBlendSrc(val)),
     }))
 }
+
+fn eval_attr_value(expr: &ExpressionNode, ctx: &mut Context) -> Result<AttrValue, E> {
+    if let Expression::TypeOrIdentifier(ty) = expr.node() {
+        let name = ty.ident.name();
+        if ty.template_args.is_none()
+            && !ctx.scope.contains(&name)
+            && ctx.source.decl(&name).is_none()
+        {
+            return Ok(AttrValue::Ident(name.to_string()));
+        }
+    }
+    let inst = with_stage!(ctx, ShaderStage::Const, { expr.eval_value(ctx) })?;
+    match inst {
+        Instance::Literal(lit) => match lit {
+            LiteralInstance::Bool(b) => Ok(AttrValue::Bool(b)),
+            LiteralInstance::AbstractInt(i) => Ok(AttrValue::Int(i)),
+            LiteralInstance::I32(i) => Ok(AttrValue::Int(i as i64)),
+            LiteralInstance::U32(u) => Ok(AttrValue::Int(u as i64)),
+            LiteralInstance::AbstractFloat(f) => Ok(AttrValue::Float(f)),
+            LiteralInstance::F32(f) => Ok(AttrValue::Float(f as f64)),
+            LiteralInstance::F16(f) => Ok(AttrValue::Float(f.to_f64())),
+            #[cfg(feature = "naga-ext")]
+            LiteralInstance::I64(i) => Ok(AttrValue::Int(i)),
+            #[cfg(feature = "naga-ext")]
+            LiteralInstance::U64(u) => i64::try_from(u)
+                .map(AttrValue::Int)
+                .map_err(|_| E::ConvOverflow(lit, Type::I64)),
+            #[cfg(feature = "naga-ext")]
+            LiteralInstance::F64(f) => Ok(AttrValue::Float(f)),
+        },
+        _ => {
+            ctx.set_err_span_ctx(expr.span());
+            Err(E::NotScalar(inst.ty()))
+        }
+    }
+}
+
+fn attr_custom(
+    attrs: &[AttributeNode],
+    name: &str,
+    ctx: &mut Context,
+) -> Option<Result<Vec<AttrValue>, E>> {
+    let attr = attrs.iter().find_map(|attr| match attr.node() {
+        Attribute::Custom(attr) if attr.name == name => Some(attr),
+        _ => None,
+    })?;
+    let args = attr.arguments.iter().flatten();
+    Some(args.map(|arg| eval_attr_value(arg, ctx)).collect())
+}
+
+#[test]
+fn test_attr_custom() {
+    use crate::Exec;
+    use wgsl_parse::syntax::{GlobalDeclaration, TranslationUnit};
+
+    let wesl = "const N = 4;
+        @meta(2 * N, 1.5, true, linear, u32(7)) @flag @bad(vec2(1, 2)) fn f() {}"
+        .parse::<TranslationUnit>()
+        .unwrap();
+    let mut ctx = Context::new(&wesl);
+    wesl.exec(&mut ctx).unwrap();
+    let GlobalDeclaration::Function(f) = wesl.global_declarations[1].node() else {
+        panic!("expected a function");
+    };
+    let meta = f.attr_custom("meta", &mut ctx).unwrap().unwrap();
+    assert_eq!(
+        meta,
+        [
+            AttrValue::Int(8),
+            AttrValue::Float(1.5),
+            AttrValue::Bool(true),
+            AttrValue::Ident("linear".to_string()),
+            AttrValue::Int(7),
+        ]
+    );
+    assert_eq!(f.attr_custom("flag", &mut ctx).unwrap(), Some(Vec::new()));
+    assert_eq!(f.attr_custom("other", &mut ctx).unwrap(), None);
+    assert!(matches!(
+        f.attr_custom("bad", &mut ctx),
+        Err(E::NotScalar(_))
+    ));
+    assert!(ctx.err_ctx().1.is_some());
+}
@@ -111,10 +111,13 @@ impl IsConst for Attribute {
             Attribute::If(_) => true,             // if attributes are translate-time (always const)
             Attribute::Elif(_) => true,           // if attributes are translate-time (always const)
             Attribute::Else => true,              // if attributes are translate-time (always const)
-            #[cfg(feature = "generics")]
+            Attribute::CompileError(_) => true,   // translate-time diagnostics
+            Attribute::CompileWarn(_) => true,    // translate-time diagnostics
+            Attribute::Slot(_) => true,           // filled at translate-time
             Attribute::Type(_) => todo!(),
-            #[cfg(feature = "naga-ext")]
             Attribute::EarlyDepthTest(_) => true,
+            Attribute::Task | Attribute::Mesh(_) | Attribute::Payload(_) => false, // attrs on entrypoint function (never const)
+            Attribute::PerPrimitive => false, // attr on entrypoints IO (never const)
             Attribute::Custom(attr) => attr.arguments.is_const(wesl, locals),
         }
     }
@@ -73,6 +73,12 @@ impl Eval for LiteralExpression {
             LiteralExpression::U64(l) => Ok(LiteralInstance::U64(*l).into()),
             #[cfg(feature = "naga-ext")]
             LiteralExpression::F64(l) => Ok(LiteralInstance::F64(*l).into()),
+            #[cfg(not(feature = "naga-ext"))]
+            LiteralExpression::I64(_) => Err(E::UnknownType("i64".to_string())),
+            #[cfg(not(feature = "naga-ext"))]
+            LiteralExpression::U64(_) => Err(E::UnknownType("u64".to_string())),
+            #[cfg(not(feature = "naga-ext"))]
+            LiteralExpression::F64(_) => Err(E::UnknownType("f64".to_string())),
         }
     }
 }
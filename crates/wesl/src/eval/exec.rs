@@ -772,6 +772,18 @@ impl Inputs {
     }
 }
 
+/// Call a user-declared function with arguments, in the stage of the context.
+///
+/// In the [`ShaderStage::Const`] stage, the function and the functions it calls must be
+/// `@const`. The arguments are converted to the types of the parameters.
+pub fn exec_function(
+    decl: &Function,
+    args: Vec<Instance>,
+    ctx: &mut Context,
+) -> Result<Option<Instance>, E> {
+    exec_fn(decl, None, args, ctx)
+}
+
 pub fn exec_entrypoint(
     entrypoint: &Function,
     inputs: Inputs,
@@ -1046,8 +1058,11 @@ impl Exec for Declaration {
                             RefInstance::new(inst, a_s, a_m).into()
                         }
                         AddressSpace::Handle => todo!("handle address space"),
-                        #[cfg(feature = "naga-ext")]
-                        AddressSpace::PushConstant => todo!("push_constant address space"),
+                        AddressSpace::PushConstant => {
+                            return Err(E::Todo(
+                                "executing shaders with push constants".to_string(),
+                            ));
+                        }
                     }
                 }
             }
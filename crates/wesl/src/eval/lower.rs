@@ -343,9 +343,18 @@ impl Lower for Statement {
                     if let Some(clause) = stmt.else_if_clauses.first() {
                         if *clause.expression == EXPR_TRUE {
                             assign_clause!(self, clause.body);
+                        } else {
+                            // the first else-if clause becomes the if clause
+                            let clause = stmt.else_if_clauses.remove(0);
+                            stmt.if_clause = IfClause {
+                                expression: clause.expression,
+                                body: clause.body,
+                            };
                         }
                     } else if let Some(clause) = &stmt.else_clause {
                         assign_clause!(self, clause.body);
+                    } else {
+                        *self = Statement::Void;
                     }
                 }
             }
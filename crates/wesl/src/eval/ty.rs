@@ -6,7 +6,7 @@ use super::{
     ATTR_INTRINSIC, ArrayTemplate, AtomicTemplate, Context, Convert, EvalAttrs, EvalError, Exec,
     MatTemplate, PtrTemplate, SamplerType, ScopeKind, StructType, SyntaxUtil, TextureTemplate,
     TextureType, Ty, Type, VecTemplate, check_swizzle, convert_ty, is_ctor, type_builtin_fn,
-    type_ctor, with_stage,
+    type_ctor, typecheck_struct_ctor, with_stage,
 };
 
 type E = EvalError;
@@ -75,6 +75,12 @@ impl EvalTy for LiteralExpression {
             LiteralExpression::U64(_) => Ok(Type::U64),
             #[cfg(feature = "naga-ext")]
             LiteralExpression::F64(_) => Ok(Type::F64),
+            #[cfg(not(feature = "naga-ext"))]
+            LiteralExpression::I64(_) => Err(E::UnknownType("i64".to_string())),
+            #[cfg(not(feature = "naga-ext"))]
+            LiteralExpression::U64(_) => Err(E::UnknownType("u64".to_string())),
+            #[cfg(not(feature = "naga-ext"))]
+            LiteralExpression::F64(_) => Err(E::UnknownType("f64".to_string())),
         }
     }
 }
@@ -508,7 +514,11 @@ impl EvalTy for FunctionCallExpression {
 
         if let Some(decl) = ctx.source.decl(&ty.ident.name()) {
             match decl {
-                GlobalDeclaration::Struct(decl) => decl.eval_ty(ctx),
+                GlobalDeclaration::Struct(decl) => {
+                    let ty = decl.eval_ty(ctx)?.unwrap_struct();
+                    typecheck_struct_ctor(&ty, &args)?;
+                    Ok(Type::Struct(ty))
+                }
                 GlobalDeclaration::Function(decl) => {
                     if decl.body.contains_attribute(&ATTR_INTRINSIC) {
                         type_builtin_fn(&name, tplt.as_deref(), &args)?
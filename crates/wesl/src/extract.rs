@@ -0,0 +1,141 @@
+//! Extraction of a single declaration into a standalone module. See [`extract`].
+
+use std::{borrow::Cow, path::PathBuf};
+
+use wgsl_parse::syntax::{GlobalDeclaration, ModulePath, TranslationUnit};
+
+use crate::{
+    CompileOptions, Error, EscapeMangler, Hooks, Mangler, ResolveError, Resolver, SyntaxUtil,
+    compile_impl, import,
+};
+
+/// A resolver that removes the `const_assert`s of the modules it resolves: they are not
+/// dependencies of the extracted declaration, but would keep their own dependencies.
+struct NoAssertResolver<'a, R: Resolver> {
+    resolver: &'a R,
+}
+
+impl<R: Resolver> Resolver for NoAssertResolver<'_, R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let mut wesl = self.resolver.resolve_module(path)?;
+        wesl.global_declarations
+            .retain(|decl| !matches!(decl.node(), GlobalDeclaration::ConstAssert(_)));
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+pub(crate) fn extract_impl(
+    root: &ModulePath,
+    item: &str,
+    resolver: &impl Resolver,
+    mangler: &impl Mangler,
+    options: &CompileOptions,
+    hooks: &Hooks,
+) -> Result<TranslationUnit, Error> {
+    let options = CompileOptions {
+        strip: true,
+        keep: Some(vec![item.to_string()]),
+        keep_root: false,
+        mangle_root: false,
+        header: None,
+        emit: None,
+        ..options.clone()
+    };
+    let resolver = NoAssertResolver { resolver };
+    let res = compile_impl(root, &resolver, mangler, &options, hooks)?;
+    if res.syntax.find_decl(item).is_none() {
+        // the declaration does not exist, or is disabled by conditional translation.
+        let wesl = resolver.resolve_module(root)?;
+        let module = import::Module::new(wesl, root.clone())?;
+        return Err(module.missing_decl(item).into());
+    }
+    Ok(res.syntax)
+}
+
+/// Extract a declaration of a module into a standalone module, containing only the
+/// declaration and its transitive dependencies, e.g. for documentation examples, bug
+/// reports or unit tests of a single library function.
+///
+/// The module is compiled with `item` as the only declaration to keep, see
+/// [`CompileOptions::keep`]: the other options apply, but stripping is always enabled
+/// and the declarations of `root` are never mangled. Declarations imported from other
+/// modules are mangled with [`EscapeMangler`]. The global directives of the modules are
+/// kept, their `const_assert`s are removed.
+///
+/// Fails with [`crate::ImportError::MissingDecl`] if the module has no declaration
+/// `item`, or if it is disabled by conditional translation.
+pub fn extract(
+    root: &ModulePath,
+    item: &str,
+    resolver: &impl Resolver,
+    options: &CompileOptions,
+) -> Result<TranslationUnit, Error> {
+    extract_impl(
+        root,
+        item,
+        resolver,
+        &EscapeMangler,
+        options,
+        &Hooks::default(),
+    )
+}
+
+#[test]
+fn test_extract() {
+    use crate::{ImportError, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::lighting".parse().unwrap(),
+        "import package::util::saturate;
+        struct Light { color: vec3f, intensity: f32 }
+        const AMBIENT = 0.1;
+        const_assert AMBIENT < 1.0;
+        fn unrelated() -> f32 { return AMBIENT; }
+        fn shade(l: Light, n_dot_l: f32) -> vec3f {
+            return l.color * saturate(n_dot_l * l.intensity + AMBIENT);
+        }
+        @fragment fn main() -> @location(0) vec4f { return vec4f(0.0); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn saturate(x: f32) -> f32 { return clamp(x, 0.0, 1.0); }
+        fn unused() {}"
+            .into(),
+    );
+    let path = "package::lighting".parse().unwrap();
+    let wesl = extract(&path, "shade", &resolver, &CompileOptions::default())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let names = wesl
+        .global_declarations
+        .iter()
+        .filter_map(|decl| decl.ident().map(|id| id.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(names.len(), 4, "{wesl}");
+    for name in ["Light", "AMBIENT", "shade", "package_util_saturate"] {
+        assert!(names.iter().any(|n| n == name), "{wesl}");
+    }
+    assert!(!wesl.to_string().contains("const_assert"), "{wesl}");
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let Err(err) = compiler.extract(&path, "shadow") else {
+        panic!("extracting a missing declaration must fail");
+    };
+    let err = crate::Diagnostic::from(err);
+    let Error::ImportError(ImportError::MissingDecl(_, name, similar)) = &*err.error else {
+        panic!("expected a missing declaration error, got {err}");
+    };
+    assert_eq!(name, "shadow");
+    assert_eq!(similar[0].name, "shade");
+}
@@ -0,0 +1,318 @@
+use std::fmt::Write;
+
+use wgsl_parse::syntax::ModulePath;
+
+/// A module of a [`ModuleGraph`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleNode {
+    pub path: ModulePath,
+    /// Number of module-scope declarations, after conditional translation.
+    pub declarations: usize,
+    /// Size of the module source, in bytes.
+    pub bytes: usize,
+}
+
+/// The import graph of a WESL program. See [`crate::Wesl::module_graph`].
+///
+/// Modules are indexed in the order of [`crate::Wesl::resolve_dependencies`], the root
+/// module first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleGraph {
+    pub modules: Vec<ModuleNode>,
+    /// `(importer, imported)` pairs of module indices, sorted.
+    pub imports: Vec<(usize, usize)>,
+}
+
+impl ModuleGraph {
+    fn successors(&self, module: usize) -> impl Iterator<Item = usize> + '_ {
+        self.imports
+            .iter()
+            .filter(move |(from, _)| *from == module)
+            .map(|(_, to)| *to)
+    }
+
+    /// The strongly connected components, in reverse topological order (imported
+    /// components first). Tarjan's algorithm.
+    fn components(&self) -> Vec<Vec<usize>> {
+        struct Tarjan<'a> {
+            graph: &'a ModuleGraph,
+            index: Vec<Option<usize>>,
+            low: Vec<usize>,
+            stack: Vec<usize>,
+            on_stack: Vec<bool>,
+            next: usize,
+            components: Vec<Vec<usize>>,
+        }
+        impl Tarjan<'_> {
+            fn visit(&mut self, v: usize) {
+                self.index[v] = Some(self.next);
+                self.low[v] = self.next;
+                self.next += 1;
+                self.stack.push(v);
+                self.on_stack[v] = true;
+                for w in self.graph.successors(v) {
+                    match self.index[w] {
+                        None => {
+                            self.visit(w);
+                            self.low[v] = self.low[v].min(self.low[w]);
+                        }
+                        Some(index) if self.on_stack[w] => self.low[v] = self.low[v].min(index),
+                        Some(_) => {}
+                    }
+                }
+                if Some(self.low[v]) == self.index[v] {
+                    let mut component = Vec::new();
+                    while let Some(w) = self.stack.pop() {
+                        self.on_stack[w] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    self.components.push(component);
+                }
+            }
+        }
+
+        let n = self.modules.len();
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: vec![None; n],
+            low: vec![0; n],
+            stack: Vec::new(),
+            on_stack: vec![false; n],
+            next: 0,
+            components: Vec::new(),
+        };
+        for v in 0..n {
+            if tarjan.index[v].is_none() {
+                tarjan.visit(v);
+            }
+        }
+        tarjan.components
+    }
+
+    /// The import cycles: groups of modules that import each other, directly or
+    /// indirectly. WESL allows cyclic imports, but they make modules harder to
+    /// understand and refactor independently.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let mut cycles = self
+            .components()
+            .into_iter()
+            .filter(|c| c.len() > 1 || self.imports.contains(&(c[0], c[0])))
+            .collect::<Vec<_>>();
+        cycles.sort();
+        cycles
+    }
+
+    /// The chain of imports from the root module with the largest total size in bytes,
+    /// i.e. the modules that are worth splitting first.
+    ///
+    /// Modules of an import cycle are all part of the path, since importing one of them
+    /// imports all of them.
+    pub fn heaviest_path(&self) -> Vec<usize> {
+        if self.modules.is_empty() {
+            return Vec::new();
+        }
+        let components = self.components();
+        let mut component_of = vec![0; self.modules.len()];
+        for (c, component) in components.iter().enumerate() {
+            for v in component {
+                component_of[*v] = c;
+            }
+        }
+        // components are in reverse topological order: the successors of a component
+        // come first.
+        let mut weight = vec![0; components.len()];
+        let mut next = vec![None; components.len()];
+        for (c, component) in components.iter().enumerate() {
+            let size = component
+                .iter()
+                .map(|v| self.modules[*v].bytes)
+                .sum::<usize>();
+            let best = component
+                .iter()
+                .flat_map(|v| self.successors(*v))
+                .map(|w| component_of[w])
+                .filter(|d| *d != c)
+                .max_by_key(|d| (weight[*d], std::cmp::Reverse(*d)));
+            weight[c] = size + best.map(|d| weight[d]).unwrap_or(0);
+            next[c] = best;
+        }
+        let mut path = Vec::new();
+        let mut c = Some(component_of[0]);
+        while let Some(current) = c {
+            path.extend(&components[current]);
+            c = next[current];
+        }
+        path
+    }
+
+    /// The label of a module node. Module paths may contain quotes, e.g. in quoted
+    /// package names, so they are escaped for the output language.
+    fn label(&self, module: usize, escape: fn(&str) -> String, line_break: &str) -> String {
+        let node = &self.modules[module];
+        format!(
+            "{}{line_break}{} decls, {} B",
+            escape(&node.path.to_string()),
+            node.declarations,
+            node.bytes
+        )
+    }
+
+    /// The graph in the Graphviz DOT language. Modules of import cycles and imports
+    /// between them are red, and the heaviest path is bold.
+    pub fn to_dot(&self) -> String {
+        let cycles = self.cycles();
+        let in_cycle = |v: usize| cycles.iter().any(|c| c.contains(&v));
+        let heaviest = self.heaviest_path();
+        let mut dot = String::from("digraph modules {\n    node [shape=box];\n");
+        for v in 0..self.modules.len() {
+            let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+            let mut attrs = vec![format!("label=\"{}\"", self.label(v, escape, "\\n"))];
+            if in_cycle(v) {
+                attrs.push("color=red".to_string());
+            }
+            if heaviest.contains(&v) {
+                attrs.push("style=bold".to_string());
+            }
+            writeln!(dot, "    m{v} [{}];", attrs.join(", ")).unwrap();
+        }
+        for (from, to) in &self.imports {
+            let same_cycle = cycles.iter().any(|c| c.contains(from) && c.contains(to));
+            let attrs = if same_cycle {
+                " [color=red]"
+            } else {
+                ""
+            };
+            writeln!(dot, "    m{from} -> m{to}{attrs};").unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The graph as a Mermaid flowchart. Modules of import cycles are red, and the
+    /// heaviest path is bold.
+    pub fn to_mermaid(&self) -> String {
+        let cycles = self.cycles();
+        let heaviest = self.heaviest_path();
+        let mut mermaid = String::from("flowchart TD\n");
+        for v in 0..self.modules.len() {
+            let escape = |s: &str| s.replace('"', "#quot;");
+            writeln!(mermaid, "    m{v}[\"{}\"]", self.label(v, escape, "<br/>")).unwrap();
+        }
+        for (from, to) in &self.imports {
+            writeln!(mermaid, "    m{from} --> m{to}").unwrap();
+        }
+        let class = |name: &str, modules: &mut dyn Iterator<Item = usize>| {
+            let modules = modules.map(|v| format!("m{v}")).collect::<Vec<_>>();
+            (!modules.is_empty()).then(|| format!("    class {} {name}\n", modules.join(",")))
+        };
+        mermaid.push_str("    classDef cycle stroke:#d33,color:#d33\n");
+        mermaid.push_str("    classDef heaviest stroke-width:3px\n");
+        if let Some(line) = class("cycle", &mut cycles.iter().flatten().copied()) {
+            mermaid.push_str(&line);
+        }
+        if let Some(line) = class("heaviest", &mut heaviest.iter().copied()) {
+            mermaid.push_str(&line);
+        }
+        mermaid
+    }
+}
+
+#[test]
+fn test_module_graph() {
+    let module = |name: &str, bytes| ModuleNode {
+        path: format!("package::{name}").parse().unwrap(),
+        declarations: 1,
+        bytes,
+    };
+    let graph = ModuleGraph {
+        modules: vec![
+            module("main", 10),
+            module("a", 100),
+            module("b", 20),
+            module("c", 30),
+            module("d", 5),
+        ],
+        // main -> a -> d, main -> b <-> c
+        imports: vec![(0, 1), (0, 2), (1, 4), (2, 3), (3, 2)],
+    };
+    assert_eq!(graph.cycles(), vec![vec![2, 3]]);
+    assert_eq!(graph.heaviest_path(), vec![0, 1, 4]);
+
+    let dot = graph.to_dot();
+    assert!(dot.contains("m2 -> m3 [color=red];"), "{dot}");
+    assert!(
+        dot.contains("m1 [label=\"package::a\\n1 decls, 100 B\", style=bold];"),
+        "{dot}"
+    );
+    let mermaid = graph.to_mermaid();
+    assert!(mermaid.contains("class m2,m3 cycle"), "{mermaid}");
+    assert!(mermaid.contains("class m0,m1,m4 heaviest"), "{mermaid}");
+
+    // quoted package names are escaped.
+    let graph = ModuleGraph {
+        modules: vec![ModuleNode {
+            path: ModulePath::new(
+                wgsl_parse::syntax::PathOrigin::Package("a \"b\"".to_string()),
+                vec![],
+            ),
+            declarations: 0,
+            bytes: 0,
+        }],
+        imports: vec![],
+    };
+    let dot = graph.to_dot();
+    assert!(
+        dot.contains(r#"m0 [label="\"a \\\"b\\\"\"\n0 decls, 0 B"#),
+        "{dot}"
+    );
+    let mermaid = graph.to_mermaid();
+    assert!(
+        mermaid.contains(r##"m0["#quot;a \#quot;b\#quot;#quot;<br/>0 decls, 0 B"]"##),
+        "{mermaid}"
+    );
+}
+
+#[test]
+fn test_wesl_module_graph() {
+    use crate::{VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    let mut add = |path: &str, source: &'static str| {
+        resolver.add_module(path.parse().unwrap(), source.into());
+    };
+    add(
+        "package::main",
+        "import package::a::f;
+        @compute @workgroup_size(1) fn main() { f(); package::b::g(); }",
+    );
+    add("package::a", "fn f() {}");
+    add(
+        "package::b",
+        "import package::c::h; fn g() { h(); } fn k() {}",
+    );
+    add("package::c", "fn h() { package::b::k(); }");
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.use_imports(true);
+    let graph = compiler
+        .module_graph(&"package::main".parse().unwrap())
+        .unwrap();
+    let paths = graph
+        .modules
+        .iter()
+        .map(|m| m.path.to_string())
+        .collect::<Vec<_>>();
+    let index = |path: &str| paths.iter().position(|p| p == path).unwrap();
+    assert_eq!(graph.modules[index("package::a")].declarations, 1);
+    assert_eq!(graph.modules[index("package::a")].bytes, 9);
+    assert!(graph.imports.contains(&(0, index("package::a"))));
+    assert!(graph.imports.contains(&(0, index("package::b"))));
+    let mut cycle = vec![index("package::b"), index("package::c")];
+    cycle.sort();
+    assert_eq!(graph.cycles(), vec![cycle]);
+}
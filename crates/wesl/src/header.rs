@@ -0,0 +1,69 @@
+use std::{
+    fmt::Write,
+    hash::{DefaultHasher, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::ModulePath;
+
+use crate::{CompileOptions, Feature, Features};
+
+/// Options of the reproducibility header. See [`crate::CompileOptions::header`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Header {
+    /// Add the time of compilation, in seconds since the Unix epoch.
+    ///
+    /// The output is no longer deterministic. Ignored on `wasm32` targets, where the
+    /// system time is not available.
+    pub timestamp: bool,
+}
+
+fn fmt_feature(feature: Feature) -> &'static str {
+    match feature {
+        Feature::Enable => "enable",
+        Feature::Disable => "disable",
+        Feature::Keep => "keep",
+        Feature::Error => "error",
+    }
+}
+
+fn fmt_features(features: &Features) -> String {
+    let flags = features
+        .flags
+        .iter()
+        .sorted_by_key(|(name, _)| name.as_str())
+        .map(|(name, feature)| format!(", {name}={}", fmt_feature(*feature)));
+    format!(
+        "default={}{}",
+        fmt_feature(features.default),
+        flags.format("")
+    )
+}
+
+/// The comment block prepended to the output, with the inputs of the compilation: the
+/// WESL version, the root module, the feature flags and a hash of the other options.
+pub(crate) fn header(header: &Header, root: &ModulePath, options: &CompileOptions) -> String {
+    let mut hasher = DefaultHasher::new();
+    crate::hash_options(options, &mut hasher);
+
+    let mut res = String::new();
+    writeln!(res, "// generated by wesl {}", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(res, "// root: {root}").unwrap();
+    writeln!(res, "// features: {}", fmt_features(&options.features)).unwrap();
+    for (prefix, features) in &options.package_features {
+        writeln!(res, "// features of {prefix}: {}", fmt_features(features)).unwrap();
+    }
+    writeln!(res, "// options: {:016x}", hasher.finish()).unwrap();
+    // `SystemTime::now` panics on `wasm32-unknown-unknown`.
+    if header.timestamp && cfg!(not(target_arch = "wasm32")) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        writeln!(res, "// timestamp: {time}").unwrap();
+    }
+    res
+}
@@ -0,0 +1,249 @@
+//! Hermetic compilation: record the module sources read by a compilation in a
+//! [`Manifest`], and compile later from the manifest only.
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::PathBuf,
+};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{ModulePath, TranslationUnit};
+
+use crate::{CompileOptions, Features, ResolveError, Resolver, hash_options};
+
+/// A module recorded in a [`Manifest`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManifestEntry {
+    pub path: ModulePath,
+    /// The file of the module, see [`Resolver::fs_path`].
+    pub file: Option<PathBuf>,
+    /// The display name of the module, see [`Resolver::display_name`].
+    pub display_name: Option<String>,
+    pub source: String,
+}
+
+/// The module sources read by a compilation.
+///
+/// Record it with a [`RecordingResolver`] or [`Manifest::new`], and compile from it
+/// with a [`ReplayResolver`], without accessing the filesystem. The
+/// [`Manifest::cache_key`] identifies the inputs of a compilation, e.g. for a remote
+/// cache.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Manifest {
+    /// The modules, in order of first resolution.
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Record the sources of modules, e.g. the ones returned by
+    /// [`crate::Wesl::resolve_dependencies`].
+    pub fn new<'a>(
+        modules: impl IntoIterator<Item = &'a ModulePath>,
+        resolver: &impl Resolver,
+    ) -> Result<Self, ResolveError> {
+        let entries = modules
+            .into_iter()
+            .map(|path| entry(path, resolver))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { entries })
+    }
+
+    /// Get the recorded entry of a module.
+    pub fn get(&self, path: &ModulePath) -> Option<&ManifestEntry> {
+        self.entries.iter().find(|entry| entry.path == *path)
+    }
+
+    fn insert(&mut self, entry: ManifestEntry) {
+        if self.get(&entry.path).is_none() {
+            self.entries.push(entry);
+        }
+    }
+
+    /// Compute a hash of the inputs of a compilation: the module sources and the
+    /// compilation options, including the feature flags.
+    ///
+    /// Unlike [`crate::Wesl::content_hash`], it is computed without parsing, and changes
+    /// to comments and whitespace change the hash. The hash does not depend on the order
+    /// of the entries. It is stable for a given build of this crate, but not across
+    /// versions.
+    pub fn cache_key(&self, options: &CompileOptions) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for entry in self
+            .entries
+            .iter()
+            .sorted_by_key(|entry| entry.path.to_string())
+        {
+            entry.path.hash(&mut hasher);
+            entry.source.hash(&mut hasher);
+        }
+        hash_options(options, &mut hasher);
+        hash_features(&options.features, &mut hasher);
+        for (prefix, features) in &options.package_features {
+            prefix.hash(&mut hasher);
+            hash_features(features, &mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+fn hash_features(features: &Features, state: &mut impl Hasher) {
+    features.default.hash(state);
+    for (name, feature) in features.flags.iter().sorted_by_key(|(name, _)| *name) {
+        name.hash(state);
+        feature.hash(state);
+    }
+}
+
+fn entry(path: &ModulePath, resolver: &impl Resolver) -> Result<ManifestEntry, ResolveError> {
+    Ok(ManifestEntry {
+        path: path.clone(),
+        file: resolver.fs_path(path),
+        display_name: resolver.display_name(path),
+        source: resolver.resolve_source(path)?.into_owned(),
+    })
+}
+
+/// A resolver that records the modules it resolves in a [`Manifest`].
+///
+/// Module sources are recorded, not syntax trees: wrap it in resolvers that transform
+/// the syntax trees (e.g. [`crate::Preprocessor`]), so that a [`ReplayResolver`] can be
+/// wrapped in the same way.
+pub struct RecordingResolver<R: Resolver> {
+    resolver: R,
+    manifest: RefCell<Manifest>,
+}
+
+impl<R: Resolver> RecordingResolver<R> {
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            manifest: RefCell::new(Manifest::default()),
+        }
+    }
+
+    /// The modules resolved so far.
+    pub fn manifest(&self) -> Manifest {
+        self.manifest.borrow().clone()
+    }
+
+    fn record(&self, path: &ModulePath, source: &str) {
+        if self.manifest.borrow().get(path).is_none() {
+            self.manifest.borrow_mut().insert(ManifestEntry {
+                path: path.clone(),
+                file: self.resolver.fs_path(path),
+                display_name: self.resolver.display_name(path),
+                source: source.to_string(),
+            });
+        }
+    }
+}
+
+impl<R: Resolver> Resolver for RecordingResolver<R> {
+    fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+        let source = self.resolver.resolve_source(path)?;
+        self.record(path, &source);
+        Ok(source)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let wesl = self.resolver.resolve_module(path)?;
+        if self.manifest.borrow().get(path).is_none() {
+            self.resolve_source(path)?;
+        }
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+/// A resolver that resolves modules from a [`Manifest`] only.
+///
+/// Modules that are not recorded in the manifest fail with
+/// [`ResolveError::ModuleNotFound`].
+#[derive(Clone, Debug, Default)]
+pub struct ReplayResolver {
+    manifest: Manifest,
+}
+
+impl ReplayResolver {
+    pub fn new(manifest: Manifest) -> Self {
+        Self { manifest }
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+}
+
+impl Resolver for ReplayResolver {
+    fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+        self.manifest
+            .get(path)
+            .map(|entry| entry.source.as_str().into())
+            .ok_or_else(|| {
+                ResolveError::ModuleNotFound(path.clone(), "not in the manifest".to_string())
+            })
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.manifest.get(path)?.display_name.clone()
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.manifest.get(path)?.file.clone()
+    }
+}
+
+#[test]
+fn test_record_replay() {
+    use crate::{VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f;
+        @fragment fn main() -> @location(0) vec4f { return vec4f(f()); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn f() -> f32 { return 1.0; }".into(),
+    );
+    resolver.add_module("package::unused".parse().unwrap(), "".into());
+    let root = "package::main".parse().unwrap();
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(RecordingResolver::new(resolver));
+    compiler.set_options(CompileOptions::default());
+    let expected = compiler.compile(&root).unwrap().to_string();
+    let manifest = compiler.resolver().manifest();
+    let paths = manifest
+        .entries
+        .iter()
+        .map(|e| e.path.to_string())
+        .collect_vec();
+    assert_eq!(paths, ["package::main", "package::util"]);
+
+    let mut replay = Wesl::new_barebones().set_custom_resolver(ReplayResolver::new(manifest));
+    replay.set_options(CompileOptions::default());
+    assert_eq!(replay.compile(&root).unwrap().to_string(), expected);
+
+    let manifest = replay.resolver().manifest();
+    let options = CompileOptions::default();
+    let mut reversed = manifest.clone();
+    reversed.entries.reverse();
+    assert_eq!(manifest.cache_key(&options), reversed.cache_key(&options));
+    let mut options_b = CompileOptions::default();
+    options_b
+        .features
+        .flags
+        .insert("a".to_string(), true.into());
+    assert_ne!(manifest.cache_key(&options), manifest.cache_key(&options_b));
+    let mut changed = manifest.clone();
+    changed.entries[1].source.push(' ');
+    assert_ne!(manifest.cache_key(&options), changed.cache_key(&options));
+}
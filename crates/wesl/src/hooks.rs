@@ -0,0 +1,114 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use wgsl_parse::syntax::{ModulePath, TranslationUnit};
+
+use crate::{AnnotationLint, Diagnostic, Error, ResolveError, Resolver};
+
+/// A callback invoked on a module during compilation. See [`Hooks`].
+///
+/// Returning an error aborts the compilation.
+pub type Hook = Box<dyn Fn(&TranslationUnit, &ModulePath) -> Result<(), Error> + Send + Sync>;
+
+/// Callbacks invoked after each compilation stage, for each module.
+///
+/// Hooks receive a read-only view of the module and its path. They can be used to log,
+/// collect metrics or enforce project-specific policies.
+/// See [`crate::Wesl::on_post_parse`], [`crate::Wesl::on_post_condcomp`],
+/// [`crate::Wesl::on_post_resolution`] and [`crate::Wesl::add_annotation_lint`].
+#[derive(Default)]
+pub struct Hooks {
+    /// Invoked after a module is parsed.
+    pub post_parse: Vec<Hook>,
+    /// Invoked after conditional translation is applied to a module.
+    pub post_condcomp: Vec<Hook>,
+    /// Invoked after all imports are resolved, before mangling.
+    pub post_resolution: Vec<Hook>,
+    /// Checked after all imports are resolved, on all modules at once.
+    pub annotation_lints: Vec<Box<dyn AnnotationLint>>,
+}
+
+impl Hooks {
+    pub(crate) fn run(
+        hooks: &[Hook],
+        wesl: &TranslationUnit,
+        path: &ModulePath,
+    ) -> Result<(), Error> {
+        hooks.iter().try_for_each(|hook| hook(wesl, path))
+    }
+}
+
+/// A resolver that runs hooks on each module it resolves.
+pub(crate) struct HookResolver<'a, R: Resolver> {
+    resolver: R,
+    hooks: &'a [Hook],
+}
+
+impl<'a, R: Resolver> HookResolver<'a, R> {
+    pub(crate) fn new(resolver: R, hooks: &'a [Hook]) -> Self {
+        Self { resolver, hooks }
+    }
+}
+
+impl<R: Resolver> Resolver for HookResolver<'_, R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let wesl = self.resolver.resolve_module(path)?;
+        Hooks::run(self.hooks, &wesl, path).map_err(|e| {
+            Diagnostic::from(e)
+                .with_module_path(path.clone(), self.display_name(path))
+                .with_source(self.resolve_source(path).unwrap().to_string())
+        })?;
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+#[test]
+fn test_hook_abort() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let util = "fn forbidden() {}";
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::forbidden;
+        @fragment fn main() { forbidden(); }"
+            .into(),
+    );
+    resolver.add_module("package::util".parse().unwrap(), util.into());
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .on_post_parse(|wesl, _| {
+            if wesl
+                .global_declarations
+                .iter()
+                .any(|decl| decl.ident().is_some_and(|id| *id.name() == "forbidden"))
+            {
+                return Err(Error::Custom("`forbidden` is not allowed".to_string()));
+            }
+            Ok(())
+        });
+
+    let Err(err) = compiler.compile(&"package::main".parse().unwrap()) else {
+        panic!("expected the hook to abort the compilation");
+    };
+    let diagnostic = match err {
+        Error::Error(d) => d,
+        Error::ResolveError(ResolveError::Error(d)) => d,
+        e => panic!("expected a diagnostic, got {e:?}"),
+    };
+    assert_eq!(
+        diagnostic.detail.module_path,
+        Some("package::util".parse().unwrap())
+    );
+    assert_eq!(diagnostic.detail.source.as_deref(), Some(util));
+    assert_eq!(diagnostic.error.to_string(), "`forbidden` is not allowed");
+}
@@ -0,0 +1,97 @@
+use itertools::Itertools;
+
+use crate::eval::{Instance, LiteralInstance};
+
+/// Generate Rust constant declarations from the values returned by
+/// [`crate::Wesl::host_consts`].
+///
+/// Scalars, vectors, matrices and arrays of those are supported. Vectors are
+/// represented as arrays `[T; N]` and matrices as arrays of columns `[[T; R]; C]`.
+/// Abstract integers and floats become `i64` and `f64` and `f16` becomes `f32`, so
+/// that no precision is lost. Values of other types (e.g. structs) are skipped.
+///
+/// ```rust
+/// # use wesl::{Wesl, VirtualResolver, rust_consts};
+/// # let mut resolver = VirtualResolver::new();
+/// # resolver.add_module("package::main".parse().unwrap(), "@export const MAX_LIGHTS = 16u;".into());
+/// # let compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+/// let consts = compiler.host_consts(&"package::main".parse().unwrap()).unwrap();
+/// assert_eq!(rust_consts(&consts), "pub const MAX_LIGHTS: u32 = 16;\n");
+/// ```
+pub fn rust_consts(consts: &[(String, Instance)]) -> String {
+    consts
+        .iter()
+        .filter_map(|(name, inst)| {
+            let (ty, value) = to_rust(inst)?;
+            Some(format!("pub const {name}: {ty} = {value};\n"))
+        })
+        .collect()
+}
+
+/// Convert an instance to a Rust type and expression.
+fn to_rust(inst: &Instance) -> Option<(String, String)> {
+    fn array<'a>(comps: impl Iterator<Item = &'a Instance>) -> Option<(String, String)> {
+        let comps = comps.map(to_rust).collect::<Option<Vec<_>>>()?;
+        let ty = comps.first()?.0.clone();
+        let n = comps.len();
+        let values = comps.into_iter().map(|(_, value)| value).join(", ");
+        Some((format!("[{ty}; {n}]"), format!("[{values}]")))
+    }
+    match inst {
+        Instance::Literal(lit) => {
+            let (ty, value) = match lit {
+                LiteralInstance::Bool(b) => ("bool", b.to_string()),
+                LiteralInstance::AbstractInt(i) => ("i64", i.to_string()),
+                LiteralInstance::AbstractFloat(f) => ("f64", format!("{f:?}")),
+                LiteralInstance::I32(i) => ("i32", i.to_string()),
+                LiteralInstance::U32(u) => ("u32", u.to_string()),
+                LiteralInstance::F32(f) => ("f32", format!("{f:?}")),
+                LiteralInstance::F16(f) => ("f32", format!("{:?}", f.to_f32())),
+                #[cfg(feature = "naga-ext")]
+                LiteralInstance::I64(i) => ("i64", i.to_string()),
+                #[cfg(feature = "naga-ext")]
+                LiteralInstance::U64(u) => ("u64", u.to_string()),
+                #[cfg(feature = "naga-ext")]
+                LiteralInstance::F64(f) => ("f64", format!("{f:?}")),
+            };
+            Some((ty.to_string(), value))
+        }
+        Instance::Vec(v) => array(v.iter()),
+        Instance::Mat(m) => array(m.iter_cols()),
+        Instance::Array(a) => array(a.iter()),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_host_consts() {
+    use crate::{VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::SCALE;
+        @export const MAX_LIGHTS = 4u * 4u;
+        @export const TINT = vec3f(1.0, 0.5, 0.0) * SCALE;
+        @export const IDS = array(1, 2, 3);
+        const PRIVATE = 1;
+        override O: f32;
+        @fragment fn main() {}"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "@export const SCALE = 2.0;".into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(Default::default());
+    let consts = compiler
+        .host_consts(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(
+        rust_consts(&consts),
+        "pub const MAX_LIGHTS: u32 = 16;\n\
+        pub const TINT: [f32; 3] = [2.0, 1.0, 0.0];\n\
+        pub const IDS: [i64; 3] = [1, 2, 3];\n"
+    );
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+
+use wgsl_parse::{
+    lexer::is_valid_ident,
+    syntax::{
+        CompoundStatement, GlobalDeclaration, Ident, Statement, StatementNode, TranslationUnit,
+        TypeExpression,
+    },
+};
+
+use crate::{idents::builtin_ident, visit::Visit};
+
+/// Generates names that are not used in a translation unit.
+///
+/// Used names are the names of global declarations, function parameters and local
+/// declarations, and the names referenced by type expressions. Names of built-in
+/// functions and types, keywords and reserved words are never generated.
+#[derive(Clone, Debug, Default)]
+pub struct FreshNames {
+    used: HashSet<String>,
+}
+
+impl FreshNames {
+    /// Collect the names used in a translation unit.
+    pub fn new(wesl: &TranslationUnit) -> Self {
+        let mut names = Self::default();
+        names.add(wesl);
+        names
+    }
+
+    /// Collect the names used in another translation unit, e.g. another module.
+    pub fn add(&mut self, wesl: &TranslationUnit) {
+        let used = &mut self.used;
+        for decl in &wesl.global_declarations {
+            if let Some(id) = decl.ident() {
+                used.insert(id.to_string());
+            }
+            if let GlobalDeclaration::Function(f) = decl.node() {
+                used.extend(f.parameters.iter().map(|p| p.ident.to_string()));
+            }
+        }
+        for_each_local(Visit::<StatementNode>::visit(wesl), &mut |id| {
+            used.insert(id.to_string());
+        });
+        Visit::<TypeExpression>::visit_rec(wesl, &mut |ty| {
+            used.insert(ty.ident.to_string());
+        });
+    }
+
+    /// Mark a name as used, so that it is never generated.
+    pub fn reserve(&mut self, name: impl Into<String>) {
+        self.used.insert(name.into());
+    }
+
+    /// Generate an unused name: `base` if it is unused, else `base_1`, `base_2`...
+    ///
+    /// If `base` cannot be suffixed to a valid identifier, e.g. it starts with `__`, the
+    /// characters other than ASCII letters and digits are replaced first.
+    /// The name is reserved.
+    pub fn fresh_name(&mut self, base: &str) -> String {
+        let sanitized;
+        let base = if is_valid_ident(&format!("{base}_1")) {
+            base
+        } else {
+            sanitized = sanitize(base);
+            &sanitized
+        };
+        let name = (0..)
+            .map(|i| match i {
+                0 => base.to_string(),
+                i => format!("{base}_{i}"),
+            })
+            .find(|name| {
+                !self.used.contains(name) && builtin_ident(name).is_none() && is_valid_ident(name)
+            })
+            .unwrap();
+        self.used.insert(name.clone());
+        name
+    }
+
+    /// Generate a new [`Ident`] with an unused name. See [`Self::fresh_name`].
+    pub fn fresh_ident(&mut self, base: &str) -> Ident {
+        Ident::new(self.fresh_name(base))
+    }
+}
+
+/// A name made of ASCII letters, digits and underscores, starting with a letter.
+fn sanitize(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c
+            } else {
+                '_'
+            }
+        })
+        .skip_while(|c| !c.is_ascii_alphabetic())
+        .collect::<String>();
+    if name.is_empty() {
+        "x".to_string()
+    } else {
+        name
+    }
+}
+
+pub(crate) fn for_each_local<'a>(
+    stats: impl Iterator<Item = &'a StatementNode>,
+    f: &mut impl FnMut(&Ident),
+) {
+    for stat in stats {
+        if let Statement::Declaration(decl) = stat.node() {
+            f(&decl.ident);
+        }
+        for_each_local(Visit::<StatementNode>::visit(stat.node()), f);
+    }
+}
+
+fn for_each_local_mut<'a>(
+    stats: impl Iterator<Item = &'a mut StatementNode>,
+    f: &mut impl FnMut(&mut Ident),
+) {
+    for stat in stats {
+        if let Statement::Declaration(decl) = stat.node_mut() {
+            f(&mut decl.ident);
+        }
+        for_each_local_mut(Visit::<StatementNode>::visit_mut(stat.node_mut()), f);
+    }
+}
+
+/// Give fresh names to the local declarations of a sequence of statements and their
+/// children.
+///
+/// The declarations get new idents, and their references in the statements are replaced
+/// by the new idents. This way, statements cloned from another function can be renamed
+/// without renaming the original.
+///
+/// Idents must point at their declaration, see [`crate::SyntaxUtil::retarget_idents`].
+pub fn alpha_rename(stats: &mut [StatementNode], names: &mut FreshNames) {
+    let mut renames = HashMap::<Ident, Ident>::new();
+    for_each_local_mut(stats.iter_mut(), &mut |id| {
+        let fresh = names.fresh_ident(&id.name());
+        renames.insert(id.clone(), fresh.clone());
+        *id = fresh;
+    });
+    for stat in stats {
+        Visit::<TypeExpression>::visit_rec_mut(stat.node_mut(), &mut |ty| {
+            if let Some(id) = renames.get(&ty.ident) {
+                ty.ident = id.clone();
+            }
+        });
+    }
+}
+
+/// Insert statements in a block at `index`, avoiding name capture.
+///
+/// The inserted statements are alpha-renamed (see [`alpha_rename`]), so their local
+/// declarations cannot shadow the declarations referenced by the following statements.
+/// The local declarations of the block that have the name of a declaration referenced
+/// by the inserted statements are given a fresh name, so they cannot capture the
+/// reference.
+///
+/// Declarations outside of the block, e.g. function parameters, are not renamed.
+/// Idents must point at their declaration, see [`crate::SyntaxUtil::retarget_idents`].
+///
+/// # Panics
+/// Panics if `index > block.statements.len()`.
+pub fn splice_statements(
+    block: &mut CompoundStatement,
+    index: usize,
+    stats: impl IntoIterator<Item = StatementNode>,
+    names: &mut FreshNames,
+) {
+    let mut stats = stats.into_iter().collect::<Vec<_>>();
+    alpha_rename(&mut stats, names);
+    avoid_capture(block, &stats, names);
+    block.statements.splice(index..index, stats);
+}
+
+/// Give fresh names to the local declarations of a block (and its children) that have
+/// the name of a declaration referenced by statements to be inserted in the block, so
+/// they cannot capture the reference.
+pub(crate) fn avoid_capture(
+    block: &mut CompoundStatement,
+    stats: &[StatementNode],
+    names: &mut FreshNames,
+) {
+    let mut locals = HashSet::new();
+    for_each_local(stats.iter(), &mut |id| {
+        locals.insert(id.clone());
+    });
+    let mut free = HashMap::<String, HashSet<Ident>>::new();
+    for stat in stats {
+        Visit::<TypeExpression>::visit_rec(stat.node(), &mut |ty| {
+            if ty.path.is_none() && !locals.contains(&ty.ident) {
+                free.entry(ty.ident.to_string())
+                    .or_default()
+                    .insert(ty.ident.clone());
+            }
+        });
+    }
+
+    for_each_local_mut(block.statements.iter_mut(), &mut |id| {
+        let captures = free.get(&*id.name()).is_some_and(|refs| !refs.contains(id));
+        if captures {
+            let name = names.fresh_name(&id.name());
+            id.rename(name);
+        }
+    });
+}
+
+#[test]
+fn test_splice_statements() {
+    use crate::SyntaxUtil;
+
+    let mut wesl = wgsl_parse::parse_str(
+        "const a = 1;
+        fn f() -> i32 { let x = a * 2; { let y = x; } return x; }
+        fn main() -> i32 { let a = 5; let x = 3; return a + x; }",
+    )
+    .unwrap();
+    wesl.retarget_idents();
+    let mut names = FreshNames::new(&wesl);
+
+    let body = |wesl: &TranslationUnit, name: &str| -> CompoundStatement {
+        match wesl.find_decl(name).unwrap().node() {
+            GlobalDeclaration::Function(f) => f.body.clone(),
+            _ => unreachable!(),
+        }
+    };
+    let stats = body(&wesl, "f").statements[..2].to_vec();
+    let main = wesl
+        .global_declarations
+        .iter_mut()
+        .find_map(|decl| match decl.node_mut() {
+            GlobalDeclaration::Function(f) if *f.ident.name() == "main" => Some(f),
+            _ => None,
+        })
+        .unwrap();
+    splice_statements(&mut main.body, 2, stats, &mut names);
+
+    let main = body(&wesl, "main").to_string();
+    assert!(main.contains("let a_1 = 5;"), "{main}");
+    assert!(main.contains("let x_1 = a * 2;"), "{main}");
+    assert!(main.contains("let y_1 = x_1;"), "{main}");
+    assert!(main.contains("return a_1 + x;"), "{main}");
+    // the original function is not renamed.
+    let f = body(&wesl, "f").to_string();
+    assert!(f.contains("let x = a * 2;"), "{f}");
+    assert!(f.contains("let y = x;"), "{f}");
+}
@@ -134,7 +134,6 @@ impl BuiltinIdent for AddressSpace {
             Self::Uniform => builtin_ident("uniform"),
             Self::Storage => builtin_ident("storage"),
             Self::Handle => None,
-            #[cfg(feature = "naga-ext")]
             Self::PushConstant => builtin_ident("push_constant"),
         }
     }
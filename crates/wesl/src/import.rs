@@ -5,12 +5,20 @@ use std::{
 };
 
 use itertools::Itertools;
-use wgsl_parse::syntax::{
-    GlobalDeclaration, Ident, ImportContent, ImportStatement, ModulePath, PathOrigin,
-    TranslationUnit, TypeExpression,
+use wgsl_parse::{
+    lexer::is_valid_ident,
+    span::Span,
+    syntax::{
+        DeclarationKind, Expression, ExpressionNode, GlobalDeclaration, Ident, ImportContent,
+        ImportStatement, ModulePath, PathOrigin, Statement, StatementNode, TranslationUnit,
+        TypeExpression,
+    },
 };
 
-use crate::{Diagnostic, Error, Mangler, ResolveError, Resolver, SyntaxUtil, visit::Visit};
+use crate::{
+    Diagnostic, Error, FreshNames, Mangler, ResolveError, Resolver, SyntaxUtil,
+    idents::builtin_ident, visit::Visit,
+};
 
 #[derive(Clone, Debug)]
 struct ImportItem {
@@ -29,22 +37,153 @@ pub enum ImportError {
     DuplicateSymbol(String),
     #[error("{0}")]
     ResolveError(#[from] ResolveError),
-    #[error("module `{0}` has no declaration `{1}`")]
-    MissingDecl(ModulePath, String),
+    #[error("module `{}` has no declaration `{}`{}", .0, .1, fmt_similar(.2))]
+    MissingDecl(ModulePath, String, Vec<SimilarDecl>),
+    #[error("`{}` in module `{}` is a {}, expected a {}", .1.name, .0, .1.kind, .2)]
+    WrongKind(ModulePath, SimilarDecl, &'static str),
     #[error(
         "import of `{0}` in module `{1}` is not `@publish`, but another module tried to import it"
     )]
     Private(String, ModulePath),
+    #[error("`{0}` in module `{1}` conflicts with a prelude item of the same name")]
+    PreludeConflict(String, ModulePath),
+    #[error("path `{0}` in module `{1}` has too many `super` components")]
+    TooManySuper(ModulePath, ModulePath),
 }
 
 type E = ImportError;
 
+/// The kind of a module-scope declaration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeclKind {
+    Const,
+    Override,
+    Var,
+    Alias,
+    Struct,
+    Function,
+}
+
+impl DeclKind {
+    /// The kind of a declaration. Returns `None` for declarations without a name.
+    pub fn of(decl: &GlobalDeclaration) -> Option<Self> {
+        match decl {
+            GlobalDeclaration::Declaration(d) => Some(match d.kind {
+                DeclarationKind::Const => Self::Const,
+                DeclarationKind::Override => Self::Override,
+                DeclarationKind::Let | DeclarationKind::Var(_) => Self::Var,
+            }),
+            GlobalDeclaration::TypeAlias(_) => Some(Self::Alias),
+            GlobalDeclaration::Struct(_) => Some(Self::Struct),
+            GlobalDeclaration::Function(_) => Some(Self::Function),
+            GlobalDeclaration::Void | GlobalDeclaration::ConstAssert(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DeclKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Const => f.write_str("const"),
+            Self::Override => f.write_str("override"),
+            Self::Var => f.write_str("var"),
+            Self::Alias => f.write_str("alias"),
+            Self::Struct => f.write_str("struct"),
+            Self::Function => f.write_str("function"),
+        }
+    }
+}
+
+/// A declaration whose name is similar to a missing declaration, or an imported
+/// declaration of the wrong kind. See [`ImportError::MissingDecl`] and
+/// [`ImportError::WrongKind`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimilarDecl {
+    pub name: String,
+    pub kind: DeclKind,
+    /// The span of the declaration in its module.
+    pub span: Span,
+}
+
+/// Where a declaration of the compiled shader comes from. See [`crate::CompileResult::provenance`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    /// The module declaring the declaration.
+    pub module: ModulePath,
+    /// The name of the declaration in its module, before mangling.
+    pub name: String,
+    /// The span of the declaration in its module.
+    pub span: Span,
+}
+
+/// Key provenances by declaration name.
+///
+/// Stripping relies on ident use-counts, so the idents must not be retained during
+/// compilation. Call this after mangling and get the idents back with
+/// [`provenances_by_ident`].
+pub(crate) fn provenances_by_name(
+    provenances: HashMap<Ident, Provenance>,
+) -> HashMap<String, Provenance> {
+    provenances
+        .into_iter()
+        .map(|(id, prov)| (id.to_string(), prov))
+        .collect()
+}
+
+/// Key provenances by the declaration idents of `wgsl`. See [`provenances_by_name`].
+pub(crate) fn provenances_by_ident(
+    wgsl: &TranslationUnit,
+    mut provenances: HashMap<String, Provenance>,
+) -> HashMap<Ident, Provenance> {
+    wgsl.global_declarations
+        .iter()
+        .filter_map(|decl| {
+            let id = decl.ident()?;
+            let prov = provenances.remove(&*id.name())?;
+            Some((id.clone(), prov))
+        })
+        .collect()
+}
+
+fn fmt_similar(similar: &[SimilarDecl]) -> String {
+    if similar.is_empty() {
+        String::new()
+    } else {
+        let list = similar
+            .iter()
+            .map(|decl| format!("{} `{}`", decl.kind, decl.name))
+            .format(", ");
+        format!(" (similar declarations: {list})")
+    }
+}
+
+/// Levenshtein distance between two strings, ignoring case.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.to_lowercase().chars().collect_vec();
+    let b = b.to_lowercase().chars().collect_vec();
+    let mut row = (0..=b.len()).collect_vec();
+    for (i, ca) in a.iter().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j + 1])
+            };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
 #[derive(Debug)]
 pub(crate) struct Module {
     pub(crate) source: TranslationUnit,
     pub(crate) path: ModulePath,
-    idents: HashMap<Ident, usize>, // lookup (ident, decl_index)
-    treated_idents: RefCell<HashSet<Ident>>, // used idents that have already been usage-analyzed
+    idents: HashMap<Ident, (usize, DeclKind)>, // lookup (ident, (decl_index, decl_kind))
+    treated_idents: RefCell<HashSet<Ident>>,   // used idents that have already been usage-analyzed
     imports: Imports,
 }
 
@@ -54,7 +193,7 @@ impl Module {
             .global_declarations
             .iter()
             .enumerate()
-            .filter_map(|(i, decl)| decl.ident().map(|id| (id.clone(), i)))
+            .filter_map(|(i, decl)| Some((decl.ident()?.clone(), (i, DeclKind::of(decl)?))))
             .collect::<HashMap<_, _>>();
         let imports = flatten_imports(&source.imports, &path)?;
 
@@ -66,6 +205,44 @@ impl Module {
             imports,
         })
     }
+
+    /// Declarations with a name similar to `name`: differing by case or by a typo.
+    fn similar_decls(&self, name: &str) -> Vec<SimilarDecl> {
+        let max_dist = if name.chars().count() > 3 {
+            2
+        } else {
+            1
+        };
+        self.idents
+            .iter()
+            .filter(|(id, _)| *id.name() != name && edit_distance(&id.name(), name) <= max_dist)
+            .map(|(id, (n, kind))| SimilarDecl {
+                name: id.to_string(),
+                kind: *kind,
+                span: self.source.global_declarations[*n].span(),
+            })
+            .sorted_by_key(|decl| decl.span.start)
+            .collect()
+    }
+
+    pub(crate) fn missing_decl(&self, name: &str) -> E {
+        E::MissingDecl(
+            self.path.clone(),
+            name.to_string(),
+            self.similar_decls(name),
+        )
+    }
+
+    /// The module paths referenced by the import statements. An import item `a::b`
+    /// references both `a` and `a::b`, since `b` may be a module.
+    fn imported_paths(&self) -> HashSet<ModulePath> {
+        let mut paths = HashSet::new();
+        for item in self.imports.values() {
+            paths.insert(item.path.clone());
+            paths.insert(item.path.clone().join([item.ident.to_string()]));
+        }
+        paths
+    }
 }
 
 #[derive(Debug)]
@@ -91,18 +268,216 @@ impl Resolutions {
     pub(crate) fn modules(&self) -> impl Iterator<Item = Rc<RefCell<Module>>> + '_ {
         self.order.iter().map(|i| self.modules[i].clone())
     }
-    pub(crate) fn push_module(&mut self, module: Module) -> Rc<RefCell<Module>> {
+    /// The spans of the module source are tagged with the index of the module in the
+    /// module order, see [`wgsl_parse::span::Span::source`].
+    pub(crate) fn push_module(&mut self, mut module: Module) -> Rc<RefCell<Module>> {
+        module.source.set_source(self.order.len() as u32);
         let path = module.path.clone();
         let module = Rc::new(RefCell::new(module));
         self.modules.insert(path.clone(), module.clone());
         self.order.push(path);
         module
     }
+    /// The module of origin, name and span of each declaration.
+    ///
+    /// Call this before [`Self::mangle`] to record the original declaration names.
+    pub(crate) fn provenances(&self) -> HashMap<Ident, Provenance> {
+        self.modules()
+            .flat_map(|module| {
+                let module = module.borrow();
+                module
+                    .idents
+                    .iter()
+                    .map(|(id, (n, _))| {
+                        let prov = Provenance {
+                            module: module.path.clone(),
+                            name: id.to_string(),
+                            span: module.source.global_declarations[*n].span(),
+                        };
+                        (id.clone(), prov)
+                    })
+                    .collect_vec()
+            })
+            .collect()
+    }
+    /// Check that imported declarations are used as their kind permits: only functions
+    /// and types are callable, only structs and aliases are types and functions are not
+    /// values.
+    ///
+    /// Call this after resolution, when the idents point at the external declarations.
+    pub(crate) fn check_kinds(&self, resolver: &impl Resolver) -> Result<(), Error> {
+        let decls = self
+            .modules()
+            .flat_map(|module| {
+                let module = module.borrow();
+                module
+                    .idents
+                    .iter()
+                    .map(|(id, (n, kind))| {
+                        let decl = SimilarDecl {
+                            name: id.to_string(),
+                            kind: *kind,
+                            span: module.source.global_declarations[*n].span(),
+                        };
+                        (id.clone(), (module.path.clone(), decl))
+                    })
+                    .collect_vec()
+            })
+            .collect::<HashMap<_, _>>();
+        for module in self.modules() {
+            let module = module.borrow();
+            let mut checker = KindChecker {
+                module: &module.path,
+                decls: &decls,
+                span: Span::default(),
+            };
+            checker.check(&module.source).map_err(|e| {
+                Diagnostic::from(e)
+                    .with_span(checker.span)
+                    .with_module_path(module.path.clone(), resolver.display_name(&module.path))
+            })?;
+        }
+        Ok(())
+    }
+    /// The imports between the loaded modules: for each module in module order, the
+    /// indices of the modules it imports or references with inline paths, sorted.
+    ///
+    /// Call this after resolution, when the idents point at the external declarations.
+    pub(crate) fn dependency_edges(&self) -> Vec<Vec<usize>> {
+        let owners = self
+            .modules()
+            .enumerate()
+            .flat_map(|(i, module)| {
+                let module = module.borrow();
+                module.idents.keys().map(|id| (id.clone(), i)).collect_vec()
+            })
+            .collect::<HashMap<_, _>>();
+        self.modules()
+            .enumerate()
+            .map(|(i, module)| {
+                let module = module.borrow();
+                let paths = module.imported_paths();
+                let mut deps = self
+                    .order
+                    .iter()
+                    .positions(|path| paths.contains(path))
+                    .collect::<HashSet<_>>();
+                Visit::<TypeExpression>::visit_rec(&module.source, &mut |ty| {
+                    deps.extend(owners.get(&ty.ident));
+                });
+                deps.remove(&i);
+                deps.into_iter().sorted().collect()
+            })
+            .collect()
+    }
     pub(crate) fn into_module_order(self) -> Vec<ModulePath> {
         self.order
     }
 }
 
+/// See [`Resolutions::check_kinds`].
+struct KindChecker<'a> {
+    module: &'a ModulePath,
+    decls: &'a HashMap<Ident, (ModulePath, SimilarDecl)>,
+    /// The span of the innermost node with a span. On error, the span of the use site.
+    span: Span,
+}
+
+type KindResult = Result<(), E>;
+
+impl KindChecker<'_> {
+    fn check(&mut self, wesl: &TranslationUnit) -> KindResult {
+        for decl in &wesl.global_declarations {
+            self.span = decl.span();
+            match decl.node() {
+                GlobalDeclaration::Declaration(decl) => self.opt_ty(decl.ty.as_ref())?,
+                GlobalDeclaration::TypeAlias(alias) => self.ty(&alias.ty, "type")?,
+                GlobalDeclaration::Struct(strukt) => {
+                    for member in &strukt.members {
+                        self.ty(&member.ty, "type")?;
+                    }
+                }
+                GlobalDeclaration::Function(f) => {
+                    for param in &f.parameters {
+                        self.ty(&param.ty, "type")?;
+                    }
+                    self.opt_ty(f.return_type.as_ref())?;
+                    for stat in &f.body.statements {
+                        self.stat(stat)?;
+                    }
+                }
+                GlobalDeclaration::ConstAssert(assert) => self.expr(&assert.expression)?,
+                GlobalDeclaration::Void => {}
+            }
+            for expr in Visit::<ExpressionNode>::visit(decl.node()) {
+                self.expr(expr)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn opt_ty(&mut self, ty: Option<&TypeExpression>) -> KindResult {
+        match ty {
+            Some(ty) => self.ty(ty, "type"),
+            None => Ok(()),
+        }
+    }
+
+    /// Check a reference and the template arguments. `expected` is the usage: a
+    /// `function` call, a `type` or a `value`.
+    fn ty(&mut self, ty: &TypeExpression, expected: &'static str) -> KindResult {
+        if let Some((path, decl)) = self.decls.get(&ty.ident) {
+            let ok = match expected {
+                "function" => !matches!(
+                    decl.kind,
+                    DeclKind::Const | DeclKind::Override | DeclKind::Var
+                ),
+                "type" => matches!(decl.kind, DeclKind::Alias | DeclKind::Struct),
+                _ => decl.kind != DeclKind::Function,
+            };
+            // declarations of the same module are checked by validation.
+            if !ok && path != self.module {
+                return Err(E::WrongKind(path.clone(), decl.clone(), expected));
+            }
+        }
+        for arg in ty.template_args.iter().flatten() {
+            self.expr(&arg.expression)?;
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &ExpressionNode) -> KindResult {
+        let span = std::mem::replace(&mut self.span, expr.span());
+        match expr.node() {
+            Expression::FunctionCall(call) => self.ty(&call.ty, "function")?,
+            // template arguments can be types, e.g. `array<T, 4>`.
+            Expression::TypeOrIdentifier(ty) => self.ty(ty, "value")?,
+            _ => {}
+        }
+        for expr in Visit::<ExpressionNode>::visit(expr.node()) {
+            self.expr(expr)?;
+        }
+        self.span = span;
+        Ok(())
+    }
+
+    /// Check the callees and the declaration types of a statement. The expressions are
+    /// checked separately.
+    fn stat(&mut self, stat: &StatementNode) -> KindResult {
+        let span = std::mem::replace(&mut self.span, stat.span());
+        match stat.node() {
+            Statement::FunctionCall(stat) => self.ty(&stat.call.ty, "function")?,
+            Statement::Declaration(decl) => self.opt_ty(decl.ty.as_ref())?,
+            _ => {}
+        }
+        for stat in Visit::<StatementNode>::visit(stat.node()) {
+            self.stat(stat)?;
+        }
+        self.span = span;
+        Ok(())
+    }
+}
+
 fn err_with_module(e: Error, module: &Module, resolver: &impl Resolver) -> Error {
     Error::from(
         Diagnostic::from(e)
@@ -175,7 +550,7 @@ pub fn resolve_lazy<'a>(
         resolutions: &mut Resolutions,
         resolver: &impl Resolver,
     ) -> Result<(), Error> {
-        if let Some((ident, n)) = module
+        if let Some((ident, (n, _))) = module
             .idents
             .iter()
             .find(|(id, _)| *id.name() == *name.name())
@@ -200,7 +575,7 @@ pub fn resolve_lazy<'a>(
                 Err(E::Private(name.to_string(), module.path.clone()).into())
             }
         } else {
-            Err(E::MissingDecl(module.path.clone(), name.to_string()).into())
+            Err(module.missing_decl(&name.name()).into())
         }
     }
 
@@ -216,13 +591,13 @@ pub fn resolve_lazy<'a>(
         }
 
         let (ext_path, ext_id) = if let Some(path) = &ty.path {
-            let path = resolve_inline_path(path, &module.path, &module.imports);
+            let path = resolve_inline_path(path, &module.path, &module.imports)?;
             (path, ty.ident.clone())
         } else if let Some(item) = module.imports.get(&ty.ident) {
             (item.path.clone(), item.ident.clone())
         } else {
             // points to a local decl, we stop here.
-            if let Some(n) = module.idents.get(&ty.ident) {
+            if let Some((n, _)) = module.idents.get(&ty.ident) {
                 let decl = module.source.global_declarations.get(*n).unwrap();
                 if module.treated_idents.borrow().contains(&ty.ident) {
                     return Ok(());
@@ -273,7 +648,7 @@ pub fn resolve_lazy<'a>(
     }
 
     resolutions.retarget();
-    Ok(())
+    resolutions.check_kinds(resolver)
 }
 
 pub fn resolve_eager(resolutions: &mut Resolutions, resolver: &impl Resolver) -> Result<(), Error> {
@@ -288,7 +663,7 @@ pub fn resolve_eager(resolutions: &mut Resolutions, resolver: &impl Resolver) ->
         }
 
         let (ext_path, ext_id) = if let Some(path) = &ty.path {
-            let res = resolve_inline_path(path, &module.path, &module.imports);
+            let res = resolve_inline_path(path, &module.path, &module.imports)?;
             (res, ty.ident.clone())
         } else if let Some(item) = module.imports.get(&ty.ident) {
             (item.path.clone(), item.ident.clone())
@@ -302,7 +677,7 @@ pub fn resolve_eager(resolutions: &mut Resolutions, resolver: &impl Resolver) ->
             if module.idents.contains_key(&ty.ident) {
                 return Ok(());
             } else {
-                return Err(E::MissingDecl(ext_path, ty.ident.to_string()).into());
+                return Err(module.missing_decl(&ty.ident.name()).into());
             }
         }
 
@@ -327,7 +702,7 @@ pub fn resolve_eager(resolutions: &mut Resolutions, resolver: &impl Resolver) ->
                 .any(|(id, item)| item.public && *id.name() == *ext_id.name())
         {
             return Err(err_with_module(
-                E::MissingDecl(ext_path.clone(), ext_id.to_string()).into(),
+                ext_mod.missing_decl(&ext_id.name()).into(),
                 module,
                 resolver,
             ));
@@ -340,14 +715,29 @@ pub fn resolve_eager(resolutions: &mut Resolutions, resolver: &impl Resolver) ->
         resolver: &impl Resolver,
     ) -> Result<(), Error> {
         for item in module.imports.values() {
-            if !resolutions.modules.contains_key(&item.path) {
-                let mut source = resolver.resolve_module(&item.path)?;
-                source.retarget_idents();
-                let module = resolutions.push_module(Module::new(source, item.path.clone())?);
-                let module = module.borrow();
-                resolve_module(&module, resolutions, resolver)
-                    .map_err(|e| err_with_module(e, &module, resolver))?;
+            if resolutions.modules.contains_key(&item.path) {
+                continue;
             }
+            let (path, mut source) = match resolver.resolve_module(&item.path) {
+                Ok(source) => (item.path.clone(), source),
+                Err(e) => {
+                    // the imported item may be a module, e.g. `import package::foo;`
+                    // followed by inline paths `foo::bar`.
+                    let path = item.path.clone().join([item.ident.to_string()]);
+                    if resolutions.modules.contains_key(&path) {
+                        continue;
+                    }
+                    match resolver.resolve_module(&path) {
+                        Ok(source) => (path, source),
+                        Err(_) => return Err(e.into()),
+                    }
+                }
+            };
+            source.retarget_idents();
+            let module = resolutions.push_module(Module::new(source, path)?);
+            let module = module.borrow();
+            resolve_module(&module, resolutions, resolver)
+                .map_err(|e| err_with_module(e, &module, resolver))?;
         }
 
         for ty in Visit::<TypeExpression>::visit(&module.source) {
@@ -363,7 +753,7 @@ pub fn resolve_eager(resolutions: &mut Resolutions, resolver: &impl Resolver) ->
             .map_err(|e| err_with_module(e, &module, resolver))?;
     }
     resolutions.retarget();
-    Ok(())
+    resolutions.check_kinds(resolver)
 }
 
 /// Flatten imports to a list of module paths.
@@ -402,7 +792,9 @@ fn flatten_imports(imports: &[ImportStatement], parent_path: &ModulePath) -> Res
         let public = import.attributes.iter().any(|attr| attr.is_publish());
         match &import.path {
             Some(import_path) => {
-                let path = parent_path.join_path(import_path);
+                let path = parent_path
+                    .try_join_path(import_path)
+                    .ok_or_else(|| E::TooManySuper(import_path.clone(), parent_path.clone()))?;
                 rec(&import.content, path, public, &mut res)?;
             }
             None => {
@@ -446,24 +838,22 @@ fn resolve_inline_path(
     path: &ModulePath,
     parent_path: &ModulePath,
     imports: &Imports,
-) -> ModulePath {
-    match &path.origin {
-        PathOrigin::Package(pkg_name) => {
-            // the path could be either a package, of referencing an imported module alias.
-            let imported_item = imports.iter().find(|(ident, _)| *ident.name() == *pkg_name);
-
-            if let Some((_, ext_item)) = imported_item {
-                // this inline path references an imported item. Example:
-                // import a::b::c as foo; foo::bar::baz() => a::b::c::bar::baz()
-                let mut res = ext_item.path.clone(); // a::b
-                res.push(&ext_item.ident.name()); // c
-                res.join(path.components.iter().cloned())
-            } else {
-                parent_path.join_path(path)
-            }
+) -> Result<ModulePath, E> {
+    if let PathOrigin::Package(pkg_name) = &path.origin {
+        // the path could be either a package, of referencing an imported module alias.
+        let imported_item = imports.iter().find(|(ident, _)| *ident.name() == *pkg_name);
+
+        if let Some((_, ext_item)) = imported_item {
+            // this inline path references an imported item. Example:
+            // import a::b::c as foo; foo::bar::baz() => a::b::c::bar::baz()
+            let mut res = ext_item.path.clone(); // a::b
+            res.push(&ext_item.ident.name()); // c
+            return Ok(res.join(path.components.iter().cloned()));
         }
-        _ => parent_path.join_path(path),
     }
+    parent_path
+        .try_join_path(path)
+        .ok_or_else(|| E::TooManySuper(path.clone(), parent_path.clone()))
 }
 
 pub(crate) fn mangle_decls<'a>(
@@ -516,7 +906,10 @@ impl Resolutions {
             let module = &mut *module;
             Visit::<TypeExpression>::visit_rec_mut(&mut module.source, &mut |ty| {
                 let (ext_path, ext_id) = if let Some(path) = &ty.path {
-                    let res = resolve_inline_path(path, &module.path, &module.imports);
+                    // errors were already reported during resolution.
+                    let Ok(res) = resolve_inline_path(path, &module.path, &module.imports) else {
+                        return;
+                    };
                     (res, ty.ident.clone())
                 } else if let Some(item) = module.imports.get(&ty.ident) {
                     (item.path.clone(), item.ident.clone())
@@ -547,19 +940,49 @@ impl Resolutions {
 
     /// Mangle all declarations in all modules. Should be called after [`Self::retarget`].
     ///
+    /// Mangled names that are not valid identifiers (e.g. keywords or reserved words) or
+    /// that shadow built-in functions and types are adjusted with a suffix, see
+    /// [`FreshNames::fresh_name`].
+    ///
     /// Panics if a module is already borrowed.
     pub(crate) fn mangle(&mut self, mangler: &impl Mangler, mangle_root: bool) {
         let root_path = self.root_path().clone();
+        let mut invalid = Vec::new();
         for (path, module) in self.modules.iter_mut() {
             if mangle_root || path != &root_path {
                 let mut module = module.borrow_mut();
                 mangle_decls(&mut module.source, path, mangler);
+                invalid.extend(
+                    module
+                        .source
+                        .global_declarations
+                        .iter()
+                        .filter_map(|decl| decl.ident())
+                        .filter(|id| {
+                            let name = id.name();
+                            !is_valid_ident(&name) || builtin_ident(&name).is_some()
+                        })
+                        .cloned(),
+                );
+            }
+        }
+        if !invalid.is_empty() {
+            let mut names = FreshNames::default();
+            for module in self.modules.values() {
+                names.add(&module.borrow().source);
+            }
+            for mut id in invalid {
+                let name = names.fresh_name(&id.name());
+                id.rename(name);
             }
         }
     }
 
     /// Merge all declarations into a single module. If the `strip` flag is set, it will
     /// copy over only used declarations.
+    ///
+    /// Identical `const_assert`s are included once, e.g. when several modules assert the
+    /// same condition on a shared dependency.
     pub(crate) fn assemble(&self, strip: bool) -> TranslationUnit {
         let mut wesl = TranslationUnit::default();
         for module in self.modules() {
@@ -590,6 +1013,380 @@ impl Resolutions {
         // * include all directives used (if strip)
         // * include all directives (if not strip)
         wesl.global_directives.dedup();
+        // idents point at their declaration, so identical asserts refer to the same decls.
+        let mut const_asserts = Vec::new();
+        wesl.global_declarations.retain(|decl| match decl.node() {
+            GlobalDeclaration::ConstAssert(assert) if const_asserts.contains(assert) => false,
+            GlobalDeclaration::ConstAssert(assert) => {
+                const_asserts.push(assert.clone());
+                true
+            }
+            _ => true,
+        });
         wesl
     }
 }
+
+/// Inline paths must be resolved wherever a type or declaration name can appear.
+#[test]
+fn test_inline_paths() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "struct S { x: f32 }
+        alias A = f32;
+        const N = 4;
+        fn f(x: f32) -> f32 { return x; }"
+            .into(),
+    );
+    resolver.add_module("package::other".parse().unwrap(), "const X = 1.0;".into());
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util;
+        import super::other as o;
+        struct T {
+            @size(package::util::N * 4) s: package::util::S,
+            a: array<package::util::A, package::util::N>,
+        }
+        fn g(p: ptr<function, package::util::S>, q: util::A) -> package::util::A {
+            return bitcast<util::A>(1u) + o::X;
+        }
+        alias B = package::util::S;
+        alias C = array<package::util::A, 2>;
+        const M = package::util::N + 1;
+        @compute @workgroup_size(package::util::N)
+        fn main() {
+            var t: T;
+            var s: package::util::S;
+            let g = g(&s, 1.0) + o::X + util::f(2.0);
+            let b = B(package::util::f(1.0));
+            let c = C();
+            var a: array<package::util::S, package::util::N>;
+            const_assert package::util::N == 4;
+            for (var i = 0; i < package::util::N; i++) {
+                let v = package::util::S(package::util::f(f32(i)));
+            }
+        }"
+        .into(),
+    );
+    for lazy in [true, false] {
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+        compiler.set_options(CompileOptions {
+            lazy,
+            ..Default::default()
+        });
+        let res = compiler
+            .compile(&"package::main".parse().unwrap())
+            .unwrap()
+            .to_string();
+        assert!(
+            !res.contains("::"),
+            "unresolved inline path (lazy: {lazy}):\n{res}"
+        );
+    }
+}
+
+#[test]
+fn test_const_assert_dedupe() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::{a::f, b::g, util::N};
+        const_assert N == 4;
+        @compute @workgroup_size(1) fn main() { f(); g(); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::a".parse().unwrap(),
+        "import package::util::N; const_assert N == 4; const_assert N > 1; fn f() {}".into(),
+    );
+    resolver.add_module(
+        "package::b".parse().unwrap(),
+        "const N = 4; const_assert N == 4; fn g() {}".into(),
+    );
+    resolver.add_module("package::util".parse().unwrap(), "const N = 4;".into());
+    for lazy in [true, false] {
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+        compiler
+            .set_options(CompileOptions {
+                lazy,
+                ..Default::default()
+            })
+            .set_mangler(crate::ManglerKind::Escape);
+        let res = compiler
+            .compile(&"package::main".parse().unwrap())
+            .unwrap()
+            .to_string();
+        // the assert on the `N` of `package::b` is not a duplicate.
+        assert_eq!(res.matches("const_assert").count(), 3, "{res}");
+    }
+}
+
+#[test]
+fn test_string_path_segments() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        r#"import package::"my-shader.v2"::f;
+        import package::"util-lib" as util;
+        @compute @workgroup_size(1) fn main() { f(); util::g(); }"#
+            .into(),
+    );
+    resolver.add_module(
+        r#"package::"my-shader.v2""#.parse().unwrap(),
+        "fn f() {}".into(),
+    );
+    resolver.add_module(
+        r#"package::"util-lib""#.parse().unwrap(),
+        "fn g() {}".into(),
+    );
+    for lazy in [true, false] {
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+        compiler
+            .set_options(CompileOptions {
+                lazy,
+                ..Default::default()
+            })
+            .set_mangler(crate::ManglerKind::Escape);
+        let res = compiler
+            .compile(&"package::main".parse().unwrap())
+            .unwrap()
+            .to_string();
+        assert!(res.contains("fn package_as_7574696c2d6c6962_g()"), "{res}");
+    }
+}
+
+#[test]
+fn test_too_many_super() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module("package::util".parse().unwrap(), "const N = 4;".into());
+    resolver.add_module(
+        "package::a".parse().unwrap(),
+        "import super::super::util::N; const M = N;".into(),
+    );
+    resolver.add_module(
+        "package::b".parse().unwrap(),
+        "const M = super::super::util::N;".into(),
+    );
+    for root in ["package::a", "package::b"] {
+        for lazy in [true, false] {
+            let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+            compiler.set_options(CompileOptions {
+                lazy,
+                strip: false,
+                ..Default::default()
+            });
+            let Err(err) = compiler.compile(&root.parse().unwrap()) else {
+                panic!("`{root}` should fail to compile (lazy: {lazy})");
+            };
+            let err = Diagnostic::from(err);
+            assert!(
+                matches!(*err.error, Error::ImportError(E::TooManySuper(_, _))),
+                "{err}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_similar_decls() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "struct Light { color: vec3f }
+        fn light_color() -> vec3f { return vec3f(); }
+        const LIGHTS = 4;"
+            .into(),
+    );
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::light;
+        @fragment fn main() { let l = light(); }"
+            .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+    compiler.set_options(CompileOptions::default());
+    let Err(err) = compiler.compile(&"package::main".parse().unwrap()) else {
+        panic!("expected a missing declaration error");
+    };
+    let err = Diagnostic::from(err);
+    let Error::ImportError(E::MissingDecl(_, name, similar)) = &*err.error else {
+        panic!("expected a missing declaration error, got {err}");
+    };
+    assert_eq!(name, "light");
+    let similar = similar
+        .iter()
+        .map(|decl| (decl.name.as_str(), decl.kind))
+        .collect_vec();
+    assert_eq!(
+        similar,
+        [("Light", DeclKind::Struct), ("LIGHTS", DeclKind::Const)]
+    );
+
+    // the similar declarations are labeled in their module.
+    assert_eq!(err.detail.labels.len(), 2);
+    compiler.use_sourcemap(true);
+    let Err(err) = compiler.compile(&"package::main".parse().unwrap()) else {
+        panic!("expected a missing declaration error");
+    };
+    let err = Diagnostic::from(err);
+    let label = &err.detail.labels[0];
+    assert_eq!(label.module_path, Some("package::util".parse().unwrap()));
+    assert_eq!(
+        &label.source.as_ref().unwrap()[label.span.range()],
+        "struct Light { color: vec3f }"
+    );
+    let err = err.to_string();
+    assert!(err.contains("struct `Light` declared here"));
+    assert!(err.contains("package::util"));
+}
+
+#[test]
+fn test_wrong_kind() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "struct Light { color: vec3f }
+        fn shade() -> vec3f { return vec3f(); }
+        const LIGHTS = 4;"
+            .into(),
+    );
+    let main = "import package::util::{Light, shade, LIGHTS};
+        @fragment fn main() { let l = Light(shade()); let n = LIGHTS(); }";
+    resolver.add_module("package::main".parse().unwrap(), main.into());
+    resolver.add_module(
+        "package::other".parse().unwrap(),
+        "import package::util::shade;
+        fn f(x: shade) {}
+        @compute @workgroup_size(1) fn main() { f(vec3f()); }"
+            .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+    compiler.set_options(CompileOptions::default());
+    let Err(err) = compiler.compile(&"package::main".parse().unwrap()) else {
+        panic!("`LIGHTS` is not callable");
+    };
+    let err = Diagnostic::from(err);
+    let Error::ImportError(E::WrongKind(path, decl, expected)) = &*err.error else {
+        panic!("expected a wrong kind error, got {err}");
+    };
+    assert_eq!(*path, "package::util".parse().unwrap());
+    assert_eq!((decl.name.as_str(), decl.kind), ("LIGHTS", DeclKind::Const));
+    assert_eq!(*expected, "function");
+    // the error is reported at the use site, the declaration is labeled.
+    assert_eq!(
+        err.detail.module_path,
+        Some("package::main".parse().unwrap())
+    );
+    assert_eq!(&main[err.detail.span.unwrap().range()], "LIGHTS()");
+    assert_eq!(err.detail.labels.len(), 1);
+
+    let Err(err) = compiler.compile(&"package::other".parse().unwrap()) else {
+        panic!("`shade` is not a type");
+    };
+    let err = err.to_string();
+    assert!(
+        err.contains("`shade` in module `package::util` is a function, expected a type"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_provenance() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f;
+        fn unused() {}
+        @fragment fn main() -> @location(0) vec4f { return vec4f(f()); }"
+            .into(),
+    );
+    let util = "const x = 1.0; fn f() -> f32 { return x; }";
+    resolver.add_module("package::util".parse().unwrap(), util.into());
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let res = compiler
+        .compile_result(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let f = res
+        .syntax
+        .global_declarations
+        .iter()
+        .filter_map(|decl| decl.ident())
+        .find(|id| *id.name() != "main" && id.name().ends_with("f"))
+        .unwrap();
+    let prov = res.provenance(f).unwrap();
+    assert_eq!(prov.module, "package::util".parse().unwrap());
+    assert_eq!(prov.name, "f");
+    assert_eq!(&util[prov.span.range()], "fn f() -> f32 { return x; }");
+    assert_eq!(res.provenance_by_name("main").unwrap().name, "main");
+    // provenances must not prevent stripping
+    assert!(res.provenance_by_name("unused").is_none());
+    assert!(!res.syntax.to_string().contains("unused"));
+}
+
+#[test]
+fn test_mangle_reserved_names() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    struct KeywordMangler;
+    impl Mangler for KeywordMangler {
+        fn mangle(&self, _path: &ModulePath, item: &str) -> String {
+            match item {
+                "g" => "loop".to_string(),
+                "h" => "__h".to_string(),
+                _ => item.to_string(),
+            }
+        }
+    }
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f;
+        @fragment fn main() -> @location(0) vec4f { return vec4f(f(), min(1.0, 2.0), 0.0, 1.0); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn min() -> f32 { return 0.0; }
+        fn g() -> f32 { return min(); }
+        fn h() -> f32 { return g(); }
+        fn f() -> f32 { return h(); }"
+            .into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .set_custom_mangler(KeywordMangler);
+    let wgsl = compiler
+        .compile(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"))
+        .to_string();
+    assert!(wgsl.contains("fn min_1() -> f32"), "{wgsl}");
+    assert!(wgsl.contains("return min_1();"), "{wgsl}");
+    assert!(wgsl.contains("min(1.0, 2.0)"), "{wgsl}");
+    assert!(wgsl.contains("fn loop_1() -> f32"), "{wgsl}");
+    assert!(
+        wgsl.contains("fn h() -> f32 {\n    return loop_1();"),
+        "{wgsl}"
+    );
+    wgsl_parse::parse_str(&wgsl).unwrap();
+
+    let mut id = Ident::new("a".to_string());
+    assert!(id.try_rename("let".to_string()).is_err());
+    assert!(id.try_rename("__b".to_string()).is_err());
+    assert!(id.try_rename("a b".to_string()).is_err());
+    assert_eq!(*id.name(), "a");
+    id.try_rename("b".to_string()).unwrap();
+    assert_eq!(*id.name(), "b");
+}
@@ -0,0 +1,315 @@
+//! Import maps: rewrite module path prefixes at resolution time, e.g. to use a vendored
+//! fork of a package.
+
+use std::{borrow::Cow, cell::RefCell, fmt::Display, path::PathBuf};
+
+use wgsl_parse::syntax::{ModulePath, PathOrigin, TranslationUnit};
+
+use crate::{FileResolver, ResolveError, Resolver};
+
+/// The target of an [`ImportMapping`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ImportTarget {
+    /// Another module path prefix, e.g. `package::vendor::pbr`. It is resolved by the
+    /// inner resolver.
+    Module(String),
+    /// A directory. The module `prefix::a::b` is the file `<dir>/a/b.wesl`, and the
+    /// module `prefix` is the file `<dir>.wesl`, like with a [`FileResolver`].
+    Path(PathBuf),
+}
+
+impl Display for ImportTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportTarget::Module(path) => write!(f, "{path}"),
+            ImportTarget::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A rewrite of the module paths starting with a prefix. See [`ImportMap`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImportMapping {
+    /// The module path prefix to rewrite, e.g. `official_pbr`.
+    pub prefix: String,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub target: ImportTarget,
+}
+
+/// Rewrites of module path prefixes to other prefixes or to directories.
+///
+/// With the `serde` feature, it can be read from a config file, e.g. in JSON:
+/// ```json
+/// { "mappings": [
+///     { "prefix": "official_pbr", "module": "package::vendor::pbr" },
+///     { "prefix": "noise", "path": "third_party/noise" }
+/// ] }
+/// ```
+///
+/// Apply it with an [`ImportMapResolver`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImportMap {
+    pub mappings: Vec<ImportMapping>,
+}
+
+impl ImportMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mapping. Prefer [`Self::map_module`] or [`Self::map_path`].
+    pub fn add_mapping(&mut self, prefix: impl ToString, target: ImportTarget) -> &mut Self {
+        self.mappings.push(ImportMapping {
+            prefix: prefix.to_string(),
+            target,
+        });
+        self
+    }
+
+    /// Redirect the module paths starting with `prefix` to the module path prefix `to`.
+    pub fn map_module(&mut self, prefix: impl ToString, to: impl ToString) -> &mut Self {
+        self.add_mapping(prefix, ImportTarget::Module(to.to_string()))
+    }
+
+    /// Redirect the module paths starting with `prefix` to files in the directory `to`.
+    pub fn map_path(&mut self, prefix: impl ToString, to: impl Into<PathBuf>) -> &mut Self {
+        self.add_mapping(prefix, ImportTarget::Path(to.into()))
+    }
+}
+
+/// A mapping applied by an [`ImportMapResolver`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppliedMapping {
+    /// The requested module path.
+    pub from: ModulePath,
+    /// The module path or file it was redirected to.
+    pub to: String,
+    /// The prefix of the mapping that was applied.
+    pub prefix: ModulePath,
+}
+
+impl Display for AppliedMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` -> `{}` (import map prefix `{}`)",
+            self.from, self.to, self.prefix
+        )
+    }
+}
+
+enum Target {
+    Module(ModulePath),
+    Path(FileResolver),
+}
+
+/// Where a module path is redirected to.
+enum Route<'a> {
+    Module(ModulePath),
+    File(&'a FileResolver, ModulePath),
+}
+
+/// A resolver that applies an [`ImportMap`] before resolving modules with an inner
+/// resolver.
+///
+/// The mapping with the longest matching prefix is applied. Rewritten module paths are
+/// not mapped again. The mappings applied so far are listed by [`Self::applied`], and
+/// resolution errors of rewritten modules mention the mapping.
+///
+/// This resolver is not thread-safe (not [`Send`] or [`Sync`]).
+pub struct ImportMapResolver<R: Resolver> {
+    resolver: R,
+    mappings: Vec<(ModulePath, Target)>,
+    applied: RefCell<Vec<AppliedMapping>>,
+}
+
+impl<R: Resolver> ImportMapResolver<R> {
+    /// Create a new resolver.
+    ///
+    /// Fails with [`ResolveError::InvalidImportMap`] if a prefix or a target module path
+    /// is not a valid module path, or is relative.
+    pub fn new(resolver: R, map: &ImportMap) -> Result<Self, ResolveError> {
+        let parse = |path: &str| -> Result<ModulePath, ResolveError> {
+            let invalid = |reason: String| ResolveError::InvalidImportMap(path.to_string(), reason);
+            let parsed = path
+                .parse::<ModulePath>()
+                .map_err(|e| invalid(e.to_string()))?;
+            if matches!(parsed.origin, PathOrigin::Relative(_)) {
+                return Err(invalid(
+                    "relative module paths cannot be mapped".to_string(),
+                ));
+            }
+            Ok(parsed)
+        };
+        let mappings = map
+            .mappings
+            .iter()
+            .map(|mapping| {
+                let target = match &mapping.target {
+                    ImportTarget::Module(path) => Target::Module(parse(path)?),
+                    ImportTarget::Path(dir) => Target::Path(FileResolver::new(dir)),
+                };
+                Ok((parse(&mapping.prefix)?, target))
+            })
+            .collect::<Result<_, ResolveError>>()?;
+        Ok(Self {
+            resolver,
+            mappings,
+            applied: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// The inner resolver.
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
+    /// The mappings applied to the modules resolved so far, in order of first resolution.
+    pub fn applied(&self) -> Vec<AppliedMapping> {
+        self.applied.borrow().clone()
+    }
+
+    fn route(&self, path: &ModulePath) -> Option<(&ModulePath, Route<'_>)> {
+        let (prefix, target) = self
+            .mappings
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.components.len())?;
+        let suffix = &path.components[prefix.components.len()..];
+        let route = match target {
+            Target::Module(to) => {
+                let mut to = to.clone();
+                to.components.extend_from_slice(suffix);
+                Route::Module(to)
+            }
+            Target::Path(files) => Route::File(
+                files,
+                ModulePath::new(PathOrigin::Absolute, suffix.to_vec()),
+            ),
+        };
+        Some((prefix, route))
+    }
+
+    fn record(&self, path: &ModulePath, prefix: &ModulePath, route: &Route) {
+        if self.applied.borrow().iter().any(|m| m.from == *path) {
+            return;
+        }
+        let to = match route {
+            Route::Module(to) => to.to_string(),
+            Route::File(files, suffix) => files
+                .fs_path(suffix)
+                .map(|file| file.display().to_string())
+                .unwrap_or_else(|| suffix.to_string()),
+        };
+        self.applied.borrow_mut().push(AppliedMapping {
+            from: path.clone(),
+            to,
+            prefix: prefix.clone(),
+        });
+    }
+
+    /// Resolve a module with the mapping applied, and mention the mapping in errors.
+    fn resolve<'a, T>(
+        &'a self,
+        path: &ModulePath,
+        resolve: impl FnOnce(&'a dyn Resolver, &ModulePath) -> Result<T, ResolveError>,
+    ) -> Result<T, ResolveError> {
+        let Some((prefix, route)) = self.route(path) else {
+            return resolve(&self.resolver, path);
+        };
+        let res = match &route {
+            Route::Module(to) => resolve(&self.resolver, to),
+            Route::File(files, suffix) => resolve(*files, suffix),
+        };
+        // the compiler also probes paths that are not modules, e.g. package roots.
+        if res.is_ok() {
+            self.record(path, prefix, &route);
+        }
+        res.map_err(|e| {
+            let note = |msg: String| format!("{msg}, `{path}` is mapped by the import map");
+            match e {
+                ResolveError::FileNotFound(file, msg) => {
+                    ResolveError::FileNotFound(file, note(msg))
+                }
+                ResolveError::ModuleNotFound(module, msg) => {
+                    ResolveError::ModuleNotFound(module, note(msg))
+                }
+                e => e,
+            }
+        })
+    }
+}
+
+impl<R: Resolver> Resolver for ImportMapResolver<R> {
+    fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+        self.resolve(path, |resolver, path| resolver.resolve_source(path))
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        self.resolve(path, |resolver, path| resolver.resolve_module(path))
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        match self.route(path) {
+            Some((_, Route::Module(to))) => self.resolver.display_name(&to),
+            Some((_, Route::File(files, suffix))) => files.display_name(&suffix),
+            None => self.resolver.display_name(path),
+        }
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        match self.route(path) {
+            Some((_, Route::Module(to))) => self.resolver.fs_path(&to),
+            Some((_, Route::File(files, suffix))) => files.fs_path(&suffix),
+            None => self.resolver.fs_path(path),
+        }
+    }
+}
+
+#[test]
+fn test_import_map() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import official_pbr::lights::shade;
+        @fragment fn main() -> @location(0) vec4f { return shade(); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::vendor::pbr::lights".parse().unwrap(),
+        "fn shade() -> vec4f { return vec4f(1.0); }".into(),
+    );
+    let mut map = ImportMap::new();
+    map.map_module("official_pbr", "package::vendor::pbr")
+        .map_module("official_pbr::missing", "package::nowhere");
+    let resolver = ImportMapResolver::new(resolver, &map).unwrap();
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let root = "package::main".parse().unwrap();
+    let res = compiler.compile(&root).unwrap_or_else(|e| panic!("{e}"));
+    assert!(res.to_string().contains("vec4f(1.0)"));
+    let applied = compiler.resolver().applied();
+    assert_eq!(applied.len(), 1);
+    assert_eq!(applied[0].from.to_string(), "official_pbr::lights");
+    assert_eq!(applied[0].to, "package::vendor::pbr::lights");
+
+    // the longest prefix is applied, and errors mention the mapping.
+    let err = compiler
+        .resolver()
+        .resolve_source(&"official_pbr::missing::x".parse().unwrap())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("package::nowhere::x"), "{err}");
+    assert!(err.contains("import map"), "{err}");
+
+    let mut invalid = ImportMap::new();
+    invalid.map_module("super::a", "package::b");
+    assert!(ImportMapResolver::new(VirtualResolver::new(), &invalid).is_err());
+}
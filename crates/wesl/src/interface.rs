@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{
+    AssignmentOperator, Attribute, Expression, ExpressionNode, GlobalDeclaration, Ident,
+    InterpolateAttribute, InterpolationSampling, InterpolationType, Statement, StatementNode,
+    TranslationUnit, TypeExpression,
+};
+
+use crate::{
+    Binding, Bindings, CompileResult, EntryPoint, EntryPointStage, Error, InterfaceVariable,
+    SyntaxUtil, ValidateError, visit::Visit,
+};
+
+/// A mismatch between the interfaces of the vertex and fragment stages of a render
+/// pipeline. See [`PipelineReport`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum InterfaceError {
+    #[error("entry point `{0}` is not a {1:?} shader")]
+    WrongStage(String, EntryPointStage),
+    #[error("fragment input `{1}` at `@location({0})` is not written by the vertex stage")]
+    MissingOutput(u32, String),
+    #[error("`@location({0})` is a `{1}` vertex output but a `{2}` fragment input")]
+    TypeMismatch(u32, String, String),
+    #[error(
+        "`@location({0})` has interpolation `{1}` in the vertex stage but `{2}` in the fragment stage"
+    )]
+    InterpolationMismatch(u32, String, String),
+    #[error(
+        "`@group({0}) @binding({1})` is bound to `{2}` in the vertex stage but `{3}` in the fragment stage"
+    )]
+    BindingMismatch(u32, u32, String, String),
+}
+
+/// The result of the compatibility check of a vertex and a fragment entry point.
+///
+/// See [`PipelineReport::new`] and [`check_pipeline`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PipelineReport {
+    /// The interface mismatches. The pipeline is compatible if there are none.
+    pub errors: Vec<InterfaceError>,
+    /// The vertex output locations not read by the fragment stage. They are allowed,
+    /// but may indicate dead varyings.
+    pub unused_outputs: Vec<u32>,
+    /// The `(group, binding)` pairs used by both stages.
+    pub shared_bindings: Vec<(u32, u32)>,
+}
+
+impl PipelineReport {
+    /// Check that the outputs of the `vertex` entry point match the inputs of the
+    /// `fragment` entry point (locations, types and interpolation), and that the resource
+    /// bindings used by both stages agree.
+    ///
+    /// Types are compared after resolving type aliases declared in `vertex_wesl` and
+    /// `fragment_wesl`, the translation units declaring the entry points.
+    pub fn new(
+        vertex: &EntryPoint,
+        vertex_bindings: &Bindings,
+        vertex_wesl: &TranslationUnit,
+        fragment: &EntryPoint,
+        fragment_bindings: &Bindings,
+        fragment_wesl: &TranslationUnit,
+    ) -> Self {
+        let mut report = Self::default();
+        if vertex.stage != EntryPointStage::Vertex {
+            let err = InterfaceError::WrongStage(vertex.name.clone(), EntryPointStage::Vertex);
+            report.errors.push(err);
+        }
+        if fragment.stage != EntryPointStage::Fragment {
+            let err = InterfaceError::WrongStage(fragment.name.clone(), EntryPointStage::Fragment);
+            report.errors.push(err);
+        }
+
+        let outputs = vertex
+            .outputs
+            .iter()
+            .filter_map(|var| Some((var.location?, var)))
+            .collect_vec();
+
+        for input in &fragment.inputs {
+            let Some(location) = input.location else {
+                continue;
+            };
+            let Some((_, output)) = outputs.iter().find(|(loc, _)| *loc == location) else {
+                let name = input.name.clone().unwrap_or_default();
+                report
+                    .errors
+                    .push(InterfaceError::MissingOutput(location, name));
+                continue;
+            };
+            let out_ty = canonical_ty(&output.ty, vertex_wesl);
+            let in_ty = canonical_ty(&input.ty, fragment_wesl);
+            if out_ty != in_ty {
+                let err = InterfaceError::TypeMismatch(location, out_ty, in_ty);
+                report.errors.push(err);
+            }
+            let out_interp = interpolation(output);
+            let in_interp = interpolation(input);
+            if out_interp != in_interp {
+                let err = InterfaceError::InterpolationMismatch(
+                    location,
+                    fmt_interpolation(out_interp),
+                    fmt_interpolation(in_interp),
+                );
+                report.errors.push(err);
+            }
+        }
+
+        report.unused_outputs = outputs
+            .iter()
+            .map(|(loc, _)| *loc)
+            .filter(|loc| !fragment.inputs.iter().any(|var| var.location == Some(*loc)))
+            .collect();
+
+        for vb in vertex_bindings.iter() {
+            let (Some(group), Some(binding)) = (vb.group, vb.binding) else {
+                continue;
+            };
+            let Some(fb) = fragment_bindings.get_binding(group, binding) else {
+                continue;
+            };
+            report.shared_bindings.push((group, binding));
+            let vb_str = fmt_binding(vb, vertex_wesl);
+            let fb_str = fmt_binding(fb, fragment_wesl);
+            if vb_str != fb_str {
+                let err = InterfaceError::BindingMismatch(group, binding, vb_str, fb_str);
+                report.errors.push(err);
+            }
+        }
+
+        report
+    }
+
+    /// Whether the stages are compatible.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Check the compatibility of a vertex and a fragment entry point, possibly from
+/// different compilation results. See [`PipelineReport::new`].
+///
+/// Fails if one of the entry points does not exist.
+pub fn check_pipeline(
+    vertex: (&CompileResult, &str),
+    fragment: (&CompileResult, &str),
+) -> Result<PipelineReport, Error> {
+    let entry_point = |(res, name): (&CompileResult, &str)| {
+        res.entry_points()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ValidateError::MissingEntryPoint(name.to_string()))
+    };
+    let vs = entry_point(vertex)?;
+    let fs = entry_point(fragment)?;
+    Ok(PipelineReport::new(
+        &vs,
+        &vertex.0.bindings(),
+        &vertex.0.syntax,
+        &fs,
+        &fragment.0.bindings(),
+        &fragment.0.syntax,
+    ))
+}
+
+/// The vertex and fragment stages of a render pipeline, compiled by
+/// [`crate::Wesl::compile_pipeline`].
+#[derive(Clone)]
+pub struct PipelineResult {
+    pub vertex: CompileResult,
+    pub fragment: CompileResult,
+    /// The locations of the varyings removed by [`remove_dead_varyings`].
+    pub removed_varyings: Vec<u32>,
+}
+
+/// Remove the vertex outputs that are never read by the fragment stage, from both
+/// interfaces. Returns the locations of the removed varyings.
+///
+/// A vertex output is dead if the fragment stage has no input at its location, or if the
+/// input is never read. Outputs are members of the IO struct returned by the `vertex`
+/// entry point: the member is removed, along with the assignments to it and the
+/// corresponding arguments of the struct constructors. Fragment inputs are removed
+/// similarly, or are parameters of the `fragment` entry point.
+///
+/// The analysis is conservative: a member is kept if it is accessed other than by an
+/// assignment to a variable of the struct type, or if the struct is part of the interface
+/// of another entry point. The removed expressions should have no side effects.
+///
+/// The vertex and fragment stages must be in different translation units, e.g. two
+/// compilation results, even if they come from the same root module. Fails if the
+/// interfaces are not compatible, see [`PipelineReport`].
+pub fn remove_dead_varyings(
+    vertex: (&mut TranslationUnit, &str),
+    fragment: (&mut TranslationUnit, &str),
+) -> Result<Vec<u32>, Error> {
+    let (vertex_wesl, vertex_name) = vertex;
+    let (fragment_wesl, fragment_name) = fragment;
+    let entry_point = |wesl: &TranslationUnit, name: &str| {
+        crate::EntryPoints::from_syntax(wesl)
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ValidateError::MissingEntryPoint(name.to_string()))
+    };
+    let vs = entry_point(vertex_wesl, vertex_name)?;
+    let fs = entry_point(fragment_wesl, fragment_name)?;
+    let report = PipelineReport::new(
+        &vs,
+        &Bindings::from_syntax(vertex_wesl),
+        vertex_wesl,
+        &fs,
+        &Bindings::from_syntax(fragment_wesl),
+        fragment_wesl,
+    );
+    if let Some(err) = report.errors.into_iter().next() {
+        return Err(err.into());
+    }
+    let entry_points = [vertex_name, fragment_name];
+
+    let Some(strukt) = return_struct(vertex_wesl, vertex_name) else {
+        return Ok(Vec::new());
+    };
+    let mut removed = Vec::new();
+    for output in &vs.outputs {
+        let (Some(location), Some(name)) = (output.location, &output.name) else {
+            continue;
+        };
+        let Some(vertex_removal) = MemberRemoval::new(vertex_wesl, &strukt, name, &entry_points)
+        else {
+            continue;
+        };
+        let input = fs.inputs.iter().find(|var| var.location == Some(location));
+        // the fragment input, if any, is removed if it is never read.
+        if let Some(input_name) = input.and_then(|var| var.name.as_ref()) {
+            match io_param(fragment_wesl, fragment_name, input_name) {
+                Some(IoParam::Param(ident))
+                    if !is_param_read(fragment_wesl, fragment_name, &ident) =>
+                {
+                    remove_param(fragment_wesl, fragment_name, &ident);
+                }
+                Some(IoParam::Member(input_strukt)) => {
+                    let removal =
+                        MemberRemoval::new(fragment_wesl, &input_strukt, input_name, &entry_points);
+                    match removal {
+                        Some(removal) => removal.apply(fragment_wesl),
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            }
+        }
+        vertex_removal.apply(vertex_wesl);
+        removed.push(location);
+    }
+    Ok(removed)
+}
+
+/// The struct returned by an entry point.
+fn return_struct(wesl: &TranslationUnit, entry_point: &str) -> Option<Ident> {
+    let func = wesl.functions().find(|f| *f.ident.name() == entry_point)?;
+    let ty = wesl.canonical_type(func.return_type.as_ref()?)?;
+    match wesl.find_decl_by_ident(&ty.ident)?.node() {
+        GlobalDeclaration::Struct(_) => Some(ty.ident),
+        _ => None,
+    }
+}
+
+/// Where an entry point input is declared.
+enum IoParam {
+    /// A parameter of the entry point.
+    Param(Ident),
+    /// A member of this struct, the type of a parameter.
+    Member(Ident),
+}
+
+fn io_param(wesl: &TranslationUnit, entry_point: &str, name: &str) -> Option<IoParam> {
+    let func = wesl.functions().find(|f| *f.ident.name() == entry_point)?;
+    let is_location =
+        |attr: &wgsl_parse::syntax::AttributeNode| matches!(attr.node(), Attribute::Location(_));
+    for param in &func.parameters {
+        if *param.ident.name() == name && param.attributes.iter().any(is_location) {
+            return Some(IoParam::Param(param.ident.clone()));
+        }
+        let Some(ty) = wesl.canonical_type(&param.ty) else {
+            continue;
+        };
+        let decl = wesl.find_decl_by_ident(&ty.ident).map(|decl| decl.node());
+        if let Some(GlobalDeclaration::Struct(decl)) = decl {
+            if decl
+                .members
+                .iter()
+                .any(|m| *m.ident.name() == name && m.attributes.iter().any(is_location))
+            {
+                return Some(IoParam::Member(ty.ident));
+            }
+        }
+    }
+    None
+}
+
+fn is_param_read(wesl: &TranslationUnit, entry_point: &str, param: &Ident) -> bool {
+    let Some(func) = wesl.functions().find(|f| *f.ident.name() == entry_point) else {
+        return true;
+    };
+    let mut read = false;
+    Visit::<TypeExpression>::visit_rec(func, &mut |ty| read |= ty.ident == *param);
+    read
+}
+
+fn remove_param(wesl: &mut TranslationUnit, entry_point: &str, param: &Ident) {
+    for decl in &mut wesl.global_declarations {
+        if let GlobalDeclaration::Function(func) = decl.node_mut() {
+            if *func.ident.name() == entry_point {
+                func.parameters.retain(|p| p.ident != *param);
+            }
+        }
+    }
+}
+
+/// The removal of a member of an IO struct. See [`remove_dead_varyings`].
+struct MemberRemoval {
+    strukt: Ident,
+    member: String,
+    index: usize,
+    count: usize,
+    /// The struct and its aliases.
+    names: Vec<Ident>,
+    /// The variables and parameters whose type is the struct.
+    vars: Vec<Ident>,
+}
+
+impl MemberRemoval {
+    /// Check that the member can be removed: it is only accessed by assignments to
+    /// variables of the struct type, and the struct is not in the interface of an entry
+    /// point other than `entry_points`.
+    fn new(
+        wesl: &TranslationUnit,
+        strukt: &Ident,
+        member: &str,
+        entry_points: &[&str],
+    ) -> Option<Self> {
+        let GlobalDeclaration::Struct(decl) = wesl.find_decl_by_ident(strukt)?.node() else {
+            return None;
+        };
+        let index = decl
+            .members
+            .iter()
+            .position(|m| *m.ident.name() == member)?;
+        let count = decl.members.len();
+        let is_struct = |ty: &TypeExpression| {
+            wesl.canonical_type(ty)
+                .is_some_and(|ty| ty.ident == *strukt)
+        };
+
+        let other_interfaces = wesl
+            .entry_point_functions()
+            .filter(|f| !entry_points.contains(&f.ident.name().as_str()))
+            .any(|f| {
+                f.parameters.iter().any(|p| is_struct(&p.ty))
+                    || f.return_type.as_ref().is_some_and(is_struct)
+            });
+        if other_interfaces {
+            return None;
+        }
+
+        let names = wesl
+            .global_declarations
+            .iter()
+            .filter_map(|decl| match decl.node() {
+                GlobalDeclaration::Struct(s) if s.ident == *strukt => Some(s.ident.clone()),
+                GlobalDeclaration::TypeAlias(a) if is_struct(&a.ty) => Some(a.ident.clone()),
+                _ => None,
+            })
+            .collect_vec();
+
+        // the type of every variable and parameter, if it is known.
+        let mut types = HashMap::new();
+        for func in wesl.functions() {
+            for p in &func.parameters {
+                types.insert(p.ident.clone(), is_struct(&p.ty));
+            }
+        }
+        let mut decl_type = |decl: &wgsl_parse::syntax::Declaration| {
+            let is_struct = match (&decl.ty, decl.initializer.as_ref().map(|e| e.node())) {
+                (Some(ty), _) => is_struct(ty),
+                // the type of a constructed value is known, not of a function result.
+                (None, Some(Expression::FunctionCall(call)))
+                    if !matches!(
+                        wesl.find_decl_by_ident(&call.ty.ident).map(|d| d.node()),
+                        Some(GlobalDeclaration::Function(_))
+                    ) =>
+                {
+                    names.contains(&call.ty.ident)
+                }
+                _ => return,
+            };
+            types.insert(decl.ident.clone(), is_struct);
+        };
+        wesl.declarations().for_each(&mut decl_type);
+        each_stat(Visit::<StatementNode>::visit(wesl), &mut |stmt| {
+            if let Statement::Declaration(decl) = stmt {
+                decl_type(decl);
+            }
+        });
+        let vars = types
+            .iter()
+            .filter(|(_, is_struct)| **is_struct)
+            .map(|(ident, _)| ident.clone())
+            .collect_vec();
+
+        let removal = Self {
+            strukt: strukt.clone(),
+            member: member.to_string(),
+            index,
+            count,
+            names,
+            vars,
+        };
+
+        let mut accesses = 0;
+        let mut valid = true;
+        each_expr(
+            Visit::<ExpressionNode>::visit(wesl),
+            &mut |expr| match expr {
+                Expression::NamedComponent(e) if *e.component.name() == member => {
+                    let other_type = matches!(
+                        e.base.node(),
+                        Expression::TypeOrIdentifier(ty) if types.get(&ty.ident) == Some(&false)
+                    );
+                    if !other_type {
+                        accesses += 1;
+                    }
+                }
+                Expression::FunctionCall(call) if removal.names.contains(&call.ty.ident) => {
+                    valid &= call.arguments.is_empty() || call.arguments.len() == count;
+                }
+                _ => {}
+            },
+        );
+        let mut writes = 0;
+        each_stat(Visit::<StatementNode>::visit(wesl), &mut |stmt| {
+            if removal.is_write(stmt) {
+                writes += 1;
+            }
+        });
+        (valid && accesses == writes).then_some(removal)
+    }
+
+    /// Whether the statement assigns the member of a variable of the struct type.
+    fn is_write(&self, stmt: &Statement) -> bool {
+        let Statement::Assignment(stmt) = stmt else {
+            return false;
+        };
+        let Expression::NamedComponent(lhs) = stmt.lhs.node() else {
+            return false;
+        };
+        stmt.operator == AssignmentOperator::Equal
+            && *lhs.component.name() == self.member
+            && matches!(lhs.base.node(), Expression::TypeOrIdentifier(ty) if self.vars.contains(&ty.ident))
+    }
+
+    fn apply(&self, wesl: &mut TranslationUnit) {
+        for decl in &mut wesl.global_declarations {
+            if let GlobalDeclaration::Struct(decl) = decl.node_mut() {
+                if decl.ident == self.strukt {
+                    decl.members.remove(self.index);
+                }
+            }
+        }
+        each_expr_mut(Visit::<ExpressionNode>::visit_mut(wesl), &mut |expr| {
+            if let Expression::FunctionCall(call) = expr {
+                if self.names.contains(&call.ty.ident) && call.arguments.len() == self.count {
+                    call.arguments.remove(self.index);
+                }
+            }
+        });
+        each_stat_mut(Visit::<StatementNode>::visit_mut(wesl), &mut |stmt| {
+            if self.is_write(stmt) {
+                *stmt = Statement::Void;
+            }
+        });
+    }
+}
+
+fn each_stat<'a>(stats: impl Iterator<Item = &'a StatementNode>, f: &mut impl FnMut(&Statement)) {
+    for stat in stats {
+        f(stat.node());
+        each_stat(Visit::<StatementNode>::visit(stat.node()), f);
+    }
+}
+
+fn each_stat_mut<'a>(
+    stats: impl Iterator<Item = &'a mut StatementNode>,
+    f: &mut impl FnMut(&mut Statement),
+) {
+    for stat in stats {
+        f(stat.node_mut());
+        each_stat_mut(Visit::<StatementNode>::visit_mut(stat.node_mut()), f);
+    }
+}
+
+fn each_expr<'a>(exprs: impl Iterator<Item = &'a ExpressionNode>, f: &mut impl FnMut(&Expression)) {
+    for expr in exprs {
+        f(expr.node());
+        each_expr(Visit::<ExpressionNode>::visit(expr.node()), f);
+    }
+}
+
+fn each_expr_mut<'a>(
+    exprs: impl Iterator<Item = &'a mut ExpressionNode>,
+    f: &mut impl FnMut(&mut Expression),
+) {
+    for expr in exprs {
+        f(expr.node_mut());
+        each_expr_mut(Visit::<ExpressionNode>::visit_mut(expr.node_mut()), f);
+    }
+}
+
+/// The interpolation of a user-defined IO, with defaults made explicit.
+fn interpolation(var: &InterfaceVariable) -> (InterpolationType, InterpolationSampling) {
+    match &var.interpolate {
+        Some(InterpolateAttribute { ty, sampling }) => {
+            let sampling = sampling.unwrap_or(match ty {
+                InterpolationType::Flat => InterpolationSampling::First,
+                _ => InterpolationSampling::Center,
+            });
+            (*ty, sampling)
+        }
+        None => (
+            InterpolationType::Perspective,
+            InterpolationSampling::Center,
+        ),
+    }
+}
+
+fn fmt_interpolation((ty, sampling): (InterpolationType, InterpolationSampling)) -> String {
+    format!("{ty}, {sampling}")
+}
+
+fn fmt_binding(binding: &Binding, wesl: &TranslationUnit) -> String {
+    format!(
+        "var<{}, {}> {}",
+        binding.address_space,
+        binding.access_mode,
+        canonical_ty(&binding.ty, wesl)
+    )
+}
+
+/// Format a type with type aliases and predeclared aliases (e.g. `vec4f`) resolved.
+fn canonical_ty(ty: &TypeExpression, wesl: &TranslationUnit) -> String {
+    wesl.canonical_type(ty)
+        .unwrap_or_else(|| ty.clone())
+        .to_string()
+}
+
+#[test]
+fn test_pipeline_report() {
+    let wesl: TranslationUnit = "
+        alias Color = vec4<f32>;
+        struct VsOut {
+            @builtin(position) pos: vec4f,
+            @location(0) color: Color,
+            @location(1) @interpolate(flat) id: u32,
+            @location(2) uv: vec2f,
+        }
+        @group(0) @binding(0) var<uniform> scale: f32;
+        @vertex fn vs() -> VsOut { return VsOut(); }"
+        .parse()
+        .unwrap();
+    let ok: TranslationUnit = "
+        @group(0) @binding(0) var<uniform> scale: f32;
+        @fragment fn fs(@location(0) color: vec4f, @location(1) @interpolate(flat, first) id: u32) -> @location(0) vec4f {
+            return color * scale;
+        }"
+    .parse()
+    .unwrap();
+    let bad: TranslationUnit = "
+        @group(0) @binding(0) var<uniform> scale: vec2f;
+        @fragment fn fs(@location(0) color: vec3f, @location(1) id: u32, @location(3) x: f32) -> @location(0) vec4f {
+            return vec4f(color, 1.0);
+        }"
+    .parse()
+    .unwrap();
+
+    let vs = crate::EntryPoints::from_syntax(&wesl);
+    let vs = vs.get("vs").unwrap();
+    let vs_bindings = Bindings::from_syntax(&wesl);
+
+    let fs = crate::EntryPoints::from_syntax(&ok);
+    let fs = fs.get("fs").unwrap();
+    let report = PipelineReport::new(
+        vs,
+        &vs_bindings,
+        &wesl,
+        fs,
+        &Bindings::from_syntax(&ok),
+        &ok,
+    );
+    assert!(report.is_ok(), "{:?}", report.errors);
+    assert_eq!(report.unused_outputs, [2]);
+    assert_eq!(report.shared_bindings, [(0, 0)]);
+
+    let fs = crate::EntryPoints::from_syntax(&bad);
+    let fs = fs.get("fs").unwrap();
+    let report = PipelineReport::new(
+        vs,
+        &vs_bindings,
+        &wesl,
+        fs,
+        &Bindings::from_syntax(&bad),
+        &bad,
+    );
+    assert!(matches!(
+        report.errors.as_slice(),
+        [
+            InterfaceError::TypeMismatch(0, _, _),
+            InterfaceError::InterpolationMismatch(1, _, _),
+            InterfaceError::MissingOutput(3, _),
+            InterfaceError::BindingMismatch(0, 0, _, _),
+        ]
+    ));
+}
+
+#[test]
+fn test_remove_dead_varyings() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "struct VsOut {
+            @builtin(position) pos: vec4f,
+            @location(0) color: vec4f,
+            @location(1) uv: vec2f,
+            @location(2) normal: vec3f,
+        }
+        struct FsIn {
+            @location(0) color: vec4f,
+            @location(1) uv: vec2f,
+        }
+        @vertex fn vs(@location(0) pos: vec4f) -> VsOut {
+            var out = VsOut(pos, vec4f(1.0), vec2f(), vec3f());
+            out.uv = pos.xy;
+            out.normal = vec3f(0.0, 0.0, 1.0);
+            return out;
+        }
+        @fragment fn fs(in: FsIn) -> @location(0) vec4f {
+            return in.color;
+        }
+        @fragment fn fs_uv(@location(1) uv: vec2f) -> @location(0) vec4f {
+            return vec4f(uv, 0.0, 1.0);
+        }"
+        .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let main = "package::main".parse().unwrap();
+
+    let res = compiler
+        .compile_pipeline((&main, "vs"), (&main, "fs"))
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(res.removed_varyings, [1, 2]);
+    let vs = res.vertex.to_string();
+    assert!(vs.contains("VsOut(pos, vec4f(1.0))"), "{vs}");
+    assert!(!vs.contains("normal") && !vs.contains("out.uv"), "{vs}");
+    let fs = res.fragment.entry_points();
+    assert_eq!(fs.get("fs").unwrap().inputs.len(), 1);
+    let report = check_pipeline((&res.vertex, "vs"), (&res.fragment, "fs")).unwrap();
+    assert!(report.is_ok() && report.unused_outputs.is_empty());
+
+    let res = compiler
+        .compile_pipeline((&main, "vs"), (&main, "fs_uv"))
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(res.removed_varyings, [0, 2]);
+    let vs = res.vertex.to_string();
+    assert!(vs.contains("VsOut(pos, vec2f())"), "{vs}");
+}
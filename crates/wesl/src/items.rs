@@ -0,0 +1,105 @@
+use wgsl_parse::syntax::{ModulePath, Span};
+
+use crate::{DeclKind, ResolveError, Resolver};
+
+/// A module-scope declaration that can be imported from a module. See [`list_items`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemInfo {
+    pub name: String,
+    pub kind: DeclKind,
+    /// The span of the declaration in the module source, see [`Resolver::resolve_source`].
+    pub span: Span,
+    /// The line comments right above the declaration, without the `//` or `///` markers.
+    pub doc: Option<String>,
+}
+
+/// The line comments right above a byte offset, e.g. the documentation of a declaration.
+fn doc_comment(source: &str, start: usize) -> Option<String> {
+    let before = source.get(..start)?;
+    // the line of the declaration, up to the declaration.
+    let line_start = before.rfind('\n').map_or(0, |n| n + 1);
+    if !before[line_start..].trim().is_empty() {
+        return None;
+    }
+    let mut lines = before[..line_start]
+        .lines()
+        .rev()
+        .map_while(|line| {
+            let line = line.trim_start();
+            let text = line
+                .strip_prefix("///")
+                .or_else(|| line.strip_prefix("//"))?;
+            Some(text.strip_prefix(' ').unwrap_or(text).trim_end())
+        })
+        .collect::<Vec<_>>();
+    lines.reverse();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// List the declarations of a module, e.g. for completion or documentation.
+///
+/// The declarations are listed in source order, with their documentation: the line
+/// comments right above them. Items re-exported with `@publish` imports are not listed,
+/// and conditional translation is not performed, see [`crate::Wesl::list_items`].
+pub fn list_items(
+    resolver: &impl Resolver,
+    path: &ModulePath,
+) -> Result<Vec<ItemInfo>, ResolveError> {
+    let wesl = resolver.resolve_module(path)?;
+    let source = resolver.resolve_source(path)?;
+    let items = wesl
+        .global_declarations
+        .iter()
+        .filter_map(|decl| {
+            let kind = DeclKind::of(decl)?;
+            let span = decl.span();
+            Some(ItemInfo {
+                name: decl.ident()?.to_string(),
+                kind,
+                span,
+                doc: doc_comment(&source, span.start),
+            })
+        })
+        .collect();
+    Ok(items)
+}
+
+#[test]
+fn test_list_items() {
+    use crate::VirtualResolver;
+
+    let mut resolver = VirtualResolver::new();
+    let path: ModulePath = "package::util".parse().unwrap();
+    resolver.add_module(
+        path.clone(),
+        "import package::other::x;
+
+        /// The circle constant.
+        /// Approximately.
+        const PI = 3.14;
+
+        // not a doc comment: separated by a blank line.
+
+        struct Light { color: vec4f }
+        // Scale a value.
+        @must_use fn scale(x: f32) -> f32 { return x * PI; }
+        const_assert PI > 3.0;"
+            .into(),
+    );
+    let items = list_items(&resolver, &path).unwrap();
+    let names = items
+        .iter()
+        .map(|item| item.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["PI", "Light", "scale"]);
+    assert_eq!(items[0].kind, DeclKind::Const);
+    assert_eq!(
+        items[0].doc.as_deref(),
+        Some("The circle constant.\nApproximately.")
+    );
+    assert_eq!(items[1].doc, None);
+    assert_eq!(items[2].kind, DeclKind::Function);
+    assert_eq!(items[2].doc.as_deref(), Some("Scale a value."));
+    let source = resolver.get_module(&path).unwrap();
+    assert!(source[items[2].span.range()].starts_with("@must_use fn scale"));
+}
@@ -7,22 +7,77 @@ pub mod eval;
 mod generics;
 #[cfg(feature = "package")]
 mod package;
+pub mod pipeline;
 
+mod annotations;
+mod bind_groups;
+mod binding_range;
+mod call_graph;
+mod compat;
 mod condcomp;
+mod cost;
+mod cse;
+mod define;
+mod diff;
+mod emit;
+mod equiv;
 mod error;
+mod extract;
+mod graph;
+mod header;
+mod hermetic;
+mod hooks;
+#[cfg(feature = "eval")]
+mod host;
+mod hygiene;
 mod idents;
 mod import;
+mod import_map;
+mod interface;
+mod items;
+mod limits;
 mod lower;
 mod mangle;
+mod naming;
+mod plain;
+mod prelude;
+mod printf;
+mod rebind;
+mod refactor;
+mod reflect;
 mod resolve;
+mod sections;
+mod session;
+mod shader;
+mod size;
+mod slot;
 mod sourcemap;
+mod split;
 mod strip;
 mod syntax_util;
+mod synth;
+mod trace;
+mod usage;
 mod validate;
+mod variants;
+mod version;
+#[cfg(feature = "eval")]
+mod virtualize;
 mod visit;
+mod workspace;
 
 #[cfg(feature = "eval")]
 pub use eval::{Eval, EvalError, Exec, Inputs, exec_entrypoint};
+#[cfg(feature = "eval")]
+pub use host::rust_consts;
+#[cfg(feature = "eval")]
+pub use lower::specialize;
+#[cfg(feature = "eval")]
+pub use reflect::struct_layouts;
+#[cfg(feature = "eval")]
+pub use virtualize::storage_to_uniform;
+#[cfg(all(feature = "eval", feature = "naga-ext"))]
+pub use virtualize::uniform_to_push_constant;
 
 #[cfg(feature = "generics")]
 pub use generics::GenericsError;
@@ -30,33 +85,100 @@ pub use generics::GenericsError;
 #[cfg(feature = "package")]
 pub use package::{Module, Pkg, PkgBuilder};
 
+pub use annotations::{AnnotationLint, ExactAnnotation};
+pub use bind_groups::rust_bind_groups;
+pub use binding_range::BindingRange;
+pub use call_graph::{CallGraph, FunctionNode};
+pub use compat::{CompatError, Target};
+use condcomp::CondCompResolver;
 pub use condcomp::{CondCompError, Feature, Features};
-pub use error::{Diagnostic, Error};
-pub use import::ImportError;
-pub use lower::lower;
+pub use cost::{Cost, CostBudget, EntryPointCost, estimate_costs};
+pub use cse::eliminate_common_subexpressions;
+pub use define::ConstValue;
+pub use diff::{AstChange, diff};
+pub use emit::{Emit, EmitStage};
+pub use equiv::{assert_equivalent, equivalent, normalize};
+pub use error::{Diagnostic, Error, Label};
+pub use extract::extract;
+pub use graph::{ModuleGraph, ModuleNode};
+pub use header::Header;
+pub use hermetic::{Manifest, ManifestEntry, RecordingResolver, ReplayResolver};
+pub use hooks::{Hook, Hooks};
+pub use hygiene::{FreshNames, alpha_rename, splice_statements};
+pub use import::{DeclKind, ImportError, Provenance, SimilarDecl};
+pub use import_map::{AppliedMapping, ImportMap, ImportMapResolver, ImportMapping, ImportTarget};
+pub use interface::{
+    InterfaceError, PipelineReport, PipelineResult, check_pipeline, remove_dead_varyings,
+};
+pub use items::{ItemInfo, list_items};
+pub use limits::{LimitError, Limits};
+pub use lower::{lower, lower_switches};
 pub use mangle::{CacheMangler, EscapeMangler, HashMangler, Mangler, NoMangler, UnicodeMangler};
+pub use naming::{NamingCase, NamingLints, NamingViolation, check_naming, fix_naming};
+pub use pipeline::{Pass, Pipeline, Plugin};
+pub use printf::{
+    DebugArg, DebugBuffer, DebugCallSite, DebugMessage, DebugPrintf, DebugPrintfError, DebugScalar,
+    DebugValue,
+};
+pub use rebind::{BindingError, BindingPolicy, Rebinding};
+pub use refactor::{
+    WgslImport, import_wgsl, module_interface, rewrite_import_paths, rewrite_import_paths_in_source,
+};
+pub use reflect::{
+    Binding, Bindings, EntryPoint, EntryPointStage, EntryPoints, InterfaceVariable, Override,
+    Overrides, Reflection, ReflectionChange, ResourceUsage, SamplerPair, texture_type,
+};
 pub use resolve::{
     CodegenModule, CodegenPkg, FileResolver, NoResolver, PkgResolver, Preprocessor, ResolveError,
-    Resolver, Router, StandardResolver, VirtualResolver, emit_rerun_if_changed,
+    Resolver, Router, StandardResolver, SymlinkPolicy, VirtualResolver, emit_rerun_if_changed,
 };
+pub use session::Session;
+pub use shader::CompiledShader;
+pub use size::{DeclarationSize, ModuleSize, SizeReport};
+pub use slot::SlotError;
 pub use sourcemap::{BasicSourceMap, NoSourceMap, SourceMap, SourceMapper};
+pub use split::EntryPointOutputs;
 pub use syntax_util::SyntaxUtil;
-pub use validate::{ValidateError, validate_wesl, validate_wgsl};
+pub use synth::{EntryPointTemplate, SynthError, synthesize_entry_points};
+pub use validate::{
+    Extensions, ValidateError, ValidationLevel, normalize_attributes, validate_wesl, validate_wgsl,
+};
+pub use variants::{LayoutBinding, VariantOutput, VariantsResult};
+pub use version::{Version, VersionError, VersionReq};
+pub use workspace::WorkspaceResult;
 
 // re-exports
 pub use wesl_macros::*;
 pub use wgsl_parse::syntax;
 pub use wgsl_parse::syntax::ModulePath;
+pub use wgsl_types::ty::{TextureDimensions, TextureType};
+pub use wgsl_types::{MemberLayout, StructLayout};
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+};
 
-#[cfg(feature = "eval")]
-use std::collections::HashMap;
-use std::{collections::HashSet, fmt::Display, path::Path};
-
-use strip::strip_except;
+use emit::EmitResolver;
+use hooks::HookResolver;
+use limits::LimitResolver;
+use plain::PlainWgslResolver;
+use prelude::PreludeResolver;
+use resolve::TreeResolver;
+use slot::SlotResolver;
+use strip::{strip_except, strip_members};
+use trace::{TraceResolver, event, span};
 use wgsl_parse::syntax::{Ident, TranslationUnit};
 
 /// Compilation options. Used in [`compile`] and [`Wesl::set_options`].
+///
+/// With the `serde` crate feature, this type can be (de)serialized. Missing fields take
+/// their default value, so a partial options file is valid.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct CompileOptions {
     /// Toggle [WESL Imports](https://github.com/wgsl-tooling-wg/wesl-spec/blob/main/Imports.md).
     ///
@@ -85,11 +207,21 @@ pub struct CompileOptions {
     ///
     /// See [`lower`].
     pub lower: bool,
-    /// Enable validation of individual WESL modules and the final output.
+    /// Convert `switch` statements to `if` chains, for WGSL implementations with poor
+    /// `switch` support.
+    ///
+    /// See [`lower_switches`].
+    pub lower_switches: bool,
+    /// The amount of validation of individual WESL modules and the final output.
     /// This will catch *some* errors, not all.
-    /// See [`validate_wesl`] and [`validate_wgsl`].
+    /// See [`ValidationLevel`], [`validate_wesl`] and [`validate_wgsl`].
     ///
     /// Requires the `eval` crate feature flag.
+    pub validation_level: ValidationLevel,
+    /// Disabling it is the same as setting [`Self::validation_level`] to
+    /// [`ValidationLevel::None`]. Kept for compatibility with the options written before
+    /// the validation levels, see [`Self::validation`].
+    #[deprecated(note = "use `validation_level` instead")]
     pub validate: bool,
     /// Make the import resolution lazy (This is the default mandated by WESL).
     ///
@@ -102,6 +234,11 @@ pub struct CompileOptions {
     ///
     /// By default, WESL does not mangle root module declarations.
     pub mangle_root: bool,
+    /// The mangling scheme of the declarations of imported modules.
+    ///
+    /// A custom mangler set with [`Wesl::set_custom_mangler`] takes precedence. The
+    /// free functions, e.g. [`compile`], take the mangler as an argument instead.
+    pub mangler: ManglerKind,
     /// If `Some`, specify a list of root module declarations to keep. If `None`, only the
     /// entrypoint functions (and their dependencies) are kept.
     ///
@@ -122,9 +259,183 @@ pub struct CompileOptions {
     ///
     /// This option has no effect if [`Self::condcomp`] is disabled.
     pub features: Features,
+    /// Feature flags of the modules under a path prefix, e.g. a package `pkg_a` or a
+    /// mount point `package::shaders`. Modules under a prefix use these feature flags
+    /// instead of [`Self::features`]. If several prefixes match, the longest one wins.
+    ///
+    /// See [`Self::features_of`]. This option has no effect if [`Self::condcomp`] is
+    /// disabled.
+    pub package_features: Vec<(ModulePath, Features)>,
+    /// If `Some`, specify a list of entry points that must be present in the output, by
+    /// their name in the source, before mangling. Compilation fails with
+    /// [`ValidateError::MissingEntryPoint`] if one is missing.
+    ///
+    /// See also [`CompileResult::entry_points`].
+    pub entry_points: Option<Vec<String>>,
+    /// If `Some`, the declarations of this module are in scope in all other modules,
+    /// as if they were imported explicitly. It must be an absolute path, e.g.
+    /// `package::prelude`.
+    ///
+    /// Items re-exported by the prelude with `@publish` imports are in scope too.
+    /// Compilation fails with [`ImportError::PreludeConflict`] if a module declares or
+    /// imports an item with the same name as a prelude item.
+    ///
+    /// This option has no effect if [`Self::imports`] is disabled.
+    pub prelude: Option<ModulePath>,
+    /// Group the output declarations by module of origin, in import resolution order
+    /// (root module first), each group preceded by a banner comment.
+    ///
+    /// Use it to get human-auditable output. See [`CompileResult::module_files`] to get
+    /// one WGSL source per module.
+    pub group_by_module: bool,
+    /// Non-standard language extensions to allow. Modules using a disabled extension are
+    /// rejected.
+    ///
+    /// Requires the `naga-ext` crate feature, otherwise extensions are never allowed.
+    pub extensions: Extensions,
+    /// Give an `@id` to the pipeline-overridable constants that have none, so that hosts
+    /// can set them by numeric id.
+    ///
+    /// Overrides get the lowest unused ids in the alphabetical order of their names, so
+    /// the ids do not depend on the declaration order. See [`CompileResult::overrides`].
+    pub assign_override_ids: bool,
+    /// Remove the unused members of structs that are not visible to the host, i.e.
+    /// not used in `uniform`, `storage` or `push_constant` variables or in entry
+    /// point signatures. Declarations that become unused are stripped as well.
+    ///
+    /// Structs constructed with arguments and structs in [`Self::keep`] are preserved.
+    /// This option has no effect if [`Self::strip`] is disabled.
+    pub strip_members: bool,
+    /// Resource limits, to reject adversarial input when compiling untrusted shaders.
+    /// Compilation fails with a [`LimitError`] if a limit is exceeded.
+    ///
+    /// Limits are checked on each module source as it is loaded, including the modules
+    /// that are later discarded by lazy import resolution.
+    pub limits: Limits,
+    /// If `Some`, instrument the calls to the `debug_printf` WESL built-in function with
+    /// writes to a debug storage buffer at this `@group` and `@binding`. If `None`, the
+    /// calls are removed.
+    ///
+    /// `debug_printf` is a function call statement with the types of its arguments as
+    /// template parameters, e.g. `debug_printf<u32, vec3f>(id, pos);`. The arguments
+    /// must be scalars or vectors of `bool`, `i32`, `u32` or `f32`. Writable storage
+    /// buffers are not allowed in vertex shaders. See [`CompileResult::debug_buffer`] to
+    /// decode the buffer on the host.
+    pub debug_printf: Option<DebugPrintf>,
+    /// If `Some`, resolve the resource binding collisions between packages: move the
+    /// resources of packages to their reserved groups, and give colliding resources the
+    /// next free binding. Compilation fails with a [`BindingError`] if a collision cannot
+    /// be resolved.
+    ///
+    /// The resources that were moved are listed in [`CompileResult::rebindings`].
+    pub binding_policy: Option<BindingPolicy>,
+    /// The WebGPU feature level targeted by the output. With [`Target::Compat`], the
+    /// output is checked against the restrictions of WebGPU compatibility mode after
+    /// stripping, and `@interpolate(flat)` is lowered to `@interpolate(flat, either)`.
+    /// Compilation fails with a [`CompatError`] if a restriction is not respected.
+    pub target: Target,
+    /// If `Some`, prepend a comment header to the output with the inputs of the
+    /// compilation: the WESL version, the root module, the feature flags and a hash of the
+    /// other options, so that generated files can be traced back to their inputs.
+    ///
+    /// The header is deterministic unless [`Header::timestamp`] is enabled.
+    /// See [`CompileResult::header`].
+    pub header: Option<Header>,
+    /// If `Some`, the maximum `var<workgroup>` memory of a compute entry point, in bytes,
+    /// e.g. the `maxComputeWorkgroupStorageSize` device limit (16384 by default in
+    /// WebGPU). Compilation fails with [`ValidateError::WorkgroupMemory`] if an entry
+    /// point exceeds it, and warns if it uses more than half of it, since fewer
+    /// workgroups can then run concurrently.
+    ///
+    /// See [`reflect::EntryPoint::workgroup_memory`]. Requires the `eval` crate feature
+    /// flag, otherwise the memory cannot be computed and is not checked.
+    pub max_workgroup_storage_size: Option<u32>,
+    /// If `Some`, emit a warning for each entry point whose estimated [`Cost`] exceeds
+    /// the budget. See [`CompileResult::costs`].
+    pub cost_budget: Option<CostBudget>,
+    /// Compute the pure expressions repeated in a block once, in a `let` declaration.
+    /// Useful for shaders generated by node-graph tools, which often contain duplicate
+    /// subtrees.
+    ///
+    /// See [`eliminate_common_subexpressions`].
+    pub eliminate_common_subexpressions: bool,
+    /// Sort the attributes of each declaration and statement in a canonical order, e.g.
+    /// `@group` before `@binding`, so that the output does not depend on the order in
+    /// which the attributes were written.
+    ///
+    /// See [`normalize_attributes`].
+    pub normalize_attributes: bool,
+    /// Whether the target supports `binding_array`, e.g. naga with the `naga-ext`
+    /// crate feature. Variables with a `@binding_range(binding, count)` attribute and
+    /// an `array<T, count>` type become `binding_array`s if enabled. Otherwise, they are
+    /// expanded into `count` variables with consecutive bindings, and the elements
+    /// selected by a non-constant index are accessed with generated functions.
+    /// The arguments and the array size must be literals without the `eval` crate
+    /// feature.
+    ///
+    /// The expansions are listed in [`CompileResult::binding_ranges`].
+    pub binding_arrays: bool,
+    /// Treat the modules loaded from `.wgsl` files (see [`Resolver::fs_path`]) as plain
+    /// WGSL: WESL-only syntax (imports, qualified references, conditional translation and
+    /// other WESL attributes) is an error in them. The [`FileResolver`] loads a `.wgsl`
+    /// file when there is no `.wesl` file for a module.
+    ///
+    /// Plain WGSL modules can declare items imported by WESL modules. They are listed in
+    /// [`CompileResult::wgsl_modules`], whether this option is enabled or not.
+    pub plain_wgsl: bool,
+    /// Naming convention lints for the module-scope declarations of the local package
+    /// (`package::` modules). Violations are [`CompileResult::warnings`], or errors if
+    /// [`NamingLints::deny`] is enabled, with a rename suggestion. See [`fix_naming`].
+    pub naming: NamingLints,
+    /// If `Some`, write the syntax tree after some compilation stages to a directory, to
+    /// find which stage introduced an issue in the output. See [`Emit`].
+    pub emit: Option<Emit>,
+    /// Snippets of statements that replace the statements of the `@slot("name")` blocks
+    /// of the modules, by slot name. Libraries mark the injection points of the
+    /// application code with slots, e.g. the surface function of a material system:
+    /// `@slot("surface_eval") { return vec4f(1.0); }`.
+    ///
+    /// Snippets are inserted after conditional translation, in all modules having the
+    /// slot. They can reference the parameters of the function and the module-scope
+    /// declarations of the module, like a function body, and are checked in place. Local
+    /// declarations are renamed to avoid name capture. Slots without a snippet keep their
+    /// statements.
+    pub slots: BTreeMap<String, String>,
+    /// Module-scope `const` declarations added to the root module, e.g. to pass a
+    /// numeric knob from the host without string templating. See [`ConstValue`].
+    ///
+    /// The constants are declared with their explicit type, e.g.
+    /// `const MAX_LIGHTS: u32 = 16u;`. A name that is declared or imported by the root
+    /// module is an error.
+    pub define_consts: HashMap<String, ConstValue>,
+}
+
+impl CompileOptions {
+    /// The effective validation level: [`ValidationLevel::None`] if the deprecated
+    /// [`Self::validate`] option is disabled, [`Self::validation_level`] otherwise.
+    #[allow(deprecated)]
+    pub fn validation(&self) -> ValidationLevel {
+        if self.validate {
+            self.validation_level
+        } else {
+            ValidationLevel::None
+        }
+    }
+
+    /// The feature flags used to compile the module at `path`.
+    /// See [`Self::package_features`].
+    pub fn features_of(&self, path: &ModulePath) -> &Features {
+        self.package_features
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.components.len())
+            .map(|(_, features)| features)
+            .unwrap_or(&self.features)
+    }
 }
 
 impl Default for CompileOptions {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
             imports: true,
@@ -132,18 +443,45 @@ impl Default for CompileOptions {
             generics: false,
             strip: true,
             lower: false,
+            lower_switches: false,
+            validation_level: ValidationLevel::Standard,
             validate: true,
             lazy: true,
             mangle_root: false,
+            mangler: ManglerKind::Escape,
             keep: Default::default(),
             keep_root: false,
             features: Default::default(),
+            entry_points: None,
+            prelude: None,
+            group_by_module: false,
+            extensions: Default::default(),
+            assign_override_ids: false,
+            strip_members: false,
+            package_features: Vec::new(),
+            limits: Limits::default(),
+            debug_printf: None,
+            binding_policy: None,
+            target: Target::Core,
+            header: None,
+            max_workgroup_storage_size: None,
+            cost_budget: None,
+            eliminate_common_subexpressions: false,
+            normalize_attributes: false,
+            binding_arrays: false,
+            plain_wgsl: false,
+            naming: NamingLints::default(),
+            emit: None,
+            slots: BTreeMap::new(),
+            define_consts: HashMap::new(),
         }
     }
 }
 
-/// Mangling scheme. Used in [`Wesl::set_mangler`].
+/// Mangling scheme. Used in [`CompileOptions::mangler`] and [`Wesl::set_mangler`].
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ManglerKind {
     /// Escaped path mangler.
     /// `foo_bar::item -> _1foo_bar_item`
@@ -159,6 +497,17 @@ pub enum ManglerKind {
     None,
 }
 
+impl ManglerKind {
+    fn mangler(self) -> Box<dyn Mangler + Send + Sync> {
+        match self {
+            ManglerKind::Escape => Box::new(EscapeMangler),
+            ManglerKind::Hash => Box::new(HashMangler),
+            ManglerKind::Unicode => Box::new(UnicodeMangler),
+            ManglerKind::None => Box::new(NoMangler),
+        }
+    }
+}
+
 /// Include a WGSL file compiled with [`Wesl::build_artifact`] as a string.
 ///
 /// The argument corresponds to the `artifact_name` passed to [`Wesl::build_artifact`].
@@ -211,7 +560,9 @@ pub struct Wesl<R: Resolver> {
     options: CompileOptions,
     use_sourcemap: bool,
     resolver: R,
-    mangler: Box<dyn Mangler + Send + Sync + 'static>,
+    /// A custom mangler, overriding [`CompileOptions::mangler`].
+    mangler: Option<Box<dyn Mangler + Send + Sync + 'static>>,
+    hooks: Hooks,
 }
 
 impl Wesl<StandardResolver> {
@@ -230,7 +581,8 @@ impl Wesl<StandardResolver> {
             options: CompileOptions::default(),
             use_sourcemap: true,
             resolver: StandardResolver::new(base),
-            mangler: Box::new(EscapeMangler),
+            mangler: None,
+            hooks: Hooks::default(),
         }
     }
 
@@ -250,11 +602,13 @@ impl Wesl<StandardResolver> {
             options: CompileOptions {
                 generics: true,
                 lower: true,
+                lower_switches: false,
                 ..Default::default()
             },
             use_sourcemap: true,
             resolver: StandardResolver::new(base),
-            mangler: Box::new(EscapeMangler),
+            mangler: None,
+            hooks: Hooks::default(),
         }
     }
 
@@ -279,6 +633,21 @@ impl Wesl<StandardResolver> {
         self
     }
 
+    /// Require a version of a package dependency, e.g. `pkg_lighting >= 0.3`.
+    ///
+    /// Compilation fails with [`ResolveError::VersionMismatch`] when importing from a
+    /// package that does not match the requirement.
+    ///
+    /// ```rust
+    /// # use wesl::Wesl;
+    /// let mut compiler = Wesl::new("src/shaders");
+    /// compiler.require_package("pkg_lighting", ">= 0.3".parse().unwrap());
+    /// ```
+    pub fn require_package(&mut self, pkg: impl ToString, req: VersionReq) -> &mut Self {
+        self.resolver.require(pkg, req);
+        self
+    }
+
     /// Add a const-declaration to the special `constants` module.
     ///
     /// See [`StandardResolver::add_constant`].
@@ -310,6 +679,7 @@ impl Wesl<NoResolver> {
     /// # WESL Reference
     /// This WESL compiler is *not* spec-compliant because it does not enable *mandatory*
     /// WESL extensions. See [`Wesl::new`].
+    #[allow(deprecated)]
     pub fn new_barebones() -> Self {
         Self {
             options: CompileOptions {
@@ -318,16 +688,42 @@ impl Wesl<NoResolver> {
                 generics: false,
                 strip: false,
                 lower: false,
-                validate: false,
+                lower_switches: false,
+                validation_level: ValidationLevel::None,
+                validate: true,
                 lazy: false,
                 mangle_root: false,
+                mangler: ManglerKind::None,
                 keep: None,
                 keep_root: false,
                 features: Default::default(),
+                entry_points: None,
+                prelude: None,
+                group_by_module: false,
+                extensions: Default::default(),
+                assign_override_ids: false,
+                strip_members: false,
+                package_features: Vec::new(),
+                limits: Limits::default(),
+                debug_printf: None,
+                binding_policy: None,
+                target: Target::Core,
+                header: None,
+                max_workgroup_storage_size: None,
+                cost_budget: None,
+                eliminate_common_subexpressions: false,
+                normalize_attributes: false,
+                binding_arrays: false,
+                plain_wgsl: false,
+                naming: NamingLints::default(),
+                emit: None,
+                slots: BTreeMap::new(),
+                define_consts: HashMap::new(),
             },
             use_sourcemap: false,
             resolver: NoResolver,
-            mangler: Box::new(NoMangler),
+            mangler: None,
+            hooks: Hooks::default(),
         }
     }
 }
@@ -339,35 +735,40 @@ impl<R: Resolver> Wesl<R> {
         self
     }
 
-    /// Set the [`Mangler`].
+    /// Set the [`Mangler`]. See [`CompileOptions::mangler`].
     ///
-    /// The default mangler is [`EscapeMangler`].
+    /// The default mangler is [`EscapeMangler`]. This replaces a custom mangler set with
+    /// [`Self::set_custom_mangler`].
     ///
     /// # WESL Reference
     /// Custom manglers *must* conform to the constraints described in [`Mangler`].
     ///
     /// Spec: not yet available.
     pub fn set_mangler(&mut self, kind: ManglerKind) -> &mut Self {
-        self.mangler = match kind {
-            ManglerKind::Escape => Box::new(EscapeMangler),
-            ManglerKind::Hash => Box::new(HashMangler),
-            ManglerKind::Unicode => Box::new(UnicodeMangler),
-            ManglerKind::None => Box::new(NoMangler),
-        };
+        self.options.mangler = kind;
+        self.mangler = None;
         self
     }
 
-    /// Set a custom [`Mangler`].
+    /// Set a custom [`Mangler`]. It takes precedence over [`CompileOptions::mangler`].
     ///
     /// The default mangler is [`EscapeMangler`].
     pub fn set_custom_mangler(
         &mut self,
         mangler: impl Mangler + Send + Sync + 'static,
     ) -> &mut Self {
-        self.mangler = Box::new(mangler);
+        self.mangler = Some(Box::new(mangler));
         self
     }
 
+    /// The custom mangler if any, or the mangler of [`CompileOptions::mangler`].
+    fn mangler(&self) -> Box<dyn Mangler + Send + Sync + '_> {
+        match &self.mangler {
+            Some(mangler) => Box::new(mangler),
+            None => self.options.mangler.mangler(),
+        }
+    }
+
     /// Set a custom [`Resolver`] (customize how import paths are translated to WESL modules).
     ///
     ///```rust
@@ -392,10 +793,56 @@ impl<R: Resolver> Wesl<R> {
             options: self.options,
             use_sourcemap: self.use_sourcemap,
             mangler: self.mangler,
+            hooks: self.hooks,
             resolver,
         }
     }
 
+    /// Add a callback invoked on each module right after it is parsed.
+    ///
+    /// Returning an error from the callback aborts the compilation. See [`Hooks`].
+    pub fn on_post_parse(
+        &mut self,
+        hook: impl Fn(&TranslationUnit, &ModulePath) -> Result<(), Error> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.hooks.post_parse.push(Box::new(hook));
+        self
+    }
+
+    /// Add a callback invoked on each module after conditional translation.
+    ///
+    /// The callback is invoked even if conditional translation is disabled.
+    /// Returning an error from the callback aborts the compilation. See [`Hooks`].
+    pub fn on_post_condcomp(
+        &mut self,
+        hook: impl Fn(&TranslationUnit, &ModulePath) -> Result<(), Error> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.hooks.post_condcomp.push(Box::new(hook));
+        self
+    }
+
+    /// Add a callback invoked on each module once all imports are resolved.
+    ///
+    /// At this point, declarations are not mangled yet.
+    /// Returning an error from the callback aborts the compilation. See [`Hooks`].
+    pub fn on_post_resolution(
+        &mut self,
+        hook: impl Fn(&TranslationUnit, &ModulePath) -> Result<(), Error> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.hooks.post_resolution.push(Box::new(hook));
+        self
+    }
+
+    /// Register a semantic annotation attribute, e.g. `@units(meters)`, checked across
+    /// assignments and function calls once all imports are resolved.
+    ///
+    /// Use [`ExactAnnotation`] to require identical annotations. Mismatches fail the
+    /// compilation with [`ValidateError::Annotation`]. See [`AnnotationLint`].
+    pub fn add_annotation_lint(&mut self, lint: impl AnnotationLint + 'static) -> &mut Self {
+        self.hooks.annotation_lints.push(Box::new(lint));
+        self
+    }
+
     /// Enable sourcemapping.
     ///
     /// Turning "on" this option improves the quality of error messages.
@@ -468,6 +915,31 @@ impl<R: Resolver> Wesl<R> {
             .extend(feats.into_iter().map(|(k, v)| (k.to_string(), v.into())));
         self
     }
+    /// Set a conditional compilation feature flag for the modules under a path prefix.
+    /// See [`CompileOptions::package_features`].
+    ///
+    /// The first flag set for a prefix starts a new feature set, with
+    /// [`Features::default`] behavior for unspecified flags.
+    ///
+    /// # WESL Reference
+    /// Per-package feature flags are not part of the WESL spec.
+    pub fn set_package_feature(
+        &mut self,
+        prefix: ModulePath,
+        feat: &str,
+        val: impl Into<Feature>,
+    ) -> &mut Self {
+        let features = &mut self.options.package_features;
+        let idx = match features.iter().position(|(p, _)| *p == prefix) {
+            Some(idx) => idx,
+            None => {
+                features.push((prefix, Features::default()));
+                features.len() - 1
+            }
+        };
+        features[idx].1.flags.insert(feat.to_string(), val.into());
+        self
+    }
     /// Unset a conditional compilation feature flag.
     ///
     /// # WESL Reference
@@ -478,6 +950,149 @@ impl<R: Resolver> Wesl<R> {
         self.options.features.flags.remove(feat);
         self
     }
+    /// Set a prelude module, whose declarations are in scope in all modules without
+    /// import statements. See [`CompileOptions::prelude`].
+    ///
+    /// # WESL Reference
+    /// The prelude is not part of the WESL spec. Code relying on it is not portable to
+    /// other WESL implementations.
+    pub fn set_prelude(&mut self, path: ModulePath) -> &mut Self {
+        self.options.prelude = Some(path);
+        self
+    }
+    /// Allow non-standard language extensions. See [`CompileOptions::extensions`].
+    ///
+    /// # WESL Reference
+    /// Language extensions are not part of WGSL. They are implemented by naga, the
+    /// shader compiler of wgpu.
+    pub fn set_extensions(&mut self, extensions: Extensions) -> &mut Self {
+        self.options.extensions = extensions;
+        self
+    }
+    /// Give an `@id` to all pipeline-overridable constants. See
+    /// [`CompileOptions::assign_override_ids`].
+    pub fn set_assign_override_ids(&mut self, val: bool) -> &mut Self {
+        self.options.assign_override_ids = val;
+        self
+    }
+    /// Convert `switch` statements to `if` chains. See [`CompileOptions::lower_switches`].
+    pub fn set_lower_switches(&mut self, val: bool) -> &mut Self {
+        self.options.lower_switches = val;
+        self
+    }
+    /// Remove unused members of internal structs. See [`CompileOptions::strip_members`].
+    pub fn set_strip_members(&mut self, val: bool) -> &mut Self {
+        self.options.strip_members = val;
+        self
+    }
+    /// Set resource limits. See [`CompileOptions::limits`].
+    pub fn set_limits(&mut self, limits: Limits) -> &mut Self {
+        self.options.limits = limits;
+        self
+    }
+    /// Instrument `debug_printf` calls with a debug storage buffer.
+    /// See [`CompileOptions::debug_printf`].
+    pub fn set_debug_printf(&mut self, val: Option<DebugPrintf>) -> &mut Self {
+        self.options.debug_printf = val;
+        self
+    }
+    /// Resolve binding collisions between packages.
+    /// See [`CompileOptions::binding_policy`].
+    pub fn set_binding_policy(&mut self, val: Option<BindingPolicy>) -> &mut Self {
+        self.options.binding_policy = val;
+        self
+    }
+    /// Set the WebGPU feature level targeted by the output.
+    /// See [`CompileOptions::target`].
+    pub fn set_target(&mut self, val: Target) -> &mut Self {
+        self.options.target = val;
+        self
+    }
+    /// Prepend a reproducibility header to the output. See [`CompileOptions::header`].
+    pub fn set_header(&mut self, val: Option<Header>) -> &mut Self {
+        self.options.header = val;
+        self
+    }
+    /// Check the workgroup memory of compute entry points against a device limit.
+    /// See [`CompileOptions::max_workgroup_storage_size`].
+    pub fn set_max_workgroup_storage_size(&mut self, val: Option<u32>) -> &mut Self {
+        self.options.max_workgroup_storage_size = val;
+        self
+    }
+    /// Warn when the estimated cost of an entry point exceeds a budget.
+    /// See [`CompileOptions::cost_budget`].
+    pub fn set_cost_budget(&mut self, val: Option<CostBudget>) -> &mut Self {
+        self.options.cost_budget = val;
+        self
+    }
+    /// Compute repeated pure expressions once.
+    /// See [`CompileOptions::eliminate_common_subexpressions`].
+    pub fn set_eliminate_common_subexpressions(&mut self, val: bool) -> &mut Self {
+        self.options.eliminate_common_subexpressions = val;
+        self
+    }
+    /// Sort attributes in a canonical order.
+    /// See [`CompileOptions::normalize_attributes`].
+    pub fn set_normalize_attributes(&mut self, val: bool) -> &mut Self {
+        self.options.normalize_attributes = val;
+        self
+    }
+    /// Declare that the target supports `binding_array`.
+    /// See [`CompileOptions::binding_arrays`].
+    pub fn set_binding_arrays(&mut self, val: bool) -> &mut Self {
+        self.options.binding_arrays = val;
+        self
+    }
+    /// Reject WESL-only syntax in modules loaded from `.wgsl` files.
+    /// See [`CompileOptions::plain_wgsl`].
+    pub fn set_plain_wgsl(&mut self, val: bool) -> &mut Self {
+        self.options.plain_wgsl = val;
+        self
+    }
+
+    /// See [`CompileOptions::naming`].
+    pub fn set_naming_lints(&mut self, lints: NamingLints) -> &mut Self {
+        self.options.naming = lints;
+        self
+    }
+
+    /// See [`CompileOptions::emit`].
+    pub fn set_emit(&mut self, emit: Option<Emit>) -> &mut Self {
+        self.options.emit = emit;
+        self
+    }
+
+    /// Define a module-scope constant in the root module. See
+    /// [`CompileOptions::define_consts`].
+    pub fn set_define_const(&mut self, name: &str, value: impl Into<ConstValue>) -> &mut Self {
+        self.options
+            .define_consts
+            .insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Provide the snippet of a `@slot` block. See [`CompileOptions::slots`].
+    pub fn set_slot(&mut self, name: impl Into<String>, snippet: impl Into<String>) -> &mut Self {
+        self.options.slots.insert(name.into(), snippet.into());
+        self
+    }
+
+    /// Set the amount of validation of the WESL modules and the output.
+    ///
+    /// See [`CompileOptions::validation_level`]. It replaces a disabled
+    /// [`CompileOptions::validate`].
+    #[allow(deprecated)]
+    pub fn set_validation_level(&mut self, level: ValidationLevel) -> &mut Self {
+        self.options.validation_level = level;
+        self.options.validate = true;
+        self
+    }
+    /// Group the output declarations by module, with banner comments.
+    /// See [`CompileOptions::group_by_module`].
+    pub fn set_group_by_module(&mut self, val: bool) -> &mut Self {
+        self.options.group_by_module = val;
+        self
+    }
     /// Set the behavior for unspecified conditional compilation feature flags.
     ///
     /// Controls what happens when a feature flag is used in shader code but not set with
@@ -557,21 +1172,173 @@ impl<R: Resolver> Wesl<R> {
     }
 }
 
-/// The result of [`Wesl::compile`].
+/// The result of [`Wesl::compile_result`].
 ///
 /// This type contains both the resulting WGSL syntax tree and the sourcemap if
 /// [`Wesl`] was invoked with sourcemapping enabled.
 ///
-/// This type implements `Display`, call `to_string()` to get the compiled WGSL.
+/// This type implements `Display`, call `to_string()` to get the compiled WGSL. Convert
+/// it to a [`CompiledShader`] to compute the output metadata once.
 #[derive(Clone, Default)]
 pub struct CompileResult {
     pub syntax: TranslationUnit,
     pub sourcemap: Option<BasicSourceMap>,
     /// A list of absolute paths or packages.
     pub modules: Vec<ModulePath>,
+    /// Whether the output is split by module of origin, see
+    /// [`CompileOptions::group_by_module`] and [`Self::module_files`].
+    pub group_by_module: bool,
+    /// The warnings emitted during compilation, e.g. by `@compile_warn` attributes.
+    pub warnings: Vec<Diagnostic<Error>>,
+    /// The declaring module, original name and span of the declarations in
+    /// [`Self::syntax`]. Declarations generated during compilation have no provenance.
+    pub provenances: HashMap<Ident, Provenance>,
+    /// The debug storage buffer, if [`CompileOptions::debug_printf`] is enabled and the
+    /// output calls `debug_printf`. Use [`DebugBuffer::decode`] to read the messages.
+    pub debug_buffer: Option<DebugBuffer>,
+    /// The resources moved by [`CompileOptions::binding_policy`]. See also
+    /// [`Self::bindings`] for the final assignment.
+    pub rebindings: Vec<Rebinding>,
+    /// The `@binding_range` variables expanded into individual bindings. See
+    /// [`CompileOptions::binding_arrays`].
+    pub binding_ranges: Vec<BindingRange>,
+    /// The modules loaded from plain WGSL (`.wgsl`) files, in the order of
+    /// [`Self::modules`]. See [`CompileOptions::plain_wgsl`].
+    pub wgsl_modules: Vec<ModulePath>,
+    /// The reproducibility header, if [`CompileOptions::header`] is enabled. It is
+    /// prepended to the output by the `Display` implementation.
+    pub header: Option<String>,
+    /// The entry points marked with `@export`, or `None` if no entry point is exported.
+    /// See [`Self::entry_points`].
+    pub exports: Option<Vec<String>>,
 }
 
 impl CompileResult {
+    /// Get the declaring module, original name and span of a declaration ident.
+    ///
+    /// Idents are compared by reference: `ident` must come from [`Self::syntax`].
+    pub fn provenance(&self, ident: &Ident) -> Option<&Provenance> {
+        self.provenances.get(ident)
+    }
+
+    /// Get the declaring module, original name and span of a declaration by its
+    /// (mangled) name in [`Self::syntax`].
+    pub fn provenance_by_name(&self, name: &str) -> Option<&Provenance> {
+        self.provenances
+            .iter()
+            .find(|(id, _)| *id.name() == name)
+            .map(|(_, prov)| prov)
+    }
+
+    /// Get the module a span of [`Self::syntax`] refers to, see [`syntax::Span::source`].
+    ///
+    /// Returns `None` for spans of generated syntax nodes.
+    pub fn span_module(&self, span: syntax::Span) -> Option<&ModulePath> {
+        span.source()
+            .and_then(|source| self.modules.get(source as usize))
+    }
+
+    /// Get the size of the compiled shader by originating module and declaration, to find
+    /// which modules and packages contribute the most code.
+    ///
+    /// Use it with [`Wesl::compile_entry_points`] to get the size of each entry point.
+    pub fn size_report(&self) -> SizeReport {
+        SizeReport::new(self)
+    }
+
+    /// Compute a hash of the compiled WGSL, as printed by `to_string()`, e.g. to find
+    /// identical shaders. See [`Wesl::compile_variants`].
+    ///
+    /// The hash is stable for a given build of this crate, but not across versions.
+    pub fn output_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.to_string().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Get the entry points of the compiled shader, with their stage and IO signature.
+    ///
+    /// If some entry points are marked with `@export`, only those are listed. This also
+    /// restricts the stages reported by [`EntryPoints::stages_using`].
+    pub fn entry_points(&self) -> EntryPoints {
+        let mut entry_points = EntryPoints::from_syntax(&self.syntax);
+        if let Some(exports) = &self.exports {
+            entry_points.retain_exported(exports);
+        }
+        entry_points
+    }
+
+    /// Get the call graph of the compiled shader, with the resource bindings each
+    /// function uses.
+    pub fn call_graph(&self) -> CallGraph {
+        CallGraph::from_syntax(&self.syntax)
+    }
+
+    /// Get the resource bindings of the compiled shader, including push constants.
+    pub fn bindings(&self) -> Bindings {
+        Bindings::from_syntax(&self.syntax).with_ranges(&self.binding_ranges)
+    }
+
+    /// Get the pipeline-overridable constants of the compiled shader, with their `@id`.
+    pub fn overrides(&self) -> Overrides {
+        Overrides::from_syntax(&self.syntax)
+    }
+
+    /// Get the estimated cost of the entry points of the compiled shader, e.g. to set
+    /// budgets on shader complexity. See [`CompileOptions::cost_budget`].
+    pub fn costs(&self) -> Vec<EntryPointCost> {
+        estimate_costs(&self.syntax)
+    }
+
+    /// Get the host-visible interface of the compiled shader: entry points, bindings,
+    /// overrides and struct layouts. Compare two versions with [`Reflection::diff`].
+    pub fn reflection(&self) -> Result<Reflection, Error> {
+        let mut reflection = Reflection::from_syntax(&self.syntax)?;
+        reflection.bindings = reflection.bindings.with_ranges(&self.binding_ranges);
+        Ok(reflection)
+    }
+
+    /// Generate typed Rust wrappers of the bind groups of the compiled shader, for the
+    /// `wgpu` crate. See [`rust_bind_groups`].
+    pub fn rust_bind_groups(&self, names: &[(u32, &str)]) -> Result<String, Error> {
+        Ok(rust_bind_groups(&self.reflection()?, names))
+    }
+
+    /// Get the compiled WGSL split by module, each preceded by a banner comment.
+    ///
+    /// The global directives are in the first module. Concatenating the sources in
+    /// order, separated by a newline, gives the same output as `to_string()`.
+    /// If [`CompileOptions::group_by_module`] is disabled, the whole output is attributed
+    /// to the root module.
+    ///
+    /// Declarations are attributed with [`Self::provenances`], so the grouping follows
+    /// changes made to [`Self::syntax`] after compilation.
+    pub fn module_files(&self) -> Vec<(ModulePath, String)> {
+        if self.group_by_module {
+            sections::fmt_sections(&self.syntax, &self.provenances, &self.modules)
+        } else {
+            let root = self
+                .modules
+                .first()
+                .cloned()
+                .unwrap_or_else(ModulePath::new_root);
+            vec![(root, self.syntax.to_string())]
+        }
+    }
+
+    /// Write one WGSL file per module in a directory. See [`Self::module_files`].
+    ///
+    /// Files are prefixed with their position, so that they sort in concatenation
+    /// order, e.g. `00_package.main.wgsl`, `01_package.util.wgsl`.
+    pub fn write_module_files(&self, dir: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        for (n, (path, source)) in self.module_files().into_iter().enumerate() {
+            let name = path.to_string().replace("::", ".");
+            std::fs::write(dir.as_ref().join(format!("{n:02}_{name}.wgsl")), source)?;
+        }
+        Ok(())
+    }
+
     pub fn write_to_file(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
         std::fs::write(path, self.to_string())
     }
@@ -600,7 +1367,16 @@ impl CompileResult {
 
 impl Display for CompileResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.syntax.fmt(f)
+        if let Some(header) = &self.header {
+            writeln!(f, "{header}")?;
+        }
+        if self.group_by_module {
+            let files = self.module_files();
+            let sources = files.iter().map(|(_, source)| source.as_str());
+            write!(f, "{}", sources.collect::<Vec<_>>().join("\n"))
+        } else {
+            self.syntax.fmt(f)
+        }
     }
 }
 
@@ -698,6 +1474,50 @@ impl CompileResult {
         Ok(res)
     }
 
+    /// Compute the memory layout of the host-shareable structs of the compiled shader:
+    /// the offset, size and alignment of each member, and the padding holes.
+    ///
+    /// This helps debugging mismatches between the host data and the shader structs,
+    /// e.g. caused by the 16-byte alignment of `vec3`. See [`struct_layouts`].
+    pub fn struct_layouts(&self) -> Result<Vec<StructLayout>, Error> {
+        struct_layouts(&self.syntax)
+    }
+
+    /// Generate a shader specialized for a configuration, with values substituted for
+    /// module-scope `const` and `override` declarations and the unreachable code
+    /// pruned. Names are the declaration names in the output. See [`specialize`].
+    ///
+    /// Call it once per configuration to get a set of specialized shaders from a
+    /// single compilation.
+    pub fn specialize(
+        &self,
+        constants: &HashMap<String, syntax::Expression>,
+    ) -> Result<CompileResult, Error> {
+        let provenances = self
+            .provenances
+            .iter()
+            .map(|(id, prov)| (id.to_string(), prov.clone()))
+            .collect();
+        // the idents of a clone are shared, they would prevent stripping.
+        let mut syntax = self.syntax.clone();
+        workspace::fresh_idents(&mut syntax);
+        specialize(&mut syntax, constants)?;
+        Ok(CompileResult {
+            provenances: import::provenances_by_ident(&syntax, provenances),
+            syntax,
+            sourcemap: self.sourcemap.clone(),
+            modules: self.modules.clone(),
+            group_by_module: self.group_by_module,
+            warnings: self.warnings.clone(),
+            debug_buffer: self.debug_buffer.clone(),
+            rebindings: self.rebindings.clone(),
+            binding_ranges: self.binding_ranges.clone(),
+            wgsl_modules: self.wgsl_modules.clone(),
+            header: self.header.clone(),
+            exports: self.exports.clone(),
+        })
+    }
+
     /// Execute an entrypoint in the same way that it would be executed on the GPU.
     ///
     /// Highly experimental.
@@ -734,19 +1554,368 @@ impl CompileResult {
 }
 
 impl<R: Resolver> Wesl<R> {
-    /// Compile a WESL program from a root file.
+    /// Compile a WESL program from a root file, and bundle the output with its
+    /// metadata: source map, reflection, warnings and size report.
+    ///
+    /// # Migration
+    /// `compile` used to return a [`CompileResult`], it now returns a [`CompiledShader`].
+    /// Code calling `to_string()` on the result or reading its `syntax`, `sourcemap` or
+    /// `warnings` is unchanged. Use [`Self::compile_result`] for the other methods of
+    /// [`CompileResult`], e.g. `exec`, `specialize` or `provenance`.
     ///
     /// # WESL Reference
     /// Spec: not available yet.
-    pub fn compile(&self, root: &ModulePath) -> Result<CompileResult, Error> {
+    pub fn compile(&self, root: &ModulePath) -> Result<CompiledShader, Error> {
+        self.compile_result(root).map(CompiledShader::from_result)
+    }
+
+    /// Compile a WESL program from a root file, without computing the metadata of
+    /// [`CompiledShader`].
+    ///
+    /// The [`CompileResult`] keeps the output syntax tree and the results of the
+    /// optional passes, e.g. to evaluate or execute the output.
+    pub fn compile_result(&self, root: &ModulePath) -> Result<CompileResult, Error> {
         // TODO
         // root.origin = PathOrigin::Absolute; // we force absolute paths
 
         if self.use_sourcemap {
-            compile_sourcemap(root, &self.resolver, &self.mangler, &self.options)
+            compile_sourcemap_impl(
+                root,
+                &self.resolver,
+                &self.mangler(),
+                &self.options,
+                &self.hooks,
+            )
+        } else {
+            compile_impl(
+                root,
+                &self.resolver,
+                &self.mangler(),
+                &self.options,
+                &self.hooks,
+            )
+        }
+    }
+
+    /// Compile a WESL program from an in-memory root module, e.g. a module generated by a
+    /// codegen pipeline, without printing and re-parsing it.
+    ///
+    /// `root_path` is the path of the root module, it is used to resolve its relative
+    /// imports. The other modules are resolved by the resolver, except `root_path`.
+    pub fn compile_tree(
+        &self,
+        root: &TranslationUnit,
+        root_path: &ModulePath,
+    ) -> Result<CompileResult, Error> {
+        let resolver = TreeResolver::new(root_path, root, &self.resolver);
+        if self.use_sourcemap {
+            compile_sourcemap_impl(
+                root_path,
+                &resolver,
+                &self.mangler(),
+                &self.options,
+                &self.hooks,
+            )
+        } else {
+            compile_impl(
+                root_path,
+                &resolver,
+                &self.mangler(),
+                &self.options,
+                &self.hooks,
+            )
+        }
+    }
+
+    /// Compile the vertex and fragment stages of a render pipeline, from the root modules
+    /// declaring the entry points, and remove the dead varyings: the vertex outputs
+    /// never read by the fragment stage. See [`remove_dead_varyings`].
+    ///
+    /// Both roots can be the same module. Fails if the interfaces of the stages are not
+    /// compatible, see [`PipelineReport`].
+    pub fn compile_pipeline(
+        &self,
+        vertex: (&ModulePath, &str),
+        fragment: (&ModulePath, &str),
+    ) -> Result<PipelineResult, Error> {
+        let mut vs = self.compile_result(vertex.0)?;
+        let mut fs = self.compile_result(fragment.0)?;
+        let removed_varyings =
+            remove_dead_varyings((&mut vs.syntax, vertex.1), (&mut fs.syntax, fragment.1))?;
+        Ok(PipelineResult {
+            vertex: vs,
+            fragment: fs,
+            removed_varyings,
+        })
+    }
+
+    /// Compile a WESL program to one WGSL output per entry point, e.g. for engines that
+    /// create one shader module per pipeline.
+    ///
+    /// The program is compiled once, then each output keeps only the declarations used
+    /// by its entry point and the declarations in [`CompileOptions::keep`]. Declarations
+    /// used by several entry points are duplicated in each output, see
+    /// [`EntryPointOutputs::report`].
+    pub fn compile_entry_points(&self, root: &ModulePath) -> Result<EntryPointOutputs, Error> {
+        let compiled = self.compile_result(root)?;
+        Ok(split::split_entry_points(
+            compiled,
+            self.options.keep.as_deref(),
+        ))
+    }
+
+    /// Compile several WESL programs that share modules, e.g. the entry shaders of an
+    /// engine.
+    ///
+    /// This gives the same results as calling [`Self::compile_result`] on each root, but the
+    /// shared modules are loaded, parsed and conditionally translated only once. Since
+    /// the same mangler is used, a shared declaration has the same name in each output.
+    /// See [`WorkspaceResult`] for the reflection shared by the roots. The
+    /// [`CompileOptions::limits`] bound the whole workspace rather than each root.
+    ///
+    /// Source maps are not shared: with [`Self::use_sourcemap`], the roots are compiled
+    /// separately.
+    pub fn compile_workspace(&self, roots: &[ModulePath]) -> Result<WorkspaceResult, Error> {
+        if self.use_sourcemap {
+            let results = roots
+                .iter()
+                .map(|root| Ok((root.clone(), self.compile_result(root)?)))
+                .collect::<Result<_, Error>>()?;
+            Ok(WorkspaceResult { results })
+        } else {
+            workspace::compile_workspace(
+                roots,
+                &self.resolver,
+                &self.mangler(),
+                &self.options,
+                &self.hooks,
+            )
+        }
+    }
+
+    /// Compile a WESL program from a root file to a WGSL string.
+    ///
+    /// This is a shorthand for `compile(root)?.to_string()`.
+    ///
+    /// ```rust
+    /// # use wesl::{Wesl, VirtualResolver};
+    /// # let mut resolver = VirtualResolver::new();
+    /// # resolver.add_module("package::main".parse().unwrap(), "@if(debug) fn my_fn() {}".into());
+    /// let mut compiler = Wesl::new("src/shaders");
+    /// # let mut compiler = compiler.set_custom_resolver(resolver);
+    /// compiler.set_feature("debug", true).keep_declarations(vec!["my_fn".to_string()]);
+    /// let wgsl = compiler.compile_to_string(&"package::main".parse().unwrap()).unwrap();
+    /// assert!(wgsl.contains("fn my_fn()"));
+    /// ```
+    pub fn compile_to_string(&self, root: &ModulePath) -> Result<String, Error> {
+        self.compile_result(root).map(|res| res.to_string())
+    }
+
+    /// Resolve the imports of a WESL program without compiling it.
+    ///
+    /// Returns the list of modules the root module depends on, including itself, in the
+    /// same order as [`CompileResult::modules`]. Only conditional translation and import
+    /// resolution are performed, later passes and validation are skipped. Use
+    /// [`Resolver::fs_path`] to get the files corresponding to the modules.
+    ///
+    /// This is useful for setting up dependency tracking in external build systems.
+    pub fn resolve_dependencies(&self, root: &ModulePath) -> Result<Vec<ModulePath>, Error> {
+        let options = CompileOptions {
+            validation_level: ValidationLevel::None,
+            ..self.options.clone()
+        };
+        let (resolutions, _, _) =
+            compile_pre_assembly(root, &self.resolver, &options, &self.hooks)?;
+        Ok(resolutions.into_module_order())
+    }
+
+    /// Returns the feature flags declared with `@feature` by the modules the root module
+    /// depends on and their parent modules, with their declared default. Flags declared
+    /// several times are listed once, in order of first declaration.
+    ///
+    /// The modules are the ones returned by [`Self::resolve_dependencies`]: modules that
+    /// are only imported with other feature flags are not included. Use it with
+    /// [`Features::variants`] to check all the variants of a shader.
+    pub fn declared_features(&self, root: &ModulePath) -> Result<Vec<(String, bool)>, Error> {
+        let resolver = CondCompResolver::new(&self.resolver, &self.options);
+        let mut decls: Vec<(String, bool)> = Vec::new();
+        for path in self.resolve_dependencies(root)? {
+            for (name, default) in resolver.declarations(&path) {
+                if !decls.iter().any(|(n, _)| *n == name) {
+                    decls.push((name, default));
+                }
+            }
+        }
+        Ok(decls)
+    }
+
+    /// List the declarations of a module, with their kind, span and documentation.
+    ///
+    /// Unlike [`list_items`], declarations disabled by conditional translation are not
+    /// listed when [`CompileOptions::condcomp`] is enabled.
+    pub fn list_items(&self, path: &ModulePath) -> Result<Vec<ItemInfo>, Error> {
+        let items = if self.options.condcomp {
+            list_items(&CondCompResolver::new(&self.resolver, &self.options), path)?
         } else {
-            compile(root, &self.resolver, &self.mangler, &self.options)
+            list_items(&self.resolver, path)?
+        };
+        Ok(items)
+    }
+
+    /// Extract a declaration of a module into a standalone module, containing only the
+    /// declaration and its transitive dependencies.
+    ///
+    /// See [`extract`]. Declarations imported from other modules are mangled with the
+    /// mangler of the compiler.
+    pub fn extract(&self, path: &ModulePath, item: &str) -> Result<TranslationUnit, Error> {
+        extract::extract_impl(
+            path,
+            item,
+            &self.resolver,
+            &self.mangler(),
+            &self.options,
+            &self.hooks,
+        )
+    }
+
+    /// Build the import graph of a WESL program, with the size of each module.
+    ///
+    /// The modules are the ones returned by [`Self::resolve_dependencies`]: with lazy
+    /// import resolution (the default), modules that are imported but not used are not
+    /// part of the graph. See [`ModuleGraph::cycles`] and [`ModuleGraph::heaviest_path`]
+    /// to find what to refactor.
+    pub fn module_graph(&self, root: &ModulePath) -> Result<ModuleGraph, Error> {
+        let options = CompileOptions {
+            validation_level: ValidationLevel::None,
+            ..self.options.clone()
+        };
+        let (resolutions, _, _) =
+            compile_pre_assembly(root, &self.resolver, &options, &self.hooks)?;
+        let modules = resolutions
+            .modules()
+            .map(|module| {
+                let module = module.borrow();
+                Ok(ModuleNode {
+                    path: module.path.clone(),
+                    declarations: module.source.global_declarations.len(),
+                    bytes: self.resolver.resolve_source(&module.path)?.len(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        let imports = resolutions
+            .dependency_edges()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(from, to)| to.into_iter().map(move |to| (from, to)))
+            .collect();
+        Ok(ModuleGraph { modules, imports })
+    }
+
+    /// Compute a hash of what determines the output of [`Self::compile`], without
+    /// compiling.
+    ///
+    /// The hash covers the modules the root module depends on, after conditional
+    /// translation, the compilation options and the mangler. Feature flags are accounted
+    /// for by conditional translation: variants that differ only by unused features have
+    /// the same hash. Changes to comments and whitespace do not change the hash.
+    /// Only conditional translation and import resolution are performed, like
+    /// [`Self::resolve_dependencies`].
+    ///
+    /// Use it to key a cache of compiled shaders, e.g. a pipeline cache, and skip
+    /// recompiling unchanged variants. The hash is stable for a given build of this crate,
+    /// but not across versions. Hooks (see [`Self::on_post_parse`]) are not hashed.
+    pub fn content_hash(&self, root: &ModulePath) -> Result<u64, Error> {
+        content_hash(
+            root,
+            &self.resolver,
+            &self.mangler(),
+            &self.options,
+            &self.hooks,
+        )
+    }
+
+    /// Compile a WESL program with several combinations of feature flags, e.g. the
+    /// variants returned by [`Features::variants`], and deduplicate the outputs.
+    ///
+    /// The flags of each variant are added to the flags of the compiler, like
+    /// [`Self::set_features`]. Variants with the same [`Self::content_hash`] are compiled
+    /// once, and variants with the same [`CompileResult::output_hash`] share their
+    /// output. Fails with the error of the first variant that fails to compile.
+    ///
+    /// See [`VariantsResult::binding_layout`] to share a bind group layout between the
+    /// variants, and [`BindingPolicy::stable_variants`] to keep the resources moved by
+    /// the binding policy at the same binding in all the variants.
+    pub fn compile_variants(
+        &self,
+        root: &ModulePath,
+        variants: &[Features],
+    ) -> Result<VariantsResult, Error> {
+        let mut res = self.compile_variants_with(root, variants, &self.options)?;
+        if let Some(policy) = (self.options.binding_policy.as_ref()).filter(|p| p.stable_variants) {
+            let mut policy = policy.clone();
+            policy
+                .pinned
+                .extend(variants::stable_pins(&res.outputs, &policy));
+            let options = CompileOptions {
+                binding_policy: Some(policy),
+                ..self.options.clone()
+            };
+            res = self.compile_variants_with(root, variants, &options)?;
+        }
+        res.binding_layout = variants::binding_layout(&res.outputs);
+        Ok(res)
+    }
+
+    fn compile_variants_with(
+        &self,
+        root: &ModulePath,
+        variants: &[Features],
+        options: &CompileOptions,
+    ) -> Result<VariantsResult, Error> {
+        let mut res = VariantsResult {
+            variants: variants.to_vec(),
+            ..Default::default()
+        };
+        // content hash -> output index
+        let mut compiled = HashMap::<u64, usize>::new();
+        for (n, variant) in variants.iter().enumerate() {
+            let mut options = options.clone();
+            options.features.flags.extend(variant.flags.clone());
+            let content =
+                content_hash(root, &self.resolver, &self.mangler(), &options, &self.hooks)?;
+            let index = match compiled.get(&content) {
+                Some(index) => *index,
+                None => {
+                    let result = if self.use_sourcemap {
+                        compile_sourcemap_impl(
+                            root,
+                            &self.resolver,
+                            &self.mangler(),
+                            &options,
+                            &self.hooks,
+                        )?
+                    } else {
+                        compile_impl(root, &self.resolver, &self.mangler(), &options, &self.hooks)?
+                    };
+                    let hash = result.output_hash();
+                    let index = match res.outputs.iter().position(|out| out.hash == hash) {
+                        Some(index) => index,
+                        None => {
+                            res.outputs.push(VariantOutput {
+                                result,
+                                hash,
+                                variants: Vec::new(),
+                            });
+                            res.outputs.len() - 1
+                        }
+                    };
+                    compiled.insert(content, index);
+                    index
+                }
+            };
+            res.outputs[index].variants.push(n);
         }
+        Ok(res)
     }
 
     /// Compile a WESL program from a root file and output the result in Rust's `OUT_DIR`.
@@ -767,7 +1936,7 @@ impl<R: Resolver> Wesl<R> {
     /// Pretty-prints the WESL error message to stderr.
     pub fn build_artifact(&self, root: &ModulePath, artifact_name: &str) {
         let compiled = self
-            .compile(root)
+            .compile_result(root)
             .inspect_err(|e| {
                 eprintln!("failed to build WESL shader `{root}`.\n{e}");
                 panic!();
@@ -776,6 +1945,99 @@ impl<R: Resolver> Wesl<R> {
         emit_rerun_if_changed(&compiled.modules, &self.resolver);
         compiled.write_artifact(artifact_name);
     }
+
+    /// Evaluate the `const` declarations of the root module marked with `@export` and
+    /// return their values.
+    ///
+    /// This allows sharing constants between the host and the shader, e.g. a
+    /// `MAX_LIGHTS` constant defined once in WESL. The exported constants are evaluated
+    /// whether they are used by the shader or not. Constants imported from other modules
+    /// are not exported. See [`rust_consts`] to generate Rust constants, e.g. in a
+    /// `build.rs` script.
+    ///
+    /// Requires the `eval` crate feature flag.
+    #[cfg(feature = "eval")]
+    pub fn host_consts(&self, root: &ModulePath) -> Result<Vec<(String, eval::Instance)>, Error> {
+        let options = CompileOptions {
+            lower: false,
+            keep_root: true,
+            mangle_root: false,
+            ..self.options.clone()
+        };
+        let compiled = if self.use_sourcemap {
+            compile_sourcemap_impl(root, &self.resolver, &self.mangler(), &options, &self.hooks)
+        } else {
+            compile_impl(root, &self.resolver, &self.mangler(), &options, &self.hooks)
+        }?;
+
+        let exported = reflect::exported_consts(&self.resolver.resolve_module(root)?);
+        compiled
+            .syntax
+            .global_declarations
+            .iter()
+            .filter_map(|decl| match decl.node() {
+                syntax::GlobalDeclaration::Declaration(d)
+                    if d.kind == syntax::DeclarationKind::Const
+                        && compiled
+                            .provenance(&d.ident)
+                            .is_some_and(|p| p.module == *root && exported.contains(&p.name)) =>
+                {
+                    Some(d.ident.to_string())
+                }
+                _ => None,
+            })
+            .map(|name| {
+                let inst = compiled.eval(&name)?.inst;
+                Ok((name, inst))
+            })
+            .collect()
+    }
+
+    /// Evaluate a `@const` function of a module with arguments provided by the host,
+    /// e.g. to precompute a lookup table, or to compare a utility function with its Rust
+    /// implementation.
+    ///
+    /// The function is [extracted][Self::extract] with its dependencies, without
+    /// lowering, and executed by the evaluator like in a const-expression. It must have
+    /// the `@const` attribute, the functions it calls are `@const` if they can be
+    /// evaluated in a const-expression. The arguments are converted to the types of the
+    /// parameters, e.g. from [`ConstValue`]s. Returns `None` if the function has no return
+    /// type.
+    ///
+    /// Requires the `eval` crate feature flag.
+    ///
+    /// # WESL Reference
+    /// The `@const` attribute is non-standard.
+    #[cfg(feature = "eval")]
+    pub fn eval_function(
+        &self,
+        path: &ModulePath,
+        name: &str,
+        args: &[eval::Instance],
+    ) -> Result<Option<eval::Instance>, Error> {
+        let options = CompileOptions {
+            lower: false,
+            ..self.options.clone()
+        };
+        let mut wesl = extract::extract_impl(
+            path,
+            name,
+            &self.resolver,
+            &self.mangler(),
+            &options,
+            &self.hooks,
+        )?;
+        use wgsl_parse::Decorated;
+        // the other functions are marked after the function is checked.
+        let is_const = eval::SyntaxUtil::decl_function(&wesl, name)
+            .is_some_and(|decl| decl.contains_attribute(&syntax::Attribute::Const));
+        if !is_const {
+            return Err(EvalError::NotConst(name.to_string()).into());
+        }
+        eval::mark_functions_const(&mut wesl);
+        let (res, ctx) = eval_function(name, args, &wesl);
+        res.map_err(|e| Diagnostic::from(e).with_ctx(&ctx).into())
+    }
 }
 
 /// What idents to keep from the root module. They should be either:
@@ -813,21 +2075,67 @@ fn keep_idents(
     }
 }
 
+/// The resolved modules, the declarations to keep and the warnings.
+type PreAssembly = (import::Resolutions, HashSet<Ident>, Vec<Diagnostic<Error>>);
+
 fn compile_pre_assembly(
     root: &ModulePath,
     resolver: &impl Resolver,
     opts: &CompileOptions,
-) -> Result<(import::Resolutions, HashSet<Ident>), Error> {
+    hooks: &Hooks,
+) -> Result<PreAssembly, Error> {
+    let resolver = pre_assembly_resolver(resolver, opts, hooks)?;
+    resolve_pre_assembly(root, &resolver, opts, hooks)
+}
+
+/// Wrap the user resolver with the per-module passes: plain WGSL checks, limits, hooks,
+/// conditional translation and prelude.
+fn pre_assembly_resolver<'a>(
+    resolver: &'a impl Resolver,
+    opts: &'a CompileOptions,
+    hooks: &'a Hooks,
+) -> Result<impl Resolver + 'a, Error> {
+    let resolver: Box<dyn Resolver> = if opts.plain_wgsl {
+        Box::new(PlainWgslResolver::new(resolver))
+    } else {
+        Box::new(resolver)
+    };
+    let resolver: Box<dyn Resolver> = if opts.limits.is_unlimited() {
+        Box::new(resolver)
+    } else {
+        Box::new(LimitResolver::new(resolver, &opts.limits))
+    };
+    let resolver = HookResolver::new(resolver, &hooks.post_parse);
     let resolver: Box<dyn Resolver> = if opts.condcomp {
-        Box::new(Preprocessor::new(resolver, |wesl| {
-            condcomp::run(wesl, &opts.features)?;
-            Ok(())
-        }))
+        Box::new(CondCompResolver::new(resolver, opts))
     } else {
         Box::new(resolver)
     };
+    let resolver = HookResolver::new(resolver, &hooks.post_condcomp);
+    let resolver: Box<dyn Resolver> = match &opts.emit {
+        Some(emit) if emit.stages.contains(&EmitStage::AfterCondcomp) => {
+            Box::new(EmitResolver::new(resolver, emit))
+        }
+        _ => Box::new(resolver),
+    };
+    let resolver: Box<dyn Resolver + '_> = match &opts.prelude {
+        Some(prelude) if opts.imports => Box::new(PreludeResolver::new(resolver, prelude.clone())?),
+        _ => Box::new(resolver),
+    };
+    let resolver = SlotResolver::new(resolver, &opts.slots);
+    Ok(TraceResolver::new(resolver))
+}
 
+fn resolve_pre_assembly(
+    root: &ModulePath,
+    resolver: &impl Resolver,
+    opts: &CompileOptions,
+    hooks: &Hooks,
+) -> Result<PreAssembly, Error> {
+    span!("resolve", root = %root);
     let mut wesl = resolver.resolve_module(root)?;
+    define::define_consts(&mut wesl, &opts.define_consts)
+        .map_err(|d| d.with_module_path(root.clone(), resolver.display_name(root)))?;
     wesl.retarget_idents();
     let keep = keep_idents(&wesl, &opts.keep, opts.keep_root, opts.strip);
 
@@ -837,13 +2145,60 @@ fn compile_pre_assembly(
 
     if opts.imports {
         if opts.lazy {
-            import::resolve_lazy(&keep, &mut resolutions, &resolver)?
+            import::resolve_lazy(&keep, &mut resolutions, resolver)?
+        } else {
+            import::resolve_eager(&mut resolutions, resolver)?
+        }
+    }
+    event!(modules = resolutions.modules().count(), "resolved imports");
+
+    let mut warnings = Vec::new();
+    for module in resolutions.modules() {
+        let mut module = module.borrow_mut();
+        if opts.condcomp {
+            let mut user_warnings = condcomp::user_diagnostics(&mut module.source)
+                .map_err(|d| {
+                    d.with_module_path(module.path.clone(), resolver.display_name(&module.path))
+                })?
+                .into_iter()
+                .map(|d| {
+                    d.with_module_path(module.path.clone(), resolver.display_name(&module.path))
+                })
+                .collect();
+            warnings.append(&mut user_warnings);
         } else {
-            import::resolve_eager(&mut resolutions, &resolver)?
+            condcomp::strip_user_diagnostics(&mut module.source);
+        }
+        Hooks::run(&hooks.post_resolution, &module.source, &module.path).map_err(|e| {
+            Diagnostic::from(e)
+                .with_module_path(module.path.clone(), resolver.display_name(&module.path))
+        })?;
+        #[cfg(feature = "naga-ext")]
+        validate::check_extensions(&module.source, &opts.extensions).map_err(|d| {
+            d.with_module_path(module.path.clone(), resolver.display_name(&module.path))
+        })?;
+    }
+
+    if !hooks.annotation_lints.is_empty() {
+        span!("annotations");
+        let modules = resolutions.modules().collect::<Vec<_>>();
+        let sources = modules.iter().map(|m| m.borrow()).collect::<Vec<_>>();
+        let decls = annotations::declarations(sources.iter().map(|m| &m.source));
+        for module in &sources {
+            annotations::check_annotations(&module.source, &decls, &hooks.annotation_lints)
+                .map_err(|e| {
+                    Diagnostic::from(e)
+                        .with_module_path(module.path.clone(), resolver.display_name(&module.path))
+                })?;
+        }
+        drop(sources);
+        for module in modules {
+            let source = &mut module.borrow_mut().source;
+            annotations::strip_annotations(source, &hooks.annotation_lints);
         }
     }
 
-    if opts.validate {
+    if opts.validation() >= ValidationLevel::Syntax {
         for module in resolutions.modules() {
             let module = module.borrow();
             validate_wesl(&module.source).map_err(|d| {
@@ -852,32 +2207,231 @@ fn compile_pre_assembly(
         }
     }
 
-    Ok((resolutions, keep))
+    if opts.naming.is_enabled() {
+        span!("naming");
+        for module in resolutions.modules() {
+            let module = module.borrow();
+            // only the local package is checked.
+            if module.path.origin != syntax::PathOrigin::Absolute {
+                continue;
+            }
+            for violation in check_naming(&module.source, &opts.naming) {
+                let mut d = Diagnostic::from(&violation)
+                    .with_module_path(module.path.clone(), resolver.display_name(&module.path));
+                if opts.naming.deny {
+                    return Err(d.into());
+                }
+                d.detail.warning = true;
+                warnings.push(d);
+            }
+        }
+    }
+
+    for module in resolutions.modules() {
+        let module = module.borrow();
+        let emit = opts.emit.as_ref();
+        Emit::write(
+            emit,
+            EmitStage::AfterImports,
+            Some(&module.path),
+            &module.source,
+        )?;
+    }
+
+    Ok((resolutions, keep, warnings))
 }
 
+/// The debug buffer, the resources moved by the binding policy, the expanded binding
+/// ranges and the exported entry points.
+type PostAssembly = (
+    Option<DebugBuffer>,
+    Vec<Rebinding>,
+    Vec<BindingRange>,
+    Option<Vec<String>>,
+    Vec<Diagnostic<Error>>,
+);
+
 fn compile_post_assembly(
     wesl: &mut TranslationUnit,
     options: &CompileOptions,
     keep: &HashSet<Ident>,
-) -> Result<(), Error> {
+    provenances: &HashMap<String, Provenance>,
+) -> Result<PostAssembly, Error> {
     #[cfg(feature = "generics")]
     if options.generics {
+        span!("generics");
         generics::generate_variants(wesl)?;
         generics::replace_calls(wesl)?;
+        event!(
+            declarations = wesl.global_declarations.len(),
+            "generated variants"
+        );
+    };
+    let binding_ranges = {
+        span!("binding_ranges");
+        binding_range::expand_binding_ranges(wesl, options.binding_arrays)?
     };
-    if options.validate {
-        validate_wgsl(wesl)?;
+    let debug_buffer = {
+        span!("debug_printf");
+        printf::instrument(wesl, options.debug_printf.as_ref())?
+    };
+    match options.validation() {
+        ValidationLevel::None | ValidationLevel::Syntax => {}
+        ValidationLevel::Standard => {
+            span!("validate");
+            validate_wgsl(wesl)?;
+        }
+        ValidationLevel::Pedantic => {
+            span!("validate");
+            validate::validate_pedantic(wesl)?;
+        }
     }
     if options.lower {
+        span!("lower");
         lower(wesl)?;
+        event!(declarations = wesl.global_declarations.len(), "lowered");
+    }
+    Emit::write(options.emit.as_ref(), EmitStage::AfterLower, None, wesl)?;
+    if options.lower_switches {
+        span!("lower_switches");
+        lower_switches(wesl);
+    }
+    if options.eliminate_common_subexpressions {
+        span!("eliminate_common_subexpressions");
+        eliminate_common_subexpressions(wesl);
     }
     if options.strip {
+        span!("strip");
         strip_except(wesl, keep);
+        if options.strip_members {
+            strip_members(wesl, keep);
+        }
+        if options.lower {
+            // stripping can remove the last usages of an extension.
+            lower::remove_unused_enables(wesl);
+        }
+        event!(declarations = wesl.global_declarations.len(), "stripped");
     }
-    Ok(())
+    if options.target == Target::Compat {
+        span!("compat");
+        compat::check_compat(wesl)?;
+    }
+    let rebindings = match &options.binding_policy {
+        Some(policy) => {
+            span!("resolve_binding_collisions");
+            rebind::resolve_collisions(wesl, policy, provenances)?
+        }
+        None => Vec::new(),
+    };
+    if options.assign_override_ids {
+        span!("assign_override_ids");
+        reflect::assign_override_ids(wesl)?;
+    }
+    if options.normalize_attributes {
+        span!("normalize_attributes");
+        normalize_attributes(wesl);
+    }
+    if let Some(entry_points) = &options.entry_points {
+        for name in entry_points {
+            // compare with the names before mangling, e.g. with `mangle_root`.
+            let found = wesl.entry_points().any(|id| {
+                let id = id.name();
+                provenances
+                    .get(id.as_str())
+                    .map_or(*id == *name, |prov| prov.name == *name)
+            });
+            if !found {
+                return Err(ValidateError::MissingEntryPoint(name.clone()).into());
+            }
+        }
+    }
+    let exports = reflect::strip_exports(wesl)?;
+    let mut warnings = match options.max_workgroup_storage_size {
+        Some(limit) => {
+            span!("workgroup_memory");
+            reflect::check_workgroup_memory(wesl, limit)?
+        }
+        None => Vec::new(),
+    };
+    if let Some(budget) = &options.cost_budget {
+        span!("cost_budget");
+        let exceeded = cost::check_costs(wesl, budget);
+        warnings.extend(exceeded.into_iter().map(|e| e.into()));
+    }
+    Ok((debug_buffer, rebindings, binding_ranges, exports, warnings))
+}
+
+/// Hash the options that determine the output, except the feature flags: they are
+/// accounted for by hashing the modules after conditional translation.
+#[allow(deprecated)]
+pub(crate) fn hash_options(options: &CompileOptions, state: &mut impl Hasher) {
+    // exhaustive, so that new options are not forgotten.
+    let CompileOptions {
+        imports,
+        condcomp,
+        generics,
+        strip,
+        lower,
+        lower_switches,
+        validation_level: _,
+        validate: _, // both are hashed by the effective validation level.
+        lazy,
+        mangle_root,
+        mangler: _, // the mangler is hashed by the names it produces.
+        keep,
+        keep_root,
+        features: _,
+        package_features: _,
+        entry_points,
+        prelude,
+        group_by_module,
+        extensions,
+        assign_override_ids,
+        strip_members,
+        limits: _, // limits only reject inputs.
+        plain_wgsl: _,
+        naming: _, // naming lints only emit diagnostics.
+        emit: _,
+        slots,
+        define_consts,
+        debug_printf,
+        binding_policy,
+        target,
+        header,
+        max_workgroup_storage_size,
+        cost_budget,
+        eliminate_common_subexpressions,
+        normalize_attributes,
+        binding_arrays,
+    } = options;
+    (imports, condcomp, generics, strip, lower, lower_switches).hash(state);
+    let validation = options.validation();
+    (validation, lazy, mangle_root, keep, keep_root, entry_points).hash(state);
+    (prelude, group_by_module, extensions, assign_override_ids).hash(state);
+    (
+        strip_members,
+        debug_printf,
+        binding_policy,
+        target,
+        header,
+        slots,
+    )
+        .hash(state);
+    let cse = eliminate_common_subexpressions;
+    (
+        max_workgroup_storage_size,
+        cost_budget,
+        cse,
+        normalize_attributes,
+        binding_arrays,
+    )
+        .hash(state);
+    let mut define_consts = define_consts.iter().collect::<Vec<_>>();
+    define_consts.sort_by_key(|(name, _)| *name);
+    define_consts.hash(state);
 }
 
-/// Low-level version of [`Wesl::compile`].
+/// Low-level version of [`Wesl::compile_result`].
 /// To get a source map, use [`compile_sourcemap`] instead.
 pub fn compile(
     root: &ModulePath,
@@ -885,19 +2439,117 @@ pub fn compile(
     mangler: &impl Mangler,
     options: &CompileOptions,
 ) -> Result<CompileResult, Error> {
-    let (mut resolutions, keep) = compile_pre_assembly(root, resolver, options)?;
-    resolutions.mangle(mangler, options.mangle_root);
-    let mut assembly = resolutions.assemble(options.strip && options.lazy);
+    compile_impl(root, resolver, mangler, options, &Hooks::default())
+}
+
+fn compile_impl(
+    root: &ModulePath,
+    resolver: &impl Resolver,
+    mangler: &impl Mangler,
+    options: &CompileOptions,
+    hooks: &Hooks,
+) -> Result<CompileResult, Error> {
+    span!("compile", root = %root);
+    let pre_assembly = compile_pre_assembly(root, resolver, options, hooks)?;
+    compile_resolved(root, pre_assembly, resolver, mangler, options)
+}
+
+/// Low-level version of [`Wesl::compile_tree`].
+pub fn compile_tree(
+    root: &TranslationUnit,
+    root_path: &ModulePath,
+    resolver: &impl Resolver,
+    mangler: &impl Mangler,
+    options: &CompileOptions,
+) -> Result<CompileResult, Error> {
+    let resolver = TreeResolver::new(root_path, root, resolver);
+    compile_impl(root_path, &resolver, mangler, options, &Hooks::default())
+}
+
+/// Assemble the resolved modules and run the post-assembly passes.
+fn compile_resolved(
+    root: &ModulePath,
+    (mut resolutions, keep, mut warnings): PreAssembly,
+    resolver: &impl Resolver,
+    mangler: &impl Mangler,
+    options: &CompileOptions,
+) -> Result<CompileResult, Error> {
+    let provenances = resolutions.provenances();
+    let mut assembly = {
+        span!("assemble");
+        resolutions.mangle(mangler, options.mangle_root);
+        resolutions.assemble(options.strip && options.lazy)
+    };
+    event!(
+        declarations = assembly.global_declarations.len(),
+        "assembled"
+    );
+    Emit::write(
+        options.emit.as_ref(),
+        EmitStage::AfterMangle,
+        None,
+        &assembly,
+    )?;
+    let provenances = import::provenances_by_name(provenances);
     // resolutions hold idents use-counts. We only need the list of modules now.
     let modules = resolutions.into_module_order();
-    compile_post_assembly(&mut assembly, options, &keep)?;
+    let (debug_buffer, rebindings, binding_ranges, exports, post_warnings) =
+        compile_post_assembly(&mut assembly, options, &keep, &provenances).map_err(|e| {
+            Error::from(Diagnostic::from(e).with_span_modules(&modules, &NoSourceMap))
+        })?;
+    warnings.extend(post_warnings);
+    let provenances = import::provenances_by_ident(&assembly, provenances);
+    if options.group_by_module {
+        sections::group_by_module(&mut assembly, &provenances, &modules);
+    }
     Ok(CompileResult {
         syntax: assembly,
         sourcemap: None,
+        group_by_module: options.group_by_module,
+        warnings,
+        provenances,
+        debug_buffer,
+        rebindings,
+        binding_ranges,
+        wgsl_modules: plain::plain_wgsl_modules(&modules, resolver),
         modules,
+        header: (options.header.as_ref()).map(|h| header::header(h, root, options)),
+        exports,
     })
 }
 
+fn content_hash(
+    root: &ModulePath,
+    resolver: &impl Resolver,
+    mangler: &impl Mangler,
+    options: &CompileOptions,
+    hooks: &Hooks,
+) -> Result<u64, Error> {
+    let (resolutions, _, _) = compile_pre_assembly(root, resolver, options, hooks)?;
+    let mut hasher = DefaultHasher::new();
+    for module in resolutions.modules() {
+        let module = module.borrow();
+        module.path.hash(&mut hasher);
+        module.source.to_string().hash(&mut hasher);
+    }
+    hash_options(options, &mut hasher);
+    // the mangler is identified by the names it produces.
+    let path = ModulePath::new(syntax::PathOrigin::Absolute, vec!["a_b".to_string()]);
+    mangler.mangle(&path, "c_d").hash(&mut hasher);
+    mangler.mangle_types("c_d", 1, &[]).hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// Low-level version of [`Wesl::compile_workspace`].
+pub fn compile_workspace(
+    roots: &[ModulePath],
+    resolver: &impl Resolver,
+    mangler: &impl Mangler,
+    options: &CompileOptions,
+) -> Result<WorkspaceResult, Error> {
+    workspace::compile_workspace(roots, resolver, mangler, options, &Hooks::default())
+}
+
 /// Like [`compile`], but provides better error diagnostics and returns the sourcemap.
 pub fn compile_sourcemap(
     root: &ModulePath,
@@ -905,27 +2557,76 @@ pub fn compile_sourcemap(
     mangler: &impl Mangler,
     options: &CompileOptions,
 ) -> Result<CompileResult, Error> {
+    compile_sourcemap_impl(root, resolver, mangler, options, &Hooks::default())
+}
+
+fn compile_sourcemap_impl(
+    root: &ModulePath,
+    resolver: &impl Resolver,
+    mangler: &impl Mangler,
+    options: &CompileOptions,
+    hooks: &Hooks,
+) -> Result<CompileResult, Error> {
+    span!("compile", root = %root);
     let sourcemapper = SourceMapper::new(root, resolver, mangler);
 
-    match compile_pre_assembly(root, &sourcemapper, options) {
-        Ok((mut resolutions, keep)) => {
-            resolutions.mangle(&sourcemapper, options.mangle_root);
+    match compile_pre_assembly(root, &sourcemapper, options, hooks) {
+        Ok((mut resolutions, keep, warnings)) => {
+            let provenances = resolutions.provenances();
+            let mut assembly = {
+                span!("assemble");
+                resolutions.mangle(&sourcemapper, options.mangle_root);
+                resolutions.assemble(options.strip && options.lazy)
+            };
+            event!(
+                declarations = assembly.global_declarations.len(),
+                "assembled"
+            );
+            Emit::write(
+                options.emit.as_ref(),
+                EmitStage::AfterMangle,
+                None,
+                &assembly,
+            )?;
+            let provenances = import::provenances_by_name(provenances);
             let sourcemap = sourcemapper.finish();
-            let mut assembly = resolutions.assemble(options.strip && options.lazy);
             let modules = resolutions.into_module_order();
-            compile_post_assembly(&mut assembly, options, &keep)
+            compile_post_assembly(&mut assembly, options, &keep, &provenances)
                 .map_err(|e| {
                     Diagnostic::from(e)
                         .with_output(assembly.to_string())
                         .with_sourcemap(&sourcemap)
+                        .with_span_modules(&modules, &sourcemap)
                         .unmangle(Some(&sourcemap), Some(&mangler))
                         .into()
                 })
-                .map(|()| CompileResult {
-                    syntax: assembly,
-                    sourcemap: Some(sourcemap),
-                    modules,
-                })
+                .map(
+                    |(debug_buffer, rebindings, binding_ranges, exports, post_warnings)| {
+                        let provenances = import::provenances_by_ident(&assembly, provenances);
+                        if options.group_by_module {
+                            sections::group_by_module(&mut assembly, &provenances, &modules);
+                        }
+                        CompileResult {
+                            syntax: assembly,
+                            warnings: warnings
+                                .into_iter()
+                                .chain(post_warnings)
+                                .map(|w| w.with_sourcemap(&sourcemap))
+                                .collect(),
+                            sourcemap: Some(sourcemap),
+                            group_by_module: options.group_by_module,
+                            provenances,
+                            debug_buffer,
+                            rebindings,
+                            binding_ranges,
+                            wgsl_modules: plain::plain_wgsl_modules(&modules, resolver),
+                            modules,
+                            header: (options.header.as_ref())
+                                .map(|h| header::header(h, root, options)),
+                            exports,
+                        }
+                    },
+                )
         }
         Err(e) => {
             let sourcemap = sourcemapper.finish();
@@ -970,6 +2671,25 @@ pub fn eval<'s>(
     (res, ctx)
 }
 
+/// Low-level version of [`Wesl::eval_function`].
+///
+/// The function and the functions it calls must be `@const`.
+#[cfg(feature = "eval")]
+pub fn eval_function<'s>(
+    name: &str,
+    args: &[eval::Instance],
+    wgsl: &'s TranslationUnit,
+) -> (Result<Option<eval::Instance>, EvalError>, eval::Context<'s>) {
+    let mut ctx = eval::Context::new(wgsl);
+    let res = match eval::SyntaxUtil::decl_function(wgsl, name) {
+        Some(decl) => wgsl
+            .exec(&mut ctx)
+            .and_then(|_| eval::exec_function(decl, args.to_vec(), &mut ctx)),
+        None => Err(EvalError::UnknownFunction(name.to_string())),
+    };
+    (res, ctx)
+}
+
 /// Low-level version of [`CompileResult::exec`].
 #[cfg(feature = "eval")]
 pub fn exec<'s>(
@@ -996,3 +2716,405 @@ fn test_send_sync() {
     fn assert_send_sync<T: Send + Sync>() {}
     assert_send_sync::<Wesl<StandardResolver>>();
 }
+
+#[test]
+fn test_span_module() {
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f;
+        @fragment fn main() -> @location(0) vec4f { return vec4f(f()); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn f() -> f32 { return 1.0; }".into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .set_mangler(ManglerKind::None);
+    let res = compiler
+        .compile_result(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let module = |name: &str| {
+        let decl = res.syntax.find_decl(name).unwrap();
+        res.span_module(decl.span()).cloned()
+    };
+    assert_eq!(module("main"), Some("package::main".parse().unwrap()));
+    assert_eq!(module("f"), Some("package::util".parse().unwrap()));
+}
+
+#[test]
+fn test_spans_after_condcomp() {
+    let main = "import package::util::g;
+        @if(debug) fn unused() {}
+        @fragment fn main() -> @location(0) vec4f {
+            @if(debug) let x = 1.0;
+            @if(root_error) { f32(2.0); }
+            return vec4f(g());
+        }";
+    let util = "@must_use fn h() -> f32 { return 1.0; }
+        @if(debug) const c = 1;
+        fn g() -> f32 {
+            @if(debug) { let y = c; }
+            @else { h(); }
+            return 1.0;
+        }";
+    let compiler = |debug: bool, root_error: bool, sourcemap: bool| {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module("package::main".parse().unwrap(), main.into());
+        resolver.add_module("package::util".parse().unwrap(), util.into());
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        compiler
+            .set_options(CompileOptions::default())
+            .set_mangler(ManglerKind::None)
+            .use_sourcemap(sourcemap)
+            .set_feature("debug", debug)
+            .set_feature("root_error", root_error);
+        compiler
+    };
+    let compile = |root_error: bool, sourcemap: bool| match compiler(false, root_error, sourcemap)
+        .compile(&"package::main".parse().unwrap())
+    {
+        Err(Error::Error(d)) => d,
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("expected an error"),
+    };
+
+    // the surviving nodes keep their original spans.
+    let mut res = compiler(true, false, true)
+        .compile(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let sources = [main, util];
+    let text = |span: syntax::Span| &sources[span.source().unwrap() as usize][span.range()];
+    let g = res.syntax.find_decl("g").unwrap();
+    assert!(text(g.span()).starts_with("fn g() -> f32"));
+    let syntax::GlobalDeclaration::Function(g) = g.node() else {
+        panic!("expected a function")
+    };
+    assert_eq!(
+        text(g.body.statements[0].span()),
+        "@if(debug) { let y = c; }"
+    );
+    res.syntax
+        .spans_mut(|span| assert!(span.source().is_some(), "{span:?}"));
+
+    for sourcemap in [true, false] {
+        let d = compile(false, sourcemap);
+        assert!(matches!(
+            *d.error,
+            Error::ValidateError(ValidateError::MustUse(_))
+        ));
+        assert_eq!(d.detail.module_path, Some("package::util".parse().unwrap()));
+        assert_eq!(&util[d.detail.span.unwrap().range()], "h();");
+        if sourcemap {
+            assert_eq!(d.detail.source.as_deref(), Some(util));
+        }
+
+        let d = compile(true, sourcemap);
+        assert_eq!(d.detail.module_path, Some("package::main".parse().unwrap()));
+        assert_eq!(&main[d.detail.span.unwrap().range()], "f32(2.0);");
+    }
+}
+
+#[test]
+fn test_declared_features() {
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@feature(shadows, true) @feature(fog) import package::util::f;
+        @fragment fn main() -> @location(0) vec4f { return vec4f(f()); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "@feature(fog) @feature(hdr) fn f() -> f32 { return 1.0; }".into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let decls = compiler
+        .declared_features(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let expected = [("shadows", true), ("fog", false), ("hdr", false)];
+    assert_eq!(decls, expected.map(|(name, val)| (name.to_string(), val)));
+}
+
+#[test]
+fn test_content_hash() {
+    let hash = |util: &str, configure: &dyn Fn(&mut Wesl<VirtualResolver<'static>>)| {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "package::main".parse().unwrap(),
+            "import package::util::f;
+            @fragment fn main() -> @location(0) vec4f { return vec4f(f()); }"
+                .into(),
+        );
+        resolver.add_module("package::util".parse().unwrap(), util.to_string().into());
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        compiler.set_options(CompileOptions::default());
+        configure(&mut compiler);
+        compiler
+            .content_hash(&"package::main".parse().unwrap())
+            .unwrap_or_else(|e| panic!("{e}"))
+    };
+    let util = "fn f() -> f32 { @if(hq) { return 2.0; } return 1.0; }";
+    let base = hash(util, &|_| {});
+    assert_eq!(base, hash(util, &|_| {}));
+    // comments and unused features are irrelevant.
+    let commented = "// comment\nfn f() -> f32 { @if(hq) { return 2.0; } return 1.0; }";
+    assert_eq!(
+        base,
+        hash(commented, &|c| _ = c.set_feature("unused", true))
+    );
+
+    assert_ne!(base, hash(util, &|c| _ = c.set_feature("hq", true)));
+    assert_ne!(base, hash(util, &|c| _ = c.set_target(Target::Compat)));
+    assert_ne!(base, hash(util, &|c| _ = c.set_mangler(ManglerKind::Hash)));
+    assert_ne!(base, hash("fn f() -> f32 { return 3.0; }", &|_| {}));
+}
+
+#[test]
+fn test_header() {
+    let compile = |configure: &dyn Fn(&mut Wesl<VirtualResolver<'static>>)| {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "package::main".parse().unwrap(),
+            "@fragment fn main() -> @location(0) vec4f { return vec4f(); }".into(),
+        );
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        compiler.set_options(CompileOptions::default());
+        configure(&mut compiler);
+        compiler
+            .compile(&"package::main".parse().unwrap())
+            .unwrap_or_else(|e| panic!("{e}"))
+            .to_string()
+    };
+    assert!(!compile(&|_| {}).starts_with("//"));
+
+    let header = Some(Header::default());
+    let wgsl = compile(&|c| {
+        c.set_header(header)
+            .set_feature("b", false)
+            .set_feature("a", true);
+    });
+    let version = env!("CARGO_PKG_VERSION");
+    assert!(wgsl.starts_with(&format!("// generated by wesl {version}\n")));
+    assert!(wgsl.contains("// root: package::main\n"));
+    assert!(wgsl.contains("// features: default=disable, a=enable, b=disable\n"));
+    assert!(!wgsl.contains("// timestamp"));
+    let options = |wgsl: &str| {
+        wgsl.lines()
+            .find(|l| l.starts_with("// options"))
+            .map(str::to_string)
+    };
+    assert_eq!(
+        options(&wgsl),
+        options(&compile(&|c| _ = c.set_header(header)))
+    );
+    let lowered = compile(&|c| _ = c.set_header(header).set_lower_switches(true));
+    assert_ne!(options(&wgsl), options(&lowered));
+
+    let wgsl = compile(&|c| _ = c.set_header(Some(Header { timestamp: true })));
+    assert!(wgsl.contains("// timestamp: "));
+}
+
+#[test]
+fn test_compile_tree() {
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn f() -> f32 { return 1.0; }".into(),
+    );
+    let root = wgsl_parse::parse_str(
+        "import super::util::f;
+        @fragment fn main() -> @location(0) vec4f { return vec4f(f()); }",
+    )
+    .unwrap();
+    let root_path = "package::gen".parse().unwrap();
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .set_mangler(ManglerKind::Escape);
+    compiler.options.mangle_root = true;
+    let res = compiler
+        .compile_tree(&root, &root_path)
+        .unwrap_or_else(|e| panic!("{e}"));
+    let wgsl = res.to_string();
+    assert!(wgsl.contains("fn package_util_f() -> f32"), "{wgsl}");
+    assert!(wgsl.contains("fn package_gen_main()"), "{wgsl}");
+    // the caller's tree is not renamed.
+    assert!(root.to_string().contains("fn main()"));
+
+    let res = compile_tree(
+        &root,
+        &root_path,
+        &compiler.resolver,
+        &EscapeMangler,
+        &CompileOptions::default(),
+    )
+    .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(res.modules.len(), 2);
+}
+
+#[cfg(feature = "eval")]
+#[test]
+fn test_eval_function() {
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::easing".parse().unwrap(),
+        "import package::util::saturate;
+        fn smoothstep01(t: f32) -> f32 { return t * t * (3.0 - 2.0 * t); }
+        @const fn easing(t: f32) -> f32 { return smoothstep01(saturate(t)); }
+        @const fn scale(v: vec2f, s: f32) -> vec2f { return v * s; }
+        fn linear(t: f32) -> f32 { return t; }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn saturate(x: f32) -> f32 { return clamp(x, 0.0, 1.0); }".into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let path = "package::easing".parse().unwrap();
+    let eval = |name: &str, args: &[eval::Instance]| {
+        compiler
+            .eval_function(&path, name, args)
+            .unwrap_or_else(|e| panic!("{e}"))
+            .unwrap()
+    };
+
+    for t in [0.0f32, 0.3, 0.5, 2.0] {
+        let res = eval("easing", &[t.into()]).unwrap_literal().unwrap_f32();
+        let t = t.clamp(0.0, 1.0);
+        assert_eq!(res, t * t * (3.0 - 2.0 * t));
+    }
+    let v = ConstValue::from([1.0f32, 2.0]).into();
+    let res = eval("scale", &[v, ConstValue::F32(0.5).into()]);
+    assert_eq!(res.to_string(), "vec2(0.5f, 1f)");
+
+    let Err(err) = compiler.eval_function(&path, "linear", &[0.5f32.into()]) else {
+        panic!("functions without `@const` cannot be evaluated");
+    };
+    let err = err.to_string();
+    assert!(err.contains("non-const function `linear`"), "{err}");
+}
+
+#[test]
+fn test_resolve_dependencies() {
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f;
+        import package::unused::g;
+        @fragment fn main() { f(); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "import package::leaf::h;
+        fn f() { h(); }"
+            .into(),
+    );
+    resolver.add_module("package::leaf".parse().unwrap(), "fn h() {}".into());
+    resolver.add_module("package::unused".parse().unwrap(), "fn g() {}".into());
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    let deps = |compiler: &Wesl<VirtualResolver>| {
+        compiler
+            .resolve_dependencies(&"package::main".parse().unwrap())
+            .unwrap_or_else(|e| panic!("{e}"))
+            .iter()
+            .map(|path| path.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // lazy resolution only loads the modules whose items are used.
+    compiler.set_options(CompileOptions::default());
+    assert_eq!(
+        deps(&compiler),
+        ["package::main", "package::util", "package::leaf"]
+    );
+
+    compiler.set_options(CompileOptions {
+        lazy: false,
+        ..Default::default()
+    });
+    let mut eager = deps(&compiler);
+    assert_eq!(eager.first().map(String::as_str), Some("package::main"));
+    eager.sort();
+    assert_eq!(
+        eager,
+        [
+            "package::leaf",
+            "package::main",
+            "package::unused",
+            "package::util"
+        ]
+    );
+}
+
+#[test]
+fn test_exported_entry_points() {
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@group(0) @binding(0) var<uniform> u: f32;
+        @export @vertex fn vs() -> @builtin(position) vec4f { return vec4f(u); }
+        @fragment fn fs() -> @location(0) vec4f { return vec4f(u); }
+        @compute @workgroup_size(1) fn debug() { _ = u; }"
+            .into(),
+    );
+    let path = "package::main".parse().unwrap();
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        mangle_root: true,
+        entry_points: Some(vec!["vs".to_string(), "fs".to_string()]),
+        ..Default::default()
+    });
+    let res = compiler
+        .compile_result(&path)
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert!(!res.to_string().contains("@export"));
+    let entry_points = res.entry_points();
+    assert_eq!(entry_points.len(), 1);
+    let vs = res.provenance_by_name(&entry_points[0].name).unwrap();
+    assert_eq!(vs.name, "vs");
+    let u = res.bindings()[0].name.clone();
+    assert_eq!(entry_points.stages_using(&u), [EntryPointStage::Vertex]);
+
+    compiler.set_options(CompileOptions {
+        mangle_root: true,
+        entry_points: Some(vec!["missing".to_string()]),
+        ..Default::default()
+    });
+    let Err(err) = compiler.compile(&path) else {
+        panic!("`missing` is not an entry point");
+    };
+    assert!(err.to_string().contains("entry point `missing` is missing"));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_validate() {
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@fragment fn main() -> @location(0) vec4f { sin(1.0); return vec4f(); }".into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    let root = "package::main".parse().unwrap();
+    compiler.set_options(CompileOptions::default());
+    assert!(compiler.compile(&root).is_err());
+
+    // `validate: false` disables the validation, whatever the level.
+    compiler.set_options(CompileOptions {
+        validate: false,
+        ..Default::default()
+    });
+    assert_eq!(compiler.options.validation(), ValidationLevel::None);
+    assert!(compiler.compile(&root).is_ok());
+
+    compiler.set_validation_level(ValidationLevel::Standard);
+    assert!(compiler.compile(&root).is_err());
+}
@@ -0,0 +1,252 @@
+use std::{borrow::Cow, cell::RefCell, collections::HashSet, path::PathBuf};
+
+use wgsl_parse::{
+    lexer::{Lexer, Token},
+    syntax::{ModulePath, TranslationUnit},
+};
+
+use crate::{Diagnostic, Error, ResolveError, Resolver};
+
+/// Resource limits of a compilation. See [`crate::CompileOptions::limits`].
+///
+/// Limits allow services compiling untrusted shaders (e.g. web playgrounds) to reject
+/// adversarial input before it exhausts the memory or the stack. They are checked on
+/// the source of each module before it is parsed. `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Limits {
+    /// Maximum number of modules loaded, including the root module.
+    pub max_modules: Option<usize>,
+    /// Maximum total size of the sources of the modules loaded, in bytes.
+    pub max_source_bytes: Option<usize>,
+    /// Maximum number of lexical tokens in a module. It bounds the size of the syntax
+    /// tree of the module.
+    pub max_tokens: Option<usize>,
+    /// Maximum nesting depth of a module. The depth is estimated from the source: each
+    /// bracket, brace, parenthesis, template list and operator (within an expression)
+    /// adds a level. It bounds the recursion depth of the passes on the syntax tree.
+    pub max_depth: Option<usize>,
+}
+
+/// Error produced when a [`Limits`] is exceeded.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum LimitError {
+    #[error("too many modules (the limit is {0})")]
+    Modules(usize),
+    #[error("the sources are too large (the limit is {0} bytes)")]
+    SourceBytes(usize),
+    #[error("the module has too many tokens (the limit is {0})")]
+    Tokens(usize),
+    #[error("the module is nested too deeply (the limit is {0})")]
+    Depth(usize),
+}
+
+impl Limits {
+    /// Whether no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the token count and nesting depth limits of a module source.
+    pub fn check_source(&self, source: &str) -> Result<(), LimitError> {
+        if self.max_tokens.is_none() && self.max_depth.is_none() {
+            return Ok(());
+        }
+
+        // depth of each enclosing bracket level, plus the operators of the current
+        // expression at this level.
+        let mut levels = vec![0usize];
+        let mut depth = 1;
+
+        for (i, tok) in Lexer::new(source).enumerate() {
+            let Ok((_, tok, _)) = tok else {
+                break; // the parser reports lexing errors.
+            };
+            if self.max_tokens.is_some_and(|max| i >= max) {
+                return Err(LimitError::Tokens(self.max_tokens.unwrap()));
+            }
+
+            match tok {
+                Token::SymBraceLeft => {
+                    // a block ends the expression before it, e.g. an `if` condition.
+                    depth -= std::mem::take(levels.last_mut().unwrap());
+                    levels.push(0);
+                    depth += 1;
+                }
+                Token::SymParenLeft | Token::SymBracketLeft | Token::TemplateArgsStart => {
+                    levels.push(0);
+                    depth += 1;
+                }
+                Token::SymParenRight
+                | Token::SymBracketRight
+                | Token::SymBraceRight
+                | Token::TemplateArgsEnd
+                    if levels.len() > 1 =>
+                {
+                    depth -= 1 + levels.pop().unwrap();
+                }
+                Token::SymSemicolon | Token::SymComma => {
+                    depth -= std::mem::take(levels.last_mut().unwrap());
+                }
+                tok if is_operator(&tok) => {
+                    *levels.last_mut().unwrap() += 1;
+                    depth += 1;
+                }
+                _ => (),
+            }
+            if self.max_depth.is_some_and(|max| depth > max) {
+                return Err(LimitError::Depth(self.max_depth.unwrap()));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_operator(tok: &Token) -> bool {
+    matches!(
+        tok,
+        Token::SymAnd
+            | Token::SymAndAnd
+            | Token::SymForwardSlash
+            | Token::SymBang
+            | Token::SymEqualEqual
+            | Token::SymNotEqual
+            | Token::SymGreaterThan
+            | Token::SymGreaterThanEqual
+            | Token::SymShiftRight
+            | Token::SymLessThan
+            | Token::SymLessThanEqual
+            | Token::SymShiftLeft
+            | Token::SymModulo
+            | Token::SymMinus
+            | Token::SymPeriod
+            | Token::SymPlus
+            | Token::SymOr
+            | Token::SymOrOr
+            | Token::SymStar
+            | Token::SymTilde
+            | Token::SymXor
+    )
+}
+
+/// A resolver that enforces [`Limits`] on the modules it resolves.
+pub(crate) struct LimitResolver<'a, R: Resolver> {
+    resolver: R,
+    limits: &'a Limits,
+    loaded: RefCell<(HashSet<ModulePath>, usize)>,
+}
+
+impl<'a, R: Resolver> LimitResolver<'a, R> {
+    pub(crate) fn new(resolver: R, limits: &'a Limits) -> Self {
+        Self {
+            resolver,
+            limits,
+            loaded: Default::default(),
+        }
+    }
+
+    fn check(&self, path: &ModulePath) -> Result<(), ResolveError> {
+        let source = self.resolver.resolve_source(path)?;
+        let err = |e: LimitError| {
+            Diagnostic::from(Error::from(e)).with_module_path(path.clone(), self.display_name(path))
+        };
+
+        let mut loaded = self.loaded.borrow_mut();
+        let (modules, bytes) = &mut *loaded;
+        if modules.insert(path.clone()) {
+            *bytes += source.len();
+        }
+        if let Some(max) = self.limits.max_modules.filter(|max| modules.len() > *max) {
+            return Err(err(LimitError::Modules(max)).into());
+        }
+        if let Some(max) = self.limits.max_source_bytes.filter(|max| *bytes > *max) {
+            return Err(err(LimitError::SourceBytes(max)).into());
+        }
+        self.limits
+            .check_source(&source)
+            .map_err(|e| err(e).with_source(source.to_string()).into())
+    }
+}
+
+impl<R: Resolver> Resolver for LimitResolver<'_, R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.check(path)?;
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        self.check(path)?;
+        self.resolver.resolve_module(path)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+#[test]
+fn test_limits() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let limits = Limits {
+        max_depth: Some(16),
+        ..Default::default()
+    };
+    assert!(
+        limits
+            .check_source("fn f() { let x = (1 + 2) * 3; }")
+            .is_ok()
+    );
+    let deep = format!("const x = {};", ["1"; 20].join(" + "));
+    assert_eq!(limits.check_source(&deep), Err(LimitError::Depth(16)));
+    let nested = format!("const x = {}1{};", "(".repeat(20), ")".repeat(20));
+    assert_eq!(limits.check_source(&nested), Err(LimitError::Depth(16)));
+    let flat = "const x = 1 + 1;\n".repeat(100);
+    assert!(limits.check_source(&flat).is_ok());
+    let ifs = "if a < b { x = 1; }\n".repeat(40);
+    let ifs = format!("fn f() {{ {ifs} if a < b {{ if c > d {{}} }} }}");
+    assert!(limits.check_source(&ifs).is_ok());
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::a::f; @compute @workgroup_size(1) fn main() { f(); }".into(),
+    );
+    resolver.add_module(
+        "package::a".parse().unwrap(),
+        "import package::b::g; fn f() { g(); }".into(),
+    );
+    resolver.add_module("package::b".parse().unwrap(), "fn g() {}".into());
+
+    let compile = |limits: Limits| {
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+        compiler.set_options(CompileOptions {
+            limits,
+            ..Default::default()
+        });
+        compiler
+            .compile(&"package::main".parse().unwrap())
+            .map(|_| ())
+            .map_err(|e| Diagnostic::from(e).error.to_string())
+    };
+    assert!(compile(Limits::default()).is_ok());
+    let limits = Limits {
+        max_modules: Some(3),
+        max_source_bytes: Some(200),
+        max_tokens: Some(30),
+        max_depth: Some(8),
+    };
+    assert!(compile(limits).is_ok());
+    let err = compile(Limits {
+        max_modules: Some(2),
+        ..limits
+    });
+    assert_eq!(err.unwrap_err(), LimitError::Modules(2).to_string());
+    let err = compile(Limits {
+        max_tokens: Some(10),
+        ..limits
+    });
+    assert_eq!(err.unwrap_err(), LimitError::Tokens(10).to_string());
+}
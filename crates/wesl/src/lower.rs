@@ -1,4 +1,14 @@
-use crate::{Error, visit::Visit};
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::{
+    Error, FreshNames, ValidateError,
+    idents::builtin_ident,
+    reflect::Folder,
+    validate::{TRACKED_EXTENSIONS, required_extensions},
+    visit::Visit,
+};
 
 use wgsl_parse::syntax::*;
 
@@ -7,9 +17,10 @@ use wgsl_parse::syntax::*;
 ///
 /// Currently, `lower` performs the following transforms:
 /// * remove aliases (inlined)
-/// * remove consts (inlined)
+/// * remove consts (inlined, including consts declared in other modules)
 /// * remove deprecated, non-standard attributes
 /// * remove import declarations
+/// * remove unused extensions from `enable` directives (see [`remove_unused_enables`])
 ///
 /// with the `eval` feature flag enabled, it performs additional transforms:
 /// * evaluate const-expressions (including calls to const functions)
@@ -34,8 +45,7 @@ pub fn lower(wesl: &mut TranslationUnit) -> Result<(), Error> {
     #[cfg(not(feature = "eval"))]
     {
         // these are redundant with eval::lower.
-        remove_type_aliases(wesl);
-        remove_global_consts(wesl);
+        remove_aliases_and_consts(wesl)?;
     }
     #[cfg(feature = "eval")]
     {
@@ -43,6 +53,8 @@ pub fn lower(wesl: &mut TranslationUnit) -> Result<(), Error> {
         use crate::eval::{Context, Exec, Lower, mark_functions_const};
         use wgsl_parse::Decorated;
         mark_functions_const(wesl);
+        // the evaluation does not terminate with cyclic declarations.
+        sorted_inlined_decls(wesl)?;
 
         // we want to drop wesl2 at the end of the block for idents use_count
         {
@@ -61,65 +73,588 @@ pub fn lower(wesl: &mut TranslationUnit) -> Result<(), Error> {
             }
         }
     }
+
+    remove_unused_enables(wesl);
     Ok(())
 }
 
-/// Eliminate all type aliases.
-/// Naga doesn't like this: `alias T = u32; vec<T>`
-#[allow(unused)]
-fn remove_type_aliases(wesl: &mut TranslationUnit) {
-    let take_next_alias = |wesl: &mut TranslationUnit| {
-        let index = wesl
+/// Specialize a translation unit for a configuration: substitute values for module-scope
+/// `const` and `override` declarations, then fold the const-expressions and prune the
+/// code paths that became unreachable, e.g. `if USE_SHADOWS { ... }` blocks. See
+/// [`lower`].
+///
+/// Specialized overrides become const-declarations and lose their `@id`. Declarations
+/// that are no longer used by the entry points or the remaining overrides are removed.
+///
+/// Fails with [`EvalError::UnknownDecl`](crate::EvalError::UnknownDecl) if a name is not
+/// a module-scope `const` or `override` declaration.
+#[cfg(feature = "eval")]
+pub fn specialize(
+    wesl: &mut TranslationUnit,
+    constants: &std::collections::HashMap<String, Expression>,
+) -> Result<(), Error> {
+    use crate::{EvalError, SyntaxUtil, strip::strip_except};
+
+    for (name, value) in constants {
+        let decl = wesl
             .global_declarations
-            .iter()
-            .position(|decl| matches!(decl.node(), GlobalDeclaration::TypeAlias(_)));
-        index.map(|index| {
-            let decl = wesl.global_declarations.swap_remove(index);
-            match decl.into_inner() {
-                GlobalDeclaration::TypeAlias(alias) => alias,
-                _ => unreachable!(),
+            .iter_mut()
+            .find_map(|decl| match decl.node_mut() {
+                GlobalDeclaration::Declaration(d)
+                    if *d.ident.name() == *name
+                        && matches!(d.kind, DeclarationKind::Const | DeclarationKind::Override) =>
+                {
+                    Some(d)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| EvalError::UnknownDecl(name.clone()))?;
+        decl.kind = DeclarationKind::Const;
+        decl.initializer = Some(value.clone().into());
+        decl.attributes
+            .retain(|attr| !matches!(attr.node(), Attribute::Id(_)));
+    }
+
+    lower(wesl)?;
+
+    let keep = wesl
+        .entry_points()
+        .cloned()
+        .chain(
+            wesl.global_declarations
+                .iter()
+                .filter_map(|decl| match decl.node() {
+                    GlobalDeclaration::Declaration(d) if d.kind == DeclarationKind::Override => {
+                        Some(d.ident.clone())
+                    }
+                    _ => None,
+                }),
+        )
+        .collect::<HashSet<_>>();
+    strip_except(wesl, &keep);
+    Ok(())
+}
+
+/// Remove the extensions of `enable` directives that are no longer used, e.g. after
+/// conditional compilation or stripping removed the declarations using them.
+///
+/// Only the `f16`, `clip_distances`, `dual_source_blending` and `subgroups` extensions
+/// are removed. `enable` directives left with no extension are removed.
+pub(crate) fn remove_unused_enables(wesl: &mut TranslationUnit) {
+    let required = required_extensions(wesl)
+        .into_iter()
+        .map(|(ext, ..)| ext)
+        .collect::<HashSet<_>>();
+    wesl.global_directives
+        .retain_mut(|directive| match directive {
+            GlobalDirective::Enable(enable) => {
+                enable.extensions.retain(|ext| {
+                    !TRACKED_EXTENSIONS.contains(&ext.as_str()) || required.contains(ext.as_str())
+                });
+                !enable.extensions.is_empty()
+            }
+            _ => true,
+        });
+}
+
+/// Convert the `switch` statements to `if` chains, for WGSL implementations with poor
+/// `switch` support. See [`crate::CompileOptions::lower_switches`].
+///
+/// The `switch` expression is evaluated once, in a `let` declaration. Like in WGSL, its
+/// type is the concrete type of the `case` selectors, if one is concrete, e.g. the `let`
+/// declaration of `switch 1 { case 1u: {} default: {} }` is a `u32`. The `break`
+/// statements ending a clause are removed. Switches with other `break` statements are
+/// kept, since these would break out of the enclosing loop instead.
+///
+/// Without the `eval` crate feature, the type is only inferred from literal selectors.
+pub fn lower_switches(wesl: &mut TranslationUnit) {
+    fn rec(stat: &mut StatementNode, names: &mut FreshNames, folder: &mut Folder) {
+        for stat in Visit::<StatementNode>::visit_mut(stat.node_mut()) {
+            rec(stat, names, folder);
+        }
+        if let Statement::Switch(switch) = stat.node() {
+            if let Some(lowered) = switch_to_if(switch, names, folder) {
+                *stat.node_mut() = lowered;
             }
+        }
+    }
+    let source = wesl.clone();
+    let mut folder = Folder::new(&source);
+    let mut names = FreshNames::new(wesl);
+    for stat in Visit::<StatementNode>::visit_mut(wesl) {
+        rec(stat, &mut names, &mut folder);
+    }
+}
+
+/// Whether a sequence of statements has a `break` targeting the enclosing `switch`.
+fn has_break(stats: &[StatementNode]) -> bool {
+    stats.iter().any(|stat| match stat.node() {
+        Statement::Break(_) => true,
+        Statement::Loop(_) | Statement::For(_) | Statement::While(_) | Statement::Switch(_) => {
+            false
+        }
+        stat => Visit::<StatementNode>::visit(stat).any(|s| has_break(std::slice::from_ref(s))),
+    })
+}
+
+fn switch_to_if(
+    switch: &SwitchStatement,
+    names: &mut FreshNames,
+    folder: &mut Folder,
+) -> Option<Statement> {
+    let mut cases = Vec::new();
+    let mut default = None;
+    for clause in &switch.clauses {
+        let mut body = clause.body.clone();
+        if let Some(Statement::Break(_)) = body.statements.last().map(|stat| stat.node()) {
+            body.statements.pop();
+        }
+        if has_break(&body.statements) {
+            return None;
+        }
+        if clause
+            .case_selectors
+            .iter()
+            .any(|sel| matches!(sel, CaseSelector::Default))
+        {
+            // the other selectors of the default clause are redundant.
+            default = Some(body);
+        } else {
+            cases.push((&clause.case_selectors, body));
+        }
+    }
+
+    // the selector and the case selectors are converted to a common type. An abstract
+    // selector would otherwise be an `i32`.
+    let ty = switch
+        .clauses
+        .iter()
+        .flat_map(|clause| &clause.case_selectors)
+        .find_map(|sel| match sel {
+            CaseSelector::Expression(expr) => folder.fold_int(expr)?.1,
+            CaseSelector::Default => None,
         })
+        .and_then(builtin_ident)
+        .map(|id| TypeExpression::new(id.clone()));
+
+    let ident = names.fresh_ident("selector");
+    let decl = Declaration {
+        attributes: Vec::new(),
+        kind: DeclarationKind::Let,
+        ident: ident.clone(),
+        ty,
+        initializer: Some(switch.expression.clone()),
+    };
+    let condition = |selectors: &Vec<CaseSelector>| {
+        selectors
+            .iter()
+            .filter_map(|sel| match sel {
+                CaseSelector::Expression(expr) => Some(expr),
+                CaseSelector::Default => None,
+            })
+            .map(|expr| {
+                let right = match expr.node() {
+                    Expression::Literal(_) | Expression::TypeOrIdentifier(_) => expr.clone(),
+                    _ => Expression::Parenthesized(ParenthesizedExpression {
+                        expression: expr.clone(),
+                    })
+                    .into(),
+                };
+                let left = Expression::TypeOrIdentifier(TypeExpression::new(ident.clone()));
+                Expression::Binary(BinaryExpression {
+                    operator: BinaryOperator::Equality,
+                    left: left.into(),
+                    right,
+                })
+            })
+            .reduce(|left, right| {
+                Expression::Binary(BinaryExpression {
+                    operator: BinaryOperator::ShortCircuitOr,
+                    left: left.into(),
+                    right: right.into(),
+                })
+            })
+            .unwrap()
     };
 
-    while let Some(mut alias) = take_next_alias(wesl) {
-        // we rename the alias and all references to its type expression,
-        // and drop the alias declaration.
-        alias.ident.rename(format!("{}", alias.ty));
+    let mut statements = vec![Statement::Declaration(decl).into()];
+    let mut cases = cases.into_iter();
+    match cases.next() {
+        Some((selectors, body)) => {
+            let stat = IfStatement {
+                attributes: switch.body_attributes.clone(),
+                if_clause: IfClause {
+                    expression: condition(selectors).into(),
+                    body,
+                },
+                else_if_clauses: cases
+                    .map(|(selectors, body)| ElseIfClause {
+                        attributes: Vec::new(),
+                        expression: condition(selectors).into(),
+                        body,
+                    })
+                    .collect(),
+                else_clause: default.map(|body| ElseClause {
+                    attributes: Vec::new(),
+                    body,
+                }),
+            };
+            statements.push(Statement::If(stat).into());
+        }
+        None => statements.extend(default.map(|body| Statement::Compound(body).into())),
+    }
+    Some(Statement::Compound(CompoundStatement {
+        attributes: switch.attributes.clone(),
+        statements,
+    }))
+}
+
+/// Whether a declaration is inlined at usage sites by [`lower`], i.e. it is a type alias
+/// or a const-declaration.
+fn is_inlined(decl: &GlobalDeclaration) -> bool {
+    matches!(
+        decl,
+        GlobalDeclaration::TypeAlias(_)
+            | GlobalDeclaration::Declaration(Declaration {
+                kind: DeclarationKind::Const,
+                ..
+            })
+    )
+}
+
+/// The indices of the module-scope type aliases and const-declarations in dependency
+/// order: each declaration comes after the declarations its type or initializer refers
+/// to.
+///
+/// The declarations are sorted individually, not by module: modules importing each other
+/// are fine as long as the declarations are not cyclic.
+///
+/// Fails with [`ValidateError::Cycle`] if a declaration refers to itself.
+fn sorted_inlined_decls(wesl: &TranslationUnit) -> Result<Vec<usize>, Error> {
+    fn refs(ty: &TypeExpression, res: &mut Vec<Ident>) {
+        res.push(ty.ident.clone());
+        for ty in Visit::<TypeExpression>::visit(ty) {
+            refs(ty, res);
+        }
+    }
+
+    fn visit<'a>(
+        id: &'a Ident,
+        decls: &HashMap<&'a Ident, (usize, &'a GlobalDeclaration)>,
+        stack: &mut Vec<&'a Ident>,
+        sorted: &mut Vec<usize>,
+    ) -> Result<(), ValidateError> {
+        let (i, decl) = decls[id];
+        if sorted.contains(&i) {
+            return Ok(());
+        }
+        if let Some(pos) = stack.iter().position(|x| *x == id) {
+            let via = stack.get(pos + 1).unwrap_or(&id);
+            return Err(ValidateError::Cycle(id.to_string(), via.to_string()));
+        }
+        stack.push(id);
+        let mut deps = Vec::new();
+        match decl {
+            GlobalDeclaration::Declaration(decl) => {
+                let tys = decl
+                    .initializer
+                    .iter()
+                    .flat_map(|expr| Visit::<TypeExpression>::visit(expr.node()));
+                for ty in tys {
+                    refs(ty, &mut deps);
+                }
+            }
+            GlobalDeclaration::TypeAlias(alias) => refs(&alias.ty, &mut deps),
+            _ => {}
+        }
+        for dep in &deps {
+            if let Some((dep, _)) = decls.get_key_value(dep) {
+                visit(dep, decls, stack, sorted)?;
+            }
+        }
+        stack.pop();
+        sorted.push(i);
+        Ok(())
     }
+
+    let decls = wesl
+        .global_declarations
+        .iter()
+        .enumerate()
+        .filter(|(_, decl)| is_inlined(decl))
+        .filter_map(|(i, decl)| Some((decl.ident()?, (i, decl.node()))))
+        .collect::<HashMap<_, _>>();
+    let mut sorted = Vec::new();
+    for id in decls.keys().sorted_by_key(|id| decls[*id].0) {
+        visit(id, &decls, &mut Vec::new(), &mut sorted)?;
+    }
+    Ok(sorted)
 }
 
-/// Eliminate all const-declarations.
+/// Eliminate all type aliases and const-declarations.
+/// Naga doesn't like this: `alias T = u32; vec<T>`
 ///
-/// Replace usages of the const-declaration with its expression.
+/// Replace usages of type aliases with their type expression, and usages of
+/// const-declarations with their expression. Declarations are eliminated in dependency
+/// order, so the expressions do not refer to other eliminated declarations.
 ///
 /// # Panics
 /// panics if the const-declaration is ill-formed, i.e. has no initializer.
 #[allow(unused)]
-fn remove_global_consts(wesl: &mut TranslationUnit) {
-    let take_next_const = |wesl: &mut TranslationUnit| {
-        let index = wesl.global_declarations.iter().position(|decl| {
-            matches!(
-                decl.node(),
-                GlobalDeclaration::Declaration(Declaration {
-                    kind: DeclarationKind::Const,
-                    ..
-                })
-            )
-        });
-        index.map(|index| {
-            let decl = wesl.global_declarations.swap_remove(index);
-            match decl.into_inner() {
-                GlobalDeclaration::Declaration(d) => d,
-                _ => unreachable!(),
+fn remove_aliases_and_consts(wesl: &mut TranslationUnit) -> Result<(), Error> {
+    for i in sorted_inlined_decls(wesl)? {
+        // we rename the declaration and all references to its type expression, or to its
+        // expression in parentheses.
+        match wesl.global_declarations[i].node_mut() {
+            GlobalDeclaration::TypeAlias(alias) => alias.ident.rename(alias.ty.to_string()),
+            GlobalDeclaration::Declaration(decl) => {
+                let expr = format!("({})", decl.initializer.as_ref().unwrap());
+                decl.ident.rename(expr);
             }
-        })
+            _ => unreachable!(),
+        }
+    }
+    // and drop the declarations.
+    wesl.global_declarations.retain(|decl| !is_inlined(decl));
+    Ok(())
+}
+
+#[test]
+fn test_global_consts() {
+    use crate::{CompileOptions, ValidationLevel, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::consts::{N, WG, STEPS};
+        var<private> data: array<f32, N * 2>;
+        @compute @workgroup_size(WG) fn main() {
+            for (var i = 0; i < STEPS; i++) { data[i] = 1.0; }
+        }"
+        .into(),
+    );
+    resolver.add_module(
+        "package::consts".parse().unwrap(),
+        "import package::base::BASE;
+        const STEPS = i32(N);
+        const WG = N * 8u;
+        const N = BASE + 1;"
+            .into(),
+    );
+    resolver.add_module("package::base".parse().unwrap(), "const BASE = 3u;".into());
+
+    let compile = |resolver: &VirtualResolver, validation_level| {
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        compiler.set_options(CompileOptions {
+            lower: true,
+            validation_level,
+            ..Default::default()
+        });
+        compiler.compile(&"package::main".parse().unwrap())
+    };
+    let wgsl = compile(&resolver, ValidationLevel::Standard).unwrap_or_else(|e| panic!("{e}"));
+    let wgsl = wgsl.to_string();
+    assert!(!wgsl.contains("BASE") && !wgsl.contains("const"));
+    wgsl_parse::parse_str(&wgsl).unwrap();
+    #[cfg(feature = "eval")]
+    assert!(wgsl.contains("array<f32, 8>") && wgsl.contains("@workgroup_size(32u)"));
+
+    resolver.add_module(
+        "package::base".parse().unwrap(),
+        "import package::consts::WG; const BASE = WG;".into(),
+    );
+    let Err(err) = compile(&resolver, ValidationLevel::None) else {
+        panic!("expected a cycle error");
     };
+    assert!(err.to_string().contains("is cyclic"));
+}
+
+#[test]
+fn test_cyclic_modules() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
 
-    while let Some(mut decl) = take_next_const(wesl) {
-        // we rename the const and all references to its expression in parentheses,
-        // and drop the const declaration.
-        decl.ident
-            .rename(format!("({})", decl.initializer.unwrap()));
+    // the modules import each other, but the declarations are not cyclic.
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::a::{A2, S, arr};
+        @compute @workgroup_size(A2) fn main() { let s = S(1u); arr[0] = s.x; }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::a".parse().unwrap(),
+        "import package::b::{B1, Arr};
+        alias A = Arr;
+        var<private> arr: A;
+        const A1 = 2u;
+        const A2 = B1 * 2u;
+        struct S { x: u32 }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::b".parse().unwrap(),
+        "import package::a::{A1, S};
+        alias Arr = array<u32, B1>;
+        const B1 = A1 + 1u;
+        const_assert B1 == 3u;
+        fn f() -> S { return S(B1); }"
+            .into(),
+    );
+
+    for lazy in [true, false] {
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+        compiler.set_options(CompileOptions {
+            lower: true,
+            lazy,
+            ..Default::default()
+        });
+        let wgsl = compiler
+            .compile(&"package::main".parse().unwrap())
+            .unwrap_or_else(|e| panic!("{e}"))
+            .to_string();
+        assert!(!wgsl.contains("alias") && !wgsl.contains("const "));
+        assert!(!wgsl.contains("Arr") && !wgsl.contains("A1") && !wgsl.contains("B1"));
+        wgsl_parse::parse_str(&wgsl).unwrap();
     }
 }
+
+#[cfg(feature = "eval")]
+#[test]
+fn test_specialize() {
+    use std::collections::HashMap;
+
+    let source = "
+        const USE_SHADOWS = false;
+        @id(0) override quality: u32 = 1;
+        override scale: f32;
+        @group(0) @binding(0) var<uniform> light: vec4f;
+        fn shadow() -> f32 { return light.w; }
+        fn sample(x: f32) -> f32 {
+            if quality == 0u { return x; } else if quality == 1u { return x * 2.0; }
+            return x * 4.0;
+        }
+        @fragment fn main() -> @location(0) vec4f {
+            var color = light * scale;
+            if USE_SHADOWS { color *= shadow(); }
+            color.x = sample(color.x);
+            return color;
+        }";
+    let specialize = |constants: &[(&str, &str)]| {
+        let mut wesl: TranslationUnit = source.parse().unwrap();
+        wesl.retarget_idents();
+        let constants = constants
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.parse().unwrap()))
+            .collect::<HashMap<_, _>>();
+        specialize(&mut wesl, &constants).map(|()| wesl)
+    };
+    use crate::SyntaxUtil;
+
+    let wgsl = specialize(&[("USE_SHADOWS", "true"), ("quality", "1u")]).unwrap();
+    let wgsl_str = wgsl.to_string();
+    assert!(wgsl.find_decl("shadow").is_some());
+    assert!(!wgsl_str.contains("quality"));
+    assert!(!wgsl_str.contains("if "));
+    assert!(wgsl.find_decl("scale").is_some());
+
+    let wgsl = specialize(&[("quality", "0u")]).unwrap();
+    assert!(wgsl.find_decl("shadow").is_none());
+    assert!(!wgsl.to_string().contains("x * 2"));
+
+    assert!(specialize(&[("light", "vec4f()")]).is_err());
+}
+
+#[test]
+fn test_lower_switches() {
+    let mut wesl = wgsl_parse::parse_str(
+        "fn f(x: i32) -> i32 {
+            var y = 0;
+            switch x + 1 {
+                case 1, 2: { y = 1; break; }
+                case 3 | 4, default: { y = 2; }
+                case 5 { y = 3; }
+            }
+            loop {
+                switch x { case 0: { if y > 0 { break; } } default: {} }
+                break;
+            }
+            return y;
+        }",
+    )
+    .unwrap();
+    crate::SyntaxUtil::retarget_idents(&mut wesl);
+    lower_switches(&mut wesl);
+    let out = wesl.to_string();
+    assert!(out.contains("let selector = x + 1;"), "{out}");
+    assert!(out.contains("if selector == 1 || selector == 2 {"), "{out}");
+    assert!(out.contains("else if selector == 5 {"), "{out}");
+    assert!(out.contains("else {\n"), "{out}");
+    assert!(!out.contains("y = 1;\n            break;"), "{out}");
+    // the `break` in the `if` would break out of the loop.
+    assert!(out.contains("switch x"), "{out}");
+
+    // the abstract selector has the type of the cases.
+    let mut wesl =
+        wgsl_parse::parse_str("fn f() { switch 1 { case 1u: {} default: {} } }").unwrap();
+    crate::SyntaxUtil::retarget_idents(&mut wesl);
+    lower_switches(&mut wesl);
+    let out = wesl.to_string();
+    assert!(out.contains("let selector: u32 = 1;"), "{out}");
+    assert!(out.contains("if selector == 1u {"), "{out}");
+}
+
+#[cfg(feature = "eval")]
+#[test]
+fn test_constructor_types() {
+    let lower_str = |source: &str| {
+        let mut wesl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wesl);
+        lower(&mut wesl)
+            .map(|()| wesl.to_string())
+            .map_err(|e| crate::Diagnostic::from(e).error.to_string())
+    };
+    let wgsl = lower_str(
+        "struct S { a: u32, b: f32 }
+        fn f(x: i32, y: f32, u: vec2u, p: vec2f) {
+            let s = S(1, y);
+            let v = vec3(x, 2, 3);
+            let m = mat2x2(p, vec2(y));
+            let n = mat2x3(y, 2, 3, 4, 5, 6.0);
+            let a = array(1u, u.x);
+            let c = vec3f(p, 2);
+        }",
+    )
+    .unwrap();
+    for decl in [
+        "let s: S",
+        "let v: vec3<i32>",
+        "let m: mat2x2<f32>",
+        "let n: mat2x3<f32>",
+        "let a: array<u32, 2>",
+        "let c: vec3<f32>",
+    ] {
+        assert!(wgsl.contains(decl), "{wgsl}");
+    }
+
+    let err = |source: &str| lower_str(source).unwrap_err();
+    assert_eq!(
+        err("fn f(x: vec4f) { let v = vec2(x); }"),
+        "cannot convert from `vec4<f32>` to `vec2<f32>`"
+    );
+    assert_eq!(
+        err("fn f(x: vec3f) { let m = mat2x2<f32>(x, x); }"),
+        "cannot convert from `vec3<f32>` to `vec2<f32>`"
+    );
+    assert_eq!(
+        err("fn f(x: f32) { let a = array<f32, 2>(x); }"),
+        "incorrect number of arguments to `array`, expected `2`, got `1`"
+    );
+    assert_eq!(
+        err("fn f(x: f32) { let v = vec2<u32>(x, 1u); }"),
+        "cannot convert from `f32` to `u32`"
+    );
+    assert_eq!(
+        err("struct S { a: u32, b: f32 } fn f(x: f32) { let s = S(x, 1); }"),
+        "invalid parameter type, expected `u32`, got `f32`"
+    );
+}
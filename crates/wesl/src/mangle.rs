@@ -85,6 +85,8 @@ impl Mangler for HashMangler {
 /// This is WESL's default mangler.
 ///
 /// If a component contains `_`, it is prefixed by `_`, followed by the number of `_`.
+/// If a component is not a valid identifier (e.g. `"my-shader.v2"`), it is replaced by
+/// `as_` followed by its UTF-8 bytes in hexadecimal. (`as` is not a valid module name.)
 /// This scheme guarantees that paths can be unambiguously mangled and unmangled.
 #[derive(Default, Clone, Debug)]
 pub struct EscapeMangler;
@@ -98,6 +100,8 @@ impl EscapeMangler {
                 "import_{count}_{}",
                 comp.split('/').map(Self::escape_component).format("_")
             )
+        } else if !comp.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            format!("as_{:02x}", comp.bytes().format(""))
         } else {
             let underscores = comp.chars().filter(|c| *c == '_').count();
             if underscores > 0 {
@@ -123,7 +127,14 @@ impl EscapeMangler {
             Some((count, &part[digits..]))
         }
 
-        let component = if part.is_empty() {
+        let component = if part == "as" {
+            let hex = parts.next()?;
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                .collect::<Option<Vec<_>>>()?;
+            String::from_utf8(bytes).ok()?
+        } else if part.is_empty() {
             let first = parts.next()?;
             let (n, rem) = extract_count(first)?;
 
@@ -218,6 +229,11 @@ fn test_escape_mangler() {
         ("bevy/pbr", "import_2_bevy_pbr_item"),
         ("bevy/pbr::lighting", "import_2_bevy_pbr_lighting_item"),
         ("bevy_pbr::lighting", "_1bevy_pbr_lighting_item"),
+        // test components that are not identifiers
+        (
+            r#"package::"my-shader.v2""#,
+            "package_as_6d792d7368616465722e7632_item",
+        ),
     ];
 
     for (p, m) in tests {
@@ -0,0 +1,325 @@
+//! Naming convention lints. See [`NamingLints`].
+
+use std::fmt::Display;
+
+use itertools::Itertools;
+use wgsl_parse::{
+    lexer::{Lexer, Token},
+    span::Span,
+    syntax::TranslationUnit,
+};
+
+use crate::{DeclKind, Diagnostic, Error, FreshNames, ValidateError};
+
+/// A naming convention of identifiers. See [`NamingLints`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum NamingCase {
+    /// `snake_case`
+    Snake,
+    /// `SCREAMING_SNAKE_CASE`
+    ScreamingSnake,
+    /// `PascalCase`
+    Pascal,
+    /// `camelCase`
+    Camel,
+}
+
+impl NamingCase {
+    /// Whether a name follows the convention. Leading underscores are ignored, and
+    /// acronyms are allowed in `PascalCase` and `camelCase` names, e.g. `HDRTexture`.
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim_start_matches('_');
+        let mut chars = name.chars();
+        match self {
+            Self::Snake => !name.chars().any(char::is_uppercase),
+            Self::ScreamingSnake => !name.chars().any(char::is_lowercase),
+            Self::Pascal => chars.next().is_none_or(|c| !c.is_lowercase()) && !name.contains('_'),
+            Self::Camel => chars.next().is_none_or(|c| !c.is_uppercase()) && !name.contains('_'),
+        }
+    }
+
+    /// Convert a name to the convention, e.g. `HDRTexture` becomes `hdr_texture` in
+    /// `snake_case`. Leading underscores are kept.
+    pub fn convert(&self, name: &str) -> String {
+        let trimmed = name.trim_start_matches('_');
+        let prefix = &name[..name.len() - trimmed.len()];
+        let words = words(trimmed);
+        let capitalize = |word: &String| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        };
+        let name = match self {
+            Self::Snake => words.join("_"),
+            Self::ScreamingSnake => words.join("_").to_uppercase(),
+            Self::Pascal => words.iter().map(capitalize).join(""),
+            Self::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    if i == 0 {
+                        word.clone()
+                    } else {
+                        capitalize(word)
+                    }
+                })
+                .join(""),
+        };
+        format!("{prefix}{name}")
+    }
+}
+
+impl Display for NamingCase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Snake => f.write_str("snake_case"),
+            Self::ScreamingSnake => f.write_str("SCREAMING_SNAKE_CASE"),
+            Self::Pascal => f.write_str("PascalCase"),
+            Self::Camel => f.write_str("camelCase"),
+        }
+    }
+}
+
+/// The lowercase words of a name, split at underscores and case changes.
+fn words(name: &str) -> Vec<String> {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        // `fooBar` and `HDRTexture` are split before the `B` and the `T`.
+        let boundary = c.is_uppercase()
+            && !word.is_empty()
+            && (!chars[i - 1].is_uppercase()
+                || chars.get(i + 1).is_some_and(|next| next.is_lowercase()));
+        if boundary {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Naming convention lints for the module-scope declarations of the local package.
+/// See [`crate::CompileOptions::naming`].
+///
+/// All lints are disabled by default, see [`Self::recommended`]. Declarations of
+/// external packages are not checked.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct NamingLints {
+    /// The convention of function names.
+    pub functions: Option<NamingCase>,
+    /// The convention of `const` and `override` declaration names.
+    pub constants: Option<NamingCase>,
+    /// The convention of module-scope `var` declaration names.
+    pub variables: Option<NamingCase>,
+    /// The convention of struct and type alias names.
+    pub types: Option<NamingCase>,
+    /// Reject names starting with `__`, which are reserved by WGSL.
+    pub reserved: bool,
+    /// Fail the compilation with the first violation, instead of emitting warnings.
+    pub deny: bool,
+}
+
+impl NamingLints {
+    /// `snake_case` functions and variables, `SCREAMING_SNAKE_CASE` constants,
+    /// `PascalCase` types, and no reserved names.
+    pub fn recommended() -> Self {
+        Self {
+            functions: Some(NamingCase::Snake),
+            constants: Some(NamingCase::ScreamingSnake),
+            variables: Some(NamingCase::Snake),
+            types: Some(NamingCase::Pascal),
+            reserved: true,
+            deny: false,
+        }
+    }
+
+    /// Whether a lint is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.functions.is_some()
+            || self.constants.is_some()
+            || self.variables.is_some()
+            || self.types.is_some()
+            || self.reserved
+    }
+
+    fn case(&self, kind: DeclKind) -> Option<NamingCase> {
+        match kind {
+            DeclKind::Function => self.functions,
+            DeclKind::Const | DeclKind::Override => self.constants,
+            DeclKind::Var => self.variables,
+            DeclKind::Struct | DeclKind::Alias => self.types,
+        }
+    }
+}
+
+/// A declaration that breaks a [`NamingLints`] rule, with a rename suggestion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamingViolation {
+    pub kind: DeclKind,
+    pub name: String,
+    /// The convention the name does not follow. `None` if the name is only reserved.
+    pub case: Option<NamingCase>,
+    /// The suggested name. It follows the conventions and is not used in the module.
+    pub suggestion: String,
+    /// The span of the declaration.
+    pub span: Span,
+}
+
+impl From<&NamingViolation> for Diagnostic<Error> {
+    fn from(v: &NamingViolation) -> Self {
+        let reason = match v.case {
+            Some(case) => format!("should be {case}"),
+            None => "starts with `__`, which is reserved".to_string(),
+        };
+        let err = ValidateError::Naming(v.kind, v.name.clone(), reason, v.suggestion.clone());
+        let mut d = Diagnostic::from(Error::from(err)).with_span(v.span);
+        d.detail.declaration = Some(v.name.clone());
+        d
+    }
+}
+
+/// Check the names of the module-scope declarations of a module.
+///
+/// The violations are in declaration order. See [`fix_naming`] to apply the rename
+/// suggestions.
+pub fn check_naming(wesl: &TranslationUnit, lints: &NamingLints) -> Vec<NamingViolation> {
+    let mut names = FreshNames::new(wesl);
+    wesl.global_declarations
+        .iter()
+        .filter_map(|decl| {
+            let kind = DeclKind::of(decl)?;
+            let name = decl.ident()?.to_string();
+            let case = lints.case(kind).filter(|case| !case.matches(&name));
+            let reserved = lints.reserved && name.starts_with("__");
+            if case.is_none() && !reserved {
+                return None;
+            }
+            let mut suggestion = if reserved {
+                name.trim_start_matches('_').to_string()
+            } else {
+                name.clone()
+            };
+            if let Some(case) = lints.case(kind) {
+                suggestion = case.convert(&suggestion);
+            }
+            Some(NamingViolation {
+                kind,
+                case,
+                suggestion: names.fresh_name(&suggestion),
+                name,
+                span: decl.span(),
+            })
+        })
+        .collect()
+}
+
+/// Rename the declarations of a WESL source that break the naming lints, and their
+/// uses in the module, preserving the formatting of the source.
+///
+/// Modules importing the renamed declarations must be updated as well, e.g. by calling
+/// this function on them with the same lints. Member accesses (`a.b`) are not renamed.
+/// Renaming entry points and overrides changes the interface seen by the host.
+pub fn fix_naming(source: &str, lints: &NamingLints) -> Result<String, Error> {
+    let wesl = wgsl_parse::parse_str(source)?;
+    let renames = check_naming(&wesl, lints);
+    let mut res = String::with_capacity(source.len());
+    let mut last = 0;
+    let mut prev = None;
+    for (start, tok, end) in Lexer::new(source).filter_map(Result::ok) {
+        let rename = match &tok {
+            Token::Ident(name) if prev != Some(Token::SymPeriod) => {
+                renames.iter().find(|r| r.name == *name)
+            }
+            _ => None,
+        };
+        if let Some(rename) = rename {
+            res.push_str(&source[last..start]);
+            res.push_str(&rename.suggestion);
+            last = end;
+        }
+        prev = Some(tok);
+    }
+    res.push_str(&source[last..]);
+    Ok(res)
+}
+
+#[test]
+fn test_naming() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    assert_eq!(NamingCase::Snake.convert("HDRTexture"), "hdr_texture");
+    assert_eq!(NamingCase::Pascal.convert("light_data2"), "LightData2");
+    assert_eq!(
+        NamingCase::ScreamingSnake.convert("maxLights"),
+        "MAX_LIGHTS"
+    );
+    assert_eq!(NamingCase::Camel.convert("__get_color"), "__getColor");
+    assert!(NamingCase::Pascal.matches("HDRTexture"));
+
+    let source = "struct light_data { color: vec4f }
+        const maxLights = 4;
+        fn __helper(l: light_data) -> vec4f { return l.color; }
+        fn shadeLight(l: light_data) -> vec4f { return __helper(l) * f32(maxLights); }";
+    let lints = NamingLints::recommended();
+    let wesl = wgsl_parse::parse_str(source).unwrap();
+    let violations = check_naming(&wesl, &lints);
+    let renames = violations
+        .iter()
+        .map(|v| (v.name.as_str(), v.suggestion.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        renames,
+        [
+            ("light_data", "LightData"),
+            ("maxLights", "MAX_LIGHTS"),
+            ("__helper", "helper"),
+            ("shadeLight", "shade_light"),
+        ]
+    );
+    assert_eq!(violations[2].case, None);
+    let fixed = fix_naming(source, &lints).unwrap();
+    let wesl = wgsl_parse::parse_str(&fixed).unwrap();
+    assert!(check_naming(&wesl, &lints).is_empty(), "{fixed}");
+    assert!(fixed.contains("return helper(l) * f32(MAX_LIGHTS);"));
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module("package::main".parse().unwrap(), source.into());
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .set_naming_lints(lints.clone());
+    let root = "package::main".parse().unwrap();
+    let res = compiler.compile(&root).unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(res.warnings.len(), 4);
+    let warning = res.warnings[3].to_string();
+    assert!(warning.contains("rename it to `shade_light`"), "{warning}");
+
+    compiler.set_naming_lints(NamingLints {
+        deny: true,
+        ..lints
+    });
+    let Err(err) = compiler.compile(&root) else {
+        panic!("naming violations must fail with `deny`");
+    };
+    let err = err.to_string();
+    assert!(
+        err.contains("struct `light_data` should be PascalCase"),
+        "{err}"
+    );
+}
@@ -4,7 +4,8 @@ use std::{
 };
 
 use crate::{
-    Diagnostic, Error, ModulePath, SyntaxUtil, resolve::CodegenPkg, validate::validate_wesl,
+    Diagnostic, Error, ModulePath, ResolveError, SyntaxUtil, VersionReq, resolve::CodegenPkg,
+    validate::validate_wesl,
 };
 use quote::{format_ident, quote};
 use wgsl_parse::syntax::{PathOrigin, TranslationUnit};
@@ -46,6 +47,7 @@ use wgsl_parse::syntax::{PathOrigin, TranslationUnit};
 pub struct PkgBuilder {
     name: String,
     dependencies: Vec<&'static CodegenPkg>,
+    requirements: Vec<(String, VersionReq)>,
 }
 
 /// The type holding the source code of packages.
@@ -55,8 +57,12 @@ pub struct PkgBuilder {
 /// [`Self::build_artifact`] or [`Self::codegen`].
 pub struct Pkg {
     pub crate_name: String,
+    /// The package version, by default the version of the crate.
+    pub version: String,
     pub root: Module,
     pub dependencies: Vec<&'static CodegenPkg>,
+    /// Version requirements on the dependencies, see [`PkgBuilder::require`].
+    pub requirements: Vec<(String, VersionReq)>,
 }
 
 /// The type holding the source code of individual modules in packages.
@@ -82,6 +88,7 @@ impl PkgBuilder {
         Self {
             name: name.replace('-', "_"),
             dependencies: Vec::new(),
+            requirements: Vec::new(),
         }
     }
 
@@ -103,6 +110,15 @@ impl PkgBuilder {
         self
     }
 
+    /// Require a version of a package dependency, e.g. `pkg_lighting >= 0.3`.
+    ///
+    /// The requirement is checked by [`Pkg::validate`] and when resolving imports from
+    /// the dependency in consumers of this package.
+    pub fn require(mut self, pkg: &str, req: VersionReq) -> Self {
+        self.requirements.push((pkg.replace('-', "_"), req));
+        self
+    }
+
     /// Reads all files to include in the package, starting from the root module.
     ///
     /// The input path must point at the root file or folder. The package will include
@@ -178,11 +194,15 @@ impl PkgBuilder {
         let crate_name = std::env::var("CARGO_PKG_NAME")
             .expect("CARGO_PKG_NAME environment variable is not defined")
             .to_string();
+        let version = std::env::var("CARGO_PKG_VERSION")
+            .expect("CARGO_PKG_VERSION environment variable is not defined");
 
         Ok(Pkg {
             crate_name,
+            version,
             root: module,
             dependencies: self.dependencies,
+            requirements: self.requirements,
         })
     }
 }
@@ -243,15 +263,23 @@ impl Pkg {
             quote! { &#crate_name::#mod_name::PACKAGE }
         });
 
+        let reqs = self.requirements.iter().map(|(name, req)| {
+            let req = req.to_string();
+            quote! { (#name, #req) }
+        });
+
         let crate_name = &self.crate_name;
+        let version = &self.version;
         let root = format_ident!("{}", self.root.name);
         let root_mod = self.root.codegen();
 
         let tokens = quote! {
             pub const PACKAGE: CodegenPkg = CodegenPkg {
                 crate_name: #crate_name,
+                version: #version,
                 root: &#root::MODULE,
                 dependencies: &[#(#deps),*],
+                requirements: &[#(#reqs),*],
             };
 
             #root_mod
@@ -260,8 +288,33 @@ impl Pkg {
         tokens.to_string()
     }
 
-    /// Run validation checks on each of the scanned files.
+    /// Run validation checks on each of the scanned files, and check that the
+    /// dependencies match the version requirements.
     pub fn validate(self) -> Result<Self, Error> {
+        for (name, req) in &self.requirements {
+            let dep = self
+                .dependencies
+                .iter()
+                .find(|dep| dep.root.name == name)
+                .ok_or_else(|| {
+                    Error::Custom(format!(
+                        "version requirement on unknown dependency `{name}`"
+                    ))
+                })?;
+            let matches = dep
+                .version
+                .parse()
+                .is_ok_and(|version| req.matches(&version));
+            if !matches {
+                return Err(ResolveError::VersionMismatch(
+                    name.clone(),
+                    dep.version.to_string(),
+                    req.to_string(),
+                    format!("package `{}`", self.root.name),
+                )
+                .into());
+            }
+        }
         let path = ModulePath::new(PathOrigin::Absolute, vec![self.root.name.clone()]);
         self.root.validate(path)?;
         Ok(self)
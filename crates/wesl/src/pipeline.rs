@@ -0,0 +1,745 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use wgsl_parse::syntax::{ModulePath, TranslationUnit};
+
+use crate::{
+    CompileResult, CompiledShader, Diagnostic, Error, EscapeMangler, Features, Mangler, Provenance,
+    ResolveError, Resolver, SyntaxUtil, condcomp, import, lower, lower_switches,
+    strip::strip_except,
+    trace::{TraceResolver, event, span},
+};
+
+/// A transformation applied to a syntax tree by a [`Pipeline`].
+///
+/// Passes added before the [`Imports`] step run on each module right after it is
+/// loaded. Passes added after it run on the assembled translation unit.
+///
+/// Closures with the signature `Fn(&mut TranslationUnit) -> Result<(), Error>` are
+/// passes too.
+pub trait Pass {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error>;
+}
+
+impl<F: Fn(&mut TranslationUnit) -> Result<(), Error>> Pass for F {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error> {
+        self(wesl)
+    }
+}
+
+/// The version of the [`Plugin`] interface. It is incremented on each breaking change
+/// of [`Plugin`] or [`PassContext`].
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+/// A third-party pass, run by a [`Pipeline`]. See [`Pipeline::plugin`].
+///
+/// Unlike a [`Pass`], a plugin receives a [`PassContext`] with the pipeline options, the
+/// symbol table and a diagnostics sink. Plugins are used as trait objects, so external
+/// crates can ship reusable passes (obfuscators, engine-specific lowering, ...) and hosts
+/// can load them dynamically.
+///
+/// # Usage
+///
+/// ```rust
+/// # use wesl::{Error, VirtualResolver, syntax::TranslationUnit};
+/// # use wesl::pipeline::{Imports, PLUGIN_API_VERSION, PassContext, Pipeline, Plugin};
+/// struct CountDecls;
+///
+/// impl Plugin for CountDecls {
+///     fn name(&self) -> &str {
+///         "count-decls"
+///     }
+///     fn api_version(&self) -> u32 {
+///         PLUGIN_API_VERSION
+///     }
+///     fn run(&self, wesl: &mut TranslationUnit, ctx: &mut PassContext) -> Result<(), Error> {
+///         let max = ctx.option("max-decls").map_or(Ok(usize::MAX), str::parse).unwrap();
+///         if wesl.global_declarations.len() > max {
+///             return Err(Error::Custom("too many declarations".to_string()));
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let mut resolver = VirtualResolver::new();
+/// resolver.add_module("package::main".parse().unwrap(), "@fragment fn main() {}".into());
+///
+/// let res = Pipeline::new(resolver)
+///     .pass(Imports { lazy: true })
+///     .plugin(CountDecls)
+///     .option("max-decls", "10")
+///     .compile(&"package::main".parse().unwrap());
+/// assert!(res.is_ok());
+/// ```
+pub trait Plugin: Send + Sync {
+    /// The name of the plugin, used in error messages and traces.
+    fn name(&self) -> &str;
+    /// The version of the plugin interface the plugin is built against. Implementors
+    /// should return [`PLUGIN_API_VERSION`]. A pipeline fails to compile if one of its
+    /// plugins targets another version.
+    fn api_version(&self) -> u32;
+    /// Transform a module, if the plugin is added before the [`Imports`] step, or the
+    /// assembled translation unit.
+    fn run(&self, wesl: &mut TranslationUnit, ctx: &mut PassContext) -> Result<(), Error>;
+}
+
+impl Plugin for Box<dyn Plugin> {
+    fn name(&self) -> &str {
+        self.as_ref().name()
+    }
+    fn api_version(&self) -> u32 {
+        self.as_ref().api_version()
+    }
+    fn run(&self, wesl: &mut TranslationUnit, ctx: &mut PassContext) -> Result<(), Error> {
+        self.as_ref().run(wesl, ctx)
+    }
+}
+
+/// What a [`Plugin`] can access besides the syntax tree.
+pub struct PassContext<'a> {
+    module: Option<&'a ModulePath>,
+    options: &'a HashMap<String, String>,
+    symbols: &'a HashMap<String, Provenance>,
+    diagnostics: &'a mut Vec<Diagnostic<Error>>,
+}
+
+impl PassContext<'_> {
+    /// The path of the module being transformed, or `None` for the assembled
+    /// translation unit.
+    pub fn module_path(&self) -> Option<&ModulePath> {
+        self.module
+    }
+    /// Get a pipeline option. See [`Pipeline::option`].
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+    /// Get where a module-scope declaration comes from, by its name in the syntax tree.
+    /// In the assembled translation unit, names are mangled.
+    pub fn symbol(&self, name: &str) -> Option<&Provenance> {
+        self.symbols.get(name)
+    }
+    /// Iterate over the module-scope declarations, by name. See [`Self::symbol`].
+    pub fn symbols(&self) -> impl Iterator<Item = (&str, &Provenance)> {
+        self.symbols
+            .iter()
+            .map(|(name, prov)| (name.as_str(), prov))
+    }
+    /// Emit a warning, reported in [`CompileResult::warnings`]. Return an error from
+    /// [`Plugin::run`] to abort the compilation instead.
+    pub fn warn(&mut self, diagnostic: impl Into<Diagnostic<Error>>) {
+        let mut diagnostic = diagnostic.into();
+        diagnostic.detail.warning = true;
+        if let Some(path) = self.module {
+            diagnostic = diagnostic.with_module_path(path.clone(), None);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+/// Evaluate `@if`, `@elif` and `@else` attributes.
+///
+/// See [`crate::CompileOptions::features`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CondComp(pub Features);
+
+impl Pass for CondComp {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error> {
+        condcomp::run(wesl, &self.0)?;
+        Ok(())
+    }
+}
+
+/// Resolve imports and assemble the modules.
+///
+/// See [`crate::CompileOptions::lazy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Imports {
+    pub lazy: bool,
+}
+
+impl Default for Imports {
+    fn default() -> Self {
+        Self { lazy: true }
+    }
+}
+
+/// Mangle the declarations of imported modules with a custom [`Mangler`].
+///
+/// Mangling is part of the assembly, so a `Mangle` step must directly follow the
+/// [`Imports`] step. If a pipeline has no `Mangle` step, the [`EscapeMangler`] is used.
+pub struct Mangle<M: Mangler>(pub M);
+
+/// Lower the assembled translation unit. See [`lower`].
+///
+/// See [`crate::CompileOptions::lower`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Lower(pub LowerOptions);
+
+/// The options of the [`Lower`] step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LowerOptions {
+    /// Convert `switch` statements to `if` chains.
+    ///
+    /// See [`crate::CompileOptions::lower_switches`].
+    pub switches: bool,
+}
+
+impl Pass for Lower {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error> {
+        lower(wesl)?;
+        if self.0.switches {
+            lower_switches(wesl);
+        }
+        Ok(())
+    }
+}
+
+/// Remove declarations that are not reachable from the entrypoints.
+///
+/// See [`crate::CompileOptions::strip`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Strip;
+
+impl Pass for Strip {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error> {
+        let keep = wesl.entry_points().cloned().collect::<HashSet<_>>();
+        strip_except(wesl, &keep);
+        Ok(())
+    }
+}
+
+/// Compute the repeated pure expressions once.
+///
+/// See [`crate::eliminate_common_subexpressions`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EliminateCommonSubexpressions;
+
+impl Pass for EliminateCommonSubexpressions {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error> {
+        crate::eliminate_common_subexpressions(wesl);
+        Ok(())
+    }
+}
+
+/// Sort the attributes in a canonical order.
+///
+/// See [`crate::normalize_attributes`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NormalizeAttributes;
+
+impl Pass for NormalizeAttributes {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error> {
+        crate::normalize_attributes(wesl);
+        Ok(())
+    }
+}
+
+/// Substitute values for module-scope constants and prune the unreachable code.
+///
+/// See [`crate::specialize`].
+#[cfg(feature = "eval")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Specialize(pub std::collections::HashMap<String, wgsl_parse::syntax::Expression>);
+
+#[cfg(feature = "eval")]
+impl Pass for Specialize {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error> {
+        crate::specialize(wesl, &self.0)
+    }
+}
+
+/// Move the small read-only storage buffers to the `uniform` address space.
+///
+/// See [`crate::storage_to_uniform`].
+#[cfg(feature = "eval")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StorageToUniform {
+    pub max_size: u32,
+}
+
+#[cfg(feature = "eval")]
+impl Pass for StorageToUniform {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error> {
+        crate::storage_to_uniform(wesl, self.max_size)?;
+        Ok(())
+    }
+}
+
+/// Move a small uniform buffer to the `push_constant` address space.
+///
+/// See [`crate::uniform_to_push_constant`].
+#[cfg(all(feature = "eval", feature = "naga-ext"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UniformToPushConstant {
+    pub max_size: u32,
+}
+
+#[cfg(all(feature = "eval", feature = "naga-ext"))]
+impl Pass for UniformToPushConstant {
+    fn apply(&self, wesl: &mut TranslationUnit) -> Result<(), Error> {
+        crate::uniform_to_push_constant(wesl, self.max_size)?;
+        Ok(())
+    }
+}
+
+/// A step of a [`Pipeline`].
+///
+/// Anything that implements [`Pass`], as well as [`Imports`] and [`Mangle`], converts
+/// into a `Step`. [`Plugin`]s are added with [`Pipeline::plugin`].
+pub enum Step {
+    Pass(Box<dyn Pass + Send + Sync>),
+    Plugin(Box<dyn Plugin>),
+    Imports(Imports),
+    Mangle(Box<dyn Mangler + Send + Sync>),
+}
+
+impl<P: Pass + Send + Sync + 'static> From<P> for Step {
+    fn from(pass: P) -> Self {
+        Step::Pass(Box::new(pass))
+    }
+}
+
+impl From<Imports> for Step {
+    fn from(imports: Imports) -> Self {
+        Step::Imports(imports)
+    }
+}
+
+impl<M: Mangler + Send + Sync + 'static> From<Mangle<M>> for Step {
+    fn from(mangle: Mangle<M>) -> Self {
+        Step::Mangle(Box::new(mangle.0))
+    }
+}
+
+/// A customizable WESL compilation driver.
+///
+/// Unlike [`crate::Wesl`], which drives a fixed sequence of stages configured by
+/// [`crate::CompileOptions`], a `Pipeline` runs exactly the steps it is given, in order.
+/// Custom [`Pass`]es can be inserted between the built-in ones.
+///
+/// # Usage
+///
+/// ```rust
+/// # use wesl::{VirtualResolver, Features, syntax::TranslationUnit};
+/// # use wesl::pipeline::{CondComp, Imports, Lower, LowerOptions, Pipeline};
+/// let mut resolver = VirtualResolver::new();
+/// resolver.add_module("package::main".parse().unwrap(), "@fragment fn main() {}".into());
+///
+/// let wgsl = Pipeline::new(resolver)
+///     .pass(CondComp(Features::default()))
+///     .pass(Imports { lazy: true })
+///     .pass(|wesl: &mut TranslationUnit| {
+///         // custom pass running on the assembled module
+///         Ok(())
+///     })
+///     .pass(Lower(LowerOptions::default()))
+///     .compile(&"package::main".parse().unwrap())
+///     .unwrap()
+///     .to_string();
+/// ```
+///
+/// # WESL Reference
+/// The pipeline is not part of the WESL spec. Omitting the [`Imports`] step disables
+/// imports, like [`crate::CompileOptions::imports`].
+pub struct Pipeline<R: Resolver> {
+    resolver: R,
+    steps: Vec<Step>,
+    options: HashMap<String, String>,
+}
+
+impl Step {
+    fn apply<'a>(
+        &self,
+        wesl: &mut TranslationUnit,
+        ctx: impl FnOnce() -> PassContext<'a>,
+    ) -> Result<(), Error> {
+        match self {
+            Step::Pass(pass) => pass.apply(wesl),
+            Step::Plugin(plugin) => {
+                span!("plugin", name = plugin.name());
+                plugin.run(wesl, &mut ctx())
+            }
+            Step::Imports(_) | Step::Mangle(_) => Ok(()),
+        }
+    }
+}
+
+/// A resolver that runs the steps preceding [`Imports`] on each module it resolves.
+struct StepResolver<'a, R: Resolver> {
+    resolver: R,
+    steps: &'a [Step],
+    options: &'a HashMap<String, String>,
+    diagnostics: Mutex<Vec<Diagnostic<Error>>>,
+}
+
+impl<R: Resolver> Resolver for StepResolver<'_, R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let mut wesl = self.resolver.resolve_module(path)?;
+        let symbols = wesl
+            .global_declarations
+            .iter()
+            .filter_map(|decl| {
+                let name = decl.ident()?.to_string();
+                let prov = Provenance {
+                    module: path.clone(),
+                    name: name.clone(),
+                    span: decl.span(),
+                };
+                Some((name, prov))
+            })
+            .collect();
+        let mut diagnostics = self.diagnostics.lock().unwrap();
+        for step in self.steps {
+            span!("module_pass");
+            step.apply(&mut wesl, || PassContext {
+                module: Some(path),
+                options: self.options,
+                symbols: &symbols,
+                diagnostics: &mut diagnostics,
+            })
+            .map_err(|e| {
+                Diagnostic::from(e)
+                    .with_module_path(path.clone(), self.display_name(path))
+                    .with_source(self.resolve_source(path).unwrap().to_string())
+            })?;
+        }
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+impl<R: Resolver> Pipeline<R> {
+    /// Create an empty pipeline.
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            steps: Vec::new(),
+            options: HashMap::new(),
+        }
+    }
+
+    /// Append a step to the pipeline.
+    pub fn pass(mut self, step: impl Into<Step>) -> Self {
+        self.steps.push(step.into());
+        self
+    }
+
+    /// Append a [`Plugin`] to the pipeline.
+    pub fn plugin(mut self, plugin: impl Plugin + 'static) -> Self {
+        self.steps.push(Step::Plugin(Box::new(plugin)));
+        self
+    }
+
+    /// Set an option for the plugins. See [`PassContext::option`].
+    pub fn option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.insert(key.into(), value.into());
+        self
+    }
+
+    /// Get a reference to the resolver.
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
+    /// Check that the [`Imports`] and [`Mangle`] steps appear at most once, and that the
+    /// `Mangle` step directly follows the `Imports` step.
+    fn check_steps(&self) -> Result<(), Error> {
+        let count = |f: fn(&Step) -> bool| self.steps.iter().filter(|s| f(s)).count();
+        if count(|s| matches!(s, Step::Imports(_))) > 1 {
+            return Err(Error::Custom(
+                "a pipeline can have only one `Imports` step".to_string(),
+            ));
+        }
+        if count(|s| matches!(s, Step::Mangle(_))) > 1 {
+            return Err(Error::Custom(
+                "a pipeline can have only one `Mangle` step".to_string(),
+            ));
+        }
+        let mangle = self.steps.iter().position(|s| matches!(s, Step::Mangle(_)));
+        if let Some(i) = mangle {
+            if i == 0 || !matches!(self.steps[i - 1], Step::Imports(_)) {
+                return Err(Error::Custom(
+                    "the `Mangle` step must directly follow the `Imports` step".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Compile a WESL program from a root file by running each step in order, and
+    /// bundle the output with its metadata. See [`crate::Wesl::compile`].
+    ///
+    /// Fails if the steps are misplaced, see [`Mangle`].
+    pub fn compile(&self, root: &ModulePath) -> Result<CompiledShader, Error> {
+        self.compile_result(root).map(CompiledShader::from_result)
+    }
+
+    /// Compile a WESL program from a root file by running each step in order, without
+    /// computing the metadata of [`CompiledShader`]. See [`crate::Wesl::compile_result`].
+    pub fn compile_result(&self, root: &ModulePath) -> Result<CompileResult, Error> {
+        span!("pipeline", root = %root);
+        self.check_steps()?;
+        for step in &self.steps {
+            let Step::Plugin(plugin) = step else {
+                continue;
+            };
+            if plugin.api_version() != PLUGIN_API_VERSION {
+                return Err(Error::Custom(format!(
+                    "plugin `{}` targets the plugin API version {}, expected version {PLUGIN_API_VERSION}",
+                    plugin.name(),
+                    plugin.api_version(),
+                )));
+            }
+        }
+        let imports = self
+            .steps
+            .iter()
+            .position(|s| matches!(s, Step::Imports(_)));
+        let (module_steps, output_steps) = self.steps.split_at(imports.unwrap_or(0));
+
+        let step_resolver = StepResolver {
+            resolver: &self.resolver,
+            steps: module_steps,
+            options: &self.options,
+            diagnostics: Mutex::new(Vec::new()),
+        };
+        let resolver = TraceResolver::new(&step_resolver);
+
+        let mut wesl = resolver.resolve_module(root)?;
+        wesl.retarget_idents();
+        // the root declarations are always kept, stripping is done by the `Strip` pass.
+        let keep = wesl
+            .global_declarations
+            .iter()
+            .filter_map(|decl| decl.ident())
+            .cloned()
+            .collect::<HashSet<_>>();
+
+        let mut resolutions = import::Resolutions::new();
+        let module = import::Module::new(wesl, root.clone())?;
+        resolutions.push_module(module);
+
+        match imports.map(|i| &self.steps[i]) {
+            Some(Step::Imports(Imports { lazy: true })) => {
+                import::resolve_lazy(&keep, &mut resolutions, &resolver)?
+            }
+            Some(Step::Imports(Imports { lazy: false })) => {
+                import::resolve_eager(&mut resolutions, &resolver)?
+            }
+            _ => (),
+        }
+
+        let provenances = resolutions.provenances();
+        let mangler = self.steps.iter().find_map(|step| match step {
+            Step::Mangle(mangler) => Some(mangler),
+            _ => None,
+        });
+        match mangler {
+            Some(mangler) => resolutions.mangle(mangler, false),
+            None => resolutions.mangle(&EscapeMangler, false),
+        }
+        let provenances = import::provenances_by_name(provenances);
+
+        let mut assembly = resolutions.assemble(false);
+        // resolutions hold idents use-counts. We only need the list of modules now.
+        let modules = resolutions.into_module_order();
+
+        event!(
+            declarations = assembly.global_declarations.len(),
+            "assembled"
+        );
+
+        let mut warnings = step_resolver.diagnostics.into_inner().unwrap();
+        for step in output_steps {
+            span!("pass");
+            step.apply(&mut assembly, || PassContext {
+                module: None,
+                options: &self.options,
+                symbols: &provenances,
+                diagnostics: &mut warnings,
+            })?;
+            event!(
+                declarations = assembly.global_declarations.len(),
+                "applied pass"
+            );
+        }
+        let provenances = import::provenances_by_ident(&assembly, provenances);
+
+        Ok(CompileResult {
+            syntax: assembly,
+            sourcemap: None,
+            modules,
+            group_by_module: false,
+            warnings,
+            provenances,
+            debug_buffer: None,
+            rebindings: Vec::new(),
+            binding_ranges: Vec::new(),
+            wgsl_modules: Vec::new(),
+            header: None,
+            exports: None,
+        })
+    }
+}
+
+#[test]
+fn test_plugins() {
+    use crate::VirtualResolver;
+
+    struct Lint(u32);
+    impl Plugin for Lint {
+        fn name(&self) -> &str {
+            "lint"
+        }
+        fn api_version(&self) -> u32 {
+            self.0
+        }
+        fn run(&self, wesl: &mut TranslationUnit, ctx: &mut PassContext) -> Result<(), Error> {
+            let prefix = ctx.option("prefix").unwrap_or("_").to_string();
+            for decl in &wesl.global_declarations {
+                let Some(name) = decl.ident().map(|id| id.to_string()) else {
+                    continue;
+                };
+                let prov = ctx.symbol(&name).unwrap().clone();
+                if prov.name.starts_with(&prefix) {
+                    let msg = format!("`{}` in `{}`", prov.name, prov.module);
+                    ctx.warn(Error::Custom(msg));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let pipeline = |plugin: Lint| {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "package::main".parse().unwrap(),
+            "import package::util::tmp_f;
+            @fragment fn main() { tmp_f(); }"
+                .into(),
+        );
+        resolver.add_module("package::util".parse().unwrap(), "fn tmp_f() {}".into());
+        Pipeline::new(resolver)
+            .plugin(Box::new(Lint(plugin.0)) as Box<dyn Plugin>)
+            .pass(Imports { lazy: true })
+            .plugin(plugin)
+            .option("prefix", "tmp")
+    };
+    let root = "package::main".parse().unwrap();
+    let res = pipeline(Lint(PLUGIN_API_VERSION)).compile(&root).unwrap();
+    let warnings = res
+        .warnings
+        .iter()
+        .map(|w| w.error.to_string())
+        .collect::<Vec<_>>();
+    // once on the module, once on the assembled translation unit
+    assert_eq!(warnings, vec!["`tmp_f` in `package::util`"; 2]);
+    assert!(res.warnings.iter().all(|w| w.detail.warning));
+
+    let Err(err) = pipeline(Lint(0)).compile(&root) else {
+        panic!("expected a plugin version error");
+    };
+    assert!(err.to_string().contains("plugin API version 0"), "{err}");
+}
+
+#[test]
+fn test_step_order() {
+    use crate::{NoMangler, VirtualResolver};
+    use std::sync::{Arc, Mutex};
+
+    let resolver = || {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "package::main".parse().unwrap(),
+            "import package::util::f;
+            @fragment fn main() { f(); }"
+                .into(),
+        );
+        resolver.add_module("package::util".parse().unwrap(), "fn f() {}".into());
+        resolver
+    };
+    let root = "package::main".parse().unwrap();
+
+    // passes before `Imports` see each module, passes after it see the assembly.
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let record = |tag: &'static str| {
+        let seen = seen.clone();
+        move |wesl: &mut TranslationUnit| {
+            let mut names = wesl
+                .global_declarations
+                .iter()
+                .filter_map(|decl| decl.ident().map(|id| id.to_string()))
+                .collect::<Vec<_>>();
+            names.sort();
+            seen.lock()
+                .unwrap()
+                .push(format!("{tag}: {}", names.join(", ")));
+            Ok(())
+        }
+    };
+    Pipeline::new(resolver())
+        .pass(record("module"))
+        .pass(Imports { lazy: true })
+        .pass(Mangle(NoMangler))
+        .pass(record("output"))
+        .compile(&root)
+        .unwrap();
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec!["module: main", "module: f", "output: f, main"]
+    );
+
+    let err = |pipeline: Pipeline<VirtualResolver>| match pipeline.compile(&root) {
+        Ok(_) => panic!("expected a step order error"),
+        Err(e) => e.to_string(),
+    };
+    let msg = err(Pipeline::new(resolver()).pass(Mangle(NoMangler)));
+    assert!(msg.contains("must directly follow"), "{msg}");
+    let msg = err(Pipeline::new(resolver())
+        .pass(Imports { lazy: true })
+        .pass(Lower::default())
+        .pass(Mangle(NoMangler)));
+    assert!(msg.contains("must directly follow"), "{msg}");
+    let msg = err(Pipeline::new(resolver())
+        .pass(Imports { lazy: true })
+        .pass(Mangle(NoMangler))
+        .pass(Mangle(EscapeMangler)));
+    assert!(msg.contains("only one `Mangle` step"), "{msg}");
+    let msg = err(Pipeline::new(resolver())
+        .pass(Imports { lazy: true })
+        .pass(Imports { lazy: false }));
+    assert!(msg.contains("only one `Imports` step"), "{msg}");
+}
+
+#[test]
+fn test_lower_options() {
+    use crate::VirtualResolver;
+
+    let compile = |opts: LowerOptions| {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module(
+            "package::main".parse().unwrap(),
+            "@fragment fn main() { switch 1 { default {} } }".into(),
+        );
+        Pipeline::new(resolver)
+            .pass(Imports { lazy: true })
+            .pass(Lower(opts))
+            .compile(&"package::main".parse().unwrap())
+            .unwrap()
+            .to_string()
+    };
+    assert!(compile(LowerOptions::default()).contains("switch"));
+    assert!(!compile(LowerOptions { switches: true }).contains("switch"));
+}
@@ -0,0 +1,144 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use wgsl_parse::syntax::{Attribute, Attributes, ModulePath, TranslationUnit, TypeExpression};
+
+use crate::{Diagnostic, Error, ResolveError, Resolver, ValidateError, visit::Visit};
+
+/// Whether a module is loaded from a plain WGSL file, i.e. a file with the `.wgsl`
+/// extension. See [`Resolver::fs_path`].
+pub(crate) fn is_plain_wgsl(resolver: &impl Resolver, path: &ModulePath) -> bool {
+    resolver
+        .fs_path(path)
+        .is_some_and(|file| file.extension().is_some_and(|ext| ext == "wgsl"))
+}
+
+/// The modules loaded from plain WGSL files, see [`crate::CompileResult::wgsl_modules`].
+pub(crate) fn plain_wgsl_modules(
+    modules: &[ModulePath],
+    resolver: &impl Resolver,
+) -> Vec<ModulePath> {
+    modules
+        .iter()
+        .filter(|path| is_plain_wgsl(resolver, path))
+        .cloned()
+        .collect()
+}
+
+fn wesl_attribute(attr: &Attribute) -> Option<&str> {
+    match attr {
+        Attribute::Publish => Some("publish"),
+        Attribute::If(_) => Some("if"),
+        Attribute::Elif(_) => Some("elif"),
+        Attribute::Else => Some("else"),
+        Attribute::CompileError(_) => Some("compile_error"),
+        Attribute::CompileWarn(_) => Some("compile_warn"),
+        Attribute::Slot(_) => Some("slot"),
+        Attribute::Type(_) => Some("type"),
+        Attribute::Custom(attr) => Some(&attr.name),
+        _ => None,
+    }
+}
+
+/// Check that a module does not use WESL-only syntax: imports, qualified references
+/// and WESL attributes (conditional translation, generics, user diagnostics and custom
+/// attributes).
+fn check_plain_wgsl(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    let err = |syntax: String| Diagnostic::from(Error::from(ValidateError::WeslSyntax(syntax)));
+    if !wesl.imports.is_empty() {
+        return Err(err("import statements".to_string()));
+    }
+    for attrs in Visit::<Attributes>::visit(wesl) {
+        for attr in attrs {
+            if let Some(name) = wesl_attribute(attr) {
+                return Err(err(format!("the `@{name}` attribute")).with_span(attr.span()));
+            }
+        }
+    }
+    let mut qualified = None;
+    Visit::<TypeExpression>::visit_rec(wesl, &mut |ty| {
+        if let Some(path) = ty.path.as_ref().filter(|_| qualified.is_none()) {
+            qualified = Some(format!("{path}::{}", ty.ident));
+        }
+    });
+    match qualified {
+        Some(name) => Err(err(format!("the qualified reference `{name}`"))),
+        None => Ok(()),
+    }
+}
+
+/// A resolver that rejects WESL-only syntax in plain WGSL modules, see
+/// [`crate::CompileOptions::plain_wgsl`].
+pub(crate) struct PlainWgslResolver<R: Resolver> {
+    resolver: R,
+}
+
+impl<R: Resolver> PlainWgslResolver<R> {
+    pub(crate) fn new(resolver: R) -> Self {
+        Self { resolver }
+    }
+}
+
+impl<R: Resolver> Resolver for PlainWgslResolver<R> {
+    fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let wesl = self.resolver.resolve_module(path)?;
+        if is_plain_wgsl(&self.resolver, path) {
+            check_plain_wgsl(&wesl).map_err(|d| {
+                let d = d.with_module_path(path.clone(), self.display_name(path));
+                match self.resolver.resolve_source(path) {
+                    Ok(source) => d.with_source(source.into_owned()),
+                    Err(_) => d,
+                }
+            })?;
+        }
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+#[test]
+fn test_plain_wgsl() {
+    use crate::{CompileOptions, Wesl};
+
+    let dir = std::env::temp_dir().join(format!("wesl-plain-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("main.wesl"),
+        "import package::util::f;
+        @fragment fn main() -> @location(0) vec4f { return vec4f(f()); }",
+    )
+    .unwrap();
+    std::fs::write(dir.join("util.wgsl"), "fn f() -> f32 { return 1.0; }").unwrap();
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(crate::FileResolver::new(&dir));
+    compiler
+        .set_options(CompileOptions::default())
+        .set_plain_wgsl(true);
+    let root = "package::main".parse().unwrap();
+    let res = compiler
+        .compile_result(&root)
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(res.wgsl_modules, ["package::util".parse().unwrap()]);
+    let report = res.size_report().to_string();
+    assert!(report.contains("package::util (plain WGSL)"), "{report}");
+
+    std::fs::write(
+        dir.join("util.wgsl"),
+        "@if(debug) fn g() {}
+        fn f() -> f32 { return 1.0; }",
+    )
+    .unwrap();
+    let Err(err) = compiler.compile_result(&root) else {
+        panic!("WESL syntax in a plain WGSL module must fail");
+    };
+    let err = err.to_string();
+    assert!(err.contains("`@if` attribute"), "{err}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
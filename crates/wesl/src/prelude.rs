@@ -0,0 +1,265 @@
+use std::{borrow::Cow, collections::HashSet, path::PathBuf};
+
+use wgsl_parse::syntax::{
+    Ident, Import, ImportContent, ImportItem, ImportStatement, ModulePath, TranslationUnit,
+};
+
+use crate::{Diagnostic, Error, ImportError, ResolveError, Resolver};
+
+/// A resolver that injects an import of all the prelude declarations in each module.
+///
+/// The prelude is not injected in the prelude module itself and in the modules it
+/// imports from, transitively, to avoid conflicts with the items it re-exports.
+///
+/// See [`crate::CompileOptions::prelude`].
+pub(crate) struct PreludeResolver<R: Resolver> {
+    resolver: R,
+    path: ModulePath,
+    names: Vec<String>,
+    deps: HashSet<ModulePath>,
+}
+
+impl<R: Resolver> PreludeResolver<R> {
+    pub(crate) fn new(resolver: R, path: ModulePath) -> Result<Self, ResolveError> {
+        let prelude = resolver.resolve_module(&path)?;
+        let decls = prelude
+            .global_declarations
+            .iter()
+            .filter_map(|decl| decl.ident())
+            .map(|id| id.to_string());
+        let reexports = imported_names(&prelude.imports, true);
+        let names = decls.chain(reexports).collect();
+        let deps = transitive_import_paths(&resolver, &prelude.imports, &path);
+        Ok(Self {
+            resolver,
+            path,
+            names,
+            deps,
+        })
+    }
+}
+
+/// The module paths imported from by import statements. Since an imported item can be
+/// a module, its path is included too.
+fn import_paths(imports: &[ImportStatement], parent_path: &ModulePath) -> HashSet<ModulePath> {
+    fn rec(content: &ImportContent, path: ModulePath, paths: &mut HashSet<ModulePath>) {
+        match content {
+            ImportContent::Item(item) => {
+                paths.insert(path.clone().join([item.ident.to_string()]));
+                paths.insert(path);
+            }
+            ImportContent::Collection(coll) => {
+                for import in coll {
+                    rec(
+                        &import.content,
+                        path.clone().join(import.path.clone()),
+                        paths,
+                    );
+                }
+            }
+        }
+    }
+
+    let mut paths = HashSet::new();
+    for import in imports {
+        if let Some(path) = &import.path {
+            rec(&import.content, parent_path.join_path(path), &mut paths);
+        }
+    }
+    paths
+}
+
+/// The module paths imported from by the prelude, and by the modules it imports from,
+/// recursively. Paths that cannot be resolved are imported items, not modules.
+fn transitive_import_paths(
+    resolver: &impl Resolver,
+    imports: &[ImportStatement],
+    path: &ModulePath,
+) -> HashSet<ModulePath> {
+    let mut deps = HashSet::new();
+    let mut queue = import_paths(imports, path).into_iter().collect::<Vec<_>>();
+    while let Some(path) = queue.pop() {
+        if !deps.insert(path.clone()) {
+            continue;
+        }
+        if let Ok(module) = resolver.resolve_module(&path) {
+            queue.extend(import_paths(&module.imports, &path));
+        }
+    }
+    deps
+}
+
+/// The names of the items imported by import statements.
+pub(crate) fn imported_names(imports: &[ImportStatement], only_public: bool) -> Vec<String> {
+    fn rec(content: &ImportContent, names: &mut Vec<String>) {
+        match content {
+            ImportContent::Item(item) => {
+                names.push(item.rename.as_ref().unwrap_or(&item.ident).to_string())
+            }
+            ImportContent::Collection(coll) => {
+                for import in coll {
+                    rec(&import.content, names);
+                }
+            }
+        }
+    }
+
+    let mut names = Vec::new();
+    for import in imports {
+        if !only_public || import.attributes.iter().any(|attr| attr.is_publish()) {
+            rec(&import.content, &mut names);
+        }
+    }
+    names
+}
+
+impl<R: Resolver> Resolver for PreludeResolver<R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let mut wesl = self.resolver.resolve_module(path)?;
+        if *path == self.path || self.deps.contains(path) {
+            return Ok(wesl);
+        }
+
+        let conflict = |name: &String, decl: Option<&Ident>| {
+            let mut diagnostic = Diagnostic::from(Error::from(ImportError::PreludeConflict(
+                name.clone(),
+                path.clone(),
+            )))
+            .with_module_path(path.clone(), self.display_name(path))
+            .with_source(self.resolve_source(path).unwrap().to_string());
+            if let Some(decl) = decl {
+                let span = wesl
+                    .global_declarations
+                    .iter()
+                    .find(|d| d.ident() == Some(decl))
+                    .map(|d| d.span());
+                if let Some(span) = span {
+                    diagnostic = diagnostic.with_span(span);
+                }
+            }
+            ResolveError::Error(diagnostic)
+        };
+
+        let imported = imported_names(&wesl.imports, false);
+        for name in &self.names {
+            let decl = wesl
+                .global_declarations
+                .iter()
+                .filter_map(|decl| decl.ident())
+                .find(|id| *id.name() == *name);
+            if let Some(decl) = decl {
+                return Err(conflict(name, Some(decl)));
+            }
+            if imported.contains(name) {
+                return Err(conflict(name, None));
+            }
+        }
+
+        if !self.names.is_empty() {
+            let coll = self
+                .names
+                .iter()
+                .map(|name| Import {
+                    path: Vec::new(),
+                    content: ImportContent::Item(ImportItem {
+                        ident: Ident::new(name.clone()),
+                        rename: None,
+                    }),
+                })
+                .collect();
+            wesl.imports.push(ImportStatement {
+                attributes: Default::default(),
+                path: Some(self.path.clone()),
+                content: ImportContent::Collection(coll),
+            });
+        }
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+#[test]
+fn test_prelude() {
+    use crate::{CompileOptions, ManglerKind, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::prelude".parse().unwrap(),
+        "@publish import package::consts::PI; alias Color = vec4f;".into(),
+    );
+    resolver.add_module(
+        "package::consts".parse().unwrap(),
+        "const PI = 3.14;".into(),
+    );
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@fragment fn main() -> @location(0) Color { return Color(PI); }".into(),
+    );
+    resolver.add_module(
+        "package::conflict".parse().unwrap(),
+        "const PI = 3.0; @fragment fn main() -> @location(0) vec4f { return vec4f(PI); }".into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        prelude: Some("package::prelude".parse().unwrap()),
+        mangler: ManglerKind::None,
+        ..Default::default()
+    });
+    let wgsl = compiler
+        .compile(&"package::main".parse().unwrap())
+        .map(|res| res.to_string())
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert!(wgsl.contains("const PI = 3.14;"), "{wgsl}");
+    assert!(wgsl.contains("alias Color = vec4f;"), "{wgsl}");
+
+    let Err(err) = compiler.compile(&"package::conflict".parse().unwrap()) else {
+        panic!("expected a prelude conflict");
+    };
+    assert!(
+        matches!(
+            *Diagnostic::from(err).error,
+            Error::ImportError(ImportError::PreludeConflict(_, _))
+        ),
+        "expected a prelude conflict"
+    );
+}
+
+#[test]
+fn test_prelude_transitive_deps() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::prelude".parse().unwrap(),
+        "@publish import package::consts::HALF_TAU;".into(),
+    );
+    resolver.add_module(
+        "package::consts".parse().unwrap(),
+        "import package::math::TAU; const HALF_TAU = TAU / 2.0;".into(),
+    );
+    // `math` is a dependency of the prelude, declaring the same name is not a conflict.
+    resolver.add_module(
+        "package::math".parse().unwrap(),
+        "const TAU = 6.28; const HALF_TAU = 3.14;".into(),
+    );
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@fragment fn main() -> @location(0) vec4f { return vec4f(HALF_TAU); }".into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        prelude: Some("package::prelude".parse().unwrap()),
+        ..Default::default()
+    });
+    compiler
+        .compile(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+}
@@ -0,0 +1,533 @@
+use std::{collections::HashMap, fmt::Display};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{
+    Expression, GlobalDeclaration, Ident, Statement, StatementNode, TranslationUnit, TypeExpression,
+};
+
+use crate::{Error, FreshNames, SyntaxUtil, visit::Visit};
+
+/// Name of the WESL built-in function instrumented by [`crate::CompileOptions::debug_printf`].
+pub(crate) const DEBUG_PRINTF: &str = "debug_printf";
+
+/// Options of the debug printf instrumentation. See
+/// [`crate::CompileOptions::debug_printf`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DebugPrintf {
+    /// The `@group` of the debug storage buffer.
+    pub group: u32,
+    /// The `@binding` of the debug storage buffer.
+    pub binding: u32,
+}
+
+/// Error produced by the debug printf instrumentation.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DebugPrintfError {
+    #[error("`debug_printf` has {0} argument types but {1} arguments")]
+    ArgCount(usize, usize),
+    #[error(
+        "`debug_printf` cannot print type `{0}`, expected a scalar or vector of `bool`, `i32`, `u32` or `f32`"
+    )]
+    UnsupportedType(String),
+}
+
+/// The scalar type of a [`DebugArg`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugScalar {
+    Bool,
+    I32,
+    U32,
+    F32,
+}
+
+impl DebugScalar {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bool" => Some(Self::Bool),
+            "i32" => Some(Self::I32),
+            "u32" => Some(Self::U32),
+            "f32" => Some(Self::F32),
+            _ => None,
+        }
+    }
+
+    /// Convert a WGSL expression of this type to `u32`.
+    fn to_word(self, expr: &str) -> String {
+        match self {
+            Self::Bool => format!("select(0u, 1u, {expr})"),
+            Self::I32 | Self::F32 => format!("bitcast<u32>({expr})"),
+            Self::U32 => expr.to_string(),
+        }
+    }
+
+    /// Decode a word written by [`Self::to_word`].
+    pub fn decode(self, word: u32) -> DebugValue {
+        match self {
+            Self::Bool => DebugValue::Bool(word != 0),
+            Self::I32 => DebugValue::I32(word as i32),
+            Self::U32 => DebugValue::U32(word),
+            Self::F32 => DebugValue::F32(f32::from_bits(word)),
+        }
+    }
+}
+
+/// A value printed by `debug_printf`, decoded by [`DebugBuffer::decode`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugValue {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    F32(f32),
+}
+
+impl Display for DebugValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(v) => write!(f, "{v}"),
+            Self::I32(v) => write!(f, "{v}"),
+            Self::U32(v) => write!(f, "{v}"),
+            Self::F32(v) => write!(f, "{v:?}"),
+        }
+    }
+}
+
+/// An argument of a `debug_printf` call site.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugArg {
+    /// The argument expression, as written in the output.
+    pub expr: String,
+    pub scalar: DebugScalar,
+    /// 1 for scalars, the number of components for vectors.
+    pub components: usize,
+}
+
+/// A `debug_printf` call site.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugCallSite {
+    /// The name of the function containing the call, in the output.
+    pub function: String,
+    pub args: Vec<DebugArg>,
+}
+
+impl DebugCallSite {
+    /// The number of `u32` words of a message, including the call site id.
+    pub fn words(&self) -> usize {
+        1 + self.args.iter().map(|arg| arg.components).sum::<usize>()
+    }
+}
+
+/// The debug storage buffer added by the debug printf instrumentation, and the call
+/// sites writing to it. See [`crate::CompileResult::debug_buffer`].
+///
+/// The buffer is a `u32` word count followed by a runtime-sized array of `u32` words.
+/// The host must zero it before each dispatch or draw, and read it back afterwards.
+/// Messages that do not fit in the buffer are dropped, the word count keeps growing.
+/// Each message starts with its call site id plus one, so the zero words of a dropped
+/// message are never mistaken for a message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DebugBuffer {
+    /// The name of the buffer variable in the output.
+    pub name: String,
+    pub group: u32,
+    pub binding: u32,
+    /// The call sites, indexed by call site id.
+    pub call_sites: Vec<DebugCallSite>,
+}
+
+/// A message written by a `debug_printf` call. `Display` formats it as
+/// `function: arg = value, ...`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebugMessage<'a> {
+    pub call_site: &'a DebugCallSite,
+    /// The values of each argument. Vectors have one value per component.
+    pub values: Vec<Vec<DebugValue>>,
+}
+
+impl Display for DebugMessage<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.call_site.function)?;
+        let args = self.call_site.args.iter().zip(&self.values);
+        for (i, (arg, values)) in args.enumerate() {
+            let sep = if i == 0 {
+                " "
+            } else {
+                ", "
+            };
+            match values.as_slice() {
+                [value] => write!(f, "{sep}{} = {value}", arg.expr)?,
+                values => write!(f, "{sep}{} = ({})", arg.expr, values.iter().format(", "))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DebugBuffer {
+    /// Decode the contents of the buffer (little-endian bytes) read back from the GPU.
+    ///
+    /// Messages are returned in the order they were written. Decoding stops at the
+    /// first invalid message, or at the first message dropped because it did not fit.
+    pub fn decode(&self, bytes: &[u8]) -> Vec<DebugMessage<'_>> {
+        let mut words = bytes
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]));
+        let len = words.next().unwrap_or(0) as usize;
+        let len = len.min(words.len());
+        let words = words.take(len).collect_vec();
+
+        let mut messages = Vec::new();
+        let mut rest = words.as_slice();
+        while let Some(site) = rest
+            .first()
+            .and_then(|tag| tag.checked_sub(1))
+            .and_then(|id| self.call_sites.get(id as usize))
+        {
+            let Some((message, tail)) = rest.split_at_checked(site.words()) else {
+                break;
+            };
+            let mut values = message[1..].iter();
+            let values = site
+                .args
+                .iter()
+                .map(|arg| {
+                    (&mut values)
+                        .take(arg.components)
+                        .map(|word| arg.scalar.decode(*word))
+                        .collect()
+                })
+                .collect();
+            messages.push(DebugMessage {
+                call_site: site,
+                values,
+            });
+            rest = tail;
+        }
+        messages
+    }
+
+    /// Whether messages were dropped because the buffer was too small.
+    pub fn overflowed(&self, bytes: &[u8]) -> bool {
+        let len = bytes
+            .first_chunk::<4>()
+            .map(|w| u32::from_le_bytes(*w) as usize)
+            .unwrap_or(0);
+        len > (bytes.len() / 4).saturating_sub(1)
+    }
+}
+
+/// Whether a call refers to the `debug_printf` built-in, i.e. it is not a user
+/// declaration.
+pub(crate) fn is_debug_printf(ty: &TypeExpression, wesl: &TranslationUnit) -> bool {
+    ty.path.is_none()
+        && *ty.ident.name() == DEBUG_PRINTF
+        && wesl.find_decl_by_ident(&ty.ident).is_none()
+}
+
+fn debug_arg(expr: &Expression, ty: &TypeExpression, wesl: &TranslationUnit) -> Option<DebugArg> {
+    let ty = wesl.canonical_type(ty)?;
+    let (scalar, components) = match (ty.ident.name().as_str(), &ty.template_args) {
+        ("vec2", Some(args)) => (&args[0].expression, 2),
+        ("vec3", Some(args)) => (&args[0].expression, 3),
+        ("vec4", Some(args)) => (&args[0].expression, 4),
+        (name, None) => {
+            return DebugScalar::from_name(name).map(|scalar| DebugArg {
+                expr: expr.to_string(),
+                scalar,
+                components: 1,
+            });
+        }
+        _ => return None,
+    };
+    match scalar.node() {
+        Expression::TypeOrIdentifier(ty) if ty.template_args.is_none() => {
+            DebugScalar::from_name(&ty.ident.name()).map(|scalar| DebugArg {
+                expr: expr.to_string(),
+                scalar,
+                components,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The WGSL source of the function writing the messages of a call site.
+fn call_site_fn(name: &str, id: usize, site: &DebugCallSite, tys: &[String], buf: &str) -> String {
+    let words = site.words();
+    let params = tys
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| format!("arg{i}: {ty}"))
+        .join(", ");
+    let mut writes = vec![format!("{buf}.data[offset] = {}u;", id + 1)];
+    for (i, arg) in site.args.iter().enumerate() {
+        for c in 0..arg.components {
+            let expr = if arg.components == 1 {
+                format!("arg{i}")
+            } else {
+                format!("arg{i}[{c}]")
+            };
+            let word = arg.scalar.to_word(&expr);
+            writes.push(format!("{buf}.data[offset + {}u] = {word};", writes.len()));
+        }
+    }
+    format!(
+        "fn {name}({params}) {{
+            let offset = atomicAdd(&{buf}.len, {words}u);
+            if offset + {words}u <= arrayLength(&{buf}.data) {{
+                {}
+            }}
+        }}",
+        writes.join("\n")
+    )
+}
+
+/// Instrument the `debug_printf` calls with writes to a debug storage buffer.
+///
+/// Each `debug_printf<T1, T2, ...>(a1, a2, ...)` call statement is replaced by a call to
+/// a generated function that appends a message to the buffer. If `options` is `None`,
+/// the call statements are removed instead.
+pub(crate) fn instrument(
+    wesl: &mut TranslationUnit,
+    options: Option<&DebugPrintf>,
+) -> Result<Option<DebugBuffer>, Error> {
+    fn rewrite_calls<'a>(
+        stats: impl Iterator<Item = &'a mut StatementNode>,
+        f: &mut impl FnMut(&mut Statement) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        for stat in stats {
+            f(stat.node_mut())?;
+            rewrite_calls(Visit::<StatementNode>::visit_mut(stat.node_mut()), f)?;
+        }
+        Ok(())
+    }
+
+    let mut names = FreshNames::new(wesl);
+    let buf_name = names.fresh_name("debug_printf_buffer");
+    let buf_ty_name = names.fresh_name("DebugPrintfBuffer");
+
+    let source = wesl.clone();
+    let mut buffer = DebugBuffer {
+        name: buf_name.clone(),
+        group: options.map(|opts| opts.group).unwrap_or_default(),
+        binding: options.map(|opts| opts.binding).unwrap_or_default(),
+        call_sites: Vec::new(),
+    };
+    let mut generated = Vec::new();
+    let mut idents = HashMap::new();
+
+    for decl in &mut wesl.global_declarations {
+        let GlobalDeclaration::Function(func) = decl.node_mut() else {
+            continue;
+        };
+        let function = func.ident.to_string();
+        let mut removed = false;
+        let mut rewrite = |stat: &mut Statement| {
+            let Statement::FunctionCall(call) = stat else {
+                return Ok(());
+            };
+            if !is_debug_printf(&call.call.ty, &source) {
+                return Ok(());
+            }
+            if options.is_none() {
+                *stat = Statement::Void;
+                removed = true;
+                return Ok(());
+            }
+            let call = &mut call.call;
+            let tys = call.ty.template_args.iter().flatten().collect_vec();
+            if tys.len() != call.arguments.len() {
+                let err = DebugPrintfError::ArgCount(tys.len(), call.arguments.len());
+                return Err(Error::from(err));
+            }
+            let args = call
+                .arguments
+                .iter()
+                .zip(&tys)
+                .map(|(arg, ty)| {
+                    match ty.expression.node() {
+                        Expression::TypeOrIdentifier(ty) => debug_arg(arg, ty, &source),
+                        _ => None,
+                    }
+                    .ok_or_else(|| DebugPrintfError::UnsupportedType(ty.expression.to_string()))
+                })
+                .collect::<Result<_, _>>()?;
+            let site = DebugCallSite {
+                function: function.clone(),
+                args,
+            };
+            let id = buffer.call_sites.len();
+            let name = names.fresh_name(&format!("debug_printf_{id}"));
+            let tys = tys.iter().map(|ty| ty.expression.to_string()).collect_vec();
+            generated.push(call_site_fn(&name, id, &site, &tys, &buf_name));
+            buffer.call_sites.push(site);
+            let ident = Ident::new(name.clone());
+            call.ty = TypeExpression::new(ident.clone());
+            idents.insert(name, ident);
+            Ok(())
+        };
+        rewrite_calls(func.body.statements.iter_mut(), &mut rewrite)?;
+        if removed {
+            func.body.remove_voids();
+        }
+    }
+
+    let Some(options) = options else {
+        return Ok(None);
+    };
+    if buffer.call_sites.is_empty() {
+        return Ok(None);
+    }
+
+    let source = format!(
+        "struct {buf_ty_name} {{ len: atomic<u32>, data: array<u32> }}
+        @group({}) @binding({}) var<storage, read_write> {buf_name}: {buf_ty_name};
+        {}",
+        options.group,
+        options.binding,
+        generated.join("\n")
+    );
+    let mut generated = wgsl_parse::parse_str(&source)?;
+    generated.retarget_idents();
+    // link the generated functions to the call sites.
+    for decl in &mut generated.global_declarations {
+        if let GlobalDeclaration::Function(func) = decl.node_mut() {
+            func.ident = idents[&func.ident.to_string()].clone();
+        }
+    }
+    wesl.global_declarations
+        .extend(generated.global_declarations);
+    Ok(Some(buffer))
+}
+
+#[test]
+fn test_debug_printf() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f;
+        @compute @workgroup_size(1) fn main(@builtin(global_invocation_id) id: vec3u) {
+            let x = f(id.x);
+            debug_printf<u32, vec2f>(id.x, vec2(x, 2.0));
+            if x > 1.0 { debug_printf<bool>(true); }
+        }"
+        .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn f(x: u32) -> f32 { debug_printf(); return f32(x) * 0.5; }".into(),
+    );
+
+    let compile = |debug_printf| {
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+        compiler
+            .set_options(CompileOptions::default())
+            .set_mangler(crate::ManglerKind::Escape)
+            .set_debug_printf(debug_printf);
+        compiler.compile_result(&"package::main".parse().unwrap())
+    };
+
+    let res = compile(None).unwrap_or_else(|e| panic!("{e}"));
+    assert!(res.debug_buffer.is_none());
+    assert!(!res.to_string().contains("debug_printf"));
+
+    let options = DebugPrintf {
+        group: 1,
+        binding: 2,
+    };
+    let res = compile(Some(options)).unwrap_or_else(|e| panic!("{e}"));
+    let buffer = res.debug_buffer.as_ref().unwrap();
+    assert_eq!(buffer.call_sites.len(), 3);
+    let binding = res.bindings().get(&buffer.name).cloned().unwrap();
+    assert_eq!((binding.group, binding.binding), (Some(1), Some(2)));
+    let sites = buffer
+        .call_sites
+        .iter()
+        .map(|site| site.words())
+        .collect_vec();
+    assert_eq!(sites, [4, 2, 1]);
+
+    // simulate the messages written by `main` with `id.x = 3`.
+    let util = buffer
+        .call_sites
+        .iter()
+        .position(|s| s.args.is_empty())
+        .unwrap() as u32;
+    let main = buffer
+        .call_sites
+        .iter()
+        .position(|s| s.words() == 4)
+        .unwrap() as u32;
+    let branch = buffer
+        .call_sites
+        .iter()
+        .position(|s| s.words() == 2)
+        .unwrap() as u32;
+    let words = [
+        7,
+        util + 1,
+        main + 1,
+        3,
+        1.5f32.to_bits(),
+        2.0f32.to_bits(),
+        branch + 1,
+        1,
+        0,
+    ];
+    let bytes = words.iter().flat_map(|w| w.to_le_bytes()).collect_vec();
+    let messages = buffer.decode(&bytes);
+    assert_eq!(messages.len(), 3);
+    assert_eq!(
+        messages[1].to_string(),
+        "main: id.x = 3, vec2(x, 2.0) = (1.5, 2.0)"
+    );
+    assert_eq!(messages[2].values, [[DebugValue::Bool(true)]]);
+    assert!(!buffer.overflowed(&bytes));
+    assert!(buffer.overflowed(&bytes[..16]));
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@compute @workgroup_size(1) fn main() { debug_printf<mat2x2f>(mat2x2f()); }".into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .set_debug_printf(Some(options));
+    let Err(err) = compiler.compile_result(&"package::main".parse().unwrap()) else {
+        panic!("expected an error");
+    };
+    assert!(err.to_string().contains("cannot print type `mat2x2f`"));
+}
+
+#[test]
+fn test_debug_buffer_overflow() {
+    let site = |components| DebugCallSite {
+        function: "main".to_string(),
+        args: vec![DebugArg {
+            expr: "x".to_string(),
+            scalar: DebugScalar::U32,
+            components,
+        }],
+    };
+    let buffer = DebugBuffer {
+        name: "debug".to_string(),
+        group: 0,
+        binding: 0,
+        call_sites: vec![site(1), site(3)],
+    };
+
+    // a buffer of 5 words: call site 0 wrote 2 words, call site 1 reserved 4 words at
+    // offset 2 which did not fit, and call site 0 reserved 2 more words at offset 6.
+    let words = [8, 1, 7, 0, 0, 0];
+    let bytes = words
+        .iter()
+        .flat_map(|w: &u32| w.to_le_bytes())
+        .collect_vec();
+    let messages = buffer.decode(&bytes);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].to_string(), "main: x = 7");
+    assert!(buffer.overflowed(&bytes));
+}
@@ -0,0 +1,312 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{
+    Attribute, Expression, ExpressionNode, GlobalDeclaration, LiteralExpression, ModulePath,
+    TranslationUnit,
+};
+
+use crate::{Provenance, reflect::Folder};
+
+/// Policy of the binding collision resolution. See
+/// [`crate::CompileOptions::binding_policy`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BindingPolicy {
+    /// The `@group`s reserved for the resources of the modules under a path prefix,
+    /// e.g. a package `pkg_a`. If several prefixes match, the longest one wins.
+    ///
+    /// The groups of these resources are offset by the start of the range: `@group(0)`
+    /// becomes the first reserved group. The resources of other modules are not moved.
+    pub reserved_groups: Vec<(ModulePath, Range<u32>)>,
+    /// Resources with a fixed `(group, binding)` in the output, by declaring module and
+    /// name. They take precedence over the reserved groups, and their bindings are never
+    /// given to other moved resources, even when they are not in the output.
+    pub pinned: Vec<(ModulePath, String, (u32, u32))>,
+    /// Give each moved resource the same binding in all the variants compiled by
+    /// [`crate::Wesl::compile_variants`], so that one bind group layout works with all
+    /// of them. The bindings of resources compiled out of a variant are left as holes.
+    /// See [`crate::VariantsResult::binding_layout`].
+    ///
+    /// The variants are compiled twice: once to find the resources of all variants,
+    /// then with these resources [pinned][Self::pinned].
+    pub stable_variants: bool,
+}
+
+impl BindingPolicy {
+    /// The groups reserved for the module at `path`, if any.
+    pub fn groups_of(&self, path: &ModulePath) -> Option<&Range<u32>> {
+        self.reserved_groups
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.components.len())
+            .map(|(_, groups)| groups)
+    }
+
+    /// The pinned binding of a resource, if any.
+    pub fn pinned_binding(&self, module: &ModulePath, name: &str) -> Option<(u32, u32)> {
+        self.pinned
+            .iter()
+            .find(|(path, n, _)| path == module && n == name)
+            .map(|(_, _, binding)| *binding)
+    }
+}
+
+/// Error produced by the binding collision resolution and the `@binding_range`
+/// expansion.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum BindingError {
+    #[error(
+        "resource `{0}` in `@group({1})` does not fit in the groups reserved for `{2}` ({3:?})"
+    )]
+    GroupOutOfRange(String, u32, ModulePath, Range<u32>),
+    #[error("`@group({0}) @binding({1})` is bound to both `{2}` and `{3}`")]
+    Collision(u32, u32, String, String),
+    #[error("invalid `@binding_range` of `{0}`: {1}")]
+    InvalidRange(String, &'static str),
+    #[error(
+        "`{0}` is indexed with a non-constant index outside of an argument of a user-declared function"
+    )]
+    DynamicRangeIndex(String),
+    #[error("index {1} is out of the `@binding_range` of `{0}` ({2} bindings)")]
+    RangeIndex(String, u32, u32),
+    #[error("`@binding_range` variable `{0}` can only be indexed")]
+    RangeUsage(String),
+}
+
+/// A resource moved by the binding collision resolution.
+/// See [`crate::CompileResult::rebindings`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rebinding {
+    /// The name of the resource in the output.
+    pub name: String,
+    /// The module declaring the resource.
+    pub module: ModulePath,
+    /// The original `(group, binding)`.
+    pub from: (u32, u32),
+    /// The `(group, binding)` in the output.
+    pub to: (u32, u32),
+}
+
+struct Resource {
+    index: usize,
+    name: String,
+    module: Option<ModulePath>,
+    from: (u32, u32),
+    to: (u32, u32),
+    movable: bool,
+}
+
+/// Reassign the bindings of resources according to a [`BindingPolicy`].
+///
+/// The resources of modules with reserved groups are moved to these groups. Then, if a
+/// resource collides with a resource seen before, it gets the lowest free binding in its
+/// group if it is movable. Otherwise, the collision is an error. Resources with a
+/// `@group` or `@binding` that cannot be evaluated are ignored.
+pub(crate) fn resolve_collisions(
+    wesl: &mut TranslationUnit,
+    policy: &BindingPolicy,
+    provenances: &HashMap<String, Provenance>,
+) -> Result<Vec<Rebinding>, BindingError> {
+    let mut folder = Folder::new(wesl);
+    let mut resources = Vec::new();
+    for (index, decl) in wesl.global_declarations.iter().enumerate() {
+        let GlobalDeclaration::Declaration(decl) = decl.node() else {
+            continue;
+        };
+        let mut attr = |f: fn(&Attribute) -> Option<&ExpressionNode>| {
+            let expr = decl.attributes.iter().find_map(|attr| f(attr.node()))?;
+            folder.fold(expr.node())
+        };
+        let group = attr(|attr| match attr {
+            Attribute::Group(expr) => Some(expr),
+            _ => None,
+        });
+        let binding = attr(|attr| match attr {
+            Attribute::Binding(expr) => Some(expr),
+            _ => None,
+        });
+        let (Some(group), Some(binding)) = (group, binding) else {
+            continue;
+        };
+        let name = decl.ident.to_string();
+        let prov = provenances.get(&name);
+        let module = prov.map(|prov| prov.module.clone());
+        let pinned = prov.and_then(|prov| policy.pinned_binding(&prov.module, &prov.name));
+        let groups = module.as_ref().and_then(|path| policy.groups_of(path));
+        let to = match (pinned, groups) {
+            (Some(pinned), _) => pinned,
+            (None, Some(groups)) => {
+                let moved = groups.start.checked_add(group);
+                match moved.filter(|g| groups.contains(g)) {
+                    Some(moved) => (moved, binding),
+                    None => {
+                        let path = module.clone().unwrap();
+                        let err = BindingError::GroupOutOfRange(name, group, path, groups.clone());
+                        return Err(err);
+                    }
+                }
+            }
+            (None, None) => (group, binding),
+        };
+        resources.push(Resource {
+            index,
+            name,
+            module,
+            from: (group, binding),
+            to,
+            movable: pinned.is_none() && groups.is_some(),
+        });
+    }
+
+    // fixed and pinned resources claim their bindings first. Moved resources do not take
+    // the bindings of pinned resources missing from the output.
+    resources.sort_by_key(|res| res.movable);
+    let pinned = (policy.pinned.iter())
+        .map(|(_, _, binding)| *binding)
+        .collect::<HashSet<_>>();
+    let mut used = HashMap::<(u32, u32), String>::new();
+    for res in &mut resources {
+        let movable = res.movable;
+        let taken =
+            |used: &HashMap<_, _>, to| used.contains_key(&to) || movable && pinned.contains(&to);
+        if taken(&used, res.to) {
+            if !res.movable {
+                let (group, binding) = res.to;
+                let other = used[&res.to].clone();
+                let err = BindingError::Collision(group, binding, other, res.name.clone());
+                return Err(err);
+            }
+            let group = res.to.0;
+            let binding = (0..).find(|b| !taken(&used, (group, *b))).unwrap();
+            res.to = (group, binding);
+        }
+        used.insert(res.to, res.name.clone());
+    }
+
+    let rebindings = resources
+        .into_iter()
+        .filter(|res| res.to != res.from)
+        .sorted_by_key(|res| res.index)
+        .map(|res| {
+            let decl = &mut wesl.global_declarations[res.index];
+            if let GlobalDeclaration::Declaration(decl) = decl.node_mut() {
+                for attr in &mut decl.attributes {
+                    let (expr, value) = match attr.node_mut() {
+                        Attribute::Group(expr) => (expr, res.to.0),
+                        Attribute::Binding(expr) => (expr, res.to.1),
+                        _ => continue,
+                    };
+                    *expr =
+                        Expression::Literal(LiteralExpression::AbstractInt(value.into())).into();
+                }
+            }
+            Rebinding {
+                name: res.name,
+                // only the resources with reserved groups or pinned are moved.
+                module: res.module.unwrap(),
+                from: res.from,
+                to: res.to,
+            }
+        })
+        .collect();
+    Ok(rebindings)
+}
+
+#[test]
+fn test_binding_policy() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::lights::shade;
+        import package::shadows::sample_shadow;
+        @group(0) @binding(0) var<uniform> camera: mat4x4f;
+        @fragment fn main() -> @location(0) vec4f {
+            return camera[0] * shade() * sample_shadow();
+        }"
+        .into(),
+    );
+    resolver.add_module(
+        "package::lights".parse().unwrap(),
+        "@group(0) @binding(0) var<uniform> lights: vec4f;
+        @group(0) @binding(1) var<uniform> ambient: vec4f;
+        fn shade() -> vec4f { return lights + ambient; }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::shadows".parse().unwrap(),
+        "@group(0) @binding(0) var<uniform> shadow: vec4f;
+        fn sample_shadow() -> vec4f { return shadow; }"
+            .into(),
+    );
+
+    let compile = |policy| {
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(&resolver);
+        compiler
+            .set_options(CompileOptions::default())
+            .set_mangler(crate::ManglerKind::Escape)
+            .set_binding_policy(policy);
+        compiler.compile_result(&"package::main".parse().unwrap())
+    };
+
+    let policy = |groups: [(&str, Range<u32>); 2]| BindingPolicy {
+        reserved_groups: groups
+            .map(|(path, groups)| (format!("package::{path}").parse().unwrap(), groups))
+            .to_vec(),
+        ..Default::default()
+    };
+    let slots = |res: &crate::CompileResult| {
+        res.bindings()
+            .iter()
+            .map(|b| (b.group.unwrap(), b.binding.unwrap()))
+            .sorted()
+            .collect_vec()
+    };
+
+    let res = compile(Some(policy([("lights", 1..2), ("shadows", 2..4)])))
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(res.rebindings.len(), 3);
+    let shadow = res
+        .rebindings
+        .iter()
+        .find(|r| r.from == (0, 0) && r.to.0 == 2);
+    assert_eq!(shadow.unwrap().module, "package::shadows".parse().unwrap());
+
+    // both libraries share group 1, the second one gets the next free binding.
+    let res = compile(Some(policy([("lights", 1..2), ("shadows", 1..2)])))
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(slots(&res), [(0, 0), (1, 0), (1, 1), (1, 2)]);
+    let shadow = res.rebindings.iter().find(|r| r.to == (1, 2));
+    assert_eq!(shadow.unwrap().module, "package::shadows".parse().unwrap());
+
+    // pinned bindings are not given to other resources, even if they are not bound.
+    let shadows = "package::shadows".parse::<ModulePath>().unwrap();
+    let mut pinned = policy([("lights", 1..2), ("lights::x", 2..3)]);
+    pinned.pinned = vec![
+        (shadows.clone(), "shadow".to_string(), (1, 0)),
+        (shadows, "unused".to_string(), (1, 1)),
+    ];
+    let res = compile(Some(pinned)).unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(slots(&res), [(0, 0), (1, 0), (1, 2), (1, 3)]);
+    let shadow = res
+        .rebindings
+        .iter()
+        .find(|r| r.name == "package_shadows_shadow");
+    assert_eq!(shadow.unwrap().to, (1, 0));
+
+    let err = |res: Result<crate::CompileResult, crate::Error>| match res {
+        Ok(_) => panic!("expected an error"),
+        Err(e) => crate::Diagnostic::from(e).error.to_string(),
+    };
+    // `shadows` has no reserved groups, so it cannot be moved.
+    let res = compile(Some(policy([("lights", 1..2), ("lights::x", 2..3)])));
+    assert!(err(res).contains("is bound to both"));
+    let res = compile(Some(policy([("lights", 1..1), ("shadows", 2..3)])));
+    assert!(err(res).contains("does not fit"));
+    assert!(compile(None).is_ok());
+}
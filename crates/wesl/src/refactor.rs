@@ -0,0 +1,552 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+use wgsl_parse::{
+    lexer::{Lexer, Token},
+    syntax::{
+        DeclarationKind, Expression, FunctionCall, GlobalDeclaration, Ident, ImportContent,
+        ImportItem, ImportStatement, ModulePath, PathOrigin, ReturnStatement, Statement,
+        TranslationUnit, TypeExpression,
+    },
+};
+
+use crate::{Diagnostic, Error, SyntaxUtil, idents::builtin_ident, visit::Visit};
+
+/// Rewrite the module paths of import statements and inline qualified paths.
+///
+/// `f` receives each module path, as written in the source (i.e. `super::` paths are
+/// not resolved), and returns the new path, or `None` to keep it. The path of an
+/// imported item excludes the item name: for `import package::foo::bar;`, `f` receives
+/// `package::foo`, then `package::foo::bar` in case `bar` is a module. When a module
+/// item is renamed, it is imported with an alias to keep its local name, e.g.
+/// `import package::foo::baz as bar;`.
+///
+/// If an import in a collection is moved outside of the collection path, it is
+/// extracted in a separate import statement.
+///
+/// See [`rewrite_import_paths_in_source`] to preserve the formatting of a source file.
+pub fn rewrite_import_paths(
+    wesl: &mut TranslationUnit,
+    f: &dyn Fn(&ModulePath) -> Option<ModulePath>,
+) {
+    wesl.imports = std::mem::take(&mut wesl.imports)
+        .into_iter()
+        .flat_map(|stmt| rewrite_import(stmt, f))
+        .collect();
+
+    Visit::<TypeExpression>::visit_rec_mut(wesl, &mut |ty| {
+        if let Some(path) = ty.path.as_ref().and_then(f) {
+            ty.path = Some(path);
+        }
+    });
+}
+
+/// Rewrite the module paths of import statements and inline qualified paths in a WESL
+/// source file. See [`rewrite_import_paths`].
+///
+/// Unlike [`rewrite_import_paths`], the formatting and comments of the source are
+/// preserved, except in rewritten import statements.
+pub fn rewrite_import_paths_in_source(
+    source: &str,
+    f: &dyn Fn(&ModulePath) -> Option<ModulePath>,
+) -> Result<String, Error> {
+    let wesl = wgsl_parse::parse_str(source)?;
+    let tokens = Lexer::new(source).filter_map(Result::ok).collect_vec();
+
+    let mut edits = Vec::new(); // (start, end, replacement)
+    let mut imports = wesl.imports.iter();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let (start, tok, _) = &tokens[i];
+
+        if *tok == Token::KwImport {
+            let end = (i..tokens.len())
+                .find(|j| tokens[*j].1 == Token::SymSemicolon)
+                .unwrap_or(tokens.len() - 1);
+            let Some(stmt) = imports.next() else {
+                return Err(Error::Custom(
+                    "the import statements do not match the source".to_string(),
+                ));
+            };
+            let new = rewrite_import(stmt.clone(), f);
+            if new.len() != 1 || new[0].to_string() != stmt.to_string() {
+                let replacement = new
+                    .into_iter()
+                    .enumerate()
+                    .map(|(n, mut stmt)| {
+                        // the attributes of the first statement are kept in the source.
+                        let attrs = if n == 0 {
+                            String::new()
+                        } else {
+                            stmt.attributes
+                                .iter()
+                                .map(|attr| format!("{attr} "))
+                                .join("")
+                        };
+                        stmt.attributes.clear();
+                        format!("{attrs}import {stmt}")
+                    })
+                    .join("\n");
+                edits.push((*start, tokens[end].2, replacement));
+            }
+            i = end + 1;
+            continue;
+        }
+
+        // inline qualified path: a sequence of `name::` followed by an identifier.
+        let mut j = i;
+        while j + 1 < tokens.len()
+            && is_path_component(&tokens[j].1)
+            && tokens[j + 1].1 == Token::SymColonColon
+        {
+            j += 2;
+        }
+        if j > i {
+            let path = (i..j)
+                .step_by(2)
+                .map(|k| &source[tokens[k].0..tokens[k].2])
+                .join("::");
+            if let Some(new) = path.parse().ok().as_ref().and_then(f) {
+                edits.push((*start, tokens[j - 1].2, format!("{new}::")));
+            }
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+
+    let mut res = source.to_string();
+    for (start, end, replacement) in edits.into_iter().rev() {
+        res.replace_range(start..end, &replacement);
+    }
+    Ok(res)
+}
+
+fn is_path_component(tok: &Token) -> bool {
+    matches!(
+        tok,
+        Token::Ident(_) | Token::KwPackage | Token::KwSuper | Token::KwSelf
+    )
+}
+
+fn rewrite_import(
+    mut stmt: ImportStatement,
+    f: &dyn Fn(&ModulePath) -> Option<ModulePath>,
+) -> Vec<ImportStatement> {
+    let Some(path) = stmt.path.clone() else {
+        return vec![stmt];
+    };
+    if let Some(new) = f(&path) {
+        stmt.path = Some(new);
+        return vec![stmt];
+    }
+
+    let mut extracted = Vec::new();
+    let keep = rewrite_content(&mut stmt.content, &path, f, &mut extracted);
+    let extracted = extracted
+        .into_iter()
+        .map(|(path, content)| ImportStatement {
+            attributes: stmt.attributes.clone(),
+            path: Some(path),
+            content,
+        })
+        .collect_vec();
+    keep.then_some(stmt).into_iter().chain(extracted).collect()
+}
+
+/// Rewrite the paths inside an import collection. Returns `false` if the collection
+/// became empty.
+fn rewrite_content(
+    content: &mut ImportContent,
+    base: &ModulePath,
+    f: &dyn Fn(&ModulePath) -> Option<ModulePath>,
+    extracted: &mut Vec<(ModulePath, ImportContent)>,
+) -> bool {
+    match content {
+        ImportContent::Item(item) => match rewrite_item(item, base, f) {
+            Some(parent) if parent != *base => {
+                extracted.push((parent, ImportContent::Item(item.clone())));
+                false
+            }
+            _ => true,
+        },
+        ImportContent::Collection(coll) => {
+            coll.retain_mut(|import| {
+                let path = base.clone().join(import.path.iter().cloned());
+                match f(&path) {
+                    Some(new)
+                        if new.origin == base.origin
+                            && new.components.starts_with(&base.components) =>
+                    {
+                        import.path = new.components[base.components.len()..].to_vec();
+                        true
+                    }
+                    Some(new) => {
+                        extracted.push((new, import.content.clone()));
+                        false
+                    }
+                    None => rewrite_content(&mut import.content, &path, f, extracted),
+                }
+            });
+            !coll.is_empty()
+        }
+    }
+}
+
+/// Rewrite an imported item that is a module. Returns the new path of the item, excluding
+/// the item name.
+fn rewrite_item(
+    item: &mut ImportItem,
+    base: &ModulePath,
+    f: &dyn Fn(&ModulePath) -> Option<ModulePath>,
+) -> Option<ModulePath> {
+    let name = item.ident.to_string();
+    let mut new = f(&base.clone().join([name.clone()]))?;
+    let new_name = new.components.pop()?;
+    if new_name != name {
+        item.rename.get_or_insert_with(|| item.ident.clone());
+        item.ident = Ident::new(new_name);
+    }
+    Some(new)
+}
+
+/// The WESL package generated by [`import_wgsl`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WgslImport {
+    /// The WESL source of each module, in the order of the input.
+    pub modules: Vec<(ModulePath, String)>,
+    /// References to a name declared in several modules: `(module, name, candidates)`.
+    /// The first candidate is imported.
+    pub ambiguous: Vec<(ModulePath, String, Vec<ModulePath>)>,
+    /// References to a name declared in no module: `(module, name)`.
+    pub unresolved: Vec<(ModulePath, String)>,
+}
+
+/// Convert a set of plain WGSL files into WESL modules with explicit imports.
+///
+/// Each source becomes a module with its own namespace, so declarations with the same
+/// name in several sources no longer collide. References to names declared in other
+/// modules are resolved with an import statement, prepended to the source. The
+/// formatting and comments of the source are preserved.
+///
+/// When a name is declared in several other modules, the module closest to the
+/// referencing module in the package hierarchy is imported (i.e. with the longest
+/// common path prefix). Ties are reported in [`WgslImport::ambiguous`].
+pub fn import_wgsl(
+    sources: impl IntoIterator<Item = (ModulePath, String)>,
+) -> Result<WgslImport, Error> {
+    let sources = sources.into_iter().collect_vec();
+    let wgsl = sources
+        .iter()
+        .map(|(path, source)| {
+            let mut wgsl = wgsl_parse::parse_str(source).map_err(|e| {
+                Diagnostic::from(e)
+                    .with_module_path(path.clone(), None)
+                    .with_source(source.clone())
+            })?;
+            wgsl.retarget_idents();
+            Ok(wgsl)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // the modules declaring each global name.
+    let mut decls = HashMap::<String, Vec<&ModulePath>>::new();
+    for ((path, _), wgsl) in sources.iter().zip(&wgsl) {
+        for id in wgsl.global_declarations.iter().filter_map(|d| d.ident()) {
+            decls.entry(id.to_string()).or_default().push(path);
+        }
+    }
+
+    let mut res = WgslImport::default();
+    for ((path, source), wgsl) in sources.iter().zip(&wgsl) {
+        // references that are not retargeted to a declaration are only referenced once.
+        let mut free = Vec::new();
+        Visit::<TypeExpression>::visit_rec(wgsl, &mut |ty| {
+            let name = ty.ident.name();
+            if ty.path.is_none()
+                && ty.ident.use_count() == 1
+                && *name != "_"
+                && builtin_ident(&name).is_none()
+                && !free.contains(&*name)
+            {
+                free.push(name.to_string());
+            }
+        });
+
+        let mut imports: Vec<(&ModulePath, Vec<String>)> = Vec::new();
+        for name in free {
+            let Some(candidates) = decls.get(&name) else {
+                res.unresolved.push((path.clone(), name));
+                continue;
+            };
+            let common_prefix = |other: &&ModulePath| {
+                if other.origin != path.origin {
+                    return 0;
+                }
+                path.components
+                    .iter()
+                    .zip(&other.components)
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            };
+            let best = candidates.iter().copied().max_set_by_key(common_prefix);
+            let module = best[0];
+            if best.len() > 1 {
+                let candidates = best.into_iter().cloned().collect();
+                res.ambiguous.push((path.clone(), name.clone(), candidates));
+            }
+            match imports.iter_mut().find(|(p, _)| *p == module) {
+                Some((_, names)) => names.push(name),
+                None => imports.push((module, vec![name])),
+            }
+        }
+
+        let module = if imports.is_empty() {
+            source.clone()
+        } else {
+            let imports = imports
+                .iter()
+                .map(|(module, names)| match names.as_slice() {
+                    [name] => format!("import {module}::{name};\n"),
+                    names => format!("import {module}::{{ {} }};\n", names.iter().format(", ")),
+                })
+                .format("");
+            format!("{imports}\n{source}")
+        };
+        res.modules.push((path.clone(), module));
+    }
+    Ok(res)
+}
+
+/// Generate the interface of a module: its declarations without the function bodies.
+///
+/// Interface modules (`.wesli` files) let vendors ship closed-source shader packages.
+/// Consumers can import, validate and reflect on the declarations of the package as
+/// usual. [`FileResolver`](crate::FileResolver) resolves `.wesli` files when there is
+/// no source file; the full sources must be substituted at final assembly by a
+/// different resolver, since the interface functions do nothing.
+///
+/// Function bodies, `var` initializers and `const_assert`s are removed. A function
+/// with a return type returns the zero value of the type, i.e. `return T();`, so that
+/// the interface is valid WESL. The other declarations are kept as is, including `const`
+/// initializers which can be used in types, e.g. array sizes. Imports that are no
+/// longer used are removed.
+pub fn module_interface(wesl: &TranslationUnit) -> TranslationUnit {
+    let mut wesl = wesl.clone();
+    wesl.global_declarations
+        .retain(|decl| !matches!(decl.node(), GlobalDeclaration::ConstAssert(_)));
+    for decl in &mut wesl.global_declarations {
+        match decl.node_mut() {
+            GlobalDeclaration::Declaration(decl)
+                if matches!(decl.kind, DeclarationKind::Var(_)) =>
+            {
+                decl.initializer = None;
+            }
+            GlobalDeclaration::Function(decl) => {
+                decl.body.statements.clear();
+                if let Some(ty) = &decl.return_type {
+                    let zero = Expression::FunctionCall(FunctionCall {
+                        ty: ty.clone(),
+                        arguments: Vec::new(),
+                    });
+                    let stmt = Statement::Return(ReturnStatement {
+                        attributes: Vec::new(),
+                        expression: Some(zero.into()),
+                    });
+                    decl.body.statements.push(stmt.into());
+                }
+            }
+            _ => (),
+        }
+    }
+    remove_unused_imports(&mut wesl);
+    wesl
+}
+
+/// Remove the imported items whose local name is not referenced, and the imports left
+/// empty. Names are compared as strings, the module needs not be retargeted.
+fn remove_unused_imports(wesl: &mut TranslationUnit) {
+    let mut used = HashSet::new();
+    Visit::<TypeExpression>::visit_rec(wesl, &mut |ty| match &ty.path {
+        // a qualified reference through an imported module, e.g. `utils::foo`.
+        Some(ModulePath {
+            origin: PathOrigin::Package(name),
+            ..
+        }) => {
+            used.insert(name.clone());
+        }
+        Some(_) => {}
+        None => {
+            used.insert(ty.ident.to_string());
+        }
+    });
+
+    fn retain(content: &mut ImportContent, used: &HashSet<String>) -> bool {
+        match content {
+            ImportContent::Item(item) => {
+                used.contains(&*item.rename.as_ref().unwrap_or(&item.ident).name())
+            }
+            ImportContent::Collection(imports) => {
+                imports.retain_mut(|import| retain(&mut import.content, used));
+                !imports.is_empty()
+            }
+        }
+    }
+    wesl.imports
+        .retain_mut(|import| retain(&mut import.content, &used));
+}
+
+#[test]
+fn test_rewrite_import_paths() {
+    let source = "import package::utils::{foo, bar as baz};
+import package::{utils::qux, other::x};
+
+// a comment
+fn main() -> u32 {
+    return package::utils::foo(1u) + package::other::x;
+}
+";
+    let from: ModulePath = "package::utils".parse().unwrap();
+    let to: ModulePath = "lib::common".parse().unwrap();
+    let f = |path: &ModulePath| {
+        (path.origin == from.origin && path.components.starts_with(&from.components)).then(|| {
+            to.clone()
+                .join(path.components[from.components.len()..].iter().cloned())
+        })
+    };
+
+    let res = rewrite_import_paths_in_source(source, &f).unwrap();
+    assert_eq!(
+        res,
+        "import lib::common::{ foo, bar as baz };
+import package::{ other::x };
+import lib::common::qux;
+
+// a comment
+fn main() -> u32 {
+    return lib::common::foo(1u) + package::other::x;
+}
+"
+    );
+
+    let mut wesl = wgsl_parse::parse_str(source).unwrap();
+    rewrite_import_paths(&mut wesl, &f);
+    assert_eq!(
+        wesl.to_string(),
+        wgsl_parse::parse_str(&res).unwrap().to_string()
+    );
+
+    // plain item imports, where the item is a declaration or a module.
+    let source = "import package::utils::foo;
+import package::utils;
+import package::{utils, other};
+import package::utils as u;
+";
+    let res = rewrite_import_paths_in_source(source, &f).unwrap();
+    assert_eq!(
+        res,
+        "import lib::common::foo;
+import lib::common as utils;
+import package::{ other };
+import lib::common as utils;
+import lib::common as u;
+"
+    );
+}
+
+#[test]
+fn test_import_wgsl() {
+    let path = |s: &str| s.parse::<ModulePath>().unwrap();
+    let sources = [
+        (
+            path("package::math"),
+            "fn square(x: f32) -> f32 { return x * x; }
+            fn helper() {}"
+                .to_string(),
+        ),
+        (
+            path("package::util::color"),
+            "struct Color { rgb: vec3f }
+            fn helper() {}"
+                .to_string(),
+        ),
+        (
+            path("package::util::main"),
+            "// entry point
+            @fragment fn main() -> @location(0) vec4f {
+                let c = Color(vec3f(square(2.0)));
+                helper();
+                missing();
+                return vec4f(c.rgb, 1.0);
+            }"
+            .to_string(),
+        ),
+    ];
+
+    let res = import_wgsl(sources).unwrap();
+    let main = &res.modules[2].1;
+    assert!(main.starts_with(
+        "import package::util::color::{ Color, helper };\nimport package::math::square;\n\n// entry point"
+    ));
+    assert_eq!(
+        res.modules[0].1,
+        "fn square(x: f32) -> f32 { return x * x; }\n            fn helper() {}"
+    );
+    assert!(res.ambiguous.is_empty());
+    assert_eq!(
+        res.unresolved,
+        [(path("package::util::main"), "missing".to_string())]
+    );
+}
+
+#[test]
+fn test_module_interface() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let source = "import package::util::{ helper, Color };
+        const N = 4;
+        struct Light { color: Color }
+        @group(0) @binding(0) var<storage> lights: array<Light, N>;
+        var<private> seed: u32 = 42u;
+        const_assert N > 0;
+        fn shade(l: Light) -> vec3f { return helper(l.color); }";
+    let wesl: TranslationUnit = source.parse().unwrap();
+    let iface = module_interface(&wesl);
+    assert!(iface.find_decl("N").unwrap().to_string().contains('4'));
+    assert!(!iface.find_decl("seed").unwrap().to_string().contains("42"));
+    assert!(!iface.to_string().contains("const_assert"));
+    let shade = iface.find_decl("shade").unwrap().to_string();
+    assert!(shade.contains("return vec3f();") && !shade.contains("helper"));
+    // the imports used only in function bodies are removed.
+    assert_eq!(iface.imports.len(), 1);
+    assert!(!iface.imports[0].to_string().contains("helper"));
+
+    // consumers compile against the interface, the full sources are used at assembly.
+    let consumer = "import package::lights::{ Light, lights, shade };
+        @fragment fn main() -> @location(0) vec4f { return vec4f(shade(lights[0]), 1.0); }";
+    let compile = |lights: &str| {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module("package::main".parse().unwrap(), consumer.into());
+        resolver.add_module(
+            "package::lights".parse().unwrap(),
+            lights.to_string().into(),
+        );
+        resolver.add_module(
+            "package::util".parse().unwrap(),
+            "alias Color = vec3f;
+            fn helper(c: vec3f) -> vec3f { return c; }"
+                .into(),
+        );
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        compiler.set_options(CompileOptions::default());
+        compiler
+            .compile_result(&"package::main".parse().unwrap())
+            .unwrap_or_else(|e| panic!("{e}"))
+    };
+    let res = compile(&iface.to_string());
+    assert_eq!(res.bindings().len(), 1);
+    assert!(!res.to_string().contains("helper"));
+    let res = compile(source);
+    assert!(res.to_string().contains("helper"));
+}
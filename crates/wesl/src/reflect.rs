@@ -0,0 +1,1340 @@
+use std::collections::HashSet;
+
+use derive_more::Deref;
+#[cfg(not(feature = "eval"))]
+use wgsl_parse::syntax::UnaryOperator;
+use wgsl_parse::syntax::{
+    AccessMode, AddressSpace, Attribute, Attributes, BuiltinValue, Declaration, DeclarationKind,
+    Enumerant, Expression, ExpressionNode, Function, GlobalDeclaration, InterpolateAttribute,
+    LiteralExpression, TranslationUnit, TypeExpression,
+};
+use wgsl_types::{
+    tplt::{TextureTemplate, TpltParam},
+    ty::{TextureType, Type},
+};
+
+use crate::{BindingRange, SyntaxUtil, ValidateError, idents::builtin_ident};
+
+/// The pipeline stage of an [`EntryPoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EntryPointStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+/// An entry point input or output: a parameter, a return value or a member of an IO
+/// struct.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InterfaceVariable {
+    /// Name of the parameter or struct member. `None` for return values.
+    pub name: Option<String>,
+    pub ty: TypeExpression,
+    /// The `@location`, if it can be evaluated.
+    pub location: Option<u32>,
+    /// The `@blend_src`, if it can be evaluated. Only fragment outputs used for
+    /// dual-source blending have one.
+    pub blend_src: Option<u32>,
+    /// The `@interpolate` attribute, if any.
+    pub interpolate: Option<InterpolateAttribute>,
+    pub builtin: Option<BuiltinValue>,
+}
+
+/// Metadata of a shader entry point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryPoint {
+    pub name: String,
+    pub stage: EntryPointStage,
+    /// The `@workgroup_size` of compute shaders, with missing dimensions set to 1.
+    ///
+    /// `None` for other stages, or if the size cannot be evaluated, e.g. because it
+    /// depends on a pipeline-overridable constant. Evaluating const-expressions requires
+    /// the `eval` crate feature, otherwise only literals are supported.
+    pub workgroup_size: Option<[u32; 3]>,
+    /// The `var<workgroup>` memory used by compute shaders, in bytes: the sum of the
+    /// sizes of the workgroup variables the entry point uses (transitively), each rounded
+    /// up to 16 bytes as in WebGPU's `maxComputeWorkgroupStorageSize` limit.
+    ///
+    /// `None` for other stages, or if a size cannot be evaluated, e.g. because an array
+    /// size depends on a pipeline-overridable constant. Requires the `eval` crate feature.
+    pub workgroup_memory: Option<u32>,
+    /// Inputs of the entry point. Struct-typed parameters are flattened.
+    pub inputs: Vec<InterfaceVariable>,
+    /// Outputs of the entry point. Struct-typed return values are flattened.
+    pub outputs: Vec<InterfaceVariable>,
+    /// The resource bindings used by the entry point, directly or through the functions
+    /// it calls, in declaration order.
+    pub resources: Vec<ResourceUsage>,
+    /// The textures sampled by the entry point, with the samplers they are sampled with.
+    pub sampler_pairs: Vec<SamplerPair>,
+}
+
+/// How an entry point uses a resource binding. See [`EntryPoint::resources`].
+///
+/// Usage is determined statically: a resource accessed in a branch that never runs is
+/// still used.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ResourceUsage {
+    /// The name of the [`Binding`].
+    pub name: String,
+    /// Whether the contents are read, e.g. by a load, `textureLoad`, `textureSample` or
+    /// an atomic read-modify-write.
+    pub read: bool,
+    /// Whether the contents are written, e.g. by an assignment, `textureStore` or an
+    /// atomic operation.
+    pub write: bool,
+}
+
+/// A texture sampled with a sampler by an entry point, e.g. with `textureSample`.
+/// See [`EntryPoint::sampler_pairs`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerPair {
+    /// The name of the texture [`Binding`].
+    pub texture: String,
+    /// The name of the sampler [`Binding`].
+    pub sampler: String,
+    /// The `@group` and `@binding` of the texture, if they can be evaluated.
+    pub texture_binding: Option<(u32, u32)>,
+    /// The `@group` and `@binding` of the sampler, if they can be evaluated.
+    pub sampler_binding: Option<(u32, u32)>,
+}
+
+impl EntryPoint {
+    /// Whether the entry point outputs two colors to `@location(0)` for dual-source
+    /// blending, with `@blend_src(0)` and `@blend_src(1)`.
+    pub fn is_dual_source_blending(&self) -> bool {
+        self.outputs.iter().any(|var| var.blend_src.is_some())
+    }
+}
+
+/// The list of entry points of a shader. See [`crate::CompileResult::entry_points`].
+#[derive(Clone, Debug, Default, PartialEq, Deref)]
+pub struct EntryPoints(Vec<EntryPoint>);
+
+impl EntryPoints {
+    /// Collect the entry points declared in a translation unit.
+    pub fn from_syntax(wesl: &TranslationUnit) -> Self {
+        let mut folder = Folder::new(wesl);
+        let bindings = Bindings::from_syntax(wesl);
+        let entry_points = wesl
+            .global_declarations
+            .iter()
+            .filter_map(|decl| match decl.node() {
+                GlobalDeclaration::Function(f) => entry_point(f, wesl, &bindings, &mut folder),
+                _ => None,
+            })
+            .collect();
+        Self(entry_points)
+    }
+
+    /// Find an entry point by name.
+    pub fn get(&self, name: &str) -> Option<&EntryPoint> {
+        self.0.iter().find(|ep| ep.name == name)
+    }
+
+    /// The stages of the entry points using a resource binding, e.g. to set the
+    /// visibility of a bind group layout entry.
+    pub fn stages_using(&self, binding: &str) -> Vec<EntryPointStage> {
+        let mut stages = Vec::new();
+        for ep in &self.0 {
+            if ep.resources.iter().any(|res| res.name == binding) && !stages.contains(&ep.stage) {
+                stages.push(ep.stage);
+            }
+        }
+        stages
+    }
+
+    pub fn into_inner(self) -> Vec<EntryPoint> {
+        self.0
+    }
+
+    /// Keep only the entry points in `exports`. See [`strip_exports`].
+    pub(crate) fn retain_exported(&mut self, exports: &[String]) {
+        self.0.retain(|ep| exports.contains(&ep.name));
+    }
+}
+
+/// The name of the `@export` attribute, which selects the entry points reported by
+/// [`crate::CompileResult::entry_points`].
+pub(crate) const EXPORT: &str = "export";
+
+fn is_export(attr: &Attribute) -> bool {
+    matches!(attr, Attribute::Custom(attr) if attr.name == EXPORT)
+}
+
+/// The names of the `const` declarations marked with `@export`. They are exported to
+/// the host by [`crate::Wesl::host_consts`].
+#[cfg(feature = "eval")]
+pub(crate) fn exported_consts(wesl: &TranslationUnit) -> HashSet<String> {
+    wesl.global_declarations
+        .iter()
+        .filter_map(|decl| match decl.node() {
+            GlobalDeclaration::Declaration(d)
+                if d.kind == DeclarationKind::Const
+                    && d.attributes.iter().any(|attr| is_export(attr)) =>
+            {
+                Some(d.ident.to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Remove the `@export` attributes from the entry points and `const` declarations and
+/// return the names of the exported entry points, or `None` if no entry point is
+/// exported.
+///
+/// `@export` is not valid WGSL, it must be removed before the shader is emitted.
+pub(crate) fn strip_exports(
+    wesl: &mut TranslationUnit,
+) -> Result<Option<Vec<String>>, ValidateError> {
+    let mut exports = Vec::new();
+    for decl in &mut wesl.global_declarations {
+        let f = match decl.node_mut() {
+            GlobalDeclaration::Function(f) => f,
+            GlobalDeclaration::Declaration(d) if d.kind == DeclarationKind::Const => {
+                d.attributes.retain(|attr| !is_export(attr));
+                continue;
+            }
+            _ => continue,
+        };
+        let len = f.attributes.len();
+        f.attributes.retain(|attr| !is_export(attr));
+        if f.attributes.len() == len {
+            continue;
+        }
+        if entry_point_stage(f).is_none() {
+            return Err(ValidateError::NotExportable(f.ident.to_string()));
+        }
+        exports.push(f.ident.to_string());
+    }
+    Ok((!exports.is_empty()).then_some(exports))
+}
+
+/// The stage of a function, if it is an entry point.
+pub(crate) fn entry_point_stage(f: &Function) -> Option<EntryPointStage> {
+    f.attributes.iter().find_map(|attr| match attr.node() {
+        Attribute::Vertex => Some(EntryPointStage::Vertex),
+        Attribute::Fragment => Some(EntryPointStage::Fragment),
+        Attribute::Compute => Some(EntryPointStage::Compute),
+        _ => None,
+    })
+}
+
+fn entry_point(
+    f: &Function,
+    wesl: &TranslationUnit,
+    bindings: &Bindings,
+    folder: &mut Folder,
+) -> Option<EntryPoint> {
+    let stage = entry_point_stage(f)?;
+
+    let workgroup_size = f.attributes.iter().find_map(|attr| match attr.node() {
+        Attribute::WorkgroupSize(size) => {
+            let mut dim = |expr: Option<&Expression>| match expr {
+                Some(expr) => folder.fold(expr),
+                None => Some(1),
+            };
+            Some([
+                dim(Some(size.x.node()))?,
+                dim(size.y.as_deref())?,
+                dim(size.z.as_deref())?,
+            ])
+        }
+        _ => None,
+    });
+
+    let workgroup_memory = if stage == EntryPointStage::Compute {
+        workgroup_memory(f, wesl, folder)
+    } else {
+        None
+    };
+
+    let mut inputs = Vec::new();
+    for param in &f.parameters {
+        interface_variables(
+            Some(param.ident.to_string()),
+            &param.ty,
+            &param.attributes,
+            wesl,
+            folder,
+            &mut inputs,
+        );
+    }
+
+    let mut outputs = Vec::new();
+    if let Some(ty) = &f.return_type {
+        interface_variables(None, ty, &f.return_attributes, wesl, folder, &mut outputs);
+    }
+
+    let (resources, sampler_pairs) = crate::usage::resource_usage(f, wesl, bindings);
+
+    Some(EntryPoint {
+        name: f.ident.to_string(),
+        stage,
+        workgroup_size,
+        workgroup_memory,
+        inputs,
+        outputs,
+        resources,
+        sampler_pairs,
+    })
+}
+
+fn workgroup_memory(f: &Function, wesl: &TranslationUnit, folder: &mut Folder) -> Option<u32> {
+    let used = crate::compat::used_decls(&f.ident, wesl);
+    wesl.declarations()
+        .filter(|decl| {
+            matches!(
+                decl.kind,
+                DeclarationKind::Var(Some((AddressSpace::Workgroup, _)))
+            ) && used.contains(&decl.ident)
+        })
+        .map(|decl| {
+            let size = folder.size_of(decl.ty.as_ref()?)?;
+            Some(size.div_ceil(16) * 16)
+        })
+        .sum()
+}
+
+/// Check the workgroup memory of the compute entry points against a device limit.
+/// See [`crate::CompileOptions::max_workgroup_storage_size`].
+///
+/// Returns a warning for the entry points using more than half of the limit, since
+/// fewer workgroups can run concurrently on a compute unit.
+pub(crate) fn check_workgroup_memory(
+    wesl: &TranslationUnit,
+    limit: u32,
+) -> Result<Vec<crate::Diagnostic<crate::Error>>, ValidateError> {
+    let mut warnings = Vec::new();
+    for ep in EntryPoints::from_syntax(wesl).0 {
+        let Some(size) = ep.workgroup_memory else {
+            continue;
+        };
+        if size > limit {
+            return Err(ValidateError::WorkgroupMemory(ep.name, size, limit));
+        } else if size > limit / 2 {
+            let warning = ValidateError::WorkgroupPressure(ep.name, size, limit);
+            warnings.push(warning.into());
+        }
+    }
+    Ok(warnings)
+}
+
+/// A module-scope variable bound to a pipeline resource: a buffer, a texture, a sampler,
+/// or with the `naga-ext` crate feature, a push constant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Binding {
+    pub name: String,
+    /// The `@group`, if it can be evaluated. Push constants have no group.
+    pub group: Option<u32>,
+    /// The `@binding`, if it can be evaluated. Push constants have no binding.
+    pub binding: Option<u32>,
+    /// The address space. Textures and samplers are in the `handle` address space.
+    pub address_space: AddressSpace,
+    pub access_mode: AccessMode,
+    pub ty: TypeExpression,
+    /// Whether the type, after resolving type aliases, is a `binding_array` (`naga-ext`
+    /// crate feature).
+    pub binding_array: bool,
+    /// The element count of a `binding_array`, if it is fixed-size and can be evaluated.
+    pub count: Option<u32>,
+    /// The texture type, if `ty` (or the element type of a `binding_array`) is a valid
+    /// texture type, after resolving type aliases. See [`texture_type`].
+    pub texture: Option<TextureType>,
+    /// If the binding was expanded from a `@binding_range` variable, the name of the
+    /// variable and the index of the binding in the range. See
+    /// [`crate::CompileResult::binding_ranges`].
+    pub range: Option<(String, u32)>,
+}
+
+/// The list of resource bindings of a shader. See [`crate::CompileResult::bindings`].
+#[derive(Clone, Debug, Default, PartialEq, Deref)]
+pub struct Bindings(Vec<Binding>);
+
+impl Bindings {
+    /// Collect the resource variables declared in a translation unit.
+    ///
+    /// Variables in the `function`, `private` and `workgroup` address spaces are not
+    /// resources and are ignored.
+    pub fn from_syntax(wesl: &TranslationUnit) -> Self {
+        let mut folder = Folder::new(wesl);
+        let bindings = wesl
+            .global_declarations
+            .iter()
+            .filter_map(|decl| match decl.node() {
+                GlobalDeclaration::Declaration(d) => binding(d, wesl, &mut folder),
+                _ => None,
+            })
+            .collect();
+        Self(bindings)
+    }
+
+    /// Find a binding by variable name.
+    pub fn get(&self, name: &str) -> Option<&Binding> {
+        self.0.iter().find(|b| b.name == name)
+    }
+
+    /// Find a binding by group and binding number.
+    pub fn get_binding(&self, group: u32, binding: u32) -> Option<&Binding> {
+        self.0
+            .iter()
+            .find(|b| b.group == Some(group) && b.binding == Some(binding))
+    }
+
+    /// Iterate over the push constant variables.
+    #[cfg(feature = "naga-ext")]
+    pub fn push_constants(&self) -> impl Iterator<Item = &Binding> {
+        self.0
+            .iter()
+            .filter(|b| b.address_space == AddressSpace::PushConstant)
+    }
+
+    /// Set the [`Binding::range`] of the bindings expanded from `@binding_range`
+    /// variables.
+    pub(crate) fn with_ranges(mut self, ranges: &[BindingRange]) -> Self {
+        for binding in &mut self.0 {
+            binding.range = ranges.iter().find_map(|range| {
+                let i = range.elements.iter().position(|e| *e == binding.name)?;
+                Some((range.name.clone(), i as u32))
+            });
+        }
+        self
+    }
+
+    pub fn into_inner(self) -> Vec<Binding> {
+        self.0
+    }
+}
+
+impl FromIterator<Binding> for Bindings {
+    fn from_iter<T: IntoIterator<Item = Binding>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+fn binding(decl: &Declaration, wesl: &TranslationUnit, folder: &mut Folder) -> Option<Binding> {
+    let (address_space, access_mode) = match decl.kind {
+        DeclarationKind::Var(None) => (AddressSpace::Handle, AccessMode::Read),
+        DeclarationKind::Var(Some((space, access))) => {
+            (space, access.unwrap_or_else(|| space.default_access_mode()))
+        }
+        _ => return None,
+    };
+    if matches!(
+        address_space,
+        AddressSpace::Function | AddressSpace::Private | AddressSpace::Workgroup
+    ) {
+        return None;
+    }
+    let ty = decl.ty.clone()?;
+
+    let mut attr = |f: fn(&Attribute) -> Option<&ExpressionNode>| {
+        let expr = decl.attributes.iter().find_map(|attr| f(attr.node()))?;
+        folder.fold(expr.node())
+    };
+    let group = attr(|attr| match attr {
+        Attribute::Group(expr) => Some(expr),
+        _ => None,
+    });
+    let binding = attr(|attr| match attr {
+        Attribute::Binding(expr) => Some(expr),
+        _ => None,
+    });
+
+    // the type may be an alias of a binding array or texture.
+    let canonical = wesl.canonical_type(&ty).unwrap_or_else(|| ty.clone());
+    let binding_array = *canonical.ident.name() == "binding_array";
+    let count = binding_array
+        .then(|| canonical.template_args.as_ref()?.get(1))
+        .flatten()
+        .and_then(|arg| folder.fold(arg.expression.node()));
+
+    let texture = match canonical.template_args.as_deref() {
+        Some([arg, ..]) if binding_array => match arg.expression.node() {
+            Expression::TypeOrIdentifier(ty) => texture_type(ty),
+            _ => None,
+        },
+        _ => texture_type(&canonical),
+    };
+
+    Some(Binding {
+        name: decl.ident.to_string(),
+        group,
+        binding,
+        address_space,
+        access_mode,
+        ty,
+        binding_array,
+        count,
+        texture: texture.and_then(Result::ok),
+        range: None,
+    })
+}
+
+/// Convert a texture type expression to a typed [`TextureType`], without evaluation.
+///
+/// Returns `None` if `ty` is not a built-in texture type, or if its sampled type is not
+/// a built-in type (it may be a type alias, see [`SyntaxUtil::canonical_type`]). Returns
+/// an error if the template parameters are invalid.
+pub fn texture_type(ty: &TypeExpression) -> Option<Result<TextureType, wgsl_types::Error>> {
+    if ty.path.is_some() {
+        return None;
+    }
+    let name = ty.ident.name();
+    let texture = match name.as_str() {
+        "texture_depth_2d" => TextureType::Depth2D,
+        "texture_depth_2d_array" => TextureType::Depth2DArray,
+        "texture_depth_cube" => TextureType::DepthCube,
+        "texture_depth_cube_array" => TextureType::DepthCubeArray,
+        "texture_depth_multisampled_2d" => TextureType::DepthMultisampled2D,
+        "texture_external" => TextureType::External,
+        "texture_1d"
+        | "texture_2d"
+        | "texture_2d_array"
+        | "texture_3d"
+        | "texture_cube"
+        | "texture_cube_array"
+        | "texture_multisampled_2d"
+        | "texture_storage_1d"
+        | "texture_storage_2d"
+        | "texture_storage_2d_array"
+        | "texture_storage_3d" => return texture_template(&name, ty),
+        #[cfg(feature = "naga-ext")]
+        "texture_1d_array" | "texture_storage_1d_array" | "texture_multisampled_2d_array" => {
+            return texture_template(&name, ty);
+        }
+        _ => return None,
+    };
+    if ty.template_args.is_some() {
+        Some(Err(wgsl_types::Error::TemplateArgs("texture")))
+    } else {
+        Some(Ok(texture))
+    }
+}
+
+fn texture_template(
+    name: &str,
+    ty: &TypeExpression,
+) -> Option<Result<TextureType, wgsl_types::Error>> {
+    let invalid = wgsl_types::Error::Builtin("invalid texture template parameter");
+    let storage = name.starts_with("texture_storage");
+    let mut tplt = Vec::new();
+    for arg in ty.template_args.iter().flatten() {
+        let Expression::TypeOrIdentifier(ty) = arg.expression.node() else {
+            return Some(Err(invalid));
+        };
+        let name = ty.ident.name();
+        let scalar = match name.as_str() {
+            "i32" => Some(Type::I32),
+            "u32" => Some(Type::U32),
+            "f32" => Some(Type::F32),
+            _ => None,
+        };
+        if let Ok(enumerant) = name.parse::<Enumerant>() {
+            tplt.push(TpltParam::Enumerant(enumerant));
+        } else if let Some(scalar) = scalar.filter(|_| ty.template_args.is_none()) {
+            tplt.push(TpltParam::Type(scalar));
+        } else if !storage && (ty.path.is_some() || builtin_ident(&name).is_none()) {
+            // a user-declared type, which may be an alias of a valid sampled type.
+            return None;
+        } else {
+            return Some(Err(invalid));
+        }
+    }
+    Some(TextureTemplate::parse(name, &tplt).map(|tplt| tplt.ty()))
+}
+
+/// A pipeline-overridable constant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Override {
+    pub name: String,
+    /// The `@id`, if it can be evaluated.
+    ///
+    /// See [`crate::CompileOptions::assign_override_ids`] to give an `@id` to all
+    /// overrides.
+    pub id: Option<u32>,
+    pub ty: Option<TypeExpression>,
+    /// Whether the override has an initializer. Overrides without an initializer must be
+    /// set by the host when creating the pipeline.
+    pub has_default: bool,
+}
+
+/// The list of pipeline-overridable constants of a shader. See
+/// [`crate::CompileResult::overrides`].
+#[derive(Clone, Debug, Default, PartialEq, Deref)]
+pub struct Overrides(Vec<Override>);
+
+impl Overrides {
+    /// Collect the overrides declared in a translation unit.
+    pub fn from_syntax(wesl: &TranslationUnit) -> Self {
+        let mut folder = Folder::new(wesl);
+        let overrides = wesl
+            .global_declarations
+            .iter()
+            .filter_map(|decl| match decl.node() {
+                GlobalDeclaration::Declaration(d) if d.kind == DeclarationKind::Override => {
+                    Some(Override {
+                        name: d.ident.to_string(),
+                        id: override_id(d, &mut folder).flatten(),
+                        ty: d.ty.clone(),
+                        has_default: d.initializer.is_some(),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        Self(overrides)
+    }
+
+    /// Find an override by name.
+    pub fn get(&self, name: &str) -> Option<&Override> {
+        self.0.iter().find(|o| o.name == name)
+    }
+
+    /// Find an override by `@id`.
+    pub fn get_id(&self, id: u32) -> Option<&Override> {
+        self.0.iter().find(|o| o.id == Some(id))
+    }
+
+    pub fn into_inner(self) -> Vec<Override> {
+        self.0
+    }
+}
+
+/// The `@id` of an override: `None` if it has no `@id` attribute, `Some(None)` if the
+/// attribute cannot be evaluated.
+fn override_id(decl: &Declaration, folder: &mut Folder) -> Option<Option<u32>> {
+    decl.attributes.iter().find_map(|attr| match attr.node() {
+        Attribute::Id(expr) => Some(folder.fold(expr)),
+        _ => None,
+    })
+}
+
+/// Check that the override `@id`s are unique and give an `@id` to the overrides that
+/// have none. See [`crate::CompileOptions::assign_override_ids`].
+///
+/// Overrides without an `@id` get the lowest unused ids, in the alphabetical order of
+/// their names. This makes the ids independent of the declaration order.
+pub(crate) fn assign_override_ids(wesl: &mut TranslationUnit) -> Result<(), ValidateError> {
+    let mut used = HashSet::new();
+    let mut unnumbered = Vec::new();
+
+    let mut folder = Folder::new(wesl);
+    for (i, decl) in wesl.global_declarations.iter().enumerate() {
+        let GlobalDeclaration::Declaration(d) = decl.node() else {
+            continue;
+        };
+        if d.kind != DeclarationKind::Override {
+            continue;
+        }
+        match override_id(d, &mut folder) {
+            Some(Some(id)) => {
+                if !used.insert(id) {
+                    return Err(ValidateError::DuplicateOverrideId(id, d.ident.to_string()));
+                }
+            }
+            Some(None) => return Err(ValidateError::OverrideId(d.ident.to_string())),
+            None => unnumbered.push((d.ident.to_string(), i)),
+        }
+    }
+
+    unnumbered.sort();
+    let ids = (0..).filter(|id| !used.contains(id));
+    for ((_, i), id) in unnumbered.into_iter().zip(ids) {
+        if let GlobalDeclaration::Declaration(d) = wesl.global_declarations[i].node_mut() {
+            let expr = Expression::Literal(LiteralExpression::AbstractInt(id.into()));
+            d.attributes.push(Attribute::Id(expr.into()).into());
+        }
+    }
+    Ok(())
+}
+
+fn interface_variables(
+    name: Option<String>,
+    ty: &TypeExpression,
+    attrs: &Attributes,
+    wesl: &TranslationUnit,
+    folder: &mut Folder,
+    vars: &mut Vec<InterfaceVariable>,
+) {
+    let location = attrs.iter().find_map(|attr| match attr.node() {
+        Attribute::Location(expr) => Some(expr),
+        _ => None,
+    });
+    let blend_src = attrs.iter().find_map(|attr| match attr.node() {
+        Attribute::BlendSrc(expr) => Some(expr),
+        _ => None,
+    });
+    let builtin = attrs.iter().find_map(|attr| match attr.node() {
+        Attribute::Builtin(builtin) => Some(*builtin),
+        _ => None,
+    });
+    let interpolate = attrs.iter().find_map(|attr| match attr.node() {
+        Attribute::Interpolate(interp) => Some(interp.clone()),
+        _ => None,
+    });
+
+    // the type may be an alias of a struct.
+    let canonical = wesl.canonical_type(ty);
+    let struct_ty = canonical.as_ref().unwrap_or(ty);
+    let strukt = (location.is_none() && builtin.is_none())
+        .then(|| {
+            wesl.global_declarations
+                .iter()
+                .find_map(|decl| match decl.node() {
+                    GlobalDeclaration::Struct(s) if *s.ident.name() == *struct_ty.ident.name() => {
+                        Some(s)
+                    }
+                    _ => None,
+                })
+        })
+        .flatten();
+
+    if let Some(strukt) = strukt {
+        for m in &strukt.members {
+            interface_variables(
+                Some(m.ident.to_string()),
+                &m.ty,
+                &m.attributes,
+                wesl,
+                folder,
+                vars,
+            );
+        }
+    } else {
+        vars.push(InterfaceVariable {
+            name,
+            ty: ty.clone(),
+            location: location.and_then(|expr| folder.fold(expr)),
+            blend_src: blend_src.and_then(|expr| folder.fold(expr)),
+            interpolate,
+            builtin,
+        })
+    }
+}
+
+/// Compute the memory layout of the host-shareable structs declared in a translation
+/// unit, in declaration order. See [`crate::CompileResult::struct_layouts`].
+///
+/// Structs that are not host-shareable (e.g. containing a `bool`) are skipped.
+///
+/// Requires the `eval` crate feature flag.
+#[cfg(feature = "eval")]
+pub fn struct_layouts(
+    wesl: &TranslationUnit,
+) -> Result<Vec<wgsl_types::StructLayout>, crate::Error> {
+    use crate::eval::Exec;
+    let mut ctx = crate::eval::Context::new(wesl);
+    wesl.exec(&mut ctx)?;
+    let mut layouts = Vec::new();
+    for s in wesl.structs() {
+        let ty = TypeExpression::new(s.ident.clone());
+        let ty = crate::eval::ty_eval_ty(&ty, &mut ctx)
+            .map_err(|e| crate::Diagnostic::from(e).with_ctx(&ctx))?;
+        if let Type::Struct(s) = ty {
+            if !contains_bool(&Type::Struct(s.clone())) {
+                layouts.extend(s.layout());
+            }
+        }
+    }
+    Ok(layouts)
+}
+
+/// `bool` has a size, but is not host-shareable.
+#[cfg(feature = "eval")]
+fn contains_bool(ty: &Type) -> bool {
+    match ty {
+        Type::Bool => true,
+        Type::Struct(s) => s.members.iter().any(|m| contains_bool(&m.ty)),
+        Type::Array(ty, _) | Type::Vec(_, ty) | Type::Atomic(ty) => contains_bool(ty),
+        _ => false,
+    }
+}
+
+/// The host-visible interface of a shader. See [`crate::CompileResult::reflection`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Reflection {
+    pub entry_points: EntryPoints,
+    pub bindings: Bindings,
+    pub overrides: Overrides,
+    /// The layouts of the host-shareable structs. Empty without the `eval` crate feature.
+    pub struct_layouts: Vec<wgsl_types::StructLayout>,
+}
+
+/// A change of the host-visible interface of a shader, reported by [`Reflection::diff`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ReflectionChange {
+    /// A resource binding was added.
+    BindingAdded(String),
+    /// A resource binding was removed.
+    BindingRemoved(String),
+    /// The `@group` or `@binding` of a resource changed.
+    BindingMoved(String),
+    /// The type, address space or access mode of a resource changed.
+    BindingRetyped(String),
+    /// The memory layout of a host-shareable struct changed, or a struct stopped being
+    /// host-shareable.
+    StructLayoutChanged(String),
+    /// An entry point was added.
+    EntryPointAdded(String),
+    /// An entry point was removed.
+    EntryPointRemoved(String),
+    /// The stage, workgroup size, inputs or outputs of an entry point changed.
+    EntryPointChanged(String),
+    /// A pipeline-overridable constant was added.
+    OverrideAdded(String),
+    /// A pipeline-overridable constant was removed.
+    OverrideRemoved(String),
+    /// The `@id`, type or default value presence of an override changed.
+    OverrideChanged(String),
+}
+
+impl Reflection {
+    /// Collect the host-visible interface declared in a translation unit.
+    pub fn from_syntax(wesl: &TranslationUnit) -> Result<Self, crate::Error> {
+        Ok(Self {
+            entry_points: EntryPoints::from_syntax(wesl),
+            bindings: Bindings::from_syntax(wesl),
+            overrides: Overrides::from_syntax(wesl),
+            #[cfg(feature = "eval")]
+            struct_layouts: struct_layouts(wesl)?,
+            #[cfg(not(feature = "eval"))]
+            struct_layouts: Vec::new(),
+        })
+    }
+
+    /// Compare the interfaces of two versions of a shader.
+    ///
+    /// Items are matched by name, so the changes can be used to detect at build time
+    /// when a shader change requires updating the host-side pipelines, bind group
+    /// layouts or buffers. Types are compared by their spelling.
+    pub fn diff(old: &Self, new: &Self) -> Vec<ReflectionChange> {
+        let mut changes = Vec::new();
+
+        for b in old.bindings.iter() {
+            match new.bindings.get(&b.name) {
+                None => changes.push(ReflectionChange::BindingRemoved(b.name.clone())),
+                Some(n) => {
+                    if (b.group, b.binding) != (n.group, n.binding) {
+                        changes.push(ReflectionChange::BindingMoved(b.name.clone()));
+                    }
+                    if binding_signature(b) != binding_signature(n) {
+                        changes.push(ReflectionChange::BindingRetyped(b.name.clone()));
+                    }
+                }
+            }
+        }
+        for b in new.bindings.iter() {
+            if old.bindings.get(&b.name).is_none() {
+                changes.push(ReflectionChange::BindingAdded(b.name.clone()));
+            }
+        }
+
+        for l in &old.struct_layouts {
+            if new.struct_layouts.iter().find(|n| n.name == l.name) != Some(l) {
+                changes.push(ReflectionChange::StructLayoutChanged(l.name.clone()));
+            }
+        }
+
+        for ep in old.entry_points.iter() {
+            match new.entry_points.get(&ep.name) {
+                None => changes.push(ReflectionChange::EntryPointRemoved(ep.name.clone())),
+                Some(n) if entry_point_signature(ep) != entry_point_signature(n) => {
+                    changes.push(ReflectionChange::EntryPointChanged(ep.name.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for ep in new.entry_points.iter() {
+            if old.entry_points.get(&ep.name).is_none() {
+                changes.push(ReflectionChange::EntryPointAdded(ep.name.clone()));
+            }
+        }
+
+        let override_signature =
+            |o: &Override| (o.id, o.ty.as_ref().map(|ty| ty.to_string()), o.has_default);
+        for o in old.overrides.iter() {
+            match new.overrides.get(&o.name) {
+                None => changes.push(ReflectionChange::OverrideRemoved(o.name.clone())),
+                Some(n) if override_signature(o) != override_signature(n) => {
+                    changes.push(ReflectionChange::OverrideChanged(o.name.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for o in new.overrides.iter() {
+            if old.overrides.get(&o.name).is_none() {
+                changes.push(ReflectionChange::OverrideAdded(o.name.clone()));
+            }
+        }
+
+        changes
+    }
+}
+
+/// Identifiers compare by reference, so types are compared by their spelling.
+fn binding_signature(b: &Binding) -> impl PartialEq {
+    (
+        b.address_space,
+        b.access_mode,
+        b.ty.to_string(),
+        b.binding_array,
+        b.count,
+        b.texture.clone(),
+    )
+}
+
+fn entry_point_signature(ep: &EntryPoint) -> impl PartialEq {
+    let vars = |vars: &[InterfaceVariable]| {
+        vars.iter()
+            .map(|var| {
+                (
+                    var.name.clone(),
+                    var.ty.to_string(),
+                    var.location,
+                    var.blend_src,
+                    var.interpolate.clone(),
+                    var.builtin,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    (
+        ep.stage,
+        ep.workgroup_size,
+        vars(&ep.inputs),
+        vars(&ep.outputs),
+    )
+}
+
+/// Evaluates attribute arguments to positive integers.
+pub(crate) struct Folder<'a> {
+    #[cfg(feature = "eval")]
+    ctx: Option<crate::eval::Context<'a>>,
+    #[cfg(not(feature = "eval"))]
+    _wesl: std::marker::PhantomData<&'a TranslationUnit>,
+}
+
+impl<'a> Folder<'a> {
+    pub(crate) fn new(#[allow(unused)] wesl: &'a TranslationUnit) -> Self {
+        Self {
+            #[cfg(feature = "eval")]
+            ctx: {
+                use crate::Exec;
+                let mut ctx = crate::eval::Context::new(wesl);
+                wesl.exec(&mut ctx).is_ok().then_some(ctx)
+            },
+            #[cfg(not(feature = "eval"))]
+            _wesl: std::marker::PhantomData,
+        }
+    }
+
+    pub(crate) fn fold(&mut self, expr: &Expression) -> Option<u32> {
+        match expr {
+            Expression::Literal(LiteralExpression::AbstractInt(i)) => u32::try_from(*i).ok(),
+            Expression::Literal(LiteralExpression::I32(i)) => u32::try_from(*i).ok(),
+            Expression::Literal(LiteralExpression::U32(u)) => Some(*u),
+            #[cfg(feature = "eval")]
+            _ => {
+                use crate::eval::{Eval, Instance, LiteralInstance};
+                let ctx = self.ctx.as_mut()?;
+                match expr.eval_value(ctx).ok()? {
+                    Instance::Literal(LiteralInstance::AbstractInt(i)) => u32::try_from(i).ok(),
+                    Instance::Literal(LiteralInstance::I32(i)) => u32::try_from(i).ok(),
+                    Instance::Literal(LiteralInstance::U32(u)) => Some(u),
+                    _ => None,
+                }
+            }
+            #[cfg(not(feature = "eval"))]
+            _ => None,
+        }
+    }
+
+    /// Compute the size of a type, see [`Type::size_of`]. Requires the `eval` crate
+    /// feature, otherwise it returns `None`.
+    pub(crate) fn size_of(&mut self, #[allow(unused)] ty: &TypeExpression) -> Option<u32> {
+        #[cfg(feature = "eval")]
+        {
+            let ctx = self.ctx.as_mut()?;
+            crate::eval::ty_eval_ty(ty, ctx).ok()?.size_of()
+        }
+        #[cfg(not(feature = "eval"))]
+        None
+    }
+
+    /// Evaluate an integer const-expression, with its type: `i32`, `u32`, or `None` if
+    /// it is an abstract integer. Without the `eval` crate feature, only literals are
+    /// supported.
+    pub(crate) fn fold_int(&mut self, expr: &Expression) -> Option<(i64, Option<&'static str>)> {
+        match expr {
+            Expression::Literal(LiteralExpression::AbstractInt(i)) => Some((*i, None)),
+            Expression::Literal(LiteralExpression::I32(i)) => Some((*i as i64, Some("i32"))),
+            Expression::Literal(LiteralExpression::U32(u)) => Some((*u as i64, Some("u32"))),
+            #[cfg(feature = "eval")]
+            _ => {
+                use crate::eval::{Eval, Instance, LiteralInstance};
+                let ctx = self.ctx.as_mut()?;
+                match expr.eval_value(ctx).ok()? {
+                    Instance::Literal(LiteralInstance::AbstractInt(i)) => Some((i, None)),
+                    Instance::Literal(LiteralInstance::I32(i)) => Some((i as i64, Some("i32"))),
+                    Instance::Literal(LiteralInstance::U32(u)) => Some((u as i64, Some("u32"))),
+                    _ => None,
+                }
+            }
+            #[cfg(not(feature = "eval"))]
+            Expression::Parenthesized(paren) => self.fold_int(paren.expression.node()),
+            #[cfg(not(feature = "eval"))]
+            Expression::Unary(unary) if unary.operator == UnaryOperator::Negation => {
+                self.fold_int(unary.operand.node()).map(|(i, ty)| (-i, ty))
+            }
+            #[cfg(not(feature = "eval"))]
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_entry_points() {
+    use wgsl_parse::syntax::BuiltinValue;
+    let wesl = wgsl_parse::parse_str(
+        "struct VertexOut { @builtin(position) pos: vec4f, @location(0 + 1) uv: vec2f }
+        struct FragOut { @location(0) @blend_src(0) a: vec4f, @location(0) @blend_src(1) b: vec4f }
+        const SIZE = 8;
+        @vertex fn vs(@location(0) uv: vec2f) -> VertexOut { return VertexOut(); }
+        @fragment fn fs() -> FragOut { return FragOut(); }
+        @compute @workgroup_size(SIZE, 2) fn cs() {}
+        fn not_an_entry_point() {}",
+    )
+    .unwrap();
+    let eps = EntryPoints::from_syntax(&wesl);
+    assert_eq!(eps.len(), 3);
+
+    let vs = eps.get("vs").unwrap();
+    assert_eq!(vs.stage, EntryPointStage::Vertex);
+    assert_eq!(vs.inputs[0].location, Some(0));
+    assert_eq!(vs.outputs.len(), 2);
+    assert_eq!(vs.outputs[0].builtin, Some(BuiltinValue::Position));
+    assert!(!vs.is_dual_source_blending());
+
+    let fs = eps.get("fs").unwrap();
+    assert!(fs.is_dual_source_blending());
+    assert_eq!(fs.outputs[1].blend_src, Some(1));
+
+    #[cfg(feature = "eval")]
+    {
+        assert_eq!(vs.outputs[1].location, Some(1));
+        assert_eq!(eps.get("cs").unwrap().workgroup_size, Some([8, 2, 1]));
+    }
+}
+
+#[test]
+fn test_bindings() {
+    let wesl = wgsl_parse::parse_str(
+        "const N = 4;
+        @group(0) @binding(N - 4) var<uniform> camera: mat4x4f;
+        @group(1) @binding(0) var<storage, read_write> data: array<f32>;
+        @group(1) @binding(1) var tex: texture_2d<f32>;
+        alias Scalar = f32;
+        alias Texture = texture_3d<Scalar>;
+        @group(1) @binding(2) var tex3d: Texture;
+        var<private> p: f32;
+        var<workgroup> w: f32;",
+    )
+    .unwrap();
+    let bindings = Bindings::from_syntax(&wesl);
+    assert_eq!(bindings.len(), 4);
+
+    let data = bindings.get("data").unwrap();
+    assert_eq!(data.address_space, AddressSpace::Storage);
+    assert_eq!(data.access_mode, AccessMode::ReadWrite);
+    assert_eq!(bindings.get_binding(1, 1).unwrap().name, "tex");
+    assert_eq!(
+        bindings.get("tex").unwrap().address_space,
+        AddressSpace::Handle
+    );
+    assert_eq!(
+        bindings.get("tex").unwrap().texture,
+        Some(TextureType::Sampled2D(wgsl_types::syntax::SampledType::F32))
+    );
+    assert_eq!(
+        bindings.get("tex3d").unwrap().texture,
+        Some(TextureType::Sampled3D(wgsl_types::syntax::SampledType::F32))
+    );
+
+    #[cfg(feature = "eval")]
+    assert_eq!(bindings.get_binding(0, 0).unwrap().name, "camera");
+}
+
+#[cfg(feature = "naga-ext")]
+#[test]
+fn test_naga_ext_bindings() {
+    let wesl = wgsl_parse::parse_str(
+        "struct Consts { scale: f32 }
+        var<push_constant> consts: Consts;
+        @group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 8>;",
+    )
+    .unwrap();
+    let bindings = Bindings::from_syntax(&wesl);
+    assert_eq!(bindings.push_constants().count(), 1);
+
+    let textures = bindings.get("textures").unwrap();
+    assert!(textures.binding_array);
+    assert_eq!(textures.count, Some(8));
+    assert!(textures.texture.is_some());
+}
+
+#[test]
+fn test_overrides() {
+    let mut wesl = wgsl_parse::parse_str(
+        "@id(1) override b: f32 = 1.0;
+        override c: u32;
+        override a: bool;
+        @id(0) override d: i32 = 2;",
+    )
+    .unwrap();
+    let overrides = Overrides::from_syntax(&wesl);
+    assert_eq!(overrides.len(), 4);
+    assert_eq!(overrides.get_id(1).unwrap().name, "b");
+    assert!(!overrides.get("c").unwrap().has_default);
+    assert_eq!(overrides.get("a").unwrap().id, None);
+
+    assign_override_ids(&mut wesl).unwrap();
+    let overrides = Overrides::from_syntax(&wesl);
+    assert_eq!(overrides.get("a").unwrap().id, Some(2));
+    assert_eq!(overrides.get("c").unwrap().id, Some(3));
+
+    let mut wesl =
+        wgsl_parse::parse_str("@id(1) override a: f32; @id(1) override b: f32;").unwrap();
+    assert!(matches!(
+        assign_override_ids(&mut wesl),
+        Err(ValidateError::DuplicateOverrideId(1, name)) if name == "b"
+    ));
+}
+
+#[test]
+fn test_texture_type() {
+    use wgsl_types::syntax::{SampledType, TexelFormat};
+    let texture = |source: &str| {
+        let ty = wgsl_parse::parse_type(source).unwrap();
+        texture_type(&ty).map(|res| res.ok())
+    };
+    assert_eq!(
+        texture("texture_storage_2d<rgba8unorm, write>").unwrap(),
+        Some(TextureType::Storage2D(
+            TexelFormat::Rgba8Unorm,
+            AccessMode::Write
+        ))
+    );
+    assert_eq!(
+        texture("texture_cube<u32>").unwrap(),
+        Some(TextureType::SampledCube(SampledType::U32))
+    );
+    assert_eq!(
+        texture("texture_depth_2d").unwrap(),
+        Some(TextureType::Depth2D)
+    );
+    assert!(texture("texture_2d<f16>").unwrap().is_none());
+    assert!(texture("texture_storage_2d<rgba8unorm>").unwrap().is_none());
+    assert!(
+        texture("texture_storage_2d<rgb8unorm, read>")
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        texture("texture_storage_2d<rgb8unorm, read_write>")
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        texture("texture_storage_2d<read, rgba8unorm>")
+            .unwrap()
+            .is_none()
+    );
+    assert!(texture("texture_depth_2d<f32>").unwrap().is_none());
+    assert!(texture("vec4<f32>").is_none());
+}
+
+#[cfg(feature = "eval")]
+#[test]
+fn test_struct_layouts() {
+    let wesl = wgsl_parse::parse_str(
+        "struct Light { color: vec3f, intensity: f32, dir: vec3f }
+        struct Params { scale: f32, @align(16) offset: vec2f, lights: array<Light> }
+        struct Flags { enabled: bool }",
+    )
+    .unwrap();
+    let layouts = struct_layouts(&wesl).unwrap();
+    assert_eq!(layouts.len(), 2);
+
+    let light = &layouts[0];
+    assert_eq!((light.size, light.align), (32, 16));
+    let offsets = light.members.iter().map(|m| m.offset).collect::<Vec<_>>();
+    assert_eq!(offsets, [0, 12, 16]);
+    assert_eq!(light.members[2].padding, 4);
+
+    let params = &layouts[1];
+    assert_eq!(params.members[0].padding, 12);
+    assert_eq!(params.members[2].offset, 32);
+    let table = params.to_string();
+    assert!(table.contains("(padding)"), "{table}");
+    assert!(
+        params
+            .to_markdown()
+            .contains("| 4 | 12 |  | (padding) |  |")
+    );
+}
+
+#[test]
+fn test_reflection_diff() {
+    let reflect = |source: &str| {
+        let wesl = wgsl_parse::parse_str(source).unwrap();
+        Reflection::from_syntax(&wesl).unwrap()
+    };
+    let old = reflect(
+        "struct Light { color: vec4f, intensity: f32 }
+        @group(0) @binding(0) var<uniform> light: Light;
+        @group(0) @binding(1) var tex: texture_2d<f32>;
+        @group(0) @binding(2) var samp: sampler;
+        override scale: f32 = 1.0;
+        @vertex fn vs(@location(0) pos: vec4f) -> @builtin(position) vec4f { return pos; }
+        @fragment fn fs() -> @location(0) vec4f { return light.color; }",
+    );
+    assert!(Reflection::diff(&old, &old).is_empty());
+
+    let new = reflect(
+        "struct Light { color: vec3f, intensity: f32 }
+        @group(0) @binding(0) var<uniform> light: Light;
+        @group(1) @binding(1) var tex: texture_2d<f32>;
+        @group(0) @binding(3) var<storage> data: array<f32>;
+        alias Sampler = sampler_comparison;
+        @group(0) @binding(2) var samp: Sampler;
+        @vertex fn vs(@location(0) pos: vec4f, @location(1) uv: vec2f)
+            -> @builtin(position) vec4f { return pos; }
+        @fragment fn fs() -> @location(0) vec4f { return vec4f(light.color, 1.0); }
+        @compute @workgroup_size(1) fn cs() {}",
+    );
+    let changes = Reflection::diff(&old, &new);
+    use ReflectionChange::*;
+    for change in [
+        BindingMoved("tex".to_string()),
+        BindingRetyped("samp".to_string()),
+        BindingAdded("data".to_string()),
+        EntryPointChanged("vs".to_string()),
+        EntryPointAdded("cs".to_string()),
+        OverrideRemoved("scale".to_string()),
+    ] {
+        assert!(changes.contains(&change), "{change:?} not in {changes:?}");
+    }
+    assert!(!changes.contains(&EntryPointChanged("fs".to_string())));
+    assert!(!changes.contains(&BindingRetyped("light".to_string())));
+    #[cfg(feature = "eval")]
+    assert!(changes.contains(&StructLayoutChanged("Light".to_string())));
+}
+
+#[cfg(feature = "eval")]
+#[test]
+fn test_workgroup_memory() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let source = "const N = 64;
+        struct Tile { data: array<f32, N>, count: u32 }
+        var<workgroup> tile: Tile;
+        var<workgroup> flags: array<u32, 3>;
+        var<workgroup> unused: array<vec4f, 1024>;
+        fn load() -> f32 { return tile.data[flags[0]]; }
+        @compute @workgroup_size(N) fn main() { _ = load(); }
+        @compute @workgroup_size(1) fn empty() {}";
+    let mut wesl = wgsl_parse::parse_str(source).unwrap();
+    wesl.retarget_idents();
+    let eps = EntryPoints::from_syntax(&wesl);
+    let main = eps.iter().find(|ep| ep.name == "main").unwrap();
+    // `Tile` is 260 bytes, rounded up to 272, and 12 bytes rounded up to 16.
+    assert_eq!(main.workgroup_memory, Some(288));
+    let empty = eps.iter().find(|ep| ep.name == "empty").unwrap();
+    assert_eq!(empty.workgroup_memory, Some(0));
+
+    let compile = |limit| {
+        let mut resolver = VirtualResolver::new();
+        resolver.add_module("package::main".parse().unwrap(), source.into());
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+        compiler
+            .set_options(CompileOptions::default())
+            .set_max_workgroup_storage_size(limit);
+        compiler.compile(&"package::main".parse().unwrap())
+    };
+    let res = compile(Some(1024)).unwrap_or_else(|e| panic!("{e}"));
+    assert!(res.warnings.is_empty());
+    let res = compile(Some(512)).unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(res.warnings.len(), 1);
+    assert!(res.warnings[0].to_string().contains("limits occupancy"));
+    let err = compile(Some(256)).err().unwrap();
+    assert!(
+        err.to_string()
+            .contains("uses 288 bytes of workgroup memory")
+    );
+}
+
+#[test]
+fn test_resource_usage() {
+    let source = "@group(0) @binding(0) var albedo: texture_2d<f32>;
+        @group(0) @binding(1) var normals: texture_2d<f32>;
+        @group(0) @binding(2) var linear: sampler;
+        @group(0) @binding(3) var nearest: sampler;
+        @group(1) @binding(0) var<storage, read_write> counters: array<atomic<u32>>;
+        @group(1) @binding(1) var<storage, read_write> out: array<vec4f>;
+        @group(1) @binding(2) var<storage> lut: array<f32>;
+        @group(1) @binding(3) var image: texture_storage_2d<rgba8unorm, write>;
+        fn shade(t: texture_2d<f32>, s: sampler, uv: vec2f) -> vec4f {
+            return textureSample(t, s, uv);
+        }
+        fn store(p: ptr<storage, array<vec4f>, read_write>, i: u32, v: vec4f) { (*p)[i] = v; }
+        @fragment fn fs(@location(0) uv: vec2f) -> @location(0) vec4f {
+            let n = textureGather(1, normals, nearest, uv);
+            return shade(albedo, linear, uv) + n;
+        }
+        @compute @workgroup_size(1) fn cs() {
+            let i = atomicAdd(&counters[0], 1u);
+            store(&out, i, vec4f(lut[i]));
+            textureStore(image, vec2u(i), vec4f(arrayLength(&lut)));
+        }";
+    let mut wesl = wgsl_parse::parse_str(source).unwrap();
+    wesl.retarget_idents();
+    let eps = EntryPoints::from_syntax(&wesl);
+
+    let usage = |ep: &str| {
+        eps.get(ep)
+            .unwrap()
+            .resources
+            .iter()
+            .map(|res| (res.name.as_str(), res.read, res.write))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        usage("fs"),
+        vec![
+            ("albedo", true, false),
+            ("normals", true, false),
+            ("linear", true, false),
+            ("nearest", true, false),
+        ]
+    );
+    assert_eq!(
+        usage("cs"),
+        vec![
+            ("counters", true, true),
+            ("out", false, true),
+            ("lut", true, false),
+            ("image", false, true),
+        ]
+    );
+    let pairs = &eps.get("fs").unwrap().sampler_pairs;
+    assert_eq!(pairs.len(), 2);
+    assert_eq!(
+        (pairs[0].texture.as_str(), pairs[0].sampler.as_str()),
+        ("normals", "nearest")
+    );
+    assert_eq!(pairs[1].texture_binding, Some((0, 0)));
+    assert_eq!(pairs[1].sampler_binding, Some((0, 2)));
+    assert_eq!(eps.stages_using("lut"), vec![EntryPointStage::Compute]);
+}
@@ -1,4 +1,4 @@
-use crate::{Diagnostic, Error};
+use crate::{Diagnostic, Error, Version, VersionReq, workspace::fresh_idents};
 
 use itertools::Itertools;
 use wgsl_parse::syntax::{ModulePath, PathOrigin, TranslationUnit};
@@ -7,7 +7,7 @@ use std::{
     borrow::Cow,
     collections::HashMap,
     fs,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 /// Error produced by module resolution.
@@ -17,6 +17,12 @@ pub enum ResolveError {
     FileNotFound(PathBuf, String),
     #[error("module not found: `{0}` ({1})")]
     ModuleNotFound(ModulePath, String),
+    #[error("access denied: `{0}` ({1})")]
+    AccessDenied(PathBuf, String),
+    #[error("package `{0}` has version {1}, but {3} requires `{2}`")]
+    VersionMismatch(String, String, String, String),
+    #[error("invalid import map path `{0}`: {1}")]
+    InvalidImportMap(String, String),
     #[error("{0}")]
     Error(#[from] Diagnostic<Error>),
 }
@@ -99,13 +105,35 @@ impl Resolver for NoResolver {
     }
 }
 
+/// How a sandboxed [`FileResolver`] treats symbolic links.
+/// See [`FileResolver::set_symlink_policy`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Follow symbolic links if the file they point to is inside the base directory.
+    #[default]
+    FollowInside,
+    /// Follow all symbolic links, even if they point outside the base directory.
+    Follow,
+    /// Reject module files whose path contains a symbolic link.
+    Deny,
+}
+
 /// A resolver that looks for files in the filesystem.
 ///
 /// It simply translates module paths to file paths. This is the intended behavior.
+/// Files with the `.wesl` extension (see [`Self::set_extension`]) are looked up first,
+/// then `.wgsl` files, then `.wesli` interface files (see [`crate::module_interface`]).
+/// `.wgsl` files can be checked to contain plain WGSL, see
+/// [`crate::CompileOptions::plain_wgsl`].
+///
+/// Module paths are not trusted to stay inside the base directory: enable
+/// [`Self::set_sandbox`] when compiling untrusted shaders.
 #[derive(Default)]
 pub struct FileResolver {
     base: PathBuf,
     extension: &'static str,
+    sandbox: bool,
+    symlinks: SymlinkPolicy,
 }
 
 impl FileResolver {
@@ -116,6 +144,8 @@ impl FileResolver {
         Self {
             base: base.as_ref().to_path_buf(),
             extension: "wesl",
+            sandbox: false,
+            symlinks: SymlinkPolicy::default(),
         }
     }
 
@@ -124,6 +154,56 @@ impl FileResolver {
         self.extension = extension;
     }
 
+    /// Only resolve files inside the base directory. Default: `false`.
+    ///
+    /// In sandbox mode, module path components must be plain file names (no `..`, `.`
+    /// or path separators) and symbolic links are handled according to
+    /// [`Self::set_symlink_policy`]. Other paths fail with [`ResolveError::AccessDenied`].
+    pub fn set_sandbox(&mut self, sandbox: bool) {
+        self.sandbox = sandbox;
+    }
+
+    /// Set how symbolic links are handled in sandbox mode.
+    /// Default: [`SymlinkPolicy::FollowInside`].
+    pub fn set_symlink_policy(&mut self, policy: SymlinkPolicy) {
+        self.symlinks = policy;
+    }
+
+    /// Check that a resolved file path does not escape the base directory.
+    fn check_sandbox(&self, fs_path: &Path) -> Result<(), ResolveError> {
+        let denied = |reason: &str| E::AccessDenied(fs_path.to_path_buf(), reason.to_string());
+        match self.symlinks {
+            SymlinkPolicy::FollowInside => {
+                let base = self
+                    .base
+                    .canonicalize()
+                    .map_err(|_| denied("cannot canonicalize the base directory"))?;
+                let canonical = fs_path
+                    .canonicalize()
+                    .map_err(|_| denied("cannot canonicalize the path"))?;
+                if !canonical.starts_with(base) {
+                    return Err(denied("the path escapes the base directory"));
+                }
+            }
+            SymlinkPolicy::Follow => {}
+            SymlinkPolicy::Deny => {
+                let mut path = fs_path;
+                while path != self.base {
+                    let is_symlink = fs::symlink_metadata(path)
+                        .map(|meta| meta.file_type().is_symlink())
+                        .unwrap_or(true);
+                    if is_symlink {
+                        return Err(denied("symbolic links are not allowed"));
+                    }
+                    path = path
+                        .parent()
+                        .expect("path must be under the base directory");
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn file_path(&self, path: &ModulePath) -> Result<PathBuf, ResolveError> {
         if path.origin.is_package() {
             return Err(E::ModuleNotFound(
@@ -133,18 +213,46 @@ impl FileResolver {
             ));
         }
         let mut fs_path = self.base.to_path_buf();
+        if self.sandbox {
+            for comp in &path.components {
+                let mut parts = Path::new(comp).components();
+                if !matches!(
+                    (parts.next(), parts.next()),
+                    (Some(Component::Normal(_)), None)
+                ) {
+                    fs_path.push(comp);
+                    return Err(E::AccessDenied(
+                        fs_path,
+                        format!("`{comp}` is not a valid module name"),
+                    ));
+                }
+            }
+        }
         fs_path.extend(&path.components);
-        fs_path.set_extension(self.extension);
-        if fs_path.exists() {
-            Ok(fs_path)
-        } else {
-            fs_path.set_extension("wgsl");
-            if fs_path.exists() {
-                Ok(fs_path)
-            } else {
-                Err(E::FileNotFound(fs_path, "physical file".to_string()))
+        // module names can contain dots, e.g. `"my-shader.v2"`: append the extension
+        // instead of replacing it.
+        let with_extension = |ext: &str| {
+            let mut fs_path = fs_path.clone().into_os_string();
+            fs_path.push(".");
+            fs_path.push(ext);
+            PathBuf::from(fs_path)
+        };
+        let mut fs_path = with_extension(self.extension);
+        if !fs_path.exists() {
+            fs_path = with_extension("wgsl");
+            if !fs_path.exists() {
+                // interface modules are used only when there is no source module.
+                let interface = with_extension("wesli");
+                if !interface.exists() {
+                    return Err(E::FileNotFound(fs_path, "physical file".to_string()));
+                }
+                fs_path = interface;
             }
         }
+        if self.sandbox {
+            self.check_sandbox(&fs_path)?;
+        }
+        Ok(fs_path)
     }
 }
 
@@ -166,13 +274,15 @@ impl Resolver for FileResolver {
     }
 }
 
-/// A resolver that resolves in-memory modules added with [`Self::add_module`].
+/// A resolver that resolves in-memory modules added with [`Self::add_module`] or
+/// [`Self::add_parsed_module`].
 ///
 /// Use-cases are platforms that lack a filesystem (e.g. WASM), tests or
 /// runtime-generated files.
 #[derive(Default)]
 pub struct VirtualResolver<'a> {
     files: HashMap<ModulePath, Cow<'a, str>>,
+    parsed: HashMap<ModulePath, TranslationUnit>,
 }
 
 impl<'a> VirtualResolver<'a> {
@@ -180,6 +290,7 @@ impl<'a> VirtualResolver<'a> {
     pub fn new() -> Self {
         Self {
             files: HashMap::new(),
+            parsed: HashMap::new(),
         }
     }
 
@@ -187,9 +298,21 @@ impl<'a> VirtualResolver<'a> {
     ///
     /// The path must not be relative.
     pub fn add_module(&mut self, path: ModulePath, file: Cow<'a, str>) {
+        self.parsed.remove(&path);
         self.files.insert(path, file);
     }
 
+    /// Resolve imports of `path` with the given syntax tree, e.g. generated code.
+    ///
+    /// The module is not printed and re-parsed. Its source, used in diagnostics, is the
+    /// printed syntax tree.
+    ///
+    /// The path must not be relative.
+    pub fn add_parsed_module(&mut self, path: ModulePath, wesl: TranslationUnit) {
+        self.files.remove(&path);
+        self.parsed.insert(path, wesl);
+    }
+
     /// Get a module registered with [`Self::add_module`].
     pub fn get_module(&self, path: &ModulePath) -> Result<&str, ResolveError> {
         let source = self
@@ -207,9 +330,75 @@ impl<'a> VirtualResolver<'a> {
 
 impl Resolver for VirtualResolver<'_> {
     fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        if let Some(wesl) = self.parsed.get(path) {
+            return Ok(wesl.to_string().into());
+        }
         let source = self.get_module(path)?;
         Ok(source.into())
     }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        if let Some(wesl) = self.parsed.get(path) {
+            // the copy must not share idents with the registered module, they get renamed.
+            let mut wesl = wesl.clone();
+            fresh_idents(&mut wesl);
+            return Ok(wesl);
+        }
+        let source = self.get_module(path)?;
+        let wesl: TranslationUnit = source.parse().map_err(|e| {
+            Diagnostic::from(e)
+                .with_module_path(path.clone(), self.display_name(path))
+                .with_source(source.to_string())
+        })?;
+        Ok(wesl)
+    }
+}
+
+/// A resolver that resolves one module to an in-memory syntax tree, and the other
+/// modules with an inner resolver. See [`crate::compile_tree`].
+pub(crate) struct TreeResolver<'a, R> {
+    path: &'a ModulePath,
+    tree: &'a TranslationUnit,
+    resolver: &'a R,
+}
+
+impl<'a, R: Resolver> TreeResolver<'a, R> {
+    pub(crate) fn new(path: &'a ModulePath, tree: &'a TranslationUnit, resolver: &'a R) -> Self {
+        Self {
+            path,
+            tree,
+            resolver,
+        }
+    }
+}
+
+impl<R: Resolver> Resolver for TreeResolver<'_, R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        if path == self.path {
+            Ok(self.tree.to_string().into())
+        } else {
+            self.resolver.resolve_source(path)
+        }
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        if path == self.path {
+            // the copy must not share idents with the caller's tree, they get renamed.
+            let mut wesl = self.tree.clone();
+            fresh_idents(&mut wesl);
+            Ok(wesl)
+        } else {
+            self.resolver.resolve_module(path)
+        }
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        (path != self.path)
+            .then(|| self.resolver.display_name(path))
+            .flatten()
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        (path != self.path)
+            .then(|| self.resolver.fs_path(path))
+            .flatten()
+    }
 }
 
 // trait alias
@@ -347,8 +536,38 @@ impl Resolver for Router {
 #[derive(Debug, PartialEq, Eq)]
 pub struct CodegenPkg {
     pub crate_name: &'static str,
+    /// The semantic version of the package, see [`Version`].
+    pub version: &'static str,
     pub root: &'static CodegenModule,
     pub dependencies: &'static [&'static CodegenPkg],
+    /// Version requirements on the dependencies: `(package name, requirement)` pairs,
+    /// see [`VersionReq`].
+    pub requirements: &'static [(&'static str, &'static str)],
+}
+
+impl CodegenPkg {
+    /// Check that the package version matches a requirement. `required_by` is the name
+    /// of the requiring package, or `None` for the root package.
+    fn check_version(&self, req: &VersionReq, required_by: Option<&str>) -> Result<(), E> {
+        let matches = self
+            .version
+            .parse::<Version>()
+            .is_ok_and(|version| req.matches(&version));
+        if matches {
+            Ok(())
+        } else {
+            let required_by = match required_by {
+                Some(name) => format!("package `{name}`"),
+                None => "the root package".to_string(),
+            };
+            Err(E::VersionMismatch(
+                self.root.name.to_string(),
+                self.version.to_string(),
+                req.to_string(),
+                required_by,
+            ))
+        }
+    }
 }
 
 /// The type holding the source code of modules in external packages.
@@ -366,6 +585,7 @@ pub struct CodegenModule {
 /// Register external packages with [`Self::add_package`].
 pub struct PkgResolver {
     packages: Vec<&'static CodegenPkg>,
+    requirements: HashMap<String, VersionReq>,
 }
 
 impl PkgResolver {
@@ -373,6 +593,7 @@ impl PkgResolver {
     pub fn new() -> Self {
         Self {
             packages: Vec::new(),
+            requirements: HashMap::new(),
         }
     }
 
@@ -380,6 +601,16 @@ impl PkgResolver {
     pub fn add_package(&mut self, pkg: &'static CodegenPkg) {
         self.packages.push(pkg);
     }
+
+    /// Require a version of a package, e.g. `pkg_lighting >= 0.3`. Importing from a
+    /// package that does not match the requirement fails with
+    /// [`ResolveError::VersionMismatch`].
+    ///
+    /// Requirements of packages on their own dependencies are declared with
+    /// [`crate::PkgBuilder::require`].
+    pub fn require(&mut self, pkg: impl ToString, req: VersionReq) {
+        self.requirements.insert(pkg.to_string(), req);
+    }
 }
 
 impl Default for PkgResolver {
@@ -414,22 +645,39 @@ impl Resolver for PkgResolver {
                     format!("dependency `{}` not found", pkg_parts.iter().format("/"),),
                 )
             })?;
+        if let Some(req) = self.requirements.get(root_pkg.root.name) {
+            root_pkg.check_version(req, None)?;
+        }
 
-        let pkg = pkg_parts.iter().skip(1).try_fold(root_pkg, |dep, name| {
-            dep.dependencies
-                .iter()
-                .find(|p| p.root.name == *name)
-                .ok_or_else(|| {
-                    E::ModuleNotFound(
-                        path.clone(),
-                        format!(
-                            "dependency `{}` not found in package path `{}`",
-                            name,
-                            pkg_parts.iter().format("/"),
-                        ),
-                    )
-                })
-        })?;
+        let pkg = pkg_parts
+            .iter()
+            .skip(1)
+            .try_fold(root_pkg, |dep, name| -> Result<_, E> {
+                let sub_dep = dep
+                    .dependencies
+                    .iter()
+                    .find(|p| p.root.name == *name)
+                    .ok_or_else(|| {
+                        E::ModuleNotFound(
+                            path.clone(),
+                            format!(
+                                "dependency `{}` not found in package path `{}`",
+                                name,
+                                pkg_parts.iter().format("/"),
+                            ),
+                        )
+                    })?;
+                if let Some((_, req)) = dep.requirements.iter().find(|(pkg, _)| pkg == name) {
+                    let req = req.parse::<VersionReq>().map_err(|e| {
+                        E::ModuleNotFound(
+                            path.clone(),
+                            format!("in package `{}`: {e}", dep.root.name),
+                        )
+                    })?;
+                    sub_dep.check_version(&req, Some(dep.root.name))?;
+                }
+                Ok(sub_dep)
+            })?;
 
         // TODO: the resolution algorithm is currently not spec-compliant.
         // https://github.com/wgsl-tooling-wg/wesl-spec/blob/imports-update/Imports.md
@@ -475,6 +723,24 @@ impl StandardResolver {
         self.pkg.add_package(pkg)
     }
 
+    /// Require a version of an external package.
+    /// See [`PkgResolver::require`].
+    pub fn require(&mut self, pkg: impl ToString, req: VersionReq) {
+        self.pkg.require(pkg, req)
+    }
+
+    /// Only resolve local files inside the base directory.
+    /// See [`FileResolver::set_sandbox`].
+    pub fn set_sandbox(&mut self, sandbox: bool) {
+        self.files.set_sandbox(sandbox)
+    }
+
+    /// Set how symbolic links are handled in sandbox mode.
+    /// See [`FileResolver::set_symlink_policy`].
+    pub fn set_symlink_policy(&mut self, policy: SymlinkPolicy) {
+        self.files.set_symlink_policy(policy)
+    }
+
     /// Add a numeric constant.
     ///
     /// Numeric constants live WESL's special package named `constants`. This package is
@@ -591,4 +857,129 @@ mod test {
             "m6"
         );
     }
+
+    #[test]
+    fn package_version_requirements() {
+        static NOISE: CodegenPkg = CodegenPkg {
+            crate_name: "pkg_noise",
+            version: "0.2.1",
+            root: &CodegenModule {
+                name: "pkg_noise",
+                source: "fn noise() {}",
+                submodules: &[],
+            },
+            dependencies: &[],
+            requirements: &[],
+        };
+        static LIGHTING: CodegenPkg = CodegenPkg {
+            crate_name: "pkg_lighting",
+            version: "0.3.4",
+            root: &CodegenModule {
+                name: "pkg_lighting",
+                source: "fn light() {}",
+                submodules: &[],
+            },
+            dependencies: &[&NOISE],
+            requirements: &[("pkg_noise", ">=0.3")],
+        };
+        let path = |pkg: &str| ModulePath::new(PathOrigin::Package(pkg.to_string()), vec![]);
+
+        let mut r = PkgResolver::new();
+        r.add_package(&LIGHTING);
+        r.require("pkg_lighting", ">= 0.3".parse().unwrap());
+        assert_eq!(
+            r.resolve_source(&path("pkg_lighting")).unwrap(),
+            "fn light() {}"
+        );
+
+        let err = r
+            .resolve_source(&path("pkg_lighting/pkg_noise"))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "package `pkg_noise` has version 0.2.1, but package `pkg_lighting` requires `>=0.3`"
+        );
+
+        r.require("pkg_lighting", "^0.4".parse().unwrap());
+        let err = r.resolve_source(&path("pkg_lighting")).unwrap_err();
+        assert!(
+            matches!(&err, ResolveError::VersionMismatch(_, found, req, _) if found == "0.3.4" && req == "^0.4"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn file_resolver_sandbox() {
+        let dir = std::env::temp_dir().join(format!("wesl_sandbox_{}", std::process::id()));
+        let base = dir.join("base");
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("main.wesl"), "m1").unwrap();
+        fs::write(dir.join("secret.wesl"), "m2").unwrap();
+
+        let escape = ModulePath::new(PathOrigin::Absolute, vec!["..".into(), "secret".into()]);
+        let main = "package::main".parse().unwrap();
+
+        let mut r = FileResolver::new(&base);
+        assert_eq!(r.resolve_source(&escape).unwrap(), "m2");
+        r.set_sandbox(true);
+        assert_eq!(r.resolve_source(&main).unwrap(), "m1");
+        assert!(matches!(
+            r.resolve_source(&escape),
+            Err(ResolveError::AccessDenied(..))
+        ));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("secret.wesl"), base.join("link.wesl")).unwrap();
+            let link = "package::link".parse().unwrap();
+            assert!(matches!(
+                r.resolve_source(&link),
+                Err(ResolveError::AccessDenied(..))
+            ));
+            r.set_symlink_policy(SymlinkPolicy::Follow);
+            assert_eq!(r.resolve_source(&link).unwrap(), "m2");
+            r.set_symlink_policy(SymlinkPolicy::Deny);
+            assert!(matches!(
+                r.resolve_source(&link),
+                Err(ResolveError::AccessDenied(..))
+            ));
+            assert_eq!(r.resolve_source(&main).unwrap(), "m1");
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parsed_virtual_modules() {
+        use crate::Wesl;
+
+        let mut r = VirtualResolver::new();
+        r.add_module(
+            "package::main".parse().unwrap(),
+            "import package::gen::{f, N};
+            @compute @workgroup_size(N) fn main() { f(); }"
+                .into(),
+        );
+        let generated = wgsl_parse::parse_str("const N = 4; fn f() {}").unwrap();
+        r.add_parsed_module("package::gen".parse().unwrap(), generated);
+
+        for sourcemap in [false, true] {
+            let mut compiler = Wesl::new_barebones().set_custom_resolver(&r);
+            compiler
+                .set_options(crate::CompileOptions::default())
+                .set_mangler(crate::ManglerKind::Escape)
+                .use_sourcemap(sourcemap);
+            // compiling twice checks that the registered module is not mangled in-place.
+            for _ in 0..2 {
+                let res = compiler
+                    .compile(&"package::main".parse().unwrap())
+                    .unwrap()
+                    .to_string();
+                assert!(res.contains("fn package_gen_f()"), "{res}");
+            }
+        }
+        let source = r.resolve_source(&"package::gen".parse().unwrap()).unwrap();
+        assert!(source.contains("const N = 4;"));
+    }
 }
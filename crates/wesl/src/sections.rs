@@ -0,0 +1,180 @@
+use std::{collections::HashMap, fmt::Write};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{GlobalDeclarationNode, Ident, ModulePath, TranslationUnit};
+
+use crate::Provenance;
+
+/// Attribute each non-void declaration to its module of origin, by position in `modules`.
+///
+/// Declarations with unknown origin (`const_assert`s and declarations generated after
+/// assembly) are attributed to the module of the preceding declaration, or to the root
+/// module if there is none. The result is sorted by module, the order of declarations
+/// within a module is preserved.
+fn decl_modules<'a>(
+    decls: impl IntoIterator<Item = &'a GlobalDeclarationNode>,
+    origins: &HashMap<Ident, Provenance>,
+    modules: &[ModulePath],
+) -> Vec<(usize, &'a GlobalDeclarationNode)> {
+    let mut current = 0;
+    let mut decls = decls
+        .into_iter()
+        .filter(|decl| !decl.is_void())
+        .map(|decl| {
+            let module = decl
+                .ident()
+                .and_then(|id| origins.get(id))
+                .and_then(|prov| modules.iter().position(|p| *p == prov.module));
+            if let Some(module) = module {
+                current = module;
+            }
+            (current, decl)
+        })
+        .collect_vec();
+    decls.sort_by_key(|(module, _)| *module); // stable sort
+    decls
+}
+
+/// Reorder the declarations by module of origin, following the order of `modules`.
+/// See [`fmt_sections`].
+pub(crate) fn group_by_module(
+    wesl: &mut TranslationUnit,
+    origins: &HashMap<Ident, Provenance>,
+    modules: &[ModulePath],
+) {
+    let decls = decl_modules(&wesl.global_declarations, origins, modules)
+        .into_iter()
+        .map(|(_, decl)| decl.clone())
+        .collect_vec();
+    wesl.global_declarations = decls;
+}
+
+/// Format the declarations by module of origin, each module with a banner comment,
+/// skipping empty modules. The grouping is computed from `origins`, so it follows
+/// changes made to the syntax tree after compilation.
+///
+/// The global directives are in the first section, so that the concatenation of all
+/// sections is a valid WGSL module. If there are no declarations, the directives are
+/// attributed to the root module.
+pub(crate) fn fmt_sections(
+    wesl: &TranslationUnit,
+    origins: &HashMap<Ident, Provenance>,
+    modules: &[ModulePath],
+) -> Vec<(ModulePath, String)> {
+    let root = modules
+        .first()
+        .cloned()
+        .unwrap_or_else(ModulePath::new_root);
+    let decls = decl_modules(&wesl.global_declarations, origins, modules);
+    let mut sections = decls
+        .iter()
+        .chunk_by(|(module, _)| *module)
+        .into_iter()
+        .map(|(module, chunk)| {
+            let path = modules.get(module).cloned().unwrap_or_else(|| root.clone());
+            let decls = chunk.map(|(_, decl)| decl).format("\n\n");
+            (path, format!("{decls}\n"))
+        })
+        .collect_vec();
+    if sections.is_empty() {
+        sections.push((root, String::new()));
+    }
+    sections
+        .into_iter()
+        .enumerate()
+        .map(|(n, (path, decls))| {
+            let mut res = format!("// ---- module {path} ----\n\n");
+            if n == 0 && !wesl.global_directives.is_empty() {
+                let directives = wesl.global_directives.iter().format("\n");
+                write!(res, "{directives}\n\n").unwrap();
+            }
+            res.push_str(&decls);
+            (path, res)
+        })
+        .collect()
+}
+
+#[test]
+fn test_group_by_module() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::{f, g};
+        enable f16;
+        @fragment fn main() -> @location(0) vec4f { return vec4f(f() + g()); }
+        const_assert 1 < 2;"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn f() -> f32 { return 1.0; } fn g() -> f32 { return 2.0; }".into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        group_by_module: true,
+        ..Default::default()
+    });
+    let res = compiler
+        .compile_result(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let files = res.module_files();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].0, "package::main".parse().unwrap());
+    assert_eq!(files[1].0, "package::util".parse().unwrap());
+    assert!(
+        files[0]
+            .1
+            .starts_with("// ---- module package::main ----\n\nenable f16;")
+    );
+    assert!(files[0].1.contains("const_assert"));
+    assert!(files[1].1.contains("return 1.0") && files[1].1.contains("return 2.0"));
+    assert_eq!(
+        res.to_string(),
+        files.iter().map(|(_, source)| source.as_str()).join("\n")
+    );
+}
+
+#[test]
+fn test_group_by_module_after_change() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f;
+        enable f16;
+        const c = 1.0;
+        @fragment fn main() -> @location(0) vec4f { return vec4f(f() + c); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn f() -> f32 { return 2.0; }".into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        group_by_module: true,
+        ..Default::default()
+    });
+    let mut res = compiler
+        .compile_result(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    // remove the first root declaration and move the util declaration first.
+    res.syntax.global_declarations.remove(0);
+    res.syntax.global_declarations.rotate_right(1);
+    let files = res.module_files();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].0, "package::main".parse().unwrap());
+    assert!(files[0].1.contains("enable f16;") && files[0].1.contains("fn main"));
+    assert_eq!(files[1].0, "package::util".parse().unwrap());
+    assert!(files[1].1.contains("return 2.0"));
+
+    // the directives are kept without declarations.
+    res.syntax.global_declarations.clear();
+    let files = res.module_files();
+    assert_eq!(files.len(), 1);
+    assert!(res.to_string().contains("enable f16;"));
+}
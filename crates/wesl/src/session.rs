@@ -0,0 +1,232 @@
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, path::PathBuf};
+
+use wgsl_parse::syntax::{ModulePath, TranslationUnit};
+
+use crate::{
+    CompileResult, Diagnostic, Error, ResolveError, Resolver, Wesl, workspace::ModuleCache,
+};
+
+/// A resolver that resolves the modules updated in a [`Session`] from their new source,
+/// and the other modules with an inner resolver.
+struct Overlay<R: Resolver> {
+    resolver: R,
+    sources: RefCell<HashMap<ModulePath, String>>,
+}
+
+impl<R: Resolver> Resolver for Overlay<R> {
+    fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+        match self.sources.borrow().get(path) {
+            Some(source) => Ok(source.clone().into()),
+            None => self.resolver.resolve_source(path),
+        }
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let Some(source) = self.sources.borrow().get(path).cloned() else {
+            return self.resolver.resolve_module(path);
+        };
+        let wesl: TranslationUnit = source.parse().map_err(|e| {
+            Diagnostic::from(e)
+                .with_module_path(path.clone(), self.display_name(path))
+                .with_source(source)
+        })?;
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+/// The compilation result of a root module of a [`Session`].
+#[derive(Clone)]
+struct Output {
+    root: ModulePath,
+    result: Result<CompileResult, Error>,
+}
+
+impl Output {
+    /// Whether the result depends on the module. Failed compilations depend on all
+    /// modules, since their dependencies are unknown.
+    fn depends_on(&self, path: &ModulePath) -> bool {
+        match &self.result {
+            Ok(res) => res.modules.contains(path),
+            Err(_) => true,
+        }
+    }
+
+    /// The compiled WGSL, or the error message.
+    fn output(&self) -> Result<String, String> {
+        match &self.result {
+            Ok(res) => Ok(res.to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// An editing session: compile root modules, then recompile them incrementally when
+/// modules change, e.g. in a watch mode or a language server.
+///
+/// The parsed modules are kept in memory across compilations. When a module changes
+/// with [`Self::update_module`] or [`Self::reload_module`], only the roots that depend
+/// on it are recompiled, and only the changed module is parsed again.
+///
+/// This type is not thread-safe (not [`Send`] or [`Sync`]).
+pub struct Session<R: Resolver> {
+    compiler: Wesl<ModuleCache<Overlay<R>>>,
+    outputs: Vec<Output>,
+}
+
+impl<R: Resolver> Session<R> {
+    /// Create a session with the options, resolver and mangler of a compiler.
+    pub fn new(compiler: Wesl<R>) -> Self {
+        let resolver = ModuleCache::new(Overlay {
+            resolver: compiler.resolver,
+            sources: Default::default(),
+        });
+        Self {
+            compiler: Wesl {
+                options: compiler.options,
+                use_sourcemap: compiler.use_sourcemap,
+                resolver,
+                mangler: compiler.mangler,
+                hooks: compiler.hooks,
+            },
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Compile a root module and keep it up to date in the session.
+    ///
+    /// Adding a root twice compiles it again.
+    pub fn add_root(&mut self, root: ModulePath) -> &Result<CompileResult, Error> {
+        let result = self.compiler.compile_result(&root);
+        let pos = match self.outputs.iter().position(|out| out.root == root) {
+            Some(pos) => {
+                self.outputs[pos].result = result;
+                pos
+            }
+            None => {
+                self.outputs.push(Output { root, result });
+                self.outputs.len() - 1
+            }
+        };
+        &self.outputs[pos].result
+    }
+
+    /// Stop compiling a root module.
+    pub fn remove_root(&mut self, root: &ModulePath) {
+        self.outputs.retain(|out| out.root != *root);
+    }
+
+    /// The root modules, in order of addition.
+    pub fn roots(&self) -> impl Iterator<Item = &ModulePath> {
+        self.outputs.iter().map(|out| &out.root)
+    }
+
+    /// Get the latest compilation result of a root module.
+    pub fn result(&self, root: &ModulePath) -> Option<&Result<CompileResult, Error>> {
+        self.outputs
+            .iter()
+            .find(|out| out.root == *root)
+            .map(|out| &out.result)
+    }
+
+    /// Replace the source of a module, e.g. with the unsaved contents of an editor.
+    /// The module does not need to exist in the resolver.
+    ///
+    /// Recompiles the roots that depend on the module, and the roots that failed to
+    /// compile. Returns the roots whose output (WGSL or error) changed.
+    pub fn update_module(
+        &mut self,
+        path: ModulePath,
+        source: impl Into<String>,
+    ) -> Vec<ModulePath> {
+        let resolver = &self.compiler.resolver;
+        resolver
+            .resolver()
+            .sources
+            .borrow_mut()
+            .insert(path.clone(), source.into());
+        self.recompile(&path)
+    }
+
+    /// Discard the source set by [`Self::update_module`] and the parsed module, e.g.
+    /// when the file of the module changed on disk.
+    ///
+    /// Recompiles the roots like [`Self::update_module`].
+    pub fn reload_module(&mut self, path: &ModulePath) -> Vec<ModulePath> {
+        let resolver = &self.compiler.resolver;
+        resolver.resolver().sources.borrow_mut().remove(path);
+        self.recompile(path)
+    }
+
+    fn recompile(&mut self, path: &ModulePath) -> Vec<ModulePath> {
+        self.compiler.resolver.invalidate(path);
+        let mut changed = Vec::new();
+        for out in &mut self.outputs {
+            if !out.depends_on(path) {
+                continue;
+            }
+            let before = out.output();
+            out.result = self.compiler.compile_result(&out.root);
+            if out.output() != before {
+                changed.push(out.root.clone());
+            }
+        }
+        changed
+    }
+}
+
+#[test]
+fn test_session() {
+    use crate::{CompileOptions, VirtualResolver};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::a".parse().unwrap(),
+        "import package::util::f;
+        @compute @workgroup_size(1) fn main() { let x = f(); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::b".parse().unwrap(),
+        "@compute @workgroup_size(1) fn main() { let x = 1; }".into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn f() -> f32 { return 1.0; }".into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let mut session = Session::new(compiler);
+    let (a, b): (ModulePath, ModulePath) =
+        ("package::a".parse().unwrap(), "package::b".parse().unwrap());
+    assert!(session.add_root(a.clone()).is_ok());
+    assert!(session.add_root(b.clone()).is_ok());
+    let util: ModulePath = "package::util".parse().unwrap();
+
+    // only the dependents are recompiled.
+    let changed = session.update_module(util.clone(), "fn f() -> f32 { return 2.0; }");
+    assert_eq!(changed, vec![a.clone()]);
+    let output = session.result(&a).unwrap().as_ref().unwrap().to_string();
+    assert!(output.contains("2.0"));
+    assert!(
+        session
+            .update_module(util.clone(), "fn f() -> f32 { return 2.0; }")
+            .is_empty()
+    );
+
+    // failed roots are recompiled on each change.
+    let changed = session.update_module(util.clone(), "fn g() {}");
+    assert_eq!(changed, vec![a.clone()]);
+    assert!(session.result(&a).unwrap().is_err());
+    let changed = session.update_module(util.clone(), "fn f() -> f32 { return 3.0; }");
+    assert_eq!(changed, vec![a.clone()]);
+
+    let changed = session.reload_module(&util);
+    assert_eq!(changed, vec![a.clone()]);
+    let output = session.result(&a).unwrap().as_ref().unwrap().to_string();
+    assert!(output.contains("1.0"));
+}
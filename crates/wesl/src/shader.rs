@@ -0,0 +1,110 @@
+use std::fmt::Display;
+
+use wgsl_parse::syntax::TranslationUnit;
+
+use crate::{
+    BasicSourceMap, CompileResult, Diagnostic, EntryPoints, Error, Reflection, SizeReport,
+};
+
+/// A compiled shader: the WGSL output and its metadata. See [`crate::Wesl::compile`].
+///
+/// Unlike [`CompileResult`], the metadata is computed once, when the shader is created.
+/// New metadata may be added in future versions without a breaking change.
+///
+/// Use [`crate::Wesl::compile_result`] to get a [`CompileResult`] instead, e.g. to call
+/// `exec` or `specialize` on the output syntax tree, or to read the results of optional
+/// passes such as [`CompileResult::debug_buffer`].
+///
+/// This type implements `Display`, call `to_string()` to get the compiled WGSL.
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct CompiledShader {
+    /// The compiled WGSL, as printed by [`CompileResult`].
+    pub code: String,
+    /// The syntax tree of the compiled WGSL.
+    pub syntax: TranslationUnit,
+    /// The source map of the compiled WGSL, if enabled with
+    /// [`crate::Wesl::use_sourcemap`].
+    pub sourcemap: Option<BasicSourceMap>,
+    /// The host-visible interface, see [`CompileResult::reflection`]. The struct
+    /// layouts are empty if they cannot be evaluated.
+    pub reflection: Reflection,
+    /// The warnings emitted during compilation, e.g. by `@compile_warn` attributes.
+    pub warnings: Vec<Diagnostic<Error>>,
+    /// The size of the output by module and declaration, see
+    /// [`CompileResult::size_report`].
+    pub report: SizeReport,
+}
+
+impl CompiledShader {
+    /// The entry points of the shader, with their stage and IO signature.
+    pub fn entry_points(&self) -> &EntryPoints {
+        &self.reflection.entry_points
+    }
+
+    /// Bundle a compile result with its metadata. Unlike the `TryFrom` implementation,
+    /// it does not fail when the struct layouts cannot be evaluated.
+    pub(crate) fn from_result(res: CompileResult) -> Self {
+        let reflection = res.reflection().unwrap_or_else(|_| Reflection {
+            entry_points: EntryPoints::from_syntax(&res.syntax),
+            bindings: res.bindings(),
+            overrides: res.overrides(),
+            struct_layouts: Vec::new(),
+        });
+        Self {
+            code: res.to_string(),
+            reflection,
+            report: res.size_report(),
+            syntax: res.syntax,
+            sourcemap: res.sourcemap,
+            warnings: res.warnings,
+        }
+    }
+}
+
+impl TryFrom<CompileResult> for CompiledShader {
+    type Error = Error;
+
+    /// Fails if the reflection of the shader fails, see [`CompileResult::reflection`].
+    fn try_from(res: CompileResult) -> Result<Self, Self::Error> {
+        Ok(Self {
+            code: res.to_string(),
+            reflection: res.reflection()?,
+            report: res.size_report(),
+            syntax: res.syntax,
+            sourcemap: res.sourcemap,
+            warnings: res.warnings,
+        })
+    }
+}
+
+impl Display for CompiledShader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
+#[test]
+fn test_compiled_shader() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@group(0) @binding(0) var<uniform> color: vec4f;
+        @fragment fn main() -> @location(0) vec4f { return color; }"
+            .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let root = "package::main".parse().unwrap();
+    let shader = compiler.compile(&root).unwrap_or_else(|e| panic!("{e}"));
+
+    assert_eq!(
+        shader.to_string(),
+        compiler.compile_result(&root).unwrap().to_string()
+    );
+    assert_eq!(shader.entry_points().len(), 1);
+    assert_eq!(shader.reflection.bindings.len(), 1);
+    assert_eq!(shader.report.total, shader.code.len());
+}
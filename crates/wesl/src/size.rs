@@ -0,0 +1,210 @@
+use std::fmt::Display;
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{ModulePath, PathOrigin};
+
+use crate::CompileResult;
+
+/// The size of a declaration in the output. See [`SizeReport`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeclarationSize {
+    /// The name of the declaration in the output, after mangling. `None` for
+    /// declarations without a name, e.g. `const_assert`.
+    pub name: Option<String>,
+    /// The module declaring the declaration. `None` for generated declarations.
+    pub module: Option<ModulePath>,
+    /// Size of the declaration in the output, in bytes.
+    pub bytes: usize,
+}
+
+/// The size of the declarations of a module in the output. See [`SizeReport`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleSize {
+    pub path: ModulePath,
+    /// Number of declarations of the module in the output.
+    pub declarations: usize,
+    /// Size of the declarations of the module in the output, in bytes.
+    pub bytes: usize,
+    /// Whether the module is loaded from a plain WGSL file, see
+    /// [`crate::CompileResult::wgsl_modules`].
+    pub plain_wgsl: bool,
+}
+
+/// The size of a compiled shader by originating module and declaration, after mangling
+/// and stripping. See [`crate::CompileResult::size_report`].
+///
+/// The sizes are the ones of the declarations printed individually. The rest of the
+/// output (directives, separators, header) is only counted in [`Self::total`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizeReport {
+    /// Size of the output, in bytes.
+    pub total: usize,
+    /// The modules with declarations in the output, by decreasing size.
+    pub modules: Vec<ModuleSize>,
+    /// The declarations of the output, by decreasing size.
+    pub declarations: Vec<DeclarationSize>,
+}
+
+impl SizeReport {
+    pub(crate) fn new(res: &CompileResult) -> Self {
+        let declarations = res
+            .syntax
+            .global_declarations
+            .iter()
+            .map(|decl| {
+                let module = decl
+                    .ident()
+                    .and_then(|id| res.provenance(id))
+                    .map(|prov| &prov.module)
+                    .or_else(|| res.span_module(decl.span()));
+                DeclarationSize {
+                    name: decl.ident().map(|id| id.to_string()),
+                    module: module.cloned(),
+                    bytes: decl.to_string().len(),
+                }
+            })
+            // stable sort: declarations of equal size stay in output order.
+            .sorted_by(|a, b| b.bytes.cmp(&a.bytes))
+            .collect_vec();
+        let modules = res
+            .modules
+            .iter()
+            .map(|path| {
+                let decls = declarations
+                    .iter()
+                    .filter(|decl| decl.module.as_ref() == Some(path));
+                let (count, bytes) = decls.fold((0, 0), |(n, b), decl| (n + 1, b + decl.bytes));
+                ModuleSize {
+                    path: path.clone(),
+                    declarations: count,
+                    bytes,
+                    plain_wgsl: res.wgsl_modules.contains(path),
+                }
+            })
+            .filter(|module| module.declarations > 0)
+            .sorted_by(|a, b| b.bytes.cmp(&a.bytes))
+            .collect_vec();
+        Self {
+            total: res.to_string().len(),
+            modules,
+            declarations,
+        }
+    }
+
+    /// The size of the declarations of each package in the output, by decreasing size.
+    /// The local package is named `package`.
+    pub fn packages(&self) -> Vec<(String, usize)> {
+        self.modules
+            .iter()
+            .into_grouping_map_by(|module| match &module.path.origin {
+                PathOrigin::Package(name) => name.clone(),
+                _ => "package".to_string(),
+            })
+            .fold(0, |bytes, _, module| bytes + module.bytes)
+            .into_iter()
+            .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)))
+            .collect()
+    }
+
+    fn percent(&self, bytes: usize) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            bytes as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+impl Display for SizeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "total: {} bytes", self.total)?;
+        let packages = self.packages();
+        if packages.len() > 1 {
+            writeln!(f, "\n  bytes      %  package")?;
+            for (name, bytes) in packages {
+                writeln!(f, "{bytes:>7} {:>5.1}%  {name}", self.percent(bytes))?;
+            }
+        }
+        writeln!(f, "\n  bytes      %  module")?;
+        for module in &self.modules {
+            let (bytes, path) = (module.bytes, &module.path);
+            write!(f, "{bytes:>7} {:>5.1}%  {path}", self.percent(bytes))?;
+            if module.plain_wgsl {
+                write!(f, " (plain WGSL)")?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "\n  bytes      %  declaration")?;
+        for decl in &self.declarations {
+            let bytes = decl.bytes;
+            let name = decl.name.as_deref().unwrap_or("<unnamed>");
+            write!(f, "{bytes:>7} {:>5.1}%  {name}", self.percent(bytes))?;
+            match &decl.module {
+                Some(module) => writeln!(f, " ({module})")?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_size_report() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::{shade, Light};
+        @group(0) @binding(0) var<uniform> light: Light;
+        const_assert 1 < 2;
+        @fragment fn main() -> @location(0) vec4f { return shade(light); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "struct Light { color: vec4f, intensity: f32 }
+        fn shade(l: Light) -> vec4f {
+            let scaled = l.color * l.intensity * l.intensity;
+            return clamp(scaled, vec4f(0.0), vec4f(1.0));
+        }
+        fn unused() {}"
+            .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let res = compiler
+        .compile_result(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let report = res.size_report();
+
+    assert_eq!(report.total, res.to_string().len());
+    assert_eq!(report.declarations.len(), 5);
+    assert!(report.declarations.is_sorted_by(|a, b| a.bytes >= b.bytes));
+    let util = "package::util".parse::<ModulePath>().unwrap();
+    let shade = report
+        .declarations
+        .iter()
+        .find(|decl| {
+            decl.name
+                .as_ref()
+                .is_some_and(|name| name.ends_with("shade"))
+        })
+        .unwrap();
+    assert_eq!(shade.module, Some(util.clone()));
+    let assert = report.declarations.iter().find(|decl| decl.name.is_none());
+    assert_eq!(
+        assert.and_then(|decl| decl.module.as_ref()),
+        Some(&"package::main".parse().unwrap())
+    );
+
+    assert_eq!(report.modules.len(), 2);
+    assert_eq!(report.modules[0].path, util);
+    assert_eq!(report.modules[0].declarations, 2);
+    let bytes = report.modules.iter().map(|m| m.bytes).sum::<usize>();
+    assert!(bytes <= report.total);
+    assert_eq!(report.packages(), vec![("package".to_string(), bytes)]);
+}
@@ -0,0 +1,298 @@
+//! Injection of application snippets into `@slot("name")` blocks. See
+//! [`crate::CompileOptions::slots`].
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+};
+
+use wgsl_parse::syntax::{
+    Attribute, Attributes, CompoundStatement, GlobalDeclaration, ModulePath, Statement,
+    StatementNode, TranslationUnit, TypeExpression,
+};
+
+use crate::{
+    Diagnostic, Error, FreshNames, ResolveError, Resolver, SyntaxUtil,
+    hygiene::{alpha_rename, avoid_capture, for_each_local},
+    idents::builtin_ident,
+    prelude::imported_names,
+    visit::Visit,
+};
+
+/// Error produced when filling the `@slot` blocks.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SlotError {
+    #[error("invalid snippet of slot `{0}`: {1}")]
+    Snippet(String, String),
+    #[error(
+        "the snippet of slot `{0}` references `{1}`, which is not a parameter of `{2}` or a module-scope declaration"
+    )]
+    UnknownName(String, String, String),
+    #[error(
+        "`@slot(\"{0}\")` must be attached to a compound statement of a function, outside of other slots"
+    )]
+    Placement(String),
+}
+
+fn slot_name(block: &CompoundStatement) -> Option<&str> {
+    block.attributes.iter().find_map(|attr| match attr.node() {
+        Attribute::Slot(name) => Some(name.as_str()),
+        _ => None,
+    })
+}
+
+/// The `@slot` blocks of a sequence of statements, in order. Slots in slots are not
+/// collected.
+fn slot_blocks<'a>(
+    stats: impl Iterator<Item = &'a mut StatementNode>,
+    blocks: &mut Vec<&'a mut CompoundStatement>,
+) {
+    for stat in stats {
+        let is_slot =
+            matches!(stat.node(), Statement::Compound(block) if slot_name(block).is_some());
+        if !is_slot {
+            slot_blocks(Visit::<StatementNode>::visit_mut(stat.node_mut()), blocks);
+        } else if let Statement::Compound(block) = stat.node_mut() {
+            blocks.push(block);
+        }
+    }
+}
+
+/// Parse the statements of a snippet. Idents point at their declaration.
+fn parse_snippet(slot: &str, snippet: &str) -> Result<Vec<StatementNode>, SlotError> {
+    let err = |msg: String| SlotError::Snippet(slot.to_string(), msg);
+    // the snippet is parsed as a function body, so that it cannot close it.
+    let mut wesl = wgsl_parse::parse_str(&format!("fn slot() {{\n{snippet}\n}}"))
+        .map_err(|e| err(e.to_string()))?;
+    if wesl.global_declarations.len() != 1 || !wesl.imports.is_empty() {
+        return Err(err("expected a sequence of statements".to_string()));
+    }
+    for attr in Visit::<Attributes>::visit(&wesl).flatten() {
+        match attr.node() {
+            Attribute::Slot(_) => return Err(err("snippets cannot declare slots".to_string())),
+            Attribute::If(_) | Attribute::Elif(_) | Attribute::Else => {
+                return Err(err(
+                    "snippets are inserted after conditional translation, they cannot use `@if`"
+                        .to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+    wesl.retarget_idents();
+    match wesl.global_declarations.pop().map(|decl| decl.into_inner()) {
+        Some(GlobalDeclaration::Function(f)) => Ok(f.body.statements),
+        _ => Err(err("expected a sequence of statements".to_string())),
+    }
+}
+
+/// Replace the statements of the `@slot` blocks with the snippets provided for them, and
+/// remove the `@slot` attributes. Slots without a snippet keep their statements.
+///
+/// Snippets see the parameters of the function and the module-scope declarations and
+/// imports of the module, like a function body. The local declarations of the function
+/// cannot capture the references of a snippet: they are renamed if needed. See
+/// [`crate::splice_statements`].
+pub(crate) fn fill_slots(
+    wesl: &mut TranslationUnit,
+    slots: &BTreeMap<String, String>,
+) -> Result<(), SlotError> {
+    let has_slots = Visit::<Attributes>::visit(wesl)
+        .flatten()
+        .any(|attr| matches!(attr.node(), Attribute::Slot(_)));
+    if !has_slots {
+        return Ok(());
+    }
+    wesl.retarget_idents();
+    let mut names = FreshNames::new(wesl);
+    let module_names = wesl
+        .global_declarations
+        .iter()
+        .filter_map(|decl| decl.ident().map(|id| id.to_string()))
+        .chain(imported_names(&wesl.imports, false))
+        .collect::<HashSet<_>>();
+
+    for decl in &mut wesl.global_declarations {
+        let GlobalDeclaration::Function(f) = decl.node_mut() else {
+            continue;
+        };
+        let mut blocks = Vec::new();
+        slot_blocks(f.body.statements.iter_mut(), &mut blocks);
+        let slot_names = blocks
+            .iter()
+            .map(|block| slot_name(block).unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        if slot_names.is_empty() {
+            continue;
+        }
+
+        let params = f
+            .parameters
+            .iter()
+            .map(|p| p.ident.to_string())
+            .collect::<HashSet<_>>();
+        let mut snippets = Vec::new();
+        for slot in &slot_names {
+            let Some(snippet) = slots.get(slot) else {
+                snippets.push(None);
+                continue;
+            };
+            let mut stats = parse_snippet(slot, snippet)?;
+            let mut locals = HashSet::new();
+            for_each_local(stats.iter(), &mut |id| {
+                locals.insert(id.clone());
+            });
+            let mut unknown = None;
+            for stat in &stats {
+                Visit::<TypeExpression>::visit_rec(stat.node(), &mut |ty| {
+                    let name = ty.ident.name();
+                    let known = ty.path.is_some()
+                        || locals.contains(&ty.ident)
+                        || params.contains(name.as_str())
+                        || module_names.contains(name.as_str())
+                        || builtin_ident(&name).is_some();
+                    if !known && unknown.is_none() {
+                        unknown = Some(name.to_string());
+                    }
+                });
+            }
+            if let Some(name) = unknown {
+                return Err(SlotError::UnknownName(
+                    slot.clone(),
+                    name,
+                    f.ident.to_string(),
+                ));
+            }
+            alpha_rename(&mut stats, &mut names);
+            snippets.push(Some(stats));
+        }
+
+        let inserted = snippets
+            .iter()
+            .flatten()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        avoid_capture(&mut f.body, &inserted, &mut names);
+
+        let mut blocks = Vec::new();
+        slot_blocks(f.body.statements.iter_mut(), &mut blocks);
+        for (block, snippet) in blocks.into_iter().zip(snippets) {
+            block
+                .attributes
+                .retain(|attr| !matches!(attr.node(), Attribute::Slot(_)));
+            if let Some(stats) = snippet {
+                block.statements = stats;
+            }
+        }
+    }
+
+    let misplaced = Visit::<Attributes>::visit(wesl)
+        .flatten()
+        .find_map(|attr| match attr.node() {
+            Attribute::Slot(name) => Some(name.clone()),
+            _ => None,
+        });
+    match misplaced {
+        Some(name) => Err(SlotError::Placement(name)),
+        None => Ok(()),
+    }
+}
+
+/// A resolver that fills the `@slot` blocks of the modules it resolves, see
+/// [`crate::CompileOptions::slots`].
+pub(crate) struct SlotResolver<'a, R: Resolver> {
+    resolver: R,
+    slots: &'a BTreeMap<String, String>,
+}
+
+impl<'a, R: Resolver> SlotResolver<'a, R> {
+    pub(crate) fn new(resolver: R, slots: &'a BTreeMap<String, String>) -> Self {
+        Self { resolver, slots }
+    }
+}
+
+impl<R: Resolver> Resolver for SlotResolver<'_, R> {
+    fn resolve_source<'b>(&'b self, path: &ModulePath) -> Result<Cow<'b, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        let mut wesl = self.resolver.resolve_module(path)?;
+        fill_slots(&mut wesl, self.slots).map_err(|e| {
+            let d = Diagnostic::from(Error::from(e))
+                .with_module_path(path.clone(), self.display_name(path));
+            match self.resolver.resolve_source(path) {
+                Ok(source) => d.with_source(source.into_owned()),
+                Err(_) => d,
+            }
+        })?;
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+#[test]
+fn test_slots() {
+    use crate::{CompileOptions, ManglerKind, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::material::shade;
+        @fragment fn main(@location(0) uv: vec2f) -> @location(0) vec4f { return shade(uv); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::material".parse().unwrap(),
+        "const TINT = vec4f(1.0, 0.5, 0.5, 1.0);
+        fn shade(uv: vec2f) -> vec4f {
+            let TINT = 2.0;
+            @slot(\"surface_eval\") { return vec4f(uv, 0.0, 1.0); }
+            return vec4f(TINT);
+        }"
+        .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .set_mangler(ManglerKind::None);
+    let root = "package::main".parse().unwrap();
+    let res = compiler.compile(&root).unwrap_or_else(|e| panic!("{e}"));
+    let output = res.to_string();
+    assert!(output.contains("return vec4f(uv, 0.0, 1.0);"), "{output}");
+    assert!(!output.contains("@slot"), "{output}");
+
+    compiler.set_slot(
+        "surface_eval",
+        "let c = vec4f(uv, 0.0, 1.0); return c * TINT;",
+    );
+    let res = compiler.compile(&root).unwrap_or_else(|e| panic!("{e}"));
+    let output = res.to_string();
+    assert!(output.contains("return c * TINT;"), "{output}");
+    // the local `TINT` would capture the reference of the snippet.
+    assert!(output.contains("let TINT_1 = 2.0;"), "{output}");
+    assert!(output.contains("return vec4f(TINT_1);"), "{output}");
+
+    compiler.set_slot("surface_eval", "return vec4f(TINT.x * scale);");
+    let Err(err) = compiler.compile(&root) else {
+        panic!("unknown names in snippets must fail");
+    };
+    let err = err.to_string();
+    assert!(err.contains("references `scale`"), "{err}");
+
+    compiler.set_slot("surface_eval", "} fn f() {");
+    let Err(err) = compiler.compile(&root) else {
+        panic!("snippets must be statements");
+    };
+    let err = err.to_string();
+    assert!(
+        err.contains("invalid snippet of slot `surface_eval`"),
+        "{err}"
+    );
+}
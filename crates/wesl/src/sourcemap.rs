@@ -1,6 +1,6 @@
 use std::{cell::RefCell, collections::HashMap, path::PathBuf};
 
-use wgsl_parse::syntax::TypeExpression;
+use wgsl_parse::syntax::{TranslationUnit, TypeExpression};
 
 use crate::{Mangler, ModulePath, ResolveError, Resolver};
 
@@ -150,6 +150,11 @@ impl Resolver for SourceMapper<'_> {
         );
         Ok(res)
     }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        // record the source, but let the resolver produce the module, e.g. a pre-parsed one.
+        self.resolve_source(path)?;
+        self.resolver.resolve_module(path)
+    }
     fn display_name(&self, path: &ModulePath) -> Option<String> {
         self.resolver.display_name(path)
     }
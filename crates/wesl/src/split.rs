@@ -0,0 +1,248 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    path::Path,
+};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::GlobalDeclaration;
+
+use crate::{CompileResult, Diagnostic, Error, SyntaxUtil, compat::used_decls, sections};
+
+/// The result of [`crate::Wesl::compile_entry_points`]: one tree-shaken
+/// [`CompileResult`] per entry point.
+#[derive(Clone, Default)]
+pub struct EntryPointOutputs {
+    /// The entry point names and their compilation results, in declaration order.
+    pub outputs: Vec<(String, CompileResult)>,
+    /// The warnings of the compilation, shared by all outputs.
+    pub warnings: Vec<Diagnostic<Error>>,
+}
+
+impl EntryPointOutputs {
+    /// Get the compilation result of an entry point.
+    pub fn get(&self, entry_point: &str) -> Option<&CompileResult> {
+        self.outputs
+            .iter()
+            .find(|(name, _)| name == entry_point)
+            .map(|(_, res)| res)
+    }
+
+    /// Iterate over the entry points and their compilation results.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &CompileResult)> {
+        self.outputs.iter().map(|(name, res)| (name.as_str(), res))
+    }
+
+    /// The declarations duplicated in several outputs, with the number of outputs
+    /// containing them, in order of first appearance.
+    pub fn shared_declarations(&self) -> Vec<(String, usize)> {
+        self.outputs
+            .iter()
+            .flat_map(|(_, res)| {
+                res.syntax
+                    .global_declarations
+                    .iter()
+                    .filter_map(|decl| decl.ident().map(|id| id.to_string()))
+            })
+            .counts_by(|name| name)
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .sorted_by_key(|(name, _)| self.first_appearance(name))
+            .collect()
+    }
+
+    fn first_appearance(&self, name: &str) -> (usize, usize) {
+        self.outputs
+            .iter()
+            .enumerate()
+            .find_map(|(i, (_, res))| {
+                let j = res
+                    .syntax
+                    .global_declarations
+                    .iter()
+                    .position(|decl| decl.ident().is_some_and(|id| *id.name() == name))?;
+                Some((i, j))
+            })
+            .unwrap_or_default()
+    }
+
+    /// A summary of the outputs: the number of declarations and the size of each output,
+    /// and the declarations duplicated in several outputs.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        for (name, res) in &self.outputs {
+            writeln!(
+                report,
+                "{name}: {} declarations, {} bytes",
+                res.syntax.global_declarations.len(),
+                res.to_string().len()
+            )
+            .unwrap();
+        }
+        let shared = self.shared_declarations();
+        if !shared.is_empty() {
+            let shared = shared
+                .iter()
+                .map(|(name, count)| format!("{name} ({count})"))
+                .format(", ");
+            writeln!(report, "shared: {shared}").unwrap();
+        }
+        report
+    }
+
+    /// Write each output to `<dir>/<entry point>.wgsl`, creating the directory if needed.
+    pub fn write_files(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        for (name, res) in &self.outputs {
+            std::fs::write(dir.join(format!("{name}.wgsl")), res.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// Split a compilation result into one result per entry point, keeping only the
+/// declarations used by each entry point and the declarations named in `keep`.
+///
+/// `const_assert`s are kept if the declarations they use are kept.
+pub(crate) fn split_entry_points(
+    compiled: CompileResult,
+    keep: Option<&[String]>,
+) -> EntryPointOutputs {
+    let CompileResult {
+        syntax,
+        sourcemap,
+        modules,
+        group_by_module,
+        warnings,
+        provenances,
+        debug_buffer,
+        rebindings,
+        binding_ranges,
+        wgsl_modules,
+        header,
+        exports,
+    } = compiled;
+
+    let outputs = syntax
+        .entry_points()
+        .map(|ep| {
+            let mut used = used_decls(ep, &syntax);
+            used.insert(ep.clone());
+            used.extend(
+                syntax
+                    .global_declarations
+                    .iter()
+                    .filter_map(|decl| decl.ident())
+                    .filter(|id| keep.is_some_and(|keep| keep.contains(&id.to_string())))
+                    .cloned(),
+            );
+            let mut wesl = syntax.clone();
+            wesl.global_declarations
+                .retain(|decl| match (decl.ident(), decl.node()) {
+                    (Some(id), _) => used.contains(id),
+                    (None, GlobalDeclaration::ConstAssert(_)) => syntax
+                        .referenced_idents(decl)
+                        .into_iter()
+                        .all(|id| used.contains(&id) || syntax.find_decl_by_ident(&id).is_none()),
+                    (None, _) => true,
+                });
+
+            let decls = wesl
+                .global_declarations
+                .iter()
+                .filter_map(|decl| decl.ident().cloned())
+                .collect::<HashSet<_>>();
+            let provenances = provenances
+                .iter()
+                .filter(|(id, _)| decls.contains(id))
+                .map(|(id, prov)| (id.clone(), prov.clone()))
+                .collect::<HashMap<_, _>>();
+            // the root module is always first.
+            let modules = modules
+                .iter()
+                .enumerate()
+                .filter(|(i, path)| *i == 0 || provenances.values().any(|p| p.module == **path))
+                .map(|(_, path)| path.clone())
+                .collect::<Vec<_>>();
+            if group_by_module {
+                sections::group_by_module(&mut wesl, &provenances, &modules);
+            }
+            let rebindings = rebindings
+                .iter()
+                .filter(|r| decls.iter().any(|id| *id.name() == r.name))
+                .cloned()
+                .collect();
+
+            let res = CompileResult {
+                syntax: wesl,
+                sourcemap: sourcemap.clone(),
+                modules,
+                group_by_module,
+                warnings: Vec::new(),
+                provenances,
+                debug_buffer: debug_buffer.clone(),
+                rebindings,
+                binding_ranges: binding_ranges.clone(),
+                wgsl_modules: wgsl_modules.clone(),
+                header: header.clone(),
+                exports: exports.clone(),
+            };
+            (ep.to_string(), res)
+        })
+        .collect();
+
+    EntryPointOutputs { outputs, warnings }
+}
+
+#[test]
+fn test_split_entry_points() {
+    use crate::{VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::{shade, Light};
+        @group(0) @binding(0) var<uniform> light: Light;
+        @group(0) @binding(1) var<storage, read_write> out: array<f32>;
+        fn helper() -> f32 { return 2.0; }
+        const_assert 1 < 2;
+        @vertex fn vs() -> @builtin(position) vec4f { return vec4f(helper()); }
+        @fragment fn fs() -> @location(0) vec4f { return shade(light); }
+        @compute @workgroup_size(1) fn cs() { out[0] = helper() + shade(light).x; }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "struct Light { color: vec4f }
+        fn shade(l: Light) -> vec4f { return l.color; }"
+            .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.use_imports(true);
+    let res = compiler
+        .compile_entry_points(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    let names = res.iter().map(|(name, _)| name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["vs", "fs", "cs"]);
+    let vs = res.get("vs").unwrap().to_string();
+    assert!(
+        vs.contains("fn helper()") && vs.contains("const_assert"),
+        "{vs}"
+    );
+    assert!(!vs.contains("fn fs") && !vs.contains("light"), "{vs}");
+    let fs = res.get("fs").unwrap();
+    assert!(!fs.to_string().contains("helper"), "{fs}");
+    assert_eq!(fs.modules.len(), 2);
+    assert_eq!(res.get("vs").unwrap().modules.len(), 1);
+
+    let shared = res
+        .shared_declarations()
+        .into_iter()
+        .map(|(name, count)| (name.split("_").last().unwrap().to_string(), count))
+        .collect::<Vec<_>>();
+    assert!(shared.contains(&("helper".to_string(), 2)), "{shared:?}");
+    assert!(shared.contains(&("shade".to_string(), 2)), "{shared:?}");
+    let report = res.report();
+    assert!(report.starts_with("vs: 3 declarations"), "{report}");
+}
@@ -1,6 +1,14 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use wgsl_parse::syntax::{Ident, TranslationUnit};
+use wgsl_parse::{
+    lexer::{Lexer, Token},
+    syntax::{
+        AddressSpace, DeclarationKind, GlobalDeclaration, Ident, StructMemberNode, TranslationUnit,
+        TypeExpression,
+    },
+};
+
+use crate::{SyntaxUtil, visit::Visit};
 
 /// Remove unused declarations.
 pub(crate) fn strip_except(wgsl: &mut TranslationUnit, keep: &HashSet<Ident>) {
@@ -12,3 +20,163 @@ pub(crate) fn strip_except(wgsl: &mut TranslationUnit, keep: &HashSet<Ident>) {
         }
     });
 }
+
+/// Remove unused members of internal structs, then remove the declarations that
+/// became unused.
+///
+/// Internal structs are structs that are not visible to the host: they are not (part
+/// of) the type of a `uniform`, `storage` or `push_constant` variable, not (part of)
+/// an entry point parameter or return type, and not in `keep`. The members of
+/// internal structs are removed if they are never accessed. Structs constructed with
+/// arguments are never modified, and structs always keep at least one member.
+///
+/// Member accesses are found by name, regardless of the type of the accessed value,
+/// so the analysis is conservative.
+pub(crate) fn strip_members(wgsl: &mut TranslationUnit, keep: &HashSet<Ident>) {
+    // tokens are used because they contain all member accesses, including those in
+    // attributes and template arguments, which are not reachable by `Visit`.
+    let source = wgsl.to_string();
+    let tokens = Lexer::new(&source)
+        .filter_map(|tok| tok.ok().map(|(_, tok, _)| tok))
+        .collect::<Vec<_>>();
+    let accessed = tokens
+        .windows(2)
+        .filter_map(|w| match w {
+            [Token::SymPeriod, Token::Ident(name)] => Some(name.as_str()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let constructed = tokens
+        .windows(3)
+        .filter_map(|w| match w {
+            [Token::Ident(name), Token::SymParenLeft, next] if *next != Token::SymParenRight => {
+                Some(name.as_str())
+            }
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    // the struct names referenced by each type alias and struct declaration.
+    let deps = wgsl
+        .global_declarations
+        .iter()
+        .filter_map(|decl| {
+            let names = match decl.node() {
+                GlobalDeclaration::TypeAlias(decl) => type_names(&decl.ty),
+                GlobalDeclaration::Struct(decl) => decl
+                    .members
+                    .iter()
+                    .flat_map(|m| type_names(&m.ty))
+                    .collect(),
+                _ => return None,
+            };
+            Some((decl.ident()?.to_string(), names))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut roots = Vec::new();
+    for decl in wgsl.declarations() {
+        if let (DeclarationKind::Var(Some((space, _))), Some(ty)) = (decl.kind, &decl.ty) {
+            if is_host_shareable(space) {
+                roots.extend(type_names(ty));
+            }
+        }
+    }
+    for decl in wgsl.entry_point_functions() {
+        roots.extend(decl.parameters.iter().flat_map(|p| type_names(&p.ty)));
+        roots.extend(decl.return_type.iter().flat_map(type_names));
+    }
+    roots.extend(keep.iter().map(|id| id.to_string()));
+    roots.extend(constructed.iter().map(|name| name.to_string()));
+
+    // aliases of constructed structs are roots, so aliased structs are preserved too.
+    let mut preserved = HashSet::new();
+    while let Some(name) = roots.pop() {
+        if preserved.insert(name.clone()) {
+            roots.extend(deps.get(&name).into_iter().flatten().cloned());
+        }
+    }
+
+    for decl in &mut wgsl.global_declarations {
+        let GlobalDeclaration::Struct(decl) = decl.node_mut() else {
+            continue;
+        };
+        if !preserved.contains(&*decl.ident.name()) && decl.members.len() > 1 {
+            let is_accessed = |m: &StructMemberNode| accessed.contains(m.ident.name().as_str());
+            if decl.members.iter().any(is_accessed) {
+                decl.members.retain(is_accessed);
+            } else {
+                decl.members.truncate(1);
+            }
+        }
+    }
+
+    // removing members and declarations can make more declarations unused.
+    loop {
+        let len = wgsl.global_declarations.len();
+        strip_except(wgsl, keep);
+        if wgsl.global_declarations.len() == len {
+            break;
+        }
+    }
+}
+
+fn is_host_shareable(space: AddressSpace) -> bool {
+    matches!(
+        space,
+        AddressSpace::Uniform | AddressSpace::Storage | AddressSpace::PushConstant
+    )
+}
+
+/// The names of the types referenced in a type expression, including template arguments.
+fn type_names(ty: &TypeExpression) -> Vec<String> {
+    let mut names = vec![ty.ident.to_string()];
+    Visit::<TypeExpression>::visit_rec(ty, &mut |ty| names.push(ty.ident.to_string()));
+    names
+}
+
+#[test]
+fn test_strip_members() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "struct Light { color: vec3f, shadow: Shadow, intensity: f32 }
+        struct Shadow { bias: f32 }
+        struct Params { scale: f32, unused: f32 }
+        struct Pair { a: f32, b: f32 }
+        @group(0) @binding(0) var<uniform> params: Params;
+        var<private> light: Light;
+        @fragment fn main() -> @location(0) vec4f {
+            let p = Pair(1.0, 2.0);
+            return vec4f(light.color * light.intensity * params.scale * p.a, 1.0);
+        }"
+        .into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        strip_members: true,
+        ..Default::default()
+    });
+    let res = compiler
+        .compile(&"package::main".parse().unwrap())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let wgsl = &res.syntax;
+    let light = wgsl.find_decl("Light").unwrap().to_string();
+    assert!(light.contains("color") && light.contains("intensity"));
+    assert!(!light.contains("shadow"));
+    assert!(wgsl.find_decl("Shadow").is_none());
+    assert!(
+        wgsl.find_decl("Params")
+            .unwrap()
+            .to_string()
+            .contains("unused")
+    );
+    assert!(
+        wgsl.find_decl("Pair")
+            .unwrap()
+            .to_string()
+            .contains("b: f32")
+    );
+}
@@ -1,4 +1,8 @@
-use std::{borrow::Cow, collections::HashMap, iter::Iterator};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    iter::Iterator,
+};
 
 use crate::{idents::builtin_ident, visit::Visit};
 use wesl_macros::query_mut;
@@ -24,29 +28,178 @@ impl<T: Iterator> IteratorExt for T {
 }
 
 pub trait SyntaxUtil {
+    /// Find a global declaration by name.
+    fn find_decl(&self, name: &str) -> Option<&GlobalDeclarationNode>;
+
+    /// Find the global declaration declaring `ident`.
+    ///
+    /// Idents are compared by reference, see [`Ident`].
+    fn find_decl_by_ident(&self, ident: &Ident) -> Option<&GlobalDeclarationNode>;
+
+    /// Iterate over the global variable, value and override declarations.
+    fn declarations(&self) -> impl Iterator<Item = &Declaration>;
+
+    /// Iterate over the type alias declarations.
+    fn type_aliases(&self) -> impl Iterator<Item = &TypeAlias>;
+
+    /// Iterate over the struct declarations.
+    fn structs(&self) -> impl Iterator<Item = &Struct>;
+
+    /// Iterate over the function declarations.
+    fn functions(&self) -> impl Iterator<Item = &Function>;
+
+    /// Iterate over the functions with a `@vertex`, `@fragment` or `@compute` attribute.
+    fn entry_point_functions(&self) -> impl Iterator<Item = &Function>;
+
+    /// Iterate over the names of the entry point functions.
     fn entry_points(&self) -> impl Iterator<Item = &Ident>;
+
+    /// The set of global declarations of this module referenced by `decl`.
+    ///
+    /// Idents must point at their declaration, see [`Self::retarget_idents`].
+    fn referenced_idents(&self, decl: &GlobalDeclaration) -> HashSet<Ident>;
+
+    /// Resolve the type aliases in a type expression, including in its template
+    /// arguments.
+    ///
+    /// Both user-declared aliases and predeclared aliases (e.g. `vec4f` is `vec4<f32>`)
+    /// are resolved, so two type expressions denote the same type if their canonical
+    /// types are equal when formatted. Aliases are looked up by ident, or by name if
+    /// the idents were not retargeted (see [`Self::retarget_idents`]). In an assembled
+    /// translation unit, alias chains are thus resolved across modules.
+    ///
+    /// Returns `None` if the alias definitions are cyclic.
+    fn canonical_type(&self, ty: &TypeExpression) -> Option<TypeExpression>;
+
     fn retarget_idents(&mut self);
 }
 
 impl SyntaxUtil for TranslationUnit {
-    fn entry_points(&self) -> impl Iterator<Item = &Ident> {
+    fn find_decl(&self, name: &str) -> Option<&GlobalDeclarationNode> {
+        self.global_declarations
+            .iter()
+            .find(|decl| decl.ident().is_some_and(|id| *id.name() == name))
+    }
+
+    fn find_decl_by_ident(&self, ident: &Ident) -> Option<&GlobalDeclarationNode> {
+        self.global_declarations
+            .iter()
+            .find(|decl| decl.ident() == Some(ident))
+    }
+
+    fn declarations(&self) -> impl Iterator<Item = &Declaration> {
         self.global_declarations
             .iter()
             .filter_map(|decl| match decl.node() {
-                GlobalDeclaration::Function(decl) => decl
-                    .attributes
-                    .iter()
-                    .any(|attr| {
-                        matches!(
-                            attr.node(),
-                            Attribute::Vertex | Attribute::Fragment | Attribute::Compute
-                        )
-                    })
-                    .then_some(&decl.ident),
+                GlobalDeclaration::Declaration(decl) => Some(decl),
+                _ => None,
+            })
+    }
+
+    fn type_aliases(&self) -> impl Iterator<Item = &TypeAlias> {
+        self.global_declarations
+            .iter()
+            .filter_map(|decl| match decl.node() {
+                GlobalDeclaration::TypeAlias(decl) => Some(decl),
+                _ => None,
+            })
+    }
+
+    fn structs(&self) -> impl Iterator<Item = &Struct> {
+        self.global_declarations
+            .iter()
+            .filter_map(|decl| match decl.node() {
+                GlobalDeclaration::Struct(decl) => Some(decl),
                 _ => None,
             })
     }
 
+    fn functions(&self) -> impl Iterator<Item = &Function> {
+        self.global_declarations
+            .iter()
+            .filter_map(|decl| match decl.node() {
+                GlobalDeclaration::Function(decl) => Some(decl),
+                _ => None,
+            })
+    }
+
+    fn entry_point_functions(&self) -> impl Iterator<Item = &Function> {
+        self.functions().filter(|decl| {
+            decl.attributes.iter().any(|attr| {
+                matches!(
+                    attr.node(),
+                    Attribute::Vertex | Attribute::Fragment | Attribute::Compute
+                )
+            })
+        })
+    }
+
+    fn entry_points(&self) -> impl Iterator<Item = &Ident> {
+        self.entry_point_functions().map(|decl| &decl.ident)
+    }
+
+    fn referenced_idents(&self, decl: &GlobalDeclaration) -> HashSet<Ident> {
+        let globals = self
+            .global_declarations
+            .iter()
+            .filter_map(|decl| decl.ident())
+            .collect::<HashSet<_>>();
+        let mut res = HashSet::new();
+        Visit::<TypeExpression>::visit_rec(decl, &mut |ty| {
+            if globals.contains(&ty.ident) {
+                res.insert(ty.ident.clone());
+            }
+        });
+        res
+    }
+
+    fn canonical_type(&self, ty: &TypeExpression) -> Option<TypeExpression> {
+        let mut aliases = Vec::new();
+        let mut ty = ty;
+        // aliases cannot be templated.
+        while ty.template_args.is_none() {
+            let alias = self.type_aliases().find(|alias| {
+                alias.ident == ty.ident
+                    || ty.path.is_none() && *alias.ident.name() == *ty.ident.name()
+            });
+            match alias {
+                Some(alias) if aliases.contains(&&alias.ident) => return None,
+                Some(alias) => {
+                    aliases.push(&alias.ident);
+                    ty = &alias.ty;
+                }
+                None => break,
+            }
+        }
+
+        let name = ty.ident.name().clone();
+        let is_declared = || self.find_decl(&name).is_some();
+        let alias = predeclared_alias(&name).filter(|_| ty.path.is_none() && !is_declared());
+        if let Some((base, scalar)) = alias {
+            let ident = |name: &str| {
+                builtin_ident(name)
+                    .cloned()
+                    .unwrap_or_else(|| Ident::new(name.to_string()))
+            };
+            let arg = TypeExpression::new(ident(scalar));
+            return Some(TypeExpression {
+                path: None,
+                ident: ident(base),
+                template_args: Some(vec![TemplateArg {
+                    expression: Expression::TypeOrIdentifier(arg).into(),
+                }]),
+            });
+        }
+
+        let mut ty = ty.clone();
+        for arg in ty.template_args.iter_mut().flatten() {
+            if let Expression::TypeOrIdentifier(arg) = arg.expression.node_mut() {
+                *arg = self.canonical_type(arg)?;
+            }
+        }
+        Some(ty)
+    }
+
     /// make all identifiers that point to the same declaration refer to the same string.
     ///
     /// retarget local references to the local declaration ident and global
@@ -342,3 +495,86 @@ impl SyntaxUtil for TranslationUnit {
         }
     }
 }
+
+/// Split a predeclared vector or matrix alias, e.g. `vec4f` into `vec4` and `f32`.
+///
+/// Reference: <https://www.w3.org/TR/WGSL/#predeclared-types>
+fn predeclared_alias(name: &str) -> Option<(&str, &'static str)> {
+    let scalar = |c| match c {
+        'f' => Some("f32"),
+        'h' => Some("f16"),
+        'i' => Some("i32"),
+        'u' => Some("u32"),
+        _ => None,
+    };
+    let suffix = name.chars().last()?;
+    let base = &name[..name.len() - suffix.len_utf8()];
+    let is_vec = base.len() == 4 && base.starts_with("vec") && base.ends_with(['2', '3', '4']);
+    let is_mat = base.len() == 6
+        && base.starts_with("mat")
+        && matches!(base.as_bytes()[3], b'2'..=b'4')
+        && base.as_bytes()[4] == b'x'
+        && matches!(base.as_bytes()[5], b'2'..=b'4');
+    let scalar = scalar(suffix)?;
+    (is_vec || (is_mat && matches!(scalar, "f32" | "f16"))).then_some((base, scalar))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgsl_parse::Decorated;
+
+    #[test]
+    fn test_queries() {
+        let mut wgsl: TranslationUnit = "
+            struct S { x: f32 }
+            alias A = S;
+            const c = 1;
+            fn f(s: A) -> f32 { return s.x + f32(c); }
+            @compute @workgroup_size(1) fn main() { let s = A(1.0); _ = f(s); }"
+            .parse()
+            .unwrap();
+        wgsl.retarget_idents();
+
+        assert_eq!(wgsl.structs().count(), 1);
+        assert_eq!(wgsl.type_aliases().count(), 1);
+        assert_eq!(wgsl.declarations().count(), 1);
+        assert_eq!(wgsl.functions().count(), 2);
+        let main = wgsl.entry_point_functions().next().unwrap();
+        assert_eq!(*main.ident.name(), "main");
+        assert!(
+            main.find_attribute(|attr| matches!(attr, Attribute::WorkgroupSize(_)))
+                .is_some()
+        );
+
+        let f = wgsl.find_decl("f").unwrap();
+        assert_eq!(wgsl.find_decl_by_ident(f.ident().unwrap()), Some(f));
+        let mut names = wgsl
+            .referenced_idents(f)
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["A", "c"]);
+    }
+
+    #[test]
+    fn test_canonical_type() {
+        let wgsl: TranslationUnit = "
+            alias Color = vec4f;
+            alias Colors = array<Color, 4>;
+            alias A = B;
+            alias B = A;"
+            .parse()
+            .unwrap();
+        let canonical = |ty: &str| {
+            let ty = ty.parse::<TypeExpression>().unwrap();
+            wgsl.canonical_type(&ty).map(|ty| ty.to_string())
+        };
+        assert_eq!(canonical("Color").unwrap(), "vec4<f32>");
+        assert_eq!(canonical("Colors").unwrap(), "array<vec4<f32>, 4>");
+        assert_eq!(canonical("mat2x3h").unwrap(), "mat2x3<f16>");
+        assert_eq!(canonical("u32").unwrap(), "u32");
+        assert_eq!(canonical("A"), None);
+    }
+}
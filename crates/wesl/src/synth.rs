@@ -0,0 +1,308 @@
+use itertools::Itertools;
+use wgsl_parse::syntax::{Expression, Function, TranslationUnit, TypeExpression};
+
+use crate::{FreshNames, SyntaxUtil, visit::Visit};
+
+/// The entry points generated by [`synthesize_entry_points`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EntryPointTemplate {
+    /// A fullscreen-triangle vertex shader and a fragment shader calling the function,
+    /// e.g. for post-processing. Draw 3 vertices without vertex buffers.
+    ///
+    /// The function takes the texture coordinates (`vec2f`), or the color of the input
+    /// texture at the fragment (`vec3f` or `vec4f`). It returns the output color (`f32`,
+    /// `vec3f` or `vec4f`). The input texture and its sampler are bound at `@binding(0)`
+    /// and `@binding(1)` of `group`.
+    Fullscreen { group: u32 },
+    /// A compute shader calling the function once per invocation, e.g. for GPGPU
+    /// harnesses.
+    ///
+    /// Each parameter is read from an array in a read-only storage buffer, at
+    /// `@binding(i)` of `group` for the i-th parameter. The result, if any, is written to
+    /// an array in a read-write storage buffer bound after the inputs. Invocations are
+    /// numbered in row-major order of the global invocation id, and invocations past
+    /// the end of the output array (or the first input array) do nothing.
+    Compute {
+        workgroup_size: [u32; 3],
+        group: u32,
+    },
+}
+
+/// Error produced by [`synthesize_entry_points`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SynthError {
+    #[error("function `{0}` not found")]
+    FunctionNotFound(String),
+    #[error("function `{0}` cannot be wrapped in a {1} entry point: {2}")]
+    Signature(String, &'static str, &'static str),
+}
+
+/// The number of components of a float scalar or vector type.
+fn float_components(ty: &TypeExpression, wesl: &TranslationUnit) -> Option<u32> {
+    let ty = wesl.canonical_type(ty)?;
+    let components = match (ty.ident.name().as_str(), &ty.template_args) {
+        ("f32", None) => return Some(1),
+        ("vec2", Some(_)) => 2,
+        ("vec3", Some(_)) => 3,
+        ("vec4", Some(_)) => 4,
+        _ => return None,
+    };
+    let args = ty.template_args.as_ref()?;
+    match args[0].expression.node() {
+        Expression::TypeOrIdentifier(ty)
+            if ty.template_args.is_none() && *ty.ident.name() == "f32" =>
+        {
+            Some(components)
+        }
+        _ => None,
+    }
+}
+
+fn fullscreen(
+    func: &Function,
+    group: u32,
+    wesl: &TranslationUnit,
+    names: &mut FreshNames,
+) -> Result<(String, Vec<String>), SynthError> {
+    let name = func.ident.to_string();
+    let err = |msg| SynthError::Signature(name.clone(), "fullscreen", msg);
+    let param = match func.parameters.as_slice() {
+        [param] => float_components(&param.ty, wesl)
+            .filter(|n| *n >= 2)
+            .ok_or_else(|| err("the parameter must be a `vec2f`, `vec3f` or `vec4f`"))?,
+        _ => return Err(err("it must take one parameter")),
+    };
+    let ret = func
+        .return_type
+        .as_ref()
+        .and_then(|ty| float_components(ty, wesl))
+        .filter(|n| *n != 2)
+        .ok_or_else(|| err("it must return a `f32`, `vec3f` or `vec4f`"))?;
+
+    let vertex_ty = names.fresh_name("FullscreenVertex");
+    let vs = names.fresh_name("vs_fullscreen");
+    let fs = names.fresh_name(&format!("fs_{name}"));
+    let texture = names.fresh_name("input_texture");
+    let sampler = names.fresh_name("input_sampler");
+    let v = names.fresh_name("v");
+    let color = names.fresh_name("color");
+
+    let alpha = if param == 2 {
+        "1.0".to_string()
+    } else {
+        format!("{color}.a")
+    };
+    let arg = match param {
+        2 => format!("{v}.uv"),
+        3 => format!("{color}.rgb"),
+        _ => color.clone(),
+    };
+    let output = match ret {
+        1 => format!("vec4f(vec3f({name}({arg})), {alpha})"),
+        3 => format!("vec4f({name}({arg}), {alpha})"),
+        _ => format!("{name}({arg})"),
+    };
+    let sample = if param == 2 {
+        String::new()
+    } else {
+        format!("let {color} = textureSample({texture}, {sampler}, {v}.uv);")
+    };
+    let source = format!(
+        "struct {vertex_ty} {{ @builtin(position) position: vec4f, @location(0) uv: vec2f }}
+        @vertex fn {vs}(@builtin(vertex_index) index: u32) -> {vertex_ty} {{
+            let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+            let position = vec4f(uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0);
+            return {vertex_ty}(position, uv);
+        }}
+        @group({group}) @binding(0) var {texture}: texture_2d<f32>;
+        @group({group}) @binding(1) var {sampler}: sampler;
+        @fragment fn {fs}({v}: {vertex_ty}) -> @location(0) vec4f {{
+            {sample}
+            return {output};
+        }}"
+    );
+    Ok((source, vec![vs, fs]))
+}
+
+fn compute(
+    func: &Function,
+    workgroup_size: [u32; 3],
+    group: u32,
+    names: &mut FreshNames,
+) -> Result<(String, Vec<String>), SynthError> {
+    let name = func.ident.to_string();
+    if func.parameters.is_empty() && func.return_type.is_none() {
+        let msg = "it must take parameters or return a value";
+        return Err(SynthError::Signature(name, "compute", msg));
+    }
+
+    let cs = names.fresh_name(&format!("cs_{name}"));
+    let id = names.fresh_name("id");
+    let count = names.fresh_name("num_workgroups");
+    let size = names.fresh_name("size");
+    let i = names.fresh_name("i");
+    let inputs = func
+        .parameters
+        .iter()
+        .map(|p| names.fresh_name(&format!("input_{}", p.ident)))
+        .collect_vec();
+    let output = func
+        .return_type
+        .as_ref()
+        .map(|_| names.fresh_name("output"));
+
+    let mut decls = func
+        .parameters
+        .iter()
+        .zip(&inputs)
+        .enumerate()
+        .map(|(i, (p, input))| {
+            format!(
+                "@group({group}) @binding({i}) var<storage> {input}: array<{}>;",
+                p.ty
+            )
+        })
+        .collect_vec();
+    if let (Some(output), Some(ty)) = (&output, &func.return_type) {
+        decls.push(format!(
+            "@group({group}) @binding({}) var<storage, read_write> {output}: array<{ty}>;",
+            inputs.len()
+        ));
+    }
+    let bound = output.as_ref().or(inputs.first()).unwrap();
+    let args = inputs
+        .iter()
+        .map(|input| format!("{input}[{i}]"))
+        .join(", ");
+    let call = match &output {
+        Some(output) => format!("{output}[{i}] = {name}({args});"),
+        None => format!("{name}({args});"),
+    };
+    let [x, y, z] = workgroup_size;
+    let source = format!(
+        "{}
+        @compute @workgroup_size({x}, {y}, {z})
+        fn {cs}(@builtin(global_invocation_id) {id}: vec3u, @builtin(num_workgroups) {count}: vec3u) {{
+            let {size} = {count} * vec3u({x}u, {y}u, {z}u);
+            let {i} = {id}.x + ({id}.y + {id}.z * {size}.y) * {size}.x;
+            if {i} >= arrayLength(&{bound}) {{
+                return;
+            }}
+            {call}
+        }}",
+        decls.join("\n")
+    );
+    Ok((source, vec![cs]))
+}
+
+/// Generate entry points calling a function of the translation unit, e.g. a library
+/// function. Returns the names of the generated entry points.
+///
+/// The generated declarations are appended to the translation unit, with names that
+/// are not used in it. See [`EntryPointTemplate`] for the supported signatures and the
+/// resources bound by the generated entry points.
+pub fn synthesize_entry_points(
+    wesl: &mut TranslationUnit,
+    function: &str,
+    template: EntryPointTemplate,
+) -> Result<Vec<String>, SynthError> {
+    let func = wesl
+        .functions()
+        .find(|f| *f.ident.name() == function)
+        .ok_or_else(|| SynthError::FunctionNotFound(function.to_string()))?;
+    let mut names = FreshNames::new(wesl);
+    let (source, entry_points) = match template {
+        EntryPointTemplate::Fullscreen { group } => fullscreen(func, group, wesl, &mut names)?,
+        EntryPointTemplate::Compute {
+            workgroup_size,
+            group,
+        } => compute(func, workgroup_size, group, &mut names)?,
+    };
+
+    let mut generated =
+        wgsl_parse::parse_str(&source).expect("generated entry points should be valid WGSL");
+    generated.retarget_idents();
+    // link the calls to the wrapped function.
+    let ident = func.ident.clone();
+    Visit::<TypeExpression>::visit_rec_mut(&mut generated, &mut |ty| {
+        if ty.path.is_none() && *ty.ident.name() == function {
+            ty.ident = ident.clone();
+        }
+    });
+    wesl.global_declarations
+        .extend(generated.global_declarations);
+    Ok(entry_points)
+}
+
+#[test]
+fn test_synthesize_entry_points() {
+    use crate::{CompileOptions, EntryPointStage, VirtualResolver, Wesl};
+
+    let compile = |wesl: &TranslationUnit| {
+        let mut compiler = Wesl::new_barebones().set_custom_resolver(VirtualResolver::new());
+        compiler.set_options(CompileOptions::default());
+        compiler
+            .compile_tree(wesl, &"package::main".parse().unwrap())
+            .unwrap_or_else(|e| panic!("{e}"))
+    };
+
+    let lib = "alias Color = vec3f;
+        fn tonemap(c: Color) -> Color { return c / (c + 1.0); }
+        fn pattern(uv: vec2f) -> f32 { return uv.x * uv.y; }
+        fn square(x: f32, y: u32) -> f32 { return x * x * f32(y); }
+        fn input_texture() {}";
+    let mut wesl = wgsl_parse::parse_str(lib).unwrap();
+    let fullscreen = EntryPointTemplate::Fullscreen { group: 1 };
+    let eps = synthesize_entry_points(&mut wesl, "tonemap", fullscreen).unwrap();
+    assert_eq!(eps, ["vs_fullscreen", "fs_tonemap"]);
+    let res = compile(&wesl);
+    let entry_points = res.entry_points();
+    assert_eq!(entry_points.len(), 2);
+    assert!(
+        entry_points
+            .iter()
+            .any(|ep| ep.name == "fs_tonemap" && ep.stage == EntryPointStage::Fragment)
+    );
+    let wgsl = res.to_string();
+    assert!(
+        wgsl.contains("var input_texture_1: texture_2d<f32>;"),
+        "{wgsl}"
+    );
+    assert!(
+        wgsl.contains("return vec4f(tonemap(color.rgb), color.a);"),
+        "{wgsl}"
+    );
+
+    let eps = synthesize_entry_points(&mut wesl, "pattern", fullscreen).unwrap();
+    assert_eq!(eps, ["vs_fullscreen_1", "fs_pattern"]);
+
+    let compute = EntryPointTemplate::Compute {
+        workgroup_size: [64, 1, 1],
+        group: 0,
+    };
+    let eps = synthesize_entry_points(&mut wesl, "square", compute).unwrap();
+    assert_eq!(eps, ["cs_square"]);
+    let res = compile(&wesl);
+    let bindings = res.bindings();
+    let output = bindings.get("output").unwrap();
+    assert_eq!((output.group, output.binding), (Some(0), Some(2)));
+    let wgsl = res.to_string();
+    assert!(
+        wgsl.contains("@compute @workgroup_size(64, 1, 1)"),
+        "{wgsl}"
+    );
+    assert!(
+        wgsl.contains("output[i] = square(input_x[i], input_y[i]);"),
+        "{wgsl}"
+    );
+
+    let err = |function: &str, template| {
+        let mut wesl = wgsl_parse::parse_str(lib).unwrap();
+        synthesize_entry_points(&mut wesl, function, template)
+            .unwrap_err()
+            .to_string()
+    };
+    assert!(err("square", fullscreen).contains("it must take one parameter"));
+    assert!(err("input_texture", compute).contains("it must take parameters"));
+    assert!(err("missing", compute).contains("not found"));
+}
@@ -0,0 +1,162 @@
+//! Instrumentation with `tracing` spans and events, enabled by the `tracing` crate
+//! feature. Without the feature, the macros expand to nothing.
+//!
+//! Spans are at the `DEBUG` level. Their durations can be reported by the subscriber,
+//! e.g. with `tracing_subscriber::fmt::format::FmtSpan::CLOSE`.
+
+use std::{borrow::Cow, path::PathBuf};
+
+use wgsl_parse::syntax::{ModulePath, TranslationUnit};
+
+use crate::{ResolveError, Resolver};
+
+/// Enter a `DEBUG` span until the end of the current block.
+macro_rules! span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+/// Emit a `DEBUG` event.
+macro_rules! event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+pub(crate) use {event, span};
+
+/// A resolver that traces the loading of each module.
+pub(crate) struct TraceResolver<R: Resolver> {
+    resolver: R,
+}
+
+impl<R: Resolver> TraceResolver<R> {
+    pub(crate) fn new(resolver: R) -> Self {
+        Self { resolver }
+    }
+}
+
+impl<R: Resolver> Resolver for TraceResolver<R> {
+    fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        span!("load_module", module = %path);
+        let wesl = self.resolver.resolve_module(path)?;
+        event!(
+            imports = wesl.imports.len(),
+            declarations = wesl.global_declarations.len(),
+            "loaded module"
+        );
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+#[test]
+fn test_trace_resolver() {
+    let dir = std::env::temp_dir().join(format!("wesl-trace-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.wesl"), "fn f() {}").unwrap();
+
+    let resolver = TraceResolver::new(crate::FileResolver::new(&dir));
+    let path = "package::main".parse().unwrap();
+    assert_eq!(resolver.fs_path(&path), Some(dir.join("main.wesl")));
+    assert_eq!(
+        resolver.display_name(&path),
+        Some(dir.join("main.wesl").display().to_string())
+    );
+    assert_eq!(resolver.resolve_source(&path).unwrap(), "fn f() {}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing() {
+    use std::sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    };
+
+    use tracing::{
+        Event, Metadata, Subscriber,
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+    };
+
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    /// Records the names of the spans and the messages of the events.
+    #[derive(Default)]
+    struct Recorder {
+        ids: AtomicU64,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct Message(String);
+
+    impl Visit for Message {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{value:?}");
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let name = span.metadata().name();
+            self.log.lock().unwrap().push(format!("span {name}"));
+            Id::from_u64(self.ids.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("event {}", message.0));
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f; @fragment fn main() { f(); }".into(),
+    );
+    resolver.add_module("package::util".parse().unwrap(), "fn f() {}".into());
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+
+    let recorder = Recorder::default();
+    let log = recorder.log.clone();
+    tracing::subscriber::with_default(recorder, || {
+        compiler
+            .compile(&"package::main".parse().unwrap())
+            .unwrap_or_else(|e| panic!("{e}"));
+    });
+    let log = log.lock().unwrap();
+    for entry in ["span compile", "span load_module", "event loaded module"] {
+        assert!(
+            log.iter().any(|e| e == entry),
+            "missing `{entry}` in {log:?}"
+        );
+    }
+    let modules = log.iter().filter(|e| *e == "span load_module").count();
+    assert!(modules >= 2, "{log:?}");
+}
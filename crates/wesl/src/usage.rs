@@ -0,0 +1,295 @@
+//! Static usage of the resource bindings by entry points. See
+//! [`crate::EntryPoint::resources`] and [`crate::EntryPoint::sampler_pairs`].
+
+use std::collections::{HashMap, HashSet};
+
+use wgsl_parse::syntax::{
+    AssignmentOperator, Expression, ExpressionNode, Function, FunctionCall, GlobalDeclaration,
+    Ident, Statement, StatementNode, TranslationUnit, UnaryOperator,
+};
+
+use crate::{
+    SyntaxUtil,
+    reflect::{Bindings, ResourceUsage, SamplerPair},
+    visit::Visit,
+};
+
+/// Built-in functions that query the properties of a resource without accessing its
+/// contents.
+const QUERY_BUILTINS: &[&str] = &[
+    "arrayLength",
+    "textureDimensions",
+    "textureNumLayers",
+    "textureNumLevels",
+    "textureNumSamples",
+];
+
+struct Usage<'a> {
+    wesl: &'a TranslationUnit,
+    bindings: &'a Bindings,
+    resources: HashMap<String, ResourceUsage>,
+    pairs: Vec<SamplerPair>,
+    /// The functions being analyzed, to stop on (invalid) recursion.
+    stack: HashSet<Ident>,
+}
+
+/// The variable at the root of a reference or pointer expression, e.g. `buf` in
+/// `&buf.data[i]`, and the index expressions along the way.
+pub(crate) fn root(expr: &Expression) -> Option<(&Ident, Vec<&ExpressionNode>)> {
+    match expr {
+        Expression::TypeOrIdentifier(ty) if ty.template_args.is_none() => {
+            Some((&ty.ident, Vec::new()))
+        }
+        Expression::Parenthesized(paren) => root(&paren.expression),
+        Expression::NamedComponent(comp) => root(&comp.base),
+        Expression::Indexing(idx) => {
+            let (id, mut indices) = root(&idx.base)?;
+            indices.push(&idx.index);
+            Some((id, indices))
+        }
+        Expression::Unary(unary)
+            if matches!(
+                unary.operator,
+                UnaryOperator::AddressOf | UnaryOperator::Indirection
+            ) =>
+        {
+            root(&unary.operand)
+        }
+        _ => None,
+    }
+}
+
+impl Usage<'_> {
+    /// The name of the resource binding an identifier refers to. `env` maps the
+    /// parameters and pointer variables of the current function to resources.
+    fn resource(&self, id: &Ident, env: &HashMap<Ident, String>) -> Option<String> {
+        if let Some(name) = env.get(id) {
+            return Some(name.clone());
+        }
+        match self.wesl.find_decl_by_ident(id)?.node() {
+            GlobalDeclaration::Declaration(decl) => {
+                let name = decl.ident.to_string();
+                self.bindings.get(&name).map(|_| name)
+            }
+            _ => None,
+        }
+    }
+
+    fn access(&mut self, name: String, read: bool, write: bool) {
+        let usage = self
+            .resources
+            .entry(name.clone())
+            .or_insert_with(|| ResourceUsage {
+                name,
+                read: false,
+                write: false,
+            });
+        usage.read |= read;
+        usage.write |= write;
+    }
+
+    /// Access the resource at the root of a reference or pointer expression. Returns the
+    /// resource name.
+    fn place(
+        &mut self,
+        expr: &Expression,
+        read: bool,
+        write: bool,
+        env: &HashMap<Ident, String>,
+    ) -> Option<String> {
+        let Some((id, indices)) = root(expr) else {
+            self.expr(expr, env);
+            return None;
+        };
+        for index in indices {
+            self.expr(index, env);
+        }
+        let name = self.resource(id, env)?;
+        self.access(name.clone(), read, write);
+        Some(name)
+    }
+
+    fn binding(&self, name: &str) -> Option<(u32, u32)> {
+        let binding = self.bindings.get(name)?;
+        Some((binding.group?, binding.binding?))
+    }
+
+    fn call(&mut self, call: &FunctionCall, env: &HashMap<Ident, String>) {
+        let decl = self.wesl.find_decl_by_ident(&call.ty.ident);
+        if let Some(GlobalDeclaration::Function(f)) = decl.map(|decl| decl.node()) {
+            // resources passed by reference or by handle are accessed by the callee.
+            let mut callee_env = HashMap::new();
+            for (param, arg) in f.parameters.iter().zip(&call.arguments) {
+                let name = match arg.node() {
+                    Expression::TypeOrIdentifier(_) | Expression::Unary(_) => {
+                        self.place(arg, false, false, env)
+                    }
+                    _ => {
+                        self.expr(arg, env);
+                        None
+                    }
+                };
+                if let Some(name) = name {
+                    callee_env.insert(param.ident.clone(), name);
+                }
+            }
+            self.function(f, callee_env);
+            return;
+        } else if decl.is_some() || call.ty.path.is_some() {
+            // type constructor
+            for arg in &call.arguments {
+                self.expr(arg, env);
+            }
+            return;
+        }
+
+        let name = call.ty.ident.name();
+        let mut args = call.arguments.iter();
+        if name.starts_with("textureSample") || name.starts_with("textureGather") {
+            // `textureGather` may take the component first.
+            let mut resources = Vec::new();
+            for arg in args {
+                match self.place(arg, true, false, env) {
+                    Some(res) => resources.push(res),
+                    None => continue,
+                }
+            }
+            if let [texture, sampler, ..] = resources.as_slice() {
+                let pair = SamplerPair {
+                    texture_binding: self.binding(texture),
+                    sampler_binding: self.binding(sampler),
+                    texture: texture.clone(),
+                    sampler: sampler.clone(),
+                };
+                if !self.pairs.contains(&pair) {
+                    self.pairs.push(pair);
+                }
+            }
+            return;
+        }
+        let access = match name.as_str() {
+            "atomicLoad" | "textureLoad" | "workgroupUniformLoad" => Some((true, false)),
+            "atomicStore" | "textureStore" => Some((false, true)),
+            name if name.starts_with("atomic") => Some((true, true)),
+            name if QUERY_BUILTINS.contains(&name) => Some((false, false)),
+            _ => None,
+        };
+        if let Some((read, write)) = access {
+            if let Some(arg) = args.next() {
+                self.place(arg, read, write, env);
+            }
+        }
+        for arg in args {
+            self.expr(arg, env);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expression, env: &HashMap<Ident, String>) {
+        match expr {
+            Expression::FunctionCall(call) => self.call(call, env),
+            Expression::TypeOrIdentifier(ty) if ty.template_args.is_none() => {
+                if let Some(name) = self.resource(&ty.ident, env) {
+                    self.access(name, true, false);
+                }
+            }
+            expr => {
+                for expr in Visit::<ExpressionNode>::visit(expr) {
+                    self.expr(expr, env);
+                }
+            }
+        }
+    }
+
+    fn stat(&mut self, stat: &StatementNode, env: &mut HashMap<Ident, String>) {
+        match stat.node() {
+            Statement::Assignment(assign) => {
+                let read = assign.operator != AssignmentOperator::Equal;
+                self.place(&assign.lhs, read, true, env);
+                self.expr(&assign.rhs, env);
+            }
+            Statement::Increment(stat) => {
+                self.place(&stat.expression, true, true, env);
+            }
+            Statement::Decrement(stat) => {
+                self.place(&stat.expression, true, true, env);
+            }
+            Statement::Declaration(decl) => match decl.initializer.as_ref().map(|e| e.node()) {
+                // pointers to resources are accessed where they are dereferenced.
+                Some(init @ Expression::Unary(unary))
+                    if unary.operator == UnaryOperator::AddressOf =>
+                {
+                    if let Some(name) = self.place(init, false, false, env) {
+                        env.insert(decl.ident.clone(), name);
+                    }
+                }
+                Some(init) => self.expr(init, env),
+                None => {}
+            },
+            Statement::If(stat) => {
+                self.expr(&stat.if_clause.expression, env);
+                for clause in &stat.else_if_clauses {
+                    self.expr(&clause.expression, env);
+                }
+            }
+            Statement::Switch(stat) => self.expr(&stat.expression, env),
+            Statement::Loop(stat) => {
+                if let Some(break_if) = stat.continuing.as_ref().and_then(|c| c.break_if.as_ref()) {
+                    self.expr(&break_if.expression, env);
+                }
+            }
+            Statement::For(stat) => {
+                if let Some(cond) = &stat.condition {
+                    self.expr(cond, env);
+                }
+            }
+            Statement::While(stat) => self.expr(&stat.condition, env),
+            Statement::Return(stat) => {
+                if let Some(expr) = &stat.expression {
+                    self.expr(expr, env);
+                }
+            }
+            Statement::FunctionCall(stat) => self.call(&stat.call, env),
+            Statement::ConstAssert(_)
+            | Statement::Void
+            | Statement::Compound(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Discard(_) => {}
+        }
+        for stat in Visit::<StatementNode>::visit(stat.node()) {
+            self.stat(stat, env);
+        }
+    }
+
+    fn function(&mut self, f: &Function, mut env: HashMap<Ident, String>) {
+        if !self.stack.insert(f.ident.clone()) {
+            return;
+        }
+        for stat in &f.body.statements {
+            self.stat(stat, &mut env);
+        }
+        self.stack.remove(&f.ident);
+    }
+}
+
+/// The resources used by an entry point, in declaration order, and the texture-sampler
+/// pairs it samples.
+pub(crate) fn resource_usage(
+    entry_point: &Function,
+    wesl: &TranslationUnit,
+    bindings: &Bindings,
+) -> (Vec<ResourceUsage>, Vec<SamplerPair>) {
+    let mut usage = Usage {
+        wesl,
+        bindings,
+        resources: HashMap::new(),
+        pairs: Vec::new(),
+        stack: HashSet::new(),
+    };
+    usage.function(entry_point, HashMap::new());
+    let resources = bindings
+        .iter()
+        .filter_map(|b| usage.resources.remove(&b.name))
+        .collect();
+    (resources, usage.pairs)
+}
@@ -0,0 +1,464 @@
+//! Table-driven validation of the placement, arguments, repetition and conflicts of
+//! attributes, and their canonical order. See [`normalize_attributes`].
+
+use wgsl_parse::syntax::{
+    Attribute, Attributes, CompoundStatement, DeclarationKind, Expression, GlobalDeclaration,
+    GlobalDirective, LiteralExpression, Statement, StatementNode, TranslationUnit, UnaryOperator,
+};
+
+use super::E;
+use crate::{Diagnostic, Error, Label, visit::Visit};
+
+/// The kind of syntax node an attribute is applied to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Target {
+    Import,
+    Directive,
+    Var,
+    /// Module-scope `const` declaration.
+    Const,
+    Override,
+    Alias,
+    Struct,
+    Member,
+    Function,
+    Parameter,
+    Return,
+    ConstAssert,
+    /// Compound statements, control flow statements and their bodies, and function
+    /// bodies.
+    Block,
+    /// Other statements.
+    OtherStatement,
+}
+
+impl Target {
+    fn describe(self) -> &'static str {
+        match self {
+            Target::Import => "imports",
+            Target::Directive => "directives",
+            Target::Var => "variables",
+            Target::Const => "constants",
+            Target::Override => "overrides",
+            Target::Alias => "type aliases",
+            Target::Struct => "structs",
+            Target::Member => "struct members",
+            Target::Function => "functions",
+            Target::Parameter => "function parameters",
+            Target::Return => "function return types",
+            Target::ConstAssert => "const assertions",
+            Target::Block => "compound and control flow statements",
+            Target::OtherStatement => "simple statements",
+        }
+    }
+}
+
+/// Constraints on the arguments of an attribute, checked when they are literals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Args {
+    /// Not checked.
+    Any,
+    /// A non-negative integer.
+    Index,
+    /// A positive integer.
+    Positive,
+    /// A positive power of two.
+    PowerOfTwo,
+}
+
+struct Rule {
+    name: &'static str,
+    targets: &'static [Target],
+    args: Args,
+    /// Whether the attribute can appear several times on the same node.
+    repeat: bool,
+}
+
+use Target::*;
+
+const IO: &[Target] = &[Parameter, Return, Member];
+const BLOCKS: &[Target] = &[Function, Block];
+const ANYWHERE: &[Target] = &[
+    Import,
+    Directive,
+    Var,
+    Const,
+    Override,
+    Alias,
+    Struct,
+    Member,
+    Function,
+    Parameter,
+    ConstAssert,
+    Block,
+    OtherStatement,
+];
+
+/// Where each attribute is allowed, what its arguments are and whether it can be
+/// repeated.
+///
+/// Reference: <https://www.w3.org/TR/WGSL/#attributes>
+#[rustfmt::skip]
+const RULES: &[Rule] = &[
+    Rule { name: "align", targets: &[Member], args: Args::PowerOfTwo, repeat: false },
+    Rule { name: "binding", targets: &[Var], args: Args::Index, repeat: false },
+    Rule { name: "blend_src", targets: &[Member], args: Args::Index, repeat: false },
+    Rule { name: "builtin", targets: IO, args: Args::Any, repeat: false },
+    Rule { name: "const", targets: &[Function], args: Args::Any, repeat: false },
+    Rule { name: "diagnostic", targets: BLOCKS, args: Args::Any, repeat: true },
+    Rule { name: "group", targets: &[Var], args: Args::Index, repeat: false },
+    Rule { name: "id", targets: &[Override], args: Args::Index, repeat: false },
+    Rule { name: "interpolate", targets: IO, args: Args::Any, repeat: false },
+    Rule { name: "invariant", targets: IO, args: Args::Any, repeat: false },
+    Rule { name: "location", targets: IO, args: Args::Index, repeat: false },
+    Rule { name: "must_use", targets: &[Function], args: Args::Any, repeat: false },
+    Rule { name: "size", targets: &[Member], args: Args::Positive, repeat: false },
+    Rule { name: "workgroup_size", targets: &[Function], args: Args::Positive, repeat: false },
+    Rule { name: "vertex", targets: &[Function], args: Args::Any, repeat: false },
+    Rule { name: "fragment", targets: &[Function], args: Args::Any, repeat: false },
+    Rule { name: "compute", targets: &[Function], args: Args::Any, repeat: false },
+    // WESL extensions
+    Rule { name: "publish", targets: &[Import, Var, Const, Override, Alias, Struct, Function], args: Args::Any, repeat: false },
+    Rule { name: "if", targets: ANYWHERE, args: Args::Any, repeat: false },
+    Rule { name: "elif", targets: ANYWHERE, args: Args::Any, repeat: false },
+    Rule { name: "else", targets: ANYWHERE, args: Args::Any, repeat: false },
+    Rule { name: "compile_error", targets: ANYWHERE, args: Args::Any, repeat: true },
+    Rule { name: "compile_warn", targets: ANYWHERE, args: Args::Any, repeat: true },
+    Rule { name: "type", targets: &[Function], args: Args::Any, repeat: true },
+    Rule { name: "slot", targets: &[Block], args: Args::Any, repeat: false },
+    // naga extensions
+    Rule { name: "early_depth_test", targets: &[Function], args: Args::Any, repeat: false },
+    Rule { name: "task", targets: &[Function], args: Args::Any, repeat: false },
+    Rule { name: "mesh", targets: &[Function], args: Args::Any, repeat: false },
+    Rule { name: "payload", targets: &[Function], args: Args::Any, repeat: false },
+    Rule { name: "per_primitive", targets: &[Parameter, Member], args: Args::Any, repeat: false },
+];
+
+/// Sets of attributes that cannot appear together on the same node.
+const CONFLICTS: &[&[&str]] = &[
+    &["if", "elif", "else"],
+    &["vertex", "fragment", "compute", "task", "mesh"],
+    &["builtin", "location"],
+    &["builtin", "interpolate"],
+];
+
+/// The canonical order of the attributes, see [`normalize_attributes`]. Custom attributes
+/// come last.
+#[rustfmt::skip]
+const ORDER: &[&str] = &[
+    // conditional translation first: it decides whether the other attributes apply.
+    "if", "elif", "else",
+    "publish", "compile_error", "compile_warn", "diagnostic", "const", "must_use", "type",
+    "vertex", "fragment", "compute", "task", "mesh", "payload", "early_depth_test",
+    "workgroup_size",
+    "group", "binding", "id",
+    "builtin", "location", "blend_src", "interpolate", "invariant", "per_primitive",
+    "align", "size",
+    "slot",
+];
+
+/// The name of a built-in attribute. Custom attributes have no rule.
+fn attr_name(attr: &Attribute) -> Option<&'static str> {
+    Some(match attr {
+        Attribute::Align(_) => "align",
+        Attribute::Binding(_) => "binding",
+        Attribute::BlendSrc(_) => "blend_src",
+        Attribute::Builtin(_) => "builtin",
+        Attribute::Const => "const",
+        Attribute::Diagnostic(_) => "diagnostic",
+        Attribute::Group(_) => "group",
+        Attribute::Id(_) => "id",
+        Attribute::Interpolate(_) => "interpolate",
+        Attribute::Invariant => "invariant",
+        Attribute::Location(_) => "location",
+        Attribute::MustUse => "must_use",
+        Attribute::Size(_) => "size",
+        Attribute::WorkgroupSize(_) => "workgroup_size",
+        Attribute::Vertex => "vertex",
+        Attribute::Fragment => "fragment",
+        Attribute::Compute => "compute",
+        Attribute::Publish => "publish",
+        Attribute::If(_) => "if",
+        Attribute::Elif(_) => "elif",
+        Attribute::Else => "else",
+        Attribute::CompileError(_) => "compile_error",
+        Attribute::CompileWarn(_) => "compile_warn",
+        Attribute::Slot(_) => "slot",
+        Attribute::Type(_) => "type",
+        Attribute::EarlyDepthTest(_) => "early_depth_test",
+        Attribute::Task => "task",
+        Attribute::Mesh(_) => "mesh",
+        Attribute::Payload(_) => "payload",
+        Attribute::PerPrimitive => "per_primitive",
+        Attribute::Custom(_) => return None,
+    })
+}
+
+fn attr_args(attr: &Attribute) -> Vec<&Expression> {
+    match attr {
+        Attribute::Align(e)
+        | Attribute::Binding(e)
+        | Attribute::BlendSrc(e)
+        | Attribute::Group(e)
+        | Attribute::Id(e)
+        | Attribute::Location(e)
+        | Attribute::Size(e) => vec![e.node()],
+        Attribute::WorkgroupSize(size) => std::iter::once(&size.x)
+            .chain(size.y.as_ref())
+            .chain(size.z.as_ref())
+            .map(|e| e.node())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Check a literal argument. Other expressions are not checked.
+fn check_arg(expr: &Expression, args: Args) -> Result<(), &'static str> {
+    let (negative, lit) = match expr {
+        Expression::Literal(lit) => (false, lit),
+        Expression::Unary(unary) if unary.operator == UnaryOperator::Negation => {
+            match unary.operand.node() {
+                Expression::Literal(lit) => (true, lit),
+                _ => return Ok(()),
+            }
+        }
+        Expression::Parenthesized(paren) => return check_arg(paren.expression.node(), args),
+        _ => return Ok(()),
+    };
+    let value = match lit {
+        LiteralExpression::AbstractInt(i) => *i,
+        LiteralExpression::I32(i) => *i as i64,
+        LiteralExpression::U32(u) => *u as i64,
+        _ => return Err("expected an integer"),
+    };
+    let value = if negative {
+        -value
+    } else {
+        value
+    };
+    match args {
+        Args::Any => Ok(()),
+        Args::Index if value < 0 => Err("expected a non-negative integer"),
+        Args::Positive if value <= 0 => Err("expected a positive integer"),
+        Args::PowerOfTwo if value <= 0 || (value & (value - 1)) != 0 => {
+            Err("expected a positive power of two")
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_attrs(attrs: &Attributes, target: Target) -> Result<(), Diagnostic<Error>> {
+    for (i, attr) in attrs.iter().enumerate() {
+        let Some(name) = attr_name(attr.node()) else {
+            continue;
+        };
+        let rule = RULES.iter().find(|rule| rule.name == name).unwrap();
+        let prev = |pred: &dyn Fn(&str) -> bool| {
+            attrs[..i].iter().find_map(|prev| {
+                let prev_name = attr_name(prev.node()).filter(|n| pred(n))?;
+                Some((prev_name, prev.span()))
+            })
+        };
+        let duplicate = (!rule.repeat).then(|| prev(&|n| n == name)).flatten();
+        let conflict = CONFLICTS
+            .iter()
+            .filter(|set| set.contains(&name))
+            .find_map(|set| prev(&|n| n != name && set.contains(&n)));
+        let (err, label) = if !rule.targets.contains(&target) {
+            (Some(E::AttributeTarget(name, target.describe())), None)
+        } else if let Some((_, span)) = duplicate {
+            let label = Label::new(format!("first `@{name}` here"), span);
+            (Some(E::DuplicateAttribute(name)), Some(label))
+        } else if let Some((other, span)) = conflict {
+            let label = Label::new(format!("conflicting `@{other}` here"), span);
+            (Some(E::ConflictingAttributes(name, other)), Some(label))
+        } else if rule.args == Args::Any {
+            (None, None)
+        } else {
+            let err = attr_args(attr.node())
+                .into_iter()
+                .find_map(|expr| check_arg(expr, rule.args).err())
+                .map(|msg| E::AttributeArgument(name, msg));
+            (err, None)
+        };
+        if let Some(err) = err {
+            let diag = Diagnostic::from(err).with_span(attr.span());
+            return Err(label.into_iter().fold(diag, Diagnostic::with_label));
+        }
+    }
+    Ok(())
+}
+
+fn check_function(f: &wgsl_parse::syntax::Function) -> Result<(), Diagnostic<Error>> {
+    check_attrs(&f.attributes, Target::Function)?;
+    for p in &f.parameters {
+        check_attrs(&p.attributes, Parameter)?;
+    }
+    check_attrs(&f.return_attributes, Return)?;
+    check_block(&f.body)
+}
+
+fn check_block(block: &CompoundStatement) -> Result<(), Diagnostic<Error>> {
+    check_attrs(&block.attributes, Block)?;
+    for stat in &block.statements {
+        check_stat(stat)?;
+    }
+    Ok(())
+}
+
+fn check_stat(stat: &StatementNode) -> Result<(), Diagnostic<Error>> {
+    match stat.node() {
+        Statement::Compound(s) => return check_block(s),
+        Statement::If(s) => {
+            check_attrs(&s.attributes, Block)?;
+            check_block(&s.if_clause.body)?;
+            for clause in &s.else_if_clauses {
+                check_attrs(&clause.attributes, Block)?;
+                check_block(&clause.body)?;
+            }
+            if let Some(clause) = &s.else_clause {
+                check_attrs(&clause.attributes, Block)?;
+                check_block(&clause.body)?;
+            }
+            return Ok(());
+        }
+        Statement::Switch(s) => {
+            check_attrs(&s.attributes, Block)?;
+            check_attrs(&s.body_attributes, Block)?;
+            for clause in &s.clauses {
+                check_attrs(&clause.attributes, Block)?;
+                check_block(&clause.body)?;
+            }
+            return Ok(());
+        }
+        Statement::Loop(s) => {
+            check_attrs(&s.attributes, Block)?;
+            check_block(&s.body)?;
+            if let Some(cont) = &s.continuing {
+                check_attrs(&cont.attributes, Block)?;
+                check_block(&cont.body)?;
+                if let Some(break_if) = &cont.break_if {
+                    check_attrs(&break_if.attributes, OtherStatement)?;
+                }
+            }
+            return Ok(());
+        }
+        Statement::For(s) => {
+            check_attrs(&s.attributes, Block)?;
+            s.initializer.iter().try_for_each(check_stat)?;
+            s.update.iter().try_for_each(check_stat)?;
+            return check_block(&s.body);
+        }
+        Statement::While(s) => {
+            check_attrs(&s.attributes, Block)?;
+            return check_block(&s.body);
+        }
+        _ => {}
+    }
+    for attrs in Visit::<Attributes>::visit(stat.node()) {
+        check_attrs(attrs, OtherStatement)?;
+    }
+    Ok(())
+}
+
+/// Check that the attributes are applied to the right kind of syntax node, that they
+/// are not repeated (except `@diagnostic`, `@compile_error`, `@compile_warn`, `@type`
+/// and custom attributes), that they do not conflict (e.g. `@if` and `@else`, or two
+/// shader stages) and that their literal arguments are in range.
+///
+/// Duplicate and conflicting attributes are reported at the second attribute, with a
+/// label at the first one. Custom attributes are not checked.
+pub(super) fn check_attributes(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    for import in &wesl.imports {
+        check_attrs(&import.attributes, Import)?;
+    }
+    for directive in &wesl.global_directives {
+        let attrs = match directive {
+            GlobalDirective::Diagnostic(d) => &d.attributes,
+            GlobalDirective::Enable(d) => &d.attributes,
+            GlobalDirective::Requires(d) => &d.attributes,
+        };
+        check_attrs(attrs, Directive)?;
+    }
+    for decl in &wesl.global_declarations {
+        let res = match decl.node() {
+            GlobalDeclaration::Void => Ok(()),
+            GlobalDeclaration::Declaration(d) => {
+                let target = match d.kind {
+                    DeclarationKind::Const => Const,
+                    DeclarationKind::Override => Override,
+                    _ => Var,
+                };
+                check_attrs(&d.attributes, target)
+            }
+            GlobalDeclaration::TypeAlias(a) => check_attrs(&a.attributes, Alias),
+            GlobalDeclaration::Struct(s) => check_attrs(&s.attributes, Struct).and_then(|()| {
+                s.members
+                    .iter()
+                    .try_for_each(|m| check_attrs(&m.attributes, Member))
+            }),
+            GlobalDeclaration::Function(f) => check_function(f),
+            GlobalDeclaration::ConstAssert(a) => check_attrs(&a.attributes, ConstAssert),
+        };
+        res.map_err(|mut d| {
+            d.detail.declaration = decl.ident().map(|id| id.to_string());
+            d
+        })?;
+    }
+    Ok(())
+}
+
+/// Sort the attributes of each syntax node in a canonical order, for stable output
+/// regardless of the order in which the attributes were written. See
+/// [`crate::CompileOptions::normalize_attributes`].
+///
+/// Conditional translation attributes come first, then declaration attributes such as
+/// `@publish` and `@must_use`, shader stages, resource bindings, input/output attributes
+/// and memory layout attributes. Custom attributes come last. The sort is stable:
+/// repeated attributes, e.g. `@diagnostic`, keep their relative order.
+pub fn normalize_attributes(wesl: &mut TranslationUnit) {
+    let rank = |attr: &Attribute| {
+        attr_name(attr)
+            .and_then(|name| ORDER.iter().position(|n| *n == name))
+            .unwrap_or(ORDER.len())
+    };
+    for attrs in Visit::<Attributes>::visit_mut(wesl) {
+        attrs.sort_by_key(|attr| rank(attr.node()));
+    }
+}
+
+#[test]
+fn test_normalize_attributes() {
+    for rule in RULES {
+        assert!(ORDER.contains(&rule.name), "`@{}` has no rank", rule.name);
+    }
+    for set in CONFLICTS {
+        assert!(set.iter().all(|name| RULES.iter().any(|r| r.name == *name)));
+    }
+
+    let mut wesl = wgsl_parse::parse_str(
+        "@binding(1) @group(0) @if(a) var<uniform> x: f32;
+        @workgroup_size(1) @diagnostic(off, derivative_uniformity) @my_attr @compute
+        @diagnostic(warning, subgroup_uniformity) fn main() {}",
+    )
+    .unwrap();
+    normalize_attributes(&mut wesl);
+    let attrs = Visit::<Attributes>::visit(&wesl)
+        .map(|attrs| {
+            attrs
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        attrs,
+        [
+            "@if(a) @group(0) @binding(1)",
+            "@diagnostic(off, derivative_uniformity) @diagnostic(warning, subgroup_uniformity) \
+             @compute @workgroup_size(1) @my_attr",
+        ]
+    );
+}
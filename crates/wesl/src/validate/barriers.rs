@@ -0,0 +1,249 @@
+//! Validation of the usage of synchronization and atomic built-in functions.
+
+use wgsl_parse::{
+    span::Span,
+    syntax::{
+        Attribute, Expression, ExpressionNode, Function, FunctionCall, GlobalDeclaration,
+        Statement, StatementNode, TranslationUnit, TypeExpression, UnaryOperator,
+    },
+};
+use wgsl_types::idents::BUILTIN_FUNCTION_NAMES;
+
+use super::{BUILTIN_VOID_NAMES, E, canonical, ident_ty};
+use crate::{Diagnostic, Error, SyntaxUtil, cost::UNIFORM_BUILTINS, visit::Visit};
+
+/// The name of the built-in function called, if the call is not to a declaration.
+fn builtin_name(call: &FunctionCall, wesl: &TranslationUnit) -> Option<String> {
+    let name = call.ty.ident.name();
+    (call.ty.path.is_none()
+        && wesl.find_decl_by_ident(&call.ty.ident).is_none()
+        && BUILTIN_FUNCTION_NAMES.contains(&name.as_str()))
+    .then(|| name.to_string())
+}
+
+/// The expressions in a function, recursively.
+fn expressions(f: &Function) -> Vec<&ExpressionNode> {
+    fn rec<'a>(expr: &'a ExpressionNode, exprs: &mut Vec<&'a ExpressionNode>) {
+        exprs.push(expr);
+        for expr in Visit::<ExpressionNode>::visit(expr.node()) {
+            rec(expr, exprs);
+        }
+    }
+    let mut exprs = Vec::new();
+    for stat in &f.body.statements {
+        for expr in Visit::<ExpressionNode>::visit(stat.node()) {
+            rec(expr, &mut exprs);
+        }
+    }
+    exprs
+}
+
+/// The function calls in a function, in statements and expressions, with their span.
+fn calls(f: &Function) -> Vec<(&FunctionCall, Span)> {
+    fn rec<'a>(stat: &'a StatementNode, calls: &mut Vec<(&'a FunctionCall, Span)>) {
+        if let Statement::FunctionCall(stat_call) = stat.node() {
+            calls.push((&stat_call.call, stat.span()));
+        }
+        for stat in Visit::<StatementNode>::visit(stat.node()) {
+            rec(stat, calls);
+        }
+    }
+    let mut calls = Vec::new();
+    for stat in &f.body.statements {
+        rec(stat, &mut calls);
+    }
+    calls.extend(
+        expressions(f)
+            .into_iter()
+            .filter_map(|expr| match expr.node() {
+                Expression::FunctionCall(call) => Some((call, expr.span())),
+                _ => None,
+            }),
+    );
+    calls
+}
+
+/// Check that the built-in functions that must be called in uniform control flow
+/// (`workgroupBarrier`, `storageBarrier`, `textureBarrier` and `workgroupUniformLoad`)
+/// are only called by compute entry points.
+pub(super) fn check_barriers(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    for ep in wesl.entry_point_functions() {
+        let stage = ep.attributes.iter().find_map(|attr| match attr.node() {
+            Attribute::Vertex => Some("vertex"),
+            Attribute::Fragment => Some("fragment"),
+            _ => None,
+        });
+        let Some(stage) = stage else {
+            continue;
+        };
+        let used = crate::compat::used_decls(&ep.ident, wesl);
+        let funcs = std::iter::once(ep).chain(used.iter().filter_map(|id| {
+            match wesl.find_decl_by_ident(id)?.node() {
+                GlobalDeclaration::Function(f) => Some(f),
+                _ => None,
+            }
+        }));
+        for f in funcs {
+            for (call, span) in calls(f) {
+                let Some(name) = builtin_name(call, wesl) else {
+                    continue;
+                };
+                if UNIFORM_BUILTINS.contains(&name.as_str()) {
+                    return Err(Diagnostic::from(E::StageBuiltin(
+                        name,
+                        stage,
+                        ep.ident.to_string(),
+                    ))
+                    .with_span(span)
+                    .with_declaration(f.ident.to_string()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that the built-in functions that must be called in uniform control flow are
+/// not called in non-uniform control flow, according to the uniformity analysis of
+/// [`crate::Cost`].
+///
+/// The analysis is less precise than the WGSL uniformity analysis and may reject valid
+/// shaders, so it is only part of [`super::ValidationLevel::Pedantic`].
+pub(super) fn check_uniformity(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    if let Some((name, function)) = crate::cost::divergent_barriers(wesl).into_iter().next() {
+        return Err(
+            Diagnostic::from(E::NonUniformBarrier(name.to_string(), function.clone()))
+                .with_declaration(function),
+        );
+    }
+    Ok(())
+}
+
+/// The `n`-th template argument of a type, if it is a type.
+fn template_ty(ty: &TypeExpression, n: usize) -> Option<TypeExpression> {
+    match ty.template_args.as_ref()?.get(n)?.expression.node() {
+        Expression::TypeOrIdentifier(ty) => Some(ty.clone()),
+        _ => None,
+    }
+}
+
+/// The type of a reference expression (e.g. `s.a[0]`) in a function, if it is known.
+fn ref_ty(expr: &Expression, f: &Function, wesl: &TranslationUnit) -> Option<TypeExpression> {
+    let ty = match expr {
+        Expression::TypeOrIdentifier(ty) if ty.template_args.is_none() => {
+            ident_ty(&ty.ident, f, wesl)?.clone()
+        }
+        Expression::Parenthesized(paren) => return ref_ty(&paren.expression, f, wesl),
+        Expression::NamedComponent(comp) => {
+            let base = ref_ty(&comp.base, f, wesl)?;
+            match wesl.find_decl_by_ident(&base.ident)?.node() {
+                GlobalDeclaration::Struct(s) => s
+                    .members
+                    .iter()
+                    .find(|m| *m.ident.name() == *comp.component.name())?
+                    .ty
+                    .clone(),
+                _ => return None,
+            }
+        }
+        Expression::Indexing(idx) => {
+            let base = ref_ty(&idx.base, f, wesl)?;
+            if !matches!(base.ident.name().as_str(), "array" | "binding_array") {
+                return None;
+            }
+            template_ty(&base, 0)?
+        }
+        Expression::Unary(unary) if unary.operator == UnaryOperator::Indirection => {
+            return ptr_ty(&unary.operand, f, wesl);
+        }
+        _ => return None,
+    };
+    Some(canonical(&ty, wesl))
+}
+
+/// The store type of a pointer expression (e.g. `&a`) in a function, if it is known.
+fn ptr_ty(expr: &Expression, f: &Function, wesl: &TranslationUnit) -> Option<TypeExpression> {
+    match expr {
+        Expression::Unary(unary) if unary.operator == UnaryOperator::AddressOf => {
+            ref_ty(&unary.operand, f, wesl)
+        }
+        Expression::Parenthesized(paren) => ptr_ty(&paren.expression, f, wesl),
+        Expression::TypeOrIdentifier(ty) if ty.template_args.is_none() => {
+            let ty = canonical(ident_ty(&ty.ident, f, wesl)?, wesl);
+            if *ty.ident.name() != "ptr" {
+                return None;
+            }
+            Some(canonical(&template_ty(&ty, 1)?, wesl))
+        }
+        _ => None,
+    }
+}
+
+/// Check the usage of atomic built-in functions and function return values:
+/// * the pointer argument of atomic built-in functions points to an `atomic` type, when
+///   its type is known,
+/// * the result of `atomicCompareExchangeWeak` is a struct, it is not used as an
+///   operand or indexed,
+/// * functions without a return type are not called in expressions.
+pub(super) fn check_atomics(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    let is_exchange = |expr: &ExpressionNode| {
+        matches!(expr.node(), Expression::FunctionCall(call)
+            if builtin_name(call, wesl).is_some_and(|name| name == "atomicCompareExchangeWeak"))
+    };
+
+    for f in wesl.functions() {
+        let with_decl = |err: E, span: Span| {
+            Diagnostic::from(err)
+                .with_span(span)
+                .with_declaration(f.ident.to_string())
+        };
+
+        for (call, span) in calls(f) {
+            let Some(name) = builtin_name(call, wesl) else {
+                continue;
+            };
+            if !name.starts_with("atomic") {
+                continue;
+            }
+            let Some(ptr) = call.arguments.first() else {
+                continue;
+            };
+            if let Some(ty) = ptr_ty(ptr, f, wesl).filter(|ty| *ty.ident.name() != "atomic") {
+                return Err(with_decl(E::AtomicType(name, ty.to_string()), span));
+            }
+        }
+
+        for expr in expressions(f) {
+            let operands = match expr.node() {
+                Expression::Unary(unary) => vec![&unary.operand],
+                Expression::Binary(binary) => vec![&binary.left, &binary.right],
+                Expression::Indexing(idx) => vec![&idx.base],
+                Expression::FunctionCall(call) => {
+                    let is_void = match wesl.find_decl_by_ident(&call.ty.ident) {
+                        Some(decl) => match decl.node() {
+                            GlobalDeclaration::Function(f) => f.return_type.is_none(),
+                            _ => false,
+                        },
+                        None => builtin_name(call, wesl)
+                            .is_some_and(|name| BUILTIN_VOID_NAMES.contains(&name.as_str())),
+                    };
+                    if is_void {
+                        return Err(with_decl(
+                            E::VoidCall(call.ty.ident.to_string()),
+                            expr.span(),
+                        ));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(operand) = operands.into_iter().find(|expr| is_exchange(expr)) {
+                return Err(with_decl(
+                    E::AtomicResult(operand.to_string()),
+                    operand.span(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
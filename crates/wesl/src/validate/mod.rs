@@ -1,16 +1,27 @@
+mod attrs;
+mod barriers;
+mod pedantic;
+
+pub use attrs::normalize_attributes;
+
 use std::collections::HashSet;
 
 use wesl_macros::query;
 use wgsl_parse::Decorated;
 use wgsl_parse::syntax::{
-    Expression, ExpressionNode, FunctionCall, GlobalDeclaration, Ident, ImportContent,
-    TranslationUnit, TypeExpression,
+    AddressSpace, Attribute, AttributeNode, Attributes, BuiltinValue, CaseSelector,
+    DeclarationKind, Expression, ExpressionNode, Function, FunctionCall, GlobalDeclaration,
+    GlobalDirective, Ident, ImportContent, LiteralExpression, Statement, StatementNode,
+    StructMember, TranslationUnit, TypeExpression,
 };
 use wgsl_types::idents::{BUILTIN_CONSTRUCTOR_NAMES, BUILTIN_FUNCTION_NAMES};
 
+use crate::DeclKind;
 use crate::idents::builtin_ident;
+use crate::printf::DEBUG_PRINTF;
+use crate::reflect::{Folder, Overrides, texture_type};
 use crate::visit::Visit;
-use crate::{Diagnostic, Error};
+use crate::{Diagnostic, Error, SyntaxUtil};
 
 /// WESL or WGSL Validation error.
 #[derive(Clone, Debug, thiserror::Error)]
@@ -25,10 +36,235 @@ pub enum ValidateError {
     Duplicate(String),
     #[error("declaration of `{0}` is cyclic via `{1}`")]
     Cycle(String, String),
+    #[error("entry point `{0}` is missing")]
+    MissingEntryPoint(String),
+    #[error("`{0}` is not an entry point, only entry points can be `@export`")]
+    NotExportable(String),
+    #[error("the return value of `{0}` must be used")]
+    MustUse(String),
+    #[error("push constant `{0}` cannot have `@group` or `@binding` attributes")]
+    PushConstantBinding(String),
+    #[error("`binding_array` can only be the type of a `uniform`, `storage` or handle variable")]
+    InvalidBindingArray,
+    #[error("`{1}` requires `enable {0};`")]
+    MissingEnable(&'static str, String),
+    #[error("invalid `@blend_src` in `{0}`: {1}")]
+    InvalidBlendSrc(String, &'static str),
+    #[error("duplicate `@id({0})` on override `{1}`")]
+    DuplicateOverrideId(u32, String),
+    #[error("cannot evaluate the `@id` of override `{0}`")]
+    OverrideId(String),
+    #[error("invalid texture type `{0}`: {1}")]
+    InvalidTexture(String, String),
+    #[error("`{1}` requires the `{0}` extension, which is not enabled")]
+    DisabledExtension(&'static str, String),
+    #[error("a `switch` statement must have exactly one `default` selector, found {0}")]
+    SwitchDefault(usize),
+    #[error("duplicate `case` selector `{0}`")]
+    DuplicateCase(String),
+    #[error("`case` selector `{0}` has type `{1}`, but the `switch` expression has type `{2}`")]
+    CaseType(String, &'static str, &'static str),
+    #[error("`@{0}` is not allowed on {1}")]
+    AttributeTarget(&'static str, &'static str),
+    #[error("duplicate `@{0}` attribute")]
+    DuplicateAttribute(&'static str),
+    #[error("`@{0}` conflicts with `@{1}`")]
+    ConflictingAttributes(&'static str, &'static str),
+    #[error("invalid argument of `@{0}`: {1}")]
+    AttributeArgument(&'static str, &'static str),
+    #[error("compute entry point `{0}` uses {1} bytes of workgroup memory, the limit is {2}")]
+    WorkgroupMemory(String, u32, u32),
+    #[error(
+        "compute entry point `{0}` uses {1} bytes of workgroup memory, more than half of the limit ({2}), which limits occupancy"
+    )]
+    WorkgroupPressure(String, u32, u32),
+    #[error("entry point `{0}` exceeds its budget of {1}: {2} > {3}")]
+    CostBudget(String, &'static str, u32, u32),
+    #[error(
+        "`{0}` can only be called in compute shaders, but is called by the {1} entry point `{2}`"
+    )]
+    StageBuiltin(String, &'static str, String),
+    #[error(
+        "`{0}` must be called in uniform control flow, but may be called in non-uniform control flow in `{1}`"
+    )]
+    NonUniformBarrier(String, String),
+    #[error("the pointer argument of `{0}` must point to an atomic type, found `{1}`")]
+    AtomicType(String, String),
+    #[error(
+        "`{0}` returns a struct and cannot be used as an operand, use its `old_value` or `exchanged` member"
+    )]
+    AtomicResult(String),
+    #[error("`{0}` does not return a value and cannot be used in an expression")]
+    VoidCall(String),
+    #[error("mismatched `@{0}` annotation: {1}")]
+    Annotation(String, String),
+    #[error("floating-point values are compared with `{0}` in `{1}`, which is not portable")]
+    FloatEquality(String, String),
+    #[error("`{0}` is not representable as `{2}` in the initializer of `{1}`")]
+    AbstractNarrowing(String, String, &'static str),
+    #[error(
+        "{0} is WESL-only syntax, which is not allowed in a plain WGSL module (rename the file to `.wesl`)"
+    )]
+    WeslSyntax(String),
+    #[error("{0} `{1}` {2}, rename it to `{3}`")]
+    Naming(DeclKind, String, String, String),
+    #[error("defined constant `{0}` collides with {1} of the root module")]
+    DefineCollision(String, &'static str),
+    #[error("invalid defined constant `{0}`: {1}")]
+    InvalidDefine(String, &'static str),
 }
 
 type E = ValidateError;
 
+/// The amount of validation performed during compilation. See
+/// [`crate::CompileOptions::validation_level`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ValidationLevel {
+    /// No validation. The fastest option, for inputs known to be valid.
+    None,
+    /// Validate the individual WESL modules with [`validate_wesl`], but not the output.
+    Syntax,
+    /// Validate the individual WESL modules with [`validate_wesl`] and the output with
+    /// [`validate_wgsl`].
+    #[default]
+    Standard,
+    /// Standard validation, and portability checks on the output:
+    /// * floating-point values are not compared with `==` or `!=`,
+    /// * the abstract literal initializers of `f32` and `f16` declarations are
+    ///   representable in the declared type,
+    /// * `workgroupBarrier`, `storageBarrier`, `textureBarrier` and
+    ///   `workgroupUniformLoad` are not called in non-uniform control flow. The
+    ///   analysis is less precise than the WGSL uniformity analysis and may reject
+    ///   valid shaders.
+    Pedantic,
+}
+
+/// Non-standard WGSL language extensions. See [`crate::CompileOptions::extensions`].
+///
+/// Extensions require the `naga-ext` crate feature. Modules using a disabled extension
+/// are rejected.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Extensions {
+    /// Ray queries: the `ray_query` and `acceleration_structure` types, the `RayDesc`
+    /// and `RayIntersection` structs, the `RAY_*` constants and the ray query built-in
+    /// functions.
+    pub ray_query: bool,
+    /// Mesh shading: the `@task`, `@mesh`, `@payload` and `@per_primitive` attributes.
+    pub mesh_shader: bool,
+}
+
+/// Built-in names of the ray query extension.
+///
+/// Reference: <https://github.com/gfx-rs/wgpu/blob/trunk/docs/api-specs/ray_tracing.md>
+#[cfg(feature = "naga-ext")]
+const RAY_QUERY_NAMES: &[&str] = &[
+    "ray_query",
+    "acceleration_structure",
+    "RayDesc",
+    "RayIntersection",
+    "RAY_FLAG_NONE",
+    "RAY_FLAG_FORCE_OPAQUE",
+    "RAY_FLAG_FORCE_NO_OPAQUE",
+    "RAY_FLAG_TERMINATE_ON_FIRST_HIT",
+    "RAY_FLAG_SKIP_CLOSEST_HIT_SHADER",
+    "RAY_FLAG_CULL_BACK_FACING",
+    "RAY_FLAG_CULL_FRONT_FACING",
+    "RAY_FLAG_CULL_OPAQUE",
+    "RAY_FLAG_CULL_NO_OPAQUE",
+    "RAY_FLAG_SKIP_TRIANGLES",
+    "RAY_FLAG_SKIP_AABBS",
+    "RAY_QUERY_INTERSECTION_NONE",
+    "RAY_QUERY_INTERSECTION_TRIANGLE",
+    "RAY_QUERY_INTERSECTION_GENERATED",
+    "RAY_QUERY_INTERSECTION_AABB",
+    "rayQueryInitialize",
+    "rayQueryProceed",
+    "rayQueryGenerateIntersection",
+    "rayQueryConfirmIntersection",
+    "rayQueryTerminate",
+    "rayQueryGetCommittedIntersection",
+    "rayQueryGetCandidateIntersection",
+    "getCommittedHitVertexPositions",
+    "getCandidateHitVertexPositions",
+];
+
+/// Reject the usage of disabled language extensions.
+#[cfg(feature = "naga-ext")]
+pub(crate) fn check_extensions(
+    wesl: &TranslationUnit,
+    extensions: &Extensions,
+) -> Result<(), Diagnostic<Error>> {
+    let declared = wesl
+        .global_declarations
+        .iter()
+        .filter_map(|decl| decl.ident())
+        .map(|id| id.to_string())
+        .collect::<HashSet<_>>();
+
+    for directive in &wesl.global_directives {
+        if let wgsl_parse::syntax::GlobalDirective::Enable(enable) = directive {
+            for ext in &enable.extensions {
+                let name = match ext.as_str() {
+                    "ray_query" | "wgpu_ray_query" if !extensions.ray_query => "ray_query",
+                    "wgpu_mesh_shader" if !extensions.mesh_shader => "mesh_shader",
+                    _ => continue,
+                };
+                return Err(Diagnostic::from(E::DisabledExtension(
+                    name,
+                    format!("enable {ext}"),
+                )));
+            }
+        }
+    }
+
+    for decl in &wesl.global_declarations {
+        let err = |name: &'static str, usage: String| {
+            let mut d = Diagnostic::from(E::DisabledExtension(name, usage));
+            d.detail.declaration = decl.ident().map(|id| id.to_string());
+            d
+        };
+        if !extensions.ray_query {
+            let mut usage = None;
+            Visit::<TypeExpression>::visit_rec(decl.node(), &mut |ty| {
+                let name = ty.ident.name();
+                if usage.is_none()
+                    && ty.path.is_none()
+                    && RAY_QUERY_NAMES.contains(&name.as_str())
+                    && !declared.contains(name.as_str())
+                {
+                    usage = Some(name.to_string());
+                }
+            });
+            if let Some(usage) = usage {
+                return Err(err("ray_query", usage));
+            }
+        }
+        if !extensions.mesh_shader {
+            let attrs = Visit::<Attributes>::visit(decl.node()).flatten();
+            let attrs = attrs.chain(match decl.node() {
+                GlobalDeclaration::Function(f) => f.return_attributes.iter(),
+                _ => [].iter(),
+            });
+            for attr in attrs {
+                if matches!(
+                    attr.node(),
+                    Attribute::Task
+                        | Attribute::Mesh(_)
+                        | Attribute::Payload(_)
+                        | Attribute::PerPrimitive
+                ) {
+                    return Err(err("mesh_shader", attr.to_string()).with_span(attr.span()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 /// An identifier is linked to a declaration if:
 /// * its use-count is greater than 1
 /// * OR it is a built-in name
@@ -54,7 +290,17 @@ fn check_defined_symbols(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>
         if let Expression::TypeOrIdentifier(ty) = expr.node() {
             check_ty(ty).map_err(|d| d.with_span(expr.span()))
         } else if let Expression::FunctionCall(call) = expr.node() {
-            check_ty(&call.ty).map_err(|d| d.with_span(expr.span()))?;
+            if call.ty.path.is_none()
+                && *call.ty.ident.name() == DEBUG_PRINTF
+                && call.ty.ident.use_count() == 1
+            {
+                // WESL built-in function, see `CompileOptions::debug_printf`.
+                for arg in call.ty.template_args.iter().flatten() {
+                    check_expr(&arg.expression)?;
+                }
+            } else {
+                check_ty(&call.ty).map_err(|d| d.with_span(expr.span()))?;
+            }
             for expr in &call.arguments {
                 check_expr(expr)?;
             }
@@ -97,6 +343,11 @@ fn check_defined_symbols(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>
     Ok(())
 }
 
+/// The type with aliases resolved. Cyclic aliases are reported by [`check_cycles`].
+fn canonical(ty: &TypeExpression, wesl: &TranslationUnit) -> TypeExpression {
+    wesl.canonical_type(ty).unwrap_or_else(|| ty.clone())
+}
+
 fn check_function_calls(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
     fn check_call(call: &FunctionCall, ident: &Ident, wesl: &TranslationUnit) -> Result<(), E> {
         let decl = wesl
@@ -124,11 +375,12 @@ fn check_function_calls(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>>
                     ));
                 }
             }
-            Some(GlobalDeclaration::TypeAlias(decl)) => {
-                if decl.ty.template_args.is_some() {
+            Some(GlobalDeclaration::TypeAlias(_)) => {
+                let ty = canonical(&call.ty, wesl);
+                if ty.template_args.is_some() {
                     // TODO: check args for builtin functions
                 } else {
-                    check_call(call, &decl.ty.ident, wesl)?;
+                    check_call(call, &ty.ident, wesl)?;
                 }
             }
             Some(_) => return Err(E::NotCallable(ident.to_string())),
@@ -170,6 +422,520 @@ fn check_function_calls(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>>
     Ok(())
 }
 
+/// Built-in functions that return a value but are not `@must_use`.
+///
+/// Reference: <https://www.w3.org/TR/WGSL/#atomic-builtin-functions>
+const BUILTIN_DISCARDABLE_NAMES: &[&str] = &[
+    "atomicLoad",
+    "atomicAdd",
+    "atomicSub",
+    "atomicMax",
+    "atomicMin",
+    "atomicAnd",
+    "atomicOr",
+    "atomicXor",
+    "atomicExchange",
+    "atomicCompareExchangeWeak",
+    "rayQueryProceed",
+];
+
+/// Built-in functions that do not return a value.
+const BUILTIN_VOID_NAMES: &[&str] = &[
+    "atomicStore",
+    "workgroupBarrier",
+    "storageBarrier",
+    "textureBarrier",
+    "textureStore",
+    "rayQueryInitialize",
+    "rayQueryGenerateIntersection",
+    "rayQueryConfirmIntersection",
+    "rayQueryTerminate",
+];
+
+/// Function call statements cannot call a function with the `@must_use` attribute.
+/// Type constructors and built-in functions returning a value are `@must_use`, except
+/// atomic functions.
+///
+/// Reference: <https://www.w3.org/TR/WGSL/#function-call-statement>
+fn check_must_use(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    fn is_must_use(ident: &Ident, wesl: &TranslationUnit) -> bool {
+        let decl = wesl
+            .global_declarations
+            .iter()
+            .find(|decl| decl.ident().is_some_and(|id| id == ident))
+            .map(|decl| decl.node());
+
+        match decl {
+            Some(GlobalDeclaration::Function(decl)) => decl
+                .attributes
+                .iter()
+                .any(|attr| *attr.node() == Attribute::MustUse),
+            Some(GlobalDeclaration::Struct(_) | GlobalDeclaration::TypeAlias(_)) => true,
+            Some(_) => false, // reported by check_function_calls
+            None => {
+                let name = ident.name();
+                BUILTIN_CONSTRUCTOR_NAMES.contains(&name.as_str())
+                    || BUILTIN_FUNCTION_NAMES.contains(&name.as_str())
+                        && !BUILTIN_DISCARDABLE_NAMES.contains(&name.as_str())
+                        && !BUILTIN_VOID_NAMES.contains(&name.as_str())
+            }
+        }
+    }
+    fn check_stat(stat: &StatementNode, wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+        if let Statement::FunctionCall(call) = stat.node() {
+            if is_must_use(&call.call.ty.ident, wesl) {
+                return Err(Diagnostic::from(E::MustUse(call.call.ty.ident.to_string()))
+                    .with_span(stat.span()));
+            }
+        }
+        for stat in Visit::<StatementNode>::visit(stat.node()) {
+            check_stat(stat, wesl)?;
+        }
+        Ok(())
+    }
+    for decl in &wesl.global_declarations {
+        for stat in Visit::<StatementNode>::visit(decl.node()) {
+            check_stat(stat, wesl).map_err(|mut d| {
+                d.detail.span.get_or_insert(stat.span());
+                d.detail.declaration = decl.ident().map(|id| id.name().to_string());
+                d
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// The type of an identifier in a function, if it refers to a declaration with an
+/// explicit type.
+fn ident_ty<'a>(
+    id: &Ident,
+    f: &'a Function,
+    wesl: &'a TranslationUnit,
+) -> Option<&'a TypeExpression> {
+    fn local<'a>(
+        id: &Ident,
+        stats: impl Iterator<Item = &'a StatementNode>,
+    ) -> Option<&'a TypeExpression> {
+        for stat in stats {
+            if let Statement::Declaration(d) = stat.node() {
+                if d.ident == *id {
+                    return d.ty.as_ref();
+                }
+            }
+            if let Some(ty) = local(id, Visit::<StatementNode>::visit(stat.node())) {
+                return Some(ty);
+            }
+        }
+        None
+    }
+    if let Some(p) = f.parameters.iter().find(|p| p.ident == *id) {
+        return Some(&p.ty);
+    }
+    if let Some(ty) = local(id, f.body.statements.iter()) {
+        return Some(ty);
+    }
+    match wesl.find_decl_by_ident(id)?.node() {
+        GlobalDeclaration::Declaration(d) => d.ty.as_ref(),
+        _ => None,
+    }
+}
+
+/// Check the `switch` statements:
+/// * there is exactly one `default` selector,
+/// * the `case` selectors are distinct after evaluation,
+/// * the `case` selectors have the same concrete type, and the type of the `switch`
+///   expression if it is known.
+///
+/// Without the `eval` crate feature, only literal `case` selectors are evaluated. The
+/// type of the `switch` expression is known if it is a literal, or an identifier of a
+/// declaration with an explicit type.
+fn check_switches(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    fn check_stat(
+        stat: &StatementNode,
+        f: &Function,
+        wesl: &TranslationUnit,
+        folder: &mut Folder,
+    ) -> Result<(), Diagnostic<Error>> {
+        if let Statement::Switch(switch) = stat.node() {
+            let selectors = switch
+                .clauses
+                .iter()
+                .flat_map(|clause| &clause.case_selectors);
+            let defaults = selectors
+                .clone()
+                .filter(|sel| matches!(sel, CaseSelector::Default))
+                .count();
+            if defaults != 1 {
+                return Err(Diagnostic::from(E::SwitchDefault(defaults)).with_span(stat.span()));
+            }
+
+            let mut expected = match switch.expression.node() {
+                Expression::TypeOrIdentifier(ty) if ty.template_args.is_none() => {
+                    ident_ty(&ty.ident, f, wesl).and_then(|ty| {
+                        let ty = canonical(ty, wesl);
+                        match ty.ident.name().as_str() {
+                            "i32" => Some("i32"),
+                            "u32" => Some("u32"),
+                            _ => None,
+                        }
+                    })
+                }
+                expr => folder.fold_int(expr).and_then(|(_, ty)| ty),
+            };
+            let mut values = HashSet::new();
+            for sel in selectors {
+                let CaseSelector::Expression(expr) = sel else {
+                    continue;
+                };
+                let Some((value, ty)) = folder.fold_int(expr) else {
+                    continue;
+                };
+                let err = match (ty, expected) {
+                    (Some(ty), Some(expected)) if ty != expected => {
+                        Some(E::CaseType(expr.to_string(), ty, expected))
+                    }
+                    _ if !values.insert(value) => Some(E::DuplicateCase(expr.to_string())),
+                    _ => None,
+                };
+                if let Some(err) = err {
+                    return Err(Diagnostic::from(err).with_span(expr.span()));
+                }
+                expected = expected.or(ty);
+            }
+        }
+        for stat in Visit::<StatementNode>::visit(stat.node()) {
+            check_stat(stat, f, wesl, folder)?;
+        }
+        Ok(())
+    }
+
+    let mut folder = Folder::new(wesl);
+    for f in wesl.functions() {
+        for stat in &f.body.statements {
+            check_stat(stat, f, wesl, &mut folder)
+                .map_err(|d| d.with_declaration(f.ident.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Check the usage of the `push_constant` address space and the `binding_array` type
+/// (`naga-ext` crate feature):
+/// * push constants are not bound to a group and binding,
+/// * `binding_array` is only allowed as the store type of module-scope resource variables.
+fn check_resources(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    for decl in &wesl.global_declarations {
+        let mut binding_array = false;
+        let mut check_ty = |ty: &TypeExpression| {
+            binding_array |= *canonical(ty, wesl).ident.name() == "binding_array"
+        };
+
+        match decl.node() {
+            // aliases are checked where they are used.
+            GlobalDeclaration::TypeAlias(_) => {}
+            GlobalDeclaration::Declaration(d) if d.kind.is_var() => {
+                let space = match d.kind {
+                    DeclarationKind::Var(Some((space, _))) => space,
+                    _ => AddressSpace::Handle,
+                };
+                if space == AddressSpace::PushConstant
+                    && d.attributes.iter().any(|attr| {
+                        matches!(attr.node(), Attribute::Group(_) | Attribute::Binding(_))
+                    })
+                {
+                    return Err(
+                        Diagnostic::from(E::PushConstantBinding(d.ident.to_string()))
+                            .with_declaration(d.ident.to_string()),
+                    );
+                }
+                match &d.ty {
+                    // the store type of a resource can be a binding_array, but not its children.
+                    Some(ty)
+                        if matches!(
+                            space,
+                            AddressSpace::Handle | AddressSpace::Uniform | AddressSpace::Storage
+                        ) =>
+                    {
+                        Visit::<TypeExpression>::visit_rec(&canonical(ty, wesl), &mut check_ty)
+                    }
+                    _ => Visit::<TypeExpression>::visit_rec(decl.node(), &mut check_ty),
+                }
+            }
+            _ => Visit::<TypeExpression>::visit_rec(decl.node(), &mut check_ty),
+        }
+
+        if binding_array {
+            let mut d = Diagnostic::from(E::InvalidBindingArray);
+            d.detail.declaration = decl.ident().map(|id| id.to_string());
+            return Err(d);
+        }
+    }
+    Ok(())
+}
+
+/// Check the template parameters of built-in texture types: the sampled type, texel
+/// format and access mode.
+fn check_textures(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    let declared = wesl
+        .global_declarations
+        .iter()
+        .filter_map(|decl| decl.ident())
+        .map(|id| id.to_string())
+        .collect::<HashSet<_>>();
+
+    for decl in &wesl.global_declarations {
+        let mut err = None;
+        Visit::<TypeExpression>::visit_rec(decl.node(), &mut |ty| {
+            if err.is_some() || declared.contains(ty.ident.name().as_str()) {
+                return;
+            }
+            if let Some(Err(e)) = texture_type(&canonical(ty, wesl)) {
+                err = Some(E::InvalidTexture(ty.to_string(), e.to_string()));
+            }
+        });
+        if let Some(err) = err {
+            let mut d = Diagnostic::from(err);
+            d.detail.declaration = decl.ident().map(|id| id.to_string());
+            return Err(d);
+        }
+    }
+    Ok(())
+}
+
+/// Standard language extensions whose usage is tracked by [`required_extensions`].
+pub(crate) const TRACKED_EXTENSIONS: &[&str] =
+    &["f16", "clip_distances", "dual_source_blending", "subgroups"];
+
+/// The tracked language extensions used by each global declaration, as
+/// `(extension, usage, declaration)` triplets.
+pub(crate) fn required_extensions(wesl: &TranslationUnit) -> Vec<(&'static str, String, String)> {
+    let declared = wesl
+        .global_declarations
+        .iter()
+        .filter_map(|decl| decl.ident())
+        .map(|id| id.to_string())
+        .collect::<HashSet<_>>();
+
+    let mut res = Vec::new();
+    for decl in &wesl.global_declarations {
+        let name = decl.ident().map(|id| id.to_string()).unwrap_or_default();
+
+        Visit::<TypeExpression>::visit_rec(decl.node(), &mut |ty| {
+            let ty_name = ty.ident.name();
+            if declared.contains(&*ty_name) {
+                return;
+            }
+            if let Some(ext) = builtin_extension(&ty_name) {
+                res.push((ext, ty_name.to_string(), name.clone()));
+            }
+        });
+        fn f16_literals(expr: &Expression, lits: &mut Vec<String>) {
+            match expr {
+                Expression::Literal(lit @ LiteralExpression::F16(_)) => lits.push(lit.to_string()),
+                Expression::Parenthesized(e) => f16_literals(&e.expression, lits),
+                Expression::NamedComponent(e) => f16_literals(&e.base, lits),
+                Expression::Indexing(e) => {
+                    f16_literals(&e.base, lits);
+                    f16_literals(&e.index, lits);
+                }
+                Expression::Unary(e) => f16_literals(&e.operand, lits),
+                Expression::Binary(e) => {
+                    f16_literals(&e.left, lits);
+                    f16_literals(&e.right, lits);
+                }
+                Expression::FunctionCall(e) => {
+                    for arg in &e.arguments {
+                        f16_literals(arg, lits);
+                    }
+                }
+                _ => (),
+            }
+        }
+        let mut lits = Vec::new();
+        for expr in Visit::<ExpressionNode>::visit(decl.node()) {
+            f16_literals(expr, &mut lits);
+        }
+        res.extend(lits.into_iter().map(|lit| ("f16", lit, name.clone())));
+
+        let attrs = Visit::<Attributes>::visit(decl.node()).flatten();
+        let attrs = attrs.chain(match decl.node() {
+            GlobalDeclaration::Function(f) => f.return_attributes.iter(),
+            _ => [].iter(),
+        });
+        for attr in attrs {
+            let ext = match attr.node() {
+                Attribute::Builtin(BuiltinValue::ClipDistances) => "clip_distances",
+                Attribute::Builtin(
+                    BuiltinValue::SubgroupInvocationId | BuiltinValue::SubgroupSize,
+                ) => "subgroups",
+                #[cfg(feature = "naga-ext")]
+                Attribute::Builtin(BuiltinValue::SubgroupId | BuiltinValue::NumSubgroups) => {
+                    "subgroups"
+                }
+                Attribute::BlendSrc(_) => "dual_source_blending",
+                _ => continue,
+            };
+            res.push((ext, attr.to_string(), name.clone()));
+        }
+    }
+    res
+}
+
+/// The tracked language extension required by a built-in type or function name.
+fn builtin_extension(name: &str) -> Option<&'static str> {
+    let is_f16_alias = name.len() == 5 && name.starts_with("vec") && name.ends_with('h')
+        || name.len() == 7 && name.starts_with("mat") && name.ends_with('h');
+    if (name == "f16" || is_f16_alias) && builtin_ident(name).is_some() {
+        Some("f16")
+    } else if (name.starts_with("subgroup") || name.starts_with("quad"))
+        && BUILTIN_FUNCTION_NAMES.contains(&name)
+    {
+        Some("subgroups")
+    } else {
+        None
+    }
+}
+
+/// Check that the tracked language extensions used are enabled. See
+/// [`TRACKED_EXTENSIONS`].
+fn check_enables(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    let enabled = wesl
+        .global_directives
+        .iter()
+        .filter_map(|directive| match directive {
+            GlobalDirective::Enable(enable) => Some(enable.extensions.iter()),
+            _ => None,
+        })
+        .flatten()
+        .map(String::as_str)
+        .collect::<HashSet<_>>();
+
+    for (ext, usage, decl) in required_extensions(wesl) {
+        if !enabled.contains(ext) {
+            return Err(Diagnostic::from(E::MissingEnable(ext, usage)).with_declaration(decl));
+        }
+    }
+    Ok(())
+}
+
+/// Check the usage of `@blend_src` (dual-source blending):
+/// * it requires the `dual_source_blending` extension,
+/// * it is only allowed on struct members,
+/// * a struct using it has exactly two members with a `@location`, both `@location(0)`,
+///   one with `@blend_src(0)` and the other with `@blend_src(1)`, and of the same type.
+///
+/// Reference: <https://www.w3.org/TR/WGSL/#input-output-locations>
+fn check_blend_src(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    fn location(m: &StructMember) -> Option<&ExpressionNode> {
+        m.attributes.iter().find_map(|attr| match attr.node() {
+            Attribute::Location(expr) => Some(expr),
+            _ => None,
+        })
+    }
+    fn blend_src(m: &StructMember) -> Option<&ExpressionNode> {
+        m.attributes.iter().find_map(|attr| match attr.node() {
+            Attribute::BlendSrc(expr) => Some(expr),
+            _ => None,
+        })
+    }
+
+    let is_blend_src = |attr: &AttributeNode| matches!(attr.node(), Attribute::BlendSrc(_));
+    let enabled = wesl.global_directives.iter().any(|directive| {
+        matches!(directive, GlobalDirective::Enable(enable)
+            if enable.extensions.iter().any(|ext| ext == "dual_source_blending"))
+    });
+    // evaluating attributes is expensive, only do it if needed.
+    let mut folder = None;
+
+    for decl in &wesl.global_declarations {
+        let name = decl.ident().map(|id| id.to_string()).unwrap_or_default();
+        let err = |e: E| Diagnostic::from(e).with_declaration(name.clone());
+
+        match decl.node() {
+            GlobalDeclaration::Struct(s) => {
+                if !s
+                    .members
+                    .iter()
+                    .any(|m| m.attributes.iter().any(is_blend_src))
+                {
+                    continue;
+                }
+                if !enabled {
+                    return Err(err(E::MissingEnable("dual_source_blending", name.clone())));
+                }
+                let invalid = |reason| err(E::InvalidBlendSrc(name.clone(), reason));
+                let members = s
+                    .members
+                    .iter()
+                    .filter(|m| location(m).is_some())
+                    .collect::<Vec<_>>();
+                if s.members
+                    .iter()
+                    .any(|m| blend_src(m).is_some() && location(m).is_none())
+                {
+                    return Err(invalid("`@blend_src` requires a `@location`"));
+                }
+                let [m1, m2] = members.as_slice() else {
+                    return Err(invalid("expected exactly two members with a `@location`"));
+                };
+                let (Some(src1), Some(src2)) = (blend_src(m1), blend_src(m2)) else {
+                    return Err(invalid("both `@location` members require a `@blend_src`"));
+                };
+                let folder = folder.get_or_insert_with(|| Folder::new(wesl));
+                let locations = [location(m1), location(m2)].map(|expr| folder.fold(expr?));
+                if locations.iter().any(|loc| loc.is_some_and(|loc| loc != 0)) {
+                    return Err(invalid("both members must be `@location(0)`"));
+                }
+                let srcs = [folder.fold(src1), folder.fold(src2)];
+                if let [Some(src1), Some(src2)] = srcs {
+                    if !matches!((src1, src2), (0, 1) | (1, 0)) {
+                        return Err(invalid(
+                            "one member must be `@blend_src(0)` and the other `@blend_src(1)`",
+                        ));
+                    }
+                }
+                if canonical(&m1.ty, wesl).to_string() != canonical(&m2.ty, wesl).to_string() {
+                    return Err(invalid("both members must have the same type"));
+                }
+            }
+            GlobalDeclaration::Function(f) => {
+                let params = f.parameters.iter().flat_map(|p| p.attributes.iter());
+                if params.chain(&f.return_attributes).any(is_blend_src) {
+                    return Err(err(E::InvalidBlendSrc(
+                        name.clone(),
+                        "`@blend_src` is only allowed on struct members",
+                    )));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Check that override `@id`s are unique. Only the ids that can be evaluated are checked.
+fn check_override_ids(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    let has_id = wesl.global_declarations.iter().any(|decl| {
+        decl.attributes()
+            .iter()
+            .any(|attr| matches!(attr.node(), Attribute::Id(_)))
+    });
+    if !has_id {
+        return Ok(());
+    }
+
+    let mut used = HashSet::new();
+    for o in Overrides::from_syntax(wesl).iter() {
+        if let Some(id) = o.id {
+            if !used.insert(id) {
+                return Err(Diagnostic::from(E::DuplicateOverrideId(id, o.name.clone()))
+                    .with_declaration(o.name.clone()));
+            }
+        }
+    }
+    Ok(())
+}
+
 fn check_duplicate_decl(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
     let mut unique = HashSet::new();
 
@@ -271,10 +1037,13 @@ fn check_cycles(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
 /// * Duplicate declarations: declarations in the same scope cannot have the same name.
 ///   (except for unresolved conditional compilation)
 /// * Cyclic declarations: no cycles are allowed in declarations.
+/// * Attributes: attributes are applied to the right kind of declaration or statement,
+///   are not repeated or conflicting, and their literal arguments are in range.
 pub fn validate_wesl(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
     check_defined_symbols(wesl)?;
     check_duplicate_decl(wesl)?;
     check_cycles(wesl)?;
+    attrs::check_attributes(wesl)?;
     Ok(())
 }
 
@@ -286,10 +1055,550 @@ pub fn validate_wesl(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
 /// * Cyclic declarations: no cycles are allowed in declarations.
 /// * Function calls: call expressions must refer to a function or a type constructor.
 ///   Check the number of arguments but not their type.
+/// * Resources: push constants have no `@group` and `@binding`, `binding_array` is
+///   only used as the type of resource variables.
+/// * Enables: the usages of the `f16`, `clip_distances`, `dual_source_blending` and
+///   `subgroups` extensions are enabled.
+/// * Dual-source blending: `@blend_src` is enabled and follows the interface rules.
+/// * Overrides: the `@id`s of pipeline-overridable constants are unique.
+/// * Textures: the template parameters of texture types are a valid sampled type, or
+///   texel format and access mode.
+/// * Must-use calls: function call statements cannot discard the return value of a
+///   `@must_use` function, type constructor or value-returning built-in function.
+/// * Switch statements: exactly one `default` selector, distinct `case` selectors of
+///   the type of the `switch` expression.
+/// * Attributes: attributes are applied to the right kind of declaration or statement,
+///   are not repeated or conflicting, and their literal arguments are in range.
+/// * Barriers: `workgroupBarrier`, `storageBarrier`, `textureBarrier` and
+///   `workgroupUniformLoad` are only called by compute shaders.
+/// * Atomics: atomic built-in functions are called on atomic types, the result of
+///   `atomicCompareExchangeWeak` is not used as a scalar, and functions without a return
+///   type are not called in expressions.
 pub fn validate_wgsl(wgsl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
     check_defined_symbols(wgsl)?;
     check_duplicate_decl(wgsl)?;
     check_cycles(wgsl)?;
     check_function_calls(wgsl)?;
+    check_must_use(wgsl)?;
+    check_resources(wgsl)?;
+    check_enables(wgsl)?;
+    check_blend_src(wgsl)?;
+    check_override_ids(wgsl)?;
+    check_textures(wgsl)?;
+    check_switches(wgsl)?;
+    attrs::check_attributes(wgsl)?;
+    barriers::check_barriers(wgsl)?;
+    barriers::check_atomics(wgsl)?;
     Ok(())
 }
+
+/// Validate the final output with [`validate_wgsl`] and the portability checks of
+/// [`ValidationLevel::Pedantic`].
+pub(crate) fn validate_pedantic(wgsl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    validate_wgsl(wgsl)?;
+    barriers::check_uniformity(wgsl)?;
+    pedantic::check_pedantic(wgsl)
+}
+
+#[test]
+fn test_must_use() {
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_wgsl(&wgsl)
+    };
+    let is_must_use = |res: Result<(), Diagnostic<Error>>| matches!(res, Err(d) if matches!(*d.error, Error::ValidateError(E::MustUse(_))));
+    assert!(check("@must_use fn f() -> u32 { return 1u; } fn main() { _ = f(); }").is_ok());
+    assert!(is_must_use(check(
+        "@must_use fn f() -> u32 { return 1u; } fn main() { f(); }"
+    )));
+    assert!(is_must_use(check(
+        "fn main() { if true {} else { sin(1.0); } }"
+    )));
+    assert!(is_must_use(check(
+        "struct S { x: u32 } fn main() { S(1u); }"
+    )));
+    assert!(check("var<workgroup> a: atomic<u32>; fn main() { atomicAdd(&a, 1u); }").is_ok());
+    assert!(check("var<workgroup> a: atomic<u32>; fn main() { atomicStore(&a, 1u); }").is_ok());
+    assert!(check("fn main() { workgroupBarrier(); }").is_ok());
+    assert!(
+        check(
+            "@group(0) @binding(0) var t: texture_storage_2d<rgba8unorm, write>;
+            fn main() { textureStore(t, vec2u(0u), vec4f(0.0)); }"
+        )
+        .is_ok()
+    );
+    assert!(check("fn f() -> u32 { return 1u; } fn main() { f(); }").is_ok());
+}
+
+#[test]
+fn test_blend_src() {
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_wgsl(&wgsl).map_err(|d| *d.error)
+    };
+    let out =
+        "struct Out { @location(0) @blend_src(0) a: vec4f, @location(0) @blend_src(1) b: vec4f }";
+    assert!(check(&format!("enable dual_source_blending; {out}")).is_ok());
+    assert!(matches!(
+        check(out),
+        Err(Error::ValidateError(E::MissingEnable(..)))
+    ));
+    assert!(matches!(
+        check(
+            "enable dual_source_blending;
+            struct Out { @location(0) @blend_src(0) a: vec4f, @location(0) @blend_src(1) b: vec4f, @location(1) c: vec4f }"
+        ),
+        Err(Error::ValidateError(E::InvalidBlendSrc(..)))
+    ));
+    assert!(matches!(
+        check(
+            "enable dual_source_blending;
+            struct Out { @location(0) @blend_src(0) a: vec4f, @location(0) @blend_src(1) b: vec3f }"
+        ),
+        Err(Error::ValidateError(E::InvalidBlendSrc(..)))
+    ));
+    assert!(matches!(
+        check(
+            "enable dual_source_blending; @fragment fn f() -> @location(0) @blend_src(0) vec4f {}"
+        ),
+        Err(Error::ValidateError(E::InvalidBlendSrc(..)))
+    ));
+}
+
+#[test]
+fn test_aliases() {
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_wgsl(&wgsl).map_err(|d| *d.error)
+    };
+    assert!(
+        check(
+            "enable dual_source_blending;
+            alias Color = vec4<f32>;
+            struct Out { @location(0) @blend_src(0) a: Color, @location(0) @blend_src(1) b: vec4f }"
+        )
+        .is_ok()
+    );
+    assert!(matches!(
+        check("alias T = bool; @group(0) @binding(0) var t: texture_2d<T>;"),
+        Err(Error::ValidateError(E::InvalidTexture(..)))
+    ));
+    assert!(matches!(
+        check("alias A = B; alias B = A;"),
+        Err(Error::ValidateError(E::Cycle(..)))
+    ));
+}
+
+#[test]
+fn test_textures() {
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_wgsl(&wgsl).map_err(|d| *d.error)
+    };
+    assert!(
+        check(
+            "@group(0) @binding(0) var t: texture_storage_2d<r32float, read_write>;
+            fn f(t: texture_2d_array<i32>) {}"
+        )
+        .is_ok()
+    );
+    assert!(matches!(
+        check("@group(0) @binding(0) var t: texture_storage_2d<rgba8unorm, f32>;"),
+        Err(Error::ValidateError(E::InvalidTexture(_, _)))
+    ));
+    assert!(matches!(
+        check("fn f(t: texture_multisampled_2d<bool>) {}"),
+        Err(Error::ValidateError(E::InvalidTexture(_, _)))
+    ));
+}
+
+#[cfg(feature = "naga-ext")]
+#[test]
+fn test_resources() {
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_wgsl(&wgsl).map_err(|d| *d.error)
+    };
+    assert!(
+        check(
+            "var<push_constant> c: f32;
+            @group(0) @binding(0) var t: binding_array<texture_2d<f32>, 4>;"
+        )
+        .is_ok()
+    );
+    assert!(matches!(
+        check("@group(0) @binding(0) var<push_constant> c: f32;"),
+        Err(Error::ValidateError(E::PushConstantBinding(_)))
+    ));
+    assert!(matches!(
+        check("struct S { t: binding_array<sampler> }"),
+        Err(Error::ValidateError(E::InvalidBindingArray))
+    ));
+    assert!(matches!(
+        check("var<private> t: binding_array<f32, 2>;"),
+        Err(Error::ValidateError(E::InvalidBindingArray))
+    ));
+}
+
+#[cfg(feature = "naga-ext")]
+#[test]
+fn test_extensions() {
+    let ray_query = "@group(0) @binding(0) var acc: acceleration_structure;
+        fn trace() -> bool {
+            var rq: ray_query;
+            rayQueryInitialize(&rq, acc, RayDesc());
+            return rayQueryProceed(&rq);
+        }";
+    let enable = "enable ray_query;";
+    let mesh = "struct Out { @builtin(position) pos: vec4f, @per_primitive @location(0) c: vec4f }";
+    let check = |source: &str, extensions: &Extensions| {
+        let wesl = wgsl_parse::parse_str(source).unwrap();
+        check_extensions(&wesl, extensions).map_err(|d| *d.error)
+    };
+
+    let disabled = Extensions::default();
+    assert!(matches!(
+        check(ray_query, &disabled),
+        Err(Error::ValidateError(E::DisabledExtension("ray_query", _)))
+    ));
+    assert!(matches!(
+        check(enable, &disabled),
+        Err(Error::ValidateError(E::DisabledExtension("ray_query", _)))
+    ));
+    assert!(matches!(
+        check(mesh, &disabled),
+        Err(Error::ValidateError(E::DisabledExtension("mesh_shader", _)))
+    ));
+
+    let enabled = Extensions {
+        ray_query: true,
+        mesh_shader: true,
+    };
+    assert!(check(ray_query, &enabled).is_ok());
+    assert!(check(enable, &enabled).is_ok());
+    assert!(check(mesh, &enabled).is_ok());
+    // user declarations can shadow extension names.
+    assert!(check("struct RayDesc { x: f32 }", &disabled).is_ok());
+}
+
+#[test]
+fn test_enables() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_wgsl(&wgsl).map_err(|d| *d.error)
+    };
+    let is_missing =
+        |res, ext| matches!(res, Err(Error::ValidateError(E::MissingEnable(e, _))) if e == ext);
+    assert!(is_missing(check("const x = 1h;"), "f16"));
+    assert!(is_missing(check("fn f() { let x = -(2.0 * 1h); }"), "f16"));
+    assert!(is_missing(check("var<private> x: vec2h;"), "f16"));
+    assert!(is_missing(
+        check("struct S { @builtin(clip_distances) d: array<f32, 1> }"),
+        "clip_distances"
+    ));
+    assert!(is_missing(
+        check("@compute @workgroup_size(1) fn f(@builtin(subgroup_size) n: u32) {}"),
+        "subgroups"
+    ));
+    assert!(check("enable f16; const x = 1h;").is_ok());
+    assert!(check("alias vec2h = u32; var<private> x: vec2h;").is_ok());
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "enable f16, subgroups;
+        @if(half) fn half() -> f16 { return 1h; }
+        @compute @workgroup_size(1) fn main() {
+            @if(half) let x = half();
+        }"
+        .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions {
+        lower: true,
+        ..Default::default()
+    });
+    let compile = |compiler: &mut Wesl<VirtualResolver>, half| {
+        compiler.set_feature("half", half);
+        compiler
+            .compile(&"package::main".parse().unwrap())
+            .unwrap_or_else(|e| panic!("{e}"))
+            .syntax
+            .global_directives
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(compile(&mut compiler, true), ["enable f16;"]);
+    assert!(compile(&mut compiler, false).is_empty());
+}
+
+#[test]
+fn test_attributes() {
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_wgsl(&wgsl).map_err(|d| (d.error.to_string(), d.detail.span))
+    };
+    assert!(
+        check(
+            "struct S { @size(16) @align(16) x: f32 }
+            @group(0) @binding(0) var<uniform> s: S;
+            @compute @workgroup_size(8, 8) @diagnostic(off, derivative_uniformity)
+            fn main(@builtin(local_invocation_index) i: u32) {
+                @diagnostic(off, derivative_uniformity) { }
+                while true @diagnostic(warning, derivative_uniformity) { break; }
+            }"
+        )
+        .is_ok()
+    );
+
+    let source = "@group(0) @binding(0) @location(0) var<uniform> x: f32;";
+    let (msg, span) = check(source).unwrap_err();
+    assert_eq!(msg, "`@location` is not allowed on variables");
+    assert_eq!(&source[span.unwrap().range()], "@location(0)");
+    // duplicate and conflicting attributes point at both attributes.
+    let both = |source: &str| {
+        let mut wesl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wesl);
+        let d = validate_wesl(&wesl).unwrap_err();
+        let span = d.detail.span.unwrap();
+        let [label] = d.detail.labels.as_slice() else {
+            panic!("expected one label, got {:?}", d.detail.labels);
+        };
+        (
+            d.error.to_string(),
+            source[span.range()].to_string(),
+            source[label.span.range()].to_string(),
+            label.message.clone(),
+        )
+    };
+    assert_eq!(
+        both("@group(0) @group(1) @binding(0) var<uniform> x: f32;"),
+        (
+            "duplicate `@group` attribute".to_string(),
+            "@group(1)".to_string(),
+            "@group(0)".to_string(),
+            "first `@group` here".to_string()
+        )
+    );
+    assert_eq!(
+        both("@if(a) const x = 1; @if(b) @if(c) const x = 2;").1,
+        "@if(c)"
+    );
+    assert_eq!(
+        both("@vertex @compute fn main() {}"),
+        (
+            "`@compute` conflicts with `@vertex`".to_string(),
+            "@compute".to_string(),
+            "@vertex".to_string(),
+            "conflicting `@vertex` here".to_string()
+        )
+    );
+    assert_eq!(
+        both("fn f(@location(0) @builtin(position) p: vec4f) {}").0,
+        "`@builtin` conflicts with `@location`"
+    );
+    assert_eq!(
+        check("struct S { @align(3) x: f32 }").unwrap_err().0,
+        "invalid argument of `@align`: expected a positive power of two"
+    );
+    assert_eq!(
+        check("@compute @workgroup_size(1, 0) fn main() {}")
+            .unwrap_err()
+            .0,
+        "invalid argument of `@workgroup_size`: expected a positive integer"
+    );
+    assert_eq!(
+        check("fn f() { @must_use { } }").unwrap_err().0,
+        "`@must_use` is not allowed on compound and control flow statements"
+    );
+    assert!(check("fn f() { @diagnostic(off, derivative_uniformity) return; }").is_err());
+}
+
+#[test]
+fn test_switches() {
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_wgsl(&wgsl).map_err(|d| d.error.to_string())
+    };
+    assert!(
+        check(
+            "const C = 2;
+            fn f(x: i32) {
+                switch x { case 1, C: {} case -3, (4): {} default: {} }
+                switch 1u { case 1, default: {} }
+            }"
+        )
+        .is_ok()
+    );
+    assert!(
+        check("fn f(x: i32) { switch x { case 1: {} } }")
+            .unwrap_err()
+            .contains("exactly one `default` selector, found 0")
+    );
+    assert!(
+        check("fn f(x: i32) { switch x { default: {} case 1, default: {} } }")
+            .unwrap_err()
+            .contains("found 2")
+    );
+    assert!(
+        check("fn f(x: i32) { switch x { case 1, 1: {} default: {} } }")
+            .unwrap_err()
+            .contains("duplicate")
+    );
+    assert!(
+        check("fn f(x: i32) { switch x { case 1u: {} default: {} } }")
+            .unwrap_err()
+            .contains("u32")
+    );
+}
+
+#[test]
+fn test_barriers_and_atomics() {
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_wgsl(&wgsl).map_err(|d| d.error.to_string())
+    };
+    assert!(
+        check(
+            "var<workgroup> a: atomic<u32>;
+            struct S { x: array<atomic<i32>, 4> }
+            var<workgroup> s: S;
+            fn inc(p: ptr<workgroup, atomic<u32>>) -> u32 { return atomicAdd(p, 1u); }
+            @compute @workgroup_size(1) fn cs() {
+                workgroupBarrier();
+                _ = inc(&a) + atomicLoad(&s.x[0]);
+                _ = atomicCompareExchangeWeak(&a, 0u, 1u).old_value;
+            }"
+        )
+        .is_ok()
+    );
+    assert!(
+        check(
+            "fn sync() { storageBarrier(); }
+            @fragment fn fs() -> @location(0) vec4f { sync(); return vec4f(); }"
+        )
+        .unwrap_err()
+        .contains("`storageBarrier` can only be called in compute shaders, but is called by the fragment entry point `fs`")
+    );
+    let check_pedantic = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_pedantic(&wgsl).map_err(|d| d.error.to_string())
+    };
+    let divergent = "@compute @workgroup_size(1) fn cs(@builtin(local_invocation_index) i: u32) {
+        if i == 0u { workgroupBarrier(); }
+    }";
+    assert!(check(divergent).is_ok());
+    assert!(
+        check_pedantic(divergent)
+            .unwrap_err()
+            .contains("non-uniform control flow in `cs`")
+    );
+    // the result of a built-in function with uniform arguments is uniform.
+    let texture_load = "@group(0) @binding(0) var t: texture_2d<f32>;
+        @compute @workgroup_size(1) fn cs() {
+            if textureLoad(t, vec2i(0), 0).x > 0.5 { workgroupBarrier(); }
+        }";
+    assert!(check(texture_load).is_ok());
+    assert!(check_pedantic(texture_load).is_ok());
+    // a uniform re-assignment makes the variable uniform again.
+    let reassigned = "@compute @workgroup_size(1) fn cs(@builtin(local_invocation_id) lid: vec3u) {
+        var x = lid.x;
+        x = 0u;
+        if x == 0u { workgroupBarrier(); }
+    }";
+    assert!(check(reassigned).is_ok());
+    assert!(check_pedantic(reassigned).is_ok());
+    assert!(
+        check_pedantic(
+            "@compute @workgroup_size(1) fn cs(@builtin(local_invocation_id) lid: vec3u) {
+                var x = 0u;
+                x = lid.x;
+                if x == 0u { workgroupBarrier(); }
+            }"
+        )
+        .is_err()
+    );
+    assert!(
+        check("var<workgroup> a: u32; fn f() { atomicStore(&a, 1u); }")
+            .unwrap_err()
+            .contains("must point to an atomic type, found `u32`")
+    );
+    assert!(
+        check(
+            "var<workgroup> a: atomic<u32>;
+            fn f() { _ = atomicCompareExchangeWeak(&a, 0u, 1u) == 0u; }"
+        )
+        .unwrap_err()
+        .contains("cannot be used as an operand")
+    );
+    assert!(
+        check("fn g() {} fn f() { let x = g(); }")
+            .unwrap_err()
+            .contains("`g` does not return a value")
+    );
+}
+
+#[test]
+fn test_pedantic() {
+    let check = |source: &str| {
+        let mut wgsl = wgsl_parse::parse_str(source).unwrap();
+        crate::SyntaxUtil::retarget_idents(&mut wgsl);
+        validate_pedantic(&wgsl).map_err(|d| d.error.to_string())
+    };
+    assert_eq!(
+        check(
+            "enable f16;
+            const BIG: f32 = 16777216;
+            fn f(x: f32, i: i32) -> bool { let y: f16 = 0.5; return abs(x) < 1e-6 && i == 0; }"
+        ),
+        Ok(())
+    );
+    assert!(
+        check("fn f(x: f32) -> bool { return x == 0.0; }")
+            .unwrap_err()
+            .contains("compared with `==` in `f`")
+    );
+    assert!(
+        check("fn g() -> vec3f { return vec3f(); } fn f() { if any(g() != vec3(1.0)) {} }")
+            .unwrap_err()
+            .contains("compared with `!=`")
+    );
+    assert!(
+        check("const C: f32 = 16777217;")
+            .unwrap_err()
+            .contains("`16777217` is not representable as `f32` in the initializer of `C`")
+    );
+    assert!(
+        check("enable f16; fn f() { let x: f16 = -1e6; }")
+            .unwrap_err()
+            .contains("as `f16`")
+    );
+
+    let mut resolver = crate::VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "@fragment fn main(@location(0) x: f32) -> @location(0) vec4f {
+            if x == 1.0 { discard; }
+            return vec4f(x);
+        }"
+        .into(),
+    );
+    let mut compiler = crate::Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(crate::CompileOptions::default());
+    let root = "package::main".parse().unwrap();
+    assert!(compiler.compile(&root).is_ok());
+    compiler.set_validation_level(ValidationLevel::Pedantic);
+    assert!(compiler.compile(&root).is_err());
+}
@@ -0,0 +1,199 @@
+//! Portability checks of [`super::ValidationLevel::Pedantic`].
+
+use wgsl_parse::syntax::{
+    BinaryOperator, Declaration, Expression, ExpressionNode, Function, GlobalDeclaration,
+    LiteralExpression, Statement, StatementNode, TranslationUnit, TypeExpression, UnaryOperator,
+};
+
+use super::{E, canonical, ident_ty};
+use crate::{Diagnostic, Error, SyntaxUtil, visit::Visit};
+
+const FLOAT_TYPES: &[&str] = &[
+    "f32", "f16", "vec2f", "vec3f", "vec4f", "vec2h", "vec3h", "vec4h",
+];
+
+/// Whether the type is a floating-point scalar or vector type.
+fn is_float_ty(ty: &TypeExpression, wesl: &TranslationUnit) -> bool {
+    let ty = canonical(ty, wesl);
+    let name = ty.ident.name();
+    if FLOAT_TYPES.contains(&name.as_str()) {
+        return true;
+    }
+    matches!(name.as_str(), "vec2" | "vec3" | "vec4")
+        && ty
+            .template_args
+            .as_ref()
+            .and_then(|args| args.first())
+            .is_some_and(|arg| match arg.expression.node() {
+                Expression::TypeOrIdentifier(ty) => is_float_ty(ty, wesl),
+                _ => false,
+            })
+}
+
+/// Whether an expression in a function is known to have a floating-point type.
+fn is_float(expr: &Expression, f: &Function, wesl: &TranslationUnit) -> bool {
+    match expr {
+        Expression::Literal(lit) => matches!(
+            lit,
+            LiteralExpression::AbstractFloat(_)
+                | LiteralExpression::F32(_)
+                | LiteralExpression::F16(_)
+        ),
+        Expression::Parenthesized(paren) => is_float(&paren.expression, f, wesl),
+        Expression::Unary(unary) if unary.operator == UnaryOperator::Negation => {
+            is_float(&unary.operand, f, wesl)
+        }
+        Expression::Binary(binary) => {
+            matches!(
+                binary.operator,
+                BinaryOperator::Addition
+                    | BinaryOperator::Subtraction
+                    | BinaryOperator::Multiplication
+                    | BinaryOperator::Division
+                    | BinaryOperator::Remainder
+            ) && (is_float(&binary.left, f, wesl) || is_float(&binary.right, f, wesl))
+        }
+        Expression::FunctionCall(call) => match wesl.find_decl_by_ident(&call.ty.ident) {
+            Some(decl) => match decl.node() {
+                GlobalDeclaration::Function(callee) => callee
+                    .return_type
+                    .as_ref()
+                    .is_some_and(|ty| is_float_ty(ty, wesl)),
+                _ => is_float_ty(&call.ty, wesl),
+            },
+            None => is_float_ty(&call.ty, wesl),
+        },
+        Expression::TypeOrIdentifier(ty) if ty.template_args.is_none() => {
+            ident_ty(&ty.ident, f, wesl).is_some_and(|ty| is_float_ty(ty, wesl))
+        }
+        _ => false,
+    }
+}
+
+/// Whether an integer is exactly representable as a `f16`.
+fn is_exact_f16(value: i64) -> bool {
+    let abs = value.unsigned_abs();
+    abs == 0 || (abs <= 65504 && abs >> abs.trailing_zeros() < 2048)
+}
+
+/// The abstract literal initializer of a declaration, if it is not representable in
+/// the declared floating-point type.
+fn narrowed_literal(decl: &Declaration, wesl: &TranslationUnit) -> Option<(String, &'static str)> {
+    let ty = canonical(decl.ty.as_ref()?, wesl);
+    let ty = match ty.ident.name().as_str() {
+        "f32" => "f32",
+        "f16" => "f16",
+        _ => return None,
+    };
+    let init = decl.initializer.as_ref()?;
+    let (negative, lit) = match init.node() {
+        Expression::Literal(lit) => (false, lit),
+        Expression::Unary(unary) if unary.operator == UnaryOperator::Negation => {
+            match unary.operand.node() {
+                Expression::Literal(lit) => (true, lit),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let exact = match (lit, ty) {
+        (LiteralExpression::AbstractInt(i), "f32") => (*i as f32) as i64 == *i,
+        (LiteralExpression::AbstractInt(i), _) => is_exact_f16(*i),
+        (LiteralExpression::AbstractFloat(x), _) => {
+            let (max, min) = match ty {
+                "f32" => (f32::MAX as f64, f32::from_bits(1) as f64),
+                _ => (65504.0, 2f64.powi(-24)),
+            };
+            *x == 0.0 || (x.abs() <= max && x.abs() >= min)
+        }
+        _ => true,
+    };
+    (!exact).then(|| {
+        let sign = if negative {
+            "-"
+        } else {
+            ""
+        };
+        (format!("{sign}{lit}"), ty)
+    })
+}
+
+fn check_stat(
+    stat: &StatementNode,
+    f: &Function,
+    wesl: &TranslationUnit,
+) -> Result<(), Diagnostic<Error>> {
+    if let Statement::Declaration(decl) = stat.node() {
+        if let Some((lit, ty)) = narrowed_literal(decl, wesl) {
+            return Err(
+                Diagnostic::from(E::AbstractNarrowing(lit, decl.ident.to_string(), ty))
+                    .with_span(stat.span())
+                    .with_declaration(f.ident.to_string()),
+            );
+        }
+    }
+    for stat in Visit::<StatementNode>::visit(stat.node()) {
+        check_stat(stat, f, wesl)?;
+    }
+    Ok(())
+}
+
+fn check_expr(
+    expr: &ExpressionNode,
+    f: &Function,
+    wesl: &TranslationUnit,
+) -> Result<(), Diagnostic<Error>> {
+    if let Expression::Binary(binary) = expr.node() {
+        let is_equality = matches!(
+            binary.operator,
+            BinaryOperator::Equality | BinaryOperator::Inequality
+        );
+        if is_equality && (is_float(&binary.left, f, wesl) || is_float(&binary.right, f, wesl)) {
+            return Err(Diagnostic::from(E::FloatEquality(
+                binary.operator.to_string(),
+                f.ident.to_string(),
+            ))
+            .with_span(expr.span())
+            .with_declaration(f.ident.to_string()));
+        }
+    }
+    for expr in Visit::<ExpressionNode>::visit(expr.node()) {
+        check_expr(expr, f, wesl)?;
+    }
+    Ok(())
+}
+
+/// Portability checks:
+/// * Float equality: floating-point values are not compared with `==` or `!=`, since
+///   the result depends on the precision of the implementation.
+/// * Abstract narrowing: the abstract literal initializers of `f32` and `f16`
+///   declarations are representable in the declared type, i.e. abstract integers are
+///   exact and abstract floats do not overflow or underflow.
+pub(super) fn check_pedantic(wesl: &TranslationUnit) -> Result<(), Diagnostic<Error>> {
+    for decl in &wesl.global_declarations {
+        match decl.node() {
+            GlobalDeclaration::Declaration(d) => {
+                if let Some((lit, ty)) = narrowed_literal(d, wesl) {
+                    return Err(Diagnostic::from(E::AbstractNarrowing(
+                        lit,
+                        d.ident.to_string(),
+                        ty,
+                    ))
+                    .with_span(decl.span())
+                    .with_declaration(d.ident.to_string()));
+                }
+            }
+            GlobalDeclaration::Function(f) => {
+                for stat in &f.body.statements {
+                    check_stat(stat, f, wesl)?;
+                    // visits the expressions of nested statements too.
+                    for expr in Visit::<ExpressionNode>::visit(stat.node()) {
+                        check_expr(expr, f, wesl)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashSet};
+
+use wgsl_parse::syntax::ModulePath;
+
+use crate::{BindingPolicy, CompileResult, Features};
+
+/// A distinct output of [`crate::Wesl::compile_variants`], shared by one or more variants.
+#[derive(Clone)]
+pub struct VariantOutput {
+    pub result: CompileResult,
+    /// The hash of the output, see [`CompileResult::output_hash`].
+    pub hash: u64,
+    /// The indices of the variants producing this output, in increasing order.
+    pub variants: Vec<usize>,
+}
+
+/// The result of [`crate::Wesl::compile_variants`].
+///
+/// Variants with identical outputs, e.g. because they differ only by feature flags
+/// that do not affect the root module, share one [`VariantOutput`]: create one pipeline
+/// per output rather than one per variant.
+#[derive(Clone, Default)]
+pub struct VariantsResult {
+    /// The feature flags of the variants, in the order they were given.
+    pub variants: Vec<Features>,
+    /// The distinct outputs, in order of first appearance.
+    pub outputs: Vec<VariantOutput>,
+    /// The bindings of all the outputs, sorted by group and binding, with the outputs
+    /// that do not use them. Create one bind group layout with all of them to share it
+    /// between the outputs, see [`BindingPolicy::stable_variants`].
+    pub binding_layout: Vec<LayoutBinding>,
+}
+
+/// A binding of the layout shared by the variants. See
+/// [`VariantsResult::binding_layout`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutBinding {
+    pub group: u32,
+    pub binding: u32,
+    /// The names of the resources bound to it, in order of first appearance. Several
+    /// resources share the binding if variants explicitly bind different resources to it,
+    /// their types must then be compatible to share a layout.
+    pub resources: Vec<String>,
+    /// The indices in [`VariantsResult::outputs`] of the outputs that do not use the
+    /// binding: the holes of the layout.
+    pub holes: Vec<usize>,
+}
+
+impl VariantsResult {
+    /// The index in [`Self::outputs`] of the output of a variant.
+    ///
+    /// # Panics
+    /// Panics if `variant` is out of bounds.
+    pub fn output_index(&self, variant: usize) -> usize {
+        self.outputs
+            .iter()
+            .position(|out| out.variants.contains(&variant))
+            .expect("variant index out of bounds")
+    }
+
+    /// The compilation result of a variant.
+    ///
+    /// # Panics
+    /// Panics if `variant` is out of bounds.
+    pub fn get(&self, variant: usize) -> &CompileResult {
+        &self.outputs[self.output_index(variant)].result
+    }
+
+    /// The number of variants whose output is identical to the one of a previous variant.
+    pub fn duplicates(&self) -> usize {
+        self.variants.len() - self.outputs.len()
+    }
+}
+
+/// The bindings of all the outputs. See [`VariantsResult::binding_layout`].
+pub(crate) fn binding_layout(outputs: &[VariantOutput]) -> Vec<LayoutBinding> {
+    let mut layout = BTreeMap::<(u32, u32), Vec<String>>::new();
+    let slots = outputs
+        .iter()
+        .map(|out| {
+            let mut slots = HashSet::new();
+            for b in out.result.bindings().iter() {
+                let (Some(group), Some(binding)) = (b.group, b.binding) else {
+                    continue;
+                };
+                let names = layout.entry((group, binding)).or_default();
+                if !names.contains(&b.name) {
+                    names.push(b.name.clone());
+                }
+                slots.insert((group, binding));
+            }
+            slots
+        })
+        .collect::<Vec<_>>();
+    layout
+        .into_iter()
+        .map(|((group, binding), resources)| LayoutBinding {
+            group,
+            binding,
+            resources,
+            holes: (0..outputs.len())
+                .filter(|i| !slots[*i].contains(&(group, binding)))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Pin the resources moved by the binding policy in any of the outputs to one binding,
+/// that is free in all the outputs. See [`BindingPolicy::stable_variants`].
+///
+/// The bindings are assigned like [`crate::CompileOptions::binding_policy`] does, but
+/// with the resources of all the outputs.
+pub(crate) fn stable_pins(
+    outputs: &[VariantOutput],
+    policy: &BindingPolicy,
+) -> Vec<(ModulePath, String, (u32, u32))> {
+    let mut used = policy
+        .pinned
+        .iter()
+        .map(|(_, _, binding)| *binding)
+        .collect::<HashSet<_>>();
+    // (module, name, binding before collision resolution)
+    let mut moved = Vec::<(ModulePath, String, (u32, u32))>::new();
+    for out in outputs {
+        let res = &out.result;
+        for b in res.bindings().iter() {
+            let (Some(group), Some(binding)) = (b.group, b.binding) else {
+                continue;
+            };
+            let prov = res
+                .provenance_by_name(&b.name)
+                .filter(|prov| policy.pinned_binding(&prov.module, &prov.name).is_none());
+            let groups = prov.and_then(|prov| policy.groups_of(&prov.module));
+            let (Some(prov), Some(groups)) = (prov, groups) else {
+                used.insert((group, binding));
+                continue;
+            };
+            if moved
+                .iter()
+                .any(|(module, name, _)| *module == prov.module && *name == prov.name)
+            {
+                continue;
+            }
+            let from = (res.rebindings.iter())
+                .find(|r| r.name == b.name)
+                .map_or((group, binding), |r| r.from);
+            let to = (groups.start + from.0, from.1);
+            moved.push((prov.module.clone(), prov.name.clone(), to));
+        }
+    }
+    moved
+        .into_iter()
+        .map(|(module, name, (group, binding))| {
+            let binding = if used.contains(&(group, binding)) {
+                (0..).find(|b| !used.contains(&(group, *b))).unwrap()
+            } else {
+                binding
+            };
+            used.insert((group, binding));
+            (module, name, (group, binding))
+        })
+        .collect()
+}
+
+#[test]
+fn test_compile_variants() {
+    use crate::{CompileOptions, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::util::f;
+        @fragment fn main() -> @location(0) vec4f {
+            @if(fog) { return vec4f(0.5); }
+            return vec4f(f());
+        }"
+        .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "fn f() -> f32 { return 1.0; }
+        @if(shadows) fn g() {}"
+            .into(),
+    );
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler.set_options(CompileOptions::default());
+    let flags = ["fog", "shadows"].map(str::to_string);
+    let variants = Features::variants(&flags, None);
+    let res = compiler
+        .compile_variants(&"package::main".parse().unwrap(), &variants)
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    // `shadows` only affects an unused declaration.
+    assert_eq!(res.variants.len(), 4);
+    assert_eq!(res.outputs.len(), 2);
+    assert_eq!(res.duplicates(), 2);
+    for (i, variant) in variants.iter().enumerate() {
+        let fog = variant.flags["fog"] == crate::Feature::Enable;
+        assert_eq!(res.get(i).to_string().contains("0.5"), fog);
+    }
+    assert_eq!(res.outputs[0].variants, [0, 2]);
+}
+
+#[test]
+fn test_stable_bindings() {
+    use crate::{CompileOptions, Feature, ManglerKind, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::main".parse().unwrap(),
+        "import package::lights::shade;
+        @group(0) @binding(0) var<uniform> camera: vec4f;
+        @fragment fn main() -> @location(0) vec4f { return camera * shade(); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::lights".parse().unwrap(),
+        "@if(shadows) @group(0) @binding(0) var<uniform> shadow: vec4f;
+        @group(0) @binding(0) var<uniform> light: vec4f;
+        fn shade() -> vec4f {
+            @if(shadows) { return light * shadow; }
+            return light;
+        }"
+        .into(),
+    );
+    let mut policy = BindingPolicy {
+        reserved_groups: vec![("package::lights".parse().unwrap(), 1..2)],
+        ..Default::default()
+    };
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .set_mangler(ManglerKind::None)
+        .set_binding_policy(Some(policy.clone()));
+    let variants = Features::variants(&["shadows".to_string()], None);
+    let root = "package::main".parse().unwrap();
+    let light_binding = |res: &VariantsResult, i: usize| {
+        let bindings = res.get(i).bindings();
+        let light = bindings.iter().find(|b| b.name == "light").unwrap();
+        (light.group.unwrap(), light.binding.unwrap())
+    };
+    let shadows = |i: usize| variants[i].flags["shadows"] == Feature::Enable;
+    let (on, off) = match shadows(0) {
+        true => (0, 1),
+        false => (1, 0),
+    };
+
+    // the shadow resource takes the first binding of the group when it is compiled in.
+    let res = compiler
+        .compile_variants(&root, &variants)
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(light_binding(&res, on), (1, 1));
+    assert_eq!(light_binding(&res, off), (1, 0));
+
+    policy.stable_variants = true;
+    compiler.set_binding_policy(Some(policy));
+    let res = compiler
+        .compile_variants(&root, &variants)
+        .unwrap_or_else(|e| panic!("{e}"));
+    let light = light_binding(&res, on);
+    assert_eq!(light_binding(&res, off), light);
+    let layout = res
+        .binding_layout
+        .iter()
+        .map(|b| (b.group, b.binding, b.resources.join(","), b.holes.clone()))
+        .collect::<Vec<_>>();
+    // the binding of the shadow resource is a hole when it is compiled out.
+    let off = res.output_index(off);
+    let shadow = (1, 1 - light.1, "shadow".to_string(), vec![off]);
+    let light = (1, light.1, "light".to_string(), vec![]);
+    let mut expected = vec![(0, 0, "camera".to_string(), vec![]), shadow, light];
+    expected.sort();
+    assert_eq!(layout, expected);
+}
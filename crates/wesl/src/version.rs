@@ -0,0 +1,358 @@
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use itertools::Itertools;
+
+/// Error produced when parsing a [`Version`] or a [`VersionReq`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum VersionError {
+    #[error("invalid version `{0}`, expected `MAJOR.MINOR.PATCH`")]
+    Version(String),
+    #[error("invalid version requirement `{0}`")]
+    Requirement(String),
+}
+
+/// A semantic version of a package, e.g. `0.3.1` or `1.0.0-alpha.2`.
+///
+/// Build metadata (`+...`) is ignored. Versions are ordered following the semver
+/// precedence: pre-release versions compare lower than their release, and pre-release
+/// identifiers are compared one by one, numerically if they are numeric (which compare
+/// lower than alphanumeric identifiers), alphabetically otherwise.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// The pre-release identifiers, e.g. `alpha.2`. Empty for releases.
+    pub pre: String,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            pre: String::new(),
+        }
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => cmp_pre(&self.pre, &other.pre),
+            })
+    }
+}
+
+/// Compare pre-release identifiers, e.g. `alpha.2 < alpha.10 < beta`.
+fn cmp_pre(a: &str, b: &str) -> Ordering {
+    let numeric = |id: &str| {
+        id.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| id.parse::<u64>().ok())
+            .flatten()
+    };
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        let ord = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (numeric(a), numeric(b)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => a.cmp(b),
+            },
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Parse `MAJOR[.MINOR[.PATCH]]`.
+fn parse_partial(s: &str) -> Option<(u64, Option<u64>, Option<u64>)> {
+    let mut parts = s.split('.');
+    let mut part = || parts.next().map(|p| p.parse::<u64>().ok());
+    let major = part()??;
+    let minor = part().map(|p| p.ok_or(())).transpose().ok()?;
+    let patch = part().map(|p| p.ok_or(())).transpose().ok()?;
+    if parts.next().is_some() || (minor.is_none() && patch.is_some()) {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+impl FromStr for Version {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || VersionError::Version(s.to_string());
+        let s = s.trim();
+        let s = s.split_once('+').map_or(s, |(v, _)| v);
+        let (v, pre) = s.split_once('-').unwrap_or((s, ""));
+        match parse_partial(v) {
+            Some((major, Some(minor), Some(patch))) => Ok(Self {
+                major,
+                minor,
+                patch,
+                pre: pre.to_string(),
+            }),
+            _ => Err(err()),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+    Wildcard,
+}
+
+impl Op {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+            Op::Wildcard => "*",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Comparator {
+    op: Op,
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: String,
+}
+
+impl Comparator {
+    /// The smallest version matching all the specified components.
+    fn lower(&self) -> Version {
+        Version {
+            major: self.major,
+            minor: self.minor.unwrap_or(0),
+            patch: self.patch.unwrap_or(0),
+            pre: self.pre.clone(),
+        }
+    }
+
+    fn matches(&self, v: &Version) -> bool {
+        let prefix = |v: &Version| match (self.minor, self.patch) {
+            (None, _) => (v.major, 0, 0),
+            (Some(_), None) => (v.major, v.minor, 0),
+            (Some(_), Some(_)) => (v.major, v.minor, v.patch),
+        };
+        let this = (self.major, self.minor.unwrap_or(0), self.patch.unwrap_or(0));
+        match self.op {
+            Op::Exact if self.patch.is_some() => *v == self.lower(),
+            Op::Exact => prefix(v) == this,
+            Op::Greater if self.patch.is_some() => *v > self.lower(),
+            Op::Greater => prefix(v) > this,
+            Op::GreaterEq => *v >= self.lower(),
+            Op::Less => *v < self.lower(),
+            Op::LessEq if self.patch.is_some() => *v <= self.lower(),
+            Op::LessEq => prefix(v) <= this,
+            Op::Tilde => {
+                *v >= self.lower()
+                    && match self.minor {
+                        None => v.major == self.major,
+                        Some(minor) => (v.major, v.minor) == (self.major, minor),
+                    }
+            }
+            Op::Caret => {
+                *v >= self.lower()
+                    && match (self.major, self.minor, self.patch) {
+                        (0, None, _) => v.major == 0,
+                        (0, Some(0), None) => (v.major, v.minor) == (0, 0),
+                        (0, Some(0), Some(patch)) => (v.major, v.minor, v.patch) == (0, 0, patch),
+                        (0, Some(minor), _) => (v.major, v.minor) == (0, minor),
+                        (major, _, _) => v.major == major,
+                    }
+            }
+            Op::Wildcard => true,
+        }
+    }
+}
+
+impl Display for Comparator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.op == Op::Wildcard {
+            return write!(f, "*");
+        }
+        write!(f, "{}{}", self.op.as_str(), self.major)?;
+        if let Some(minor) = self.minor {
+            write!(f, ".{minor}")?;
+        }
+        if let Some(patch) = self.patch {
+            write!(f, ".{patch}")?;
+        }
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre)?;
+        }
+        Ok(())
+    }
+}
+
+/// A requirement on the version of a package, e.g. `>=0.3` or `^1.2, <1.5`.
+///
+/// The syntax is the one of Cargo: comma-separated comparators, each made of an
+/// operator (`=`, `>`, `>=`, `<`, `<=`, `~` or `^`) and a version with optional minor
+/// and patch components. A version without operator is a `^` requirement, and `*`
+/// matches any release.
+///
+/// Like Cargo, a pre-release version only matches if a comparator has a pre-release
+/// with the same major, minor and patch components, e.g. `<1.5` does not match
+/// `1.5.0-alpha`, but `>=1.5.0-alpha` matches `1.5.0-beta`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VersionReq(Vec<Comparator>);
+
+impl VersionReq {
+    /// Whether a version matches all the comparators of the requirement.
+    pub fn matches(&self, version: &Version) -> bool {
+        let pre_allowed = || {
+            self.0.iter().any(|c| {
+                !c.pre.is_empty()
+                    && (c.major, c.minor, c.patch)
+                        == (version.major, Some(version.minor), Some(version.patch))
+            })
+        };
+        self.0.iter().all(|c| c.matches(version)) && (version.pre.is_empty() || pre_allowed())
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || VersionError::Requirement(s.to_string());
+        let comparators = s
+            .split(',')
+            .map(|c| {
+                let c = c.trim();
+                if c == "*" {
+                    return Ok(Comparator {
+                        op: Op::Wildcard,
+                        major: 0,
+                        minor: None,
+                        patch: None,
+                        pre: String::new(),
+                    });
+                }
+                let (op, v) = [">=", "<=", "=", ">", "<", "~", "^"]
+                    .into_iter()
+                    .zip([
+                        Op::GreaterEq,
+                        Op::LessEq,
+                        Op::Exact,
+                        Op::Greater,
+                        Op::Less,
+                        Op::Tilde,
+                        Op::Caret,
+                    ])
+                    .find_map(|(prefix, op)| Some((op, c.strip_prefix(prefix)?)))
+                    .unwrap_or((Op::Caret, c));
+                let v = v.trim();
+                let (v, pre) = v.split_once('-').unwrap_or((v, ""));
+                let (major, minor, patch) = parse_partial(v).ok_or_else(err)?;
+                Ok(Comparator {
+                    op,
+                    major,
+                    minor,
+                    patch,
+                    pre: pre.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(comparators))
+    }
+}
+
+impl Display for VersionReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.iter().format(", "))
+    }
+}
+
+#[test]
+fn test_version_req() {
+    let matches = |req: &str, v: &str| {
+        let req = req.parse::<VersionReq>().unwrap();
+        req.matches(&v.parse().unwrap())
+    };
+    assert!(matches(">= 0.3", "0.3.0"));
+    assert!(matches(">=0.3", "1.2.0"));
+    assert!(!matches(">=0.3", "0.2.9"));
+    assert!(matches("0.3", "0.3.7"));
+    assert!(!matches("0.3", "0.4.0"));
+    assert!(matches("^1.2", "1.9.0"));
+    assert!(!matches("^1.2", "2.0.0"));
+    assert!(matches("~1.2.3", "1.2.9"));
+    assert!(!matches("~1.2.3", "1.3.0"));
+    assert!(matches("=1.2", "1.2.5"));
+    assert!(!matches(">1.2", "1.2.5"));
+    assert!(matches("<=1.2", "1.2.5"));
+    assert!(matches(">=1.0, <1.5", "1.4.0"));
+    assert!(!matches(">=1.0, <1.5", "1.5.0"));
+    assert!(!matches(">=1.0.0", "1.0.0-alpha.1"));
+    assert!(matches("*", "0.0.1"));
+
+    // pre-releases only match a requirement with a pre-release of the same version.
+    assert!(!matches("<1.5", "1.5.0-alpha"));
+    assert!(!matches("*", "1.5.0-alpha"));
+    assert!(matches(">=1.5.0-alpha, <1.5", "1.5.0-beta"));
+    assert!(!matches(">=1.5.0-alpha", "1.6.0-alpha"));
+    assert!(matches(">=1.5.0-alpha.2", "1.5.0-alpha.10"));
+    assert!(!matches(">=1.5.0-alpha.10", "1.5.0-alpha.2"));
+
+    // numeric identifiers compare numerically and lower than alphanumeric ones.
+    let version = |v: &str| v.parse::<Version>().unwrap();
+    assert!(version("1.0.0-alpha.2") < version("1.0.0-alpha.10"));
+    assert!(version("1.0.0-alpha") < version("1.0.0-alpha.1"));
+    assert!(version("1.0.0-alpha.1") < version("1.0.0-alpha.beta"));
+    assert!(version("1.0.0-2") < version("1.0.0-alpha"));
+    assert!(version("1.0.0-rc.1") < version("1.0.0"));
+    assert!(">=a".parse::<VersionReq>().is_err());
+    assert!("1.2".parse::<Version>().is_err());
+    assert_eq!(
+        ">= 0.3, <1".parse::<VersionReq>().unwrap().to_string(),
+        ">=0.3, <1"
+    );
+}
@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+
+use wgsl_parse::syntax::{
+    AccessMode, AddressSpace, DeclarationKind, Expression, ExpressionNode, GlobalDeclaration,
+    TranslationUnit, UnaryOperator,
+};
+
+use crate::{
+    Diagnostic, Error,
+    eval::{Context, Exec, Type, ty_eval_ty},
+    usage,
+    visit::Visit,
+};
+
+fn round_up(align: u32, size: u32) -> u32 {
+    size.div_ceil(align) * align
+}
+
+/// Whether a host-shareable type satisfies the layout constraints of the `uniform`
+/// address space, i.e. array strides and the offsets of struct and array members are
+/// multiples of 16 bytes. Runtime-sized arrays and atomics are not allowed.
+///
+/// Reference: <https://www.w3.org/TR/WGSL/#address-space-layout-constraints>
+fn uniform_layout(ty: &Type) -> bool {
+    match ty {
+        Type::Array(elem, Some(_)) => {
+            let (Some(size), Some(align)) = (elem.size_of(), elem.align_of()) else {
+                return false;
+            };
+            round_up(align, size).is_multiple_of(16) && uniform_layout(elem)
+        }
+        Type::Array(_, None) | Type::Atomic(_) => false,
+        Type::Struct(s) => {
+            let Some(layout) = s.layout() else {
+                return false;
+            };
+            let members = &layout.members;
+            members.iter().enumerate().all(|(i, m)| {
+                let is_struct = matches!(m.ty, Type::Struct(_));
+                let aligned =
+                    !(is_struct || matches!(m.ty, Type::Array(..))) || m.offset.is_multiple_of(16);
+                // the member following a struct member is at least 16-byte aligned after it.
+                let spaced = !is_struct
+                    || members
+                        .get(i + 1)
+                        .is_none_or(|next| next.offset - m.offset >= round_up(16, m.size));
+                aligned && spaced && uniform_layout(&m.ty)
+            })
+        }
+        _ => true,
+    }
+}
+
+/// The indices and types of the global variables for which `f` returns `true`, given
+/// their address space and access mode.
+fn find_vars(
+    wesl: &TranslationUnit,
+    f: impl Fn(AddressSpace, Option<AccessMode>) -> bool,
+) -> Result<Vec<(usize, Type)>, Error> {
+    let mut ctx = Context::new(wesl);
+    wesl.exec(&mut ctx)?;
+    let mut vars = Vec::new();
+    for (i, decl) in wesl.global_declarations.iter().enumerate() {
+        let GlobalDeclaration::Declaration(decl) = decl.node() else {
+            continue;
+        };
+        let (DeclarationKind::Var(Some((space, access))), Some(ty)) = (decl.kind, &decl.ty) else {
+            continue;
+        };
+        if f(space, access) {
+            let ty = ty_eval_ty(ty, &mut ctx).map_err(|e| Diagnostic::from(e).with_ctx(&ctx))?;
+            vars.push((i, ty));
+        }
+    }
+    Ok(vars)
+}
+
+/// The indices of the global variables whose address is taken, e.g. to pass them to a
+/// function with a `ptr<storage, T>` parameter. Their address space cannot change
+/// without changing the pointer types, which may also point to other variables.
+///
+/// Names are compared as strings, so a local variable shadowing a global variable
+/// counts too.
+fn address_taken(wesl: &TranslationUnit) -> HashSet<usize> {
+    fn rec(expr: &Expression, names: &mut HashSet<String>) {
+        match expr {
+            Expression::Unary(unary) if unary.operator == UnaryOperator::AddressOf => {
+                if let Some((id, _)) = usage::root(&unary.operand) {
+                    names.insert(id.to_string());
+                }
+            }
+            _ => (),
+        }
+        for expr in Visit::<ExpressionNode>::visit(expr) {
+            rec(expr, names);
+        }
+    }
+    let mut names = HashSet::new();
+    for expr in Visit::<ExpressionNode>::visit(wesl) {
+        rec(expr, &mut names);
+    }
+    (wesl.global_declarations.iter().enumerate())
+        .filter(|(_, decl)| decl.ident().is_some_and(|id| names.contains(&*id.name())))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Set the address space of a global variable, and remove its `@group` and `@binding`
+/// attributes if it is no longer a resource. Returns the variable name.
+fn set_address_space(wesl: &mut TranslationUnit, index: usize, space: AddressSpace) -> String {
+    let GlobalDeclaration::Declaration(decl) = wesl.global_declarations[index].node_mut() else {
+        unreachable!("expected a variable declaration")
+    };
+    decl.kind = DeclarationKind::Var(Some((space, None)));
+    #[cfg(feature = "naga-ext")]
+    if space == AddressSpace::PushConstant {
+        use wgsl_parse::syntax::Attribute;
+        decl.attributes
+            .retain(|attr| !matches!(attr.node(), Attribute::Group(_) | Attribute::Binding(_)));
+    }
+    decl.ident.to_string()
+}
+
+/// Move the small read-only `storage` buffers to the `uniform` address space, e.g. for
+/// vertex shaders in WebGPU compatibility mode (see [`crate::Target::Compat`]).
+///
+/// The buffers of at most `max_size` bytes are converted, if their type satisfies the
+/// layout constraints of the `uniform` address space: no runtime-sized arrays, no
+/// atomics, and array strides multiple of 16 bytes. Returns the names of the converted
+/// buffers. Bindings are unchanged, so the reflection of the result (see
+/// [`crate::Bindings::from_syntax`]) reports the new address space.
+///
+/// The buffers whose address is taken (e.g. passed to a function with a
+/// `ptr<storage, T>` parameter) are not converted.
+///
+/// Requires the `eval` crate feature flag.
+pub fn storage_to_uniform(wesl: &mut TranslationUnit, max_size: u32) -> Result<Vec<String>, Error> {
+    let vars = find_vars(wesl, |space, access| {
+        space == AddressSpace::Storage && matches!(access, None | Some(AccessMode::Read))
+    })?;
+    let taken = address_taken(wesl);
+    let names = vars
+        .into_iter()
+        .filter(|(i, ty)| {
+            ty.size_of().is_some_and(|size| size <= max_size)
+                && uniform_layout(ty)
+                && !taken.contains(i)
+        })
+        .map(|(i, _)| set_address_space(wesl, i, AddressSpace::Uniform))
+        .collect();
+    Ok(names)
+}
+
+/// Move a small `uniform` buffer to the `push_constant` address space, e.g. on native
+/// platforms. Its `@group` and `@binding` attributes are removed.
+///
+/// An entry point can use at most one push constant variable, so the first uniform
+/// buffer of at most `max_size` bytes whose address is not taken is converted, if there
+/// is no push constant variable yet. Returns the name of the converted buffer.
+///
+/// Requires the `eval` and `naga-ext` crate feature flags.
+#[cfg(feature = "naga-ext")]
+pub fn uniform_to_push_constant(
+    wesl: &mut TranslationUnit,
+    max_size: u32,
+) -> Result<Option<String>, Error> {
+    if !find_vars(wesl, |space, _| space == AddressSpace::PushConstant)?.is_empty() {
+        return Ok(None);
+    }
+    let vars = find_vars(wesl, |space, _| space == AddressSpace::Uniform)?;
+    let taken = address_taken(wesl);
+    let name = vars
+        .into_iter()
+        .find(|(i, ty)| ty.size_of().is_some_and(|size| size <= max_size) && !taken.contains(i))
+        .map(|(i, _)| set_address_space(wesl, i, AddressSpace::PushConstant));
+    Ok(name)
+}
+
+#[test]
+fn test_storage_to_uniform() {
+    use crate::Bindings;
+
+    let mut wesl = wgsl_parse::parse_str(
+        "struct Light { color: vec3f, intensity: f32 }
+        struct Lights { count: u32, lights: array<Light, 4> }
+        @group(0) @binding(0) var<storage> lights: Lights;
+        @group(0) @binding(1) var<storage> weights: array<f32, 4>;
+        @group(0) @binding(2) var<storage> data: array<vec4f>;
+        @group(0) @binding(3) var<storage, read_write> out: array<vec4f, 4>;
+        @group(0) @binding(4) var<storage> big: array<vec4f, 1024>;",
+    )
+    .unwrap();
+    let names = storage_to_uniform(&mut wesl, 4096).unwrap();
+    assert_eq!(names, ["lights"]);
+    let bindings = Bindings::from_syntax(&wesl);
+    let lights = bindings.get("lights").unwrap();
+    assert_eq!(lights.address_space, AddressSpace::Uniform);
+    assert_eq!((lights.group, lights.binding), (Some(0), Some(0)));
+    assert!(wesl.to_string().contains("var<uniform> lights: Lights;"));
+
+    let mut wesl = wgsl_parse::parse_str(
+        "struct Inner { x: f32 }
+        struct S { a: Inner, b: f32 }
+        @group(0) @binding(0) var<storage> s: S;",
+    )
+    .unwrap();
+    assert!(storage_to_uniform(&mut wesl, 4096).unwrap().is_empty());
+
+    // buffers passed by pointer keep their address space.
+    let mut wesl = wgsl_parse::parse_str(
+        "@group(0) @binding(0) var<storage> a: array<vec4f, 4>;
+        @group(0) @binding(1) var<storage> b: array<vec4f, 4>;
+        fn first(p: ptr<storage, array<vec4f, 4>>) -> vec4f { return p[0]; }
+        @fragment fn main() -> @location(0) vec4f { return first(&a) + b[0]; }",
+    )
+    .unwrap();
+    assert_eq!(storage_to_uniform(&mut wesl, 4096).unwrap(), ["b"]);
+    assert!(wesl.to_string().contains("var<storage> a"));
+}
+
+#[cfg(feature = "naga-ext")]
+#[test]
+fn test_uniform_to_push_constant() {
+    use crate::Bindings;
+
+    let source = "struct Params { scale: f32, offset: vec2f }
+        @group(0) @binding(0) var<uniform> camera: array<mat4x4f, 4>;
+        @group(0) @binding(1) var<uniform> params: Params;
+        @group(0) @binding(2) var<uniform> time: f32;";
+    let mut wesl = wgsl_parse::parse_str(source).unwrap();
+    let name = uniform_to_push_constant(&mut wesl, 128).unwrap();
+    assert_eq!(name.as_deref(), Some("params"));
+    let bindings = Bindings::from_syntax(&wesl);
+    let params = bindings.get("params").unwrap();
+    assert_eq!(params.address_space, AddressSpace::PushConstant);
+    assert_eq!((params.group, params.binding), (None, None));
+    assert!(
+        wesl.to_string()
+            .contains("var<push_constant> params: Params;")
+    );
+    // only one push constant variable.
+    assert_eq!(uniform_to_push_constant(&mut wesl, 128).unwrap(), None);
+}
@@ -93,18 +93,12 @@ macro_rules! impl_visit {
 
 impl_visit! { Expression => ExpressionNode,
     {
-        Expression::Parenthesized.expression.(x => recurse(x)),
-        Expression::NamedComponent.base.(x => recurse(x)),
-        Expression::Indexing.{
-            base.(x => recurse(x)),
-            index.(x => recurse(x)),
-        },
-        Expression::Unary.operand.(x => recurse(x)),
-        Expression::Binary.{
-            left.(x => recurse(x)),
-            right.(x => recurse(x)),
-        },
-        Expression::FunctionCall.arguments.[].(x => recurse(x)),
+        Expression::Parenthesized.expression,
+        Expression::NamedComponent.base,
+        Expression::Indexing.{ base, index },
+        Expression::Unary.operand,
+        Expression::Binary.{ left, right },
+        Expression::FunctionCall.arguments.[],
     }
 }
 
@@ -134,6 +128,7 @@ impl_visit! { Statement => Attributes,
         Statement::Compound.{ attributes, statements.[].(x => recurse(x)) },
         Statement::If.{
             attributes,
+            if_clause.body.statements.[].(x => recurse(x)),
             else_if_clauses.[].body.statements.[].(x => recurse(x)),
             else_clause.[].body.statements.[].(x => recurse(x)),
         },
@@ -310,7 +305,8 @@ impl_visit! { Statement => ExpressionNode,
             else_if_clauses.[].{
                 expression,
                 body.statements.[].(x => recurse(x)),
-            }
+            },
+            else_clause.[].body.statements.[].(x => recurse(x)),
         },
         Statement::Switch.{
             expression,
@@ -349,6 +345,7 @@ impl_visit! { Statement => StatementNode,
         Statement::If.{
             if_clause.body.statements.[],
             else_if_clauses.[].body.statements.[],
+            else_clause.[].body.statements.[],
         },
         Statement::Switch.clauses.[].body.statements.[],
         Statement::Loop.{
@@ -378,8 +375,9 @@ impl_visit! { Attribute => TypeExpression,
             y.[].(x => visit::<Expression, TypeExpression>(x)),
             z.[].(x => visit::<Expression, TypeExpression>(x)),
         },
-        #[cfg(feature = "generics")]
         Attribute::Type.variants.[],
+        Attribute::Mesh.(x => visit::<Expression, TypeExpression>(x)),
+        Attribute::Payload.(x => visit::<Expression, TypeExpression>(x)),
         Attribute::Custom.arguments.[].[].(x => visit::<Expression, TypeExpression>(x))
     }
 }
@@ -436,6 +434,7 @@ impl_visit! { GlobalDeclaration => Attributes,
         GlobalDeclaration::Function.{
             attributes,
             parameters.[].attributes,
+            return_attributes,
             body.{ attributes, statements.[].(x => visit::<Statement, Attributes>(x)) }
         },
         GlobalDeclaration::ConstAssert.attributes,
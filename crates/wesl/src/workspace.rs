@@ -0,0 +1,309 @@
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, path::PathBuf};
+
+use itertools::Itertools;
+use wgsl_parse::syntax::{
+    Attribute, Attributes, GlobalDeclaration, Ident, ImportContent, ModulePath, Statement,
+    StatementNode, TranslationUnit, TypeExpression,
+};
+
+use crate::{
+    Bindings, CompileOptions, CompileResult, Error, Mangler, ResolveError, Resolver, hooks::Hooks,
+    idents::builtin_ident, trace::span, visit::Visit,
+};
+
+/// The result of [`crate::compile_workspace`]: one [`CompileResult`] per root module.
+#[derive(Clone, Default)]
+pub struct WorkspaceResult {
+    /// The root modules and their compilation results, in the order of the roots.
+    pub results: Vec<(ModulePath, CompileResult)>,
+}
+
+impl WorkspaceResult {
+    /// Get the compilation result of a root module.
+    pub fn get(&self, root: &ModulePath) -> Option<&CompileResult> {
+        self.results
+            .iter()
+            .find(|(path, _)| path == root)
+            .map(|(_, res)| res)
+    }
+
+    /// Iterate over the root modules and their compilation results.
+    pub fn iter(&self) -> impl Iterator<Item = (&ModulePath, &CompileResult)> {
+        self.results.iter().map(|(path, res)| (path, res))
+    }
+
+    /// The modules used by at least one root, in order of first use.
+    pub fn modules(&self) -> Vec<&ModulePath> {
+        self.results
+            .iter()
+            .flat_map(|(_, res)| &res.modules)
+            .unique()
+            .collect()
+    }
+
+    /// The modules used by more than one root, in order of first use.
+    pub fn shared_modules(&self) -> Vec<&ModulePath> {
+        let counts = self
+            .results
+            .iter()
+            .flat_map(|(_, res)| &res.modules)
+            .counts();
+        self.modules()
+            .into_iter()
+            .filter(|path| counts[path] > 1)
+            .collect()
+    }
+
+    /// The resource bindings of all roots.
+    ///
+    /// Since mangling is consistent across roots, a resource declared in a shared module
+    /// has the same name and binding in each root and is listed once. Resources with the
+    /// same name at different bindings, e.g. declared in different roots, are all listed.
+    pub fn bindings(&self) -> Bindings {
+        self.results
+            .iter()
+            .flat_map(|(_, res)| res.bindings().into_inner())
+            .unique_by(|b| (b.group, b.binding, b.name.clone()))
+            .collect()
+    }
+}
+
+/// A resolver that loads each module once and returns copies of it.
+///
+/// The copies have fresh idents, so renaming the declarations of a copy (e.g. when
+/// mangling) does not affect the cached module.
+pub(crate) struct ModuleCache<R: Resolver> {
+    resolver: R,
+    modules: RefCell<HashMap<ModulePath, TranslationUnit>>,
+}
+
+impl<R: Resolver> ModuleCache<R> {
+    pub(crate) fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            modules: Default::default(),
+        }
+    }
+
+    pub(crate) fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
+    /// Discard the cached module, it is loaded again on the next resolution.
+    pub(crate) fn invalidate(&self, path: &ModulePath) {
+        self.modules.borrow_mut().remove(path);
+    }
+}
+
+impl<R: Resolver> Resolver for ModuleCache<R> {
+    fn resolve_source<'a>(&'a self, path: &ModulePath) -> Result<Cow<'a, str>, ResolveError> {
+        self.resolver.resolve_source(path)
+    }
+    fn resolve_module(&self, path: &ModulePath) -> Result<TranslationUnit, ResolveError> {
+        if let Some(wesl) = self.modules.borrow().get(path) {
+            let mut wesl = wesl.clone();
+            fresh_idents(&mut wesl);
+            return Ok(wesl);
+        }
+        let wesl = self.resolver.resolve_module(path)?;
+        let mut cached = wesl.clone();
+        fresh_idents(&mut cached);
+        self.modules.borrow_mut().insert(path.clone(), cached);
+        Ok(wesl)
+    }
+    fn display_name(&self, path: &ModulePath) -> Option<String> {
+        self.resolver.display_name(path)
+    }
+    fn fs_path(&self, path: &ModulePath) -> Option<PathBuf> {
+        self.resolver.fs_path(path)
+    }
+}
+
+/// Replace the idents of a translation unit with new idents.
+///
+/// Cloning a translation unit shares the idents of the clone with the original. This
+/// turns a clone into a deep copy. Idents that were shared in the original are shared
+/// in the copy too. Builtin idents are kept.
+pub(crate) fn fresh_idents(wesl: &mut TranslationUnit) {
+    let mut idents = HashMap::<Ident, Ident>::new();
+    let mut fresh = |id: &mut Ident| {
+        if builtin_ident(&id.name()) == Some(id) {
+            return;
+        }
+        *id = idents
+            .entry(id.clone())
+            .or_insert_with(|| Ident::new(id.to_string()))
+            .clone();
+    };
+
+    fn fresh_imports(content: &mut ImportContent, fresh: &mut impl FnMut(&mut Ident)) {
+        match content {
+            ImportContent::Item(item) => {
+                fresh(&mut item.ident);
+                if let Some(rename) = &mut item.rename {
+                    fresh(rename);
+                }
+            }
+            ImportContent::Collection(coll) => {
+                for import in coll {
+                    fresh_imports(&mut import.content, fresh);
+                }
+            }
+        }
+    }
+
+    fn fresh_stats<'a>(
+        stats: impl Iterator<Item = &'a mut StatementNode>,
+        fresh: &mut impl FnMut(&mut Ident),
+    ) {
+        for stat in stats {
+            if let Statement::Declaration(decl) = stat.node_mut() {
+                fresh(&mut decl.ident);
+            }
+            fresh_stats(Visit::<StatementNode>::visit_mut(stat.node_mut()), fresh);
+        }
+    }
+
+    for import in &mut wesl.imports {
+        fresh_imports(&mut import.content, &mut fresh);
+    }
+    for decl in &mut wesl.global_declarations {
+        if let Some(id) = decl.ident_mut() {
+            fresh(id);
+        }
+        match decl.node_mut() {
+            GlobalDeclaration::Struct(s) => s.members.iter_mut().for_each(|m| fresh(&mut m.ident)),
+            GlobalDeclaration::Function(f) => {
+                f.parameters.iter_mut().for_each(|p| fresh(&mut p.ident))
+            }
+            _ => (),
+        }
+    }
+    fresh_stats(Visit::<StatementNode>::visit_mut(wesl), &mut fresh);
+    for attrs in Visit::<Attributes>::visit_mut(wesl) {
+        for attr in attrs {
+            if let Attribute::Type(constraint) = attr.node_mut() {
+                fresh(&mut constraint.ident);
+            }
+        }
+    }
+    Visit::<TypeExpression>::visit_rec_mut(wesl, &mut |ty| fresh(&mut ty.ident));
+}
+
+/// Compile several root modules that share modules.
+///
+/// The shared modules are loaded (read, parsed, conditionally translated and run
+/// through the hooks) once, and mangled consistently: a declaration has the same name
+/// in each output. The [`CompileOptions::limits`] bound the whole workspace.
+pub(crate) fn compile_workspace(
+    roots: &[ModulePath],
+    resolver: &impl Resolver,
+    mangler: &impl Mangler,
+    options: &CompileOptions,
+    hooks: &Hooks,
+) -> Result<WorkspaceResult, Error> {
+    span!("compile_workspace", roots = roots.len());
+    let resolver = ModuleCache::new(crate::pre_assembly_resolver(resolver, options, hooks)?);
+    let results = roots
+        .iter()
+        .map(|root| {
+            span!("compile", root = %root);
+            let pre_assembly = crate::resolve_pre_assembly(root, &resolver, options, hooks)?;
+            let res = crate::compile_resolved(root, pre_assembly, &resolver, mangler, options)?;
+            Ok((root.clone(), res))
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok(WorkspaceResult { results })
+}
+
+#[test]
+fn test_compile_workspace() {
+    use crate::{SyntaxUtil, VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::a".parse().unwrap(),
+        "import package::util::{ scale, params };
+        @compute @workgroup_size(1) fn main() { let x = scale(params.x); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::b".parse().unwrap(),
+        "import package::util::scale;
+        @fragment fn main() -> @location(0) vec4f { return vec4f(scale(1.0)); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::c".parse().unwrap(),
+        "import package::util::scale;
+        @compute @workgroup_size(1) fn main() { let x = scale(2.0); }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::util".parse().unwrap(),
+        "struct Params { x: f32 }
+        @group(0) @binding(0) var<uniform> params: Params;
+        fn scale(x: f32) -> f32 { let y = x * 2.0; return y; }"
+            .into(),
+    );
+
+    let mut compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    compiler
+        .set_options(CompileOptions::default())
+        .set_mangler(crate::ManglerKind::Escape);
+    let roots = ["package::a", "package::b", "package::c"].map(|root| root.parse().unwrap());
+    let res = compiler
+        .compile_workspace(&roots)
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    for root in &roots {
+        let expected = compiler.compile(root).unwrap().to_string();
+        assert_eq!(res.get(root).unwrap().to_string(), expected);
+    }
+    assert_eq!(res.modules().len(), 4);
+    assert_eq!(
+        res.shared_modules(),
+        [&"package::util".parse::<ModulePath>().unwrap()]
+    );
+    assert_eq!(res.bindings().len(), 1);
+
+    // the shared declarations are mangled once, not once per root.
+    let scale = |res: &CompileResult| {
+        res.syntax
+            .functions()
+            .map(|f| f.ident.to_string())
+            .find(|name| name.contains("scale"))
+            .unwrap()
+    };
+    assert_eq!(scale(&res.results[0].1), scale(&res.results[2].1));
+}
+
+#[test]
+fn test_workspace_bindings() {
+    use crate::{VirtualResolver, Wesl};
+
+    let mut resolver = VirtualResolver::new();
+    resolver.add_module(
+        "package::a".parse().unwrap(),
+        "@group(0) @binding(0) var<uniform> u: f32;
+        @compute @workgroup_size(1) fn main() { let x = u; }"
+            .into(),
+    );
+    resolver.add_module(
+        "package::b".parse().unwrap(),
+        "@group(0) @binding(1) var<uniform> u: f32;
+        @compute @workgroup_size(1) fn main() { let x = u; }"
+            .into(),
+    );
+
+    let compiler = Wesl::new_barebones().set_custom_resolver(resolver);
+    let roots = ["package::a", "package::b"].map(|root| root.parse().unwrap());
+    let res = compiler
+        .compile_workspace(&roots)
+        .unwrap_or_else(|e| panic!("{e}"));
+    let bindings = res.bindings().into_inner();
+    assert_eq!(bindings.len(), 2);
+    assert!(bindings.iter().all(|b| b.name == "u"));
+    assert_eq!(bindings[0].binding, Some(0));
+    assert_eq!(bindings[1].binding, Some(1));
+}
@@ -15,6 +15,8 @@ pub enum ErrorKind {
     InvalidToken,
     #[error("use of a reserved word `{0}`")]
     ReservedWord(String),
+    #[error("`{0}` is not a valid identifier")]
+    InvalidIdent(String),
     #[error("unexpected token `{token}`, expected `{}`", .expected.iter().format(", "))]
     UnexpectedToken {
         token: String,
@@ -30,6 +32,10 @@ pub enum ErrorKind {
     Attribute(&'static str, &'static str),
     #[error("invalid `var` template arguments, {0}")]
     VarTemplate(&'static str),
+    #[error("attribute `{0}` does not take a string argument")]
+    StringArgument(String),
+    #[error("the `{0}` extension is disabled, see `ParseOptions`")]
+    Extension(&'static str),
 }
 
 #[derive(Default, Clone, Debug, PartialEq)]
@@ -40,9 +46,11 @@ pub enum ParseError {
     DiagnosticSeverity,
     Attribute(&'static str, &'static str),
     VarTemplate(&'static str),
+    StringArgument(String),
+    Extension(&'static str),
 }
 
-type LalrpopError = lalrpop_util::ParseError<usize, Token, (usize, ParseError, usize)>;
+pub(crate) type LalrpopError = lalrpop_util::ParseError<usize, Token, (usize, ParseError, usize)>;
 
 /// WGSL parse error.
 ///
@@ -108,6 +116,8 @@ impl From<LalrpopError> for Error {
                     ParseError::DiagnosticSeverity => ErrorKind::DiagnosticSeverity,
                     ParseError::Attribute(attr, expected) => ErrorKind::Attribute(attr, expected),
                     ParseError::VarTemplate(reason) => ErrorKind::VarTemplate(reason),
+                    ParseError::StringArgument(name) => ErrorKind::StringArgument(name),
+                    ParseError::Extension(name) => ErrorKind::Extension(name),
                 };
                 Self { span, error }
             }
@@ -203,6 +203,28 @@ fn parse_hex_f64(lex: &mut logos::Lexer<Token>) -> Option<f64> {
     lexical::parse_with_options::<f64, _, HEX_FORMAT>(str, options).ok()
 }
 
+// string literals are not WGSL. They are only allowed in some WESL attributes, e.g.
+// `@compile_error("message")`, and in import paths, e.g. `import package::"my-shader"::f;`.
+#[cfg(any(feature = "condcomp", feature = "imports"))]
+fn parse_string(lex: &mut logos::Lexer<Token>) -> Option<String> {
+    let str = lex.slice();
+    let mut res = String::new();
+    let mut chars = str[1..str.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next()? {
+                'n' => res.push('\n'),
+                't' => res.push('\t'),
+                c @ ('"' | '\\') => res.push(c),
+                _ => return None,
+            }
+        } else {
+            res.push(c);
+        }
+    }
+    Some(res)
+}
+
 fn parse_line_comment(lex: &mut logos::Lexer<Token>) {
     let rem = lex.remainder();
     // see blankspace and line breaks: https://www.w3.org/TR/WGSL/#blankspace-and-line-breaks
@@ -388,6 +410,17 @@ const RESERVED_WORDS: &[&str] = &[
     "yield",
 ];
 
+/// Returns `true` if `name` is a valid WGSL identifier: it is not a keyword or a reserved
+/// word and does not start with two underscores.
+///
+/// Reference: https://www.w3.org/TR/WGSL/#identifiers
+pub fn is_valid_ident(name: &str) -> bool {
+    let mut lex = Token::lexer_with_extras(name, LexerState::default());
+    !name.starts_with("__")
+        && matches!(lex.next(), Some(Ok(Token::Ident(_))))
+        && lex.span() == (0..name.len())
+}
+
 fn parse_ident(lex: &mut logos::Lexer<Token>) -> Token {
     let ident = lex.slice().to_string();
     if RESERVED_WORDS.iter().contains(&ident.as_str()) {
@@ -653,6 +686,11 @@ pub enum Token {
     #[cfg(feature = "imports")]
     #[token("import")]
     KwImport,
+
+    // extension: wesl user diagnostics and import path literals
+    #[cfg(any(feature = "condcomp", feature = "imports"))]
+    #[regex(r#""([^"\\\n]|\\.)*""#, parse_string)]
+    String(String),
 }
 
 impl Token {
@@ -870,6 +908,8 @@ impl Display for Token {
             Token::KwAs => write!(f, "as"),
             #[cfg(feature = "imports")]
             Token::KwImport => write!(f, "import"),
+            #[cfg(any(feature = "condcomp", feature = "imports"))]
+            Token::String(s) => write!(f, "string {s:?}"),
         }
     }
 }
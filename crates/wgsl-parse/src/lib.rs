@@ -16,5 +16,8 @@ mod tokrepr;
 pub use ::tokrepr::TokRepr;
 
 pub use error::Error;
-pub use parser::{parse_str, recognize_str};
+pub use parser::{
+    ParseOptions, parse_expression, parse_statement, parse_str, parse_str_with_options, parse_type,
+    recognize_str,
+};
 pub use syntax_impl::Decorated;
@@ -3,7 +3,10 @@ use std::str::FromStr;
 use crate::{
     error::Error,
     lexer::{Lexer, TokenIterator},
-    syntax::{Expression, GlobalDeclaration, GlobalDirective, Statement, TranslationUnit},
+    syntax::{
+        Expression, ExpressionNode, GlobalDeclaration, GlobalDirective, Statement, StatementNode,
+        TranslationUnit, TypeExpression,
+    },
 };
 
 use lalrpop_util::lalrpop_mod;
@@ -19,13 +22,106 @@ lalrpop_mod!(
 
 pub use wgsl::*;
 
+/// The language extensions accepted by the parser, on top of WGSL.
+///
+/// The syntax of an extension is compiled in the parser with its cargo feature, and the
+/// options select at runtime which of the compiled-in extensions are accepted. Enabling
+/// an extension whose cargo feature is disabled has no effect. Using a disabled
+/// extension is a parse error ([`ErrorKind::Extension`]), except for the extension
+/// attributes (e.g. `@if` or `@mesh`), which are parsed as custom attributes like when
+/// their cargo feature is disabled.
+///
+/// The default options accept all the extensions enabled by cargo features. Use
+/// [`ParseOptions::wgsl`] to parse strict WGSL.
+///
+/// [`ErrorKind::Extension`]: crate::error::ErrorKind::Extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// `import` statements, inline qualified paths and the `@publish` attribute
+    /// (`imports` cargo feature).
+    pub imports: bool,
+    /// Attributes on declarations and statements that have none in WGSL
+    /// (`attributes` cargo feature).
+    pub attributes: bool,
+    /// The `@if`, `@elif` and `@else` attributes and the user diagnostics attributes
+    /// (`condcomp` cargo feature).
+    pub condcomp: bool,
+    /// The `@type` attribute (`generics` cargo feature).
+    pub generics: bool,
+    /// The Naga attributes and 64-bit literals (`naga-ext` cargo feature).
+    pub naga_ext: bool,
+}
+
+#[allow(clippy::derivable_impls, reason = "not derivable with features")]
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            imports: cfg!(feature = "imports"),
+            attributes: cfg!(feature = "attributes"),
+            condcomp: cfg!(feature = "condcomp"),
+            generics: cfg!(feature = "generics"),
+            naga_ext: cfg!(feature = "naga-ext"),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Options that accept no extension: strict WGSL.
+    pub fn wgsl() -> Self {
+        Self {
+            imports: false,
+            attributes: false,
+            condcomp: false,
+            generics: false,
+            naga_ext: false,
+        }
+    }
+}
+
 /// Parse a string into a syntax tree ([`TranslationUnit`]).
 ///
-/// Identical to [`TranslationUnit::from_str`].
+/// Identical to [`TranslationUnit::from_str`]. Accepts the extensions enabled by cargo
+/// features, see [`parse_str_with_options`].
 pub fn parse_str(source: &str) -> Result<TranslationUnit, Error> {
+    parse_str_with_options(source, &ParseOptions::default())
+}
+
+/// Parse a string into a syntax tree ([`TranslationUnit`]), with the language extensions
+/// enabled in `options`.
+pub fn parse_str_with_options(
+    source: &str,
+    options: &ParseOptions,
+) -> Result<TranslationUnit, Error> {
     let lexer = Lexer::new(source);
     let parser = TranslationUnitParser::new();
-    parser.parse(lexer).map_err(Into::into)
+    parser.parse(options, lexer).map_err(Into::into)
+}
+
+/// Parse a string into an expression with its span ([`ExpressionNode`]).
+///
+/// Contrary to [`Expression::from_str`], the root node is spanned.
+pub fn parse_expression(source: &str) -> Result<ExpressionNode, Error> {
+    let lexer = Lexer::new(source);
+    let parser = ExpressionNodeParser::new();
+    parser.parse(&Default::default(), lexer).map_err(Into::into)
+}
+
+/// Parse a string into a statement with its span ([`StatementNode`]).
+///
+/// Contrary to [`Statement::from_str`], the root node is spanned.
+pub fn parse_statement(source: &str) -> Result<StatementNode, Error> {
+    let lexer = Lexer::new(source);
+    let parser = StatementNodeParser::new();
+    parser.parse(&Default::default(), lexer).map_err(Into::into)
+}
+
+/// Parse a string into a type ([`TypeExpression`]), e.g. `array<vec3f, 4>`.
+///
+/// Identical to [`TypeExpression::from_str`].
+pub fn parse_type(source: &str) -> Result<TypeExpression, Error> {
+    let lexer = Lexer::new(source);
+    let parser = TypeSpecifierParser::new();
+    parser.parse(&Default::default(), lexer).map_err(Into::into)
 }
 
 /// Test whether a string represent a valid WGSL module ([`TranslationUnit`]).
@@ -39,7 +135,10 @@ pub fn recognize_str(source: &str) -> Result<(), Error> {
 
 pub fn recognize_template_list(lexer: impl TokenIterator) -> Result<(), Error> {
     let parser = TryTemplateListParser::new();
-    parser.parse(lexer).map(|_| ()).map_err(Into::into)
+    parser
+        .parse(&Default::default(), lexer)
+        .map(|_| ())
+        .map_err(Into::into)
 }
 
 impl FromStr for TranslationUnit {
@@ -48,7 +147,7 @@ impl FromStr for TranslationUnit {
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
         let parser = TranslationUnitParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(&Default::default(), lexer).map_err(Into::into)
     }
 }
 impl FromStr for GlobalDirective {
@@ -57,7 +156,7 @@ impl FromStr for GlobalDirective {
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
         let parser = GlobalDirectiveParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(&Default::default(), lexer).map_err(Into::into)
     }
 }
 impl FromStr for GlobalDeclaration {
@@ -66,7 +165,7 @@ impl FromStr for GlobalDeclaration {
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
         let parser = GlobalDeclParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(&Default::default(), lexer).map_err(Into::into)
     }
 }
 impl FromStr for Statement {
@@ -75,7 +174,7 @@ impl FromStr for Statement {
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
         let parser = StatementParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(&Default::default(), lexer).map_err(Into::into)
     }
 }
 impl FromStr for Expression {
@@ -84,7 +183,14 @@ impl FromStr for Expression {
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
         let parser = ExpressionParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(&Default::default(), lexer).map_err(Into::into)
+    }
+}
+impl FromStr for TypeExpression {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        parse_type(source)
     }
 }
 #[cfg(feature = "imports")]
@@ -94,6 +200,50 @@ impl FromStr for crate::syntax::ImportStatement {
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         let lexer = Lexer::new(source);
         let parser = ImportStatementParser::new();
-        parser.parse(lexer).map_err(Into::into)
+        parser.parse(&Default::default(), lexer).map_err(Into::into)
     }
 }
+
+#[test]
+fn test_parse_snippets() {
+    let expr = parse_expression(" 1 + foo(2) ").unwrap();
+    assert_eq!(expr.span().range(), 1..11);
+    let stmt = parse_statement("let x = 1;").unwrap();
+    assert!(stmt.is_declaration());
+    let ty = parse_type("array<vec3f, 4>").unwrap();
+    assert_eq!(ty.to_string(), "array<vec3f, 4>");
+}
+
+#[cfg(all(feature = "imports", feature = "condcomp"))]
+#[test]
+fn test_parse_options() {
+    use crate::{Decorated, error::ErrorKind, syntax::Attribute};
+
+    let wgsl = ParseOptions::wgsl();
+    let extension = |source: &str, options: &ParseOptions| {
+        parse_str_with_options(source, options)
+            .map(|_| ())
+            .map_err(|e| e.error)
+    };
+    for (source, name) in [
+        ("import package::foo::bar;", "imports"),
+        ("fn f() { package::foo::bar(); }", "imports"),
+        ("fn f() { @if(true) let x = 1; }", "attributes"),
+        ("@compile_error(\"unsupported\") fn f() {}", "condcomp"),
+    ] {
+        assert_eq!(extension(source, &ParseOptions::default()), Ok(()));
+        assert_eq!(
+            extension(source, &wgsl),
+            Err(ErrorKind::Extension(name)),
+            "{source}"
+        );
+    }
+
+    // disabled extension attributes are custom attributes.
+    let wesl = parse_str_with_options("@if(true) fn f() {}", &wgsl).unwrap();
+    let attrs = wesl.global_declarations[0].attributes();
+    assert!(matches!(attrs[0].node(), Attribute::Custom(_)));
+    let wesl = parse_str("@if(true) fn f() {}").unwrap();
+    let attrs = wesl.global_declarations[0].attributes();
+    assert!(matches!(attrs[0].node(), Attribute::If(_)));
+}
@@ -6,12 +6,30 @@ use itertools::Itertools;
 
 use crate::{
     error::ParseError,
+    parser::ParseOptions,
     span::{Span, Spanned},
     syntax::*,
 };
 
 type E = ParseError;
 
+/// Attributes on declarations and statements that have no attributes in WGSL require the
+/// `attributes` extension.
+#[cfg(feature = "attributes")]
+pub(crate) fn ext_attributes(
+    options: &ParseOptions,
+    attributes: Vec<AttributeNode>,
+) -> Result<Vec<AttributeNode>, crate::error::LalrpopError> {
+    match attributes.first() {
+        Some(attr) if !options.attributes => {
+            let range = attr.span().range();
+            let error = (range.start, E::Extension("attributes"), range.end);
+            Err(lalrpop_util::ParseError::User { error })
+        }
+        _ => Ok(attributes),
+    }
+}
+
 pub(crate) enum Component {
     Named(Ident),
     Index(ExpressionNode),
@@ -66,8 +84,7 @@ fn zero_args(arguments: Option<Vec<ExpressionNode>>) -> bool {
 fn ident(expr: ExpressionNode) -> Option<Ident> {
     match expr.into_inner() {
         Expression::TypeOrIdentifier(TypeExpression {
-            #[cfg(feature = "imports")]
-                path: _,
+            path: _,
             ident,
             template_args: None,
         }) => Some(ident),
@@ -75,7 +92,19 @@ fn ident(expr: ExpressionNode) -> Option<Ident> {
     }
 }
 
+/// Extension attributes that are disabled in the [`ParseOptions`] are parsed as custom
+/// attributes.
 pub(crate) fn parse_attribute(
+    #[cfg_attr(
+        not(any(
+            feature = "imports",
+            feature = "condcomp",
+            feature = "generics",
+            feature = "naga-ext"
+        )),
+        allow(unused_variables)
+    )]
+    options: &ParseOptions,
     name: String,
     args: Option<Vec<ExpressionNode>>,
 ) -> Result<Attribute, E> {
@@ -111,8 +140,7 @@ pub(crate) fn parse_attribute(
                 let severity = ident(e1).and_then(|id| id.name().parse().ok());
                 let rule = match e2.into_inner() {
                     Expression::TypeOrIdentifier(TypeExpression {
-                        #[cfg(feature = "imports")]
-                            path: _,
+                        path: _,
                         ident,
                         template_args: None,
                     }) => Some(ident.name().to_string()),
@@ -211,26 +239,26 @@ pub(crate) fn parse_attribute(
             false => Err(E::Attribute("compute", "expected 0 arguments")),
         },
         #[cfg(feature = "imports")]
-        "publish" => Ok(Attribute::Publish),
+        "publish" if options.imports => Ok(Attribute::Publish),
         #[cfg(feature = "condcomp")]
-        "if" => match one_arg(args) {
+        "if" if options.condcomp => match one_arg(args) {
             Some(expr) => Ok(Attribute::If(expr)),
             None => Err(E::Attribute("if", "expected 1 argument")),
         },
         #[cfg(feature = "condcomp")]
-        "elif" => match one_arg(args) {
+        "elif" if options.condcomp => match one_arg(args) {
             Some(expr) => Ok(Attribute::Elif(expr)),
             None => Err(E::Attribute("elif", "expected 1 argument")),
         },
         #[cfg(feature = "condcomp")]
-        "else" => match zero_args(args) {
+        "else" if options.condcomp => match zero_args(args) {
             true => Ok(Attribute::Else),
             false => Err(E::Attribute("else", "expected 0 arguments")),
         },
         #[cfg(feature = "generics")]
-        "type" => parse_attr_type(args).map(Attribute::Type),
+        "type" if options.generics => parse_attr_type(args).map(Attribute::Type),
         #[cfg(feature = "naga-ext")]
-        "early_depth_test" => match args {
+        "early_depth_test" if options.naga_ext => match args {
             Some(args) => {
                 let mut it = args.into_iter();
                 match (it.next(), it.next()) {
@@ -253,6 +281,34 @@ pub(crate) fn parse_attribute(
                 "expected 0 or 1 arguments",
             )),
         },
+        #[cfg(feature = "condcomp")]
+        "compile_error" if options.condcomp => {
+            Err(E::Attribute("compile_error", "expected a string argument"))
+        }
+        #[cfg(feature = "condcomp")]
+        "compile_warn" if options.condcomp => {
+            Err(E::Attribute("compile_warn", "expected a string argument"))
+        }
+        #[cfg(feature = "naga-ext")]
+        "task" if options.naga_ext => match zero_args(args) {
+            true => Ok(Attribute::Task),
+            false => Err(E::Attribute("task", "expected 0 arguments")),
+        },
+        #[cfg(feature = "naga-ext")]
+        "mesh" if options.naga_ext => match one_arg(args) {
+            Some(expr) => Ok(Attribute::Mesh(expr)),
+            _ => Err(E::Attribute("mesh", "expected 1 argument")),
+        },
+        #[cfg(feature = "naga-ext")]
+        "payload" if options.naga_ext => match one_arg(args) {
+            Some(expr) => Ok(Attribute::Payload(expr)),
+            _ => Err(E::Attribute("payload", "expected 1 argument")),
+        },
+        #[cfg(feature = "naga-ext")]
+        "per_primitive" if options.naga_ext => match zero_args(args) {
+            true => Ok(Attribute::PerPrimitive),
+            false => Err(E::Attribute("per_primitive", "expected 0 arguments")),
+        },
         _ => Ok(Attribute::Custom(CustomAttribute {
             name,
             arguments: args,
@@ -260,6 +316,23 @@ pub(crate) fn parse_attribute(
     }
 }
 
+#[cfg(feature = "condcomp")]
+pub(crate) fn parse_string_attribute(
+    options: &ParseOptions,
+    name: String,
+    message: String,
+) -> Result<Attribute, E> {
+    if !options.condcomp {
+        return Err(E::Extension("condcomp"));
+    }
+    match name.as_str() {
+        "compile_error" => Ok(Attribute::CompileError(message)),
+        "compile_warn" => Ok(Attribute::CompileWarn(message)),
+        "slot" => Ok(Attribute::Slot(message)),
+        _ => Err(E::StringArgument(name)),
+    }
+}
+
 // format: @type(T, foo | bar | baz)
 #[cfg(feature = "generics")]
 fn parse_attr_type(arguments: Option<Vec<ExpressionNode>>) -> Result<TypeConstraint, E> {
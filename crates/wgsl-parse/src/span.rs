@@ -2,9 +2,9 @@ use std::ops::Range;
 
 use derive_more::derive::{AsMut, AsRef, Deref, DerefMut, From};
 
+/// Identifier of a source, see [`Span::source`].
 pub type Id = u32;
 
-#[cfg_attr(feature = "tokrepr", derive(tokrepr::TokRepr))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Span {
@@ -12,22 +12,58 @@ pub struct Span {
     pub start: usize,
     /// The upper bound of the span (exclusive).
     pub end: usize,
+    /// See [`Span::source`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub(crate) source: Option<Id>,
 }
-// Deref, DerefMut, AsRef, AsMut
+
 impl Span {
     pub fn new(range: Range<usize>) -> Self {
         Self {
             start: range.start,
             end: range.end,
+            source: None,
         }
     }
     pub fn range(&self) -> Range<usize> {
         self.start..self.end
     }
+    /// The source the byte range refers to, when syntax trees from several sources are
+    /// merged. `None` refers to the source the syntax tree was parsed from.
+    ///
+    /// The WESL compiler sets it to the index of the module in its list of modules.
+    pub fn source(&self) -> Option<Id> {
+        self.source
+    }
+    pub fn with_source(self, source: Id) -> Self {
+        Self {
+            source: Some(source),
+            ..self
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+    /// Whether the span is the default span, e.g. for a generated syntax node.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+    /// Extend the span up to the end of `other`.
     pub fn extend(&self, other: Span) -> Self {
         Self {
-            start: self.start,
             end: other.end,
+            ..*self
+        }
+    }
+    /// The smallest span containing both spans. The source of `self` is kept.
+    pub fn union(&self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+            source: self.source.or(other.source),
         }
     }
 }
@@ -41,6 +77,11 @@ impl From<Range<usize>> for Span {
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Default, Clone, Debug, Deref, DerefMut, AsRef, AsMut, From)]
 pub struct Spanned<T> {
+    // spans are optional in serialized syntax trees.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Span::is_default")
+    )]
     span: Span,
     #[deref(forward)]
     #[deref_mut(forward)]
@@ -57,16 +98,33 @@ impl<T: PartialEq> PartialEq for Spanned<T> {
     }
 }
 
+impl<T: Eq> Eq for Spanned<T> {}
+
 impl<T> Spanned<T> {
-    pub fn new(node: T, span: Span) -> Self {
+    /// The span can be a [`Span`] or a byte range, e.g. `Spanned::new(node, 0..4)`.
+    pub fn new(node: T, span: impl Into<Span>) -> Self {
         Self::new_boxed(Box::new(node), span)
     }
-    pub fn new_boxed(node: Box<T>, span: Span) -> Self {
-        Self { span, node }
+    pub fn new_boxed(node: Box<T>, span: impl Into<Span>) -> Self {
+        Self {
+            span: span.into(),
+            node,
+        }
     }
     pub fn span(&self) -> Span {
         self.span
     }
+    pub fn set_span(&mut self, span: impl Into<Span>) {
+        self.span = span.into();
+    }
+    pub fn with_span(mut self, span: impl Into<Span>) -> Self {
+        self.set_span(span);
+        self
+    }
+    /// The smallest span containing both nodes.
+    pub fn span_union<U>(&self, other: &Spanned<U>) -> Span {
+        self.span.union(other.span)
+    }
     pub fn node(&self) -> &T {
         self
     }
@@ -76,6 +134,21 @@ impl<T> Spanned<T> {
     pub fn into_inner(self) -> T {
         *self.node
     }
+    pub fn into_parts(self) -> (T, Span) {
+        (*self.node, self.span)
+    }
+    /// Transform the node, keeping the span.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned::new(f(*self.node), self.span)
+    }
+    /// Transform the node, keeping the span.
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<Spanned<U>, E> {
+        Ok(Spanned::new(f(*self.node)?, self.span))
+    }
+    /// Borrow the node, keeping the span.
+    pub fn as_spanned_ref(&self) -> Spanned<&T> {
+        Spanned::new(self.node(), self.span)
+    }
 }
 
 impl<T> From<T> for Spanned<T> {
@@ -86,3 +159,9 @@ impl<T> From<T> for Spanned<T> {
         }
     }
 }
+
+impl<T> From<(T, Range<usize>)> for Spanned<T> {
+    fn from((node, range): (T, Range<usize>)) -> Self {
+        Self::new(node, range)
+    }
+}
@@ -21,10 +21,12 @@
 //! The parsing is not designed to be primarily efficient, but flexible and correct.
 //! It is made with the ultimate goal to implement spec-compliant language extensions.
 
-use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::sync::{Arc, PoisonError, RwLock};
 
 use derive_more::{From, IsVariant, Unwrap};
 
+use crate::error::ErrorKind;
+
 pub use crate::span::{Span, Spanned};
 
 pub use wgsl_types::syntax::*;
@@ -39,7 +41,6 @@ use serde::{Deserialize, Serialize};
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct TranslationUnit {
-    #[cfg(feature = "imports")]
     pub imports: Vec<ImportStatement>,
     pub global_directives: Vec<GlobalDirective>,
     pub global_declarations: Vec<GlobalDeclarationNode>,
@@ -51,23 +52,55 @@ pub struct TranslationUnit {
 /// * Can be [renamed][Self::rename] (with interior mutability)
 /// * References to the same Ident can be [counted][Self::use_count]
 /// * Equality and Hash compares the reference, NOT the internal string value
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, Debug)]
-pub struct Ident(Arc<RwLock<String>>);
+///
+/// # Concurrency
+///
+/// Idents are `Send` and `Sync`, e.g. to compile several shaders in parallel.
+/// * [`Self::name`] returns a snapshot of the name. No lock is held once it returns, so
+///   holding a name never blocks or deadlocks a rename, even on the same thread.
+/// * [`Self::rename`] replaces the name atomically: readers see the old or the new
+///   name, never a partial one. A panic while renaming cannot poison the ident.
+/// * Concurrent renames of the same ident are not ordered: one of them wins. Passes
+///   running in parallel must either own disjoint idents (e.g. one syntax tree per
+///   thread, not sharing idents with the others), or collect their renames in a
+///   [`Renames`] queue and apply them after the parallel section.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct Ident(Arc<RwLock<Arc<String>>>);
 
 impl Ident {
     /// Create a new Ident
     pub fn new(name: String) -> Ident {
         // TODO: check that the name is a valid ident
-        Ident(Arc::new(RwLock::new(name)))
+        Ident(Arc::new(RwLock::new(Arc::new(name))))
     }
-    /// Get the name of the Ident
-    pub fn name(&self) -> RwLockReadGuard<'_, String> {
-        self.0.read().unwrap()
+    /// Get the name of the Ident: a snapshot, not affected by later renames.
+    ///
+    /// A name obtained before a [`Self::rename`] or a [`Renames::apply`] is stale: it
+    /// keeps the old name. Call `name()` again after renaming to read the new name.
+    pub fn name(&self) -> Arc<String> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
     /// Rename all shared instances of the ident
     pub fn rename(&mut self, name: String) {
-        *self.0.write().unwrap() = name;
+        // the lock is only held to swap the pointer, so it is never poisoned in a
+        // partially renamed state.
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(name);
+    }
+    /// Rename all shared instances of the ident, if `name` is a valid WGSL identifier.
+    ///
+    /// Contrary to [`Self::rename`], renaming to a keyword or a reserved word fails with
+    /// [`ErrorKind::InvalidIdent`]. See [`crate::lexer::is_valid_ident`].
+    pub fn try_rename(&mut self, name: String) -> Result<(), ErrorKind> {
+        if crate::lexer::is_valid_ident(&name) {
+            self.rename(name);
+            Ok(())
+        } else {
+            Err(ErrorKind::InvalidIdent(name))
+        }
     }
     /// Count shared instances of the ident
     pub fn use_count(&self) -> usize {
@@ -75,12 +108,54 @@ impl Ident {
     }
 }
 
+// the lock is an implementation detail, only the name is printed.
+impl std::fmt::Debug for Ident {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Ident").field(&self.name()).finish()
+    }
+}
+
 impl From<String> for Ident {
     fn from(name: String) -> Self {
         Ident::new(name)
     }
 }
 
+/// A queue of renames, applied together with [`Self::apply`], e.g. once the threads
+/// of a parallel pass are joined. See [`Ident`] for the concurrency guarantees.
+///
+/// Renames are applied in the order they were queued: if an ident is renamed several
+/// times, the last rename wins. Queues filled by different threads can be merged with
+/// [`Self::append`] in a deterministic order.
+#[derive(Clone, Debug, Default)]
+pub struct Renames(Vec<(Ident, String)>);
+
+impl Renames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Queue the renaming of all shared instances of an ident.
+    pub fn push(&mut self, ident: &Ident, name: String) {
+        self.0.push((ident.clone(), name));
+    }
+    /// Move the renames of another queue after the renames of this queue.
+    pub fn append(&mut self, other: &mut Renames) {
+        self.0.append(&mut other.0);
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Apply the renames, in order.
+    pub fn apply(self) {
+        for (mut ident, name) in self.0 {
+            ident.rename(name);
+        }
+    }
+}
+
 /// equality for idents is based on address, NOT internal value
 impl PartialEq for Ident {
     fn eq(&self, other: &Self) -> bool {
@@ -98,18 +173,15 @@ impl std::hash::Hash for Ident {
     }
 }
 
-#[cfg(feature = "imports")]
 #[cfg_attr(feature = "tokrepr", derive(TokRepr))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct ImportStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub path: Option<ModulePath>,
     pub content: ImportContent,
 }
 
-#[cfg(feature = "imports")]
 #[cfg_attr(feature = "tokrepr", derive(TokRepr))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, Eq, Hash, IsVariant)]
@@ -122,7 +194,6 @@ pub enum PathOrigin {
     Package(String),
 }
 
-#[cfg(feature = "imports")]
 #[cfg_attr(feature = "tokrepr", derive(TokRepr))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -131,7 +202,6 @@ pub struct ModulePath {
     pub components: Vec<String>,
 }
 
-#[cfg(feature = "imports")]
 #[cfg_attr(feature = "tokrepr", derive(TokRepr))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
@@ -140,7 +210,6 @@ pub struct Import {
     pub content: ImportContent,
 }
 
-#[cfg(feature = "imports")]
 #[cfg_attr(feature = "tokrepr", derive(TokRepr))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, IsVariant)]
@@ -149,7 +218,6 @@ pub enum ImportContent {
     Collection(Vec<Import>),
 }
 
-#[cfg(feature = "imports")]
 #[cfg_attr(feature = "tokrepr", derive(TokRepr))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
@@ -171,7 +239,6 @@ pub enum GlobalDirective {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct DiagnosticDirective {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub severity: DiagnosticSeverity,
     pub rule_name: String,
@@ -181,7 +248,6 @@ pub struct DiagnosticDirective {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct EnableDirective {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub extensions: Vec<String>,
 }
@@ -190,7 +256,6 @@ pub struct EnableDirective {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct RequiresDirective {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub extensions: Vec<String>,
 }
@@ -234,7 +299,6 @@ pub enum DeclarationKind {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct TypeAlias {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub ident: Ident,
     pub ty: TypeExpression,
@@ -244,7 +308,6 @@ pub struct TypeAlias {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Struct {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub ident: Ident,
     pub members: Vec<StructMemberNode>,
@@ -286,7 +349,6 @@ pub struct FormalParameter {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct ConstAssert {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub expression: ExpressionNode,
 }
@@ -349,26 +411,28 @@ pub enum Attribute {
     Vertex,
     Fragment,
     Compute,
-    #[cfg(feature = "imports")]
     Publish,
-    #[cfg(feature = "condcomp")]
     If(ExpressionNode),
-    #[cfg(feature = "condcomp")]
     Elif(ExpressionNode),
-    #[cfg(feature = "condcomp")]
     Else,
-    #[cfg(feature = "generics")]
+    CompileError(String),
+    CompileWarn(String),
+    /// An injection point filled by the compiling application, see `@slot("name")`.
+    Slot(String),
     #[from]
     Type(TypeConstraint),
-    #[cfg(feature = "naga-ext")]
     EarlyDepthTest(Option<ConservativeDepth>),
+    // mesh shading
+    Task,
+    Mesh(ExpressionNode),
+    Payload(ExpressionNode),
+    PerPrimitive,
     #[from]
     Custom(CustomAttribute),
 }
 
 pub type AttributeNode = Spanned<Attribute>;
 
-#[cfg(feature = "generics")]
 #[cfg_attr(feature = "tokrepr", derive(TokRepr))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, From)]
@@ -407,13 +471,10 @@ pub enum LiteralExpression {
     F32(f32),
     #[from(skip)]
     F16(f32),
-    #[cfg(feature = "naga-ext")]
     #[from(skip)]
     I64(i64),
-    #[cfg(feature = "naga-ext")]
     #[from(skip)]
     U64(u64),
-    #[cfg(feature = "naga-ext")]
     #[from(skip)]
     F64(f64),
 }
@@ -472,7 +533,6 @@ pub type FunctionCallExpression = FunctionCall;
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct TypeExpression {
-    #[cfg(feature = "imports")]
     pub path: Option<ModulePath>,
     pub ident: Ident,
     pub template_args: TemplateArgs,
@@ -523,7 +583,6 @@ pub struct CompoundStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct AssignmentStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub operator: AssignmentOperator,
     pub lhs: ExpressionNode,
@@ -534,7 +593,6 @@ pub struct AssignmentStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct IncrementStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub expression: ExpressionNode,
 }
@@ -543,7 +601,6 @@ pub struct IncrementStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct DecrementStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub expression: ExpressionNode,
 }
@@ -570,7 +627,6 @@ pub struct IfClause {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct ElseIfClause {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub expression: ExpressionNode,
     pub body: CompoundStatement,
@@ -580,7 +636,6 @@ pub struct ElseIfClause {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct ElseClause {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub body: CompoundStatement,
 }
@@ -599,7 +654,6 @@ pub struct SwitchStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct SwitchClause {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub case_selectors: Vec<CaseSelector>,
     pub body: CompoundStatement,
@@ -629,7 +683,6 @@ pub struct LoopStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct ContinuingStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub body: CompoundStatement,
     // a BreakIfStatement can only appear inside a ContinuingStatement body, therefore it
@@ -642,7 +695,6 @@ pub struct ContinuingStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct BreakIfStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub expression: ExpressionNode,
 }
@@ -671,7 +723,6 @@ pub struct WhileStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct BreakStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
 }
 
@@ -679,7 +730,6 @@ pub struct BreakStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct ContinueStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
 }
 
@@ -687,7 +737,6 @@ pub struct ContinueStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct ReturnStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub expression: Option<ExpressionNode>,
 }
@@ -696,7 +745,6 @@ pub struct ReturnStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct DiscardStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
 }
 
@@ -704,7 +752,6 @@ pub struct DiscardStatement {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct FunctionCallStatement {
-    #[cfg(feature = "attributes")]
     pub attributes: Attributes,
     pub call: FunctionCall,
 }
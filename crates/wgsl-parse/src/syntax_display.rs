@@ -35,7 +35,6 @@ impl<T: Display> Display for Indent<T> {
 
 impl Display for TranslationUnit {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "imports")]
         if !self.imports.is_empty() {
             for import in &self.imports {
                 writeln!(f, "import {import}\n")?;
@@ -60,10 +59,8 @@ impl Display for Ident {
     }
 }
 
-#[cfg(feature = "imports")]
 impl Display for ImportStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         if let Some(path) = &self.path {
             write!(f, "{path}::")?;
@@ -73,27 +70,26 @@ impl Display for ImportStatement {
     }
 }
 
-#[cfg(feature = "imports")]
 impl Display for ModulePath {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.origin {
             PathOrigin::Absolute => write!(f, "package")?,
             PathOrigin::Relative(0) => write!(f, "self")?,
             PathOrigin::Relative(n) => write!(f, "{}", (0..*n).map(|_| "super").format("::"))?,
-            PathOrigin::Package(p) => write!(f, "{p}")?,
+            PathOrigin::Package(p) => write!(f, "{}", fmt_package_name(p))?,
         };
         if !self.components.is_empty() {
-            write!(f, "::{}", self.components.iter().format("::"))?;
+            let components = self.components.iter().map(|c| fmt_path_segment(c));
+            write!(f, "::{}", components.format("::"))?;
         }
         Ok(())
     }
 }
 
-#[cfg(feature = "imports")]
 impl Display for Import {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if !self.path.is_empty() {
-            let path = self.path.iter().format("::");
+            let path = self.path.iter().map(|c| fmt_path_segment(c)).format("::");
             write!(f, "{path}::")?;
         }
         let content = &self.content;
@@ -101,12 +97,11 @@ impl Display for Import {
     }
 }
 
-#[cfg(feature = "imports")]
 impl Display for ImportContent {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             ImportContent::Item(item) => {
-                write!(f, "{}", item.ident)?;
+                write!(f, "{}", fmt_path_segment(&item.ident.name()))?;
                 if let Some(rename) = &item.rename {
                     write!(f, " as {rename}")?;
                 }
@@ -132,7 +127,6 @@ impl Display for GlobalDirective {
 
 impl Display for DiagnosticDirective {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let severity = &self.severity;
         let rule = &self.rule_name;
@@ -142,7 +136,6 @@ impl Display for DiagnosticDirective {
 
 impl Display for EnableDirective {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let exts = self.extensions.iter().format(", ");
         write!(f, "enable {exts};")
@@ -151,7 +144,6 @@ impl Display for EnableDirective {
 
 impl Display for RequiresDirective {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let exts = self.extensions.iter().format(", ");
         write!(f, "requires {exts};")
@@ -203,7 +195,6 @@ impl Display for DeclarationKind {
 
 impl Display for TypeAlias {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let name = &self.ident;
         let ty = &self.ty;
@@ -213,7 +204,6 @@ impl Display for TypeAlias {
 
 impl Display for Struct {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let name = &self.ident;
         let members = Indent(self.members.iter().format(",\n"));
@@ -259,7 +249,6 @@ impl Display for FormalParameter {
 
 impl Display for ConstAssert {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let expr = &self.expression;
         write!(f, "const_assert {expr};",)
@@ -297,20 +286,20 @@ impl Display for Attribute {
             Attribute::Vertex => write!(f, "@vertex"),
             Attribute::Fragment => write!(f, "@fragment"),
             Attribute::Compute => write!(f, "@compute"),
-            #[cfg(feature = "imports")]
             Attribute::Publish => write!(f, "@publish"),
-            #[cfg(feature = "condcomp")]
             Attribute::If(e1) => write!(f, "@if({e1})"),
-            #[cfg(feature = "condcomp")]
             Attribute::Elif(e1) => write!(f, "@elif({e1})"),
-            #[cfg(feature = "condcomp")]
             Attribute::Else => write!(f, "@else"),
-            #[cfg(feature = "generics")]
+            Attribute::CompileError(msg) => write!(f, "@compile_error(\"{}\")", escape_str(msg)),
+            Attribute::CompileWarn(msg) => write!(f, "@compile_warn(\"{}\")", escape_str(msg)),
+            Attribute::Slot(name) => write!(f, "@slot(\"{}\")", escape_str(name)),
             Attribute::Type(e1) => write!(f, "@type({e1})"),
-            #[cfg(feature = "naga-ext")]
             Attribute::EarlyDepthTest(None) => write!(f, "@early_depth_test"),
-            #[cfg(feature = "naga-ext")]
             Attribute::EarlyDepthTest(Some(e1)) => write!(f, "@early_depth_test({e1})"),
+            Attribute::Task => write!(f, "@task"),
+            Attribute::Mesh(e1) => write!(f, "@mesh({e1})"),
+            Attribute::Payload(e1) => write!(f, "@payload({e1})"),
+            Attribute::PerPrimitive => write!(f, "@per_primitive"),
             Attribute::Custom(custom) => {
                 let name = &custom.name;
                 let args = custom.arguments.iter().format_with("", |args, f| {
@@ -322,7 +311,6 @@ impl Display for Attribute {
     }
 }
 
-#[cfg(feature = "generics")]
 impl Display for TypeConstraint {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let name = &self.ident;
@@ -373,11 +361,8 @@ impl Display for LiteralExpression {
             LiteralExpression::U32(num) => write!(f, "{num}u"),
             LiteralExpression::F32(num) => write!(f, "{num}f"),
             LiteralExpression::F16(num) => write!(f, "{num}h"),
-            #[cfg(feature = "naga-ext")]
             LiteralExpression::I64(num) => write!(f, "{num}li"),
-            #[cfg(feature = "naga-ext")]
             LiteralExpression::U64(num) => write!(f, "{num}lu"),
-            #[cfg(feature = "naga-ext")]
             LiteralExpression::F64(num) => write!(f, "{num}lf"),
         }
     }
@@ -491,7 +476,6 @@ impl Display for CompoundStatement {
 
 impl Display for AssignmentStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let operator = &self.operator;
         let lhs = &self.lhs;
@@ -502,7 +486,6 @@ impl Display for AssignmentStatement {
 
 impl Display for IncrementStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let expr = &self.expression;
         write!(f, "{expr}++;")
@@ -511,7 +494,6 @@ impl Display for IncrementStatement {
 
 impl Display for DecrementStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let expr = &self.expression;
         write!(f, "{expr}--;")
@@ -543,7 +525,6 @@ impl Display for IfClause {
 
 impl Display for ElseIfClause {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let expr = &self.expression;
         let stmt = &self.body;
@@ -553,7 +534,6 @@ impl Display for ElseIfClause {
 
 impl Display for ElseClause {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let stmt = &self.body;
         write!(f, "else {stmt}")
@@ -572,7 +552,6 @@ impl Display for SwitchStatement {
 
 impl Display for SwitchClause {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let cases = self.case_selectors.iter().format(", ");
         let body = &self.body;
@@ -612,7 +591,6 @@ impl Display for LoopStatement {
 
 impl Display for ContinuingStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let body_attrs = fmt_attrs(&self.body.attributes, false);
         let stmts = Indent(
@@ -632,7 +610,6 @@ impl Display for ContinuingStatement {
 
 impl Display for BreakIfStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let expr = &self.expression;
         write!(f, "break if {expr};")
@@ -678,7 +655,6 @@ impl Display for WhileStatement {
 
 impl Display for BreakStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         write!(f, "break;")
     }
@@ -686,7 +662,6 @@ impl Display for BreakStatement {
 
 impl Display for ContinueStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         write!(f, "continue;")
     }
@@ -694,7 +669,6 @@ impl Display for ContinueStatement {
 
 impl Display for ReturnStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let expr = self
             .expression
@@ -706,7 +680,6 @@ impl Display for ReturnStatement {
 
 impl Display for DiscardStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         write!(f, "discard;")
     }
@@ -714,9 +687,50 @@ impl Display for DiscardStatement {
 
 impl Display for FunctionCallStatement {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        #[cfg(feature = "attributes")]
         write!(f, "{}", fmt_attrs(&self.attributes, false))?;
         let call = &self.call;
         write!(f, "{call};")
     }
 }
+
+/// Format a module path segment, as a string literal if it is not a valid module name.
+fn fmt_path_segment(name: &str) -> impl fmt::Display + '_ {
+    let mut chars = name.chars();
+    let is_ident = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !matches!(
+            name,
+            "_" | "as" | "else" | "import" | "package" | "self" | "super"
+        );
+    FormatFn(move |f| {
+        if is_ident {
+            write!(f, "{name}")
+        } else {
+            write!(f, "\"{}\"", escape_str(name))
+        }
+    })
+}
+
+/// Package names are not always identifiers, e.g. the sub-package `parent/child`. They
+/// are only quoted when they would not parse back as a package name.
+fn fmt_package_name(name: &str) -> impl fmt::Display + '_ {
+    let quote = name.is_empty()
+        || name.trim() != name
+        || name.contains([':', '"'])
+        || matches!(name, "package" | "self" | "super");
+    FormatFn(move |f| {
+        if quote {
+            write!(f, "\"{}\"", escape_str(name))
+        } else {
+            write!(f, "{name}")
+        }
+    })
+}
+
+/// Escape a string literal, reversing the escapes supported by the lexer.
+fn escape_str(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
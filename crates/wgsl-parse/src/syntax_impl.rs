@@ -1,5 +1,5 @@
 use super::syntax::*;
-use crate::span::Spanned;
+use crate::span::{Id, Span, Spanned};
 
 impl TranslationUnit {
     /// New empty [`TranslationUnit`]
@@ -18,13 +18,26 @@ impl TranslationUnit {
                 }
             })
     }
+
+    /// Visit all the spans of the syntax tree.
+    pub fn spans_mut(&mut self, mut f: impl FnMut(&mut Span)) {
+        SpansMut::spans_mut(self, &mut f)
+    }
+
+    /// Set the [`Span::source`] of all the spans of the syntax tree.
+    ///
+    /// This keeps the spans meaningful when declarations from several sources are merged
+    /// in one syntax tree.
+    pub fn set_source(&mut self, source: Id) {
+        self.spans_mut(|span| span.source = Some(source))
+    }
 }
 
-#[cfg(feature = "imports")]
 impl ModulePath {
     /// Create a new module path from components.
     ///
-    /// Precondition: the path components must be valid WGSL identifiers.
+    /// Components that are not valid WGSL identifiers, e.g. file names with dashes, are
+    /// displayed as string literals: `package::"my-shader"`.
     pub fn new(origin: PathOrigin, components: Vec<String>) -> Self {
         Self { origin, components }
     }
@@ -178,6 +191,22 @@ impl ModulePath {
         }
     }
 
+    /// Append `suffix` to the module path, like [`Self::join_path`], but returns `None`
+    /// if `suffix` has more `super` components than `self` has components, i.e. if it
+    /// points above the package root.
+    ///
+    /// [`Self::join_path`] instead clamps such paths to the package root.
+    pub fn try_join_path(&self, suffix: &Self) -> Option<Self> {
+        match (&self.origin, &suffix.origin) {
+            (PathOrigin::Absolute | PathOrigin::Package(_), PathOrigin::Relative(n))
+                if *n > self.components.len() =>
+            {
+                None
+            }
+            _ => Some(self.join_path(suffix)),
+        }
+    }
+
     /// Whether the module path starts with a `prefix`.
     pub fn starts_with(&self, prefix: &Self) -> bool {
         self.origin == prefix.origin
@@ -197,7 +226,6 @@ impl ModulePath {
     }
 }
 
-#[cfg(feature = "imports")]
 #[test]
 fn test_module_path_join() {
     use std::str::FromStr;
@@ -225,7 +253,29 @@ fn test_module_path_join() {
     }
 }
 
-#[cfg(feature = "imports")]
+#[test]
+fn test_module_path_try_join() {
+    use std::str::FromStr;
+    let cases = [
+        ("package::m1", "super::foo", Some("package::foo")),
+        ("package::m1", "super::super::foo", None),
+        ("pkg::m1", "super::super::foo", None),
+        ("pkg::m1::m2", "super::super::foo", Some("pkg::foo")),
+        (
+            "super",
+            "super::super::foo",
+            Some("super::super::super::foo"),
+        ),
+    ];
+
+    for (parent, child, expect) in cases {
+        let parent = ModulePath::from_str(parent).unwrap();
+        let child = ModulePath::from_str(child).unwrap();
+        let expect = expect.map(|expect| ModulePath::from_str(expect).unwrap());
+        assert_eq!(parent.try_join_path(&child), expect);
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, thiserror::Error)]
 pub enum ModulePathParseError {
     #[error("module name cannot be empty")]
@@ -236,18 +286,71 @@ pub enum ModulePathParseError {
     MisplacedSelf,
     #[error("`super` must be a prefix of the module path")]
     MisplacedSuper,
+    #[error("module path components cannot be empty")]
+    EmptyComponent,
+}
+
+/// Split a module path at the `::` separators outside of string literals.
+fn split_path(s: &str) -> Vec<&str> {
+    let bytes = s.as_bytes();
+    let mut parts = Vec::new();
+    let (mut start, mut i) = (0, 0);
+    let (mut in_str, mut escaped) = (false, false);
+    // the separators and quotes are ASCII, they are never part of a multi-byte char.
+    while i < bytes.len() {
+        match bytes[i] {
+            _ if escaped => escaped = false,
+            b'\\' if in_str => escaped = true,
+            b'"' => in_str = !in_str,
+            b':' if !in_str && bytes.get(i + 1) == Some(&b':') => {
+                parts.push(&s[start..i]);
+                i += 2;
+                start = i;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Unescape a string literal path component, e.g. `"my-shader.v2"`. Other components
+/// are returned as-is.
+fn unquote_component(part: &str) -> String {
+    let Some(quoted) = (part.len() >= 2 && part.starts_with('"') && part.ends_with('"'))
+        .then(|| &part[1..part.len() - 1])
+    else {
+        return part.to_string();
+    };
+    let mut res = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => res.push('\n'),
+                Some('t') => res.push('\t'),
+                Some(c) => res.push(c),
+                None => {}
+            },
+            c => res.push(c),
+        }
+    }
+    res
 }
 
-#[cfg(feature = "imports")]
 impl std::str::FromStr for ModulePath {
     type Err = ModulePathParseError;
 
-    /// Parse a WGSL string into a module path.
+    /// Parse a module path with the WESL path syntax, e.g. `package::a::b`, `super::x`,
+    /// `self::y` or `my_pkg::z`. This is the inverse of the `Display` implementation.
     ///
-    /// Preconditions:
-    /// * The path components must be valid WESL module names.
+    /// Components that are not valid WESL module names can be written as string literals,
+    /// e.g. `package::"my-shader.v2"`. String literals can contain `::` and the escapes
+    /// `\\`, `\"`, `\n` and `\t`. Whitespace around the components is ignored.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split("::").peekable();
+        let mut parts = split_path(s).into_iter().map(str::trim).peekable();
 
         let origin = match parts.next() {
             Some("package") => PathOrigin::Absolute,
@@ -261,7 +364,7 @@ impl std::str::FromStr for ModulePath {
                 PathOrigin::Relative(n)
             }
             Some("") | None => return Err(ModulePathParseError::Empty),
-            Some(name) => PathOrigin::Package(name.to_string()),
+            Some(name) => PathOrigin::Package(unquote_component(name)),
         };
 
         let components = parts
@@ -269,7 +372,8 @@ impl std::str::FromStr for ModulePath {
                 "package" => Err(ModulePathParseError::MisplacedPackage),
                 "self" => Err(ModulePathParseError::MisplacedSelf),
                 "super" => Err(ModulePathParseError::MisplacedSuper),
-                _ => Ok(part.to_string()),
+                "" => Err(ModulePathParseError::EmptyComponent),
+                _ => Ok(unquote_component(part)),
             })
             .collect::<Result<Vec<_>, _>>()?;
 
@@ -277,7 +381,6 @@ impl std::str::FromStr for ModulePath {
     }
 }
 
-#[cfg(feature = "imports")]
 #[test]
 fn test_module_path_fromstr() {
     use std::str::FromStr;
@@ -294,20 +397,59 @@ fn test_module_path_fromstr() {
             "super::super::a",
             ModulePath::new(PathOrigin::Relative(2), vec!["a".to_string()]),
         ),
+        (
+            r#"package::"my-shader.v2""#,
+            ModulePath::new(PathOrigin::Absolute, vec!["my-shader.v2".to_string()]),
+        ),
     ];
     let err_cases = [
         ("", ModulePathParseError::Empty),
         ("a::super", ModulePathParseError::MisplacedSuper),
         ("super::self", ModulePathParseError::MisplacedSelf),
         ("self::package", ModulePathParseError::MisplacedPackage),
+        ("package::", ModulePathParseError::EmptyComponent),
+        ("a::::b", ModulePathParseError::EmptyComponent),
     ];
 
     for (s, m) in ok_cases {
+        assert_eq!(m.to_string(), s);
         assert_eq!(ModulePath::from_str(s), Ok(m))
     }
     for (s, e) in err_cases {
         assert_eq!(ModulePath::from_str(s), Err(e))
     }
+
+    // paths that are not written by `Display`, but parse.
+    assert_eq!(
+        ModulePath::from_str(" package :: a "),
+        ModulePath::from_str("package::a")
+    );
+    // `Display` and `FromStr` round-trip.
+    let components = [
+        "a::b",
+        "say \"hi\"",
+        "back\\slash",
+        "tab\tnew\nline",
+        "self",
+        "",
+        "a:",
+    ];
+    let origins = [
+        PathOrigin::Absolute,
+        PathOrigin::Relative(0),
+        PathOrigin::Relative(2),
+        PathOrigin::Package("bevy/pbr".to_string()),
+        PathOrigin::Package("super".to_string()),
+        PathOrigin::Package("a::b".to_string()),
+        PathOrigin::Package("a:".to_string()),
+        PathOrigin::Package(":a".to_string()),
+    ];
+    for origin in origins {
+        for comp in components {
+            let path = ModulePath::new(origin.clone(), vec![comp.to_string(), "x".to_string()]);
+            assert_eq!(ModulePath::from_str(&path.to_string()), Ok(path));
+        }
+    }
 }
 
 impl GlobalDeclaration {
@@ -322,7 +464,6 @@ impl GlobalDeclaration {
 impl TypeAlias {
     pub fn new(ident: Ident, ty: TypeExpression) -> Self {
         Self {
-            #[cfg(feature = "attributes")]
             attributes: Default::default(),
             ident,
             ty,
@@ -333,7 +474,6 @@ impl TypeAlias {
 impl Struct {
     pub fn new(ident: Ident) -> Self {
         Self {
-            #[cfg(feature = "attributes")]
             attributes: Default::default(),
             ident,
             members: Default::default(),
@@ -377,7 +517,6 @@ impl FormalParameter {
 impl ConstAssert {
     pub fn new(expression: Expression) -> Self {
         Self {
-            #[cfg(feature = "attributes")]
             attributes: Default::default(),
             expression: expression.into(),
         }
@@ -388,7 +527,6 @@ impl TypeExpression {
     /// New [`TypeExpression`] with no template.
     pub fn new(ident: Ident) -> Self {
         Self {
-            #[cfg(feature = "imports")]
             path: None,
             ident,
             template_args: None,
@@ -446,7 +584,6 @@ impl From<Ident> for TypeExpression {
 impl From<ExpressionNode> for ReturnStatement {
     fn from(expression: ExpressionNode) -> Self {
         Self {
-            #[cfg(feature = "attributes")]
             attributes: Default::default(),
             expression: Some(expression),
         }
@@ -461,7 +598,6 @@ impl From<Expression> for ReturnStatement {
 impl From<FunctionCall> for FunctionCallStatement {
     fn from(call: FunctionCall) -> Self {
         Self {
-            #[cfg(feature = "attributes")]
             attributes: Default::default(),
             call,
         }
@@ -524,6 +660,18 @@ pub trait Decorated {
     fn contains_attribute(&self, attribute: &Attribute) -> bool {
         self.attributes().iter().any(|v| v.node() == attribute)
     }
+    /// Find the first attribute matching a predicate.
+    ///
+    /// Useful to look up an attribute by kind, e.g. `matches!(attr, Attribute::Location(_))`.
+    fn find_attribute<P>(&self, mut predicate: P) -> Option<&Attribute>
+    where
+        P: FnMut(&Attribute) -> bool,
+    {
+        self.attributes()
+            .iter()
+            .map(|attr| attr.node())
+            .find(|attr| predicate(attr))
+    }
     /// Remove attributes with predicate.
     fn retain_attributes_mut<F>(&mut self, f: F)
     where
@@ -566,10 +714,8 @@ macro_rules! impl_decorated_struct {
     };
 }
 
-#[cfg(all(feature = "imports", feature = "attributes"))]
 impl_decorated_struct!(ImportStatement);
 
-#[cfg(feature = "attributes")]
 impl Decorated for GlobalDirective {
     fn attributes(&self) -> &[AttributeNode] {
         match self {
@@ -599,16 +745,12 @@ impl Decorated for GlobalDirective {
     }
 }
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(DiagnosticDirective);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(EnableDirective);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(RequiresDirective);
 
-#[cfg(feature = "attributes")]
 impl Decorated for GlobalDeclaration {
     fn attributes(&self) -> &[AttributeNode] {
         match self {
@@ -649,10 +791,8 @@ impl Decorated for GlobalDeclaration {
 
 impl_decorated_struct!(Declaration);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(TypeAlias);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(Struct);
 
 impl_decorated_struct!(StructMember);
@@ -661,10 +801,8 @@ impl_decorated_struct!(Function);
 
 impl_decorated_struct!(FormalParameter);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(ConstAssert);
 
-#[cfg(feature = "attributes")]
 impl Decorated for Statement {
     fn attributes(&self) -> &[AttributeNode] {
         match self {
@@ -738,51 +876,367 @@ impl Decorated for Statement {
 
 impl_decorated_struct!(CompoundStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(AssignmentStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(IncrementStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(DecrementStatement);
 
 impl_decorated_struct!(IfStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(ElseIfClause);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(ElseClause);
 
 impl_decorated_struct!(SwitchStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(SwitchClause);
 
 impl_decorated_struct!(LoopStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(ContinuingStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(BreakIfStatement);
 
 impl_decorated_struct!(ForStatement);
 
 impl_decorated_struct!(WhileStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(BreakStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(ContinueStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(ReturnStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(DiscardStatement);
 
-#[cfg(feature = "attributes")]
 impl_decorated_struct!(FunctionCallStatement);
+
+/// Visit all the spans of a syntax tree. See [`TranslationUnit::spans_mut`].
+trait SpansMut {
+    fn spans_mut(&mut self, f: &mut dyn FnMut(&mut Span));
+}
+
+impl<T: SpansMut> SpansMut for Spanned<T> {
+    fn spans_mut(&mut self, f: &mut dyn FnMut(&mut Span)) {
+        let mut span = self.span();
+        f(&mut span);
+        self.set_span(span);
+        self.node_mut().spans_mut(f)
+    }
+}
+
+impl<T: SpansMut> SpansMut for Vec<T> {
+    fn spans_mut(&mut self, f: &mut dyn FnMut(&mut Span)) {
+        self.iter_mut().for_each(|x| x.spans_mut(f))
+    }
+}
+
+impl<T: SpansMut> SpansMut for Option<T> {
+    fn spans_mut(&mut self, f: &mut dyn FnMut(&mut Span)) {
+        self.iter_mut().for_each(|x| x.spans_mut(f))
+    }
+}
+
+macro_rules! impl_spans_struct {
+    ($ty:ty { $($field:ident),* }) => {
+        impl SpansMut for $ty {
+            fn spans_mut(&mut self, f: &mut dyn FnMut(&mut Span)) {
+                $(self.$field.spans_mut(f);)*
+            }
+        }
+    };
+}
+
+macro_rules! impl_spans_enum {
+    ($ty:ident { $($variant:ident),* }) => {
+        impl SpansMut for $ty {
+            fn spans_mut(&mut self, f: &mut dyn FnMut(&mut Span)) {
+                match self {
+                    $($ty::$variant(x) => x.spans_mut(f),)*
+                    #[allow(unreachable_patterns)]
+                    _ => {}
+                }
+            }
+        }
+    };
+}
+
+impl_spans_struct!(TranslationUnit {
+    imports,
+    global_directives,
+    global_declarations
+});
+impl_spans_struct!(ImportStatement { attributes });
+impl_spans_enum!(GlobalDirective {
+    Diagnostic,
+    Enable,
+    Requires
+});
+impl_spans_struct!(DiagnosticDirective { attributes });
+impl_spans_struct!(EnableDirective { attributes });
+impl_spans_struct!(RequiresDirective { attributes });
+impl_spans_enum!(GlobalDeclaration {
+    Declaration,
+    TypeAlias,
+    Struct,
+    Function,
+    ConstAssert
+});
+impl_spans_struct!(Declaration {
+    attributes,
+    ty,
+    initializer
+});
+impl_spans_struct!(TypeAlias { attributes, ty });
+impl_spans_struct!(Struct {
+    attributes,
+    members
+});
+impl_spans_struct!(StructMember { attributes, ty });
+impl_spans_struct!(Function {
+    attributes,
+    parameters,
+    return_attributes,
+    return_type,
+    body
+});
+impl_spans_struct!(FormalParameter { attributes, ty });
+impl_spans_struct!(ConstAssert {
+    attributes,
+    expression
+});
+impl_spans_struct!(WorkgroupSizeAttribute { x, y, z });
+impl_spans_struct!(CustomAttribute { arguments });
+impl_spans_struct!(TypeConstraint { variants });
+
+impl SpansMut for Attribute {
+    fn spans_mut(&mut self, f: &mut dyn FnMut(&mut Span)) {
+        match self {
+            Attribute::Align(x)
+            | Attribute::Binding(x)
+            | Attribute::BlendSrc(x)
+            | Attribute::Group(x)
+            | Attribute::Id(x)
+            | Attribute::Location(x)
+            | Attribute::Size(x)
+            | Attribute::If(x)
+            | Attribute::Elif(x) => x.spans_mut(f),
+            Attribute::Mesh(x) | Attribute::Payload(x) => x.spans_mut(f),
+            Attribute::WorkgroupSize(x) => x.spans_mut(f),
+            Attribute::Type(x) => x.spans_mut(f),
+            Attribute::Custom(x) => x.spans_mut(f),
+            _ => {}
+        }
+    }
+}
+
+impl_spans_enum!(Expression {
+    Parenthesized,
+    NamedComponent,
+    Indexing,
+    Unary,
+    Binary,
+    FunctionCall,
+    TypeOrIdentifier
+});
+impl_spans_struct!(ParenthesizedExpression { expression });
+impl_spans_struct!(NamedComponentExpression { base });
+impl_spans_struct!(IndexingExpression { base, index });
+impl_spans_struct!(UnaryExpression { operand });
+impl_spans_struct!(BinaryExpression { left, right });
+impl_spans_struct!(FunctionCall { ty, arguments });
+impl_spans_struct!(TypeExpression { template_args });
+impl_spans_struct!(TemplateArg { expression });
+
+impl_spans_enum!(Statement {
+    Compound,
+    Assignment,
+    Increment,
+    Decrement,
+    If,
+    Switch,
+    Loop,
+    For,
+    While,
+    Break,
+    Continue,
+    Return,
+    Discard,
+    FunctionCall,
+    ConstAssert,
+    Declaration
+});
+impl_spans_struct!(CompoundStatement {
+    attributes,
+    statements
+});
+impl_spans_struct!(AssignmentStatement {
+    attributes,
+    lhs,
+    rhs
+});
+impl_spans_struct!(IncrementStatement {
+    attributes,
+    expression
+});
+impl_spans_struct!(DecrementStatement {
+    attributes,
+    expression
+});
+impl_spans_struct!(IfStatement {
+    attributes,
+    if_clause,
+    else_if_clauses,
+    else_clause
+});
+impl_spans_struct!(IfClause { expression, body });
+impl_spans_struct!(ElseIfClause {
+    attributes,
+    expression,
+    body
+});
+impl_spans_struct!(ElseClause { attributes, body });
+impl_spans_struct!(SwitchStatement {
+    attributes,
+    expression,
+    body_attributes,
+    clauses
+});
+impl_spans_struct!(SwitchClause {
+    attributes,
+    case_selectors,
+    body
+});
+impl_spans_enum!(CaseSelector { Expression });
+impl_spans_struct!(LoopStatement {
+    attributes,
+    body,
+    continuing
+});
+impl_spans_struct!(ContinuingStatement {
+    attributes,
+    body,
+    break_if
+});
+impl_spans_struct!(BreakIfStatement {
+    attributes,
+    expression
+});
+impl_spans_struct!(ForStatement {
+    attributes,
+    initializer,
+    condition,
+    update,
+    body
+});
+impl_spans_struct!(WhileStatement {
+    attributes,
+    condition,
+    body
+});
+impl_spans_struct!(BreakStatement { attributes });
+impl_spans_struct!(ContinueStatement { attributes });
+impl_spans_struct!(ReturnStatement {
+    attributes,
+    expression
+});
+impl_spans_struct!(DiscardStatement { attributes });
+impl_spans_struct!(FunctionCallStatement { attributes, call });
+
+#[test]
+fn test_set_source() {
+    let mut wesl: TranslationUnit = "@group(0) @binding(0) var<uniform> x: array<f32, 2 + 2>;
+        fn f() { if x[0] > 1.0 { return; } }"
+        .parse()
+        .unwrap();
+    wesl.set_source(3);
+    let mut count = 0;
+    wesl.spans_mut(|span| {
+        assert_eq!(span.source, Some(3));
+        count += 1;
+    });
+    // 2 declarations, 2 attributes with 2 expressions, 1 template argument with 3
+    // expressions, 2 statements and 5 expressions in the function.
+    assert_eq!(count, 17);
+}
+
+#[test]
+fn test_ident_rename_while_reading() {
+    let mut a = Ident::new("a".to_string());
+    let b = a.clone();
+    // holding a name does not block renaming the ident.
+    let name = b.name();
+    a.rename("c".to_string());
+    assert_eq!(*name, "a");
+    assert_eq!(*b.name(), "c");
+}
+
+#[test]
+fn test_ident_concurrent_renames() {
+    const THREADS: usize = 8;
+    const ITERATIONS: usize = 2000;
+    let ident = Ident::new("name_0".to_string());
+    let names = (0..THREADS)
+        .map(|t| format!("name_{t}"))
+        .collect::<Vec<_>>();
+    std::thread::scope(|s| {
+        for t in 0..THREADS {
+            let mut ident = ident.clone();
+            let names = &names;
+            s.spawn(move || {
+                for i in 0..ITERATIONS {
+                    if i % 2 == 0 {
+                        ident.rename(names[t].clone());
+                    } else {
+                        // readers never see a partially renamed ident.
+                        let name = ident.name();
+                        assert!(names.contains(&name), "{name}");
+                    }
+                }
+            });
+        }
+    });
+    assert!(names.contains(&ident.name()));
+    assert_eq!(ident.use_count(), 1);
+}
+
+#[test]
+fn test_renames() {
+    let idents = (0..64)
+        .map(|i| Ident::new(format!("x{i}")))
+        .collect::<Vec<_>>();
+    // each thread reads all idents and queues renames for its own chunk; renames are
+    // applied once all threads are joined.
+    let mut queues = std::thread::scope(|s| {
+        let handles = idents
+            .chunks(16)
+            .map(|chunk| {
+                let all = &idents;
+                s.spawn(move || {
+                    let mut renames = Renames::new();
+                    for ident in chunk {
+                        let total = all.iter().map(|id| id.name().len()).sum::<usize>();
+                        renames.push(ident, format!("{}_{total}", ident.name()));
+                    }
+                    renames
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    let mut renames = Renames::new();
+    for queue in &mut queues {
+        renames.append(queue);
+    }
+    // the last rename of an ident wins.
+    renames.push(&idents[0], "first".to_string());
+    assert_eq!(renames.len(), 65);
+    renames.apply();
+    // all threads saw the original names.
+    assert_eq!(*idents[1].name(), "x1_182");
+    assert_eq!(*idents[0].name(), "first");
+}
@@ -62,6 +62,17 @@ impl NamedNode for Statement {
     }
 }
 
+/// The `source` of a span is private, so spans are built with their constructor.
+impl TokRepr for Span {
+    fn tok_repr(&self) -> TokenStream {
+        let (start, end) = (self.start, self.end);
+        match self.source() {
+            Some(source) => quote! { Span::new(#start..#end).with_source(#source) },
+            None => quote! { Span::new(#start..#end) },
+        }
+    }
+}
+
 impl<T: NamedNode + TokRepr> TokRepr for Spanned<T> {
     fn tok_repr(&self) -> TokenStream {
         let node = self.node().tok_repr();
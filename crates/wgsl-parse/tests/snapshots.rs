@@ -0,0 +1,65 @@
+//! Golden syntax trees: each source in `tests/snapshots` is parsed and its debug dump is
+//! compared with the `.ast` file next to it, so that changes to the shape of the syntax
+//! tree show up as reviewable diffs.
+//!
+//! After an intended change, update the dumps with
+//! `UPDATE_SNAPSHOTS=1 cargo test -p wgsl-parse --features wesl --test snapshots`
+//! and review the diff. New sources get their dump the same way. `.wesl` sources
+//! require the `wesl` feature, they are skipped otherwise.
+
+use std::path::Path;
+
+#[test]
+fn snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some_and(|v| v != "0");
+
+    let mut sources = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let ext = path.extension().and_then(|ext| ext.to_str());
+            matches!(ext, Some("wgsl")) || (matches!(ext, Some("wesl")) && cfg!(feature = "wesl"))
+        })
+        .collect::<Vec<_>>();
+    sources.sort();
+    assert!(!sources.is_empty(), "no sources in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for source_path in &sources {
+        let source = std::fs::read_to_string(source_path).unwrap();
+        let name = source_path.file_name().unwrap().to_string_lossy();
+        let wesl = wgsl_parse::parse_str(&source)
+            .unwrap_or_else(|e| panic!("failed to parse `{name}`: {e}"));
+        let dump = format!("{wesl:#?}\n");
+        let dump_path = source_path.with_extension(format!(
+            "{}.ast",
+            source_path.extension().unwrap().to_string_lossy()
+        ));
+        if update {
+            std::fs::write(&dump_path, &dump).unwrap();
+            continue;
+        }
+        match std::fs::read_to_string(&dump_path) {
+            Ok(expected) if expected == dump => {}
+            Ok(expected) => {
+                let line = expected
+                    .lines()
+                    .zip(dump.lines())
+                    .position(|(a, b)| a != b)
+                    .unwrap_or_else(|| expected.lines().count().min(dump.lines().count()));
+                failures.push(format!(
+                    "`{name}`: the syntax tree differs at line {}",
+                    line + 1
+                ));
+            }
+            Err(_) => failures.push(format!("`{name}`: no syntax tree dump")),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\nif the changes are intended, run the tests with `UPDATE_SNAPSHOTS=1` and review the diff",
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,22 @@
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) @interpolate(flat, either) index: u32,
+    @location(1) @blend_src(0) color: vec4f,
+    @invariant @builtin(position) other: vec4f,
+}
+
+@id(7) override block_size: u32 = 64;
+
+@vertex
+fn vertex(@builtin(vertex_index) vi: u32) -> VertexOutput {
+    var out: VertexOutput;
+    return out;
+}
+
+@compute @workgroup_size(block_size, 1, 1)
+fn compute(@builtin(global_invocation_id) id: vec3u) {}
+
+@must_use @diagnostic(warning, derivative_uniformity)
+fn helper() -> @location(0) vec4f {
+    return vec4f();
+}
@@ -0,0 +1,23 @@
+enable f16;
+requires readonly_and_readwrite_storage_textures;
+diagnostic(off, derivative_uniformity);
+
+alias Color = vec4<f32>;
+
+struct Light {
+    position: vec3f,
+    @align(16) color: Color,
+}
+
+const MAX_LIGHTS: u32 = 4u;
+override intensity: f32 = 1.0;
+
+@group(0) @binding(0) var<uniform> lights: array<Light, MAX_LIGHTS>;
+@group(0) @binding(1) var<storage, read_write> counter: atomic<u32>;
+var<private> scratch: f32;
+
+const_assert MAX_LIGHTS > 0;
+
+fn luminance(c: Color) -> f32 {
+    return dot(c.rgb, vec3(0.2126, 0.7152, 0.0722));
+}
@@ -0,0 +1,10 @@
+const a = 1 + 2 * 3 - -4;
+const b = ((1 << 2u) | 3) & ~(5 ^ 6);
+const c = !(true && false) || 1 >= 2;
+const d = vec3<f32>(1.0, 2.5e-3, 0x1p4f).yz[1];
+const e = array<i32, 2>(1i, 2)[0] % 7;
+const f = bitcast<u32>(1.5h);
+fn g(p: ptr<function, i32>) -> i32 {
+    let q = &*p;
+    return *q;
+}
@@ -0,0 +1,41 @@
+fn statements(n: i32) -> i32 {
+    var acc = 0;
+    let k: i32 = 2;
+    const c = 3;
+    acc += k;
+    acc++;
+    _ = c;
+    if n < 0 {
+        return 0;
+    } else if n == 0 {
+        acc = 1;
+    } else {
+        acc--;
+    }
+    switch n {
+        case 1, 2: { acc *= 2; }
+        case default: {}
+    }
+    loop {
+        acc -= 1;
+        if acc < 0 { break; }
+        continuing {
+            break if acc > 100;
+        }
+    }
+    for (var i = 0; i < n; i++) {
+        if i == 3 { continue; }
+    }
+    while acc > 10 {
+        acc /= 2;
+    }
+    {
+        let nested = acc;
+    }
+    return acc;
+}
+
+@fragment
+fn frag() {
+    discard;
+}
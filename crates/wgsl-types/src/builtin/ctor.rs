@@ -604,7 +604,9 @@ pub fn typecheck_struct_ctor(struct_ty: &StructType, args: &[Type]) -> Result<()
 ///
 /// Reference: <https://www.w3.org/TR/WGSL/#array-builtin>
 fn array_ctor_ty_t(tplt_ty: &Type, tplt_n: usize, args: &[Type]) -> Result<Type, E> {
-    if let Some(arg) = args.iter().find(|arg| !arg.is_convertible_to(tplt_ty)) {
+    if args.len() != tplt_n {
+        Err(E::ParamCount("array".to_string(), tplt_n, args.len()))
+    } else if let Some(arg) = args.iter().find(|arg| !arg.is_convertible_to(tplt_ty)) {
         Err(E::Conversion(arg.clone(), tplt_ty.clone()))
     } else {
         Ok(Type::Array(Box::new(tplt_ty.clone()), Some(tplt_n)))
@@ -642,7 +644,10 @@ fn mat_ctor_ty_t(c: u8, r: u8, tplt_ty: &Type, args: &[Type]) -> Result<Type, E>
             .ok_or(E::Conversion(ty.inner_ty(), tplt_ty.clone()))?;
 
         // overload 2: mat from column vectors
-        if ty.is_vec() {
+        if let Type::Vec(n, _) = ty {
+            if n != r {
+                return Err(E::Conversion(ty, Type::Vec(r, Box::new(tplt_ty.clone()))));
+            }
             if args.len() != c as usize {
                 return Err(E::ParamCount(format!("mat{c}x{r}"), c as usize, args.len()));
             }
@@ -685,7 +690,10 @@ fn mat_ctor_ty(c: u8, r: u8, args: &[Type]) -> Result<Type, E> {
         }
 
         // overload 2: mat from column vectors
-        if ty.is_vec() {
+        if let Type::Vec(n, _) = ty {
+            if *n != r {
+                return Err(E::Conversion(ty.clone(), Type::Vec(r, inner_ty.into())));
+            }
             if args.len() != c as usize {
                 return Err(E::ParamCount(format!("mat{c}x{r}"), c as usize, args.len()));
             }
@@ -718,8 +726,14 @@ fn vec_ctor_ty_t(n: u8, tplt_ty: &Type, args: &[Type]) -> Result<Type, E> {
             }
         }
         // overload 2: vec conversion constructor
-        else if arg.is_vec() {
+        else if let Type::Vec(n2, _) = arg {
             // note: this is an explicit conversion, not automatic conversion
+            if *n2 != n {
+                return Err(E::Conversion(
+                    arg.clone(),
+                    Type::Vec(n, Box::new(tplt_ty.clone())),
+                ));
+            }
         } else {
             return Err(E::Conversion(arg.clone(), tplt_ty.clone()));
         }
@@ -727,16 +741,19 @@ fn vec_ctor_ty_t(n: u8, tplt_ty: &Type, args: &[Type]) -> Result<Type, E> {
     // overload 3: vec init from component values
     else {
         // flatten vecN args
-        let n2 = args
-            .iter()
-            .try_fold(0, |acc, arg| match arg {
-                ty if ty.is_scalar() => ty.is_convertible_to(tplt_ty).then_some(acc + 1),
-                Type::Vec(n, ty) => ty.is_convertible_to(tplt_ty).then_some(acc + n),
-                _ => None,
-            })
-            .ok_or(E::Builtin(
+        let n2 = args.iter().try_fold(0, |acc, arg| match arg {
+            ty if ty.is_scalar() => ty
+                .is_convertible_to(tplt_ty)
+                .then_some(acc + 1)
+                .ok_or_else(|| E::Conversion(ty.clone(), tplt_ty.clone())),
+            Type::Vec(n, ty) => ty
+                .is_convertible_to(tplt_ty)
+                .then_some(acc + n)
+                .ok_or_else(|| E::Conversion(arg.clone(), Type::Vec(*n, tplt_ty.clone().into()))),
+            _ => Err(E::Builtin(
                 "vector constructor expects scalar or vector arguments",
-            ))?;
+            )),
+        })?;
         if n2 != n {
             return Err(E::ParamCount(format!("vec{n}"), n as usize, args.len()));
         }
@@ -754,8 +771,14 @@ fn vec_ctor_ty(n: u8, args: &[Type]) -> Result<Type, E> {
         if arg.is_scalar() {
         }
         // overload 2: vec conversion constructor
-        else if arg.is_vec() {
+        else if let Type::Vec(n2, _) = arg {
             // note: `vecN(e: vecN<S>) -> vecN<S>` is no-op
+            if *n2 != n {
+                return Err(E::Conversion(
+                    arg.clone(),
+                    Type::Vec(n, arg.inner_ty().into()),
+                ));
+            }
         } else {
             return Err(E::Builtin(
                 "vector constructor expects scalar or vector arguments",
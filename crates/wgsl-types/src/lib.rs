@@ -14,6 +14,7 @@ pub mod ty;
 
 pub use error::Error;
 pub use inst::Instance;
+pub use mem::{MemberLayout, StructLayout};
 pub use ty::Type;
 
 use tplt::TpltParam;
@@ -8,7 +8,7 @@ use crate::{
         ArrayInstance, AtomicInstance, Instance, LiteralInstance, MatInstance, StructInstance,
         VecInstance,
     },
-    ty::{Ty, Type},
+    ty::{StructType, Ty, Type},
 };
 
 impl Instance {
@@ -411,3 +411,136 @@ impl Type {
         }
     }
 }
+
+/// The memory layout of a struct member. See [`StructLayout`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemberLayout {
+    pub name: String,
+    pub ty: Type,
+    /// Offset of the member from the start of the struct, in bytes.
+    pub offset: u32,
+    /// Size of the member, including the `@size` attribute.
+    pub size: u32,
+    /// Alignment of the member, including the `@align` attribute.
+    pub align: u32,
+    /// Padding bytes after the member, before the next member or the end of the struct.
+    pub padding: u32,
+}
+
+/// The memory layout of a host-shareable struct. See [`StructType::layout`].
+///
+/// Displays as a table of the members, with the padding holes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructLayout {
+    pub name: String,
+    pub size: u32,
+    pub align: u32,
+    pub members: Vec<MemberLayout>,
+}
+
+impl StructType {
+    /// Compute the memory layout of the struct.
+    ///
+    /// Returns `None` if the struct is not host-shareable. A runtime-sized array member
+    /// is counted with one element, see [`Type::min_size_of`].
+    ///
+    /// Reference: <https://www.w3.org/TR/WGSL/#structure-member-layout>
+    pub fn layout(&self) -> Option<StructLayout> {
+        let mut members: Vec<MemberLayout> = Vec::with_capacity(self.members.len());
+        let mut offset = 0;
+        for m in &self.members {
+            let size = m.size.or_else(|| m.ty.min_size_of())?;
+            let align = m.align.or_else(|| m.ty.align_of())?;
+            offset = round_up(align, offset);
+            if let Some(prev) = members.last_mut() {
+                prev.padding = offset - prev.offset - prev.size;
+            }
+            members.push(MemberLayout {
+                name: m.name.clone(),
+                ty: m.ty.clone(),
+                offset,
+                size,
+                align,
+                padding: 0,
+            });
+            offset += size;
+        }
+        let align = Type::Struct(Box::new(self.clone())).align_of()?;
+        let size = round_up(align, offset);
+        if let Some(last) = members.last_mut() {
+            last.padding = size - offset;
+        }
+        Some(StructLayout {
+            name: self.name.clone(),
+            size,
+            align,
+            members,
+        })
+    }
+}
+
+impl StructLayout {
+    /// The rows of the layout table: offset, size, align, member and type. Padding holes
+    /// are rows without align and type.
+    fn rows(&self) -> Vec<[String; 5]> {
+        let mut rows = Vec::new();
+        for m in &self.members {
+            rows.push([
+                m.offset.to_string(),
+                m.size.to_string(),
+                m.align.to_string(),
+                m.name.clone(),
+                m.ty.to_string(),
+            ]);
+            if m.padding > 0 {
+                rows.push([
+                    (m.offset + m.size).to_string(),
+                    m.padding.to_string(),
+                    String::new(),
+                    "(padding)".to_string(),
+                    String::new(),
+                ]);
+            }
+        }
+        rows
+    }
+
+    /// Render the layout as a Markdown table.
+    pub fn to_markdown(&self) -> String {
+        let mut res = format!(
+            "**struct {}** (size {}, align {})\n\n",
+            self.name, self.size, self.align
+        );
+        res.push_str("| offset | size | align | member | type |\n");
+        res.push_str("|-------:|-----:|------:|--------|------|\n");
+        for row in self.rows() {
+            res.push_str(&format!("| {} |\n", row.join(" | ")));
+        }
+        res
+    }
+}
+
+impl std::fmt::Display for StructLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "struct {} (size {}, align {})",
+            self.name, self.size, self.align
+        )?;
+        let header = ["offset", "size", "align", "member", "type"].map(String::from);
+        let rows = std::iter::once(header).chain(self.rows()).collect_vec();
+        let width = |i: usize| rows.iter().map(|row| row[i].len()).max().unwrap_or(0);
+        let widths = [width(0), width(1), width(2), width(3)];
+        for [offset, size, align, member, ty] in &rows {
+            let line = format!(
+                "{offset:>w0$}  {size:>w1$}  {align:>w2$}  {member:<w3$}  {ty}",
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+                w3 = widths[3],
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
@@ -26,7 +26,6 @@ pub enum AddressSpace {
     Uniform,
     Storage,
     Handle, // the handle address space cannot be spelled in WGSL.
-    #[cfg(feature = "naga-ext")]
     PushConstant,
 }
 
@@ -39,7 +38,6 @@ impl AddressSpace {
             AddressSpace::Uniform => AccessMode::Read,
             AddressSpace::Storage => AccessMode::Read,
             AddressSpace::Handle => AccessMode::Read,
-            #[cfg(feature = "naga-ext")]
             AddressSpace::PushConstant => AccessMode::Read,
         }
     }
@@ -255,7 +253,6 @@ pub enum InterpolationSampling {
 }
 
 /// Naga extension: Conservative Depth.
-#[cfg(feature = "naga-ext")]
 #[cfg_attr(feature = "tokrepr", derive(TokRepr))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -724,7 +721,6 @@ impl FromStr for InterpolationSampling {
     }
 }
 
-#[cfg(feature = "naga-ext")]
 impl FromStr for ConservativeDepth {
     type Err = ();
 
@@ -764,7 +760,6 @@ impl Display for AddressSpace {
             Self::Uniform => write!(f, "uniform"),
             Self::Storage => write!(f, "storage"),
             Self::Handle => write!(f, "handle"),
-            #[cfg(feature = "naga-ext")]
             Self::PushConstant => write!(f, "push_constant"),
         }
     }
@@ -970,7 +965,6 @@ impl Display for AssignmentOperator {
     }
 }
 
-#[cfg(feature = "naga-ext")]
 impl Display for ConservativeDepth {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
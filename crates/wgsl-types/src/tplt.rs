@@ -205,9 +205,11 @@ impl PtrTemplate {
                     (AddressSpace::Handle, _) => {
                         unreachable!("handle address space cannot be spelled")
                     }
-                    #[cfg(feature = "naga-ext")]
+                    (AddressSpace::PushConstant, Some(AccessMode::Read) | None) => AccessMode::Read,
                     (AddressSpace::PushConstant, _) => {
-                        todo!("push_constant")
+                        return Err(Error::Builtin(
+                            "pointer in push_constant address space must have a `read` access mode",
+                        ));
                     }
                 };
                 Ok(PtrTemplate { space, ty, access })